@@ -0,0 +1,144 @@
+//! Screen-reader friendly output (`--accessible`).
+//!
+//! The terminal output leans on emoji and symbols (✓, ✗, ⚠️, 👉) that screen
+//! readers either skip or read out as long names ("heavy check mark"). In
+//! accessible mode every status message goes through [`render`]: status
+//! symbols become explicit words (`PASS:`, `FAIL:`, `WARNING:`, `PROMPT:`),
+//! other decorations are dropped and box-drawing characters become plain
+//! dashes. Confirmations are asked as a
+//! plain question instead of an interactive widget (see
+//! [`crate::confirm::TerminalConfirmer`]).
+//!
+//! Use the [`say!`](crate::say) and [`say_err!`](crate::say_err) macros in
+//! place of `println!` and `eprintln!` for output that should follow the
+//! mode.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Symbols spoken as a word, longest first where one is a prefix of another.
+const MARKERS: &[(&str, &str)] = &[
+    ("✅", "PASS:"),
+    ("✓", "PASS:"),
+    ("🎉", "DONE:"),
+    ("❌", "FAIL:"),
+    ("✗", "FAIL:"),
+    ("💥", "ERROR:"),
+    ("🚨", "ALERT:"),
+    ("⚠️", "WARNING:"),
+    ("⚠", "WARNING:"),
+    ("💡", "HINT:"),
+    ("ℹ️", "INFO:"),
+    ("⊘", "SKIPPED:"),
+    ("⏭️", "SKIPPED:"),
+    ("👉", "PROMPT:"),
+    ("❓", "PROMPT:"),
+    ("🛑", "STOPPED:"),
+    ("⏰", "TIMEOUT:"),
+];
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Switch accessible output on or off for the rest of the process.
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::SeqCst);
+}
+
+/// Whether accessible output is on.
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::SeqCst)
+}
+
+/// `text` as it should be printed: unchanged, or [`plain`] in accessible
+/// mode.
+pub fn render(text: &str) -> String {
+    if is_enabled() {
+        plain(text)
+    } else {
+        text.to_string()
+    }
+}
+
+/// `text` without emoji or symbols: status symbols replaced by words
+/// (without the colon when nothing follows on the line), decorations (and
+/// the space after them) dropped.
+pub fn plain(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+
+    'chars: while let Some(c) = rest.chars().next() {
+        for (marker, word) in MARKERS {
+            if let Some(after) = rest.strip_prefix(marker) {
+                rest = after.strip_prefix('\u{FE0F}').unwrap_or(after);
+                let spaced = rest.trim_start_matches(' ');
+                if spaced.is_empty() || spaced.starts_with('\n') {
+                    out.push_str(word.trim_end_matches(':'));
+                } else {
+                    out.push_str(word);
+                    out.push(' ');
+                }
+                rest = spaced;
+                continue 'chars;
+            }
+        }
+
+        rest = &rest[c.len_utf8()..];
+        if is_decoration(c) {
+            rest = rest.strip_prefix('\u{FE0F}').unwrap_or(rest);
+            rest = rest.strip_prefix(' ').unwrap_or(rest);
+        } else if ('\u{2500}'..='\u{257F}').contains(&c) || c == '•' {
+            out.push('-');
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Emoji, arrows and pictographs.
+fn is_decoration(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x2190..=0x21FF | 0x2300..=0x23FF | 0x2600..=0x27BF | 0x2B00..=0x2BFF | 0x1F000..=0x1FAFF | 0xFE0F
+    )
+}
+
+/// `println!` following the accessible mode (see [`render`]).
+#[macro_export]
+macro_rules! say {
+    () => {
+        println!()
+    };
+    ($($arg:tt)*) => {
+        println!("{}", $crate::accessible::render(&format!($($arg)*)))
+    };
+}
+
+/// `eprintln!` following the accessible mode (see [`render`]).
+#[macro_export]
+macro_rules! say_err {
+    () => {
+        eprintln!()
+    };
+    ($($arg:tt)*) => {
+        eprintln!("{}", $crate::accessible::render(&format!($($arg)*)))
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plain() {
+        assert_eq!(plain("  ✓ sops found"), "  PASS: sops found");
+        assert_eq!(plain("❌ age not found"), "FAIL: age not found");
+        assert_eq!(plain("⚠️  Left without a value: API_KEY"), "WARNING: Left without a value: API_KEY");
+        assert_eq!(plain("Checking if 'sops' is installed... ✓\n"), "Checking if 'sops' is installed... PASS\n");
+        assert_eq!(plain("\n🔍 Shadow Secret Doctor"), "\nShadow Secret Doctor");
+        assert_eq!(plain("  → Target: app"), "  Target: app");
+        assert_eq!(plain("👉 Press Enter to lock"), "PROMPT: Press Enter to lock");
+        assert_eq!(plain("☁️ Pushing ──── done"), "Pushing ---- done");
+        // Text in other scripts is kept
+        assert_eq!(plain("Clé — café"), "Clé — café");
+    }
+}
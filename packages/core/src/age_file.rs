@@ -0,0 +1,96 @@
+//! Age-encrypted state files.
+//!
+//! Recovery journals (see [`crate::journal`]) and safe-mode snapshots (see
+//! [`crate::snapshot`]) hold template contents, so both are encrypted to the
+//! age recipients of the project's vault and written owner-only, as
+//! `<id>.age` files of their directory. An id is the creation time followed
+//! by the PID of the writing process, so ids sort by age.
+
+use anyhow::{Context, Result};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Extension of the encrypted files.
+pub const EXTENSION: &str = "age";
+
+/// File id for a file created at `created_at` (RFC 3339) by `pid`.
+pub fn id(created_at: &str, pid: u32) -> String {
+    format!("{}-{}", created_at.replace([':', '-'], "").trim_end_matches('Z'), pid)
+}
+
+/// Encrypt `plaintext` to `recipients`; `what` names the file in errors.
+pub fn encrypt(plaintext: &[u8], recipients: &[age::x25519::Recipient], what: &str) -> Result<Vec<u8>> {
+    if recipients.is_empty() {
+        anyhow::bail!("No age recipient to encrypt the {} to", what);
+    }
+
+    let encryptor = age::Encryptor::with_recipients(recipients.iter().map(|r| r as &dyn age::Recipient))
+        .map_err(|e| anyhow::anyhow!("Failed to set up {} encryption: {}", what, e))?;
+    let mut encrypted = Vec::new();
+    let mut writer = encryptor.wrap_output(&mut encrypted)?;
+    writer.write_all(plaintext)?;
+    writer.finish()?;
+    Ok(encrypted)
+}
+
+/// Write `encrypted` to a new file `<id>.age` of `dir`, owner-only and
+/// synced to disk. An existing file is never overwritten.
+pub fn create(dir: &Path, id: &str, encrypted: &[u8], what: &str) -> Result<PathBuf> {
+    let path = dir.join(format!("{}.{}", id, EXTENSION));
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options
+        .open(&path)
+        .and_then(|mut file| {
+            file.write_all(encrypted)?;
+            file.sync_all()
+        })
+        .with_context(|| format!("Failed to write {}: {}", what, path.display()))?;
+
+    Ok(path)
+}
+
+/// Decrypt the file at `path` with the age identities found like SOPS
+/// does (`age_key_path`, `$SOPS_AGE_KEY_FILE`, `$SOPS_AGE_KEY`, default
+/// key file).
+pub fn decrypt(path: &Path, age_key_path: Option<&str>, what: &str) -> Result<Vec<u8>> {
+    let identities = crate::age_native::load_identities(age_key_path)?;
+    let file = std::fs::File::open(path).with_context(|| format!("Failed to open {}: {}", what, path.display()))?;
+
+    let decryptor = age::Decryptor::new(std::io::BufReader::new(file))
+        .with_context(|| format!("Not an age-encrypted {}: {}", what, path.display()))?;
+    let mut reader = decryptor
+        .decrypt(identities.iter().map(|i| i.as_ref() as &dyn age::Identity))
+        .map_err(|_| anyhow::anyhow!("None of the age identities can decrypt {}", path.display()))?;
+
+    let mut plaintext = Vec::new();
+    reader.read_to_end(&mut plaintext)?;
+    Ok(plaintext)
+}
+
+/// The `<id>.age` files of `dir` as (id, path), oldest first.
+pub fn list(dir: &Path) -> Result<Vec<(String, PathBuf)>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir).with_context(|| format!("Failed to read {:?}", dir))? {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some(EXTENSION) {
+            continue;
+        }
+        let Some(id) = path.file_stem().and_then(|s| s.to_str()).map(str::to_string) else {
+            continue;
+        };
+        files.push((id, path));
+    }
+    files.sort();
+    Ok(files)
+}
@@ -0,0 +1,772 @@
+//! In-process SOPS decryption with age (`engine: "age-native"`).
+//!
+//! Reads SOPS-encrypted dotenv, YAML and JSON files without the `sops`
+//! binary, for machines where only Shadow Secret is installed:
+//!
+//! 1. the 32-byte data key is decrypted from one of the `age` stanzas of the
+//!    SOPS metadata with the user's age identities;
+//! 2. every `ENC[AES256_GCM,data:...,iv:...,tag:...,type:...]` value is
+//!    decrypted with AES-256-GCM, authenticated against its key path
+//!    (`a:b:`);
+//! 3. the file MAC (SHA-512 of all values, encrypted with `lastmodified` as
+//!    additional data) is verified, so tampering is detected as with `sops -d`.
+//!
+//! The result is the same plaintext document `sops -d` prints, and is parsed
+//! by the vault exactly like `sops` output.
+//!
+//! Only age recipients are supported (no PGP/KMS, no Shamir key groups), and
+//! writing to the vault (`canary add`, `vault rename-key`) still needs `sops`.
+//!
+//! The age key file may be protected with a passphrase (`age -p`): it is
+//! asked once per run (or read from `$SHADOW_SECRET_AGE_PASSPHRASE`), and
+//! guesses are rate limited (see [`crate::attempts`]).
+
+use aes_gcm::aead::consts::U32;
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::aes::Aes256;
+use aes_gcm::AesGcm;
+use anyhow::{Context, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine as _;
+use sha2::{Digest, Sha512};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// AES-256-GCM with the 32-byte nonces SOPS uses.
+type SopsCipher = AesGcm<Aes256, U32>;
+
+/// Environment variable giving the passphrase of a protected age key file
+/// (headless runs).
+pub const PASSPHRASE_ENV: &str = "SHADOW_SECRET_AGE_PASSPHRASE";
+
+/// Passphrase prompts per run before giving up.
+const PASSPHRASE_PROMPTS: usize = 3;
+
+/// Protected key files already unlocked by this process, so the passphrase
+/// is asked once per run.
+static UNLOCKED: Mutex<Option<HashMap<PathBuf, String>>> = Mutex::new(None);
+
+/// Prefix of the SOPS metadata keys in dotenv files.
+const DOTENV_METADATA_PREFIX: &str = "sops_";
+
+/// File formats understood by SOPS.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Dotenv,
+    Json,
+    Yaml,
+}
+
+impl Format {
+    /// Format from the extension, as `sops` does, or from the content.
+    fn detect(path: &Path, content: &str) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()).unwrap_or("") {
+            "env" | "dotenv" => Format::Dotenv,
+            "json" => Format::Json,
+            "yaml" | "yml" => Format::Yaml,
+            _ if content.trim_start().starts_with('{') => Format::Json,
+            _ if content.lines().any(|line| line.starts_with("sops_mac=")) => Format::Dotenv,
+            _ => Format::Yaml,
+        }
+    }
+}
+
+/// SOPS metadata needed for decryption.
+#[derive(Debug, Default)]
+struct Metadata {
+    /// Armored age payloads, one per recipient
+    age_stanzas: Vec<String>,
+    mac: Option<String>,
+    last_modified: Option<String>,
+    mac_only_encrypted: bool,
+    key_groups: bool,
+}
+
+/// A parsed `ENC[...]` value.
+#[derive(Debug)]
+struct EncryptedValue {
+    data: Vec<u8>,
+    iv: Vec<u8>,
+    tag: Vec<u8>,
+    datatype: String,
+}
+
+impl EncryptedValue {
+    fn parse(value: &str) -> Result<Self> {
+        let inner = value
+            .strip_prefix("ENC[AES256_GCM,")
+            .and_then(|rest| rest.strip_suffix(']'))
+            .context("Unsupported encrypted value (expected ENC[AES256_GCM,...])")?;
+
+        let mut data = None;
+        let mut iv = None;
+        let mut tag = None;
+        let mut datatype = None;
+        for field in inner.split(',') {
+            let (name, content) = field.split_once(':').context("Malformed encrypted value")?;
+            match name {
+                "data" => data = Some(BASE64.decode(content)?),
+                "iv" => iv = Some(BASE64.decode(content)?),
+                "tag" => tag = Some(BASE64.decode(content)?),
+                "type" => datatype = Some(content.to_string()),
+                _ => {}
+            }
+        }
+
+        Ok(Self {
+            data: data.context("Encrypted value has no data")?,
+            iv: iv.context("Encrypted value has no iv")?,
+            tag: tag.context("Encrypted value has no tag")?,
+            datatype: datatype.context("Encrypted value has no type")?,
+        })
+    }
+
+    /// Decrypt with `key`, authenticating `additional_data` (the key path).
+    fn decrypt(&self, key: &[u8], additional_data: &str) -> Result<Vec<u8>> {
+        if self.iv.len() != 32 {
+            anyhow::bail!("Unsupported IV length {} (expected 32)", self.iv.len());
+        }
+
+        let cipher = SopsCipher::new_from_slice(key).context("Invalid data key length")?;
+        let mut ciphertext = self.data.clone();
+        ciphertext.extend_from_slice(&self.tag);
+
+        cipher
+            .decrypt(
+                self.iv.as_slice().into(),
+                Payload {
+                    msg: &ciphertext,
+                    aad: additional_data.as_bytes(),
+                },
+            )
+            .map_err(|_| anyhow::anyhow!("Failed to decrypt value at '{}' (wrong key or tampered file)", additional_data))
+    }
+}
+
+/// Whether `value` is a SOPS-encrypted value.
+fn is_encrypted(value: &str) -> bool {
+    value.starts_with("ENC[") && value.ends_with(']')
+}
+
+/// SOPS additional data for a key path (`a:b:`).
+fn path_aad(path: &[String]) -> String {
+    let mut aad = path.join(":");
+    aad.push(':');
+    aad
+}
+
+/// Decrypts values and accumulates the MAC.
+struct Decrypter {
+    key: Vec<u8>,
+    hash: Sha512,
+    mac_only_encrypted: bool,
+}
+
+impl Decrypter {
+    fn new(key: Vec<u8>, mac_only_encrypted: bool) -> Self {
+        Self {
+            key,
+            hash: Sha512::new(),
+            mac_only_encrypted,
+        }
+    }
+
+    /// Decrypt a string leaf (returned as-is when not encrypted).
+    fn leaf(&mut self, value: &str, path: &[String]) -> Result<serde_yaml::Value> {
+        if !is_encrypted(value) {
+            if !self.mac_only_encrypted {
+                self.hash.update(value.as_bytes());
+            }
+            return Ok(serde_yaml::Value::String(value.to_string()));
+        }
+
+        let encrypted = EncryptedValue::parse(value)?;
+        let plaintext = encrypted.decrypt(&self.key, &path_aad(path))?;
+        let text = String::from_utf8_lossy(&plaintext).into_owned();
+
+        // Comments are not part of the MAC
+        if encrypted.datatype == "comment" {
+            return Ok(serde_yaml::Value::Null);
+        }
+        self.hash.update(&plaintext);
+
+        Ok(match encrypted.datatype.as_str() {
+            "int" => serde_yaml::Value::Number(
+                text.parse::<i64>()
+                    .with_context(|| format!("Invalid int at '{}'", path_aad(path)))?
+                    .into(),
+            ),
+            "float" => serde_yaml::Value::Number(
+                text.parse::<f64>()
+                    .with_context(|| format!("Invalid float at '{}'", path_aad(path)))?
+                    .into(),
+            ),
+            "bool" => serde_yaml::Value::Bool(text.eq_ignore_ascii_case("true")),
+            _ => serde_yaml::Value::String(text),
+        })
+    }
+
+    /// Unencrypted non-string leaves, hashed the way SOPS formats them.
+    fn plain(&mut self, value: &serde_yaml::Value) {
+        if self.mac_only_encrypted {
+            return;
+        }
+        let bytes = match value {
+            serde_yaml::Value::Bool(b) => b.to_string(),
+            serde_yaml::Value::Number(n) => n.to_string(),
+            _ => return,
+        };
+        self.hash.update(bytes.as_bytes());
+    }
+
+    /// Decrypt a YAML/JSON tree in document order.
+    fn tree(&mut self, value: &serde_yaml::Value, path: &mut Vec<String>) -> Result<serde_yaml::Value> {
+        match value {
+            serde_yaml::Value::Mapping(mapping) => {
+                let mut decrypted = serde_yaml::Mapping::new();
+                for (key, item) in mapping {
+                    path.push(key_name(key));
+                    let item = self.tree(item, path)?;
+                    path.pop();
+                    decrypted.insert(key.clone(), item);
+                }
+                Ok(serde_yaml::Value::Mapping(decrypted))
+            }
+            // List items share the path of the list
+            serde_yaml::Value::Sequence(items) => items
+                .iter()
+                .map(|item| self.tree(item, path))
+                .collect::<Result<Vec<_>>>()
+                .map(serde_yaml::Value::Sequence),
+            serde_yaml::Value::String(text) => self.leaf(text, path),
+            other => {
+                self.plain(other);
+                Ok(other.clone())
+            }
+        }
+    }
+
+    /// Check the accumulated hash against the file MAC.
+    fn verify_mac(self, metadata: &Metadata) -> Result<()> {
+        let mac = metadata.mac.as_deref().context("Vault file has no MAC (sops.mac)")?;
+        let last_modified = metadata
+            .last_modified
+            .as_deref()
+            .context("Vault file has no lastmodified date")?;
+
+        let expected = EncryptedValue::parse(mac)?.decrypt(&self.key, last_modified)?;
+        let computed: String = self.hash.finalize().iter().map(|b| format!("{:02X}", b)).collect();
+
+        if !computed.eq_ignore_ascii_case(&String::from_utf8_lossy(&expected)) {
+            anyhow::bail!("MAC mismatch: the vault file was modified outside of sops");
+        }
+        Ok(())
+    }
+}
+
+fn key_name(key: &serde_yaml::Value) -> String {
+    match key {
+        serde_yaml::Value::String(s) => s.clone(),
+        other => serde_yaml::to_string(other)
+            .unwrap_or_default()
+            .trim_end()
+            .to_string(),
+    }
+}
+
+/// Unescape a dotenv value (`\n` → newline), as the SOPS dotenv store does.
+fn dotenv_unescape(value: &str) -> String {
+    value.replace("\\n", "\n")
+}
+
+/// Data entries and metadata of a dotenv vault, in file order.
+fn parse_dotenv(content: &str) -> (Vec<(String, String)>, Metadata) {
+    let mut entries = Vec::new();
+    let mut metadata = Metadata::default();
+
+    for line in content.lines() {
+        // Encrypted comments (#ENC[...]) and blank lines carry no data
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+
+        match key.strip_prefix(DOTENV_METADATA_PREFIX) {
+            None => entries.push((key.to_string(), dotenv_unescape(value))),
+            Some("mac") => metadata.mac = Some(value.to_string()),
+            Some("lastmodified") => metadata.last_modified = Some(value.to_string()),
+            Some("mac_only_encrypted") => metadata.mac_only_encrypted = value == "true",
+            Some(name) if name.starts_with("age__list_") && name.ends_with("__map_enc") => {
+                metadata.age_stanzas.push(dotenv_unescape(value));
+            }
+            Some(name) if name.starts_with("key_groups") => metadata.key_groups = true,
+            Some(_) => {}
+        }
+    }
+
+    (entries, metadata)
+}
+
+/// Metadata from the `sops` section of a YAML/JSON vault.
+fn parse_tree_metadata(sops: &serde_yaml::Value) -> Metadata {
+    let text = |name: &str| sops.get(name).and_then(|v| v.as_str()).map(str::to_string);
+    Metadata {
+        age_stanzas: sops
+            .get("age")
+            .and_then(|v| v.as_sequence())
+            .map(|stanzas| {
+                stanzas
+                    .iter()
+                    .filter_map(|stanza| stanza.get("enc").and_then(|v| v.as_str()))
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default(),
+        mac: text("mac"),
+        last_modified: text("lastmodified"),
+        mac_only_encrypted: sops
+            .get("mac_only_encrypted")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false),
+        key_groups: sops.get("key_groups").is_some(),
+    }
+}
+
+/// Where age identities are read from, in SOPS order of precedence.
+pub(crate) enum IdentitySource {
+    File(PathBuf),
+    /// `SOPS_AGE_KEY`
+    Inline(String),
+}
+
+pub(crate) fn identity_source(age_key_path: Option<&str>) -> Result<IdentitySource> {
+    if let Some(path) = age_key_path {
+        return Ok(IdentitySource::File(PathBuf::from(path)));
+    }
+    if let Ok(path) = std::env::var("SOPS_AGE_KEY_FILE") {
+        return Ok(IdentitySource::File(PathBuf::from(path)));
+    }
+    if let Ok(keys) = std::env::var("SOPS_AGE_KEY") {
+        return Ok(IdentitySource::Inline(keys));
+    }
+    let default = crate::paths::user_config_dir()
+        .context("Could not determine the config directory for sops/age/keys.txt: set SOPS_AGE_KEY_FILE or age_key_path")?
+        .join("sops/age/keys.txt");
+    Ok(IdentitySource::File(default))
+}
+
+pub(crate) fn load_identities(age_key_path: Option<&str>) -> Result<Vec<Box<dyn age::Identity>>> {
+    let identities = match identity_source(age_key_path)? {
+        IdentitySource::File(path) => {
+            let content = std::fs::read(&path)
+                .with_context(|| format!("Failed to open age key file: {}", path.display()))?;
+            let keys = if is_passphrase_protected(&content) {
+                unlock_key_file(&path, &content)?.into_bytes()
+            } else {
+                content
+            };
+            age::IdentityFile::from_buffer(keys.as_slice())
+                .with_context(|| format!("Failed to parse age key file: {}", path.display()))?
+        }
+        IdentitySource::Inline(keys) => age::IdentityFile::from_buffer(keys.as_bytes())
+            .context("Failed to parse SOPS_AGE_KEY")?,
+    };
+
+    identities
+        .into_identities()
+        .map_err(|e| anyhow::anyhow!("Unsupported age identity: {}", e))
+}
+
+/// Whether an age key file is itself age-encrypted (`age -p`, armored or
+/// not).
+pub fn is_passphrase_protected(content: &[u8]) -> bool {
+    let start = content.iter().position(|byte| !byte.is_ascii_whitespace()).unwrap_or(content.len());
+    let content = &content[start..];
+    content.starts_with(b"age-encryption.org/v1") || content.starts_with(b"-----BEGIN AGE ENCRYPTED FILE-----")
+}
+
+/// Decrypt a passphrase-protected key file.
+fn decrypt_key_file(content: &[u8], passphrase: &str) -> Result<String> {
+    let decryptor = age::Decryptor::new(age::armor::ArmoredReader::new(content)).context("Invalid age key file")?;
+    if !decryptor.is_scrypt() {
+        anyhow::bail!("The age key file is encrypted to recipients, not with a passphrase");
+    }
+    let identity = age::scrypt::Identity::new(age::secrecy::SecretString::from(passphrase.to_string()));
+    let mut keys = String::new();
+    decryptor
+        .decrypt(std::iter::once(&identity as &dyn age::Identity))
+        .map_err(|_| anyhow::anyhow!("Wrong passphrase"))?
+        .read_to_string(&mut keys)
+        .context("Failed to read the age key file")?;
+    Ok(keys)
+}
+
+/// The identities of the protected key file at `path`, asking for its
+/// passphrase (rate limited, see [`crate::attempts`]).
+fn unlock_key_file(path: &Path, content: &[u8]) -> Result<String> {
+    let mut unlocked = UNLOCKED.lock().unwrap_or_else(|e| e.into_inner());
+    let unlocked = unlocked.get_or_insert_with(HashMap::new);
+    if let Some(keys) = unlocked.get(path) {
+        return Ok(keys.clone());
+    }
+
+    let subject = path.canonicalize().unwrap_or_else(|_| path.to_path_buf()).display().to_string();
+    let from_env = std::env::var(PASSPHRASE_ENV).ok();
+    let prompts = if from_env.is_some() { 1 } else { PASSPHRASE_PROMPTS };
+    for _ in 0..prompts {
+        crate::attempts::check(&subject)?;
+        let passphrase = match &from_env {
+            Some(passphrase) => passphrase.clone(),
+            None => dialoguer::Password::new()
+                .with_prompt(format!("Passphrase for {}", path.display()))
+                .interact()
+                .context("Failed to read the passphrase")?,
+        };
+
+        match decrypt_key_file(content, &passphrase) {
+            Ok(keys) => {
+                crate::attempts::record_success(&subject)?;
+                unlocked.insert(path.to_path_buf(), keys.clone());
+                return Ok(keys);
+            }
+            Err(e) => {
+                let failures = crate::attempts::record_failure(&subject)?;
+                crate::say_err!("❌ {} ({} failed attempt(s) in a row)", e, failures);
+            }
+        }
+    }
+    anyhow::bail!("Failed to unlock the age key file: {}", path.display())
+}
+
+/// Decrypt the data key from the first age stanza our identities open.
+fn decrypt_data_key(metadata: &Metadata, age_key_path: Option<&str>) -> Result<Vec<u8>> {
+    if metadata.key_groups {
+        anyhow::bail!("Shamir key groups are not supported by engine 'age-native'; use engine 'sops'");
+    }
+    if metadata.age_stanzas.is_empty() {
+        anyhow::bail!("Vault file has no age recipients; engine 'age-native' only supports age");
+    }
+
+    let identities = load_identities(age_key_path)?;
+    for stanza in &metadata.age_stanzas {
+        let reader = age::armor::ArmoredReader::new(stanza.as_bytes());
+        let Ok(decryptor) = age::Decryptor::new(reader) else {
+            continue;
+        };
+        let Ok(mut plaintext) = decryptor.decrypt(identities.iter().map(|i| i.as_ref() as &dyn age::Identity)) else {
+            continue;
+        };
+
+        let mut key = Vec::new();
+        plaintext.read_to_end(&mut key).context("Failed to read the data key")?;
+        return Ok(key);
+    }
+
+    anyhow::bail!("None of the age identities can decrypt this vault (not a recipient?)")
+}
+
+/// Decrypt a SOPS file in process and return the plaintext document, in
+/// the format `sops -d` would print.
+pub fn decrypt_file(path: &Path, age_key_path: Option<&str>) -> Result<Vec<u8>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read vault file: {}", path.display()))?;
+
+    match Format::detect(path, &content) {
+        Format::Dotenv => {
+            let (entries, metadata) = parse_dotenv(&content);
+            let mut decrypter = Decrypter::new(
+                decrypt_data_key(&metadata, age_key_path)?,
+                metadata.mac_only_encrypted,
+            );
+
+            let mut output = String::new();
+            for (key, value) in entries {
+                let value = match decrypter.leaf(&value, std::slice::from_ref(&key))? {
+                    serde_yaml::Value::String(text) => text,
+                    other => key_name(&other),
+                };
+                output.push_str(&format!("{}={}\n", key, value.replace('\n', "\\n")));
+            }
+            decrypter.verify_mac(&metadata)?;
+            Ok(output.into_bytes())
+        }
+        format => {
+            let mut document: serde_yaml::Value = serde_yaml::from_str(&content)
+                .with_context(|| format!("Failed to parse vault file: {}", path.display()))?;
+            let sops = document
+                .as_mapping_mut()
+                .and_then(|mapping| mapping.remove("sops"))
+                .with_context(|| format!("{} is not a SOPS-encrypted file (no 'sops' section)", path.display()))?;
+            let metadata = parse_tree_metadata(&sops);
+
+            let mut decrypter = Decrypter::new(
+                decrypt_data_key(&metadata, age_key_path)?,
+                metadata.mac_only_encrypted,
+            );
+            let decrypted = decrypter.tree(&document, &mut Vec::new())?;
+            decrypter.verify_mac(&metadata)?;
+
+            if format == Format::Json {
+                let mut json = serde_json::to_vec_pretty(&decrypted)?;
+                json.push(b'\n');
+                Ok(json)
+            } else {
+                Ok(serde_yaml::to_string(&decrypted)?.into_bytes())
+            }
+        }
+    }
+}
+
+/// Decrypt a single top-level key.
+///
+/// Only that value is decrypted (it is authenticated by its own GCM tag and
+/// key path); the file MAC, which covers every value, is not checked.
+pub fn decrypt_key(path: &Path, key: &str, age_key_path: Option<&str>) -> Result<Option<String>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read vault file: {}", path.display()))?;
+
+    let (value, metadata) = match Format::detect(path, &content) {
+        Format::Dotenv => {
+            let (entries, metadata) = parse_dotenv(&content);
+            let value = entries
+                .into_iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| serde_yaml::Value::String(value));
+            (value, metadata)
+        }
+        _ => {
+            let mut document: serde_yaml::Value = serde_yaml::from_str(&content)
+                .with_context(|| format!("Failed to parse vault file: {}", path.display()))?;
+            let mapping = document
+                .as_mapping_mut()
+                .with_context(|| format!("{} is not a mapping", path.display()))?;
+            let sops = mapping
+                .remove("sops")
+                .with_context(|| format!("{} is not a SOPS-encrypted file (no 'sops' section)", path.display()))?;
+            (mapping.remove(key), parse_tree_metadata(&sops))
+        }
+    };
+
+    let Some(value) = value else {
+        return Ok(None);
+    };
+
+    let mut decrypter = Decrypter::new(decrypt_data_key(&metadata, age_key_path)?, true);
+    match decrypter.tree(&value, &mut vec![key.to_string()])? {
+        serde_yaml::Value::String(text) => Ok(Some(text)),
+        other => Ok(Some(key_name(&other))),
+    }
+}
+
+/// Names of the top-level keys of a SOPS file, without decrypting it.
+///
+/// SOPS only encrypts values, so key names are readable with no age key.
+pub fn key_names(path: &Path) -> Result<Vec<String>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read vault file: {}", path.display()))?;
+
+    match Format::detect(path, &content) {
+        Format::Dotenv => Ok(parse_dotenv(&content)
+            .0
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect()),
+        _ => {
+            let document: serde_yaml::Value = serde_yaml::from_str(&content)
+                .with_context(|| format!("Failed to parse vault file: {}", path.display()))?;
+            let mapping = document
+                .as_mapping()
+                .with_context(|| format!("{} is not a mapping", path.display()))?;
+            Ok(mapping
+                .keys()
+                .map(key_name)
+                .filter(|key| key != "sops")
+                .collect())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use age::secrecy::ExposeSecret;
+    use std::io::Write;
+
+    /// Encrypt like SOPS: AES-256-GCM, 32-byte IV, key path as AAD.
+    fn encrypt(key: &[u8], plaintext: &str, aad: &str, datatype: &str) -> String {
+        let iv = [7u8; 32];
+        let mut sealed = SopsCipher::new_from_slice(key)
+            .unwrap()
+            .encrypt(
+                iv.as_slice().into(),
+                Payload {
+                    msg: plaintext.as_bytes(),
+                    aad: aad.as_bytes(),
+                },
+            )
+            .unwrap();
+        let tag = sealed.split_off(sealed.len() - 16);
+        format!(
+            "ENC[AES256_GCM,data:{},iv:{},tag:{},type:{}]",
+            BASE64.encode(sealed),
+            BASE64.encode(iv),
+            BASE64.encode(tag),
+            datatype
+        )
+    }
+
+    fn mac(key: &[u8], values: &[&str], last_modified: &str) -> String {
+        let mut hash = Sha512::new();
+        for value in values {
+            hash.update(value.as_bytes());
+        }
+        let hex: String = hash.finalize().iter().map(|b| format!("{:02X}", b)).collect();
+        encrypt(key, &hex, last_modified, "str")
+    }
+
+    /// An age identity file and the data key armored for its recipient.
+    fn age_setup(dir: &Path, data_key: &[u8]) -> (PathBuf, String) {
+        let identity = age::x25519::Identity::generate();
+        let key_path = dir.join("keys.txt");
+        std::fs::write(&key_path, identity.to_string().expose_secret()).unwrap();
+
+        let recipient = identity.to_public();
+        let encryptor =
+            age::Encryptor::with_recipients(std::iter::once(&recipient as &dyn age::Recipient)).unwrap();
+        let mut armored = Vec::new();
+        let writer = age::armor::ArmoredWriter::wrap_output(&mut armored, age::armor::Format::AsciiArmor).unwrap();
+        let mut stream = encryptor.wrap_output(writer).unwrap();
+        stream.write_all(data_key).unwrap();
+        stream.finish().unwrap().finish().unwrap();
+
+        (key_path, String::from_utf8(armored).unwrap())
+    }
+
+    const LAST_MODIFIED: &str = "2026-01-01T00:00:00Z";
+
+    fn dotenv_vault(dir: &Path, mac_values: &[&str]) -> (PathBuf, PathBuf) {
+        let data_key = [42u8; 32];
+        let (key_path, armored) = age_setup(dir, &data_key);
+
+        let content = format!(
+            "#{}\nAPI_KEY={}\nMULTI={}\nsops_age__list_0__map_enc={}\nsops_age__list_0__map_recipient=age1test\nsops_lastmodified={}\nsops_mac={}\nsops_version=3.9.0\n",
+            encrypt(&data_key, "a comment", "", "comment"),
+            encrypt(&data_key, "sk_live_123", "API_KEY:", "str"),
+            encrypt(&data_key, "line1\nline2", "MULTI:", "str"),
+            armored.replace('\n', "\\n"),
+            LAST_MODIFIED,
+            mac(&data_key, mac_values, LAST_MODIFIED),
+        );
+        let path = dir.join("secrets.enc.env");
+        std::fs::write(&path, content).unwrap();
+        (path, key_path)
+    }
+
+    #[test]
+    fn test_decrypt_dotenv() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let (path, key_path) = dotenv_vault(dir.path(), &["sk_live_123", "line1\nline2"]);
+
+        let plaintext = decrypt_file(&path, key_path.to_str()).unwrap();
+        assert_eq!(
+            String::from_utf8(plaintext).unwrap(),
+            "API_KEY=sk_live_123\nMULTI=line1\\nline2\n"
+        );
+
+        assert_eq!(
+            decrypt_key(&path, "API_KEY", key_path.to_str()).unwrap(),
+            Some("sk_live_123".to_string())
+        );
+        assert_eq!(decrypt_key(&path, "MISSING", key_path.to_str()).unwrap(), None);
+    }
+
+    #[test]
+    fn test_mac_mismatch_is_rejected() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let (path, key_path) = dotenv_vault(dir.path(), &["sk_live_123", "tampered"]);
+
+        let error = decrypt_file(&path, key_path.to_str()).unwrap_err();
+        assert!(error.to_string().contains("MAC mismatch"));
+    }
+
+    #[test]
+    fn test_decrypt_yaml_nested_paths() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let data_key = [9u8; 32];
+        let (key_path, armored) = age_setup(dir.path(), &data_key);
+
+        let mut document = serde_yaml::Mapping::new();
+        document.insert("API_KEY".into(), encrypt(&data_key, "sk_live_123", "API_KEY:", "str").into());
+        let mut db = serde_yaml::Mapping::new();
+        db.insert("port".into(), encrypt(&data_key, "5432", "db:port:", "int").into());
+        db.insert("host_unencrypted".into(), "localhost".into());
+        document.insert("db".into(), db.into());
+
+        let mut stanza = serde_yaml::Mapping::new();
+        stanza.insert("recipient".into(), "age1test".into());
+        stanza.insert("enc".into(), armored.into());
+        let mut sops = serde_yaml::Mapping::new();
+        sops.insert("age".into(), vec![serde_yaml::Value::Mapping(stanza)].into());
+        sops.insert("lastmodified".into(), LAST_MODIFIED.into());
+        sops.insert(
+            "mac".into(),
+            mac(&data_key, &["sk_live_123", "5432", "localhost"], LAST_MODIFIED).into(),
+        );
+        document.insert("sops".into(), sops.into());
+
+        let path = dir.path().join("secrets.enc.yaml");
+        std::fs::write(&path, serde_yaml::to_string(&document).unwrap()).unwrap();
+
+        let plaintext = decrypt_file(&path, key_path.to_str()).unwrap();
+        let decrypted: serde_yaml::Value = serde_yaml::from_slice(&plaintext).unwrap();
+        assert_eq!(decrypted["API_KEY"], "sk_live_123");
+        assert_eq!(decrypted["db"]["port"], 5432);
+        assert_eq!(decrypted["db"]["host_unencrypted"], "localhost");
+        assert!(decrypted.get("sops").is_none());
+    }
+
+    #[test]
+    fn test_key_names_need_no_key() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let (path, _) = dotenv_vault(dir.path(), &[]);
+        assert_eq!(key_names(&path).unwrap(), vec!["API_KEY", "MULTI"]);
+
+        let yaml = dir.path().join("secrets.enc.yaml");
+        std::fs::write(&yaml, "API_KEY: ENC[AES256_GCM,data:x]\nsops:\n  mac: x\n").unwrap();
+        assert_eq!(key_names(&yaml).unwrap(), vec!["API_KEY"]);
+    }
+
+    #[test]
+    fn test_wrong_identity_is_rejected() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let (path, _) = dotenv_vault(dir.path(), &["sk_live_123", "line1\nline2"]);
+
+        let other = dir.path().join("other.txt");
+        std::fs::write(&other, age::x25519::Identity::generate().to_string().expose_secret()).unwrap();
+
+        assert!(decrypt_file(&path, other.to_str()).is_err());
+    }
+
+    #[test]
+    fn test_passphrase_protected_key_file() {
+        let keys = age::x25519::Identity::generate().to_string().expose_secret().to_string();
+        let mut recipient = age::scrypt::Recipient::new(age::secrecy::SecretString::from("correct horse".to_string()));
+        recipient.set_work_factor(4);
+        let encryptor = age::Encryptor::with_recipients(std::iter::once(&recipient as &dyn age::Recipient)).unwrap();
+        let mut protected = Vec::new();
+        let mut writer = encryptor
+            .wrap_output(age::armor::ArmoredWriter::wrap_output(&mut protected, age::armor::Format::AsciiArmor).unwrap())
+            .unwrap();
+        std::io::Write::write_all(&mut writer, keys.as_bytes()).unwrap();
+        writer.finish().unwrap().finish().unwrap();
+
+        assert!(is_passphrase_protected(&protected));
+        assert!(!is_passphrase_protected(keys.as_bytes()));
+        assert_eq!(decrypt_key_file(&protected, "correct horse").unwrap(), keys);
+        assert_eq!(decrypt_key_file(&protected, "battery staple").unwrap_err().to_string(), "Wrong passphrase");
+    }
+}
@@ -0,0 +1,803 @@
+//! Agent mode (`shadow-secret agent start`): the vault decrypted once and
+//! served from memory.
+//!
+//! The agent decrypts the project's vault, keeps the values in locked
+//! memory (never swapped out, wiped on exit) and answers requests on a
+//! local socket: `~/.config/shadow-secret/agent.sock` on Unix (in the
+//! private state directory, created with mode `0600`, and only answering
+//! processes of the same user), the named pipe
+//! `\\.\pipe\shadow-secret-agent-<user>` on Windows (local clients only).
+//! `$SHADOW_SECRET_AGENT_SOCK` names another socket.
+//!
+//! The protocol is one JSON object per line each way, so scripts can talk
+//! to the agent directly:
+//!
+//! ```text
+//! → {"op":"get","key":"API_KEY"}
+//! ← {"ok":true,"value":"sk_live_123"}
+//! → {"op":"list"}
+//! ← {"ok":true,"keys":["API_KEY","DB_URL"]}
+//! → {"op":"inject","path":"config.json","content":"{\"key\":\"$API_KEY\"}"}
+//! ← {"ok":true,"value":"{\"key\":\"sk_live_123\"}"}
+//! → {"op":"unlock","seconds":3600}
+//! ← {"ok":true,"unlocked":true,"expires_in":3600,...}
+//! ```
+//!
+//! `ping` tells which config the agent serves and whether its targets are
+//! unlocked, and `stop` shuts it down. `unlock` injects the secrets into the
+//! config's targets until `lock`, or until their time runs out (`extend`
+//! pushes it back); the agent locks them when it stops. `get` and `list`
+//! use a running agent instead of sops when it serves the same config, and
+//! `shadow-secret tray` drives it from the system tray (see
+//! [`crate::tray`]).
+
+use crate::config::TargetConfig;
+use crate::unlock::UnlockSession;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Environment variable naming the agent's socket.
+pub const SOCKET_ENV: &str = "SHADOW_SECRET_AGENT_SOCK";
+
+/// Socket in the state directory (Unix).
+pub const SOCKET_FILE: &str = "agent.sock";
+
+/// How long a client may take to send a request.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often the agent checks whether unlocked targets timed out.
+const EXPIRY_CHECK: Duration = Duration::from_millis(250);
+
+/// A request to the agent.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum Request {
+    /// Which config the agent serves
+    Ping,
+    /// Value of one key
+    Get { key: String },
+    /// Key names
+    List,
+    /// `content` (the text of a file at `path`, whose extension picks the
+    /// format) with its placeholders replaced
+    Inject {
+        path: String,
+        content: String,
+        #[serde(default = "all_placeholders")]
+        placeholders: Vec<String>,
+    },
+    /// Inject the secrets into the config's targets, for `seconds` (the
+    /// config's session timeout when unset)
+    Unlock {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        seconds: Option<u64>,
+    },
+    /// Restore the injected targets
+    Lock,
+    /// Push the automatic lock back by `seconds`
+    Extend { seconds: u64 },
+    /// Shut the agent down
+    Stop,
+}
+
+fn all_placeholders() -> Vec<String> {
+    vec!["$ALL".to_string()]
+}
+
+/// The agent's answer. Only the fields of the request's kind are set.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Response {
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keys: Option<Vec<String>>,
+    /// Config file served (`ping`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config: Option<String>,
+    /// Process ID of the agent (`ping`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pid: Option<u32>,
+    /// Whether the targets are injected (`ping`, `unlock`, `lock`, `extend`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unlocked: Option<bool>,
+    /// Seconds until the injected targets are locked, if they ever are
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_in: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Response {
+    fn error(message: impl Into<String>) -> Self {
+        Self {
+            error: Some(message.into()),
+            ..Default::default()
+        }
+    }
+
+    /// The response's value, or its error.
+    pub fn into_result(self) -> Result<Self> {
+        match self.error {
+            Some(error) if !self.ok => anyhow::bail!("Agent: {}", error),
+            _ => Ok(self),
+        }
+    }
+}
+
+/// Secret values kept in locked memory, wiped when dropped.
+pub struct LockedSecrets {
+    secrets: HashMap<String, String>,
+    locked: bool,
+}
+
+impl LockedSecrets {
+    /// Lock the memory holding the values of `secrets`.
+    pub fn new(secrets: HashMap<String, String>) -> Self {
+        let locked = secrets
+            .values()
+            .filter(|value| !value.is_empty())
+            .all(|value| lock_memory(value.as_ptr(), value.len()));
+        Self { secrets, locked }
+    }
+
+    /// Whether every value is in locked memory (locking fails when it
+    /// exceeds the user's limit, e.g. `ulimit -l`).
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    pub fn get(&self, key: &str) -> Option<&String> {
+        self.secrets.get(key)
+    }
+
+    pub fn all(&self) -> &HashMap<String, String> {
+        &self.secrets
+    }
+}
+
+impl Drop for LockedSecrets {
+    fn drop(&mut self) {
+        for value in self.secrets.values_mut() {
+            // SAFETY: only zero bytes are written, which is valid UTF-8
+            for byte in unsafe { value.as_bytes_mut() } {
+                unsafe { std::ptr::write_volatile(byte, 0) };
+            }
+            if !value.is_empty() {
+                unlock_memory(value.as_ptr(), value.len());
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+fn lock_memory(ptr: *const u8, len: usize) -> bool {
+    unsafe { libc::mlock(ptr.cast(), len) == 0 }
+}
+
+#[cfg(unix)]
+fn unlock_memory(ptr: *const u8, len: usize) {
+    unsafe { libc::munlock(ptr.cast(), len) };
+}
+
+#[cfg(windows)]
+fn lock_memory(ptr: *const u8, len: usize) -> bool {
+    unsafe { windows_sys::Win32::System::Memory::VirtualLock(ptr.cast(), len) != 0 }
+}
+
+#[cfg(windows)]
+fn unlock_memory(ptr: *const u8, len: usize) {
+    unsafe { windows_sys::Win32::System::Memory::VirtualUnlock(ptr.cast(), len) };
+}
+
+#[cfg(not(any(unix, windows)))]
+fn lock_memory(_ptr: *const u8, _len: usize) -> bool {
+    false
+}
+
+#[cfg(not(any(unix, windows)))]
+fn unlock_memory(_ptr: *const u8, _len: usize) {}
+
+/// Targets injected by the agent (`unlock`).
+struct AgentSession {
+    session: UnlockSession,
+    /// When the targets are locked again, if ever
+    expires_at: Option<Instant>,
+}
+
+/// The agent's state: the config it serves, its secrets and the targets it
+/// injected.
+pub struct Agent {
+    /// Canonical path of the config file
+    pub config: PathBuf,
+    pub secrets: LockedSecrets,
+    /// Targets injected on `unlock`
+    targets: Vec<TargetConfig>,
+    /// How long `unlock` injects them when the request sets no limit
+    session_limit: Option<Duration>,
+    session: Mutex<Option<AgentSession>>,
+}
+
+impl Agent {
+    pub fn new(config: PathBuf, secrets: HashMap<String, String>) -> Self {
+        Self {
+            config,
+            secrets: LockedSecrets::new(secrets),
+            targets: Vec::new(),
+            session_limit: None,
+            session: Mutex::new(None),
+        }
+    }
+
+    /// Targets to inject on `unlock`, for `session_limit` when the request
+    /// sets no limit (`None`: until locked).
+    pub fn with_targets(mut self, targets: Vec<TargetConfig>, session_limit: Option<Duration>) -> Self {
+        self.targets = targets;
+        self.session_limit = session_limit;
+        self
+    }
+
+    /// Whether the targets are injected.
+    pub fn is_unlocked(&self) -> bool {
+        self.session.lock().unwrap_or_else(|e| e.into_inner()).is_some()
+    }
+
+    /// Inject the secrets into the targets, for `limit` (else the
+    /// configured session limit).
+    pub fn unlock(&self, limit: Option<Duration>) -> Result<()> {
+        let mut session = self.session.lock().unwrap_or_else(|e| e.into_inner());
+        if session.is_some() {
+            anyhow::bail!("The targets are already unlocked");
+        }
+        if self.targets.is_empty() {
+            anyhow::bail!("The config has no targets to unlock");
+        }
+
+        let vault = crate::vault::Vault::new(self.secrets.all().clone());
+        let unlocked = UnlockSession::unlock(&self.targets, &vault)?;
+        *session = Some(AgentSession {
+            session: unlocked,
+            expires_at: limit.or(self.session_limit).map(|limit| Instant::now() + limit),
+        });
+        Ok(())
+    }
+
+    /// Restore the injected targets (only those: see
+    /// [`crate::cleaner::rollback`]).
+    ///
+    /// # Returns
+    ///
+    /// Whether the targets were unlocked.
+    pub fn lock(&self) -> Result<bool> {
+        let Some(session) = self.session.lock().unwrap_or_else(|e| e.into_inner()).take() else {
+            return Ok(false);
+        };
+        let paths: Vec<String> = session.session.report().targets.iter().map(|target| target.path.clone()).collect();
+        crate::cleaner::rollback(&paths)?;
+        Ok(true)
+    }
+
+    /// Push the automatic lock back by `by`; nothing changes when the
+    /// targets stay unlocked until locked.
+    pub fn extend(&self, by: Duration) -> Result<()> {
+        let mut session = self.session.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(session) = session.as_mut() else {
+            anyhow::bail!("The targets are not unlocked");
+        };
+        if let Some(expires_at) = &mut session.expires_at {
+            *expires_at = (*expires_at).max(Instant::now()) + by;
+        }
+        Ok(())
+    }
+
+    /// Lock the targets if their time ran out.
+    ///
+    /// # Returns
+    ///
+    /// Whether they were locked.
+    pub fn expire(&self) -> Result<bool> {
+        let expired = self
+            .session
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_ref()
+            .and_then(|session| session.expires_at)
+            .is_some_and(|expires_at| expires_at <= Instant::now());
+        if !expired {
+            return Ok(false);
+        }
+        self.lock()
+    }
+
+    /// Which config the agent serves and whether its targets are unlocked.
+    fn status(&self) -> Response {
+        let session = self.session.lock().unwrap_or_else(|e| e.into_inner());
+        Response {
+            ok: true,
+            config: Some(self.config.display().to_string()),
+            pid: Some(std::process::id()),
+            unlocked: Some(session.is_some()),
+            expires_in: session
+                .as_ref()
+                .and_then(|session| session.expires_at)
+                .map(|expires_at| expires_at.saturating_duration_since(Instant::now()).as_secs()),
+            ..Default::default()
+        }
+    }
+
+    /// Answer `request`.
+    pub fn handle(&self, request: &Request) -> Response {
+        let done = |result: Result<()>| match result {
+            Ok(()) => self.status(),
+            Err(e) => Response::error(format!("{:#}", e)),
+        };
+        match request {
+            Request::Ping | Request::Stop => self.status(),
+            Request::Unlock { seconds } => done(self.unlock(seconds.map(Duration::from_secs))),
+            Request::Lock => done(self.lock().map(drop)),
+            Request::Extend { seconds } => done(self.extend(Duration::from_secs(*seconds))),
+            Request::Get { key } => match self.secrets.get(key) {
+                Some(value) => Response {
+                    ok: true,
+                    value: Some(value.clone()),
+                    ..Default::default()
+                },
+                None => Response::error(format!("Secret '{}' not found in vault", key)),
+            },
+            Request::List => {
+                let mut keys: Vec<String> = self.secrets.all().keys().cloned().collect();
+                keys.sort();
+                Response {
+                    ok: true,
+                    keys: Some(keys),
+                    ..Default::default()
+                }
+            }
+            Request::Inject {
+                path,
+                content,
+                placeholders,
+            } => {
+                let injected = crate::injector::replace_placeholders_for(
+                    Path::new(path),
+                    content,
+                    self.secrets.all(),
+                    placeholders,
+                    None,
+                );
+                match injected {
+                    Ok(value) => Response {
+                        ok: true,
+                        value: Some(value),
+                        ..Default::default()
+                    },
+                    Err(e) => Response::error(format!("{:#}", e)),
+                }
+            }
+        }
+    }
+
+    /// Answer the requests of one connection, one line each, until it
+    /// closes.
+    ///
+    /// # Returns
+    ///
+    /// Whether a `stop` request was received.
+    pub fn serve_connection(&self, stream: &mut (impl Read + Write)) -> Result<bool> {
+        let mut reader = BufReader::new(&mut *stream);
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                return Ok(false);
+            }
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (response, stop) = match serde_json::from_str::<Request>(&line) {
+                Ok(request) => (self.handle(&request), request == Request::Stop),
+                Err(e) => (Response::error(format!("Invalid request: {}", e)), false),
+            };
+            let mut reply = serde_json::to_string(&response)?;
+            reply.push('\n');
+            reader.get_mut().write_all(reply.as_bytes())?;
+            reader.get_mut().flush()?;
+            if stop {
+                return Ok(true);
+            }
+        }
+    }
+}
+
+/// The agent's socket: `$SHADOW_SECRET_AGENT_SOCK`, else the default one.
+pub fn socket_path() -> Result<PathBuf> {
+    if let Some(path) = std::env::var_os(SOCKET_ENV).filter(|path| !path.is_empty()) {
+        return Ok(PathBuf::from(path));
+    }
+    default_socket_path()
+}
+
+#[cfg(not(windows))]
+fn default_socket_path() -> Result<PathBuf> {
+    Ok(crate::isolation::state_dir()?.join(SOCKET_FILE))
+}
+
+#[cfg(windows)]
+fn default_socket_path() -> Result<PathBuf> {
+    let user = std::env::var("USERNAME").unwrap_or_else(|_| "default".to_string());
+    Ok(PathBuf::from(format!(r"\\.\pipe\shadow-secret-agent-{}", user)))
+}
+
+/// Send `request` to the agent listening on `path`.
+pub fn request(path: &Path, request: &Request) -> Result<Response> {
+    let mut stream = connect(path)?;
+    let mut line = serde_json::to_string(request)?;
+    line.push('\n');
+    stream.write_all(line.as_bytes()).context("Failed to send request to agent")?;
+    stream.flush()?;
+
+    let mut reply = String::new();
+    BufReader::new(stream)
+        .read_line(&mut reply)
+        .context("Failed to read agent response")?;
+    serde_json::from_str(&reply).context("Invalid agent response")
+}
+
+/// The agent serving `config` (a config file path), if one is running.
+///
+/// # Returns
+///
+/// The agent's socket.
+pub fn serving(config: &Path) -> Option<PathBuf> {
+    let path = socket_path().ok()?;
+    let config = config.canonicalize().ok()?;
+    let response = request(&path, &Request::Ping).ok()?;
+    (response.config.as_deref() == Some(&*config.to_string_lossy())).then_some(path)
+}
+
+#[cfg(unix)]
+fn connect(path: &Path) -> Result<std::os::unix::net::UnixStream> {
+    let stream = std::os::unix::net::UnixStream::connect(path)
+        .with_context(|| format!("No agent listening on {}", path.display()))?;
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+    Ok(stream)
+}
+
+#[cfg(windows)]
+fn connect(path: &Path) -> Result<std::fs::File> {
+    std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+        .with_context(|| format!("No agent listening on {}", path.display()))
+}
+
+#[cfg(not(any(unix, windows)))]
+fn connect(_path: &Path) -> Result<std::fs::File> {
+    anyhow::bail!("The agent is not supported on this platform")
+}
+
+/// Serve `agent` on `path` (a socket, or a named pipe on Windows) until a
+/// `stop` request, locking the targets it injected when their time runs
+/// out, and when it stops.
+///
+/// # Errors
+///
+/// Fails if another agent already listens on `path`.
+pub fn serve(agent: &Agent, path: &Path) -> Result<()> {
+    let stopped = AtomicBool::new(false);
+    let result = std::thread::scope(|scope| {
+        scope.spawn(|| {
+            while !stopped.load(Ordering::SeqCst) {
+                match agent.expire() {
+                    Ok(true) => crate::say_err!("⏰ Session timed out: targets locked"),
+                    Ok(false) => {}
+                    Err(e) => crate::say_err!("⚠️  Failed to lock the targets: {:#}", e),
+                }
+                std::thread::sleep(EXPIRY_CHECK);
+            }
+        });
+        let result = listen(agent, path);
+        stopped.store(true, Ordering::SeqCst);
+        result
+    });
+    agent.lock()?;
+    result
+}
+
+/// Answer the clients of the socket `path` until a `stop` request.
+#[cfg(unix)]
+fn listen(agent: &Agent, path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    use std::os::unix::net::UnixListener;
+
+    if path.exists() {
+        if connect(path).is_ok() {
+            anyhow::bail!("An agent is already listening on {} (stop it with 'shadow-secret agent stop')", path.display());
+        }
+        // Left by an agent that was killed
+        std::fs::remove_file(path).with_context(|| format!("Failed to remove stale socket {}", path.display()))?;
+    }
+
+    // The socket is created `0600`: never reachable by others, even briefly
+    // SAFETY: umask has no preconditions and cannot fail
+    let umask = unsafe { libc::umask(0o177) };
+    let bound = UnixListener::bind(path);
+    unsafe { libc::umask(umask) };
+    let listener = bound.with_context(|| format!("Failed to listen on {}", path.display()))?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))
+        .with_context(|| format!("Failed to restrict permissions of {}", path.display()))?;
+
+    let result = (|| {
+        for stream in listener.incoming() {
+            let mut stream = stream.context("Failed to accept agent connection")?;
+            // Only the user running the agent gets answers
+            match peer_uid(&stream) {
+                Ok(uid) if uid == crate::isolation::current_uid() => {}
+                Ok(uid) => {
+                    crate::say_err!("⚠️  Refused agent connection from UID {}", uid);
+                    continue;
+                }
+                Err(e) => {
+                    crate::say_err!("⚠️  Refused agent connection: cannot identify the client: {}", e);
+                    continue;
+                }
+            }
+            stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+            match agent.serve_connection(&mut stream) {
+                Ok(true) => return Ok(()),
+                Ok(false) => {}
+                // A misbehaving client does not stop the agent
+                Err(e) => crate::say_err!("⚠️  Agent connection failed: {:#}", e),
+            }
+        }
+        Ok(())
+    })();
+    let _ = std::fs::remove_file(path);
+    result
+}
+
+/// UID of the process at the other end of `stream`.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn peer_uid(stream: &std::os::unix::net::UnixStream) -> std::io::Result<u32> {
+    use std::os::unix::io::AsRawFd;
+
+    let mut credentials = libc::ucred { pid: 0, uid: 0, gid: 0 };
+    let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+    // SAFETY: `credentials` and `len` describe a valid ucred buffer
+    let result = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            (&mut credentials as *mut libc::ucred).cast(),
+            &mut len,
+        )
+    };
+    if result != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(credentials.uid)
+}
+
+/// UID of the process at the other end of `stream`.
+#[cfg(all(unix, not(any(target_os = "linux", target_os = "android"))))]
+fn peer_uid(stream: &std::os::unix::net::UnixStream) -> std::io::Result<u32> {
+    use std::os::unix::io::AsRawFd;
+
+    let (mut uid, mut gid) = (0, 0);
+    // SAFETY: both pointers are valid for writes
+    if unsafe { libc::getpeereid(stream.as_raw_fd(), &mut uid, &mut gid) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(uid)
+}
+
+/// Answer the clients of the named pipe `path` until a `stop` request.
+#[cfg(windows)]
+fn listen(agent: &Agent, path: &Path) -> Result<()> {
+    use std::os::windows::ffi::OsStrExt;
+    use std::os::windows::io::FromRawHandle;
+    use windows_sys::Win32::Foundation::{GetLastError, ERROR_PIPE_CONNECTED, INVALID_HANDLE_VALUE};
+    use windows_sys::Win32::Storage::FileSystem::{FILE_FLAG_FIRST_PIPE_INSTANCE, PIPE_ACCESS_DUPLEX};
+    use windows_sys::Win32::System::Pipes::{
+        ConnectNamedPipe, CreateNamedPipeW, PIPE_READMODE_BYTE, PIPE_REJECT_REMOTE_CLIENTS, PIPE_TYPE_BYTE,
+        PIPE_UNLIMITED_INSTANCES, PIPE_WAIT,
+    };
+
+    let name: Vec<u16> = path.as_os_str().encode_wide().chain(std::iter::once(0)).collect();
+    let mut first = true;
+    loop {
+        let mut open_mode = PIPE_ACCESS_DUPLEX;
+        if first {
+            // Refuse to start if another process already owns the pipe
+            open_mode |= FILE_FLAG_FIRST_PIPE_INSTANCE;
+        }
+        let handle = unsafe {
+            CreateNamedPipeW(
+                name.as_ptr(),
+                open_mode,
+                PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+                PIPE_UNLIMITED_INSTANCES,
+                4096,
+                4096,
+                0,
+                std::ptr::null(),
+            )
+        };
+        if handle == INVALID_HANDLE_VALUE {
+            anyhow::bail!(
+                "Failed to listen on {} (is an agent already running?): {}",
+                path.display(),
+                std::io::Error::last_os_error()
+            );
+        }
+        first = false;
+
+        let connected = unsafe { ConnectNamedPipe(handle, std::ptr::null_mut()) } != 0
+            || unsafe { GetLastError() } == ERROR_PIPE_CONNECTED;
+        // SAFETY: the handle is valid and owned from here on (closed on drop)
+        let mut pipe = unsafe { std::fs::File::from_raw_handle(handle as _) };
+        if !connected {
+            continue;
+        }
+        match agent.serve_connection(&mut pipe) {
+            Ok(true) => return Ok(()),
+            Ok(false) => {}
+            Err(e) => crate::say_err!("⚠️  Agent connection failed: {:#}", e),
+        }
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn listen(_agent: &Agent, _path: &Path) -> Result<()> {
+    anyhow::bail!("The agent is not supported on this platform")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn agent() -> Agent {
+        let secrets = HashMap::from([
+            ("API_KEY".to_string(), "sk_live_123".to_string()),
+            ("DB_URL".to_string(), "postgres://db".to_string()),
+        ]);
+        Agent::new(PathBuf::from("/project/project.yaml"), secrets)
+    }
+
+    #[test]
+    fn test_handle_requests() {
+        let agent = agent();
+
+        let response = agent.handle(&Request::Get { key: "API_KEY".to_string() });
+        assert_eq!(response.value.as_deref(), Some("sk_live_123"));
+        assert!(agent.handle(&Request::Get { key: "MISSING".to_string() }).into_result().is_err());
+
+        let keys = agent.handle(&Request::List).keys.unwrap();
+        assert_eq!(keys, vec!["API_KEY".to_string(), "DB_URL".to_string()]);
+
+        let request: Request =
+            serde_json::from_str(r#"{"op":"inject","path":"app.json","content":"{\"key\": \"$API_KEY\"}"}"#).unwrap();
+        assert_eq!(agent.handle(&request).value.as_deref(), Some(r#"{"key": "sk_live_123"}"#));
+
+        let ping = agent.handle(&Request::Ping);
+        assert_eq!(ping.config.as_deref(), Some("/project/project.yaml"));
+        assert_eq!(ping.pid, Some(std::process::id()));
+    }
+
+    /// A connection reading from `input` and recording what is written.
+    struct Connection {
+        input: std::io::Cursor<Vec<u8>>,
+        output: Vec<u8>,
+    }
+
+    impl Read for Connection {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for Connection {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.output.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_serve_connection_answers_each_line() {
+        let agent = agent();
+        let input = "{\"op\":\"get\",\"key\":\"DB_URL\"}\nnot json\n{\"op\":\"stop\"}\n{\"op\":\"list\"}\n";
+        let mut connection = Connection {
+            input: std::io::Cursor::new(input.as_bytes().to_vec()),
+            output: Vec::new(),
+        };
+        assert!(agent.serve_connection(&mut connection).unwrap());
+
+        let output = String::from_utf8(connection.output).unwrap();
+        let replies: Vec<Response> = output.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(replies.len(), 3, "{}", output);
+        assert_eq!(replies[0].value.as_deref(), Some("postgres://db"));
+        assert!(replies[1].error.as_deref().unwrap().starts_with("Invalid request"));
+        assert!(replies[2].ok);
+    }
+
+    #[test]
+    fn test_unlock_extend_and_lock_targets() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let target = temp_dir.path().join("app.env");
+        std::fs::write(&target, "API_KEY=$API_KEY\n").unwrap();
+        let targets = vec![TargetConfig {
+            name: "app".to_string(),
+            path: target.to_string_lossy().to_string(),
+            placeholders: vec!["$API_KEY".to_string()],
+            ..Default::default()
+        }];
+        let agent = agent().with_targets(targets, Some(Duration::from_secs(600)));
+
+        assert_eq!(agent.handle(&Request::Ping).unlocked, Some(false));
+        assert!(agent.handle(&Request::Extend { seconds: 60 }).into_result().is_err());
+
+        let unlocked = agent.handle(&Request::Unlock { seconds: None });
+        assert_eq!(unlocked.unlocked, Some(true), "{:?}", unlocked.error);
+        assert!((590..=600).contains(&unlocked.expires_in.unwrap()));
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "API_KEY=sk_live_123\n");
+        assert!(agent.handle(&Request::Unlock { seconds: None }).into_result().is_err());
+
+        let extended = agent.handle(&Request::Extend { seconds: 600 });
+        assert!(extended.expires_in.unwrap() > 1000);
+        assert!(!agent.expire().unwrap());
+
+        let locked = agent.handle(&Request::Lock);
+        assert_eq!(locked.unlocked, Some(false));
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "API_KEY=$API_KEY\n");
+
+        // Out of time: the next check locks
+        agent.handle(&Request::Unlock { seconds: Some(0) }).into_result().unwrap();
+        assert!(agent.expire().unwrap());
+        assert!(!agent.is_unlocked());
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "API_KEY=$API_KEY\n");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_serve_on_private_socket() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join(SOCKET_FILE);
+        let server = {
+            let path = path.clone();
+            std::thread::spawn(move || serve(&agent(), &path))
+        };
+
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        let ping = loop {
+            match request(&path, &Request::Ping) {
+                Ok(response) => break response,
+                Err(_) if std::time::Instant::now() < deadline => std::thread::sleep(Duration::from_millis(20)),
+                Err(e) => panic!("{:#}", e),
+            }
+        };
+        assert_eq!(ping.config.as_deref(), Some("/project/project.yaml"));
+        assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+
+        let stream = std::os::unix::net::UnixStream::connect(&path).unwrap();
+        assert_eq!(peer_uid(&stream).unwrap(), crate::isolation::current_uid());
+        drop(stream);
+
+        assert!(request(&path, &Request::Stop).unwrap().ok);
+        server.join().unwrap().unwrap();
+        assert!(!path.exists());
+    }
+}
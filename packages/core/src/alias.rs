@@ -0,0 +1,208 @@
+//! User-defined command shortcuts (`aliases:` in the global config).
+//!
+//! Frequent workflows become one short command:
+//!
+//! ```yaml
+//! aliases:
+//!   up: unlock --timeout 8h
+//!   ship: deploy --run "pnpm build" --project prj_123 --env preview
+//! ```
+//!
+//! `shadow-secret up` then runs `shadow-secret unlock --timeout 8h`, and
+//! arguments after the alias are appended (`shadow-secret up --strict`).
+//! Aliases are expanded once, before the command line is parsed: an alias
+//! must start with a built-in command, and never replaces one.
+
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+
+/// Split `command` into arguments like a POSIX shell would: whitespace
+/// separates them, single quotes keep text as is, double quotes and
+/// backslashes escape.
+pub fn split(command: &str) -> Result<Vec<String>> {
+    let mut args = Vec::new();
+    let mut current: Option<String> = None;
+    let mut chars = command.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                args.extend(current.take());
+            }
+            '\'' => {
+                let arg = current.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => arg.push(c),
+                        None => anyhow::bail!("Unclosed single quote in '{}'", command),
+                    }
+                }
+            }
+            '"' => {
+                let arg = current.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\' | '$' | '`')) => arg.push(c),
+                            Some(c) => {
+                                arg.push('\\');
+                                arg.push(c);
+                            }
+                            None => anyhow::bail!("Unclosed double quote in '{}'", command),
+                        },
+                        Some(c) => arg.push(c),
+                        None => anyhow::bail!("Unclosed double quote in '{}'", command),
+                    }
+                }
+            }
+            '\\' => {
+                let escaped = chars.next().with_context(|| format!("Trailing backslash in '{}'", command))?;
+                current.get_or_insert_with(String::new).push(escaped);
+            }
+            c => current.get_or_insert_with(String::new).push(c),
+        }
+    }
+    args.extend(current);
+    Ok(args)
+}
+
+/// Check the names and commands of `aliases`.
+pub fn validate(aliases: &BTreeMap<String, String>) -> Result<()> {
+    for (name, command) in aliases {
+        if name.is_empty() || name.starts_with('-') || name.chars().any(char::is_whitespace) {
+            anyhow::bail!("Invalid alias name '{}': use a single word not starting with '-'", name);
+        }
+        let args = split(command).with_context(|| format!("Invalid alias '{}'", name))?;
+        if args.is_empty() {
+            anyhow::bail!("Alias '{}' has no command", name);
+        }
+    }
+    Ok(())
+}
+
+/// Global flags followed by a value (`--config-dir DIR`).
+pub const GLOBAL_VALUE_FLAGS: &[&str] = &["--config-dir"];
+
+/// Index of the command in `args` (the program name first): the first
+/// argument that is neither a global flag nor its value.
+pub fn command_index(args: &[String]) -> Option<usize> {
+    let mut index = 1;
+    while let Some(arg) = args.get(index) {
+        if arg == "--" {
+            return None;
+        }
+        if !arg.starts_with('-') {
+            return (!args[index..].iter().any(|arg| arg == "--")).then_some(index);
+        }
+        index += if GLOBAL_VALUE_FLAGS.contains(&arg.as_str()) { 2 } else { 1 };
+    }
+    None
+}
+
+/// Value of the global flag `flag` (`--flag VALUE` or `--flag=VALUE`)
+/// before the command.
+pub fn global_flag_value(args: &[String], flag: &str) -> Option<String> {
+    let end = command_index(args).unwrap_or(args.len());
+    let prefix = format!("{}=", flag);
+    args[..end].iter().enumerate().skip(1).find_map(|(index, arg)| {
+        if arg == flag {
+            args.get(index + 1).cloned()
+        } else {
+            arg.strip_prefix(&prefix).map(str::to_string)
+        }
+    })
+}
+
+/// `args` with the alias in command position replaced by its command.
+/// Built-in commands (as told by `is_builtin`) and unknown names are left
+/// for the parser.
+///
+/// # Errors
+///
+/// Fails if the alias does not start with a built-in command.
+pub fn expand(
+    args: Vec<String>,
+    aliases: &BTreeMap<String, String>,
+    is_builtin: impl Fn(&str) -> bool,
+) -> Result<Vec<String>> {
+    let Some(index) = command_index(&args) else {
+        return Ok(args);
+    };
+    let name = &args[index];
+    if is_builtin(name) {
+        return Ok(args);
+    }
+    let Some(command) = aliases.get(name) else {
+        return Ok(args);
+    };
+
+    let expansion = split(command).with_context(|| format!("Invalid alias '{}'", name))?;
+    match expansion.first() {
+        Some(first) if is_builtin(first) => {}
+        Some(first) => anyhow::bail!(
+            "Alias '{}' must start with a shadow-secret command, not '{}' (aliases are not expanded twice)",
+            name,
+            first
+        ),
+        None => anyhow::bail!("Alias '{}' has no command", name),
+    }
+
+    let mut expanded = args[..index].to_vec();
+    expanded.extend(expansion);
+    expanded.extend(args[index + 1..].iter().cloned());
+    Ok(expanded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split(' ').map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_split() {
+        assert_eq!(
+            split(r#"deploy --run "pnpm build && echo \"done\"" --env 'pre view' a\ b"#).unwrap(),
+            vec!["deploy", "--run", "pnpm build && echo \"done\"", "--env", "pre view", "a b"]
+        );
+        assert_eq!(split("  unlock   ''  ").unwrap(), vec!["unlock", ""]);
+        assert!(split("unlock \"open").is_err());
+        assert!(split("unlock 'open").is_err());
+    }
+
+    #[test]
+    fn test_expand() {
+        let aliases = BTreeMap::from([
+            ("up".to_string(), "unlock --timeout 8h".to_string()),
+            ("doctor".to_string(), "fsck".to_string()),
+            ("twice".to_string(), "up".to_string()),
+        ]);
+        let is_builtin = |name: &str| ["unlock", "doctor", "fsck"].contains(&name);
+
+        assert_eq!(
+            expand(args("shadow-secret --accessible up --strict"), &aliases, is_builtin).unwrap(),
+            args("shadow-secret --accessible unlock --timeout 8h --strict")
+        );
+        // Built-in commands win, unknown names are left to the parser
+        assert_eq!(expand(args("shadow-secret doctor"), &aliases, is_builtin).unwrap(), args("shadow-secret doctor"));
+        assert_eq!(expand(args("shadow-secret nope"), &aliases, is_builtin).unwrap(), args("shadow-secret nope"));
+        assert!(expand(args("shadow-secret twice"), &aliases, is_builtin).is_err());
+        // Values of global flags are not commands
+        assert_eq!(
+            expand(args("shadow-secret --config-dir up up"), &aliases, is_builtin).unwrap(),
+            args("shadow-secret --config-dir up unlock --timeout 8h")
+        );
+        assert_eq!(
+            global_flag_value(&args("shadow-secret --config-dir=/ci up --config-dir /x"), "--config-dir"),
+            Some("/ci".to_string())
+        );
+
+        assert!(validate(&aliases).is_ok());
+        assert!(validate(&BTreeMap::from([("-x".to_string(), "unlock".to_string())])).is_err());
+        assert!(validate(&BTreeMap::from([("empty".to_string(), " ".to_string())])).is_err());
+    }
+}
@@ -0,0 +1,178 @@
+//! Rate limiting of passphrase guesses.
+//!
+//! A passphrase-protected age identity (see [`crate::age_native`]) is only
+//! as strong as the rate at which its passphrase can be guessed. Failed
+//! attempts are counted per identity in
+//! `~/.config/shadow-secret/attempts.json`, so restarting the command does
+//! not reset them. After [`FREE_ATTEMPTS`] failures in a row, each attempt
+//! must wait twice as long after the previous failure as the one before (1s,
+//! 2s, 4s, ... up to [`MAX_BACKOFF`]), and attempts made sooner are refused
+//! without trying the passphrase. Every failure is recorded in the audit
+//! log (event `passphrase`, outcome `failed`); a success resets the count.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Attempt store in the state directory.
+pub const ATTEMPTS_FILE: &str = "attempts.json";
+
+/// Failures in a row allowed before attempts are slowed down.
+pub const FREE_ATTEMPTS: u32 = 3;
+
+/// Longest wait between two attempts.
+pub const MAX_BACKOFF: Duration = Duration::from_secs(15 * 60);
+
+/// Failed attempts on one identity.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Attempts {
+    /// Failures in a row
+    pub failures: u32,
+    /// Unix time of the last failure
+    pub last_failure: i64,
+}
+
+/// Failed attempts, by identity.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct AttemptStore {
+    pub subjects: BTreeMap<String, Attempts>,
+}
+
+impl AttemptStore {
+    /// Default store location (`~/.config/shadow-secret/attempts.json`).
+    pub fn default_path() -> Result<PathBuf> {
+        Ok(crate::isolation::state_dir()?.join(ATTEMPTS_FILE))
+    }
+
+    /// Load the store (empty if the file does not exist).
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(path).with_context(|| format!("Failed to read {:?}", path))?;
+        serde_json::from_str(&content).with_context(|| format!("Failed to parse {:?}", path))
+    }
+
+    /// Save the store.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?).with_context(|| format!("Failed to write {:?}", path))
+    }
+}
+
+/// Wait required after `failures` failures in a row.
+pub fn backoff(failures: u32) -> Duration {
+    if failures < FREE_ATTEMPTS {
+        return Duration::ZERO;
+    }
+    let exponent = (failures - FREE_ATTEMPTS).min(20);
+    Duration::from_secs(1u64 << exponent).min(MAX_BACKOFF)
+}
+
+/// Refuse an attempt on `subject` made before its backoff ran out, `now`
+/// being the Unix time.
+pub fn check_in(store_path: &Path, subject: &str, now: i64) -> Result<()> {
+    let store = AttemptStore::load(store_path)?;
+    let Some(attempts) = store.subjects.get(subject) else {
+        return Ok(());
+    };
+    let ready_at = attempts.last_failure + backoff(attempts.failures).as_secs() as i64;
+    if now < ready_at {
+        anyhow::bail!(
+            "Too many failed passphrase attempts for {} ({} in a row): try again in {}s",
+            subject,
+            attempts.failures,
+            ready_at - now
+        );
+    }
+    Ok(())
+}
+
+/// Count a failed attempt on `subject`.
+///
+/// # Returns
+///
+/// The failures in a row, this one included.
+pub fn record_failure_in(store_path: &Path, subject: &str, now: i64) -> Result<u32> {
+    let mut store = AttemptStore::load(store_path)?;
+    let attempts = store.subjects.entry(subject.to_string()).or_default();
+    attempts.failures += 1;
+    attempts.last_failure = now;
+    let failures = attempts.failures;
+    store.save(store_path)?;
+    Ok(failures)
+}
+
+/// Forget the failures on `subject` after a successful attempt.
+pub fn record_success_in(store_path: &Path, subject: &str) -> Result<()> {
+    let mut store = AttemptStore::load(store_path)?;
+    if store.subjects.remove(subject).is_some() {
+        store.save(store_path)?;
+    }
+    Ok(())
+}
+
+/// [`check_in`] the default store.
+pub fn check(subject: &str) -> Result<()> {
+    check_in(&AttemptStore::default_path()?, subject, chrono::Utc::now().timestamp())
+}
+
+/// [`record_failure_in`] the default store, and record the failure in the
+/// audit log.
+pub fn record_failure(subject: &str) -> Result<u32> {
+    let failures = record_failure_in(&AttemptStore::default_path()?, subject, chrono::Utc::now().timestamp())?;
+
+    let mut event = crate::audit::AuditEvent::new("passphrase");
+    event.subject = Some(subject.to_string());
+    event.outcome = format!("failed ({} in a row)", failures);
+    if let Err(e) = crate::audit::record(&event, None) {
+        crate::say_err!("⚠️  Failed to write audit log: {:#}", e);
+    }
+    Ok(failures)
+}
+
+/// [`record_success_in`] the default store.
+pub fn record_success(subject: &str) -> Result<()> {
+    record_success_in(&AttemptStore::default_path()?, subject)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_grows_after_free_attempts() {
+        assert_eq!(backoff(0), Duration::ZERO);
+        assert_eq!(backoff(FREE_ATTEMPTS - 1), Duration::ZERO);
+        assert_eq!(backoff(FREE_ATTEMPTS), Duration::from_secs(1));
+        assert_eq!(backoff(FREE_ATTEMPTS + 3), Duration::from_secs(8));
+        assert_eq!(backoff(100), MAX_BACKOFF);
+    }
+
+    #[test]
+    fn test_failures_are_counted_until_success() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let store = temp_dir.path().join(ATTEMPTS_FILE);
+        let key = "/home/dev/.config/sops/age/keys.txt";
+
+        for failure in 1..=FREE_ATTEMPTS {
+            check_in(&store, key, 1000).unwrap();
+            assert_eq!(record_failure_in(&store, key, 1000).unwrap(), failure);
+        }
+        let error = check_in(&store, key, 1000).unwrap_err().to_string();
+        assert!(error.contains("try again in 1s"), "{}", error);
+        check_in(&store, key, 1001).unwrap();
+
+        record_failure_in(&store, key, 1001).unwrap();
+        assert!(check_in(&store, key, 1002).is_err());
+        check_in(&store, key, 1003).unwrap();
+        // Other identities are not affected
+        check_in(&store, "/other/keys.txt", 1001).unwrap();
+
+        record_success_in(&store, key).unwrap();
+        check_in(&store, key, 1003).unwrap();
+        assert!(AttemptStore::load(&store).unwrap().subjects.is_empty());
+    }
+}
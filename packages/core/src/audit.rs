@@ -0,0 +1,250 @@
+//! Append-only audit log.
+//!
+//! Sensitive operations (cloud pushes, and unlock sessions once they lock)
+//! are recorded as one JSON object per line in
+//! `~/.config/shadow-secret/audit.log`. Entries contain key names only —
+//! never secret values. `shadow-secret report` summarizes the log (see
+//! [`crate::usage`]).
+//!
+//! When `$SHADOW_SECRET_AUDIT_WEBHOOK` (or `--webhook`) is set, each entry is
+//! also posted as JSON, with a `text` field for Slack-compatible webhooks.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// File name of the audit log inside the global config directory.
+pub const AUDIT_LOG_FILE: &str = "audit.log";
+
+/// Environment variable holding a webhook notified of every audit entry.
+pub const AUDIT_WEBHOOK_ENV: &str = "SHADOW_SECRET_AUDIT_WEBHOOK";
+
+/// A single audit log entry.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEvent {
+    /// RFC 3339 timestamp (UTC)
+    pub timestamp: String,
+    /// Operation: "push_cloud", "session" or "passphrase"
+    pub event: String,
+    pub user: String,
+    pub host: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    /// Target environment (e.g. "production"); `None` means all environments
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub environment: Option<String>,
+    /// Ticket or justification supplied with `--reason`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Cloud provider pushed to (e.g. "vercel")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    /// Secret names involved (never values)
+    #[serde(default)]
+    pub keys: Vec<String>,
+    /// What else the operation was about (e.g. the age identity of a
+    /// passphrase attempt)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subject: Option<String>,
+    /// How long the operation lasted (for sessions: unlock to lock)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    /// "success", "failed", "cancelled", ...
+    pub outcome: String,
+}
+
+impl AuditEvent {
+    /// New entry for `event`, stamped with the current time, user and host.
+    pub fn new(event: &str) -> Self {
+        Self {
+            timestamp: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            event: event.to_string(),
+            user: current_user(),
+            host: crate::canary::host_name(),
+            project: None,
+            environment: None,
+            reason: None,
+            provider: None,
+            keys: Vec::new(),
+            subject: None,
+            duration_ms: None,
+            outcome: String::new(),
+        }
+    }
+
+    /// One-line human summary (used for webhook `text`).
+    pub fn summary(&self) -> String {
+        format!(
+            "[shadow-secret] {} by {}@{}: {} ({} key(s), env: {}, project: {}, reason: {})",
+            self.event,
+            self.user,
+            self.host,
+            self.outcome,
+            self.keys.len(),
+            self.environment.as_deref().unwrap_or("all"),
+            self.project.as_deref().unwrap_or("-"),
+            self.reason.as_deref().unwrap_or("-"),
+        )
+    }
+}
+
+/// Name of the user running the command.
+fn current_user() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+/// Default audit log location (`~/.config/shadow-secret/audit.log`).
+pub fn audit_log_path() -> Result<PathBuf> {
+    Ok(crate::isolation::state_dir()?.join(AUDIT_LOG_FILE))
+}
+
+/// Append an entry to the audit log at `path`.
+pub fn append(path: &Path, event: &AuditEvent) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory: {:?}", parent))?;
+    }
+
+    let mut options = std::fs::OpenOptions::new();
+    options.create(true).append(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options
+        .open(path)
+        .with_context(|| format!("Failed to open audit log: {:?}", path))?;
+
+    let line = serde_json::to_string(event)?;
+    writeln!(file, "{}", line).with_context(|| format!("Failed to write audit log: {:?}", path))?;
+
+    Ok(())
+}
+
+/// Read the audit log at `path` (empty if it does not exist).
+///
+/// # Returns
+///
+/// The entries, oldest first, and the number of lines that could not be
+/// parsed (skipped).
+pub fn read(path: &Path) -> Result<(Vec<AuditEvent>, usize)> {
+    if !path.exists() {
+        return Ok((Vec::new(), 0));
+    }
+    let content = std::fs::read_to_string(path).with_context(|| format!("Failed to read audit log: {:?}", path))?;
+
+    let (mut events, mut skipped) = (Vec::new(), 0);
+    for line in content.lines().filter(|line| !line.trim().is_empty()) {
+        match serde_json::from_str(line) {
+            Ok(event) => events.push(event),
+            Err(_) => skipped += 1,
+        }
+    }
+    Ok((events, skipped))
+}
+
+/// Record an entry in the default audit log and notify the webhook, if any.
+///
+/// `webhook` takes precedence over `$SHADOW_SECRET_AUDIT_WEBHOOK`.
+pub fn record(event: &AuditEvent, webhook: Option<&str>) -> Result<()> {
+    append(&audit_log_path()?, event)?;
+
+    let webhook = webhook
+        .map(str::to_string)
+        .or_else(|| std::env::var(AUDIT_WEBHOOK_ENV).ok());
+    if let Some(url) = webhook {
+        post_webhook(&url, event)?;
+    }
+
+    Ok(())
+}
+
+/// Post an entry to a webhook.
+pub fn post_webhook(url: &str, event: &AuditEvent) -> Result<()> {
+    let mut payload = serde_json::to_value(event)?;
+    payload["text"] = serde_json::Value::String(event.summary());
+
+    ureq::post(url)
+        .send_json(payload)
+        .map_err(|e| anyhow::anyhow!("Failed to post audit entry to webhook: {}", e))?;
+
+    Ok(())
+}
+
+/// Whether a push to `environment` needs a `--reason`.
+///
+/// Pushing without an explicit environment reaches production too.
+pub fn requires_reason(environment: Option<&str>) -> bool {
+    match environment {
+        None => true,
+        Some(env) => env.eq_ignore_ascii_case("production") || env.eq_ignore_ascii_case("prod"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_append_writes_json_lines() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("nested").join("audit.log");
+
+        let mut event = AuditEvent::new("push_cloud");
+        event.reason = Some("INC-123".to_string());
+        event.keys = vec!["API_KEY".to_string()];
+        event.outcome = "success".to_string();
+
+        append(&path, &event).unwrap();
+        append(&path, &event).unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines.len(), 2);
+
+        let parsed: AuditEvent = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(parsed, event);
+
+        std::fs::OpenOptions::new().append(true).open(&path).unwrap().write_all(b"not json\n").unwrap();
+        assert_eq!(read(&path).unwrap(), (vec![event.clone(), event], 1));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_audit_log_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("audit.log");
+        append(&path, &AuditEvent::new("push_cloud")).unwrap();
+
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[test]
+    fn test_requires_reason() {
+        assert!(requires_reason(None));
+        assert!(requires_reason(Some("production")));
+        assert!(!requires_reason(Some("preview")));
+        assert!(!requires_reason(Some("development")));
+    }
+
+    #[test]
+    fn test_summary_never_contains_values() {
+        let mut event = AuditEvent::new("push_cloud");
+        event.keys = vec!["API_KEY".to_string()];
+        event.reason = Some("INC-123".to_string());
+        event.outcome = "success".to_string();
+
+        let summary = event.summary();
+        assert!(summary.contains("INC-123"));
+        assert!(summary.contains("1 key(s)"));
+    }
+}
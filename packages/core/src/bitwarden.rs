@@ -0,0 +1,156 @@
+//! Bitwarden Secrets Manager backend (`vault.engine: bitwarden`).
+//!
+//! Every secret of one project is read with the Bitwarden Secrets Manager
+//! CLI (`bws secret list <project-id>`), authenticated by a machine
+//! account access token. Each secret's key becomes a vault key. The token is
+//! handed to `bws` through its environment, never on the command line, and
+//! output is captured in memory only.
+
+use crate::config::BitwardenConfig;
+use crate::process;
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+
+/// Access token read by `bws` (and by Shadow Secret unless `access_token_env`
+/// says otherwise).
+pub const ACCESS_TOKEN_ENV: &str = "BWS_ACCESS_TOKEN";
+
+/// Name of the source in provenance output (`bitwarden:<project-id>`).
+pub fn source_name(config: &BitwardenConfig) -> String {
+    format!("{}:{}", crate::vault::ENGINE_BITWARDEN, config.project_id)
+}
+
+/// Arguments of the `bws` invocation listing the project's secrets.
+pub fn list_args(config: &BitwardenConfig) -> Vec<String> {
+    let mut args = vec![
+        "secret".to_string(),
+        "list".to_string(),
+        config.project_id.clone(),
+        "--output".to_string(),
+        "json".to_string(),
+    ];
+    if let Some(server_url) = &config.server_url {
+        args.extend(["--server-url".to_string(), server_url.clone()]);
+    }
+    args
+}
+
+/// Fetch the secrets of the configured project.
+pub fn fetch(config: &BitwardenConfig) -> Result<HashMap<String, String>> {
+    let var = config.access_token_env.as_deref().unwrap_or(ACCESS_TOKEN_ENV);
+    let token = std::env::var(var)
+        .ok()
+        .filter(|token| !token.is_empty())
+        .with_context(|| format!("No Bitwarden access token: set ${} to a machine account token", var))?;
+
+    let mut command = process::command("bws")?;
+    command.args(list_args(config)).env(ACCESS_TOKEN_ENV, token);
+    let output = process::output_within(&mut command, process::timeouts().decrypt).map_err(|e| {
+        if process::is_interrupted(&e) {
+            return e.into();
+        }
+        anyhow::anyhow!(
+            "Bitwarden Secrets Manager CLI (bws) is not installed or not in PATH: {}. Install it: https://bitwarden.com/help/secrets-manager-cli/",
+            e
+        )
+    })?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(classify_error(&config.project_id, &stderr));
+    }
+
+    parse_secret_list(&output.stdout)
+}
+
+/// Parse `bws secret list --output json`: an array of `{key, value, ...}`.
+pub fn parse_secret_list(json: &[u8]) -> Result<HashMap<String, String>> {
+    let value: serde_json::Value =
+        serde_json::from_slice(json).context("Failed to parse Bitwarden secret list")?;
+    let secrets = value
+        .as_array()
+        .context("Bitwarden secret list must be a JSON array")?;
+
+    let mut parsed = HashMap::new();
+    for secret in secrets {
+        let key = secret
+            .get("key")
+            .and_then(|key| key.as_str())
+            .context("Bitwarden secret has no key")?;
+        let value = secret
+            .get("value")
+            .and_then(|value| value.as_str())
+            .with_context(|| format!("Bitwarden secret '{}' has no value", key))?;
+        if parsed.insert(key.to_string(), value.to_string()).is_some() {
+            anyhow::bail!("Bitwarden project has several secrets named '{}'", key);
+        }
+    }
+    Ok(parsed)
+}
+
+/// Turn `bws` stderr into an actionable error.
+fn classify_error(project_id: &str, stderr: &str) -> anyhow::Error {
+    let stderr = stderr.trim();
+    if stderr.contains("404") || stderr.contains("Resource not found") {
+        anyhow::anyhow!(
+            "Bitwarden has no project '{}' visible to this machine account: {}",
+            project_id,
+            stderr
+        )
+    } else if stderr.contains("401") || stderr.contains("access token") {
+        anyhow::anyhow!(
+            "Bitwarden rejected the access token (expired or revoked?): {}",
+            stderr
+        )
+    } else {
+        anyhow::anyhow!("Failed to list Bitwarden project '{}': {}", project_id, stderr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockRunner;
+    use std::sync::Arc;
+
+    fn config() -> BitwardenConfig {
+        BitwardenConfig {
+            project_id: "e325ea69-a3ab-4dff-836f-b02e013fe530".to_string(),
+            access_token_env: Some("SHADOW_SECRET_TEST_BWS_TOKEN".to_string()),
+            server_url: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_secret_list() {
+        let secrets = parse_secret_list(
+            br#"[{"id":"1","key":"API_KEY","value":"sk_test_123","note":""},{"id":"2","key":"DB_URL","value":"postgres://x"}]"#,
+        )
+        .unwrap();
+        assert_eq!(secrets["API_KEY"], "sk_test_123");
+        assert_eq!(secrets.len(), 2);
+
+        assert!(parse_secret_list(br#"[{"key":"A","value":"1"},{"key":"A","value":"2"}]"#).is_err());
+        assert!(parse_secret_list(br#"{"key":"A"}"#).is_err());
+    }
+
+    #[test]
+    fn test_fetch_passes_token_through_env() {
+        let runner = Arc::new(MockRunner::new().respond(
+            "bws",
+            &["secret", "list", "e325ea69-a3ab-4dff-836f-b02e013fe530", "--output", "json"],
+            0,
+            r#"[{"key":"API_KEY","value":"sk_from_bws"}]"#,
+        ));
+
+        std::env::set_var("SHADOW_SECRET_TEST_BWS_TOKEN", "0.machine-token");
+        let secrets = process::with_runner(runner.clone(), || fetch(&config())).unwrap();
+
+        assert_eq!(secrets["API_KEY"], "sk_from_bws");
+        let call = &runner.calls()[0];
+        assert!(!call.args.iter().any(|arg| arg.contains("machine-token")));
+        assert!(call
+            .env
+            .contains(&(ACCESS_TOKEN_ENV.to_string(), "0.machine-token".to_string())));
+    }
+}
@@ -0,0 +1,214 @@
+//! Canary secrets for leak detection.
+//!
+//! A canary is a unique, worthless token stored in the vault next to real
+//! secrets (`SHADOW_CANARY_<PROJECT>_<HOST>`). It travels wherever the real
+//! secrets go — injected files, `push-cloud` — so if it ever shows up in a
+//! public place, the surrounding secrets have leaked too.
+//!
+//! `shadow-secret canary check` searches GitHub code search for every canary
+//! in the vault and posts an alert to a webhook when one is found.
+
+use anyhow::{Context, Result};
+use serde_json::json;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Prefix of every canary key in the vault.
+pub const CANARY_PREFIX: &str = "SHADOW_CANARY_";
+
+/// Prefix of every canary token (makes leaks easy to search for).
+pub const TOKEN_PREFIX: &str = "sscanary_";
+
+/// Default GitHub API endpoint (override for GitHub Enterprise).
+pub const GITHUB_API: &str = "https://api.github.com";
+
+/// Environment variable holding the GitHub token used for code search.
+pub const GITHUB_TOKEN_ENV: &str = "GITHUB_TOKEN";
+
+/// Vault key for the canary of `project` on `host`.
+pub fn canary_key(project: &str, host: &str) -> String {
+    format!("{}{}_{}", CANARY_PREFIX, normalize(project), normalize(host))
+}
+
+/// Uppercase, `[A-Z0-9_]` only.
+fn normalize(part: &str) -> String {
+    let normalized: String = part
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    let trimmed = normalized.trim_matches('_');
+    if trimmed.is_empty() {
+        "UNKNOWN".to_string()
+    } else {
+        trimmed.to_string()
+    }
+}
+
+/// Generate a new random canary token.
+pub fn generate_token() -> Result<String> {
+    let mut bytes = [0u8; 16];
+    getrandom::getrandom(&mut bytes)
+        .map_err(|e| anyhow::anyhow!("Failed to generate random canary token: {}", e))?;
+
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    Ok(format!("{}{}", TOKEN_PREFIX, hex))
+}
+
+/// Name of the current machine.
+pub fn host_name() -> String {
+    sysinfo::System::host_name().unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Canary entries (key, token) found in a set of secrets, sorted by key.
+pub fn canaries(secrets: &HashMap<String, String>) -> Vec<(String, String)> {
+    let mut found: Vec<(String, String)> = secrets
+        .iter()
+        .filter(|(key, _)| key.starts_with(CANARY_PREFIX))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    found.sort();
+    found
+}
+
+/// Add a canary for the current machine to an encrypted vault.
+///
+/// # Returns
+///
+/// The key of the new canary.
+pub fn add_canary(vault_path: &Path, project: &str, age_key_path: Option<&str>) -> Result<String> {
+    let key = canary_key(project, &host_name());
+    let token = generate_token()?;
+
+    crate::vault::set_secret(vault_path, &key, &token, age_key_path)
+        .with_context(|| format!("Failed to add canary {} to {:?}", key, vault_path))?;
+
+    Ok(key)
+}
+
+/// Search GitHub code search for a canary token.
+///
+/// # Returns
+///
+/// URLs of every file containing the token.
+pub fn search_github(api_base: &str, token: &str, canary: &str) -> Result<Vec<String>> {
+    let url = format!("{}/search/code", api_base.trim_end_matches('/'));
+
+    let response = ureq::get(&url)
+        .query("q", &format!("\"{}\"", canary))
+        .set("Accept", "application/vnd.github+json")
+        .set("Authorization", &format!("Bearer {}", token))
+        .set("User-Agent", "shadow-secret")
+        .call()
+        .map_err(|e| anyhow::anyhow!("GitHub code search failed: {}", e))?;
+
+    let body: serde_json::Value = response
+        .into_json()
+        .context("Failed to parse GitHub code search response")?;
+
+    Ok(parse_search_results(&body))
+}
+
+/// Extract matching file URLs from a GitHub code search response.
+fn parse_search_results(body: &serde_json::Value) -> Vec<String> {
+    body.get("items")
+        .and_then(|items| items.as_array())
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| item.get("html_url").and_then(|url| url.as_str()))
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// JSON payload posted to the alert webhook.
+///
+/// Includes a `text` field so Slack/Mattermost incoming webhooks render it.
+pub fn alert_payload(key: &str, matches: &[String]) -> serde_json::Value {
+    json!({
+        "event": "canary_leak",
+        "canary": key,
+        "matches": matches,
+        "text": format!(
+            "🚨 Shadow Secret canary {} found in {} public location(s). Rotate the secrets stored alongside it.\n{}",
+            key,
+            matches.len(),
+            matches.join("\n")
+        ),
+    })
+}
+
+/// Post a leak alert to a webhook.
+pub fn send_alert(webhook: &str, key: &str, matches: &[String]) -> Result<()> {
+    ureq::post(webhook)
+        .send_json(alert_payload(key, matches))
+        .map_err(|e| anyhow::anyhow!("Failed to send canary alert to webhook: {}", e))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canary_key_is_normalized() {
+        assert_eq!(
+            canary_key("my-app", "dev.laptop.local"),
+            "SHADOW_CANARY_MY_APP_DEV_LAPTOP_LOCAL"
+        );
+        assert_eq!(canary_key("", "--"), "SHADOW_CANARY_UNKNOWN_UNKNOWN");
+    }
+
+    #[test]
+    fn test_generate_token_is_unique() {
+        let a = generate_token().unwrap();
+        let b = generate_token().unwrap();
+
+        assert!(a.starts_with(TOKEN_PREFIX));
+        assert_eq!(a.len(), TOKEN_PREFIX.len() + 32);
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn test_canaries_filters_vault() {
+        let mut secrets = HashMap::new();
+        secrets.insert("API_KEY".to_string(), "real".to_string());
+        secrets.insert("SHADOW_CANARY_APP_HOST".to_string(), "sscanary_1".to_string());
+
+        assert_eq!(
+            canaries(&secrets),
+            vec![("SHADOW_CANARY_APP_HOST".to_string(), "sscanary_1".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_parse_search_results() {
+        let body = json!({
+            "total_count": 1,
+            "items": [{ "html_url": "https://github.com/acme/app/blob/main/.env" }]
+        });
+
+        assert_eq!(
+            parse_search_results(&body),
+            vec!["https://github.com/acme/app/blob/main/.env".to_string()]
+        );
+        assert!(parse_search_results(&json!({ "total_count": 0, "items": [] })).is_empty());
+    }
+
+    #[test]
+    fn test_alert_payload() {
+        let payload = alert_payload("SHADOW_CANARY_APP_HOST", &["https://x".to_string()]);
+
+        assert_eq!(payload["event"], "canary_leak");
+        assert!(payload["text"].as_str().unwrap().contains("SHADOW_CANARY_APP_HOST"));
+    }
+}
@@ -1,20 +1,36 @@
 // Shadow Secret - Cleaner Module
 //
 // This module handles cleanup operations including:
-// - Signal handling (SIGINT, SIGTERM)
-// - Process termination (node, openclaw)
-// - File restoration from backups
+// - Signal handling (SIGINT, SIGTERM, SIGHUP, console close on Windows)
+// - Termination of processes holding the target files open
+// - File restoration from backups (wiping the injected content first when
+//   the target asks for it)
 // - Panic handling
 
 use anyhow::{Context, Result};
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::fs;
+use std::path::PathBuf;
 use std::sync::{Mutex, OnceLock};
-use sysinfo::System;
+use std::time::{Duration, Instant};
+use sysinfo::{Pid, ProcessStatus, ProcessesToUpdate, Signal, System};
+
+/// Time a blocking process gets to exit after SIGTERM before it is killed
+pub const TERMINATE_GRACE: Duration = Duration::from_secs(3);
 
 /// Global storage for file backups
 static BACKUPS: OnceLock<Mutex<HashMap<String, String>>> = OnceLock::new();
 
+/// Files created by unlock (`create_if_missing`), deleted on cleanup
+static CREATED: OnceLock<Mutex<Vec<String>>> = OnceLock::new();
+
+/// Files whose injected content is wiped before they are restored or
+/// deleted (`secure_wipe`)
+static SECURE_WIPE: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+
+/// Crash-recovery journal of the current session, deleted once restored
+static JOURNAL: Mutex<Option<PathBuf>> = Mutex::new(None);
+
 /// Initialize the global backups storage
 fn init_backups() -> &'static Mutex<HashMap<String, String>> {
     BACKUPS.get_or_init(|| Mutex::new(HashMap::new()))
@@ -44,6 +60,48 @@ fn backups_is_empty() -> bool {
         .unwrap_or(true)
 }
 
+/// Files registered for cleanup: backed up or created by unlock
+fn registered_paths() -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = init_backups()
+        .lock()
+        .map(|backups| backups.keys().map(PathBuf::from).collect())
+        .unwrap_or_default();
+    if let Ok(created) = init_created().lock() {
+        paths.extend(created.iter().map(PathBuf::from));
+    }
+    paths
+}
+
+/// Whether unlock left files to restore or delete.
+pub fn has_registered_files() -> bool {
+    !registered_paths().is_empty()
+}
+
+/// Initialize the global created files storage
+fn init_created() -> &'static Mutex<Vec<String>> {
+    CREATED.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+/// Get all created files and clear the storage
+fn take_all_created() -> Vec<String> {
+    init_created()
+        .lock()
+        .map(|mut created| std::mem::take(&mut *created))
+        .unwrap_or_default()
+}
+
+/// Register a file created by unlock, to be deleted on cleanup
+///
+/// # Arguments
+/// * `path` - The file that did not exist before unlock
+pub fn register_created_file(path: &str) {
+    if let Ok(mut created) = init_created().lock() {
+        if !created.iter().any(|p| p == path) {
+            created.push(path.to_string());
+        }
+    }
+}
+
 /// Register a backup for a file to be restored on cleanup
 ///
 /// # Arguments
@@ -60,11 +118,50 @@ pub fn register_backup(path: &str, content: &str) {
     register_backup_global(path.to_string(), content.to_string());
 }
 
+/// Register a file whose injected content is overwritten before it is
+/// restored or deleted on cleanup (see [`crate::injector::wipe_file`])
+pub fn register_secure_wipe(path: &str) {
+    SECURE_WIPE.lock().unwrap_or_else(|e| e.into_inner()).insert(path.to_string());
+}
+
+/// Wipe `path` if it was registered for secure wipe; a failure is reported
+/// but does not prevent the restore.
+fn wipe_if_registered(path: &str) {
+    if !SECURE_WIPE.lock().unwrap_or_else(|e| e.into_inner()).remove(path) {
+        return;
+    }
+    let path = std::path::Path::new(path);
+    if !path.exists() {
+        return;
+    }
+    match crate::injector::wipe_file(path) {
+        Ok(()) => crate::say_err!("  ✓ Wiped: {}", path.display()),
+        Err(e) => crate::say_err!("  ⚠️  Failed to wipe {}: {:#}", path.display(), e),
+    }
+}
+
+/// Register the crash-recovery journal describing the registered backups
+/// (see [`crate::journal`]); it is deleted once cleanup restored everything.
+pub fn register_journal(path: PathBuf) {
+    *JOURNAL.lock().unwrap_or_else(|e| e.into_inner()) = Some(path);
+}
+
+/// Revoke the leases still active (see [`crate::lease`]).
+fn revoke_leases() {
+    for (key, result) in crate::lease::revoke_all(crate::lease::RevokeReason::Locked) {
+        match result {
+            Ok(()) => crate::say_err!("  ✓ Revoked lease on {}", key),
+            Err(e) => crate::say_err!("  ✗ Failed to revoke lease on {}: {:#}", key, e),
+        }
+    }
+}
+
 /// Setup signal handlers for graceful shutdown
 ///
 /// This registers handlers for:
 /// - SIGINT (Ctrl+C)
-/// - SIGTERM (termination signal)
+/// - SIGTERM (service managers, `kill`) and SIGHUP (terminal closed) on Unix
+/// - Console close, logoff and shutdown events on Windows
 /// - Panic handler
 ///
 /// # Example
@@ -74,29 +171,59 @@ pub fn register_backup(path: &str, content: &str) {
 /// setup_signal_handlers();
 /// ```
 pub fn setup_signal_handlers() {
-    // Setup Ctrl+C handler
+    // Setup Ctrl+C handler (also SIGTERM and SIGHUP: ctrlc "termination")
     if let Err(e) = ctrlc::set_handler(|| {
-        eprintln!("\n🛑 Received SIGINT (Ctrl+C)");
+        crate::say_err!("\n🛑 Received a termination signal (Ctrl+C, SIGTERM or SIGHUP)");
         cleanup_and_restore();
         std::process::exit(0);
     }) {
-        eprintln!("⚠️  Failed to set SIGINT handler: {}", e);
+        crate::say_err!("⚠️  Failed to set signal handler: {}", e);
     }
 
+    #[cfg(windows)]
+    set_console_close_handler();
+
     // Setup panic handler
     std::panic::set_hook(Box::new(|panic_info| {
-        eprintln!("\n💥 PANIC: {}", panic_info);
+        crate::say_err!("\n💥 PANIC: {}", panic_info);
         cleanup_and_restore();
     }));
 
-    eprintln!("✓ Signal handlers registered");
+    crate::say_err!("✓ Signal handlers registered");
+}
+
+/// Restore on console close, logoff and shutdown events.
+///
+/// Windows ends the process as soon as the handler of these events returns,
+/// so unlike Ctrl+C (handled on the ctrlc thread) the cleanup has to run in
+/// the handler itself. Registered after ctrlc's, it is called first.
+#[cfg(windows)]
+fn set_console_close_handler() {
+    use windows_sys::Win32::Foundation::BOOL;
+    use windows_sys::Win32::System::Console::{
+        SetConsoleCtrlHandler, CTRL_CLOSE_EVENT, CTRL_LOGOFF_EVENT, CTRL_SHUTDOWN_EVENT,
+    };
+
+    unsafe extern "system" fn handler(event: u32) -> BOOL {
+        if !matches!(event, CTRL_CLOSE_EVENT | CTRL_LOGOFF_EVENT | CTRL_SHUTDOWN_EVENT) {
+            // Ctrl+C and Ctrl+Break: left to the ctrlc handler
+            return 0;
+        }
+        crate::say_err!("\n🛑 Console closing");
+        cleanup_and_restore();
+        1
+    }
+
+    if unsafe { SetConsoleCtrlHandler(Some(handler), 1) } == 0 {
+        crate::say_err!("⚠️  Failed to set console close handler: {}", std::io::Error::last_os_error());
+    }
 }
 
 /// Perform complete cleanup and restoration
 ///
 /// This function is idempotent - safe to call multiple times.
 /// It will:
-/// 1. Kill blocking processes (node, openclaw)
+/// 1. Stop the processes holding a registered file open
 /// 2. Restore all files from backups
 /// 3. Clear the backups map
 ///
@@ -107,84 +234,322 @@ pub fn setup_signal_handlers() {
 /// cleanup_and_restore();
 /// ```
 pub fn cleanup_and_restore() {
-    if backups_is_empty() {
-        eprintln!("📭 No backups to restore");
+    revoke_leases();
+
+    let created_is_empty = init_created()
+        .lock()
+        .map(|created| created.is_empty())
+        .unwrap_or(true);
+    if backups_is_empty() && created_is_empty {
+        crate::say_err!("📭 No backups to restore");
+        set_session_state(crate::status::SessionState::Locked);
         return;
     }
 
-    eprintln!("🧹 Starting cleanup...");
+    crate::say_err!("🧹 Starting cleanup...");
+    set_session_state(crate::status::SessionState::Locking);
 
-    // Step 1: Kill blocking processes
-    if let Err(e) = kill_blocking_processes() {
-        eprintln!("⚠️  Failed to kill processes: {}", e);
+    // Step 1: Kill blocking processes (never in restricted mode)
+    if crate::process::is_restricted() {
+        crate::say_err!("  ⊘ Restricted mode: not killing blocking processes");
+    } else if let Err(e) = kill_blocking_processes(&registered_paths()) {
+        crate::say_err!("⚠️  Failed to kill processes: {}", e);
     }
 
     // Step 2: Restore all files
     let backups = take_all_backups();
     let total = backups.len();
     let mut restored = 0;
+    let mut failed = false;
 
     for (path, content) in backups {
+        wipe_if_registered(&path);
         match restore_file(&path, &content) {
             Ok(_) => {
                 restored += 1;
-                eprintln!("  ✓ Restored: {}", path);
+                crate::say_err!("  ✓ Restored: {}", path);
             }
             Err(e) => {
-                eprintln!("  ✗ Failed to restore {}: {}", path, e);
+                failed = true;
+                crate::say_err!("  ✗ Failed to restore {}: {}", path, e);
             }
         }
     }
 
-    eprintln!("✅ Cleanup complete: {}/{} files restored", restored, total);
+    // Step 3: Delete files that did not exist before unlock
+    for path in take_all_created() {
+        wipe_if_registered(&path);
+        match fs::remove_file(&path) {
+            Ok(_) => crate::say_err!("  ✓ Deleted: {}", path),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => {
+                failed = true;
+                crate::say_err!("  ✗ Failed to delete {}: {}", path, e);
+            }
+        }
+    }
+
+    // Step 4: The journal is only needed while something is left to restore
+    crate::journal::end();
+    if let Some(journal) = JOURNAL.lock().unwrap_or_else(|e| e.into_inner()).take() {
+        if failed {
+            crate::say_err!("  ⚠️  Recovery journal kept: {}", journal.display());
+        } else if let Err(e) = fs::remove_file(&journal) {
+            crate::say_err!("  ✗ Failed to delete recovery journal {}: {}", journal.display(), e);
+        }
+    }
+
+    set_session_state(if failed {
+        crate::status::SessionState::Failed
+    } else {
+        crate::status::SessionState::Locked
+    });
+    crate::say_err!("✅ Cleanup complete: {}/{} files restored", restored, total);
 }
 
-/// Kill blocking processes (node, openclaw)
+/// Restore (or delete, if unlock created them) the registered files among
+/// `paths`, leaving every other registration in place: rolls back an unlock
+/// that failed halfway, or locks the targets of one agent session. The
+/// recovery journal is deleted once nothing is left to restore.
 ///
-/// Uses sysinfo to find and terminate processes that might be
-/// blocking access to files or resources.
+/// # Errors
+///
+/// Lists every file that could not be restored or deleted (all are tried).
+pub fn rollback(paths: &[String]) -> Result<()> {
+    let backups: Vec<(String, String)> = init_backups()
+        .lock()
+        .map(|mut backups| {
+            paths
+                .iter()
+                .filter_map(|path| backups.remove(path).map(|content| (path.clone(), content)))
+                .collect()
+        })
+        .unwrap_or_default();
+    let created: Vec<String> = init_created()
+        .lock()
+        .map(|mut created| {
+            let (rolled_back, kept) = std::mem::take(&mut *created).into_iter().partition(|path| paths.contains(path));
+            *created = kept;
+            rolled_back
+        })
+        .unwrap_or_default();
+
+    let mut failures = Vec::new();
+    for (path, content) in backups {
+        wipe_if_registered(&path);
+        if let Err(e) = restore_file(&path, &content) {
+            failures.push(format!("{:#}", e));
+        }
+    }
+    for path in created {
+        wipe_if_registered(&path);
+        match fs::remove_file(&path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                failures.push(format!("Failed to delete {}: {}", path, e));
+            }
+            _ => {}
+        }
+    }
+    if !failures.is_empty() {
+        anyhow::bail!("{}", failures.join("; "));
+    }
+
+    if !has_registered_files() {
+        crate::journal::end();
+        if let Some(journal) = JOURNAL.lock().unwrap_or_else(|e| e.into_inner()).take() {
+            let _ = fs::remove_file(journal);
+        }
+    }
+    Ok(())
+}
+
+/// Report the session state to the CI status file, if any.
+fn set_session_state(state: crate::status::SessionState) {
+    if let Err(e) = crate::status::set_state(state) {
+        crate::say_err!("  ⚠️  Failed to update status file: {:#}", e);
+    }
+}
+
+/// Stop the processes holding any of `paths` open
+///
+/// Each process gets SIGTERM and [`TERMINATE_GRACE`] to exit before it is
+/// killed (on Windows, where there is no SIGTERM, it is killed directly).
+/// Shadow Secret itself is never stopped.
 ///
 /// # Errors
-/// Returns an error if process enumeration fails
+/// Returns an error if the open files cannot be listed
 ///
 /// # Example
 /// ```no_run
 /// use shadow_secret::cleaner::kill_blocking_processes;
+/// use std::path::PathBuf;
 ///
-/// if let Err(e) = kill_blocking_processes() {
+/// if let Err(e) = kill_blocking_processes(&[PathBuf::from("config.json")]) {
 ///     eprintln!("Failed to kill processes: {}", e);
 /// }
 /// ```
-pub fn kill_blocking_processes() -> Result<()> {
-    let mut sys = System::new_all();
-    sys.refresh_all();
-
-    let targets = ["node", "openclaw"];
-    let mut killed = 0;
-
-    for (pid, process) in sys.processes() {
-        let name = process.name();
-        let name_str = name.to_string_lossy();
-
-        if targets.contains(&name_str.as_ref()) {
-            eprintln!("  🔪 Killing process: {} (PID: {})", name_str, pid);
-            if process.kill() {
-                killed += 1;
-            } else {
-                eprintln!("  ⚠️  Failed to kill {} (PID: {})", name_str, pid);
-            }
+pub fn kill_blocking_processes(paths: &[PathBuf]) -> Result<()> {
+    if crate::process::is_restricted() {
+        anyhow::bail!("Process killing is disabled in restricted mode");
+    }
+
+    let existing: Vec<PathBuf> = paths.iter().filter(|path| path.exists()).cloned().collect();
+    if existing.is_empty() {
+        return Ok(());
+    }
+    let mut holders = open_file_holders(&existing)?;
+    holders.remove(&std::process::id());
+
+    let mut stopped = 0;
+    for pid in holders {
+        let pid = Pid::from_u32(pid);
+        let mut sys = System::new();
+        sys.refresh_processes(ProcessesToUpdate::Some(&[pid]));
+        let Some(process) = sys.process(pid) else {
+            continue;
+        };
+        let name = process.name().to_string_lossy().to_string();
+
+        crate::say_err!("  🔪 Stopping process holding a target open: {} (PID: {})", name, pid);
+        if terminate(&sys, pid) {
+            stopped += 1;
+        } else {
+            crate::say_err!("  ⚠️  Failed to kill {} (PID: {})", name, pid);
         }
     }
 
-    if killed > 0 {
-        eprintln!("✓ Killed {} blocking process(es)", killed);
+    if stopped > 0 {
+        crate::say_err!("✓ Stopped {} blocking process(es)", stopped);
     } else {
-        eprintln!("✓ No blocking processes found");
+        crate::say_err!("✓ No blocking processes found");
     }
 
     Ok(())
 }
 
+/// SIGTERM, then kill once [`TERMINATE_GRACE`] has passed.
+fn terminate(sys: &System, pid: Pid) -> bool {
+    let Some(process) = sys.process(pid) else {
+        return true;
+    };
+    if process.kill_with(Signal::Term) == Some(true) {
+        let deadline = Instant::now() + TERMINATE_GRACE;
+        while Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(100));
+            if !is_running(pid) {
+                return true;
+            }
+        }
+        crate::say_err!("  ⏱️  Still running after {}s: killing PID {}", TERMINATE_GRACE.as_secs(), pid);
+    }
+
+    let mut sys = System::new();
+    sys.refresh_processes(ProcessesToUpdate::Some(&[pid]));
+    sys.process(pid).is_none_or(|process| process.kill())
+}
+
+fn is_running(pid: Pid) -> bool {
+    let mut sys = System::new();
+    sys.refresh_processes(ProcessesToUpdate::Some(&[pid]));
+    sys.process(pid).is_some_and(|process| process.status() != ProcessStatus::Zombie)
+}
+
+/// PIDs of the processes with one of `paths` open, read from `/proc`.
+/// Processes of other users cannot be inspected and are skipped.
+#[cfg(target_os = "linux")]
+pub fn open_file_holders(paths: &[PathBuf]) -> Result<BTreeSet<u32>> {
+    let wanted: BTreeSet<PathBuf> = paths.iter().filter_map(|path| path.canonicalize().ok()).collect();
+    let mut holders = BTreeSet::new();
+    if wanted.is_empty() {
+        return Ok(holders);
+    }
+
+    for entry in fs::read_dir("/proc").context("Failed to read /proc")? {
+        let entry = entry?;
+        let Some(pid) = entry.file_name().to_str().and_then(|name| name.parse::<u32>().ok()) else {
+            continue;
+        };
+        let Ok(fds) = fs::read_dir(entry.path().join("fd")) else {
+            continue;
+        };
+        let holds = fds
+            .flatten()
+            .any(|fd| fs::read_link(fd.path()).is_ok_and(|target| wanted.contains(&target)));
+        if holds {
+            holders.insert(pid);
+        }
+    }
+    Ok(holders)
+}
+
+/// PIDs of the processes with one of `paths` open, from `lsof`.
+#[cfg(all(unix, not(target_os = "linux")))]
+pub fn open_file_holders(paths: &[PathBuf]) -> Result<BTreeSet<u32>> {
+    let mut command = crate::process::command("lsof")?;
+    command.arg("-t").arg("--").args(paths);
+    let output = crate::process::output_within(&mut command, Some(Duration::from_secs(10)))
+        .context("Failed to run lsof")?;
+    // lsof exits with 1 when no process has the files open
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.trim().parse().ok())
+        .collect())
+}
+
+/// PIDs of the processes with one of `paths` open, from the Restart
+/// Manager.
+#[cfg(windows)]
+pub fn open_file_holders(paths: &[PathBuf]) -> Result<BTreeSet<u32>> {
+    use windows_sys::Win32::System::RestartManager::{RmEndSession, RmStartSession, CCH_RM_SESSION_KEY};
+
+    let mut session = 0u32;
+    let mut key = [0u16; CCH_RM_SESSION_KEY as usize + 1];
+    let status = unsafe { RmStartSession(&mut session, 0, key.as_mut_ptr()) };
+    if status != 0 {
+        anyhow::bail!("Failed to start a Restart Manager session (error {})", status);
+    }
+
+    let result = restart_manager_list(session, paths);
+    unsafe { RmEndSession(session) };
+    result
+}
+
+#[cfg(windows)]
+fn restart_manager_list(session: u32, paths: &[PathBuf]) -> Result<BTreeSet<u32>> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::System::RestartManager::{RmGetList, RmRegisterResources, RM_PROCESS_INFO};
+    const ERROR_MORE_DATA: u32 = 234;
+
+    let names: Vec<Vec<u16>> = paths
+        .iter()
+        .map(|path| path.as_os_str().encode_wide().chain(std::iter::once(0)).collect())
+        .collect();
+    let pointers: Vec<*const u16> = names.iter().map(|name| name.as_ptr()).collect();
+    let status = unsafe {
+        RmRegisterResources(session, pointers.len() as u32, pointers.as_ptr(), 0, std::ptr::null(), 0, std::ptr::null())
+    };
+    if status != 0 {
+        anyhow::bail!("Failed to register the targets with the Restart Manager (error {})", status);
+    }
+
+    let mut infos: Vec<RM_PROCESS_INFO> = Vec::new();
+    loop {
+        let mut needed = 0u32;
+        let mut count = infos.len() as u32;
+        let mut reasons = 0u32;
+        let status = unsafe { RmGetList(session, &mut needed, &mut count, infos.as_mut_ptr(), &mut reasons) };
+        match status {
+            0 => {
+                infos.truncate(count as usize);
+                return Ok(infos.iter().map(|info| info.Process.dwProcessId).collect());
+            }
+            // The list grew between two calls: retry with room for it
+            ERROR_MORE_DATA => infos = vec![unsafe { std::mem::zeroed() }; needed as usize],
+            _ => anyhow::bail!("Failed to list the processes holding the targets (error {})", status),
+        }
+    }
+}
+
 /// Restore a file from its backup content
 ///
 /// # Arguments
@@ -202,8 +567,8 @@ pub fn kill_blocking_processes() -> Result<()> {
 ///     eprintln!("Failed to restore: {}", e);
 /// }
 /// ```
-fn restore_file(original_path: &str, original_content: &str) -> Result<()> {
-    fs::write(original_path, original_content)
+pub fn restore_file(original_path: &str, original_content: &str) -> Result<()> {
+    crate::injector::write_atomic(std::path::Path::new(original_path), original_content.as_bytes())
         .with_context(|| format!("Failed to restore file: {}", original_path))
 }
 
@@ -268,6 +633,18 @@ mod tests {
         assert_eq!(restored, original_content);
     }
 
+    #[test]
+    fn test_created_files_are_deleted() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("created.json");
+        fs::write(&path, "{\"key\": \"secret\"}").unwrap();
+
+        register_created_file(path.to_str().unwrap());
+        cleanup_and_restore();
+
+        assert!(!path.exists());
+    }
+
     #[test]
     fn test_restore_file_with_invalid_path() {
         let result = restore_file("/nonexistent/path/to/file.txt", "content");
@@ -301,4 +678,60 @@ mod tests {
         assert_eq!(fs::read_to_string(path1).unwrap(), "original1");
         assert_eq!(fs::read_to_string(path2).unwrap(), "original2");
     }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_secure_wipe_overwrites_injected_content() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join(".env");
+        let injected = "API_KEY=sk_live_123\n";
+        fs::write(&path, injected).unwrap();
+        // A second link keeps the injected inode reachable after the restore
+        let link = temp_dir.path().join("injected");
+        fs::hard_link(&path, &link).unwrap();
+
+        let path_str = path.to_str().unwrap();
+        register_secure_wipe(path_str);
+        register_backup(path_str, "API_KEY=$API_KEY\n");
+        cleanup_and_restore();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "API_KEY=$API_KEY\n");
+        assert_eq!(fs::read(&link).unwrap(), vec![0u8; injected.len()]);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_only_processes_holding_targets_are_stopped() {
+        use std::os::unix::process::ExitStatusExt;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let target = temp_dir.path().join("config.json");
+        let other = temp_dir.path().join("other.json");
+        fs::write(&target, "{}").unwrap();
+        fs::write(&other, "{}").unwrap();
+        let hold = |path: &PathBuf| {
+            std::process::Command::new("sh")
+                .args(["-c", "exec 3<\"$0\"; exec sleep 30"])
+                .arg(path)
+                .spawn()
+                .unwrap()
+        };
+        let mut holder = hold(&target);
+        let mut bystander = hold(&other);
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !open_file_holders(std::slice::from_ref(&target)).unwrap().contains(&holder.id()) {
+            assert!(Instant::now() < deadline, "holder never opened the target");
+            std::thread::sleep(Duration::from_millis(20));
+        }
+
+        // Shadow Secret itself is never stopped
+        let _own = fs::File::open(&target).unwrap();
+        kill_blocking_processes(std::slice::from_ref(&target)).unwrap();
+
+        assert_eq!(holder.wait().unwrap().signal(), Some(libc::SIGTERM));
+        assert!(bystander.try_wait().unwrap().is_none());
+        bystander.kill().unwrap();
+        bystander.wait().unwrap();
+    }
 }
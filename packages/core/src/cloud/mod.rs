@@ -4,6 +4,7 @@
 //!
 //! - Vercel (via Vercel CLI)
 
+pub mod transcript;
 pub mod vercel;
 
-pub use vercel::{detect_project_id, push_secrets_to_vercel};
+pub use vercel::{detect_project_id, push_secrets_to_vercel, rename_vercel_env_var, KeyOutcome, PushOutcome, PushReport};
@@ -0,0 +1,303 @@
+//! Markdown transcripts of cloud pushes.
+//!
+//! After each push, a transcript listing every variable, its environment,
+//! and short fingerprints of the previous and new values is written to
+//! `~/.config/shadow-secret/transcripts/`, ready to attach to a
+//! change-management ticket.
+//!
+//! Values never appear in transcripts. Fingerprints are the first 8 hex
+//! digits of a BLAKE2b hash: enough to tell whether a value changed, too
+//! short to confirm a guess with any confidence. Previous fingerprints come
+//! from the last push made with Shadow Secret (`push-fingerprints.json`),
+//! since Vercel does not return stored values.
+
+use anyhow::{Context, Result};
+use blake2::{Blake2b512, Digest};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+/// Directory (inside the global config directory) holding transcripts.
+pub const TRANSCRIPTS_DIR: &str = "transcripts";
+
+/// File (inside the global config directory) holding the last pushed fingerprints.
+pub const FINGERPRINTS_FILE: &str = "push-fingerprints.json";
+
+/// Domain separation for value fingerprints.
+const FINGERPRINT_CONTEXT: &[u8] = b"shadow-secret push fingerprint v1\0";
+
+/// Short fingerprint of a secret value.
+pub fn fingerprint(value: &str) -> String {
+    let digest = Blake2b512::new()
+        .chain_update(FINGERPRINT_CONTEXT)
+        .chain_update(value.as_bytes())
+        .finalize();
+    digest[..4].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// What happened to a variable during the push.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Updated,
+    Failed(String),
+}
+
+/// One variable in a transcript.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TranscriptEntry {
+    /// Remote variable name
+    pub key: String,
+    pub kind: ChangeKind,
+    /// Fingerprint of the value pushed last time, if known
+    pub old_fingerprint: Option<String>,
+    pub new_fingerprint: String,
+}
+
+/// Transcript of a single push.
+#[derive(Debug, Clone)]
+pub struct PushTranscript {
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    pub operator: String,
+    pub host: String,
+    pub project: Option<String>,
+    pub environment: Option<String>,
+    pub git_branch: Option<String>,
+    pub sensitive: bool,
+    pub entries: Vec<TranscriptEntry>,
+}
+
+impl PushTranscript {
+    /// Empty transcript stamped with the current time, user and host.
+    pub fn new(project: Option<&str>, environment: Option<&str>) -> Self {
+        let event = crate::audit::AuditEvent::new("push_cloud");
+        Self {
+            timestamp: chrono::Utc::now(),
+            operator: event.user,
+            host: event.host,
+            project: project.map(str::to_string),
+            environment: environment.map(str::to_string),
+            git_branch: None,
+            sensitive: false,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Render the transcript as Markdown.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "# Shadow Secret cloud push\n");
+        let _ = writeln!(
+            out,
+            "- **Time:** {}",
+            self.timestamp
+                .to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+        );
+        let _ = writeln!(out, "- **Operator:** {}@{}", self.operator, self.host);
+        let _ = writeln!(out, "- **Provider:** Vercel");
+        let _ = writeln!(
+            out,
+            "- **Project:** {}",
+            self.project
+                .as_deref()
+                .unwrap_or("(current Vercel CLI context)")
+        );
+        let _ = writeln!(
+            out,
+            "- **Environment:** {}",
+            self.environment.as_deref().unwrap_or("all")
+        );
+        if let Some(branch) = &self.git_branch {
+            let _ = writeln!(out, "- **Git branch:** {}", branch);
+        }
+        if self.sensitive {
+            let _ = writeln!(out, "- **Sensitive:** yes");
+        }
+
+        let failed = self
+            .entries
+            .iter()
+            .filter(|entry| matches!(entry.kind, ChangeKind::Failed(_)))
+            .count();
+        let _ = writeln!(
+            out,
+            "- **Result:** {} pushed, {} failed\n",
+            self.entries.len() - failed,
+            failed
+        );
+
+        let _ = writeln!(out, "| Variable | Change | Old | New |");
+        let _ = writeln!(out, "|---|---|---|---|");
+        for entry in &self.entries {
+            let change = match &entry.kind {
+                ChangeKind::Added => "added".to_string(),
+                ChangeKind::Updated => "updated".to_string(),
+                ChangeKind::Failed(error) => format!(
+                    "**failed**: {}",
+                    error.replace('|', "\\|").replace('\n', " ")
+                ),
+            };
+            let _ = writeln!(
+                out,
+                "| `{}` | {} | {} | `{}` |",
+                entry.key,
+                change,
+                entry
+                    .old_fingerprint
+                    .as_deref()
+                    .map(|fp| format!("`{}`", fp))
+                    .unwrap_or_else(|| "—".to_string()),
+                entry.new_fingerprint
+            );
+        }
+
+        let _ = writeln!(
+            out,
+            "\nFingerprints are BLAKE2b prefixes of the values; values are never recorded."
+        );
+        out
+    }
+
+    /// File name of the transcript (`push-<timestamp>.md`).
+    pub fn file_name(&self) -> String {
+        format!("push-{}.md", self.timestamp.format("%Y%m%dT%H%M%SZ"))
+    }
+
+    /// Write the transcript into `dir`.
+    pub fn write(&self, dir: &Path) -> Result<PathBuf> {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create directory: {:?}", dir))?;
+        let path = dir.join(self.file_name());
+        std::fs::write(&path, self.render())
+            .with_context(|| format!("Failed to write transcript: {:?}", path))?;
+        Ok(path)
+    }
+}
+
+/// Default transcript directory (`~/.config/shadow-secret/transcripts`).
+pub fn transcripts_dir() -> Result<PathBuf> {
+    Ok(crate::isolation::state_dir()?.join(TRANSCRIPTS_DIR))
+}
+
+/// Fingerprints of the values last pushed, per project/environment/variable.
+#[derive(Debug, Default)]
+pub struct FingerprintStore {
+    entries: BTreeMap<String, String>,
+}
+
+impl FingerprintStore {
+    /// Default store location (`~/.config/shadow-secret/push-fingerprints.json`).
+    pub fn default_path() -> Result<PathBuf> {
+        Ok(crate::isolation::state_dir()?.join(FINGERPRINTS_FILE))
+    }
+
+    /// Load the store (empty if the file does not exist).
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content =
+            std::fs::read_to_string(path).with_context(|| format!("Failed to read {:?}", path))?;
+        let entries = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {:?}", path))?;
+        Ok(Self { entries })
+    }
+
+    /// Save the store.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory: {:?}", parent))?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(&self.entries)?)
+            .with_context(|| format!("Failed to write {:?}", path))
+    }
+
+    fn scope(project: Option<&str>, environment: Option<&str>, key: &str) -> String {
+        format!(
+            "{}/{}/{}",
+            project.unwrap_or("-"),
+            environment.unwrap_or("all"),
+            key
+        )
+    }
+
+    pub fn get(
+        &self,
+        project: Option<&str>,
+        environment: Option<&str>,
+        key: &str,
+    ) -> Option<&String> {
+        self.entries.get(&Self::scope(project, environment, key))
+    }
+
+    pub fn set(
+        &mut self,
+        project: Option<&str>,
+        environment: Option<&str>,
+        key: &str,
+        fingerprint: String,
+    ) {
+        self.entries
+            .insert(Self::scope(project, environment, key), fingerprint);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprint_is_short_and_stable() {
+        assert_eq!(fingerprint("sk_live_123"), fingerprint("sk_live_123"));
+        assert_ne!(fingerprint("sk_live_123"), fingerprint("sk_live_124"));
+        assert_eq!(fingerprint("sk_live_123").len(), 8);
+    }
+
+    #[test]
+    fn test_render_lists_changes_without_values() {
+        let mut transcript = PushTranscript::new(Some("prj_1"), Some("production"));
+        transcript.entries = vec![
+            TranscriptEntry {
+                key: "API_KEY".to_string(),
+                kind: ChangeKind::Updated,
+                old_fingerprint: Some(fingerprint("old")),
+                new_fingerprint: fingerprint("sk_live_123"),
+            },
+            TranscriptEntry {
+                key: "DB_URL".to_string(),
+                kind: ChangeKind::Failed("timeout".to_string()),
+                old_fingerprint: None,
+                new_fingerprint: fingerprint("postgres://x"),
+            },
+        ];
+
+        let markdown = transcript.render();
+        assert!(markdown.contains("- **Environment:** production"));
+        assert!(markdown.contains(&format!(
+            "| `API_KEY` | updated | `{}` | `{}` |",
+            fingerprint("old"),
+            fingerprint("sk_live_123")
+        )));
+        assert!(markdown.contains("**failed**: timeout"));
+        assert!(markdown.contains("1 pushed, 1 failed"));
+        assert!(!markdown.contains("sk_live_123"));
+    }
+
+    #[test]
+    fn test_fingerprint_store_roundtrip() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join(FINGERPRINTS_FILE);
+
+        let mut store = FingerprintStore::load(&path).unwrap();
+        store.set(Some("prj_1"), None, "API_KEY", "abcd1234".to_string());
+        store.save(&path).unwrap();
+
+        let store = FingerprintStore::load(&path).unwrap();
+        assert_eq!(
+            store.get(Some("prj_1"), None, "API_KEY"),
+            Some(&"abcd1234".to_string())
+        );
+        assert_eq!(store.get(Some("prj_1"), Some("preview"), "API_KEY"), None);
+    }
+}
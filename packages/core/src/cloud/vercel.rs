@@ -14,10 +14,75 @@
 //! - `vercel link` - Link project (if needed)
 
 use anyhow::{Context, Result};
-use dialoguer::{theme::ColorfulTheme, Confirm};
 use serde::Deserialize;
 use std::collections::HashMap;
-use std::process::Command;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use super::transcript::{self, ChangeKind, FingerprintStore, PushTranscript, TranscriptEntry};
+use crate::config::ProviderConfig;
+use crate::process;
+
+/// Result of a push attempt.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PushOutcome {
+    /// The push ran; some variables may have failed (see [`PushReport`])
+    Pushed(PushReport),
+    /// Dry run: nothing was pushed
+    DryRun,
+    /// The user declined the confirmation prompt
+    Cancelled,
+    /// Every secret was LOCAL_ONLY_
+    NothingToPush,
+}
+
+/// What happened to one variable during a push.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyOutcome {
+    /// Remote variable name
+    pub key: String,
+    pub change: ChangeKind,
+    pub duration: Duration,
+}
+
+/// Per-variable results of a push that ran.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PushReport {
+    /// One entry per variable, sorted by name
+    pub keys: Vec<KeyOutcome>,
+    /// Variables not pushed because the push was cancelled (Ctrl+C),
+    /// sorted by name
+    pub cancelled: Vec<String>,
+    /// Transcript written for this push, if it could be written
+    pub transcript: Option<PathBuf>,
+    pub duration: Duration,
+}
+
+impl PushReport {
+    /// Names of the variables that were pushed.
+    pub fn succeeded(&self) -> Vec<String> {
+        self.keys
+            .iter()
+            .filter(|outcome| !matches!(outcome.change, ChangeKind::Failed(_)))
+            .map(|outcome| outcome.key.clone())
+            .collect()
+    }
+
+    /// Variables that failed, with their error.
+    pub fn failed(&self) -> Vec<(String, String)> {
+        self.keys
+            .iter()
+            .filter_map(|outcome| match &outcome.change {
+                ChangeKind::Failed(error) => Some((outcome.key.clone(), error.clone())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Every variable was pushed.
+    pub fn is_success(&self) -> bool {
+        self.failed().is_empty() && self.cancelled.is_empty()
+    }
+}
 
 /// Push secrets to Vercel using Vercel CLI.
 ///
@@ -25,8 +90,15 @@ use std::process::Command;
 ///
 /// * `secrets` - Secrets to push (key-value pairs)
 /// * `project_id` - Vercel project ID (optional, auto-detected if None)
+/// * `environment` - Vercel environment (production, preview, development); all if None
+/// * `provider` - Naming (prefix/suffix), sensitivity and branch scope of the variables
 /// * `dry_run` - If true, only show what would be pushed
 ///
+/// Failing variables do not abort the push: each one is reported in
+/// [`PushOutcome::Pushed`], and the caller decides what a partial push means.
+/// Ctrl+C stops the push before the next variable; the ones not pushed are
+/// listed in [`PushReport::cancelled`].
+///
 /// # Security
 ///
 /// - Never logs secret values
@@ -40,20 +112,25 @@ use std::process::Command;
 pub async fn push_secrets_to_vercel(
     secrets: &HashMap<String, String>,
     project_id: Option<String>,
+    environment: Option<&str>,
+    provider: &ProviderConfig,
     dry_run: bool,
-) -> Result<()> {
+) -> Result<PushOutcome> {
+    provider.check_scope(environment)?;
+
     // Check if Vercel CLI is installed
     check_vercel_cli_installed()?;
 
-    // Filter out LOCAL_ONLY_* secrets
-    let secrets: HashMap<&String, &String> = secrets
+    // Filter out LOCAL_ONLY_* secrets (on vault names), then apply the naming
+    let secrets: HashMap<String, &String> = secrets
         .iter()
         .filter(|(k, _)| !k.starts_with("LOCAL_ONLY_"))
+        .map(|(k, v)| (provider.remote_name(k), v))
         .collect();
 
     if secrets.is_empty() {
-        println!("⚠️  No secrets to push (all secrets start with LOCAL_ONLY_)");
-        return Ok(());
+        crate::say!("⚠️  No secrets to push (all secrets start with LOCAL_ONLY_)");
+        return Ok(PushOutcome::NothingToPush);
     }
 
     // Link project if project_id provided
@@ -62,91 +139,142 @@ pub async fn push_secrets_to_vercel(
     }
 
     // Fetch existing variables
-    println!("🔍 Fetching existing environment variables from Vercel...");
+    crate::say!("🔍 Fetching existing environment variables from Vercel...");
     let existing_vars = list_vercel_env_vars()?;
 
     // Show summary
-    println!("\n📋 Summary of variables to push:");
-    println!("   Total: {} variable(s)", secrets.len());
-    println!("   Already exists: {}", existing_vars.len());
-    println!("   New variables: {}", secrets.len() - existing_vars.len());
+    crate::say!("\n📋 Summary of variables to push:");
+    crate::say!("   Total: {} variable(s)", secrets.len());
+    crate::say!("   Already exists: {}", existing_vars.len());
+    crate::say!("   New variables: {}", secrets.len() - existing_vars.len());
 
     // List variable names (NOT values - security!)
-    println!("\n🔐 Variables to push:");
+    crate::say!("\n🔐 Variables to push:");
     for key in secrets.keys() {
-        let status = if existing_vars.contains_key(*key) {
+        let status = if existing_vars.contains_key(key) {
             "✓ (will overwrite)"
         } else {
             "  (new)"
         };
-        println!("   - {} {}", key, status);
+        crate::say!("   - {} {}", key, status);
     }
 
     // Confirm
     if dry_run {
-        println!("\n🏃 Dry run mode - no changes will be made");
-        return Ok(());
+        crate::say!("\n🏃 Dry run mode - no changes will be made");
+        return Ok(PushOutcome::DryRun);
     }
 
-    let theme = ColorfulTheme::default();
-    if !Confirm::with_theme(&theme)
-        .with_prompt("\n❓ Push these secrets to Vercel?")
-        .default(false)
-        .interact()?
-    {
-        println!("❌ Cancelled by user");
-        return Ok(());
+    if !crate::confirm::confirm("\n❓ Push these secrets to Vercel?", false)? {
+        crate::say!("❌ Cancelled by user");
+        return Ok(PushOutcome::Cancelled);
     }
 
     // Push each variable
-    println!("\n🚀 Pushing secrets to Vercel...\n");
+    crate::say!("\n🚀 Pushing secrets to Vercel...\n");
 
-    let mut succeeded = Vec::new();
-    let mut failed = Vec::new();
+    let started = Instant::now();
+    let mut report = PushReport::default();
 
-    for (key, value) in secrets {
-        print!("   → Pushing {}... ", key);
+    let fingerprints_path = FingerprintStore::default_path()?;
+    let mut fingerprints = FingerprintStore::load(&fingerprints_path).unwrap_or_default();
+    let mut transcript = PushTranscript::new(project_id.as_deref(), environment);
+    transcript.git_branch = provider.git_branch.clone();
+    transcript.sensitive = provider.sensitive;
+
+    // Ctrl+C stops the push instead of killing the process, so that what
+    // was pushed is still reported
+    let listener = tokio::spawn(cancel_on_ctrl_c());
 
-        match add_vercel_env_var(key, value).await {
+    for (key, value) in secrets {
+        if process::is_cancelled() {
+            report.cancelled.push(key);
+            continue;
+        }
+        print!("{}", crate::accessible::render(&format!("   → Pushing {}... ", key)));
+        let key_started = Instant::now();
+
+        let new_fingerprint = transcript::fingerprint(value);
+        let old_fingerprint = fingerprints
+            .get(project_id.as_deref(), environment, &key)
+            .cloned();
+        let kind = match add_vercel_env_var(&key, value, environment, provider).await {
             Ok(_) => {
-                println!("✓");
-                succeeded.push(key.clone());
+                crate::say!("✓");
+                fingerprints.set(
+                    project_id.as_deref(),
+                    environment,
+                    &key,
+                    new_fingerprint.clone(),
+                );
+                if existing_vars.contains_key(&key) {
+                    ChangeKind::Updated
+                } else {
+                    ChangeKind::Added
+                }
             }
             Err(e) => {
-                println!("✗");
-                eprintln!("      Error: {}", e);
-                failed.push((key.clone(), e.to_string()));
+                crate::say!("✗");
+                crate::say_err!("      Error: {:#}", e);
+                ChangeKind::Failed(format!("{:#}", e))
             }
-        }
+        };
+        report.keys.push(KeyOutcome {
+            key: key.clone(),
+            change: kind.clone(),
+            duration: key_started.elapsed(),
+        });
+        transcript.entries.push(TranscriptEntry {
+            key,
+            kind,
+            old_fingerprint,
+            new_fingerprint,
+        });
     }
 
-    // Show results
-    println!("\n📊 Results:");
-    println!("   ✓ Succeeded: {}", succeeded.len());
-    println!("   ✗ Failed: {}", failed.len());
+    listener.abort();
+    if process::is_cancelled() {
+        crate::say!("\n🛑 Push cancelled: {} variable(s) not pushed", report.cancelled.len());
+        process::reset_cancel();
+    }
 
-    if !failed.is_empty() {
-        println!("\n❌ Failed variables:");
-        for (key, error) in &failed {
-            println!("   - {}: {}", key, error);
-        }
-        anyhow::bail!("Failed to push {} variable(s)", failed.len());
+    // Record what happened, values masked (best effort: the push is done)
+    transcript.entries.sort_by(|a, b| a.key.cmp(&b.key));
+    if let Err(e) = fingerprints.save(&fingerprints_path) {
+        crate::say_err!("⚠️  Could not save push fingerprints: {}", e);
     }
+    match transcript::transcripts_dir().and_then(|dir| transcript.write(&dir)) {
+        Ok(path) => report.transcript = Some(path),
+        Err(e) => crate::say_err!("⚠️  Could not write push transcript: {}", e),
+    }
+    crate::sink::archive(crate::sink::REPORT_PUSH, &transcript.file_name(), transcript.render().as_bytes());
 
-    println!("\n✅ All secrets pushed successfully!");
-    Ok(())
+    report.keys.sort_by(|a, b| a.key.cmp(&b.key));
+    report.cancelled.sort();
+    report.duration = started.elapsed();
+    Ok(PushOutcome::Pushed(report))
+}
+
+/// Cancel the work in progress (see [`process::cancel`]) on Ctrl+C.
+async fn cancel_on_ctrl_c() {
+    let mut announced = false;
+    while tokio::signal::ctrl_c().await.is_ok() {
+        if !announced {
+            crate::say_err!("\n🛑 Received Ctrl+C: stopping the push...");
+            announced = true;
+        }
+        process::cancel();
+    }
 }
 
 /// Check if Vercel CLI is installed.
 fn check_vercel_cli_installed() -> Result<()> {
-    let output = Command::new("vercel")
-        .arg("--version")
-        .output();
+    let output = process::output(process::command("vercel")?.arg("--version"));
 
     match output {
         Ok(output) if output.status.success() => {
             let version = String::from_utf8_lossy(&output.stdout);
-            println!("✓ Vercel CLI detected: {}", version.trim());
+            crate::say!("✓ Vercel CLI detected: {}", version.trim());
             Ok(())
         }
         Ok(_) => {
@@ -165,13 +293,11 @@ fn check_vercel_cli_installed() -> Result<()> {
 
 /// Link Vercel project by project ID.
 fn link_vercel_project(project_id: &str) -> Result<()> {
-    println!("🔗 Linking Vercel project: {}", project_id);
+    crate::say!("🔗 Linking Vercel project: {}", project_id);
 
-    let output = Command::new("vercel")
-        .arg("link")
-        .arg("--yes")
-        .arg(project_id)
-        .output()
+    let mut command = process::command("vercel")?;
+    command.arg("link").arg("--yes").arg(project_id);
+    let output = process::output_within(&mut command, process::timeouts().cloud)
         .context("Failed to execute 'vercel link' command")?;
 
     if !output.status.success() {
@@ -186,7 +312,7 @@ fn link_vercel_project(project_id: &str) -> Result<()> {
         );
     }
 
-    println!("✓ Project linked successfully");
+    crate::say!("✓ Project linked successfully");
     Ok(())
 }
 
@@ -196,10 +322,9 @@ fn link_vercel_project(project_id: &str) -> Result<()> {
 ///
 /// Map of variable name to environment type
 fn list_vercel_env_vars() -> Result<HashMap<String, String>> {
-    let output = Command::new("vercel")
-        .arg("env")
-        .arg("ls")
-        .output()
+    let mut command = process::command("vercel")?;
+    command.arg("env").arg("ls");
+    let output = process::output_within(&mut command, process::timeouts().cloud)
         .context("Failed to execute 'vercel env ls' command")?;
 
     if !output.status.success() {
@@ -246,35 +371,38 @@ fn list_vercel_env_vars() -> Result<HashMap<String, String>> {
 ///
 /// * `key` - Variable name
 /// * `value` - Variable value
+/// * `environment` - Target environment (all if None)
+/// * `provider` - Sensitive flag and git branch scope
 ///
 /// # Security
 ///
 /// - Value is passed via stdin to avoid shell exposure
 /// - Value is never logged
-async fn add_vercel_env_var(key: &str, value: &str) -> Result<()> {
-    // Build command: vercel env add <key>
-    let mut child = Command::new("vercel")
-        .arg("env")
-        .arg("add")
-        .arg(key)
-        .arg("--yes")  // Auto-confirm
-        .stdin(std::process::Stdio::piped())
-        .stdout(std::process::Stdio::piped())
-        .stderr(std::process::Stdio::piped())
-        .spawn()
-        .context("Failed to spawn 'vercel env add' command")?;
-
-    // Write value to stdin
-    if let Some(mut stdin) = child.stdin.take() {
-        use std::io::Write;
-        writeln!(stdin, "{}", value)
-            .context("Failed to write secret value to Vercel CLI stdin")?;
-    }
-
-    // Wait for command to complete
-    let output = child
-        .wait_with_output()
-        .context("Failed to wait for 'vercel env add' command")?;
+async fn add_vercel_env_var(
+    key: &str,
+    value: &str,
+    environment: Option<&str>,
+    provider: &ProviderConfig,
+) -> Result<()> {
+    // Build command: vercel env add <key> [environment [gitbranch]] [--sensitive]
+    let mut command = process::command("vercel")?;
+    command.arg("env").arg("add").arg(key);
+    if let Some(environment) = environment {
+        command.arg(environment);
+        if let Some(branch) = &provider.git_branch {
+            command.arg(branch);
+        }
+    }
+    if provider.sensitive {
+        command.arg("--sensitive");
+    }
+
+    command.arg("--yes");  // Auto-confirm
+
+    // Value is written to stdin
+    let input = format!("{}\n", value);
+    let output = process::output_with_stdin_within(&mut command, input.as_bytes(), process::timeouts().cloud)
+        .context("Failed to execute 'vercel env add' command")?;
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
@@ -292,6 +420,67 @@ async fn add_vercel_env_var(key: &str, value: &str) -> Result<()> {
     Ok(())
 }
 
+/// Remove an environment variable from Vercel.
+///
+/// # Arguments
+///
+/// * `key` - Variable name
+/// * `environment` - Target environment (all if None)
+/// * `git_branch` - Git branch scope (preview only)
+fn remove_vercel_env_var(key: &str, environment: Option<&str>, git_branch: Option<&str>) -> Result<()> {
+    // Build command: vercel env rm <key> [environment [gitbranch]] --yes
+    let mut command = process::command("vercel")?;
+    command.arg("env").arg("rm").arg(key);
+    if let Some(environment) = environment {
+        command.arg(environment);
+        if let Some(branch) = git_branch {
+            command.arg(branch);
+        }
+    }
+
+    command.arg("--yes");
+    let output = process::output_within(&mut command, process::timeouts().cloud)
+        .context("Failed to execute 'vercel env rm' command")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!(
+            "Failed to remove env var '{}': {}",
+            key,
+            if stderr.is_empty() {
+                "Unknown error"
+            } else {
+                &*stderr
+            }
+        );
+    }
+
+    Ok(())
+}
+
+/// Rename an environment variable on Vercel.
+///
+/// The variable is added under `new_key` first and `old_key` is removed only
+/// once that succeeded, so a failure never leaves the project without it.
+pub async fn rename_vercel_env_var(
+    old_key: &str,
+    new_key: &str,
+    value: &str,
+    project_id: Option<&str>,
+    environment: Option<&str>,
+    provider: &ProviderConfig,
+) -> Result<()> {
+    provider.check_scope(environment)?;
+    check_vercel_cli_installed()?;
+    if let Some(project_id) = project_id {
+        link_vercel_project(project_id)?;
+    }
+
+    add_vercel_env_var(new_key, value, environment, provider).await?;
+    remove_vercel_env_var(old_key, environment, provider.git_branch.as_deref())
+        .with_context(|| format!("'{}' was added but '{}' could not be removed", new_key, old_key))
+}
+
 /// Detect Vercel project ID from multiple sources.
 ///
 /// # Detection Order
@@ -372,10 +561,7 @@ fn try_read_shadow_secret_yaml() -> Result<Option<String>> {
 
 /// Try to read project ID from global config.
 fn try_read_global_config() -> Result<Option<String>> {
-    let home = dirs::home_dir()
-        .context("Failed to determine home directory")?;
-
-    let path = home.join(".config").join("shadow-secret").join("config.yaml");
+    let path = crate::paths::config_dir()?.join("config.yaml");
 
     if !path.exists() {
         return Ok(None);
@@ -415,4 +601,37 @@ mod tests {
         // Will fail if CLI not installed, which is expected
         // In real tests, you'd mock the Command execution
     }
+
+    #[test]
+    fn test_push_report_splits_failures() {
+        let outcome = |key: &str, change| KeyOutcome {
+            key: key.to_string(),
+            change,
+            duration: Duration::ZERO,
+        };
+        let report = PushReport {
+            keys: vec![
+                outcome("API_KEY", ChangeKind::Added),
+                outcome("DB_URL", ChangeKind::Failed("quota exceeded".to_string())),
+                outcome("TOKEN", ChangeKind::Updated),
+            ],
+            ..Default::default()
+        };
+
+        assert!(!report.is_success());
+        assert_eq!(report.succeeded(), vec!["API_KEY", "TOKEN"]);
+        assert_eq!(
+            report.failed(),
+            vec![("DB_URL".to_string(), "quota exceeded".to_string())]
+        );
+
+        // A cancelled push is partial even if nothing failed
+        let cancelled = PushReport {
+            keys: vec![outcome("API_KEY", ChangeKind::Added)],
+            cancelled: vec!["TOKEN".to_string()],
+            ..Default::default()
+        };
+        assert!(!cancelled.is_success());
+        assert!(cancelled.failed().is_empty());
+    }
 }
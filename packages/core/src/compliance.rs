@@ -0,0 +1,286 @@
+//! Compliance report (`shadow-secret compliance-report`).
+//!
+//! A machine-readable statement of what the active configuration
+//! guarantees: how secrets are decrypted, where the key lives, what is
+//! audited, which providers are involved and which policies apply. Security
+//! teams archive it per release; the config's SHA-256 ties each report to
+//! the exact file it describes. The report only contains names, paths and
+//! settings — never secret values.
+
+use crate::config::{Config, SignatureCheck};
+use crate::vault;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+/// Version of the report format, bumped when fields change meaning.
+pub const REPORT_FORMAT: u32 = 1;
+
+/// Guarantees of one configuration.
+#[derive(Debug, Serialize)]
+pub struct ComplianceReport {
+    pub format: u32,
+    /// RFC 3339 timestamp (UTC)
+    pub generated_at: String,
+    pub shadow_secret_version: String,
+    pub config: ConfigIdentity,
+    pub decryption: Decryption,
+    pub key_storage: KeyStorage,
+    pub audit: AuditGuarantees,
+    /// Every external service or tool secrets go through
+    pub providers: Vec<String>,
+    pub policies: Policies,
+    pub targets: Vec<TargetGuarantees>,
+}
+
+/// The file the report describes.
+#[derive(Debug, Serialize)]
+pub struct ConfigIdentity {
+    pub path: String,
+    pub sha256: String,
+    /// "verified", "not_configured" (no trusted key) or "invalid"
+    pub signature: String,
+}
+
+/// How vault contents reach memory.
+#[derive(Debug, Serialize)]
+pub struct Decryption {
+    pub engine: String,
+    /// Vault files (empty for remote engines)
+    pub vault_files: Vec<String>,
+    /// Decrypted vault contents are never written to disk by shadow-secret
+    pub memory_only: bool,
+    pub method: String,
+    /// Plaintext is written to targets while unlocked, and restored on lock
+    pub plaintext_in_targets_while_unlocked: bool,
+}
+
+/// Where the decryption key lives.
+#[derive(Debug, Serialize)]
+pub struct KeyStorage {
+    /// "file", "environment" (`SOPS_AGE_KEY`) or "provider"
+    pub kind: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Whether the key file is itself encrypted with a passphrase
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub passphrase_protected: Option<bool>,
+}
+
+/// What is recorded in the audit log.
+#[derive(Debug, Serialize)]
+pub struct AuditGuarantees {
+    pub enabled: bool,
+    pub log: String,
+    pub events: Vec<String>,
+    pub webhook_configured: bool,
+    pub contains_secret_values: bool,
+}
+
+/// Policies enforced on every unlock.
+#[derive(Debug, Serialize)]
+pub struct Policies {
+    /// A trusted key is configured, so the config must match its signature
+    pub config_signature_enforced: bool,
+    /// Key naming rules (case, prefixes, pattern)
+    pub naming_rules: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_timeout: Option<String>,
+    /// Keys revoked when their lease expires or on lock
+    pub leased_keys: Vec<String>,
+    pub crash_recovery_journal: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backup_destination: Option<String>,
+    pub normalize_keys: bool,
+}
+
+/// Per-target settings that limit exposure.
+#[derive(Debug, Serialize)]
+pub struct TargetGuarantees {
+    pub name: String,
+    pub path: String,
+    /// Receives fake values instead of real secrets
+    pub untrusted: bool,
+    /// Fails the unlock instead of leaving placeholders
+    pub strict: bool,
+    /// Keys it may receive (empty: all of them)
+    pub allowed_keys: Vec<String>,
+    pub outside_project: bool,
+    /// Created on unlock and deleted on lock
+    pub created_on_unlock: bool,
+    /// Injected content overwritten before restore
+    pub secure_wipe: bool,
+}
+
+impl ComplianceReport {
+    /// Describe `config`, loaded from `config_path` whose contents are
+    /// `content`, with the signature check result `signature`.
+    pub fn build(
+        config: &Config,
+        config_path: &Path,
+        content: &[u8],
+        signature: &Result<SignatureCheck>,
+    ) -> Result<Self> {
+        let config_dir = config_path.parent().unwrap_or(Path::new("."));
+        let engine = config.vault.engine.clone();
+        let remote = vault::is_remote_engine(&engine);
+
+        let vault_files = if remote {
+            Vec::new()
+        } else {
+            config
+                .vault_source_paths(config_dir)?
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect()
+        };
+
+        let method = match engine.as_str() {
+            vault::ENGINE_AGE_NATIVE => "decrypted in process (built-in age)".to_string(),
+            vault::ENGINE_SOPS => "decrypted by the sops CLI, read from its output pipe".to_string(),
+            other => format!("fetched from {} over its CLI or API, held in memory", other),
+        };
+
+        Ok(Self {
+            format: REPORT_FORMAT,
+            generated_at: chrono::Utc::now().to_rfc3339(),
+            shadow_secret_version: env!("CARGO_PKG_VERSION").to_string(),
+            config: ConfigIdentity {
+                path: config_path.display().to_string(),
+                sha256: Sha256::digest(content).iter().map(|byte| format!("{:02x}", byte)).collect(),
+                signature: match signature {
+                    Ok(SignatureCheck::Verified { .. }) => "verified",
+                    Ok(SignatureCheck::NotConfigured) => "not_configured",
+                    Err(_) => "invalid",
+                }
+                .to_string(),
+            },
+            decryption: Decryption {
+                engine: engine.clone(),
+                vault_files,
+                memory_only: true,
+                method,
+                plaintext_in_targets_while_unlocked: config.targets.iter().any(|target| !target.untrusted),
+            },
+            key_storage: key_storage(config)?,
+            audit: AuditGuarantees {
+                enabled: true,
+                log: crate::audit::audit_log_path()?.display().to_string(),
+                events: ["session", "push_cloud", "passphrase"].map(str::to_string).to_vec(),
+                webhook_configured: std::env::var(crate::audit::AUDIT_WEBHOOK_ENV).is_ok(),
+                contains_secret_values: false,
+            },
+            providers: providers(config),
+            policies: Policies {
+                config_signature_enforced: matches!(signature, Ok(SignatureCheck::Verified { .. }) | Err(_)),
+                naming_rules: !config.naming.is_default(),
+                session_timeout: config.timeouts.session.clone(),
+                leased_keys: config.leases.iter().map(|lease| lease.key.clone()).collect(),
+                crash_recovery_journal: !remote,
+                backup_destination: config.backup.destination.clone(),
+                normalize_keys: config.vault.normalize_keys,
+            },
+            targets: config
+                .targets
+                .iter()
+                .map(|target| TargetGuarantees {
+                    name: target.name.clone(),
+                    path: target.path.clone(),
+                    untrusted: target.untrusted,
+                    strict: target.strict,
+                    allowed_keys: target.keys.clone(),
+                    outside_project: target.allow_outside_project,
+                    created_on_unlock: target.create_if_missing,
+                    secure_wipe: target.secure_wipe,
+                })
+                .collect(),
+        })
+    }
+}
+
+fn key_storage(config: &Config) -> Result<KeyStorage> {
+    if vault::is_remote_engine(&config.vault.engine) {
+        return Ok(KeyStorage {
+            kind: "provider".to_string(),
+            path: None,
+            passphrase_protected: None,
+        });
+    }
+
+    Ok(match crate::age_native::identity_source(config.vault.age_key_path.as_deref())? {
+        crate::age_native::IdentitySource::File(path) => KeyStorage {
+            kind: "file".to_string(),
+            passphrase_protected: std::fs::read(&path)
+                .ok()
+                .map(|content| crate::age_native::is_passphrase_protected(&content)),
+            path: Some(path.display().to_string()),
+        },
+        crate::age_native::IdentitySource::Inline(_) => KeyStorage {
+            kind: "environment".to_string(),
+            path: None,
+            passphrase_protected: Some(false),
+        },
+    })
+}
+
+/// External services and tools: the vault engine, dynamic secret providers
+/// and the backup destination.
+fn providers(config: &Config) -> Vec<String> {
+    let mut providers = vec![config.vault.engine.clone()];
+    providers.extend(config.dynamic.iter().map(|dynamic| dynamic.provider.clone()));
+    if let Some(destination) = &config.backup.destination {
+        providers.push(if destination.starts_with("s3://") { "s3" } else { "backup-directory" }.to_string());
+    }
+    providers.dedup();
+    providers
+}
+
+/// Build the report for the config at `config_path`.
+pub fn generate(config_path: &Path) -> Result<ComplianceReport> {
+    let content = std::fs::read(config_path).with_context(|| format!("Failed to read config: {:?}", config_path))?;
+    let config = Config::from_file(config_path)?;
+    let signature = crate::config::verify_config_signature(config_path, false);
+    ComplianceReport::build(&config, config_path, &content, &signature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_describes_config() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("project.yaml");
+        let content = "vault:\n  source: secrets.enc.env\n  engine: age-native\n  age_key_path: ${PROJECT_DIR}/keys.txt\n\
+            targets:\n  - name: app\n    path: .env\n    placeholders: [\"$\"]\n    keys: [API_KEY]\n    strict: true\n\
+            leases:\n  - key: DB_TOKEN\n    ttl: 15m\n    revoke: [revoke-db]\n";
+        std::fs::write(&config_path, content).unwrap();
+        std::fs::write(temp_dir.path().join("keys.txt"), "AGE-SECRET-KEY-1...").unwrap();
+
+        let config = Config::from_file(&config_path).unwrap();
+        let report = ComplianceReport::build(
+            &config,
+            &config_path,
+            content.as_bytes(),
+            &Ok(SignatureCheck::NotConfigured),
+        )
+        .unwrap();
+
+        assert_eq!(report.config.sha256.len(), 64);
+        assert_eq!(report.config.signature, "not_configured");
+        assert!(!report.policies.config_signature_enforced);
+        assert_eq!(report.decryption.engine, "age-native");
+        assert!(report.decryption.memory_only);
+        assert_eq!(report.decryption.vault_files.len(), 1);
+        assert_eq!(report.key_storage.kind, "file");
+        assert_eq!(report.key_storage.passphrase_protected, Some(false));
+        assert_eq!(report.providers, vec!["age-native"]);
+        assert_eq!(report.policies.leased_keys, vec!["DB_TOKEN"]);
+        assert_eq!(report.targets[0].allowed_keys, vec!["API_KEY"]);
+        assert!(report.targets[0].strict);
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["audit"]["contains_secret_values"], false);
+    }
+}
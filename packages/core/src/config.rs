@@ -2,25 +2,78 @@
 //
 // This module handles loading and parsing the configuration from project.yaml or global.yaml
 
+use crate::signature::{self, PublicKey};
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 
+/// Environment variable holding the trusted config signing key.
+pub const CONFIG_KEY_ENV: &str = "SHADOW_SECRET_CONFIG_KEY";
+
+/// Outcome of a config signature check.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SignatureCheck {
+    /// The config matches its detached signature
+    Verified {
+        /// Key ID of the trusted signing key
+        key_id: String,
+        /// Trusted comment embedded in the signature
+        trusted_comment: String,
+    },
+    /// No trusted key is configured, so signatures are not enforced
+    NotConfigured,
+}
+
 /// Vault configuration
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct VaultConfig {
-    /// Path to the encrypted secrets file
+    /// Path to the encrypted secrets file (unused with remote engines)
+    #[serde(default)]
     pub source: String,
 
+    /// Additional encrypted files, decrypted concurrently and merged after
+    /// `source` (later files override earlier ones on duplicate keys)
+    #[serde(default)]
+    pub sources: Vec<String>,
+
     /// Optional: Explicit vault path (overrides source-based resolution)
     /// If specified, this path is used directly (absolute or ~/relative to home)
     /// Useful for encrypted drives or custom vault locations
     #[serde(default)]
     pub vault_path: Option<String>,
 
-    /// Encryption engine (currently only "sops" is supported)
+    /// Decryption engine: "sops" (the sops binary), "age-native" (in
+    /// process), "hashicorp" (KV v2 secrets from a HashiCorp Vault server),
+    /// "aws-secretsmanager" or "gcp-secretmanager" (one secret in AWS/GCP),
+    /// "1password" (items read with the `op` CLI), "bitwarden" (a Bitwarden
+    /// Secrets Manager project), "pass" (entries of a password-store)
     pub engine: String,
 
+    /// HashiCorp Vault settings (engine "hashicorp")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hashicorp: Option<HashicorpConfig>,
+
+    /// AWS Secrets Manager settings (engine "aws-secretsmanager")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aws_secretsmanager: Option<AwsSecretsManagerConfig>,
+
+    /// GCP Secret Manager settings (engine "gcp-secretmanager")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gcp: Option<GcpConfig>,
+
+    /// 1Password settings (engine "1password")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub onepassword: Option<OnePasswordConfig>,
+
+    /// Bitwarden Secrets Manager settings (engine "bitwarden")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bitwarden: Option<BitwardenConfig>,
+
+    /// password-store settings (engine "pass")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pass: Option<PassConfig>,
+
     /// Path to age private key for SOPS encryption/decryption
     #[serde(default)]
     pub age_key_path: Option<String>,
@@ -28,14 +81,229 @@ pub struct VaultConfig {
     /// Whether to require the vault to be mounted (for VeraCrypt volumes)
     #[serde(default = "default_require_mount")]
     pub require_mount: bool,
+
+    /// Match keys case-insensitively, treating `-` and `_` as equal
+    /// (`api-key` finds `API_KEY`), in lookups and placeholders
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub normalize_keys: bool,
 }
 
 fn default_require_mount() -> bool {
     false
 }
 
+impl VaultConfig {
+    /// SOPS vault merging `files` in order (later files win on duplicate
+    /// keys), as written with the list form of `vault`.
+    pub fn from_files(files: Vec<String>) -> Result<Self> {
+        let mut files = files.into_iter();
+        let source = files.next().context("The vault list needs at least one file")?;
+
+        Ok(Self {
+            source,
+            sources: files.collect(),
+            vault_path: None,
+            engine: crate::vault::ENGINE_SOPS.to_string(),
+            hashicorp: None,
+            aws_secretsmanager: None,
+            gcp: None,
+            onepassword: None,
+            bitwarden: None,
+            pass: None,
+            age_key_path: None,
+            require_mount: default_require_mount(),
+            normalize_keys: false,
+        })
+    }
+}
+
+/// Accept `vault` as a section or as a list of files:
+///
+/// ```yaml
+/// vault:
+///   - "~/.config/shadow-secret/global.enc.env"   # lowest precedence
+///   - ".enc.env"                                 # wins on duplicate keys
+/// ```
+fn deserialize_vault<'de, D>(deserializer: D) -> std::result::Result<VaultConfig, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    use serde::de::Error;
+
+    let value = serde_yaml::Value::deserialize(deserializer)?;
+    match value {
+        serde_yaml::Value::Sequence(_) => {
+            let files: Vec<String> = serde_yaml::from_value(value).map_err(D::Error::custom)?;
+            VaultConfig::from_files(files).map_err(D::Error::custom)
+        }
+        other => serde_yaml::from_value(other).map_err(D::Error::custom),
+    }
+}
+
+/// Where engine "hashicorp" reads secrets: one KV v2 secret, whose fields
+/// become the vault keys.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct HashicorpConfig {
+    /// Server address (default: `$VAULT_ADDR`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+
+    /// Mount point of the KV v2 secrets engine
+    #[serde(default = "default_hashicorp_mount")]
+    pub mount: String,
+
+    /// Path of the secret within the mount (e.g. "myapp/dev")
+    pub path: String,
+
+    /// Enterprise namespace (default: `$VAULT_NAMESPACE`, if set)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+
+    /// How to obtain a token
+    #[serde(default)]
+    pub auth: HashicorpAuth,
+}
+
+fn default_hashicorp_mount() -> String {
+    "secret".to_string()
+}
+
+/// Authentication to HashiCorp Vault.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "method", rename_all = "lowercase")]
+pub enum HashicorpAuth {
+    /// A token from `$VAULT_TOKEN` (or `token_env`), else `~/.vault-token`
+    /// as written by `vault login`
+    Token {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        token_env: Option<String>,
+    },
+
+    /// AppRole login; the secret ID is read from `$VAULT_SECRET_ID` (or
+    /// `secret_id_env`), never from the config
+    AppRole {
+        role_id: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        secret_id_env: Option<String>,
+        /// Mount point of the AppRole auth method (default: "approle")
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        mount: Option<String>,
+    },
+}
+
+/// Where engine "aws-secretsmanager" reads secrets: one secret whose
+/// string is a JSON object of key/value pairs.
+///
+/// Credentials come from the standard AWS chain (environment, shared
+/// config and credentials files, SSO, instance or container roles).
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct AwsSecretsManagerConfig {
+    /// Secret name or ARN
+    pub secret_id: String,
+
+    /// Region (default: from the AWS config, e.g. `$AWS_REGION`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+
+    /// Named profile (default: `$AWS_PROFILE`, else "default")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+
+    /// Version stage to read (default: "AWSCURRENT")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version_stage: Option<String>,
+}
+
+/// Where engine "gcp-secretmanager" reads secrets: one secret version whose
+/// payload is a JSON object or dotenv lines.
+///
+/// Credentials are Application Default Credentials
+/// (`$GOOGLE_APPLICATION_CREDENTIALS`, then `gcloud auth
+/// application-default login`, then the metadata server).
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct GcpConfig {
+    /// Project ID or number
+    pub project: String,
+
+    /// Secret ID
+    pub secret: String,
+
+    /// Version to read: "latest" (default) or a pinned version number
+    #[serde(default = "default_gcp_version")]
+    pub version: String,
+
+    /// API endpoint (default: https://secretmanager.googleapis.com), e.g.
+    /// a regional or Private Service Connect endpoint
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<String>,
+}
+
+/// Where engine "1password" reads secrets: every field of `item`, and/or
+/// one `op://` secret reference per key.
+///
+/// Authentication is the `op` CLI's own (desktop app integration, `op
+/// signin` or `$OP_SERVICE_ACCOUNT_TOKEN`).
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct OnePasswordConfig {
+    /// Account shorthand, sign-in address or ID (default: the CLI's)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub account: Option<String>,
+
+    /// Vault holding `item` (default: search every vault)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vault: Option<String>,
+
+    /// Item whose labelled fields all become keys
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub item: Option<String>,
+
+    /// Key name → secret reference (`op://<vault>/<item>[/<section>]/<field>`);
+    /// overrides fields of `item`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub keys: BTreeMap<String, String>,
+}
+
+/// Where engine "bitwarden" reads secrets: every secret of one Bitwarden
+/// Secrets Manager project, listed with the `bws` CLI.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct BitwardenConfig {
+    /// Project ID (a UUID, shown in the web vault)
+    pub project_id: String,
+
+    /// Environment variable holding the machine account access token
+    /// (default: BWS_ACCESS_TOKEN)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub access_token_env: Option<String>,
+
+    /// Server URL for self-hosted or EU instances (default: bitwarden.com)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server_url: Option<String>,
+}
+
+/// Where engine "pass" reads secrets: one password-store entry per key.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PassConfig {
+    /// Store directory (default: `$PASSWORD_STORE_DIR`, else ~/.password-store)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub store_dir: Option<String>,
+
+    /// Key name → entry path in the store (e.g. `DB_URL: myapp/postgres`);
+    /// the entry's first line is the value
+    pub keys: BTreeMap<String, String>,
+}
+
+fn default_gcp_version() -> String {
+    "latest".to_string()
+}
+
+impl Default for HashicorpAuth {
+    fn default() -> Self {
+        HashicorpAuth::Token { token_env: None }
+    }
+}
+
 /// Target configuration - where secrets are injected
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
 pub struct TargetConfig {
     /// Name of the target (e.g., "openclaw", "claude")
     pub name: String,
@@ -45,16 +313,652 @@ pub struct TargetConfig {
 
     /// List of placeholders to replace (e.g., ["$WEB_API_KEY", "$HOOK_TOKEN"])
     pub placeholders: Vec<String>,
+
+    /// Allow this target to live outside the config's directory tree
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub allow_outside_project: bool,
+
+    /// Inject deterministic fake values instead of real secrets
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub untrusted: bool,
+
+    /// Create the file from `template` when it does not exist (deleted on lock)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub create_if_missing: bool,
+
+    /// Body of the file created by `create_if_missing`, with placeholders
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+
+    /// Only unlock this target on some platforms or environments
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub when: Option<TargetCondition>,
+
+    /// Vault keys this target may receive (default: all of them)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keys: Vec<String>,
+
+    /// Fail the unlock instead of leaving placeholders without a value
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub strict: bool,
+
+    /// Placeholder form in the file, e.g. `{{KEY}}` (default: `$KEY`/`${KEY}`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub placeholder_style: Option<String>,
+
+    /// Replace placeholders as plain text whatever the format (no escaping
+    /// or re-rendering), and check that JSON/YAML/TOML still parse
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub preserve_formatting: bool,
+
+    /// Overwrite the injected content in place before restoring or deleting
+    /// the file, so the secrets do not linger in freed disk blocks
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub secure_wipe: bool,
+}
+
+/// Platforms accepted by `when.os`.
+pub const KNOWN_OSES: &[&str] = &["linux", "macos", "windows", "unix", "freebsd", "openbsd", "netbsd"];
+
+/// Condition of a target (`when:`), evaluated at unlock time. Every field
+/// set must hold.
+///
+/// ```yaml
+/// when: { os: windows }
+/// when: { env: 'CI != "true"' }
+/// ```
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct TargetCondition {
+    /// Platform: `linux`, `macos`, `windows`, or `unix` for any Unix
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub os: Option<String>,
+
+    /// Environment test: `VAR == "value"`, `VAR != "value"`, `VAR` (set and
+    /// not empty) or `!VAR`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub env: Option<String>,
+}
+
+/// A parsed `when.env` expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EnvCondition {
+    /// `VAR`: set and not empty
+    Set(String),
+    /// `!VAR`: unset or empty
+    Unset(String),
+    /// `VAR == "value"` (an unset variable compares as empty)
+    Equals(String, String),
+    /// `VAR != "value"`
+    NotEquals(String, String),
+}
+
+impl EnvCondition {
+    /// Parse a `when.env` expression.
+    pub fn parse(expression: &str) -> Result<Self> {
+        let expression = expression.trim();
+        let invalid = || {
+            anyhow::anyhow!(
+                "Invalid env condition '{}': expected VAR, !VAR, VAR == \"value\" or VAR != \"value\"",
+                expression
+            )
+        };
+        let variable = |name: &str| -> Result<String> {
+            let name = name.trim();
+            let valid = !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if valid { Ok(name.to_string()) } else { Err(invalid()) }
+        };
+
+        for (operator, equals) in [("==", true), ("!=", false)] {
+            if let Some((name, value)) = expression.split_once(operator) {
+                let value = value.trim();
+                let value = value
+                    .strip_prefix('"')
+                    .and_then(|v| v.strip_suffix('"'))
+                    .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+                    .unwrap_or(value)
+                    .to_string();
+                let name = variable(name)?;
+                return Ok(if equals { Self::Equals(name, value) } else { Self::NotEquals(name, value) });
+            }
+        }
+
+        match expression.strip_prefix('!') {
+            Some(name) => Ok(Self::Unset(variable(name)?)),
+            None => Ok(Self::Set(variable(expression)?)),
+        }
+    }
+
+    /// Evaluate with `lookup` giving the value of a variable.
+    pub fn holds(&self, lookup: impl Fn(&str) -> Option<String>) -> bool {
+        let value = |name: &str| lookup(name).unwrap_or_default();
+        match self {
+            Self::Set(name) => !value(name).is_empty(),
+            Self::Unset(name) => value(name).is_empty(),
+            Self::Equals(name, expected) => value(name) == *expected,
+            Self::NotEquals(name, expected) => value(name) != *expected,
+        }
+    }
+}
+
+impl TargetCondition {
+    /// Check that the condition is well-formed.
+    pub fn validate(&self) -> Result<()> {
+        if let Some(os) = &self.os {
+            if !KNOWN_OSES.contains(&os.as_str()) {
+                anyhow::bail!("Unknown os '{}' (expected one of: {})", os, KNOWN_OSES.join(", "));
+            }
+        }
+        if let Some(env) = &self.env {
+            EnvCondition::parse(env)?;
+        }
+        Ok(())
+    }
+
+    /// Whether the condition holds on this machine, in this environment.
+    pub fn holds(&self) -> Result<bool> {
+        self.holds_with(std::env::consts::OS, |name| std::env::var(name).ok())
+    }
+
+    /// Same as [`TargetCondition::holds`] for a given platform and
+    /// environment.
+    pub fn holds_with(&self, os: &str, lookup: impl Fn(&str) -> Option<String>) -> Result<bool> {
+        if let Some(wanted) = &self.os {
+            let unix = os != "windows";
+            if !(wanted == os || (wanted == "unix" && unix)) {
+                return Ok(false);
+            }
+        }
+        if let Some(env) = &self.env {
+            if !EnvCondition::parse(env)?.holds(lookup) {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+impl TargetConfig {
+    /// The target's `placeholder_style`, if any.
+    pub fn style(&self) -> Result<Option<crate::injector::PlaceholderStyle>> {
+        self.placeholder_style
+            .as_deref()
+            .map(crate::injector::PlaceholderStyle::parse)
+            .transpose()
+            .with_context(|| format!("Invalid placeholder_style of target '{}'", self.name))
+    }
+
+    /// Whether the target applies here (no `when`, or its condition holds).
+    pub fn is_active(&self) -> Result<bool> {
+        match &self.when {
+            Some(condition) => condition
+                .holds()
+                .with_context(|| format!("Invalid 'when' of target '{}'", self.name)),
+            None => Ok(true),
+        }
+    }
+}
+
+/// Split `targets` into the ones that apply here and the names of the
+/// others.
+pub fn partition_active_targets(targets: Vec<TargetConfig>) -> Result<(Vec<TargetConfig>, Vec<String>)> {
+    let mut active = Vec::new();
+    let mut skipped = Vec::new();
+    for target in targets {
+        if target.is_active()? {
+            active.push(target);
+        } else {
+            skipped.push(target.name);
+        }
+    }
+    Ok((active, skipped))
+}
+
+/// Settings of a cloud provider.
+///
+/// `prefix`/`suffix` let several apps share one provider project: with
+/// `prefix: "MYAPP_"`, `DB_URL` is stored remotely as `MYAPP_DB_URL`.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct ProviderConfig {
+    /// Prepended to every variable name
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub prefix: String,
+
+    /// Appended to every variable name
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub suffix: String,
+
+    /// Store variables as sensitive (values can no longer be read back)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub sensitive: bool,
+
+    /// Scope variables to a git branch (preview environment only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_branch: Option<String>,
+}
+
+impl ProviderConfig {
+    /// Remote name of a vault key.
+    pub fn remote_name(&self, key: &str) -> String {
+        format!("{}{}{}", self.prefix, key, self.suffix)
+    }
+
+    /// Vault key of a remote name, or `None` if the remote variable does not
+    /// carry this project's prefix/suffix.
+    pub fn local_name(&self, remote: &str) -> Option<String> {
+        let key = remote
+            .strip_prefix(self.prefix.as_str())?
+            .strip_suffix(self.suffix.as_str())?;
+        (!key.is_empty()).then(|| key.to_string())
+    }
+
+    /// Check that the branch scope is compatible with `environment`.
+    pub fn check_scope(&self, environment: Option<&str>) -> Result<()> {
+        if let Some(branch) = &self.git_branch {
+            if environment != Some("preview") {
+                anyhow::bail!(
+                    "Git branch scoping ('{}') is only supported for the preview environment; use --env preview",
+                    branch
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Cloud provider settings
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct CloudConfig {
+    #[serde(default)]
+    pub vercel: ProviderConfig,
+}
+
+/// A leased key: revoked when its `ttl` runs out or the session locks
+/// (see [`crate::lease`]).
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct LeaseConfig {
+    /// Vault key of the ephemeral credential
+    pub key: String,
+
+    /// Lease duration, e.g. `15m` or `1h`
+    pub ttl: String,
+
+    /// Revocation command (program, then arguments; no shell)
+    pub revoke: Vec<String>,
+}
+
+impl LeaseConfig {
+    /// The lease duration.
+    pub fn ttl(&self) -> Result<std::time::Duration> {
+        crate::gc::parse_max_age(&self.ttl).with_context(|| format!("Invalid ttl of lease on {}", self.key))
+    }
+}
+
+/// How long decryption, injection and cloud CLI calls may take (see
+/// [`crate::process::Timeouts`]), and how long an unlock session stays
+/// unlocked. Each is a duration such as `30s` or `5m`, or `none` for no
+/// limit.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct TimeoutsConfig {
+    /// Decrypting a vault (default: 5m)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decrypt: Option<String>,
+
+    /// Injecting secrets into one target (default: 1m)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inject: Option<String>,
+
+    /// One cloud CLI call (default: 5m)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cloud: Option<String>,
+
+    /// An unlock session, before it locks on its own (default: none)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<String>,
+}
+
+fn parse_timeout(
+    name: &str,
+    value: &Option<String>,
+    default: Option<std::time::Duration>,
+) -> Result<Option<std::time::Duration>> {
+    match value.as_deref().map(str::trim) {
+        None => Ok(default),
+        Some("none") => Ok(None),
+        Some(value) => crate::gc::parse_max_age(value)
+            .map(Some)
+            .with_context(|| format!("Invalid timeouts.{}", name)),
+    }
+}
+
+impl TimeoutsConfig {
+    /// Whether no timeout is set (nothing to serialize).
+    pub fn is_default(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// The timeouts, defaults filled in.
+    pub fn resolve(&self) -> Result<crate::process::Timeouts> {
+        let defaults = crate::process::Timeouts::DEFAULT;
+        Ok(crate::process::Timeouts {
+            decrypt: parse_timeout("decrypt", &self.decrypt, defaults.decrypt)?,
+            inject: parse_timeout("inject", &self.inject, defaults.inject)?,
+            cloud: parse_timeout("cloud", &self.cloud, defaults.cloud)?,
+        })
+    }
+
+    /// How long an unlock session stays unlocked, `None` for no limit.
+    pub fn session(&self) -> Result<Option<std::time::Duration>> {
+        parse_timeout("session", &self.session, None)
+    }
+}
+
+/// Rules every key name must follow (see [`crate::naming`]).
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct NamingConfig {
+    /// Letter case: "screaming_snake" (API_KEY) or "snake" (api_key)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub case: Option<String>,
+
+    /// Approved prefixes: every key starts with one of them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub prefixes: Vec<String>,
+
+    /// Regular expression every key matches
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+}
+
+impl NamingConfig {
+    /// Whether no rule is set (nothing to serialize).
+    pub fn is_default(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+/// Where `backup push` uploads the encrypted vaults (see [`crate::backup`]).
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct BackupConfig {
+    /// Directory (e.g. a NAS mount) or `s3://bucket/prefix`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub destination: Option<String>,
+
+    /// Snapshots kept, the oldest deleted first (default: 30)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keep: Option<usize>,
+
+    /// AWS CLI profile used for `s3://` destinations
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+}
+
+impl BackupConfig {
+    /// Whether nothing is configured (nothing to serialize).
+    pub fn is_default(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+/// A place reports are archived to (see [`crate::sink`]).
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct SinkConfig {
+    /// Directory, `s3://bucket/prefix` or `https://` webhook URL
+    pub destination: String,
+
+    /// Reports written to it: "audit", "push", "compliance" (default: all)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reports: Vec<String>,
+
+    /// AWS CLI profile used for `s3://` destinations
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+}
+
+/// A credential minted at unlock time instead of read from the vault (see
+/// [`crate::dynamic`]).
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct DynamicSecretConfig {
+    /// "aws-sts" or "gcp-access-token"
+    pub provider: String,
+
+    /// Lifetime of the credentials, e.g. `15m` (default: 1h)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration: Option<String>,
+
+    /// Role to assume (aws-sts)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role_arn: Option<String>,
+
+    /// Session name of the assumed role (aws-sts, default: "shadow-secret")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_name: Option<String>,
+
+    /// Prefix of the generated `AWS_*` keys (aws-sts)
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub prefix: String,
+
+    /// Region (aws-sts, default: from the AWS config)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+
+    /// Named profile (aws-sts, default: `$AWS_PROFILE`, else "default")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+
+    /// Service account to impersonate (gcp-access-token, default: the
+    /// Application Default Credentials themselves)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service_account: Option<String>,
+
+    /// Key receiving the token (gcp-access-token, default:
+    /// "GOOGLE_OAUTH_ACCESS_TOKEN")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+}
+
+impl DynamicSecretConfig {
+    /// Lifetime of the credentials.
+    pub fn duration(&self) -> Result<std::time::Duration> {
+        match &self.duration {
+            Some(duration) => crate::gc::parse_max_age(duration)
+                .with_context(|| format!("Invalid duration of dynamic secret '{}'", self.provider)),
+            None => Ok(crate::dynamic::DEFAULT_DURATION),
+        }
+    }
+}
+
+/// The `include:` list of a config file (other keys ignored).
+#[derive(Debug, Default, Deserialize)]
+struct Includes {
+    #[serde(default)]
+    include: Vec<String>,
+}
+
+/// A file pulled in by `include:`: more targets, and possibly more includes.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct IncludedConfig {
+    #[serde(default)]
+    include: Vec<String>,
+    #[serde(default)]
+    targets: Vec<TargetConfig>,
+}
+
+/// Vault settings a local config may override.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LocalVaultConfig {
+    #[serde(default)]
+    source: Option<String>,
+    #[serde(default)]
+    vault_path: Option<String>,
+    #[serde(default)]
+    age_key_path: Option<String>,
+}
+
+/// A developer's untracked `<name>.local.yaml`, merged over `<name>.yaml`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LocalConfig {
+    #[serde(default)]
+    vault: LocalVaultConfig,
+    /// Targets added, or replacing the shared target of the same name
+    #[serde(default)]
+    targets: Vec<TargetConfig>,
+    #[serde(default)]
+    variables: BTreeMap<String, String>,
+}
+
+/// Path of the local config overlaying the config at `path`
+/// (`project.yaml` → `project.local.yaml`).
+pub fn local_config_path(path: &Path) -> PathBuf {
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("project");
+    path.with_file_name(format!("{}.local.yaml", stem))
+}
+
+/// Load the files listed in `include` (relative to `including`'s
+/// directory), depth-first, appending each file and its targets to `loaded`.
+///
+/// `stack` holds the chain of files being included, to detect cycles.
+fn load_includes(
+    including: &Path,
+    include: &[String],
+    stack: &mut Vec<PathBuf>,
+    loaded: &mut Vec<(PathBuf, Vec<TargetConfig>)>,
+) -> Result<()> {
+    let base = including.parent().unwrap_or_else(|| Path::new("."));
+
+    for entry in include {
+        let path = base.join(entry).canonicalize().with_context(|| {
+            format!("Included file not found: {} (included from {:?})", entry, including)
+        })?;
+        if let Some(start) = stack.iter().position(|file| *file == path) {
+            let chain: Vec<String> = stack[start..]
+                .iter()
+                .chain(std::iter::once(&path))
+                .map(|file| file.display().to_string())
+                .collect();
+            anyhow::bail!("Include cycle: {}", chain.join(" → "));
+        }
+
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {:?} (included from {:?})", path, including))?;
+        let included: IncludedConfig = from_yaml(&content).with_context(|| {
+            format!(
+                "Failed to parse {:?} (included from {:?}); included files may only contain 'targets' and 'include'",
+                path, including
+            )
+        })?;
+
+        loaded.push((path.clone(), included.targets));
+        stack.push(path.clone());
+        load_includes(&path, &included.include, stack, loaded)?;
+        stack.pop();
+    }
+
+    Ok(())
 }
 
 /// Main configuration structure
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Config {
-    /// Vault configuration
+    /// Vault configuration (a full section, or a list of vault files)
+    #[serde(deserialize_with = "deserialize_vault")]
     pub vault: VaultConfig,
 
     /// List of targets
     pub targets: Vec<TargetConfig>,
+
+    /// Cloud provider settings (variable prefixes/suffixes)
+    #[serde(default)]
+    pub cloud: CloudConfig,
+
+    /// User-defined path variables, usable as `${NAME}` in target paths,
+    /// `vault_path` and `age_key_path` (values may use the built-ins)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub variables: BTreeMap<String, String>,
+
+    /// Keys revoked by a hook when their lease expires or on lock
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub leases: Vec<LeaseConfig>,
+
+    /// Short-lived credentials minted at unlock time
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dynamic: Vec<DynamicSecretConfig>,
+
+    /// Limits on decryption, injection and cloud CLI calls
+    #[serde(default, skip_serializing_if = "TimeoutsConfig::is_default")]
+    pub timeouts: TimeoutsConfig,
+
+    /// Rules every key name must follow
+    #[serde(default, skip_serializing_if = "NamingConfig::is_default")]
+    pub naming: NamingConfig,
+
+    /// Off-machine backups of the encrypted vaults
+    #[serde(default, skip_serializing_if = "BackupConfig::is_default")]
+    pub backup: BackupConfig,
+
+    /// Command shortcuts, in the global config (see [`crate::alias`])
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub aliases: BTreeMap<String, String>,
+
+    /// Where reports are archived, in the global config (see [`crate::sink`])
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sinks: Vec<SinkConfig>,
+}
+
+/// Built-in path variable: the user's home directory.
+pub const HOME_VARIABLE: &str = "HOME";
+
+/// Built-in path variable: the directory containing the config file.
+pub const PROJECT_DIR_VARIABLE: &str = "PROJECT_DIR";
+
+/// Expand every `${NAME}` of `value` from `variables`.
+///
+/// # Errors
+///
+/// Fails on an unknown variable or an unterminated `${`.
+pub fn expand_variables(value: &str, variables: &BTreeMap<String, String>) -> Result<String> {
+    let mut expanded = String::with_capacity(value.len());
+    let mut rest = value;
+
+    while let Some(start) = rest.find("${") {
+        expanded.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let end = after
+            .find('}')
+            .with_context(|| format!("Unterminated variable in '{}'", value))?;
+        let name = &after[..end];
+        let replacement = variables.get(name).with_context(|| {
+            format!("Unknown variable '${{{}}}' in '{}' (define it under 'variables')", name, value)
+        })?;
+        expanded.push_str(replacement);
+        rest = &after[end + 1..];
+    }
+
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// Deserialize a config file. Aliases are resolved and `<<` merge keys
+/// applied (serde would otherwise see `<<` as an unknown field and drop
+/// what it merges), then top-level `x-` keys are removed: as in
+/// docker-compose files, they only hold anchors to reuse.
+///
+/// ```yaml
+/// x-defaults: &defaults
+///   strict: true
+/// targets:
+///   - <<: *defaults
+///     name: app
+/// ```
+fn from_yaml<T: serde::de::DeserializeOwned>(content: &str) -> Result<T> {
+    let mut value: serde_yaml::Value = serde_yaml::from_str(content)?;
+    value.apply_merge()?;
+    if let Some(mapping) = value.as_mapping_mut() {
+        mapping.retain(|key, _| !key.as_str().is_some_and(|key| key.starts_with("x-")));
+    }
+    Ok(serde_yaml::from_value(value)?)
 }
 
 impl Config {
@@ -63,12 +967,136 @@ impl Config {
         let content = std::fs::read_to_string(path.as_ref())
             .with_context(|| format!("Failed to read config file: {:?}", path.as_ref()))?;
 
-        let config: Config = serde_yaml::from_str(&content)
+        let mut config: Config = from_yaml(&content)
             .with_context(|| format!("Failed to parse config file: {:?}", path.as_ref()))?;
 
+        for (_, targets) in Self::load_included(path.as_ref(), &content)? {
+            config.targets.extend(targets);
+        }
+        config.apply_local_config(&local_config_path(path.as_ref()))?;
+
+        let project_dir = path
+            .as_ref()
+            .canonicalize()
+            .with_context(|| format!("Failed to resolve config file path: {:?}", path.as_ref()))?
+            .parent()
+            .map(Path::to_path_buf)
+            .context("Config file has no parent directory")?;
+        config.expand_path_variables(&project_dir)?;
+
         Ok(config)
     }
 
+    /// Merge the local config at `path` if it exists: vault settings and
+    /// variables it sets win, and its targets replace shared targets of the
+    /// same name or are appended.
+    fn apply_local_config(&mut self, path: &Path) -> Result<()> {
+        if !path.exists() {
+            return Ok(());
+        }
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read local config: {:?}", path))?;
+        let local: LocalConfig = from_yaml(&content).with_context(|| {
+            format!(
+                "Failed to parse local config {:?}; it may only contain 'vault' (source, vault_path, age_key_path), 'targets' and 'variables'",
+                path
+            )
+        })?;
+
+        if let Some(source) = local.vault.source {
+            self.vault.source = source;
+        }
+        if let Some(vault_path) = local.vault.vault_path {
+            self.vault.vault_path = Some(vault_path);
+        }
+        if let Some(age_key_path) = local.vault.age_key_path {
+            self.vault.age_key_path = Some(age_key_path);
+        }
+        self.variables.extend(local.variables);
+
+        for target in local.targets {
+            match self.targets.iter_mut().find(|existing| existing.name == target.name) {
+                Some(existing) => *existing = target,
+                None => self.targets.push(target),
+            }
+        }
+        Ok(())
+    }
+
+    /// Built-in (`HOME`, `PROJECT_DIR`) and user-defined path variables for
+    /// a config in `project_dir`.
+    pub fn path_variables(&self, project_dir: &Path) -> Result<BTreeMap<String, String>> {
+        let mut builtins = BTreeMap::new();
+        if let Some(home) = crate::paths::home_dir() {
+            builtins.insert(HOME_VARIABLE.to_string(), home.to_string_lossy().to_string());
+        }
+        builtins.insert(
+            PROJECT_DIR_VARIABLE.to_string(),
+            project_dir.to_string_lossy().to_string(),
+        );
+
+        let mut variables = builtins.clone();
+        for (name, value) in &self.variables {
+            if builtins.contains_key(name) {
+                anyhow::bail!("Variable '{}' is built in and cannot be redefined", name);
+            }
+            let value = expand_variables(value, &builtins)
+                .with_context(|| format!("Invalid value of variable '{}'", name))?;
+            variables.insert(name.clone(), value);
+        }
+        Ok(variables)
+    }
+
+    /// Expand `${NAME}` in target paths, `vault_path` and `age_key_path`.
+    pub fn expand_path_variables(&mut self, project_dir: &Path) -> Result<()> {
+        let variables = self.path_variables(project_dir)?;
+
+        for target in &mut self.targets {
+            target.path = expand_variables(&target.path, &variables)
+                .with_context(|| format!("Invalid path of target '{}'", target.name))?;
+        }
+        for (field, value) in [
+            ("vault_path", &mut self.vault.vault_path),
+            ("age_key_path", &mut self.vault.age_key_path),
+        ] {
+            if let Some(value) = value {
+                *value = expand_variables(value, &variables)
+                    .with_context(|| format!("Invalid vault.{}", field))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Files pulled in (directly or not) by the `include:` list of the
+    /// config at `path`, in load order.
+    pub fn included_files<P: AsRef<Path>>(path: P) -> Result<Vec<PathBuf>> {
+        let content = std::fs::read_to_string(path.as_ref())
+            .with_context(|| format!("Failed to read config file: {:?}", path.as_ref()))?;
+
+        Ok(Self::load_included(path.as_ref(), &content)?
+            .into_iter()
+            .map(|(file, _)| file)
+            .collect())
+    }
+
+    /// Included files of the config at `path` (whose text is `content`) and
+    /// their targets. Target paths stay relative to the project directory,
+    /// not to the included file.
+    fn load_included(path: &Path, content: &str) -> Result<Vec<(PathBuf, Vec<TargetConfig>)>> {
+        let includes: Includes = from_yaml(content)
+            .with_context(|| format!("Failed to parse 'include' of config file: {:?}", path))?;
+        if includes.include.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let root = path
+            .canonicalize()
+            .with_context(|| format!("Failed to resolve config file path: {:?}", path))?;
+        let mut loaded = Vec::new();
+        load_includes(&root, &includes.include, &mut vec![root.clone()], &mut loaded)?;
+        Ok(loaded)
+    }
+
     /// Load configuration from project.yaml in the current directory
     /// Falls back to global config if not found
     pub fn from_current_dir() -> Result<Self> {
@@ -79,12 +1107,10 @@ impl Config {
         }
 
         // Fall back to global config
-        let global_config = dirs::home_dir()
-            .map(|home| home.join(".config/shadow-secret/global.yaml"))
-            .context("Failed to determine global config path")?;
+        let global_config = crate::paths::global_config_path()?;
 
         if global_config.exists() {
-            println!("🔑 Using global Shadow Secret configuration from ~/.config/shadow-secret/global.yaml");
+            crate::say!("🔑 Using global Shadow Secret configuration from {}", global_config.display());
             return Self::from_file(&global_config);
         }
 
@@ -100,14 +1126,106 @@ impl Config {
 
     /// Validate the configuration
     pub fn validate(&self) -> Result<()> {
-        // Check vault source
-        if self.vault.source.is_empty() {
+        // Check vault source (remote engines have no files)
+        if self.vault.engine == crate::vault::ENGINE_HASHICORP {
+            match &self.vault.hashicorp {
+                None => anyhow::bail!("Engine '{}' requires a 'vault.hashicorp' section", crate::vault::ENGINE_HASHICORP),
+                Some(hashicorp) if hashicorp.path.trim_matches('/').is_empty() => {
+                    anyhow::bail!("HashiCorp Vault secret path cannot be empty")
+                }
+                Some(_) => {}
+            }
+        } else if self.vault.engine == crate::vault::ENGINE_AWS_SECRETS_MANAGER {
+            match &self.vault.aws_secretsmanager {
+                None => anyhow::bail!(
+                    "Engine '{}' requires a 'vault.aws_secretsmanager' section",
+                    crate::vault::ENGINE_AWS_SECRETS_MANAGER
+                ),
+                Some(aws) if aws.secret_id.is_empty() => {
+                    anyhow::bail!("AWS Secrets Manager secret_id cannot be empty")
+                }
+                Some(_) => {}
+            }
+        } else if self.vault.engine == crate::vault::ENGINE_GCP_SECRET_MANAGER {
+            match &self.vault.gcp {
+                None => anyhow::bail!(
+                    "Engine '{}' requires a 'vault.gcp' section",
+                    crate::vault::ENGINE_GCP_SECRET_MANAGER
+                ),
+                Some(gcp) if gcp.project.is_empty() || gcp.secret.is_empty() => {
+                    anyhow::bail!("GCP Secret Manager project and secret cannot be empty")
+                }
+                Some(gcp) if gcp.version != "latest" && gcp.version.parse::<u64>().is_err() => {
+                    anyhow::bail!(
+                        "GCP Secret Manager version must be 'latest' or a number, got '{}'",
+                        gcp.version
+                    )
+                }
+                Some(_) => {}
+            }
+        } else if self.vault.engine == crate::vault::ENGINE_ONEPASSWORD {
+            match &self.vault.onepassword {
+                None => anyhow::bail!(
+                    "Engine '{}' requires a 'vault.onepassword' section",
+                    crate::vault::ENGINE_ONEPASSWORD
+                ),
+                Some(onepassword) if onepassword.item.is_none() && onepassword.keys.is_empty() => {
+                    anyhow::bail!("1Password needs an 'item' or at least one entry in 'keys'")
+                }
+                Some(onepassword) => {
+                    for (key, reference) in &onepassword.keys {
+                        crate::onepassword::SecretReference::parse(reference)
+                            .with_context(|| format!("Invalid 1Password reference for {}", key))?;
+                    }
+                }
+            }
+        } else if self.vault.engine == crate::vault::ENGINE_BITWARDEN {
+            match &self.vault.bitwarden {
+                None => anyhow::bail!(
+                    "Engine '{}' requires a 'vault.bitwarden' section",
+                    crate::vault::ENGINE_BITWARDEN
+                ),
+                Some(bitwarden) if bitwarden.project_id.is_empty() => {
+                    anyhow::bail!("Bitwarden project_id cannot be empty")
+                }
+                Some(_) => {}
+            }
+        } else if self.vault.engine == crate::vault::ENGINE_PASS {
+            match &self.vault.pass {
+                None => anyhow::bail!("Engine '{}' requires a 'vault.pass' section", crate::vault::ENGINE_PASS),
+                Some(pass) if pass.keys.is_empty() => {
+                    anyhow::bail!("pass needs at least one entry in 'keys'")
+                }
+                Some(pass) if pass.keys.values().any(|entry| entry.trim_matches('/').is_empty()) => {
+                    anyhow::bail!("pass entry paths cannot be empty")
+                }
+                Some(_) => {}
+            }
+        } else if self.vault.source.is_empty() {
             anyhow::bail!("Vault source cannot be empty");
         }
 
+        if self.vault.sources.iter().any(|source| source.is_empty()) {
+            anyhow::bail!("Vault sources cannot contain empty paths");
+        }
+
         // Check vault engine
-        if self.vault.engine != "sops" {
-            anyhow::bail!("Unsupported vault engine: '{}'. Only 'sops' is supported.", self.vault.engine);
+        if self.vault.engine != crate::vault::ENGINE_SOPS
+            && self.vault.engine != crate::vault::ENGINE_AGE_NATIVE
+            && !crate::vault::is_remote_engine(&self.vault.engine)
+        {
+            anyhow::bail!(
+                "Unsupported vault engine: '{}'. Use '{}', '{}', '{}', '{}', '{}', '{}', '{}' or '{}'.",
+                self.vault.engine,
+                crate::vault::ENGINE_SOPS,
+                crate::vault::ENGINE_AGE_NATIVE,
+                crate::vault::ENGINE_HASHICORP,
+                crate::vault::ENGINE_AWS_SECRETS_MANAGER,
+                crate::vault::ENGINE_GCP_SECRET_MANAGER,
+                crate::vault::ENGINE_ONEPASSWORD,
+                crate::vault::ENGINE_BITWARDEN,
+                crate::vault::ENGINE_PASS
+            );
         }
 
         // Check targets
@@ -126,11 +1244,128 @@ impl Config {
             if target.placeholders.is_empty() {
                 anyhow::bail!("Placeholders cannot be empty for target '{}'", target.name);
             }
+            match (target.create_if_missing, &target.template) {
+                (true, None) => anyhow::bail!(
+                    "Target '{}' sets create_if_missing but has no template",
+                    target.name
+                ),
+                (false, Some(_)) => anyhow::bail!(
+                    "Target '{}' has a template but does not set create_if_missing",
+                    target.name
+                ),
+                _ => {}
+            }
+            if !target.keys.is_empty() {
+                let allowed: Vec<String> = target.keys.iter().map(|k| crate::vault::normalize_key(k)).collect();
+                for placeholder in &target.placeholders {
+                    let key = crate::injector::extract_key_name(placeholder);
+                    let allowed_key = allowed.contains(&crate::vault::normalize_key(key));
+                    if !crate::injector::is_all_placeholder(placeholder) && !allowed_key {
+                        anyhow::bail!(
+                            "Placeholder '{}' of target '{}' is not in the target's keys",
+                            placeholder,
+                            target.name
+                        );
+                    }
+                }
+            }
+            target.style()?;
+            for placeholder in &target.placeholders {
+                for transform in crate::injector::placeholder_transforms(placeholder) {
+                    if !crate::injector::TRANSFORMS.contains(&transform) {
+                        anyhow::bail!(
+                            "Unknown transform '{}' in placeholder '{}' of target '{}' (expected one of: {})",
+                            transform,
+                            placeholder,
+                            target.name,
+                            crate::injector::TRANSFORMS.join(", ")
+                        );
+                    }
+                }
+            }
+            if let Some(condition) = &target.when {
+                condition
+                    .validate()
+                    .with_context(|| format!("Invalid 'when' of target '{}'", target.name))?;
+            }
+        }
+
+        for lease in &self.leases {
+            if lease.key.is_empty() {
+                anyhow::bail!("Lease key cannot be empty");
+            }
+            if lease.revoke.first().is_none_or(|program| program.is_empty()) {
+                anyhow::bail!("Lease on {} needs a revoke command", lease.key);
+            }
+            lease.ttl()?;
         }
+        for dynamic in &self.dynamic {
+            crate::dynamic::validate(dynamic)?;
+        }
+        self.timeouts.resolve()?;
+        self.timeouts.session()?;
+        crate::naming::Rules::new(&self.naming)?;
+        if self.backup.keep == Some(0) {
+            anyhow::bail!("backup.keep must keep at least one snapshot");
+        }
+        crate::alias::validate(&self.aliases)?;
+        crate::sink::validate(&self.sinks)?;
 
         Ok(())
     }
 
+    /// Ensure every target resolves inside the config's directory tree.
+    ///
+    /// Targets are resolved the same way the injector opens them (relative to
+    /// the current directory) and symlinks are followed, so neither `..`
+    /// segments nor links can redirect injection to arbitrary system files.
+    /// Targets with `allow_outside_project: true` are skipped.
+    ///
+    /// # Arguments
+    /// * `config_dir` - Directory containing the config file
+    pub fn check_target_containment(&self, config_dir: &Path) -> Result<()> {
+        let root = config_dir
+            .canonicalize()
+            .with_context(|| format!("Failed to resolve config directory: {:?}", config_dir))?;
+
+        for target in &self.targets {
+            if target.allow_outside_project {
+                continue;
+            }
+
+            let resolved = Self::resolve_existing_path(Path::new(&target.path))
+                .with_context(|| format!("Failed to resolve path of target '{}'", target.name))?;
+
+            if !resolved.starts_with(&root) {
+                anyhow::bail!(
+                    "Target '{}' resolves outside the project directory: {}\n\
+                    Refusing to inject secrets into {:?}.\n\
+                    If this is intended, set 'allow_outside_project: true' on the target.",
+                    target.name,
+                    target.path,
+                    resolved
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Canonicalize a path, falling back to its parent for files that don't exist yet.
+    fn resolve_existing_path(path: &Path) -> Result<PathBuf> {
+        if let Ok(resolved) = path.canonicalize() {
+            return Ok(resolved);
+        }
+
+        let file_name = path.file_name().context("Path has no file name")?;
+        let parent = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => std::env::current_dir()?,
+        };
+
+        Ok(parent.canonicalize()?.join(file_name))
+    }
+
     /// Get the absolute path for the vault source
     ///
     /// # Arguments
@@ -141,7 +1376,17 @@ impl Config {
     /// 2. If `source` is absolute, use it
     /// 3. If `source` starts with `~`, expand to home
     /// 4. Otherwise, relative to `config_dir` (not CWD)
+    ///
+    /// Fails with remote engines (HashiCorp Vault, AWS/GCP secret managers),
+    /// which have no vault file.
     pub fn vault_source_path(&self, config_dir: &Path) -> Result<PathBuf> {
+        if crate::vault::is_remote_engine(&self.vault.engine) {
+            anyhow::bail!(
+                "This command works on SOPS vault files; engine '{}' has none",
+                self.vault.engine
+            );
+        }
+
         // 1. Check explicit vault_path first (overrides source)
         if let Some(ref vault_path) = self.vault.vault_path {
             return Self::resolve_path(vault_path, config_dir);
@@ -151,8 +1396,22 @@ impl Config {
         Self::resolve_path(&self.vault.source, config_dir)
     }
 
+    /// Get the absolute paths of every vault file, in merge order.
+    ///
+    /// The primary vault (see [`Config::vault_source_path`]) comes first,
+    /// followed by each entry of `vault.sources`.
+    pub fn vault_source_paths(&self, config_dir: &Path) -> Result<Vec<PathBuf>> {
+        let mut paths = vec![self.vault_source_path(config_dir)?];
+
+        for source in &self.vault.sources {
+            paths.push(Self::resolve_path(source, config_dir)?);
+        }
+
+        Ok(paths)
+    }
+
     /// Helper to resolve a path (absolute, ~, or relative to config_dir)
-    fn resolve_path(path_str: &str, config_dir: &Path) -> Result<PathBuf> {
+    pub(crate) fn resolve_path(path_str: &str, config_dir: &Path) -> Result<PathBuf> {
         let path = Path::new(path_str);
 
         // Absolute path
@@ -162,8 +1421,7 @@ impl Config {
 
         // ~ expansion (home directory)
         if path_str.starts_with('~') {
-            let home = dirs::home_dir()
-                .context("Failed to determine home directory")?;
+            let home = crate::paths::require_home()?;
             let expanded = path_str.replacen('~', home.to_str().unwrap(), 1);
             return Ok(PathBuf::from(expanded));
         }
@@ -173,6 +1431,78 @@ impl Config {
     }
 }
 
+/// Resolve the trusted key used to verify config signatures.
+///
+/// # Resolution Order
+/// 1. `$SHADOW_SECRET_CONFIG_KEY` (inline key or path to a `.pub` file)
+/// 2. `config-signing.pub` in the config directory (`~/.config/shadow-secret`)
+///
+/// Returns `None` when no key is configured.
+pub fn trusted_config_key() -> Result<Option<PublicKey>> {
+    if let Ok(value) = std::env::var(CONFIG_KEY_ENV) {
+        return PublicKey::from_arg(&value)
+            .with_context(|| format!("Invalid key in ${}", CONFIG_KEY_ENV))
+            .map(Some);
+    }
+
+    let default_path = match crate::paths::config_dir() {
+        Ok(dir) => dir.join("config-signing.pub"),
+        Err(_) => return Ok(None),
+    };
+
+    if !default_path.exists() {
+        return Ok(None);
+    }
+
+    PublicKey::from_arg(&default_path.to_string_lossy())
+        .with_context(|| format!("Invalid config signing key: {:?}", default_path))
+        .map(Some)
+}
+
+/// Verify a config file against its detached signature (`<config>.minisig`).
+///
+/// Signatures are enforced as soon as a trusted key is configured, so a
+/// malicious change to `project.yaml` (e.g. redirecting targets to
+/// attacker-controlled paths) is rejected before anything is injected.
+///
+/// # Arguments
+/// * `path` - Config file to verify
+/// * `require` - Fail when no trusted key is configured
+pub fn verify_config_signature(path: &Path, require: bool) -> Result<SignatureCheck> {
+    let key = trusted_config_key()?;
+    verify_config_signature_with_key(path, key.as_ref(), require)
+}
+
+/// Same as [`verify_config_signature`] with an explicit trusted key.
+pub fn verify_config_signature_with_key(
+    path: &Path,
+    key: Option<&PublicKey>,
+    require: bool,
+) -> Result<SignatureCheck> {
+    let Some(key) = key else {
+        if require {
+            anyhow::bail!(
+                "Config signature required but no trusted key is configured.\n\
+                Set ${} or create ~/.config/shadow-secret/config-signing.pub",
+                CONFIG_KEY_ENV
+            );
+        }
+        return Ok(SignatureCheck::NotConfigured);
+    };
+
+    let trusted_comment = signature::verify_file(path, key).with_context(|| {
+        format!(
+            "Config signature check failed. Re-sign after reviewing changes: minisign -Sm {}",
+            path.display()
+        )
+    })?;
+
+    Ok(SignatureCheck::Verified {
+        key_id: key.key_id(),
+        trusted_comment,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -182,18 +1512,36 @@ mod tests {
         let config = Config {
             vault: VaultConfig {
                 source: "test.enc.env".to_string(),
+                sources: Vec::new(),
                 vault_path: None,
                 engine: "sops".to_string(),
+                hashicorp: None,
+                aws_secretsmanager: None,
+                gcp: None,
+                onepassword: None,
+                bitwarden: None,
+                pass: None,
                 age_key_path: None,
                 require_mount: false,
+                normalize_keys: false,
             },
             targets: vec![
                 TargetConfig {
                     name: "test".to_string(),
                     path: "/tmp/test.json".to_string(),
                     placeholders: vec!["$VAR".to_string()],
+                    ..Default::default()
                 },
             ],
+            cloud: Default::default(),
+            variables: Default::default(),
+            leases: Vec::new(),
+            dynamic: Vec::new(),
+            timeouts: Default::default(),
+            naming: Default::default(),
+            backup: Default::default(),
+            aliases: Default::default(),
+            sinks: Default::default(),
         };
 
         assert!(config.validate().is_ok());
@@ -204,12 +1552,29 @@ mod tests {
         let config = Config {
             vault: VaultConfig {
                 source: "".to_string(),
+                sources: Vec::new(),
                 vault_path: None,
                 engine: "sops".to_string(),
+                hashicorp: None,
+                aws_secretsmanager: None,
+                gcp: None,
+                onepassword: None,
+                bitwarden: None,
+                pass: None,
                 age_key_path: None,
                 require_mount: false,
+                normalize_keys: false,
             },
             targets: vec![],
+            cloud: Default::default(),
+            variables: Default::default(),
+            leases: Vec::new(),
+            dynamic: Vec::new(),
+            timeouts: Default::default(),
+            naming: Default::default(),
+            backup: Default::default(),
+            aliases: Default::default(),
+            sinks: Default::default(),
         };
 
         assert!(config.validate().is_err());
@@ -220,17 +1585,110 @@ mod tests {
         let config = Config {
             vault: VaultConfig {
                 source: "test.enc.env".to_string(),
+                sources: Vec::new(),
                 vault_path: None,
                 engine: "invalid".to_string(),
+                hashicorp: None,
+                aws_secretsmanager: None,
+                gcp: None,
+                onepassword: None,
+                bitwarden: None,
+                pass: None,
                 age_key_path: None,
                 require_mount: false,
+                normalize_keys: false,
             },
             targets: vec![],
+            cloud: Default::default(),
+            variables: Default::default(),
+            leases: Vec::new(),
+            dynamic: Vec::new(),
+            timeouts: Default::default(),
+            naming: Default::default(),
+            backup: Default::default(),
+            aliases: Default::default(),
+            sinks: Default::default(),
         };
 
         assert!(config.validate().is_err());
     }
 
+    #[test]
+    fn test_hashicorp_engine_config() {
+        let content = "vault:\n  engine: hashicorp\n  hashicorp:\n    address: https://vault.example.com:8200\n    path: myapp/dev\n    auth:\n      method: approle\n      role_id: my-role\ntargets:\n  - name: app\n    path: app.env\n    placeholders: [\"$API_KEY\"]\n";
+        let config: Config = serde_yaml::from_str(content).unwrap();
+        config.validate().unwrap();
+
+        let hashicorp = config.vault.hashicorp.as_ref().unwrap();
+        assert_eq!(hashicorp.mount, "secret");
+        assert_eq!(
+            hashicorp.auth,
+            HashicorpAuth::AppRole {
+                role_id: "my-role".to_string(),
+                secret_id_env: None,
+                mount: None,
+            }
+        );
+        assert!(config.vault_source_paths(Path::new("/project")).is_err());
+
+        let missing: Config =
+            serde_yaml::from_str("vault: {engine: hashicorp}\ntargets: [{name: app, path: app.env, placeholders: [x]}]\n").unwrap();
+        assert!(missing.validate().is_err());
+    }
+
+    #[test]
+    fn test_gcp_engine_config() {
+        let config = |version: &str| -> Config {
+            serde_yaml::from_str(&format!(
+                "vault:\n  engine: gcp-secretmanager\n  gcp: {{project: my-project, secret: myapp-dev{}}}\ntargets: [{{name: app, path: app.env, placeholders: [x]}}]\n",
+                version
+            ))
+            .unwrap()
+        };
+
+        let latest = config("");
+        latest.validate().unwrap();
+        assert_eq!(latest.vault.gcp.unwrap().version, "latest");
+        config(", version: \"3\"").validate().unwrap();
+        assert!(config(", version: previous").validate().is_err());
+    }
+
+    #[test]
+    fn test_vault_list_shorthand() {
+        let config: Config = serde_yaml::from_str(
+            "vault:\n  - \"~/.config/shadow-secret/global.enc.env\"\n  - .enc.env\ntargets: [{name: app, path: app.env, placeholders: [x]}]\n",
+        )
+        .unwrap();
+        config.validate().unwrap();
+
+        assert_eq!(config.vault.source, "~/.config/shadow-secret/global.enc.env");
+        assert_eq!(config.vault.sources, vec![".enc.env"]);
+        assert_eq!(config.vault.engine, "sops");
+
+        let empty: Result<Config, _> = serde_yaml::from_str("vault: []\ntargets: []\n");
+        assert!(empty.is_err());
+        let invalid: Result<Config, _> = serde_yaml::from_str("vault: {source: x}\ntargets: []\n");
+        assert!(invalid.unwrap_err().to_string().contains("engine"));
+    }
+
+    #[test]
+    fn test_onepassword_engine_config() {
+        let config = |block: &str| -> Config {
+            serde_yaml::from_str(&format!(
+                "vault:\n  engine: 1password\n  onepassword: {}\ntargets: [{{name: app, path: app.env, placeholders: [x]}}]\n",
+                block
+            ))
+            .unwrap()
+        };
+
+        config("{vault: Dev, item: myapp}").validate().unwrap();
+        let keys = config("{keys: {DB_URL: \"op://Dev/postgres/url\"}}");
+        keys.validate().unwrap();
+        assert_eq!(keys.vault.onepassword.unwrap().keys["DB_URL"], "op://Dev/postgres/url");
+        assert!(config("{vault: Dev}").validate().is_err());
+        assert!(config("{keys: {DB_URL: \"Dev/postgres/url\"}}").validate().is_err());
+    }
+
     // NEW TESTS for vault_path functionality
 
     #[test]
@@ -238,12 +1696,29 @@ mod tests {
         let config = Config {
             vault: VaultConfig {
                 source: "ignored.enc.env".to_string(),
+                sources: Vec::new(),
                 vault_path: Some("/absolute/path/vault.enc.env".to_string()),
                 engine: "sops".to_string(),
+                hashicorp: None,
+                aws_secretsmanager: None,
+                gcp: None,
+                onepassword: None,
+                bitwarden: None,
+                pass: None,
                 age_key_path: None,
                 require_mount: false,
+                normalize_keys: false,
             },
             targets: vec![],
+            cloud: Default::default(),
+            variables: Default::default(),
+            leases: Vec::new(),
+            dynamic: Vec::new(),
+            timeouts: Default::default(),
+            naming: Default::default(),
+            backup: Default::default(),
+            aliases: Default::default(),
+            sinks: Default::default(),
         };
 
         let config_dir = Path::new("/any/dir");
@@ -257,12 +1732,29 @@ mod tests {
         let config = Config {
             vault: VaultConfig {
                 source: "vault.enc.env".to_string(),
+                sources: Vec::new(),
                 vault_path: None,
                 engine: "sops".to_string(),
+                hashicorp: None,
+                aws_secretsmanager: None,
+                gcp: None,
+                onepassword: None,
+                bitwarden: None,
+                pass: None,
                 age_key_path: None,
                 require_mount: false,
+                normalize_keys: false,
             },
             targets: vec![],
+            cloud: Default::default(),
+            variables: Default::default(),
+            leases: Vec::new(),
+            dynamic: Vec::new(),
+            timeouts: Default::default(),
+            naming: Default::default(),
+            backup: Default::default(),
+            aliases: Default::default(),
+            sinks: Default::default(),
         };
 
         let config_dir = Path::new("/home/user/.config/shadow-secret");
@@ -279,12 +1771,29 @@ mod tests {
         let config = Config {
             vault: VaultConfig {
                 source: "/absolute/vault.enc.env".to_string(),
+                sources: Vec::new(),
                 vault_path: None,
                 engine: "sops".to_string(),
+                hashicorp: None,
+                aws_secretsmanager: None,
+                gcp: None,
+                onepassword: None,
+                bitwarden: None,
+                pass: None,
                 age_key_path: None,
                 require_mount: false,
+                normalize_keys: false,
             },
             targets: vec![],
+            cloud: Default::default(),
+            variables: Default::default(),
+            leases: Vec::new(),
+            dynamic: Vec::new(),
+            timeouts: Default::default(),
+            naming: Default::default(),
+            backup: Default::default(),
+            aliases: Default::default(),
+            sinks: Default::default(),
         };
 
         let config_dir = Path::new("/any/dir");
@@ -298,12 +1807,29 @@ mod tests {
         let config = Config {
             vault: VaultConfig {
                 source: "~/vault.enc.env".to_string(),
+                sources: Vec::new(),
                 vault_path: None,
                 engine: "sops".to_string(),
+                hashicorp: None,
+                aws_secretsmanager: None,
+                gcp: None,
+                onepassword: None,
+                bitwarden: None,
+                pass: None,
                 age_key_path: None,
                 require_mount: false,
+                normalize_keys: false,
             },
             targets: vec![],
+            cloud: Default::default(),
+            variables: Default::default(),
+            leases: Vec::new(),
+            dynamic: Vec::new(),
+            timeouts: Default::default(),
+            naming: Default::default(),
+            backup: Default::default(),
+            aliases: Default::default(),
+            sinks: Default::default(),
         };
 
         let config_dir = Path::new("/any/dir");
@@ -319,12 +1845,29 @@ mod tests {
         let config = Config {
             vault: VaultConfig {
                 source: "ignored.enc.env".to_string(),
+                sources: Vec::new(),
                 vault_path: Some("~/custom-drive/vault.enc.env".to_string()),
                 engine: "sops".to_string(),
+                hashicorp: None,
+                aws_secretsmanager: None,
+                gcp: None,
+                onepassword: None,
+                bitwarden: None,
+                pass: None,
                 age_key_path: None,
                 require_mount: false,
+                normalize_keys: false,
             },
             targets: vec![],
+            cloud: Default::default(),
+            variables: Default::default(),
+            leases: Vec::new(),
+            dynamic: Vec::new(),
+            timeouts: Default::default(),
+            naming: Default::default(),
+            backup: Default::default(),
+            aliases: Default::default(),
+            sinks: Default::default(),
         };
 
         let config_dir = Path::new("/any/dir");
@@ -334,4 +1877,453 @@ mod tests {
         assert!(result.starts_with(dirs::home_dir().unwrap()));
         assert!(result.ends_with("custom-drive/vault.enc.env"));
     }
+
+    #[test]
+    fn test_config_signature_not_configured() {
+        let result = verify_config_signature_with_key(Path::new("project.yaml"), None, false);
+        assert_eq!(result.unwrap(), SignatureCheck::NotConfigured);
+
+        let result = verify_config_signature_with_key(Path::new("project.yaml"), None, true);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_config_signature_verified_and_tampered() {
+        use crate::signature::test_support::{public_key_line, sign, signing_key};
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let key = signing_key(5);
+        let public_key = PublicKey::parse(&public_key_line(&key)).unwrap();
+
+        let config_path = temp_dir.path().join("project.yaml");
+        let content = "vault:\n  source: .enc.env\n  engine: sops\ntargets: []\n";
+        std::fs::write(&config_path, content).unwrap();
+        std::fs::write(
+            signature::signature_path(&config_path),
+            sign(&key, content.as_bytes()),
+        )
+        .unwrap();
+
+        let result = verify_config_signature_with_key(&config_path, Some(&public_key), false);
+        assert!(matches!(result.unwrap(), SignatureCheck::Verified { .. }));
+
+        // A malicious edit invalidates the signature
+        std::fs::write(&config_path, content.replace("targets: []", "targets: [{name: x, path: /etc/hosts, placeholders: [$ALL]}]")).unwrap();
+        let result = verify_config_signature_with_key(&config_path, Some(&public_key), false);
+        assert!(result.is_err());
+
+        // Missing signature fails when a key is configured
+        std::fs::remove_file(signature::signature_path(&config_path)).unwrap();
+        let result = verify_config_signature_with_key(&config_path, Some(&public_key), false);
+        assert!(result.is_err());
+    }
+
+    fn containment_config(path: &Path, allow_outside_project: bool) -> Config {
+        Config {
+            vault: VaultConfig {
+                source: "vault.enc.env".to_string(),
+                sources: Vec::new(),
+                vault_path: None,
+                engine: "sops".to_string(),
+                hashicorp: None,
+                aws_secretsmanager: None,
+                gcp: None,
+                onepassword: None,
+                bitwarden: None,
+                pass: None,
+                age_key_path: None,
+                require_mount: false,
+                normalize_keys: false,
+            },
+            targets: vec![TargetConfig {
+                name: "target".to_string(),
+                path: path.to_string_lossy().to_string(),
+                placeholders: vec!["$VAR".to_string()],
+                allow_outside_project,
+                ..Default::default()
+            }],
+            cloud: Default::default(),
+            variables: Default::default(),
+            leases: Vec::new(),
+            dynamic: Vec::new(),
+            timeouts: Default::default(),
+            naming: Default::default(),
+            backup: Default::default(),
+            aliases: Default::default(),
+            sinks: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_target_containment_inside_project() {
+        let project = tempfile::TempDir::new().unwrap();
+        let target = project.path().join("config").join("app.json");
+        std::fs::create_dir_all(target.parent().unwrap()).unwrap();
+        std::fs::write(&target, "{}").unwrap();
+
+        let config = containment_config(&target, false);
+        assert!(config.check_target_containment(project.path()).is_ok());
+    }
+
+    #[test]
+    fn test_target_containment_rejects_outside_paths() {
+        let project = tempfile::TempDir::new().unwrap();
+        let outside = tempfile::TempDir::new().unwrap();
+        let target = outside.path().join("hosts");
+        std::fs::write(&target, "").unwrap();
+
+        let config = containment_config(&target, false);
+        let result = config.check_target_containment(project.path());
+        assert!(result.is_err());
+        assert!(result.unwrap_err().to_string().contains("allow_outside_project"));
+
+        // Parent-directory escapes are caught too
+        let escaped = project.path().join("..").join(outside.path().file_name().unwrap()).join("hosts");
+        let config = containment_config(&escaped, false);
+        assert!(config.check_target_containment(project.path()).is_err());
+    }
+
+    #[test]
+    fn test_target_containment_escape_hatch() {
+        let project = tempfile::TempDir::new().unwrap();
+        let outside = tempfile::TempDir::new().unwrap();
+        let target = outside.path().join("shared.env");
+        std::fs::write(&target, "").unwrap();
+
+        let config = containment_config(&target, true);
+        assert!(config.check_target_containment(project.path()).is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_target_containment_follows_symlinks() {
+        let project = tempfile::TempDir::new().unwrap();
+        let outside = tempfile::TempDir::new().unwrap();
+        let real = outside.path().join("secret.conf");
+        std::fs::write(&real, "").unwrap();
+
+        let link = project.path().join("innocent.conf");
+        std::os::unix::fs::symlink(&real, &link).unwrap();
+
+        let config = containment_config(&link, false);
+        assert!(config.check_target_containment(project.path()).is_err());
+    }
+
+    #[test]
+    fn test_provider_naming() {
+        let naming = ProviderConfig {
+            prefix: "MYAPP_".to_string(),
+            suffix: "_V2".to_string(),
+            ..Default::default()
+        };
+
+        assert_eq!(naming.remote_name("DB_URL"), "MYAPP_DB_URL_V2");
+        assert_eq!(naming.local_name("MYAPP_DB_URL_V2"), Some("DB_URL".to_string()));
+        assert_eq!(naming.local_name("OTHER_DB_URL_V2"), None);
+        assert_eq!(naming.local_name("MYAPP__V2"), None);
+
+        let plain = ProviderConfig::default();
+        assert_eq!(plain.remote_name("DB_URL"), "DB_URL");
+        assert_eq!(plain.local_name("DB_URL"), Some("DB_URL".to_string()));
+    }
+
+    #[test]
+    fn test_git_branch_requires_preview() {
+        let provider = ProviderConfig {
+            git_branch: Some("feature/login".to_string()),
+            ..Default::default()
+        };
+
+        assert!(provider.check_scope(Some("preview")).is_ok());
+        assert!(provider.check_scope(Some("production")).is_err());
+        assert!(provider.check_scope(None).is_err());
+        assert!(ProviderConfig::default().check_scope(None).is_ok());
+    }
+
+    #[test]
+    fn test_cloud_naming_from_yaml() {
+        let config: Config = serde_yaml::from_str(
+            "vault: {source: .enc.env, engine: sops}\ntargets: []\ncloud:\n  vercel:\n    prefix: MYAPP_\n",
+        )
+        .unwrap();
+        assert_eq!(config.cloud.vercel.remote_name("DB_URL"), "MYAPP_DB_URL");
+
+        let config: Config =
+            serde_yaml::from_str("vault: {source: .enc.env, engine: sops}\ntargets: []\n").unwrap();
+        assert_eq!(config.cloud, CloudConfig::default());
+    }
+
+    #[test]
+    fn test_vault_source_paths_keep_merge_order() {
+        let config: Config = serde_yaml::from_str(
+            r#"
+vault:
+  source: "base.enc.env"
+  sources:
+    - "/shared/team.enc.env"
+    - "local.enc.yaml"
+  engine: "sops"
+targets:
+  - name: "test"
+    path: "test.json"
+    placeholders: ["$ALL"]
+"#,
+        )
+        .unwrap();
+
+        let config_dir = Path::new("/project");
+        let paths = config.vault_source_paths(config_dir).unwrap();
+
+        assert_eq!(
+            paths,
+            vec![
+                config_dir.join("base.enc.env"),
+                PathBuf::from("/shared/team.enc.env"),
+                config_dir.join("local.enc.yaml"),
+            ]
+        );
+    }
+    #[test]
+    fn test_create_if_missing_requires_template() {
+        let yaml = |extra: &str| {
+            format!(
+                "vault: {{source: .enc.env, engine: sops}}\ntargets:\n  - name: app\n    path: app.json\n    placeholders: [\"$API_KEY\"]\n{}",
+                extra
+            )
+        };
+
+        let config: Config = serde_yaml::from_str(&yaml(
+            "    create_if_missing: true\n    template: '{\"key\": \"$API_KEY\"}'\n",
+        ))
+        .unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.targets[0].template.as_deref(), Some(r#"{"key": "$API_KEY"}"#));
+
+        let config: Config = serde_yaml::from_str(&yaml("    create_if_missing: true\n")).unwrap();
+        assert!(config.validate().is_err());
+
+        let config: Config = serde_yaml::from_str(&yaml("    template: 'x'\n")).unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_target_conditions() {
+        let env = |name: &str| (name == "CI").then(|| "true".to_string());
+        let when = |os: Option<&str>, env: Option<&str>| TargetCondition {
+            os: os.map(str::to_string),
+            env: env.map(str::to_string),
+        };
+
+        assert!(when(Some("windows"), None).holds_with("windows", env).unwrap());
+        assert!(!when(Some("windows"), None).holds_with("macos", env).unwrap());
+        assert!(when(Some("unix"), None).holds_with("macos", env).unwrap());
+        assert!(!when(None, Some(r#"CI != "true""#)).holds_with("linux", env).unwrap());
+        assert!(when(None, Some("CI == 'true'")).holds_with("linux", env).unwrap());
+        assert!(when(None, Some("!HOME_LAB")).holds_with("linux", env).unwrap());
+        assert!(!when(Some("linux"), Some("HOME_LAB")).holds_with("linux", env).unwrap());
+
+        assert!(when(Some("darwin"), None).validate().is_err());
+        assert!(when(None, Some("CI = true")).validate().is_err());
+
+        let config: Config = serde_yaml::from_str(
+            "vault: [.enc.env]\ntargets:\n  - {name: win, path: a.env, placeholders: [$A], when: {os: windows}}\n  - {name: ci, path: b.env, placeholders: [$A], when: {env: 'CI != \"true\"'}}\n",
+        )
+        .unwrap();
+        config.validate().unwrap();
+        assert_eq!(config.targets[1].when.as_ref().unwrap().env.as_deref(), Some(r#"CI != "true""#));
+    }
+
+    #[test]
+    fn test_target_keys_must_cover_placeholders() {
+        let yaml = |keys: &str| {
+            format!("vault: [.enc.env]\ntargets:\n  - {{name: app, path: a.env, placeholders: [$API_KEY, '${{db-url}}'], keys: {}}}\n", keys)
+        };
+
+        let config: Config = serde_yaml::from_str(&yaml("[API_KEY, DB_URL]")).unwrap();
+        config.validate().unwrap();
+
+        let config: Config = serde_yaml::from_str(&yaml("[API_KEY]")).unwrap();
+        let error = config.validate().unwrap_err().to_string();
+        assert!(error.contains("db-url"), "{}", error);
+    }
+
+    #[test]
+    fn test_leases_are_validated() {
+        let yaml = |lease: &str| {
+            format!("vault: [.enc.env]\ntargets:\n  - {{name: app, path: a.env, placeholders: [$DEPLOY_TOKEN]}}\nleases:\n  - {}\n", lease)
+        };
+
+        let config: Config =
+            serde_yaml::from_str(&yaml("{key: DEPLOY_TOKEN, ttl: 15m, revoke: [./revoke.sh, --now]}")).unwrap();
+        config.validate().unwrap();
+        assert_eq!(config.leases[0].ttl().unwrap(), std::time::Duration::from_secs(900));
+
+        for invalid in ["{key: DEPLOY_TOKEN, ttl: 15, revoke: [./revoke.sh]}", "{key: DEPLOY_TOKEN, ttl: 15m, revoke: []}"] {
+            let config: Config = serde_yaml::from_str(&yaml(invalid)).unwrap();
+            assert!(config.validate().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_path_variables_are_expanded_on_load() {
+        let project = tempfile::TempDir::new().unwrap();
+        let config_path = project.path().join("project.yaml");
+        std::fs::write(
+            &config_path,
+            "variables:\n  TOOLS: \"${PROJECT_DIR}/tools\"\nvault:\n  source: .enc.env\n  engine: sops\n  age_key_path: \"${HOME}/keys.txt\"\ntargets:\n  - {name: app, path: \"${TOOLS}/app.env\", placeholders: [$A]}\n",
+        )
+        .unwrap();
+
+        let config = Config::from_file(&config_path).unwrap();
+        let project_dir = project.path().canonicalize().unwrap();
+        assert_eq!(
+            config.targets[0].path,
+            format!("{}/tools/app.env", project_dir.display())
+        );
+        assert_eq!(
+            config.vault.age_key_path.unwrap(),
+            format!("{}/keys.txt", dirs::home_dir().unwrap().display())
+        );
+
+        let variables = BTreeMap::from([("A".to_string(), "x".to_string())]);
+        assert_eq!(expand_variables("${A}/${A}.env", &variables).unwrap(), "x/x.env");
+        assert!(expand_variables("${B}/app.env", &variables).is_err());
+        assert!(expand_variables("${A", &variables).is_err());
+
+        std::fs::write(
+            &config_path,
+            "variables: {HOME: /tmp}\nvault: [.enc.env]\ntargets: [{name: app, path: a.env, placeholders: [$A]}]\n",
+        )
+        .unwrap();
+        assert!(Config::from_file(&config_path).is_err());
+    }
+
+    #[test]
+    fn test_yaml_anchors_and_merge_keys() {
+        let project = tempfile::TempDir::new().unwrap();
+        let config_path = project.path().join("project.yaml");
+        std::fs::write(
+            &config_path,
+            "x-target: &target
+  strict: true
+  placeholders: &keys [$API_KEY, $DB_URL]
+vault: {source: .enc.env, engine: sops}
+targets:
+  - <<: *target
+    name: api
+    path: api.env
+  - <<: *target
+    name: web
+    path: web.env
+    strict: false
+  - {name: worker, path: worker.env, placeholders: *keys}
+",
+        )
+        .unwrap();
+
+        let config = Config::from_file(&config_path).unwrap();
+        let targets: Vec<(&str, bool, usize)> = config
+            .targets
+            .iter()
+            .map(|t| (t.name.as_str(), t.strict, t.placeholders.len()))
+            .collect();
+        assert_eq!(targets, vec![("api", true, 2), ("web", false, 2), ("worker", false, 2)]);
+    }
+
+    #[test]
+    fn test_local_config_overrides_shared_config() {
+        let project = tempfile::TempDir::new().unwrap();
+        let config_path = project.path().join("project.yaml");
+        std::fs::write(
+            &config_path,
+            "vault: {source: .enc.env, engine: sops}\ntargets:\n  - {name: app, path: .env, placeholders: [$A]}\n  - {name: web, path: web.env, placeholders: [$B]}\n",
+        )
+        .unwrap();
+
+        let shared = Config::from_file(&config_path).unwrap();
+        assert_eq!(shared.targets.len(), 2);
+
+        std::fs::write(
+            local_config_path(&config_path),
+            "vault: {vault_path: /mnt/drive/vault.enc.env}\ntargets:\n  - {name: app, path: .env.dev, placeholders: [$A]}\n  - {name: tool, path: tool.env, placeholders: [$C]}\n",
+        )
+        .unwrap();
+        let config = Config::from_file(&config_path).unwrap();
+        assert_eq!(config.vault.vault_path.as_deref(), Some("/mnt/drive/vault.enc.env"));
+        assert_eq!(config.vault.source, ".enc.env");
+        let targets: Vec<(&str, &str)> = config.targets.iter().map(|t| (t.name.as_str(), t.path.as_str())).collect();
+        assert_eq!(targets, vec![("app", ".env.dev"), ("web", "web.env"), ("tool", "tool.env")]);
+
+        std::fs::write(local_config_path(&config_path), "vault: {engine: aws-secretsmanager}\n").unwrap();
+        assert!(Config::from_file(&config_path).is_err());
+    }
+
+    #[test]
+    fn test_include_merges_targets_and_detects_cycles() {
+        let project = tempfile::TempDir::new().unwrap();
+        let dir = project.path();
+        std::fs::create_dir(dir.join("targets")).unwrap();
+        std::fs::write(
+            dir.join("project.yaml"),
+            "vault: [.enc.env]\ninclude: [targets/frontend.yaml]\ntargets:\n  - {name: app, path: .env, placeholders: [$A]}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("targets/frontend.yaml"),
+            "include: [backend.yaml]\ntargets:\n  - {name: web, path: web/.env, placeholders: [$B]}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("targets/backend.yaml"),
+            "targets:\n  - {name: api, path: api/.env, placeholders: [$C]}\n",
+        )
+        .unwrap();
+
+        let config = Config::from_file(dir.join("project.yaml")).unwrap();
+        let names: Vec<&str> = config.targets.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["app", "web", "api"]);
+        assert_eq!(config.targets[2].path, "api/.env");
+        assert_eq!(Config::included_files(dir.join("project.yaml")).unwrap().len(), 2);
+
+        std::fs::write(dir.join("targets/backend.yaml"), "include: [frontend.yaml]\n").unwrap();
+        let error = format!("{:#}", Config::from_file(dir.join("project.yaml")).unwrap_err());
+        assert!(error.contains("Include cycle"), "{}", error);
+
+        std::fs::write(dir.join("targets/backend.yaml"), "vault: [x]\n").unwrap();
+        let error = format!("{:#}", Config::from_file(dir.join("project.yaml")).unwrap_err());
+        assert!(error.contains("backend.yaml") && error.contains("included from"), "{}", error);
+    }
+
+    #[test]
+    fn test_timeouts() {
+        let defaults = TimeoutsConfig::default().resolve().unwrap();
+        assert_eq!(defaults, crate::process::Timeouts::DEFAULT);
+
+        let config: Config = serde_yaml::from_str(
+            "vault: {source: .enc.env, engine: sops}\ntargets: []\ntimeouts:\n  decrypt: 30s\n  cloud: none\n",
+        )
+        .unwrap();
+        let timeouts = config.timeouts.resolve().unwrap();
+        assert_eq!(timeouts.decrypt, Some(std::time::Duration::from_secs(30)));
+        assert_eq!(timeouts.inject, defaults.inject);
+        assert_eq!(timeouts.cloud, None);
+
+        let invalid = TimeoutsConfig { inject: Some("soon".to_string()), ..Default::default() };
+        assert!(invalid.resolve().is_err());
+    }
+
+    #[test]
+    fn test_session_timeout() {
+        assert_eq!(TimeoutsConfig::default().session().unwrap(), None);
+
+        let config: Config =
+            serde_yaml::from_str("vault: {source: .enc.env, engine: sops}\ntargets: []\ntimeouts:\n  session: 30m\n").unwrap();
+        assert_eq!(config.timeouts.session().unwrap(), Some(std::time::Duration::from_secs(30 * 60)));
+        assert!(config.timeouts.resolve().is_ok());
+
+        let none = TimeoutsConfig { session: Some("none".to_string()), ..Default::default() };
+        assert_eq!(none.session().unwrap(), None);
+        let invalid = TimeoutsConfig { session: Some("later".to_string()), ..Default::default() };
+        assert!(invalid.session().is_err());
+    }
 }
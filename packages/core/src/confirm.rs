@@ -0,0 +1,219 @@
+//! Yes/no confirmations behind a pluggable [`Confirmer`].
+//!
+//! Every confirmation Shadow Secret asks for (plan approval, pushing to a
+//! cloud provider, redacting files, setup questions) goes through
+//! [`confirm`]. By default it prompts on the terminal; GUI wrappers and
+//! agents can install their own [`Confirmer`] (a dialog, a policy engine)
+//! with [`set_confirmer`], and headless runs can set
+//! `SHADOW_SECRET_CONFIRM` (see [`init_from_env`]).
+
+use anyhow::Result;
+use std::cell::RefCell;
+use std::sync::{Arc, RwLock};
+
+/// Environment variable answering every confirmation without a TTY:
+/// `yes`, `no` or `default`.
+pub const CONFIRM_ENV: &str = "SHADOW_SECRET_CONFIRM";
+
+/// Answers yes/no questions.
+pub trait Confirmer: Send + Sync {
+    /// Answer `prompt`; `default` is the answer a user gets by pressing Enter.
+    fn confirm(&self, prompt: &str, default: bool) -> Result<bool>;
+}
+
+/// Closures work as confirmers: `Arc::new(|prompt: &str, default: bool| Ok(default))`.
+impl<F> Confirmer for F
+where
+    F: Fn(&str, bool) -> Result<bool> + Send + Sync,
+{
+    fn confirm(&self, prompt: &str, default: bool) -> Result<bool> {
+        self(prompt, default)
+    }
+}
+
+/// Prompt on the terminal (the default; needs a TTY).
+///
+/// In accessible mode (see [`crate::accessible`]) the question is a plain
+/// line of text answered by typing yes or no, which screen readers announce
+/// in full.
+pub struct TerminalConfirmer;
+
+impl Confirmer for TerminalConfirmer {
+    fn confirm(&self, prompt: &str, default: bool) -> Result<bool> {
+        if crate::accessible::is_enabled() {
+            return confirm_plain(prompt, default);
+        }
+        Ok(dialoguer::Confirm::with_theme(&dialoguer::theme::ColorfulTheme::default())
+            .with_prompt(prompt)
+            .default(default)
+            .interact()?)
+    }
+}
+
+fn confirm_plain(prompt: &str, default: bool) -> Result<bool> {
+    let default_answer = if default { "yes" } else { "no" };
+    loop {
+        eprintln!(
+            "PROMPT: {} Type yes or no, then press Enter (default: {}).",
+            crate::accessible::plain(prompt).trim(),
+            default_answer
+        );
+        let mut input = String::new();
+        if std::io::stdin().read_line(&mut input)? == 0 {
+            anyhow::bail!("No answer to: {}", prompt);
+        }
+        match parse_answer(&input) {
+            Some(answer) => return Ok(answer.unwrap_or(default)),
+            None => eprintln!("Please type yes or no."),
+        }
+    }
+}
+
+/// A typed answer: `Some(None)` for the default (empty line), `None` if
+/// not understood.
+fn parse_answer(input: &str) -> Option<Option<bool>> {
+    match input.trim().to_ascii_lowercase().as_str() {
+        "" => Some(None),
+        "yes" | "y" => Some(Some(true)),
+        "no" | "n" => Some(Some(false)),
+        _ => None,
+    }
+}
+
+/// Give the same answer to every question, without prompting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FixedAnswer {
+    Yes,
+    No,
+    /// The question's default answer
+    Default,
+}
+
+impl FixedAnswer {
+    /// Parse a [`CONFIRM_ENV`] value.
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "yes" | "y" | "true" | "1" => Ok(Self::Yes),
+            "no" | "n" | "false" | "0" => Ok(Self::No),
+            "default" => Ok(Self::Default),
+            other => anyhow::bail!(
+                "Invalid ${} value '{}' (expected yes, no or default)",
+                CONFIRM_ENV,
+                other
+            ),
+        }
+    }
+}
+
+impl Confirmer for FixedAnswer {
+    fn confirm(&self, prompt: &str, default: bool) -> Result<bool> {
+        let answer = match self {
+            Self::Yes => true,
+            Self::No => false,
+            Self::Default => default,
+        };
+        println!("{} {}", prompt, if answer { "yes" } else { "no" });
+        Ok(answer)
+    }
+}
+
+static GLOBAL_CONFIRMER: RwLock<Option<Arc<dyn Confirmer>>> = RwLock::new(None);
+
+thread_local! {
+    static THREAD_CONFIRMER: RefCell<Option<Arc<dyn Confirmer>>> = const { RefCell::new(None) };
+}
+
+/// Use `confirmer` for every confirmation of the process (until
+/// [`reset_confirmer`]).
+pub fn set_confirmer(confirmer: Arc<dyn Confirmer>) {
+    *GLOBAL_CONFIRMER.write().unwrap_or_else(|e| e.into_inner()) = Some(confirmer);
+}
+
+/// Go back to prompting on the terminal.
+pub fn reset_confirmer() {
+    *GLOBAL_CONFIRMER.write().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+/// Run `f` with `confirmer` answering the confirmations of the current
+/// thread, leaving other threads alone. Meant for unit tests.
+pub fn with_confirmer<T>(confirmer: Arc<dyn Confirmer>, f: impl FnOnce() -> T) -> T {
+    // Restores the previous confirmer, even if `f` panics
+    struct Restore(Option<Arc<dyn Confirmer>>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let previous = self.0.take();
+            THREAD_CONFIRMER.with(|local| *local.borrow_mut() = previous);
+        }
+    }
+
+    let _restore = Restore(THREAD_CONFIRMER.with(|local| local.replace(Some(confirmer))));
+    f()
+}
+
+/// The confirmer in effect on this thread.
+pub fn confirmer() -> Arc<dyn Confirmer> {
+    if let Some(confirmer) = THREAD_CONFIRMER.with(|local| local.borrow().clone()) {
+        return confirmer;
+    }
+    GLOBAL_CONFIRMER
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+        .unwrap_or_else(|| Arc::new(TerminalConfirmer))
+}
+
+/// Ask a yes/no question through the current [`Confirmer`].
+pub fn confirm(prompt: &str, default: bool) -> Result<bool> {
+    confirmer().confirm(prompt, default)
+}
+
+/// Install a [`FixedAnswer`] when `SHADOW_SECRET_CONFIRM` is set.
+pub fn init_from_env() -> Result<()> {
+    if let Ok(value) = std::env::var(CONFIRM_ENV) {
+        set_confirmer(Arc::new(FixedAnswer::parse(&value)?));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_with_confirmer_answers_and_restores() {
+        let asked = Arc::new(Mutex::new(Vec::new()));
+        let log = asked.clone();
+        let policy = move |prompt: &str, _default: bool| {
+            log.lock().unwrap().push(prompt.to_string());
+            Ok(!prompt.contains("production"))
+        };
+
+        let answers = with_confirmer(Arc::new(policy), || {
+            (
+                confirm("Push to preview?", false).unwrap(),
+                confirm("Push to production?", true).unwrap(),
+            )
+        });
+
+        assert_eq!(answers, (true, false));
+        assert_eq!(asked.lock().unwrap().len(), 2);
+        assert!(THREAD_CONFIRMER.with(|local| local.borrow().is_none()));
+    }
+
+    #[test]
+    fn test_fixed_answer() {
+        assert_eq!(FixedAnswer::parse("YES").unwrap(), FixedAnswer::Yes);
+        assert!(FixedAnswer::parse("maybe").is_err());
+        assert!(FixedAnswer::Default.confirm("Continue?", true).unwrap());
+        assert!(!FixedAnswer::No.confirm("Continue?", true).unwrap());
+    }
+
+    #[test]
+    fn test_parse_typed_answer() {
+        assert_eq!(parse_answer("Yes\n"), Some(Some(true)));
+        assert_eq!(parse_answer(" n "), Some(Some(false)));
+        assert_eq!(parse_answer("\n"), Some(None));
+        assert_eq!(parse_answer("sure"), None);
+    }
+}
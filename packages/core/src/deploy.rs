@@ -0,0 +1,125 @@
+//! One-shot deploys (`shadow-secret deploy --run "pnpm build && vercel deploy"`).
+//!
+//! `deploy` chains what would otherwise be four commands: unlock the
+//! project's targets, run the build command through the shell, push the
+//! vault to Vercel (`push-cloud`), then lock. Lock always runs once
+//! anything was injected, whichever step failed; a step after a failure is
+//! skipped. Ctrl+C during the build is forwarded to the build, so the
+//! templates are still restored. [`DeployReport`] records each step for the
+//! summary printed at the end.
+
+use std::time::{Duration, Instant};
+
+/// Steps of a deploy, in order.
+pub const STEPS: [&str; 4] = ["unlock", "build", "push-cloud", "lock"];
+
+/// What happened to one step.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StepOutcome {
+    Succeeded,
+    /// The step ran and failed (error message)
+    Failed(String),
+    /// The step did not run (disabled, or an earlier step failed)
+    Skipped,
+}
+
+/// One step of the summary.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeployStep {
+    pub name: String,
+    pub outcome: StepOutcome,
+    pub duration: Duration,
+}
+
+/// Summary of a deploy.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct DeployReport {
+    pub steps: Vec<DeployStep>,
+}
+
+impl DeployReport {
+    /// Run the step `name` and record its outcome.
+    ///
+    /// # Returns
+    ///
+    /// The step's value, or `None` if it failed.
+    pub fn run<T>(&mut self, name: &str, step: impl FnOnce() -> anyhow::Result<T>) -> Option<T> {
+        let started = Instant::now();
+        let result = step();
+        let outcome = match &result {
+            Ok(_) => StepOutcome::Succeeded,
+            Err(e) => StepOutcome::Failed(format!("{:#}", e)),
+        };
+        self.steps.push(DeployStep {
+            name: name.to_string(),
+            outcome,
+            duration: started.elapsed(),
+        });
+        result.ok()
+    }
+
+    /// Record that the step `name` did not run.
+    pub fn skip(&mut self, name: &str) {
+        self.steps.push(DeployStep {
+            name: name.to_string(),
+            outcome: StepOutcome::Skipped,
+            duration: Duration::ZERO,
+        });
+    }
+
+    /// Whether no step failed.
+    pub fn is_success(&self) -> bool {
+        !self.steps.iter().any(|step| matches!(step.outcome, StepOutcome::Failed(_)))
+    }
+
+    /// The summary, one line per step.
+    pub fn render(&self) -> String {
+        let mut summary = String::new();
+        for step in &self.steps {
+            let line = match &step.outcome {
+                StepOutcome::Succeeded => format!("   ✓ {:<10} {:.1}s", step.name, step.duration.as_secs_f64()),
+                StepOutcome::Failed(error) => format!("   ✗ {:<10} {}", step.name, error),
+                StepOutcome::Skipped => format!("   ⊘ {:<10} skipped", step.name),
+            };
+            summary.push_str(&line);
+            summary.push('\n');
+        }
+        summary
+    }
+}
+
+/// Program and arguments running `script` through the platform shell.
+pub fn shell_command(script: &str) -> (String, Vec<String>) {
+    if cfg!(windows) {
+        ("cmd".to_string(), vec!["/C".to_string(), script.to_string()])
+    } else {
+        ("sh".to_string(), vec!["-c".to_string(), script.to_string()])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_records_steps() {
+        let mut report = DeployReport::default();
+        assert_eq!(report.run("unlock", || Ok(42)), Some(42));
+        assert_eq!(report.run("build", || -> anyhow::Result<()> { anyhow::bail!("exit code 2") }), None);
+        report.skip("push-cloud");
+        report.run("lock", || Ok(()));
+
+        assert!(!report.is_success());
+        let summary = report.render();
+        assert!(summary.contains("   ✗ build      exit code 2\n"), "{}", summary);
+        assert!(summary.contains("   ⊘ push-cloud skipped\n"), "{}", summary);
+        assert_eq!(summary.lines().count(), STEPS.len());
+    }
+
+    #[test]
+    fn test_shell_command() {
+        let (program, args) = shell_command("pnpm build && vercel deploy");
+        assert_eq!(args.last().unwrap(), "pnpm build && vercel deploy");
+        assert!(program == "sh" || program == "cmd");
+    }
+}
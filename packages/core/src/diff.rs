@@ -0,0 +1,104 @@
+//! Line diffs for plans and previews (`init --plan`, `unlock --dry-run`).
+
+/// Lines of `old` and `new` tagged `' '` (kept), `'-'` (removed) or `'+'`
+/// (added), following their longest common subsequence.
+fn diff_ops<'a>(old: &'a str, new: &'a str) -> Vec<(char, &'a str)> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // common[i][j]: length of the LCS of old[i..] and new[j..]
+    let mut common = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut ops = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            ops.push((' ', old[i]));
+            i += 1;
+            j += 1;
+        } else if j < new.len() && (i == old.len() || common[i][j + 1] >= common[i + 1][j]) {
+            ops.push(('+', new[j]));
+            j += 1;
+        } else {
+            ops.push(('-', old[i]));
+            i += 1;
+        }
+    }
+    ops
+}
+
+/// Line diff of `old` and `new`: every line prefixed with `-` (removed),
+/// `+` (added) or a space (kept), following their longest common
+/// subsequence.
+pub fn line_diff(old: &str, new: &str) -> Vec<String> {
+    diff_ops(old, new)
+        .into_iter()
+        .map(|(tag, line)| format!("{} {}", tag, line))
+        .collect()
+}
+
+/// Unified diff of `old` and `new` (`@@ -1,3 +1,3 @@` hunks), with
+/// `context` unchanged lines around each change. Empty if they have the
+/// same lines.
+pub fn unified_diff(old: &str, new: &str, context: usize) -> Vec<String> {
+    let mut ops = diff_ops(old, new);
+    // Removed lines come before the lines replacing them
+    for run in ops.chunk_by_mut(|a, b| (a.0 == ' ') == (b.0 == ' ')) {
+        run.sort_by_key(|(tag, _)| *tag == '+');
+    }
+    let changed: Vec<usize> = (0..ops.len()).filter(|&i| ops[i].0 != ' ').collect();
+
+    let mut diff = Vec::new();
+    let mut next = 0;
+    while next < changed.len() {
+        // Changes closer than twice the context share a hunk
+        let mut last = next;
+        while last + 1 < changed.len() && changed[last + 1] - changed[last] - 1 <= 2 * context {
+            last += 1;
+        }
+        let start = changed[next].saturating_sub(context);
+        let end = (changed[last] + context + 1).min(ops.len());
+
+        let count = |range: &[(char, &str)], tag: char| range.iter().filter(|(t, _)| *t == ' ' || *t == tag).count();
+        let (old_start, new_start) = (count(&ops[..start], '-'), count(&ops[..start], '+'));
+        let (old_count, new_count) = (count(&ops[start..end], '-'), count(&ops[start..end], '+'));
+        diff.push(format!(
+            "@@ -{},{} +{},{} @@",
+            old_start + usize::from(old_count > 0),
+            old_count,
+            new_start + usize::from(new_count > 0),
+            new_count
+        ));
+        diff.extend(ops[start..end].iter().map(|(tag, line)| format!("{}{}", tag, line)));
+        next = last + 1;
+    }
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unified_diff() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\n";
+        let new = "a\nB\nc\nd\ne\nf\ng\nh\nI\n";
+        assert_eq!(
+            unified_diff(old, new, 1),
+            vec!["@@ -1,3 +1,3 @@", " a", "-b", "+B", " c", "@@ -8,2 +8,2 @@", " h", "-i", "+I"]
+        );
+        // Close changes share a hunk
+        assert_eq!(unified_diff(old, new, 3).iter().filter(|line| line.starts_with("@@")).count(), 1);
+        assert_eq!(unified_diff("", "x\n", 3), vec!["@@ -0,0 +1,1 @@", "+x"]);
+        assert!(unified_diff(old, old, 3).is_empty());
+    }
+}
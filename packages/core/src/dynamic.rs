@@ -0,0 +1,257 @@
+//! Dynamic secrets: short-lived cloud credentials minted at unlock time.
+//!
+//! Entries of `dynamic:` are not read from the vault but generated when a
+//! session unlocks, then merged over the vault like a local overlay:
+//!
+//! - `aws-sts` assumes `role_arn` with the AWS CLI (`aws sts assume-role`)
+//!   and yields `<prefix>AWS_ACCESS_KEY_ID`, `<prefix>AWS_SECRET_ACCESS_KEY`
+//!   and `<prefix>AWS_SESSION_TOKEN`
+//! - `gcp-access-token` yields an OAuth access token in `key` (default
+//!   `GOOGLE_OAUTH_ACCESS_TOKEN`): the Application Default Credentials'
+//!   own token, or one for `service_account` generated through the IAM
+//!   Credentials API (impersonation)
+//!
+//! Targets then hold credentials that expire on their own after `duration`
+//! instead of long-lived static keys, so nothing needs revoking on lock.
+
+use crate::config::DynamicSecretConfig;
+use crate::process;
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Provider assuming an AWS role through STS.
+pub const PROVIDER_AWS_STS: &str = "aws-sts";
+
+/// Provider generating a GCP OAuth access token.
+pub const PROVIDER_GCP_ACCESS_TOKEN: &str = "gcp-access-token";
+
+/// Lifetime of the credentials when `duration` is not set.
+pub const DEFAULT_DURATION: Duration = Duration::from_secs(60 * 60);
+
+/// Session name of the assumed role when `session_name` is not set.
+const DEFAULT_SESSION_NAME: &str = "shadow-secret";
+
+/// Key receiving the GCP access token when `key` is not set.
+const DEFAULT_GCP_KEY: &str = "GOOGLE_OAUTH_ACCESS_TOKEN";
+
+/// IAM Credentials API endpoint (service account impersonation).
+const IAM_CREDENTIALS_ENDPOINT: &str = "https://iamcredentials.googleapis.com";
+
+/// OAuth scope of the generated GCP tokens.
+const GCP_SCOPE: &str = "https://www.googleapis.com/auth/cloud-platform";
+
+/// Name of the source in provenance output (`aws-sts:<role>`).
+pub fn source_name(config: &DynamicSecretConfig) -> String {
+    let target = match config.provider.as_str() {
+        PROVIDER_AWS_STS => config.role_arn.as_deref().unwrap_or_default(),
+        _ => config.service_account.as_deref().unwrap_or("application-default"),
+    };
+    format!("{}:{}", config.provider, target)
+}
+
+/// Check that `config` can mint credentials (known provider, required
+/// fields, duration accepted by the provider).
+pub fn validate(config: &DynamicSecretConfig) -> Result<()> {
+    let duration = config.duration()?;
+    match config.provider.as_str() {
+        PROVIDER_AWS_STS => {
+            if config.role_arn.as_deref().is_none_or(str::is_empty) {
+                anyhow::bail!("Dynamic secret '{}' needs a role_arn", PROVIDER_AWS_STS);
+            }
+            if !(15 * 60..=12 * 60 * 60).contains(&duration.as_secs()) {
+                anyhow::bail!("AWS STS credentials last between 15m and 12h, got '{}'", config.duration.as_deref().unwrap_or_default());
+            }
+        }
+        PROVIDER_GCP_ACCESS_TOKEN => {
+            if duration > DEFAULT_DURATION {
+                anyhow::bail!("GCP access tokens last at most 1h, got '{}'", config.duration.as_deref().unwrap_or_default());
+            }
+        }
+        other => anyhow::bail!(
+            "Unknown dynamic secret provider '{}' (expected {} or {})",
+            other,
+            PROVIDER_AWS_STS,
+            PROVIDER_GCP_ACCESS_TOKEN
+        ),
+    }
+    Ok(())
+}
+
+/// Mint the credentials of `config`.
+///
+/// # Returns
+///
+/// The generated keys and their values.
+pub fn mint(config: &DynamicSecretConfig) -> Result<HashMap<String, String>> {
+    validate(config)?;
+    match config.provider.as_str() {
+        PROVIDER_AWS_STS => assume_role(config),
+        _ => gcp_access_token(config),
+    }
+}
+
+/// Arguments of the `aws` invocation assuming the role.
+pub fn assume_role_args(config: &DynamicSecretConfig) -> Result<Vec<String>> {
+    let mut args = vec![
+        "sts".to_string(),
+        "assume-role".to_string(),
+        "--role-arn".to_string(),
+        config.role_arn.clone().unwrap_or_default(),
+        "--role-session-name".to_string(),
+        config.session_name.clone().unwrap_or_else(|| DEFAULT_SESSION_NAME.to_string()),
+        "--duration-seconds".to_string(),
+        config.duration()?.as_secs().to_string(),
+        "--output".to_string(),
+        "json".to_string(),
+    ];
+    if let Some(region) = &config.region {
+        args.extend(["--region".to_string(), region.clone()]);
+    }
+    if let Some(profile) = &config.profile {
+        args.extend(["--profile".to_string(), profile.clone()]);
+    }
+    Ok(args)
+}
+
+fn assume_role(config: &DynamicSecretConfig) -> Result<HashMap<String, String>> {
+    let mut command = process::command("aws")?;
+    command.args(assume_role_args(config)?);
+    let output = process::output_within(&mut command, process::timeouts().cloud).map_err(|e| {
+        if process::is_interrupted(&e) {
+            return e.into();
+        }
+        anyhow::anyhow!(
+            "AWS CLI is not installed or not in PATH: {}. Install it: https://aws.amazon.com/cli/",
+            e
+        )
+    })?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "Failed to assume {}: {}",
+            source_name(config),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    parse_assume_role(&output.stdout, &config.prefix)
+}
+
+/// Keys of an `assume-role` response.
+pub fn parse_assume_role(response: &[u8], prefix: &str) -> Result<HashMap<String, String>> {
+    let response: serde_json::Value =
+        serde_json::from_slice(response).context("Failed to parse AWS STS response")?;
+
+    let mut secrets = HashMap::new();
+    for (field, key) in [
+        ("AccessKeyId", "AWS_ACCESS_KEY_ID"),
+        ("SecretAccessKey", "AWS_SECRET_ACCESS_KEY"),
+        ("SessionToken", "AWS_SESSION_TOKEN"),
+    ] {
+        let value = response
+            .pointer(&format!("/Credentials/{}", field))
+            .and_then(|value| value.as_str())
+            .with_context(|| format!("AWS STS response has no Credentials.{}", field))?;
+        secrets.insert(format!("{}{}", prefix, key), value.to_string());
+    }
+    Ok(secrets)
+}
+
+/// URL of the `generateAccessToken` call for `service_account`.
+pub fn generate_access_token_url(service_account: &str) -> String {
+    format!(
+        "{}/v1/projects/-/serviceAccounts/{}:generateAccessToken",
+        IAM_CREDENTIALS_ENDPOINT, service_account
+    )
+}
+
+fn gcp_access_token(config: &DynamicSecretConfig) -> Result<HashMap<String, String>> {
+    let token = crate::gcp::access_token()?;
+    let key = config.key.clone().unwrap_or_else(|| DEFAULT_GCP_KEY.to_string());
+
+    let Some(service_account) = &config.service_account else {
+        return Ok(HashMap::from([(key, token.token)]));
+    };
+
+    let body = serde_json::json!({
+        "scope": [GCP_SCOPE],
+        "lifetime": format!("{}s", config.duration()?.as_secs()),
+    });
+    let response = ureq::post(&generate_access_token_url(service_account))
+        .set("Authorization", &format!("Bearer {}", token.token))
+        .send_json(body)
+        .map_err(|e| match e {
+            ureq::Error::Status(403, _) => anyhow::anyhow!(
+                "GCP denied impersonating {} (needs roles/iam.serviceAccountTokenCreator)",
+                service_account
+            ),
+            other => anyhow::anyhow!("GCP generateAccessToken request failed: {}", other),
+        })?;
+    let body: serde_json::Value = response
+        .into_json()
+        .context("Failed to parse GCP generateAccessToken response")?;
+    let access_token = body
+        .get("accessToken")
+        .and_then(|token| token.as_str())
+        .context("GCP generateAccessToken response has no accessToken")?;
+
+    Ok(HashMap::from([(key, access_token.to_string())]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockRunner;
+    use std::sync::Arc;
+
+    fn aws(duration: Option<&str>) -> DynamicSecretConfig {
+        DynamicSecretConfig {
+            provider: PROVIDER_AWS_STS.to_string(),
+            role_arn: Some("arn:aws:iam::123456789012:role/deploy".to_string()),
+            duration: duration.map(str::to_string),
+            region: Some("eu-west-3".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        validate(&aws(None)).unwrap();
+        assert!(validate(&aws(Some("5m"))).is_err());
+        assert!(validate(&aws(Some("13h"))).is_err());
+        assert!(validate(&DynamicSecretConfig { role_arn: None, ..aws(None) }).is_err());
+
+        let gcp = DynamicSecretConfig {
+            provider: PROVIDER_GCP_ACCESS_TOKEN.to_string(),
+            duration: Some("2h".to_string()),
+            ..Default::default()
+        };
+        assert!(validate(&gcp).is_err());
+        assert_eq!(source_name(&gcp), "gcp-access-token:application-default");
+        assert!(validate(&DynamicSecretConfig { provider: "azure".to_string(), ..Default::default() }).is_err());
+    }
+
+    #[test]
+    fn test_assume_role() {
+        let config = DynamicSecretConfig {
+            prefix: "PROD_".to_string(),
+            ..aws(Some("30m"))
+        };
+        let args = assume_role_args(&config).unwrap();
+        assert_eq!(
+            args.join(" "),
+            "sts assume-role --role-arn arn:aws:iam::123456789012:role/deploy --role-session-name shadow-secret \
+             --duration-seconds 1800 --output json --region eu-west-3"
+        );
+
+        let response = r#"{"Credentials":{"AccessKeyId":"ASIA123","SecretAccessKey":"secret","SessionToken":"token","Expiration":"2026-01-01T01:00:00Z"}}"#;
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        let runner = Arc::new(MockRunner::new().respond("aws", &args, 0, response));
+        let secrets = process::with_runner(runner, || mint(&config)).unwrap();
+        assert_eq!(secrets.len(), 3);
+        assert_eq!(secrets["PROD_AWS_ACCESS_KEY_ID"], "ASIA123");
+        assert_eq!(secrets["PROD_AWS_SESSION_TOKEN"], "token");
+
+        assert!(parse_assume_role(br#"{"Credentials":{}}"#, "").is_err());
+    }
+}
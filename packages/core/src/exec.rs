@@ -0,0 +1,188 @@
+//! Running a command with secrets in its environment.
+//!
+//! `shadow-secret exec -- npm run dev` decrypts the vault and starts the
+//! command with every secret as an environment variable. Nothing is written
+//! to disk, so there is nothing to restore when the child exits.
+//!
+//! Shadow Secret stays in front of the child until it exits: signals sent to
+//! it are forwarded to the child, and its exit status becomes the child's
+//! (`128 + N` when the child is killed by signal N, as in shells). On Linux
+//! the child is also sent SIGTERM if Shadow Secret itself dies.
+
+use crate::process;
+use crate::vault::Vault;
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::process::ExitStatus;
+
+/// Signals forwarded to the child (Unix).
+#[cfg(unix)]
+pub const FORWARDED_SIGNALS: &[libc::c_int] = &[
+    libc::SIGINT,
+    libc::SIGTERM,
+    libc::SIGHUP,
+    libc::SIGQUIT,
+    libc::SIGUSR1,
+    libc::SIGUSR2,
+];
+
+/// Environment variables to give the child.
+///
+/// All secrets when `only` is empty; otherwise exactly the keys listed in
+/// `only`, each of which must exist in the vault.
+pub fn child_environment(vault: &Vault, only: &[String]) -> Result<HashMap<String, String>> {
+    if only.is_empty() {
+        return Ok(vault.all().clone());
+    }
+
+    only.iter()
+        .map(|key| match vault.get(key) {
+            Some(value) => Ok((key.clone(), value.clone())),
+            None => anyhow::bail!("Secret '{}' not found in vault", key),
+        })
+        .collect()
+}
+
+/// Exit code to report for the child's status.
+pub fn exit_code(status: ExitStatus) -> i32 {
+    if let Some(code) = status.code() {
+        return code;
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            return 128 + signal;
+        }
+    }
+
+    1
+}
+
+/// Run `program` with `env` added to its environment and wait for it.
+///
+/// stdin, stdout and stderr are inherited. Signals received meanwhile are
+/// forwarded to the child (see [`FORWARDED_SIGNALS`]).
+pub async fn run(program: &str, args: &[String], env: &HashMap<String, String>) -> Result<ExitStatus> {
+    let mut command = process::command(program)?;
+    command.args(args).envs(env);
+
+    // Never leave the child running with secrets if we are killed
+    #[cfg(target_os = "linux")]
+    unsafe {
+        use std::os::unix::process::CommandExt;
+        command.pre_exec(|| {
+            libc::prctl(libc::PR_SET_PDEATHSIG, libc::SIGTERM);
+            Ok(())
+        });
+    }
+
+    let mut child = tokio::process::Command::from(command)
+        .spawn()
+        .with_context(|| format!("Failed to run '{}'", program))?;
+
+    wait_forwarding_signals(&mut child).await
+}
+
+#[cfg(unix)]
+async fn wait_forwarding_signals(child: &mut tokio::process::Child) -> Result<ExitStatus> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let pid = child.id().context("Child exited before it could be watched")? as libc::pid_t;
+
+    let mut forwarders = Vec::new();
+    for &signo in FORWARDED_SIGNALS {
+        let mut stream = signal(SignalKind::from_raw(signo))
+            .with_context(|| format!("Failed to listen for signal {}", signo))?;
+        forwarders.push(tokio::spawn(async move {
+            while stream.recv().await.is_some() {
+                forward_signal(pid, signo);
+            }
+        }));
+    }
+
+    let status = child.wait().await.context("Failed to wait for child");
+
+    for forwarder in forwarders {
+        forwarder.abort();
+    }
+    status
+}
+
+/// Send `signo` to the child, unless the terminal already did.
+///
+/// Ctrl+C and Ctrl+\ go to the whole foreground process group, which
+/// includes the child: forwarding them again would deliver them twice.
+#[cfg(unix)]
+fn forward_signal(pid: libc::pid_t, signo: libc::c_int) {
+    let from_terminal = (signo == libc::SIGINT || signo == libc::SIGQUIT)
+        && unsafe { libc::tcgetpgrp(libc::STDIN_FILENO) == libc::getpgrp() };
+
+    if !from_terminal {
+        unsafe {
+            libc::kill(pid, signo);
+        }
+    }
+}
+
+/// Ctrl+C reaches every process attached to the console, so the parent only
+/// has to survive it and keep waiting.
+#[cfg(not(unix))]
+async fn wait_forwarding_signals(child: &mut tokio::process::Child) -> Result<ExitStatus> {
+    loop {
+        tokio::select! {
+            status = child.wait() => return status.context("Failed to wait for child"),
+            _ = tokio::signal::ctrl_c() => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vault() -> Vault {
+        Vault::new(HashMap::from([
+            ("API_KEY".to_string(), "sk_test_123".to_string()),
+            ("DB_URL".to_string(), "postgres://localhost".to_string()),
+        ]))
+    }
+
+    #[test]
+    fn test_child_environment_selects_keys() {
+        assert_eq!(child_environment(&vault(), &[]).unwrap().len(), 2);
+
+        let env = child_environment(&vault(), &["API_KEY".to_string()]).unwrap();
+        assert_eq!(
+            env,
+            HashMap::from([("API_KEY".to_string(), "sk_test_123".to_string())])
+        );
+
+        assert!(child_environment(&vault(), &["MISSING".to_string()]).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_passes_environment_and_status() {
+        let env = child_environment(&vault(), &[]).unwrap();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+
+        let status = runtime
+            .block_on(run(
+                "sh",
+                &[
+                    "-c".to_string(),
+                    r#"[ "$API_KEY" = sk_test_123 ] && exit 7"#.to_string(),
+                ],
+                &env,
+            ))
+            .unwrap();
+        assert_eq!(exit_code(status), 7);
+
+        let status = runtime
+            .block_on(run("sh", &["-c".to_string(), "kill -TERM $$".to_string()], &env))
+            .unwrap();
+        assert_eq!(exit_code(status), 128 + libc::SIGTERM);
+    }
+}
@@ -0,0 +1,727 @@
+//! `--explain` execution plans.
+//!
+//! Before a command runs, Shadow Secret can describe exactly which external
+//! programs it will execute and which files it will read or write, so a
+//! security reviewer can see what the tool does on their machine.
+//!
+//! Plans are reproducible: they are built from the configuration alone
+//! (nothing is decrypted), listed in execution order, and never contain
+//! secret values — values sent over stdin are shown as `<elided>`.
+
+use crate::config::Config;
+use crate::init::InitConfig;
+use anyhow::Result;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+/// Placeholder printed instead of secret material.
+pub const ELIDED: &str = "<elided>";
+
+/// An external command the tool intends to run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlannedCommand {
+    program: String,
+    args: Vec<String>,
+    env: Vec<(String, String)>,
+    cwd: Option<PathBuf>,
+    stdin: Option<String>,
+    note: Option<String>,
+}
+
+impl PlannedCommand {
+    pub fn new(program: &str) -> Self {
+        Self {
+            program: program.to_string(),
+            args: Vec::new(),
+            env: Vec::new(),
+            cwd: None,
+            stdin: None,
+            note: None,
+        }
+    }
+
+    pub fn arg<S: AsRef<str>>(mut self, arg: S) -> Self {
+        self.args.push(arg.as_ref().to_string());
+        self
+    }
+
+    pub fn env(mut self, key: &str, value: &str) -> Self {
+        self.env.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn current_dir(mut self, dir: &Path) -> Self {
+        self.cwd = Some(dir.to_path_buf());
+        self
+    }
+
+    /// Mark that a secret value is written to the command's stdin.
+    pub fn secret_stdin(mut self) -> Self {
+        self.stdin = Some(ELIDED.to_string());
+        self
+    }
+
+    pub fn note(mut self, note: &str) -> Self {
+        self.note = Some(note.to_string());
+        self
+    }
+
+    /// Render as a copy-pasteable shell command line.
+    pub fn command_line(&self) -> String {
+        let mut parts: Vec<String> = self
+            .env
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, shell_quote(value)))
+            .collect();
+        parts.push(shell_quote(&self.program));
+        parts.extend(self.args.iter().map(|arg| shell_quote(arg)));
+
+        let mut line = parts.join(" ");
+        if let Some(stdin) = &self.stdin {
+            let _ = write!(line, " <<< {}", stdin);
+        }
+        line
+    }
+}
+
+/// How a file is touched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileAccess {
+    Read,
+    Create,
+    /// Overwritten with secrets, restored from an in-memory backup on exit
+    Inject,
+    Modify,
+}
+
+impl FileAccess {
+    fn label(self) -> &'static str {
+        match self {
+            FileAccess::Read => "read",
+            FileAccess::Create => "create",
+            FileAccess::Inject => "inject",
+            FileAccess::Modify => "modify",
+        }
+    }
+}
+
+/// Everything a command intends to do, in order.
+#[derive(Debug, Clone, Default)]
+pub struct Plan {
+    title: String,
+    commands: Vec<PlannedCommand>,
+    files: Vec<(FileAccess, PathBuf)>,
+    notes: Vec<String>,
+}
+
+impl Plan {
+    pub fn new(title: &str) -> Self {
+        Self {
+            title: title.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn command(&mut self, command: PlannedCommand) -> &mut Self {
+        self.commands.push(command);
+        self
+    }
+
+    pub fn file<P: Into<PathBuf>>(&mut self, access: FileAccess, path: P) -> &mut Self {
+        self.files.push((access, path.into()));
+        self
+    }
+
+    pub fn note(&mut self, note: &str) -> &mut Self {
+        self.notes.push(note.to_string());
+        self
+    }
+
+    pub fn commands(&self) -> &[PlannedCommand] {
+        &self.commands
+    }
+
+    pub fn files(&self) -> &[(FileAccess, PathBuf)] {
+        &self.files
+    }
+
+    /// Human-readable plan.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "📋 Execution plan: {}", self.title);
+
+        let _ = writeln!(out, "\nExternal commands:");
+        if self.commands.is_empty() {
+            let _ = writeln!(out, "  (none)");
+        }
+        for (i, command) in self.commands.iter().enumerate() {
+            let _ = writeln!(out, "  {}. {}", i + 1, command.command_line());
+            if let Some(cwd) = &command.cwd {
+                let _ = writeln!(out, "     cwd: {}", cwd.display());
+            }
+            if let Some(note) = &command.note {
+                let _ = writeln!(out, "     {}", note);
+            }
+        }
+
+        let _ = writeln!(out, "\nFiles:");
+        if self.files.is_empty() {
+            let _ = writeln!(out, "  (none)");
+        }
+        for (access, path) in &self.files {
+            let _ = writeln!(out, "  [{:<6}] {}", access.label(), path.display());
+        }
+
+        if !self.notes.is_empty() {
+            let _ = writeln!(out, "\nNotes:");
+            for note in &self.notes {
+                let _ = writeln!(out, "  - {}", note);
+            }
+        }
+
+        out
+    }
+
+    /// Print the plan and ask for confirmation.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the user approved the plan.
+    pub fn confirm(&self) -> Result<bool> {
+        println!("{}", self.render());
+
+        crate::confirm::confirm("Execute this plan?", false)
+    }
+}
+
+/// Quote a word for POSIX shells when needed.
+pub(crate) fn shell_quote(word: &str) -> String {
+    let safe = !word.is_empty()
+        && word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_-./:=@+,%".contains(c));
+
+    if safe {
+        word.to_string()
+    } else {
+        format!("'{}'", word.replace('\'', r"'\''"))
+    }
+}
+
+/// sops invocations used to decrypt the vault(s).
+fn sops_decrypt_commands(plan: &mut Plan, config: &Config, config_dir: &Path) -> Result<()> {
+    if let Some(hashicorp) = config
+        .vault
+        .hashicorp
+        .as_ref()
+        .filter(|_| config.vault.engine == crate::vault::ENGINE_HASHICORP)
+    {
+        let address = crate::hashicorp::address(hashicorp).unwrap_or_else(|_| "$VAULT_ADDR".to_string());
+        plan.note(&format!(
+            "Engine 'hashicorp': secrets are read over HTTP from {}, no sops process is run",
+            crate::hashicorp::kv2_url(&address, &hashicorp.mount, &hashicorp.path)
+        ));
+        return Ok(());
+    }
+
+    if let Some(aws) = config
+        .vault
+        .aws_secretsmanager
+        .as_ref()
+        .filter(|_| config.vault.engine == crate::vault::ENGINE_AWS_SECRETS_MANAGER)
+    {
+        plan.command(
+            crate::aws::get_secret_args(aws)
+                .into_iter()
+                .fold(PlannedCommand::new("aws"), |command, arg| command.arg(arg))
+                .note("secret captured in memory, never written to disk"),
+        );
+        return Ok(());
+    }
+
+    if let Some(gcp) = config
+        .vault
+        .gcp
+        .as_ref()
+        .filter(|_| config.vault.engine == crate::vault::ENGINE_GCP_SECRET_MANAGER)
+    {
+        plan.note(&format!(
+            "Engine 'gcp-secretmanager': secrets are read over HTTPS from {} with Application Default Credentials, no sops process is run",
+            crate::gcp::access_url(gcp)
+        ));
+        return Ok(());
+    }
+
+    if let Some(onepassword) = config
+        .vault
+        .onepassword
+        .as_ref()
+        .filter(|_| config.vault.engine == crate::vault::ENGINE_ONEPASSWORD)
+    {
+        for (vault, item) in crate::onepassword::items(onepassword)? {
+            plan.command(
+                crate::onepassword::item_get_args(onepassword, vault.as_deref(), &item)
+                    .into_iter()
+                    .fold(PlannedCommand::new("op"), |command, arg| command.arg(arg))
+                    .note("item captured in memory, never written to disk"),
+            );
+        }
+        return Ok(());
+    }
+
+    if let Some(bitwarden) = config
+        .vault
+        .bitwarden
+        .as_ref()
+        .filter(|_| config.vault.engine == crate::vault::ENGINE_BITWARDEN)
+    {
+        plan.command(
+            crate::bitwarden::list_args(bitwarden)
+                .into_iter()
+                .fold(PlannedCommand::new("bws"), |command, arg| command.arg(arg))
+                .note("access token passed via environment; secrets captured in memory"),
+        );
+        return Ok(());
+    }
+
+    if let Some(pass) = config
+        .vault
+        .pass
+        .as_ref()
+        .filter(|_| config.vault.engine == crate::vault::ENGINE_PASS)
+    {
+        for entry in pass.keys.values() {
+            plan.command(
+                PlannedCommand::new("pass")
+                    .arg("show")
+                    .arg(entry)
+                    .note("first line captured in memory, never written to disk"),
+            );
+        }
+        return Ok(());
+    }
+
+    let vault_paths = config.vault_source_paths(config_dir)?;
+
+    if config.vault.engine == crate::vault::ENGINE_AGE_NATIVE {
+        plan.note("Engine 'age-native': vault files are decrypted in process, no sops process is run");
+    } else {
+        plan.command(PlannedCommand::new("sops").arg("--version"));
+
+        for vault_path in &vault_paths {
+            let mut decrypt = PlannedCommand::new("sops")
+                .arg("-d")
+                .arg(vault_path.to_string_lossy())
+                .note("plaintext captured in memory, never written to disk");
+            if let Some(key_path) = &config.vault.age_key_path {
+                decrypt = decrypt.env("SOPS_AGE_KEY_FILE", key_path);
+            }
+            plan.command(decrypt);
+        }
+    }
+
+    if vault_paths.len() > 1 {
+        plan.note(&format!(
+            "{} vault files are decrypted concurrently (at most {} at a time)",
+            vault_paths.len(),
+            crate::vault::MAX_PARALLEL_DECRYPTIONS
+        ));
+    }
+
+    for vault_path in vault_paths {
+        plan.file(FileAccess::Read, vault_path);
+    }
+    match &config.vault.age_key_path {
+        Some(key_path) => plan.file(FileAccess::Read, key_path),
+        None => plan.file(FileAccess::Read, "$SOPS_AGE_KEY_FILE"),
+    };
+
+    Ok(())
+}
+
+/// Plan for `unlock` / `unlock-global` (`fake` for `unlock --fake`).
+pub fn unlock_plan(title: &str, config: &Config, config_dir: &Path, fake: bool) -> Result<Plan> {
+    let mut plan = Plan::new(title);
+    secret_source_commands(&mut plan, config, config_dir, fake)?;
+
+    for target in &config.targets {
+        if target.create_if_missing && !Path::new(&target.path).exists() {
+            plan.file(FileAccess::Create, &target.path);
+            plan.note(&format!(
+                "Target '{}' does not exist: it is created from its template and deleted on exit",
+                target.name
+            ));
+        }
+        plan.file(FileAccess::Inject, &target.path);
+        if target.untrusted {
+            plan.note(&format!(
+                "Target '{}' is untrusted: it receives fake values only",
+                target.name
+            ));
+        }
+        if target.secure_wipe {
+            plan.note(&format!(
+                "Target '{}' is wiped (overwritten with zeros) before it is restored",
+                target.name
+            ));
+        }
+    }
+
+    if crate::process::is_restricted() {
+        plan.note("Restricted mode: no processes are killed during cleanup");
+    } else {
+        plan.note("On exit, processes holding a target open are terminated (killed after 3s) before templates are restored");
+    }
+
+    Ok(plan)
+}
+
+/// Plan for `unlock --preview-dir`: targets are read, previews created.
+pub fn unlock_preview_plan(
+    config: &Config,
+    config_dir: &Path,
+    preview_dir: &Path,
+    fake: bool,
+) -> Result<Plan> {
+    let mut plan = Plan::new("unlock --preview-dir");
+    secret_source_commands(&mut plan, config, config_dir, fake)?;
+
+    let cwd = std::env::current_dir()?;
+    for target in &config.targets {
+        if Path::new(&target.path).exists() {
+            plan.file(FileAccess::Read, &target.path);
+        }
+        plan.file(
+            FileAccess::Create,
+            crate::preview::preview_path(preview_dir, config_dir, &cwd, Path::new(&target.path)),
+        );
+    }
+    plan.note("Target files are not modified; preview files contain secrets and are kept until deleted");
+
+    Ok(plan)
+}
+
+/// Where `unlock` gets its values: decryption, or key names only with `--fake`.
+fn secret_source_commands(plan: &mut Plan, config: &Config, config_dir: &Path, fake: bool) -> Result<()> {
+    if !fake {
+        sops_decrypt_commands(plan, config, config_dir)?;
+        local_overlay_commands(plan, config, config_dir);
+        return Ok(());
+    }
+
+    plan.note("Fake mode: only key names are read, nothing is decrypted; targets receive deterministic fake values");
+    for vault_path in config.vault_source_paths(config_dir)? {
+        plan.file(FileAccess::Read, vault_path);
+    }
+    if let Some(overlay) = crate::vault::LocalOverlay::find(config_dir) {
+        plan.file(FileAccess::Read, overlay.path());
+    }
+
+    Ok(())
+}
+
+/// Decryption of the developer's local overlay, if any.
+fn local_overlay_commands(plan: &mut Plan, config: &Config, config_dir: &Path) {
+    match crate::vault::LocalOverlay::find(config_dir) {
+        Some(crate::vault::LocalOverlay::Encrypted(path)) => {
+            let mut decrypt = PlannedCommand::new("sops")
+                .arg("-d")
+                .arg(path.to_string_lossy())
+                .note("local overlay, merged last");
+            if let Some(key_path) = &config.vault.age_key_path {
+                decrypt = decrypt.env("SOPS_AGE_KEY_FILE", key_path);
+            }
+            plan.command(decrypt);
+            plan.file(FileAccess::Read, path);
+        }
+        Some(overlay) => {
+            plan.note("local overlay is PLAINTEXT; its values override the vault");
+            plan.file(FileAccess::Read, overlay.path());
+        }
+        None => {}
+    }
+}
+
+/// Plan for `push-cloud`.
+pub fn push_cloud_plan(
+    config: &Config,
+    config_dir: &Path,
+    project_id: Option<&str>,
+    environment: Option<&str>,
+    dry_run: bool,
+) -> Result<Plan> {
+    let mut plan = Plan::new("push-cloud");
+    sops_decrypt_commands(&mut plan, config, config_dir)?;
+
+    plan.command(PlannedCommand::new("vercel").arg("--version"));
+    if let Some(pid) = project_id {
+        plan.command(PlannedCommand::new("vercel").arg("link").arg("--yes").arg(pid));
+    }
+    plan.command(PlannedCommand::new("vercel").arg("env").arg("ls"));
+
+    if dry_run {
+        plan.note("Dry run: no 'vercel env add' commands are executed");
+    } else {
+        let provider = &config.cloud.vercel;
+        let mut add = PlannedCommand::new("vercel")
+            .arg("env")
+            .arg("add")
+            .arg(provider.remote_name("<KEY>"));
+        if let Some(environment) = environment {
+            add = add.arg(environment);
+            if let Some(branch) = &provider.git_branch {
+                add = add.arg(branch);
+            }
+        }
+        if provider.sensitive {
+            add = add.arg("--sensitive");
+        }
+        plan.command(
+            add.arg("--yes")
+                .secret_stdin()
+                .note("repeated for each secret not prefixed with LOCAL_ONLY_, after a second confirmation"),
+        );
+        if let Ok(path) = crate::audit::audit_log_path() {
+            plan.file(FileAccess::Modify, path);
+        }
+    }
+
+    Ok(plan)
+}
+
+/// Plan for `set`: `value` goes to `vault_path` through `sops set`.
+pub fn set_plan(config: &Config, vault_path: &Path, key: &str) -> Plan {
+    let mut plan = Plan::new("set");
+
+    plan.command(PlannedCommand::new("sops").arg("--version"));
+    let mut set = PlannedCommand::new("sops")
+        .arg("set")
+        .arg("--value-stdin")
+        .arg(vault_path.to_string_lossy())
+        .arg(crate::vault::extract_expression(key))
+        .secret_stdin();
+    if let Some(key_path) = &config.vault.age_key_path {
+        set = set.env("SOPS_AGE_KEY_FILE", key_path);
+    }
+    plan.command(set);
+    plan.file(FileAccess::Modify, vault_path);
+    plan.note("The value is prompted for (or read from stdin) after the plan is approved");
+
+    plan
+}
+
+/// Plan for `exec`: the vault is decrypted, then `command` runs with the
+/// secrets in its environment.
+pub fn exec_plan(config: &Config, config_dir: &Path, command: &[String]) -> Result<Plan> {
+    let mut plan = Plan::new("exec");
+    secret_source_commands(&mut plan, config, config_dir, false)?;
+
+    if let Some((program, args)) = command.split_first() {
+        plan.command(
+            args.iter()
+                .fold(PlannedCommand::new(program), |command, arg| command.arg(arg))
+                .note("secrets passed in its environment, never written to disk"),
+        );
+    }
+
+    Ok(plan)
+}
+
+/// Reuse or generate the age master key.
+fn age_key_steps(plan: &mut Plan, key_path: &Path) {
+    if key_path.exists() {
+        plan.file(FileAccess::Read, key_path);
+        return;
+    }
+
+    plan.command(PlannedCommand::new("age").arg("--version"));
+    plan.command(
+        PlannedCommand::new("age-keygen")
+            .arg("-o")
+            .arg(key_path.to_string_lossy()),
+    );
+    plan.file(FileAccess::Create, key_path);
+}
+
+/// In-place SOPS encryption of a freshly created secrets file.
+fn sops_encrypt_steps(plan: &mut Plan, enc_env: &Path, dir: &Path) {
+    plan.command(PlannedCommand::new("sops").arg("--version"));
+    plan.command(
+        PlannedCommand::new("sops")
+            .arg("--encrypt")
+            .arg("--output")
+            .arg(enc_env.to_string_lossy())
+            .arg(enc_env.to_string_lossy())
+            .current_dir(dir),
+    );
+}
+
+/// Plan for `init-project`.
+pub fn init_project_plan(config: &InitConfig, project_dir: &Path) -> Plan {
+    let mut plan = Plan::new("init-project");
+    let enc_env = project_dir.join(".enc.env");
+
+    age_key_steps(&mut plan, &config.master_key_path);
+    if !config.master_key_path.exists() {
+        plan.note("The age keypair is only generated if you accept the prompt");
+    }
+    sops_encrypt_steps(&mut plan, &enc_env, project_dir);
+
+    plan.file(FileAccess::Create, project_dir.join(".sops.yaml"));
+    plan.file(FileAccess::Create, &enc_env);
+    plan.file(FileAccess::Create, project_dir.join("project.yaml"));
+
+    if config.prompt_global {
+        if let Ok(dir) = crate::init::get_global_config_dir() {
+            plan.file(FileAccess::Modify, dir.join("global.yaml"));
+        }
+        plan.note("global.yaml is only modified if you accept adding the project to it");
+    }
+
+    plan
+}
+
+/// Plan for `init-global`.
+pub fn init_global_plan(global_dir: &Path) -> Plan {
+    let mut plan = Plan::new("init-global");
+    let enc_env = global_dir.join("global.enc.env");
+
+    age_key_steps(&mut plan, &crate::init::get_default_master_key_path());
+
+    plan.file(FileAccess::Create, global_dir.join(".sops.yaml"));
+    if !enc_env.exists() {
+        sops_encrypt_steps(&mut plan, &enc_env, global_dir);
+        plan.file(FileAccess::Create, &enc_env);
+    }
+    plan.file(FileAccess::Create, global_dir.join("global.yaml"));
+
+    plan
+}
+
+/// Plan for `update`.
+pub fn update_plan(check_only: bool) -> Plan {
+    let mut plan = Plan::new("update");
+
+    plan.command(
+        PlannedCommand::new("npm")
+            .arg("view")
+            .arg("@oalacea/shadow-secret")
+            .arg("version"),
+    );
+    if !check_only {
+        plan.command(
+            PlannedCommand::new("npm")
+                .arg("install")
+                .arg("-g")
+                .arg("@oalacea/shadow-secret@latest")
+                .note("only if a newer version is published"),
+        );
+    }
+
+    plan
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{TargetConfig, VaultConfig};
+
+    fn config() -> Config {
+        Config {
+            vault: VaultConfig {
+                source: ".enc.env".to_string(),
+                sources: Vec::new(),
+                vault_path: None,
+                engine: "sops".to_string(),
+                hashicorp: None,
+                aws_secretsmanager: None,
+                gcp: None,
+                onepassword: None,
+                bitwarden: None,
+                pass: None,
+                age_key_path: Some("/keys/my key.txt".to_string()),
+                require_mount: false,
+                normalize_keys: false,
+            },
+            targets: vec![TargetConfig {
+                name: "app".to_string(),
+                path: "config.json".to_string(),
+                placeholders: vec!["$API_KEY".to_string()],
+                ..Default::default()
+            }],
+            cloud: Default::default(),
+            variables: Default::default(),
+            leases: Vec::new(),
+            dynamic: Vec::new(),
+            timeouts: Default::default(),
+            naming: Default::default(),
+            backup: Default::default(),
+            aliases: Default::default(),
+            sinks: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("sops"), "sops");
+        assert_eq!(shell_quote("/tmp/a b"), "'/tmp/a b'");
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+        assert_eq!(shell_quote(""), "''");
+    }
+
+    #[test]
+    fn test_unlock_plan_lists_commands_and_files() {
+        let plan = unlock_plan("unlock", &config(), Path::new("/project"), false).unwrap();
+
+        assert_eq!(plan.commands().len(), 2);
+        assert_eq!(
+            plan.commands()[1].command_line(),
+            "SOPS_AGE_KEY_FILE='/keys/my key.txt' sops -d /project/.enc.env"
+        );
+        assert!(plan
+            .files()
+            .contains(&(FileAccess::Inject, PathBuf::from("config.json"))));
+
+        let fake = unlock_plan("unlock", &config(), Path::new("/project"), true).unwrap();
+        assert!(fake.commands().is_empty());
+        assert!(fake
+            .files()
+            .contains(&(FileAccess::Read, PathBuf::from("/project/.enc.env"))));
+    }
+
+    #[test]
+    fn test_push_cloud_plan_elides_secret_values() {
+        let plan = push_cloud_plan(
+            &config(),
+            Path::new("/project"),
+            Some("prj_1"),
+            Some("preview"),
+            false,
+        ).unwrap();
+        let rendered = plan.render();
+
+        assert!(rendered.contains("vercel link --yes prj_1"));
+        assert!(rendered.contains("vercel env add '<KEY>' preview --yes <<< <elided>"));
+    }
+
+    #[test]
+    fn test_exec_and_set_plans() {
+        let command = vec!["npm".to_string(), "run".to_string(), "dev".to_string()];
+        let plan = exec_plan(&config(), Path::new("/project"), &command).unwrap();
+        assert_eq!(plan.commands().last().unwrap().command_line(), "npm run dev");
+
+        let plan = set_plan(&config(), Path::new("/project/.enc.env"), "API_KEY");
+        assert!(plan.render().contains("sops set --value-stdin /project/.enc.env '[\"API_KEY\"]' <<< <elided>"));
+        assert!(plan
+            .files()
+            .contains(&(FileAccess::Modify, PathBuf::from("/project/.enc.env"))));
+    }
+
+    #[test]
+    fn test_plan_is_reproducible() {
+        let a = unlock_plan("unlock", &config(), Path::new("/project"), false).unwrap();
+        let b = unlock_plan("unlock", &config(), Path::new("/project"), false).unwrap();
+
+        assert_eq!(a.render(), b.render());
+    }
+}
@@ -0,0 +1,85 @@
+//! Deterministic fake secret values.
+//!
+//! Used for targets marked `untrusted: true`: they receive realistic-looking
+//! values instead of real secrets, so suspicious third-party tooling can run
+//! against a complete config without any exposure.
+//!
+//! Fake values are derived from the key name only (never from the real
+//! value), so they are stable across runs and reveal nothing about the
+//! secret they replace.
+
+use blake2::{Blake2b512, Digest};
+use std::collections::HashMap;
+
+/// Domain separation for the key hash.
+const FAKE_CONTEXT: &[u8] = b"shadow-secret fake value v1\0";
+
+/// Derive a fake value for `key`.
+///
+/// The shape follows common naming conventions (`*_URL`, `*_PORT`,
+/// `*_EMAIL`, ...) so tools that validate formats keep working.
+pub fn fake_value(key: &str) -> String {
+    let digest = Blake2b512::new()
+        .chain_update(FAKE_CONTEXT)
+        .chain_update(key.as_bytes())
+        .finalize();
+    let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+    let short = &hex[..8];
+
+    let upper = key.to_ascii_uppercase();
+    let has = |needle: &str| upper.contains(needle);
+
+    if has("DATABASE_URL") || has("POSTGRES") || has("DB_URL") {
+        format!("postgres://fake_{}:{}@localhost:5432/fake_{}", short, &hex[8..24], short)
+    } else if has("REDIS") && (has("URL") || has("URI")) {
+        format!("redis://:{}@localhost:6379/0", &hex[..24])
+    } else if upper.ends_with("URL") || upper.ends_with("URI") || upper.ends_with("ENDPOINT") {
+        format!("https://fake-{}.example.invalid", short)
+    } else if has("EMAIL") {
+        format!("fake-{}@example.invalid", short)
+    } else if upper.ends_with("PORT") {
+        (1024 + u16::from_be_bytes([digest[0], digest[1]]) % 50000).to_string()
+    } else if upper.ends_with("HOST") {
+        format!("fake-{}.example.invalid", short)
+    } else {
+        format!("fake_{}", &hex[..32])
+    }
+}
+
+/// Replace every value in `secrets` with its fake counterpart.
+pub fn fake_secrets(secrets: &HashMap<String, String>) -> HashMap<String, String> {
+    secrets
+        .keys()
+        .map(|key| (key.clone(), fake_value(key)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fake_value_is_deterministic_per_key() {
+        assert_eq!(fake_value("API_KEY"), fake_value("API_KEY"));
+        assert_ne!(fake_value("API_KEY"), fake_value("OTHER_KEY"));
+        assert!(fake_value("API_KEY").starts_with("fake_"));
+    }
+
+    #[test]
+    fn test_fake_value_shapes() {
+        assert!(fake_value("DATABASE_URL").starts_with("postgres://"));
+        assert!(fake_value("WEBHOOK_URL").starts_with("https://"));
+        assert!(fake_value("ADMIN_EMAIL").contains('@'));
+        assert!(fake_value("SMTP_PORT").parse::<u16>().is_ok());
+    }
+
+    #[test]
+    fn test_fake_secrets_never_reveal_real_values() {
+        let mut secrets = HashMap::new();
+        secrets.insert("API_KEY".to_string(), "sk_live_real".to_string());
+
+        let fake = fake_secrets(&secrets);
+        assert_eq!(fake.len(), 1);
+        assert!(!fake["API_KEY"].contains("sk_live_real"));
+    }
+}
@@ -0,0 +1,553 @@
+//! Vault integrity checks (`shadow-secret fsck`).
+//!
+//! Checks the encrypted file itself rather than the configuration:
+//!
+//! - **mac**: SOPS decrypts the file and verifies its MAC
+//! - **recipients**: age recipients in the file match `.sops.yaml`
+//! - **empty**: keys with empty values
+//! - **duplicates**: repeated keys, or keys differing only by case
+//! - **encoding**: invalid UTF-8, byte order marks, CRLF line endings,
+//!   control characters in values
+//! - **naming**: keys breaking the `naming:` rules of the config (see
+//!   [`crate::naming`])
+//! - **encryption**: keys targets inject that SOPS left in plaintext
+//!   (`encrypted_regex` not matching them)
+
+use crate::config::Config;
+use crate::naming::Rules;
+use crate::sops::SopsError;
+use crate::vault;
+use anyhow::{Context, Result};
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+/// How serious a finding is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The vault is unusable or unsafe
+    Error,
+    /// The vault works but something looks wrong
+    Warning,
+}
+
+/// A single problem found in a vault.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub severity: Severity,
+    /// Check that produced the finding (e.g. "mac", "recipients")
+    pub check: &'static str,
+    pub message: String,
+}
+
+impl Finding {
+    fn error(check: &'static str, message: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            check,
+            message,
+        }
+    }
+
+    fn warning(check: &'static str, message: String) -> Self {
+        Self {
+            severity: Severity::Warning,
+            check,
+            message,
+        }
+    }
+}
+
+/// Results of checking one vault file.
+#[derive(Debug, Clone)]
+pub struct FsckReport {
+    pub path: PathBuf,
+    pub findings: Vec<Finding>,
+    /// Keys stored unencrypted in the file (partial encryption)
+    pub plaintext_keys: Vec<String>,
+}
+
+impl FsckReport {
+    /// Whether any finding is an error.
+    pub fn has_errors(&self) -> bool {
+        self.findings
+            .iter()
+            .any(|finding| finding.severity == Severity::Error)
+    }
+}
+
+/// Run every integrity check on an encrypted vault file, keys checked
+/// against `naming`. `secret_keys` are the keys that must be stored
+/// encrypted (see [`secret_keys`]).
+///
+/// # Errors
+///
+/// Returns an error only if the file cannot be read; problems with its
+/// content are reported as findings.
+pub fn check_vault(
+    path: &Path,
+    age_key_path: Option<&str>,
+    naming: &Rules,
+    secret_keys: &BTreeSet<String>,
+) -> Result<FsckReport> {
+    let mut findings = Vec::new();
+
+    let encrypted = std::fs::read(path).with_context(|| format!("Failed to read vault: {:?}", path))?;
+    let encrypted = String::from_utf8_lossy(&encrypted);
+
+    findings.extend(check_recipients(path, &encrypted));
+
+    let path_str = path.to_string_lossy();
+    let (encryption_findings, plaintext_keys) = check_encryption(&path_str, &encrypted, secret_keys);
+    findings.extend(encryption_findings);
+    match vault::execute_sops(&path_str, age_key_path) {
+        Ok(plaintext) => findings.extend(check_plaintext(&path_str, &plaintext, naming)),
+        Err(e) => {
+            let message = match e.downcast_ref::<SopsError>() {
+                Some(SopsError::MacMismatch { .. }) => {
+                    "MAC verification failed: the file was modified outside SOPS".to_string()
+                }
+                Some(sops_error) => format!("Could not decrypt to verify the MAC: {}", sops_error),
+                None => format!("Could not decrypt to verify the MAC: {:#}", e),
+            };
+            findings.push(Finding::error("mac", message));
+        }
+    }
+
+    Ok(FsckReport {
+        path: path.to_path_buf(),
+        findings,
+        plaintext_keys,
+    })
+}
+
+/// Keys targets name explicitly (placeholders and `keys`): those are
+/// secrets, whatever else the vault holds.
+pub fn secret_keys(config: &Config) -> BTreeSet<String> {
+    config
+        .targets
+        .iter()
+        .flat_map(|target| {
+            target
+                .placeholders
+                .iter()
+                .filter(|placeholder| !crate::injector::is_all_placeholder(placeholder))
+                .map(|placeholder| crate::injector::extract_key_name(placeholder).to_string())
+                .chain(target.keys.iter().cloned())
+        })
+        .collect()
+}
+
+/// Compare the keys SOPS left in plaintext with `secret_keys`.
+///
+/// # Returns
+///
+/// A warning per secret stored unencrypted, and every plaintext key.
+fn check_encryption(path: &str, encrypted: &str, secret_keys: &BTreeSet<String>) -> (Vec<Finding>, Vec<String>) {
+    let status = match vault::encryption_status(path, encrypted.as_bytes()) {
+        Ok(status) => status,
+        Err(e) => {
+            let finding = Finding::warning("encryption", format!("Could not read which keys are encrypted: {:#}", e));
+            return (vec![finding], Vec::new());
+        }
+    };
+
+    let plaintext_keys: Vec<String> = status
+        .into_iter()
+        .filter(|(_, encrypted)| !encrypted)
+        .map(|(key, _)| key)
+        .collect();
+    let findings = plaintext_keys
+        .iter()
+        .filter(|key| secret_keys.contains(*key))
+        .map(|key| {
+            Finding::warning(
+                "encryption",
+                format!("{} is injected by a target but stored unencrypted (check encrypted_regex in .sops.yaml)", key),
+            )
+        })
+        .collect();
+    (findings, plaintext_keys)
+}
+
+/// Compare recipients stored in the vault with those required by `.sops.yaml`.
+fn check_recipients(path: &Path, encrypted: &str) -> Vec<Finding> {
+    let mut findings = Vec::new();
+
+    let actual = vault_recipients(encrypted);
+    if actual.is_empty() {
+        findings.push(Finding::warning(
+            "recipients",
+            "No age recipients found in the vault metadata (not encrypted with age?)".to_string(),
+        ));
+        return findings;
+    }
+
+    let expected = match sops_config_recipients(path) {
+        Ok(Some((config_path, expected))) => {
+            if expected.is_empty() {
+                return findings;
+            }
+            (config_path, expected)
+        }
+        Ok(None) => {
+            findings.push(Finding::warning(
+                "recipients",
+                "No .sops.yaml rule matches this file; recipients not compared".to_string(),
+            ));
+            return findings;
+        }
+        Err(e) => {
+            findings.push(Finding::warning(
+                "recipients",
+                format!("Could not read .sops.yaml: {:#}", e),
+            ));
+            return findings;
+        }
+    };
+    let (config_path, expected) = expected;
+
+    for missing in expected.difference(&actual) {
+        findings.push(Finding::error(
+            "recipients",
+            format!(
+                "{} is required by {} but cannot decrypt the vault (run 'sops updatekeys')",
+                missing,
+                config_path.display()
+            ),
+        ));
+    }
+    for extra in actual.difference(&expected) {
+        findings.push(Finding::warning(
+            "recipients",
+            format!(
+                "{} can decrypt the vault but is not listed in {} (stale access?)",
+                extra,
+                config_path.display()
+            ),
+        ));
+    }
+
+    findings
+}
+
+/// Extract age recipients from SOPS metadata (dotenv, YAML or JSON files).
+pub fn vault_recipients(encrypted: &str) -> BTreeSet<String> {
+    let mut recipients = BTreeSet::new();
+
+    // dotenv: sops_age__list_0__map_recipient=age1...
+    for line in encrypted.lines() {
+        if let Some((key, value)) = line.split_once('=') {
+            if key.starts_with("sops_age__list_") && key.ends_with("__map_recipient") {
+                recipients.insert(value.trim().to_string());
+            }
+        }
+    }
+
+    // YAML/JSON: sops.age[].recipient (YAML is a superset of JSON)
+    if recipients.is_empty() {
+        if let Ok(document) = serde_yaml::from_str::<serde_yaml::Value>(encrypted) {
+            if let Some(entries) = document
+                .get("sops")
+                .and_then(|sops| sops.get("age"))
+                .and_then(|age| age.as_sequence())
+            {
+                for entry in entries {
+                    if let Some(recipient) = entry.get("recipient").and_then(|r| r.as_str()) {
+                        recipients.insert(recipient.to_string());
+                    }
+                }
+            }
+        }
+    }
+
+    recipients
+}
+
+/// Find the `.sops.yaml` governing `vault_path` and return the age
+/// recipients of the first matching creation rule.
+///
+/// Like SOPS, the config is searched in the vault's directory and its parents.
+pub fn sops_config_recipients(vault_path: &Path) -> Result<Option<(PathBuf, BTreeSet<String>)>> {
+    let absolute = if vault_path.is_absolute() {
+        vault_path.to_path_buf()
+    } else {
+        std::env::current_dir()?.join(vault_path)
+    };
+
+    let Some(config_path) = absolute
+        .ancestors()
+        .skip(1)
+        .map(|dir| dir.join(".sops.yaml"))
+        .find(|candidate| candidate.is_file())
+    else {
+        return Ok(None);
+    };
+
+    let content = std::fs::read_to_string(&config_path)
+        .with_context(|| format!("Failed to read {:?}", config_path))?;
+    let document: serde_yaml::Value = serde_yaml::from_str(&content)
+        .with_context(|| format!("Failed to parse {:?}", config_path))?;
+
+    let file_path = absolute.to_string_lossy().replace('\\', "/");
+    let rules = document
+        .get("creation_rules")
+        .and_then(|rules| rules.as_sequence())
+        .cloned()
+        .unwrap_or_default();
+
+    for rule in rules {
+        if let Some(pattern) = rule.get("path_regex").and_then(|p| p.as_str()) {
+            let regex = regex::Regex::new(pattern)
+                .with_context(|| format!("Invalid path_regex in {:?}: {}", config_path, pattern))?;
+            if !regex.is_match(&file_path) {
+                continue;
+            }
+        }
+
+        let recipients = rule
+            .get("age")
+            .and_then(|age| age.as_str())
+            .map(|age| {
+                age.split(',')
+                    .map(str::trim)
+                    .filter(|r| !r.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        return Ok(Some((config_path, recipients)));
+    }
+
+    Ok(None)
+}
+
+/// Checks on the decrypted content: empty values, duplicates, encoding.
+fn check_plaintext(path: &str, plaintext: &[u8], naming: &Rules) -> Vec<Finding> {
+    let mut findings = Vec::new();
+
+    let content = match std::str::from_utf8(plaintext) {
+        Ok(content) => content,
+        Err(e) => {
+            findings.push(Finding::error(
+                "encoding",
+                format!("Decrypted content is not valid UTF-8 ({})", e),
+            ));
+            return findings;
+        }
+    };
+
+    if content.starts_with('\u{feff}') {
+        findings.push(Finding::warning(
+            "encoding",
+            "Content starts with a UTF-8 byte order mark".to_string(),
+        ));
+    }
+    if content.contains("\r\n") {
+        findings.push(Finding::warning(
+            "encoding",
+            "Content uses CRLF line endings (values may end with '\\r')".to_string(),
+        ));
+    }
+
+    let is_env = path.ends_with(".env") || path.ends_with(".dotenv");
+    let entries: Vec<(String, String)> = if is_env {
+        env_entries(content)
+    } else {
+        match vault::parse_output(path, plaintext) {
+            Ok(secrets) => {
+                let mut entries: Vec<_> = secrets.into_iter().collect();
+                entries.sort();
+                entries
+            }
+            Err(e) => {
+                findings.push(Finding::error(
+                    "encoding",
+                    format!("Decrypted content cannot be parsed: {:#}", e),
+                ));
+                return findings;
+            }
+        }
+    };
+
+    let mut seen: HashMap<String, &str> = HashMap::new();
+    for (key, value) in &entries {
+        if value.is_empty() {
+            findings.push(Finding::warning("empty", format!("{} has an empty value", key)));
+        }
+
+        if value.chars().any(|c| c.is_control() && c != '\n' && c != '\t') {
+            findings.push(Finding::warning(
+                "encoding",
+                format!("{} contains control characters", key),
+            ));
+        }
+
+        match seen.get(&key.to_lowercase()) {
+            Some(previous) if *previous == key.as_str() => findings.push(Finding::error(
+                "duplicates",
+                format!("{} is defined more than once (only the last value is used)", key),
+            )),
+            Some(previous) => findings.push(Finding::warning(
+                "duplicates",
+                format!("{} and {} differ only by case", previous, key),
+            )),
+            None => {
+                seen.insert(key.to_lowercase(), key);
+                if let Some(violation) = naming.check(key) {
+                    findings.push(Finding::error("naming", violation.message()));
+                }
+            }
+        }
+    }
+
+    findings
+}
+
+/// Raw `KEY=value` entries in file order, keeping duplicates.
+fn env_entries(content: &str) -> Vec<(String, String)> {
+    content
+        .lines()
+        .map(|line| line.trim_start_matches('\u{feff}').trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| {
+            let value = value.trim();
+            let value = value
+                .strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .unwrap_or(value);
+            (key.trim().to_string(), value.to_string())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn checks(findings: &[Finding]) -> Vec<&'static str> {
+        findings.iter().map(|finding| finding.check).collect()
+    }
+
+    #[test]
+    fn test_vault_recipients_dotenv_and_yaml() {
+        let dotenv = "API_KEY=ENC[AES256_GCM,data:abc]\n\
+            sops_age__list_0__map_recipient=age1alice\n\
+            sops_age__list_1__map_recipient=age1bob\n";
+        let yaml = "API_KEY: ENC[AES256_GCM,data:abc]\nsops:\n  age:\n    - recipient: age1alice\n      enc: x\n";
+
+        assert_eq!(vault_recipients(dotenv).len(), 2);
+        assert!(vault_recipients(yaml).contains("age1alice"));
+    }
+
+    #[test]
+    fn test_recipients_mismatch() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(
+            temp_dir.path().join(".sops.yaml"),
+            "creation_rules:\n  - path_regex: .*\\.enc\\.env$\n    age: \"age1alice,age1carol\"\n",
+        )
+        .unwrap();
+        let vault_path = temp_dir.path().join(".enc.env");
+
+        let findings = check_recipients(
+            &vault_path,
+            "sops_age__list_0__map_recipient=age1alice\nsops_age__list_1__map_recipient=age1bob\n",
+        );
+
+        assert_eq!(findings.len(), 2);
+        assert!(findings
+            .iter()
+            .any(|f| f.severity == Severity::Error && f.message.contains("age1carol")));
+        assert!(findings
+            .iter()
+            .any(|f| f.severity == Severity::Warning && f.message.contains("age1bob")));
+    }
+
+    #[test]
+    fn test_recipients_match() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(
+            temp_dir.path().join(".sops.yaml"),
+            "creation_rules:\n  - path_regex: .*\\.enc\\.env$\n    age: \"age1alice\"\n",
+        )
+        .unwrap();
+
+        let findings = check_recipients(
+            &temp_dir.path().join("prod.enc.env"),
+            "sops_age__list_0__map_recipient=age1alice\n",
+        );
+        assert!(findings.is_empty());
+    }
+
+    #[test]
+    fn test_plaintext_checks() {
+        let plaintext = b"API_KEY=abc\nEMPTY=\napi_key=def\nAPI_KEY=ghi\nBELL=a\x07b\n";
+        let findings = check_plaintext("vault.enc.env", plaintext, &Rules::default());
+        let found = checks(&findings);
+
+        assert!(found.contains(&"empty"));
+        assert_eq!(found.iter().filter(|c| **c == "duplicates").count(), 2);
+        assert!(found.contains(&"encoding"));
+    }
+
+    #[test]
+    fn test_plaintext_encoding_problems() {
+        let findings = check_plaintext("vault.enc.env", b"\xef\xbb\xbfKEY=value\r\n", &Rules::default());
+        assert_eq!(
+            findings
+                .iter()
+                .filter(|f| f.check == "encoding")
+                .count(),
+            2
+        );
+
+        let findings = check_plaintext("vault.enc.env", b"KEY=\xff\xfe", &Rules::default());
+        assert!(findings[0].message.contains("UTF-8"));
+        assert_eq!(findings[0].severity, Severity::Error);
+    }
+
+    #[test]
+    fn test_plaintext_naming() {
+        let naming = Rules::new(&crate::config::NamingConfig {
+            case: Some("screaming_snake".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+        let findings = check_plaintext("vault.enc.env", b"API_KEY=a\ndbUrl=b\ndbUrl=c\n", &naming);
+
+        let naming: Vec<&Finding> = findings.iter().filter(|f| f.check == "naming").collect();
+        assert_eq!(naming.len(), 1);
+        assert_eq!(naming[0].severity, Severity::Error);
+        assert!(naming[0].message.contains("(suggested: DB_URL)"), "{}", naming[0].message);
+    }
+
+    #[test]
+    fn test_encryption_warns_on_unencrypted_secrets() {
+        let encrypted = "\
+API_KEY: ENC[AES256_GCM,data:abc=,iv:x,tag:y,type:str]
+DB_PASSWORD: hunter2
+LOG_LEVEL: debug
+sops:
+    encrypted_regex: ^API_KEY$
+";
+        let secret_keys = BTreeSet::from(["API_KEY".to_string(), "DB_PASSWORD".to_string()]);
+        let (findings, plaintext_keys) = check_encryption("secrets.enc.yaml", encrypted, &secret_keys);
+
+        assert_eq!(plaintext_keys, vec!["DB_PASSWORD", "LOG_LEVEL"]);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].severity, Severity::Warning);
+        assert!(findings[0].message.starts_with("DB_PASSWORD is injected"), "{}", findings[0].message);
+    }
+
+    #[test]
+    fn test_secret_keys_from_targets() {
+        let config: Config = serde_yaml::from_str(
+            "vault:\n  source: secrets.enc.yaml\n  engine: sops\ntargets:\n  - name: app\n    path: app.json\n    placeholders: [\"$API_KEY\", \"${DB_URL:-x}\", \"$ALL\"]\n    keys: [TOKEN]\n",
+        )
+        .unwrap();
+        assert_eq!(secret_keys(&config).into_iter().collect::<Vec<_>>(), vec!["API_KEY", "DB_URL", "TOKEN"]);
+    }
+}
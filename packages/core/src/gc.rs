@@ -0,0 +1,259 @@
+//! Garbage collection of stale per-user state (`shadow-secret gc`).
+//!
+//! Interrupted sessions leave recovery journals behind (see
+//! [`crate::journal`]), and crashed writes can leave `*.tmp` files in the
+//! state directory. [`collect`] removes those older than a maximum age;
+//! journals of sessions that are still running are always kept, and so is
+//! the latest injection report (see [`crate::report`]). Push
+//! transcripts, the audit log and push fingerprints are records, not
+//! leftovers, and are never collected.
+//!
+//! Shadow Secret keeps no lock files and no decrypted cache on disk, so
+//! there is nothing else to clean up.
+//!
+//! A lightweight pass ([`auto`]) runs at the start of every command, at most
+//! once a day, with the age from `$SHADOW_SECRET_GC_MAX_AGE` (default 30
+//! days, `off` disables it).
+
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Environment variable overriding the age used by the automatic pass.
+pub const MAX_AGE_ENV: &str = "SHADOW_SECRET_GC_MAX_AGE";
+
+/// Age after which leftovers are collected by default.
+pub const DEFAULT_MAX_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// Minimum time between two automatic passes.
+const AUTO_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Marker file recording the last automatic pass.
+const AUTO_MARKER: &str = ".gc-last-run";
+
+/// Extension of temporary files left by interrupted writes.
+const TEMP_EXTENSION: &str = "tmp";
+
+/// Kind of leftover removed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StaleKind {
+    /// Recovery journal of a session that is no longer running
+    Journal,
+    /// Injection report of an earlier session
+    Report,
+    /// Temporary file of an interrupted write
+    TempFile,
+}
+
+impl std::fmt::Display for StaleKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StaleKind::Journal => write!(f, "expired journal"),
+            StaleKind::Report => write!(f, "old injection report"),
+            StaleKind::TempFile => write!(f, "temporary file"),
+        }
+    }
+}
+
+/// A leftover found (and, unless dry-running, removed).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StaleEntry {
+    pub path: PathBuf,
+    pub kind: StaleKind,
+    /// Time since the file was last modified
+    pub age: Duration,
+}
+
+/// Parse an age such as `30d`, `12h`, `45m` or `90s`.
+pub fn parse_max_age(value: &str) -> Result<Duration> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .with_context(|| format!("Age '{}' needs a unit (d, h, m or s)", value))?;
+    let (amount, unit) = value.split_at(split);
+    let amount: u64 = amount
+        .parse()
+        .with_context(|| format!("Invalid age '{}': expected e.g. 30d", value))?;
+
+    let seconds = match unit {
+        "d" => amount * 24 * 60 * 60,
+        "h" => amount * 60 * 60,
+        "m" => amount * 60,
+        "s" => amount,
+        _ => anyhow::bail!("Invalid age unit '{}' (use d, h, m or s)", unit),
+    };
+    Ok(Duration::from_secs(seconds))
+}
+
+/// Find the leftovers of `state_dir` older than `max_age` and remove them
+/// (only list them when `dry_run`).
+pub fn collect(state_dir: &Path, max_age: Duration, dry_run: bool) -> Result<Vec<StaleEntry>> {
+    let now = SystemTime::now();
+    let mut stale = Vec::new();
+
+    let journal_dir = state_dir.join(crate::journal::JOURNAL_DIR);
+    for journal in crate::journal::list(&journal_dir)? {
+        if journal.is_active() {
+            continue;
+        }
+        if let Some(age) = older_than(&journal.path, now, max_age) {
+            stale.push(StaleEntry {
+                path: journal.path,
+                kind: StaleKind::Journal,
+                age,
+            });
+        }
+    }
+
+    let mut reports = crate::report::list(&state_dir.join(crate::report::REPORTS_DIR))?;
+    // `status --last` always has something to show
+    reports.pop();
+    for path in reports {
+        if let Some(age) = older_than(&path, now, max_age) {
+            stale.push(StaleEntry {
+                path,
+                kind: StaleKind::Report,
+                age,
+            });
+        }
+    }
+
+    for dir in [state_dir, journal_dir.as_path()] {
+        for path in temp_files(dir)? {
+            if let Some(age) = older_than(&path, now, max_age) {
+                stale.push(StaleEntry {
+                    path,
+                    kind: StaleKind::TempFile,
+                    age,
+                });
+            }
+        }
+    }
+
+    if !dry_run {
+        for entry in &stale {
+            std::fs::remove_file(&entry.path)
+                .with_context(|| format!("Failed to remove {}", entry.path.display()))?;
+        }
+    }
+    Ok(stale)
+}
+
+/// Lightweight automatic pass, at most once a day. Never fails: state that
+/// cannot be cleaned is left for `shadow-secret gc` to report.
+pub fn auto() -> Vec<StaleEntry> {
+    let max_age = match std::env::var(MAX_AGE_ENV) {
+        Ok(value) if value.eq_ignore_ascii_case("off") => return Vec::new(),
+        Ok(value) => match parse_max_age(&value) {
+            Ok(max_age) => max_age,
+            Err(_) => return Vec::new(),
+        },
+        Err(_) => DEFAULT_MAX_AGE,
+    };
+
+    // Never create the state directory just to clean it
+    let Ok(state_dir) = crate::init::get_global_config_dir() else {
+        return Vec::new();
+    };
+    if !state_dir.is_dir() || crate::isolation::check_owner(&state_dir).is_err() {
+        return Vec::new();
+    }
+
+    let marker = state_dir.join(AUTO_MARKER);
+    if marker.exists() && older_than(&marker, SystemTime::now(), AUTO_INTERVAL).is_none() {
+        return Vec::new();
+    }
+    if std::fs::write(&marker, "").is_err() {
+        return Vec::new();
+    }
+
+    collect(&state_dir, max_age, false).unwrap_or_default()
+}
+
+/// Age of `path` if it was last modified more than `max_age` ago.
+fn older_than(path: &Path, now: SystemTime, max_age: Duration) -> Option<Duration> {
+    let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok()?;
+    let age = now.duration_since(modified).ok()?;
+    (age > max_age).then_some(age)
+}
+
+/// `*.tmp` files directly in `dir`.
+fn temp_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir).with_context(|| format!("Failed to read {:?}", dir))? {
+        let path = entry?.path();
+        if path.is_file() && path.extension().and_then(|e| e.to_str()) == Some(TEMP_EXTENSION) {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_max_age() {
+        assert_eq!(parse_max_age("30d").unwrap(), Duration::from_secs(30 * 86400));
+        assert_eq!(parse_max_age("12h").unwrap(), Duration::from_secs(12 * 3600));
+        assert_eq!(parse_max_age("0s").unwrap(), Duration::ZERO);
+        assert!(parse_max_age("30").is_err());
+        assert!(parse_max_age("3w").is_err());
+    }
+
+    #[test]
+    fn test_collect_removes_stale_files_only() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let state = temp_dir.path();
+        let journals = state.join(crate::journal::JOURNAL_DIR);
+        std::fs::create_dir(&journals).unwrap();
+
+        // Above any pid_max: never a running session
+        let interrupted = journals.join("20260101T000000-999999999.age");
+        let active = journals.join(format!("20260101T000000-{}.age", std::process::id()));
+        let temp = state.join("push-fingerprints.json.tmp");
+        let audit = state.join("audit.log");
+        for path in [&interrupted, &active, &temp, &audit] {
+            std::fs::write(path, "x").unwrap();
+        }
+
+        std::thread::sleep(Duration::from_millis(20));
+        let dry = collect(state, Duration::from_millis(1), true).unwrap();
+        assert_eq!(dry.len(), 2);
+        assert!(interrupted.exists());
+
+        let removed = collect(state, Duration::from_millis(1), false).unwrap();
+        assert_eq!(removed[0].kind, StaleKind::Journal);
+        assert_eq!(removed[0].path, interrupted);
+        assert_eq!(removed[1].kind, StaleKind::TempFile);
+        assert!(!interrupted.exists() && !temp.exists());
+        assert!(active.exists() && audit.exists());
+
+        std::fs::write(&temp, "x").unwrap();
+        assert!(collect(state, DEFAULT_MAX_AGE, false).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_collect_keeps_latest_report() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let reports = temp_dir.path().join(crate::report::REPORTS_DIR);
+        std::fs::create_dir(&reports).unwrap();
+        let old = reports.join("session-20260101T000000-42.json");
+        let latest = reports.join("session-20260102T000000-42.json");
+        for path in [&old, &latest] {
+            std::fs::write(path, "{}").unwrap();
+        }
+
+        std::thread::sleep(Duration::from_millis(20));
+        let removed = collect(temp_dir.path(), Duration::from_millis(1), false).unwrap();
+        assert_eq!(removed.len(), 1);
+        assert_eq!((removed[0].kind, &removed[0].path), (StaleKind::Report, &old));
+        assert!(latest.exists());
+    }
+}
@@ -0,0 +1,396 @@
+//! GCP Secret Manager backend (`vault.engine: gcp-secretmanager`).
+//!
+//! One secret version (`latest` or pinned) is read over the REST API. Its
+//! payload is either a JSON object or dotenv lines; each entry becomes a
+//! vault key. The payload is decoded in memory and never written to disk.
+//!
+//! Authentication uses Application Default Credentials, in the order the
+//! Google client libraries use:
+//!
+//! 1. the JSON file named by `$GOOGLE_APPLICATION_CREDENTIALS` (service
+//!    account key or authorized user)
+//! 2. the file written by `gcloud auth application-default login`
+//! 3. the metadata server (GCE, Cloud Run, GKE workload identity)
+
+use crate::config::GcpConfig;
+use anyhow::{Context, Result};
+use base64::engine::general_purpose::{STANDARD as BASE64, URL_SAFE_NO_PAD as BASE64_URL};
+use base64::Engine as _;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Default Secret Manager API endpoint.
+pub const DEFAULT_ENDPOINT: &str = "https://secretmanager.googleapis.com";
+
+/// Environment variable naming a credentials file.
+pub const CREDENTIALS_ENV: &str = "GOOGLE_APPLICATION_CREDENTIALS";
+
+/// OAuth scope requested for the access token.
+const SCOPE: &str = "https://www.googleapis.com/auth/cloud-platform";
+
+/// Token endpoint used when the credentials file does not name one.
+const DEFAULT_TOKEN_URI: &str = "https://oauth2.googleapis.com/token";
+
+/// Metadata server token endpoint (GCE, Cloud Run, GKE).
+const METADATA_TOKEN_URL: &str =
+    "http://metadata.google.internal/computeMetadata/v1/instance/service-accounts/default/token";
+
+/// Name of the source in provenance output.
+pub fn source_name(config: &GcpConfig) -> String {
+    format!(
+        "gcp:projects/{}/secrets/{}/versions/{}",
+        config.project, config.secret, config.version
+    )
+}
+
+/// URL of the `versions.access` call for the configured secret.
+pub fn access_url(config: &GcpConfig) -> String {
+    format!(
+        "{}/v1/projects/{}/secrets/{}/versions/{}:access",
+        config
+            .endpoint
+            .as_deref()
+            .unwrap_or(DEFAULT_ENDPOINT)
+            .trim_end_matches('/'),
+        config.project,
+        config.secret,
+        config.version
+    )
+}
+
+/// Fetch the key/value pairs of the configured secret version.
+pub fn fetch(config: &GcpConfig) -> Result<HashMap<String, String>> {
+    let token = access_token()?;
+
+    let mut request = ureq::get(&access_url(config))
+        .set("Authorization", &format!("Bearer {}", token.token));
+    if let Some(project) = &token.quota_project {
+        request = request.set("x-goog-user-project", project);
+    }
+
+    let response = request.call().map_err(|e| match e {
+        ureq::Error::Status(403, _) => anyhow::anyhow!(
+            "GCP denied access to {} (needs roles/secretmanager.secretAccessor)",
+            source_name(config)
+        ),
+        ureq::Error::Status(404, _) => anyhow::anyhow!(
+            "GCP Secret Manager has no {} (check project, secret and version)",
+            source_name(config)
+        ),
+        other => anyhow::anyhow!("GCP Secret Manager request failed: {}", other),
+    })?;
+    let body: serde_json::Value = response
+        .into_json()
+        .context("Failed to parse GCP Secret Manager response")?;
+
+    let data = body
+        .pointer("/payload/data")
+        .and_then(|data| data.as_str())
+        .context("GCP Secret Manager response has no payload")?;
+    let payload = BASE64
+        .decode(data)
+        .context("GCP Secret Manager payload is not valid base64")?;
+
+    parse_payload(&payload)
+}
+
+/// Parse a secret payload: a JSON object, or dotenv lines.
+pub fn parse_payload(payload: &[u8]) -> Result<HashMap<String, String>> {
+    let text = std::str::from_utf8(payload).context("GCP secret payload is not valid UTF-8")?;
+
+    if text.trim_start().starts_with('{') {
+        let value: serde_json::Value =
+            serde_json::from_str(text).context("Failed to parse GCP secret payload as JSON")?;
+        let object = value
+            .as_object()
+            .context("GCP secret payload must be a JSON object of key/value pairs")?;
+        return crate::vault::flat_json_secrets(object, "GCP Secret Manager");
+    }
+
+    crate::vault::parse_env(payload)
+}
+
+/// Fields of an ADC file (`service_account` or `authorized_user`).
+#[derive(Debug, Deserialize)]
+struct CredentialsFile {
+    #[serde(rename = "type")]
+    kind: String,
+    client_id: Option<String>,
+    client_secret: Option<String>,
+    refresh_token: Option<String>,
+    client_email: Option<String>,
+    private_key: Option<String>,
+    token_uri: Option<String>,
+    quota_project_id: Option<String>,
+}
+
+/// An OAuth access token and the project billed for quota, if any.
+pub(crate) struct AccessToken {
+    pub(crate) token: String,
+    quota_project: Option<String>,
+}
+
+/// Obtain an access token from Application Default Credentials.
+pub(crate) fn access_token() -> Result<AccessToken> {
+    let Some(path) = credentials_path()? else {
+        return metadata_token().map(|token| AccessToken {
+            token,
+            quota_project: None,
+        });
+    };
+
+    let content = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read GCP credentials: {:?}", path))?;
+    let credentials: CredentialsFile = serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse GCP credentials: {:?}", path))?;
+    let token_uri = credentials.token_uri.as_deref().unwrap_or(DEFAULT_TOKEN_URI);
+
+    let form: Vec<(&str, String)> = match credentials.kind.as_str() {
+        "authorized_user" => vec![
+            ("grant_type", "refresh_token".to_string()),
+            ("client_id", required(&credentials.client_id, "client_id")?),
+            ("client_secret", required(&credentials.client_secret, "client_secret")?),
+            ("refresh_token", required(&credentials.refresh_token, "refresh_token")?),
+        ],
+        "service_account" => vec![
+            ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer".to_string()),
+            (
+                "assertion",
+                service_account_assertion(&credentials, token_uri, chrono::Utc::now().timestamp())?,
+            ),
+        ],
+        other => anyhow::bail!(
+            "Unsupported GCP credentials type '{}' in {:?} (use a service account key or 'gcloud auth application-default login')",
+            other,
+            path
+        ),
+    };
+
+    let form: Vec<(&str, &str)> = form.iter().map(|(key, value)| (*key, value.as_str())).collect();
+    let response = ureq::post(token_uri)
+        .send_form(&form)
+        .map_err(|e| anyhow::anyhow!("Failed to obtain a GCP access token: {}", e))?;
+
+    Ok(AccessToken {
+        token: token_from_response(response)?,
+        quota_project: credentials.quota_project_id,
+    })
+}
+
+fn required(field: &Option<String>, name: &str) -> Result<String> {
+    field
+        .clone()
+        .with_context(|| format!("GCP credentials file has no '{}'", name))
+}
+
+/// The ADC file to use, or `None` to ask the metadata server.
+fn credentials_path() -> Result<Option<PathBuf>> {
+    if let Some(path) = std::env::var_os(CREDENTIALS_ENV) {
+        let path = PathBuf::from(path);
+        if !path.exists() {
+            anyhow::bail!("${} points to a missing file: {:?}", CREDENTIALS_ENV, path);
+        }
+        return Ok(Some(path));
+    }
+
+    Ok(well_known_credentials_path().filter(|path| path.exists()))
+}
+
+/// File written by `gcloud auth application-default login`.
+fn well_known_credentials_path() -> Option<PathBuf> {
+    let gcloud_dir = match std::env::var_os("CLOUDSDK_CONFIG") {
+        Some(dir) => PathBuf::from(dir),
+        None if cfg!(windows) => crate::paths::user_config_dir()?.join("gcloud"),
+        None => crate::paths::home_dir()?.join(".config").join("gcloud"),
+    };
+    Some(gcloud_dir.join("application_default_credentials.json"))
+}
+
+/// Signed JWT exchanged for a service account access token (RS256).
+fn service_account_assertion(credentials: &CredentialsFile, token_uri: &str, now: i64) -> Result<String> {
+    let client_email = required(&credentials.client_email, "client_email")?;
+    let private_key = required(&credentials.private_key, "private_key")?;
+
+    let header = serde_json::json!({ "alg": "RS256", "typ": "JWT" });
+    let claims = serde_json::json!({
+        "iss": client_email,
+        "scope": SCOPE,
+        "aud": token_uri,
+        "iat": now,
+        "exp": now + 3600,
+    });
+    let signing_input = format!(
+        "{}.{}",
+        BASE64_URL.encode(header.to_string()),
+        BASE64_URL.encode(claims.to_string())
+    );
+
+    let der: String = private_key
+        .lines()
+        .filter(|line| !line.starts_with("-----"))
+        .collect();
+    let der = BASE64
+        .decode(der.trim())
+        .context("Service account private key is not valid PEM")?;
+    let key_pair = ring::signature::RsaKeyPair::from_pkcs8(&der)
+        .map_err(|e| anyhow::anyhow!("Invalid service account private key: {}", e))?;
+
+    let mut signature = vec![0; key_pair.public().modulus_len()];
+    key_pair
+        .sign(
+            &ring::signature::RSA_PKCS1_SHA256,
+            &ring::rand::SystemRandom::new(),
+            signing_input.as_bytes(),
+            &mut signature,
+        )
+        .map_err(|_| anyhow::anyhow!("Failed to sign service account assertion"))?;
+
+    Ok(format!("{}.{}", signing_input, BASE64_URL.encode(signature)))
+}
+
+/// Token of the instance's service account, from the metadata server.
+fn metadata_token() -> Result<String> {
+    let agent = ureq::AgentBuilder::new()
+        .timeout(Duration::from_secs(2))
+        .build();
+    let response = agent
+        .get(METADATA_TOKEN_URL)
+        .set("Metadata-Flavor", "Google")
+        .call()
+        .map_err(|_| {
+            anyhow::anyhow!(
+                "No GCP Application Default Credentials found: run 'gcloud auth application-default login' or set ${}",
+                CREDENTIALS_ENV
+            )
+        })?;
+
+    token_from_response(response)
+}
+
+fn token_from_response(response: ureq::Response) -> Result<String> {
+    let body: serde_json::Value = response
+        .into_json()
+        .context("Failed to parse GCP token response")?;
+    body.get("access_token")
+        .and_then(|token| token.as_str())
+        .map(str::to_string)
+        .context("GCP token response has no access_token")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+
+    fn config(endpoint: Option<String>) -> GcpConfig {
+        GcpConfig {
+            project: "my-project".to_string(),
+            secret: "myapp-dev".to_string(),
+            version: "latest".to_string(),
+            endpoint,
+        }
+    }
+
+    #[test]
+    fn test_access_url() {
+        assert_eq!(
+            access_url(&config(None)),
+            "https://secretmanager.googleapis.com/v1/projects/my-project/secrets/myapp-dev/versions/latest:access"
+        );
+        assert_eq!(
+            source_name(&config(None)),
+            "gcp:projects/my-project/secrets/myapp-dev/versions/latest"
+        );
+    }
+
+    #[test]
+    fn test_parse_payload() {
+        let json = parse_payload(br#"{"API_KEY":"sk_test_123","PORT":5432}"#).unwrap();
+        assert_eq!(json["API_KEY"], "sk_test_123");
+        assert_eq!(json["PORT"], "5432");
+
+        let dotenv = parse_payload(b"API_KEY=sk_test_123\nDEBUG=1\n").unwrap();
+        assert_eq!(dotenv["DEBUG"], "1");
+
+        assert!(parse_payload(b"{not json").is_err());
+    }
+
+    /// Serve one HTTP request, answering `body`; returns the request line,
+    /// headers and body.
+    fn serve_one(listener: &std::net::TcpListener, body: &str) -> (Vec<String>, String) {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut head = Vec::new();
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            if line.trim().is_empty() {
+                break;
+            }
+            head.push(line.trim().to_string());
+        }
+        let length = head
+            .iter()
+            .find_map(|line| line.to_ascii_lowercase().strip_prefix("content-length: ").map(str::to_string))
+            .map(|length| length.parse().unwrap())
+            .unwrap_or(0);
+        let mut request_body = vec![0; length];
+        reader.read_exact(&mut request_body).unwrap();
+
+        write!(
+            &stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )
+        .unwrap();
+        (head, String::from_utf8(request_body).unwrap())
+    }
+
+    #[test]
+    fn test_fetch_with_authorized_user_credentials() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let credentials = temp_dir.path().join("adc.json");
+        std::fs::write(
+            &credentials,
+            serde_json::json!({
+                "type": "authorized_user",
+                "client_id": "id.apps.googleusercontent.com",
+                "client_secret": "client-secret",
+                "refresh_token": "refresh-token",
+                "token_uri": format!("{}/token", base),
+                "quota_project_id": "billing-project",
+            })
+            .to_string(),
+        )
+        .unwrap();
+
+        let server = std::thread::spawn(move || {
+            let token = serve_one(&listener, r#"{"access_token":"ya29.test","expires_in":3599}"#);
+            let payload = BASE64.encode(r#"{"API_KEY":"sk_from_gcp"}"#);
+            let access = serve_one(&listener, &format!(r#"{{"payload":{{"data":"{}"}}}}"#, payload));
+            (token, access)
+        });
+
+        std::env::set_var(CREDENTIALS_ENV, &credentials);
+        let secrets = fetch(&config(Some(base))).unwrap();
+        std::env::remove_var(CREDENTIALS_ENV);
+        let ((token_head, token_body), (access_head, _)) = server.join().unwrap();
+
+        assert_eq!(secrets["API_KEY"], "sk_from_gcp");
+        assert_eq!(token_head[0], "POST /token HTTP/1.1");
+        assert!(token_body.contains("grant_type=refresh_token"));
+        assert_eq!(
+            access_head[0],
+            "GET /v1/projects/my-project/secrets/myapp-dev/versions/latest:access HTTP/1.1"
+        );
+        let headers: Vec<String> = access_head.iter().map(|line| line.to_ascii_lowercase()).collect();
+        assert!(headers.contains(&"authorization: bearer ya29.test".to_string()));
+        assert!(headers.contains(&"x-goog-user-project: billing-project".to_string()));
+    }
+}
@@ -0,0 +1,243 @@
+//! HashiCorp Vault backend (`vault.engine: hashicorp`).
+//!
+//! Secrets are read from one KV v2 secret over the HTTP API: every field of
+//! the secret becomes a vault key, then injection and restore work exactly
+//! as with SOPS files. Responses are parsed in memory and never written to
+//! disk.
+
+use crate::config::{HashicorpAuth, HashicorpConfig};
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+
+/// Server address used when the config has none.
+pub const ADDR_ENV: &str = "VAULT_ADDR";
+
+/// Token used by `auth: {method: token}` unless `token_env` says otherwise.
+pub const TOKEN_ENV: &str = "VAULT_TOKEN";
+
+/// Namespace used when the config has none.
+pub const NAMESPACE_ENV: &str = "VAULT_NAMESPACE";
+
+/// AppRole secret ID unless `secret_id_env` says otherwise.
+pub const SECRET_ID_ENV: &str = "VAULT_SECRET_ID";
+
+/// Default mount point of the AppRole auth method.
+pub const DEFAULT_APPROLE_MOUNT: &str = "approle";
+
+/// Server address from the config, else `$VAULT_ADDR`.
+pub fn address(config: &HashicorpConfig) -> Result<String> {
+    config
+        .address
+        .clone()
+        .or_else(|| std::env::var(ADDR_ENV).ok())
+        .filter(|address| !address.is_empty())
+        .map(|address| address.trim_end_matches('/').to_string())
+        .with_context(|| format!("No HashiCorp Vault address: set vault.hashicorp.address or ${}", ADDR_ENV))
+}
+
+/// Read URL of a KV v2 secret (`GET /v1/<mount>/data/<path>`).
+pub fn kv2_url(address: &str, mount: &str, path: &str) -> String {
+    format!(
+        "{}/v1/{}/data/{}",
+        address.trim_end_matches('/'),
+        mount.trim_matches('/'),
+        path.trim_matches('/')
+    )
+}
+
+/// Name of the source in provenance output (`hashicorp:<mount>/<path>`).
+pub fn source_name(config: &HashicorpConfig) -> String {
+    format!(
+        "hashicorp:{}/{}",
+        config.mount.trim_matches('/'),
+        config.path.trim_matches('/')
+    )
+}
+
+/// Fetch the fields of the configured secret.
+pub fn fetch(config: &HashicorpConfig) -> Result<HashMap<String, String>> {
+    let address = address(config)?;
+    let namespace = config
+        .namespace
+        .clone()
+        .or_else(|| std::env::var(NAMESPACE_ENV).ok())
+        .filter(|namespace| !namespace.is_empty());
+    let token = token(config, &address, namespace.as_deref())?;
+
+    let url = kv2_url(&address, &config.mount, &config.path);
+    let mut request = ureq::get(&url).set("X-Vault-Token", &token);
+    if let Some(namespace) = &namespace {
+        request = request.set("X-Vault-Namespace", namespace);
+    }
+
+    let response = request
+        .call()
+        .map_err(|e| request_error(e, &source_name(config)))?;
+    let body: serde_json::Value = response
+        .into_json()
+        .context("Failed to parse HashiCorp Vault response")?;
+
+    parse_kv2(&body)
+}
+
+/// Obtain a token for the configured auth method.
+fn token(config: &HashicorpConfig, address: &str, namespace: Option<&str>) -> Result<String> {
+    match &config.auth {
+        HashicorpAuth::Token { token_env } => {
+            let var = token_env.as_deref().unwrap_or(TOKEN_ENV);
+            if let Ok(token) = std::env::var(var) {
+                if !token.is_empty() {
+                    return Ok(token);
+                }
+            }
+
+            // Written by `vault login`
+            crate::paths::home_dir()
+                .map(|home| home.join(".vault-token"))
+                .and_then(|path| std::fs::read_to_string(path).ok())
+                .map(|token| token.trim().to_string())
+                .filter(|token| !token.is_empty())
+                .with_context(|| format!("No HashiCorp Vault token: set ${} or run 'vault login'", var))
+        }
+        HashicorpAuth::AppRole {
+            role_id,
+            secret_id_env,
+            mount,
+        } => {
+            let var = secret_id_env.as_deref().unwrap_or(SECRET_ID_ENV);
+            let secret_id = std::env::var(var)
+                .with_context(|| format!("AppRole login needs the secret ID in ${}", var))?;
+            let mount = mount.as_deref().unwrap_or(DEFAULT_APPROLE_MOUNT);
+
+            let url = format!("{}/v1/auth/{}/login", address, mount.trim_matches('/'));
+            let mut request = ureq::post(&url);
+            if let Some(namespace) = namespace {
+                request = request.set("X-Vault-Namespace", namespace);
+            }
+            let response = request
+                .send_json(serde_json::json!({ "role_id": role_id, "secret_id": secret_id }))
+                .map_err(|e| request_error(e, &format!("auth/{}/login", mount)))?;
+            let body: serde_json::Value = response
+                .into_json()
+                .context("Failed to parse HashiCorp Vault login response")?;
+
+            body.pointer("/auth/client_token")
+                .and_then(|token| token.as_str())
+                .map(str::to_string)
+                .context("HashiCorp Vault login response has no client token")
+        }
+    }
+}
+
+/// Turn a failed request into an actionable error.
+fn request_error(error: ureq::Error, what: &str) -> anyhow::Error {
+    match error {
+        ureq::Error::Status(403, _) => anyhow::anyhow!(
+            "HashiCorp Vault denied access to {} (invalid token or missing policy)",
+            what
+        ),
+        ureq::Error::Status(404, _) => anyhow::anyhow!(
+            "HashiCorp Vault has no secret at {} (check mount and path; only KV v2 is supported)",
+            what
+        ),
+        other => anyhow::anyhow!("HashiCorp Vault request for {} failed: {}", what, other),
+    }
+}
+
+/// Extract the fields of a KV v2 read response (`data.data`).
+///
+/// Numbers and booleans are converted to strings; nested values are
+/// rejected, as in JSON vault files.
+pub fn parse_kv2(body: &serde_json::Value) -> Result<HashMap<String, String>> {
+    let data = body
+        .pointer("/data/data")
+        .and_then(|data| data.as_object())
+        .context("HashiCorp Vault response has no KV v2 data (is the mount a KV v2 engine?)")?;
+
+    crate::vault::flat_json_secrets(data, "HashiCorp Vault")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::io::{BufRead, BufReader, Write};
+
+    fn config(address: &str) -> HashicorpConfig {
+        HashicorpConfig {
+            address: Some(address.to_string()),
+            mount: "secret".to_string(),
+            path: "/myapp/dev/".to_string(),
+            namespace: None,
+            auth: HashicorpAuth::Token {
+                token_env: Some("SHADOW_SECRET_TEST_HASHICORP_TOKEN".to_string()),
+            },
+        }
+    }
+
+    #[test]
+    fn test_kv2_url_and_source_name() {
+        assert_eq!(
+            kv2_url("https://vault.example.com:8200/", "secret", "/myapp/dev/"),
+            "https://vault.example.com:8200/v1/secret/data/myapp/dev"
+        );
+        assert_eq!(source_name(&config("http://x")), "hashicorp:secret/myapp/dev");
+    }
+
+    #[test]
+    fn test_parse_kv2() {
+        let body = json!({
+            "data": {
+                "data": { "API_KEY": "sk_test_123", "PORT": 5432, "DEBUG": true },
+                "metadata": { "version": 3 }
+            }
+        });
+        let secrets = parse_kv2(&body).unwrap();
+        assert_eq!(secrets["API_KEY"], "sk_test_123");
+        assert_eq!(secrets["PORT"], "5432");
+        assert_eq!(secrets["DEBUG"], "true");
+
+        assert!(parse_kv2(&json!({ "data": { "data": { "NESTED": {} } } })).is_err());
+        assert!(parse_kv2(&json!({ "data": { "API_KEY": "kv v1" } })).is_err());
+    }
+
+    #[test]
+    fn test_fetch_sends_token_and_reads_secret() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = format!("http://{}", listener.local_addr().unwrap());
+
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut head = Vec::new();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+                head.push(line.trim().to_string());
+            }
+
+            let body = json!({ "data": { "data": { "API_KEY": "sk_from_vault" } } }).to_string();
+            write!(
+                &stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            )
+            .unwrap();
+            head
+        });
+
+        std::env::set_var("SHADOW_SECRET_TEST_HASHICORP_TOKEN", "hvs.test");
+        let secrets = fetch(&config(&address)).unwrap();
+        let head = server.join().unwrap();
+
+        assert_eq!(secrets["API_KEY"], "sk_from_vault");
+        assert_eq!(head[0], "GET /v1/secret/data/myapp/dev HTTP/1.1");
+        assert!(head
+            .iter()
+            .any(|line| line.eq_ignore_ascii_case("x-vault-token: hvs.test")));
+    }
+}
@@ -0,0 +1,233 @@
+//! What a project's targets hold right now, and whether its vault and key
+//! are usable (`shadow-secret status`).
+//!
+//! Nothing is decrypted. A target's state is read from its placeholders:
+//! a file still showing the placeholders it lists is a template, one
+//! showing none of them has secrets injected. For `$ALL` targets the
+//! vault's key names (readable without the key, SOPS only encrypts values)
+//! stand in for the list. Leftover crash-recovery journals (see
+//! [`crate::journal`]) tell a running session from secrets left behind by
+//! one that died.
+
+use crate::config::{Config, TargetConfig};
+use crate::injector::{extract_key_name, find_placeholders, is_all_placeholder};
+use anyhow::Result;
+use std::collections::BTreeSet;
+use std::path::Path;
+
+/// What a target holds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TargetState {
+    /// Its placeholders are in place
+    Template,
+    /// None of its placeholders is left: secrets are injected
+    Injected,
+    /// Some listed placeholders are left, others were replaced
+    Partial {
+        /// Keys whose placeholders are still in the file
+        left: Vec<String>,
+    },
+    /// The file does not exist
+    Missing,
+    /// Nothing tells: no placeholder is listed or known
+    Unknown,
+}
+
+impl std::fmt::Display for TargetState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TargetState::Template => write!(f, "template"),
+            TargetState::Injected => write!(f, "secrets injected"),
+            TargetState::Partial { left } => write!(f, "partially injected ({} left)", left.join(", ")),
+            TargetState::Missing => write!(f, "missing"),
+            TargetState::Unknown => write!(f, "unknown (no placeholders to look for)"),
+        }
+    }
+}
+
+/// One vault or key check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HealthCheck {
+    pub ok: bool,
+    pub message: String,
+}
+
+impl HealthCheck {
+    fn ok(message: String) -> Self {
+        Self { ok: true, message }
+    }
+
+    fn problem(message: String) -> Self {
+        Self { ok: false, message }
+    }
+}
+
+/// State of `target` given the content of its file, `vault_keys` standing
+/// for `$ALL`.
+pub fn state_of(target: &TargetConfig, content: &str, vault_keys: &BTreeSet<String>) -> Result<TargetState> {
+    let referenced: BTreeSet<String> = match target.style()? {
+        Some(style) => style.referenced_keys(content),
+        None => find_placeholders(content).into_iter().map(|hit| hit.key).collect(),
+    };
+
+    let listed: BTreeSet<String> = target
+        .placeholders
+        .iter()
+        .filter(|placeholder| !is_all_placeholder(placeholder))
+        .map(|placeholder| extract_key_name(placeholder).to_string())
+        .collect();
+    let all: BTreeSet<&String> = if target.placeholders.iter().any(|p| is_all_placeholder(p)) {
+        vault_keys
+            .iter()
+            .filter(|key| target.keys.is_empty() || target.keys.contains(key))
+            .collect()
+    } else {
+        BTreeSet::new()
+    };
+    if listed.is_empty() && all.is_empty() {
+        return Ok(TargetState::Unknown);
+    }
+
+    let left: Vec<String> = listed
+        .iter()
+        .chain(all)
+        .filter(|key| referenced.contains(*key))
+        .cloned()
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    let replaced = listed.iter().any(|key| !referenced.contains(key));
+
+    Ok(match (left.is_empty(), replaced) {
+        (true, _) => TargetState::Injected,
+        (false, true) => TargetState::Partial { left },
+        (false, false) => TargetState::Template,
+    })
+}
+
+/// State of `target`, read from its file.
+pub fn target_state(target: &TargetConfig, vault_keys: &BTreeSet<String>) -> Result<TargetState> {
+    let path = Path::new(&target.path);
+    if !path.exists() {
+        return Ok(TargetState::Missing);
+    }
+    let content = std::fs::read_to_string(path)?;
+    state_of(target, &content, vault_keys)
+}
+
+/// Check the vault files of `config`.
+///
+/// # Returns
+///
+/// The checks, the key names of the vaults and their age recipients.
+pub fn vault_health(
+    config: &Config,
+    config_dir: &Path,
+) -> Result<(Vec<HealthCheck>, BTreeSet<String>, BTreeSet<String>)> {
+    let (mut checks, mut keys, mut recipients) = (Vec::new(), BTreeSet::new(), BTreeSet::new());
+    if crate::vault::is_remote_engine(&config.vault.engine) {
+        checks.push(HealthCheck::ok(format!(
+            "Secrets come from {} (not checked without fetching them)",
+            config.vault.engine
+        )));
+        return Ok((checks, keys, recipients));
+    }
+
+    for path in config.vault_source_paths(config_dir)? {
+        let encrypted = match std::fs::read_to_string(&path) {
+            Ok(encrypted) => encrypted,
+            Err(e) => {
+                checks.push(HealthCheck::problem(format!("{}: {}", path.display(), e)));
+                continue;
+            }
+        };
+        let file_recipients = crate::fsck::vault_recipients(&encrypted);
+        match crate::age_native::key_names(&path) {
+            Ok(names) => {
+                checks.push(HealthCheck::ok(format!(
+                    "{}: {} key(s), {} age recipient(s)",
+                    path.display(),
+                    names.len(),
+                    file_recipients.len()
+                )));
+                keys.extend(names);
+            }
+            Err(e) => checks.push(HealthCheck::problem(format!("{}: {:#}", path.display(), e))),
+        }
+        recipients.extend(file_recipients);
+    }
+    Ok((checks, keys, recipients))
+}
+
+/// Check the age key of `config` against the vaults' `recipients`.
+pub fn key_health(config: &Config, recipients: &BTreeSet<String>) -> Vec<HealthCheck> {
+    if crate::vault::is_remote_engine(&config.vault.engine) {
+        return Vec::new();
+    }
+
+    let (location, keys) = match crate::age_native::identity_source(config.vault.age_key_path.as_deref()) {
+        Ok(crate::age_native::IdentitySource::File(path)) => match std::fs::read(&path) {
+            Ok(content) if crate::age_native::is_passphrase_protected(&content) => {
+                return vec![HealthCheck::ok(format!(
+                    "{}: passphrase protected (not opened)",
+                    path.display()
+                ))];
+            }
+            Ok(content) => (path.display().to_string(), String::from_utf8_lossy(&content).to_string()),
+            Err(e) => return vec![HealthCheck::problem(format!("{}: {}", path.display(), e))],
+        },
+        Ok(crate::age_native::IdentitySource::Inline(keys)) => ("SOPS_AGE_KEY".to_string(), keys),
+        Err(e) => return vec![HealthCheck::problem(format!("{:#}", e))],
+    };
+
+    let public_keys: Vec<String> = keys
+        .lines()
+        .map(str::trim)
+        .filter(|line| line.starts_with("AGE-SECRET-KEY-"))
+        .filter_map(|line| line.parse::<age::x25519::Identity>().ok())
+        .map(|identity| identity.to_public().to_string())
+        .collect();
+
+    if public_keys.is_empty() {
+        vec![HealthCheck::problem(format!("{}: no age identity found", location))]
+    } else if recipients.is_empty() || public_keys.iter().any(|key| recipients.contains(key)) {
+        vec![HealthCheck::ok(format!("{}: {} identity(ies)", location, public_keys.len()))]
+    } else {
+        vec![HealthCheck::problem(format!(
+            "{}: none of its identities is a recipient of the vault",
+            location
+        ))]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(placeholders: &[&str]) -> TargetConfig {
+        TargetConfig {
+            name: "app".to_string(),
+            path: ".env".to_string(),
+            placeholders: placeholders.iter().map(|p| p.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_state_from_placeholders() {
+        let none = BTreeSet::new();
+        let listed = target(&["$API_KEY", "$DB_URL"]);
+        assert_eq!(state_of(&listed, "API_KEY=$API_KEY\nDB_URL=${DB_URL}\n", &none).unwrap(), TargetState::Template);
+        assert_eq!(state_of(&listed, "API_KEY=sk_1\nDB_URL=pg://\n", &none).unwrap(), TargetState::Injected);
+        assert_eq!(
+            state_of(&listed, "API_KEY=sk_1\nDB_URL=$DB_URL\n", &none).unwrap(),
+            TargetState::Partial { left: vec!["DB_URL".to_string()] }
+        );
+
+        let vault_keys = BTreeSet::from(["API_KEY".to_string(), "UNUSED".to_string()]);
+        let all = target(&["$ALL"]);
+        assert_eq!(state_of(&all, "KEY=$API_KEY\nPATH=$HOME\n", &vault_keys).unwrap(), TargetState::Template);
+        assert_eq!(state_of(&all, "KEY=sk_1\nPATH=$HOME\n", &vault_keys).unwrap(), TargetState::Injected);
+        assert_eq!(state_of(&all, "KEY=sk_1\n", &none).unwrap(), TargetState::Unknown);
+    }
+}
@@ -3,10 +3,12 @@
 //! This module handles the `init-project` command, which automates the setup of
 //! secret management infrastructure for a new project.
 
+use crate::diff::line_diff;
+use crate::profile::OrgProfile;
 use anyhow::{Context, Result};
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use crate::process;
 
 /// Age key components extracted from key file
 #[derive(Debug, Clone)]
@@ -26,6 +28,10 @@ pub struct InitConfig {
     pub create_example: bool,
     /// Whether to prompt for global config addition
     pub prompt_global: bool,
+    /// Organization defaults to apply (from `--profile`)
+    pub profile: Option<OrgProfile>,
+    /// Overwrite existing `.sops.yaml`, `.enc.env` and `project.yaml`
+    pub force: bool,
 }
 
 impl Default for InitConfig {
@@ -34,6 +40,38 @@ impl Default for InitConfig {
             master_key_path: get_default_master_key_path(),
             create_example: true,
             prompt_global: true,
+            profile: None,
+            force: false,
+        }
+    }
+}
+
+/// Files `init-project` writes, relative to the project directory.
+const PROJECT_FILES: [&str; 3] = [".sops.yaml", ".enc.env", "project.yaml"];
+
+/// Files `init-global` overwrites, relative to the global directory
+/// (`global.enc.env` is kept when it exists).
+const GLOBAL_FILES: [&str; 2] = [".sops.yaml", "global.yaml"];
+
+/// Recipient shown in plans when init would generate a new age key.
+const NEW_KEY_PLACEHOLDER: &str = "<new age public key>";
+
+/// A file init would write, as shown by `--plan`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedFile {
+    pub path: PathBuf,
+    /// Content written (before encryption for encrypted files)
+    pub content: String,
+    /// Whether the file is encrypted with SOPS once written
+    pub encrypted: bool,
+}
+
+impl PlannedFile {
+    fn plain(path: PathBuf, content: String) -> Self {
+        Self {
+            path,
+            content,
+            encrypted: false,
         }
     }
 }
@@ -59,9 +97,13 @@ pub fn get_default_master_key_path() -> PathBuf {
         }
     }
 
-    // Default: ~/.shadow-secret/keys.txt
-    let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
-    home.join(".shadow-secret").join("keys.txt")
+    // Default: ~/.shadow-secret/keys.txt, in the config directory without a home
+    match crate::paths::home_dir() {
+        Some(home) => home.join(".shadow-secret").join("keys.txt"),
+        None => crate::paths::config_dir()
+            .unwrap_or_else(|_| PathBuf::from("."))
+            .join("keys.txt"),
+    }
 }
 
 /// Extract age keypair from a key file.
@@ -116,10 +158,10 @@ pub fn extract_age_keypair(path: &Path) -> Result<AgeKeyPair> {
 
 /// Generate a new age keypair using age-keygen.
 pub fn generate_age_keypair(output_path: &Path) -> Result<AgeKeyPair> {
-    println!("🔐 Generating new age keypair...");
+    crate::say!("🔐 Generating new age keypair...");
 
     // Check if age is installed
-    let check = Command::new("age").arg("--version").output();
+    let check = process::output(process::command("age")?.arg("--version"));
 
     match check {
         Ok(output) if output.status.success() => {
@@ -145,10 +187,7 @@ pub fn generate_age_keypair(output_path: &Path) -> Result<AgeKeyPair> {
     }
 
     // Run age-keygen
-    let output = Command::new("age-keygen")
-        .arg("-o")
-        .arg(output_path)
-        .output()
+    let output = process::output(process::command("age-keygen")?.arg("-o").arg(output_path))
         .with_context(|| "Failed to execute age-keygen")?;
 
     if !output.status.success() {
@@ -163,7 +202,7 @@ pub fn generate_age_keypair(output_path: &Path) -> Result<AgeKeyPair> {
         ));
     }
 
-    println!("✓ Keypair generated at: {:?}", output_path);
+    crate::say!("✓ Keypair generated at: {:?}", output_path);
 
     // Extract the keypair from the generated file
     extract_age_keypair(output_path)
@@ -171,20 +210,35 @@ pub fn generate_age_keypair(output_path: &Path) -> Result<AgeKeyPair> {
 
 /// Create .sops.yaml configuration file.
 pub fn create_sops_config(project_dir: &Path, public_key: &str) -> Result<PathBuf> {
-    let config_path = project_dir.join(".sops.yaml");
+    create_sops_config_for_recipients(project_dir, &[public_key.to_string()])
+}
 
-    let config_content = format!(
+/// Content of the project `.sops.yaml` encrypting for `recipients`.
+fn sops_config_content(recipients: &[String]) -> String {
+    format!(
         r#"# SOPS configuration for shadow-secret
 # This file was auto-generated by: shadow-secret init-project
 
 creation_rules:
   - path_regex: .*\.enc\.env$
-    age: "{}" # Age public key for encryption
+    age: "{}" # Age public key(s) for encryption
 
 # For more information, see: https://github.com/getsops/sops
 "#,
-        public_key
-    );
+        recipients.join(",")
+    )
+}
+
+/// Create .sops.yaml configuration file encrypting for several age recipients.
+///
+/// The first recipient is the local master key; additional ones usually come
+/// from an organization profile.
+pub fn create_sops_config_for_recipients(
+    project_dir: &Path,
+    recipients: &[String],
+) -> Result<PathBuf> {
+    let config_path = project_dir.join(".sops.yaml");
+    let config_content = sops_config_content(recipients);
 
     fs::write(&config_path, config_content)
         .with_context(|| format!("Failed to write .sops.yaml to: {:?}", config_path))?;
@@ -192,11 +246,9 @@ creation_rules:
     Ok(config_path)
 }
 
-/// Create initial .enc.env file (plaintext before encryption).
-pub fn create_enc_env(project_dir: &Path, with_example: bool) -> Result<PathBuf> {
-    let enc_env_path = project_dir.join(".enc.env");
-
-    let content = if with_example {
+/// Plaintext of a new project `.enc.env`, before encryption.
+fn enc_env_content(with_example: bool) -> &'static str {
+    if with_example {
         r#"# Example secrets file (will be encrypted)
 # Replace placeholders with actual values after encryption
 
@@ -205,7 +257,13 @@ DATABASE_URL=PLACEHOLDER
 "#
     } else {
         "# Encrypted secrets file (empty for now)\n"
-    };
+    }
+}
+
+/// Create initial .enc.env file (plaintext before encryption).
+pub fn create_enc_env(project_dir: &Path, with_example: bool) -> Result<PathBuf> {
+    let enc_env_path = project_dir.join(".enc.env");
+    let content = enc_env_content(with_example);
 
     fs::write(&enc_env_path, content)
         .with_context(|| format!("Failed to write .enc.env to: {:?}", enc_env_path))?;
@@ -215,7 +273,34 @@ DATABASE_URL=PLACEHOLDER
 
 /// Create project.yaml configuration file for the project.
 pub fn create_project_config(project_dir: &Path, age_key_path: &Path) -> Result<PathBuf> {
-    let config_path = project_dir.join("project.yaml");
+    create_project_config_with_profile(project_dir, age_key_path, None)
+}
+
+/// Content of `project.yaml` (see [`create_project_config_with_profile`]).
+pub fn project_config_content(age_key_path: &Path, profile: Option<&OrgProfile>) -> Result<String> {
+    let targets_section = match profile {
+        Some(profile) if !profile.targets.is_empty() => {
+            let targets = serde_yaml::to_string(&profile.targets)
+                .with_context(|| "Failed to serialize profile targets")?;
+            format!(
+                "# Targets provided by the '{}' organization profile\ntargets:\n{}",
+                profile.name,
+                indent_yaml(&targets)
+            )
+        }
+        _ => r#"# Targets: Define where secrets should be injected
+# Each target needs:
+#   - name: A descriptive name for this target
+#   - path: Path to the file where secrets will be injected
+#   - placeholders: List of secret names to inject (use "$ALL" for all secrets)
+targets:
+  - name: "example-target"
+    path: ".env"
+    placeholders:
+      - "$ALL"
+"#
+        .to_string(),
+    };
 
     let config_content = format!(
         r#"# Shadow Secret Project Configuration
@@ -236,17 +321,7 @@ vault:
   # Whether to require vault mount (for VeraCrypt volumes)
   require_mount: false
 
-# Targets: Define where secrets should be injected
-# Each target needs:
-#   - name: A descriptive name for this target
-#   - path: Path to the file where secrets will be injected
-#   - placeholders: List of secret names to inject (use "$ALL" for all secrets)
-targets:
-  - name: "example-target"
-    path: ".env"
-    placeholders:
-      - "$ALL"
-
+{}
 # ================================
 # IMPORTANT: Configuration Required
 # ================================
@@ -274,21 +349,78 @@ targets:
 #         - "$API_KEY"
 #         - "$DATABASE_URL"
 "#,
-        age_key_path.display()
+        age_key_path.display(),
+        targets_section
     );
 
+    let config_content = match profile {
+        Some(profile) => format!("{}{}", config_content, render_profile_section(profile)?),
+        None => config_content,
+    };
+
+    Ok(config_content)
+}
+
+/// Create project.yaml, applying organization defaults when a profile is given.
+///
+/// Profile targets replace the generated example target, and the profile's
+/// identity, policies and naming conventions are recorded in the file so the
+/// whole team shares them.
+pub fn create_project_config_with_profile(
+    project_dir: &Path,
+    age_key_path: &Path,
+    profile: Option<&OrgProfile>,
+) -> Result<PathBuf> {
+    let config_path = project_dir.join("project.yaml");
+    let config_content = project_config_content(age_key_path, profile)?;
+
     fs::write(&config_path, config_content)
         .with_context(|| format!("Failed to write project.yaml to: {:?}", config_path))?;
 
     Ok(config_path)
 }
 
+/// Render the organization profile sections appended to project.yaml.
+fn render_profile_section(profile: &OrgProfile) -> Result<String> {
+    let mut section = String::from(
+        "\n# ================================\n\
+         # Organization Profile\n\
+         # ================================\n\
+         # Applied by: shadow-secret init-project --profile\n\
+         # Keep these sections in sync with your organization defaults.\n",
+    );
+
+    section.push_str("profile:\n");
+    section.push_str(&format!("  name: {:?}\n", profile.name));
+    section.push_str(&format!("  source: {:?}\n", profile.source));
+    if let Some(key_server) = &profile.key_server {
+        section.push_str(&format!("  key_server: {:?}\n", key_server));
+    }
+
+    for (name, value) in [("policies", &profile.policies), ("naming", &profile.naming)] {
+        if let Some(value) = value {
+            let yaml = serde_yaml::to_string(value)
+                .with_context(|| format!("Failed to serialize profile {}", name))?;
+            section.push_str(&format!("{}:\n{}", name, indent_yaml(&yaml)));
+        }
+    }
+
+    Ok(section)
+}
+
+/// Indent a YAML document by two spaces so it can be nested under a key.
+fn indent_yaml(yaml: &str) -> String {
+    yaml.lines()
+        .map(|line| format!("  {}\n", line))
+        .collect()
+}
+
 /// Encrypt .enc.env file using SOPS.
 pub fn encrypt_enc_env(enc_env_path: &Path) -> Result<()> {
-    println!("🔒 Encrypting .enc.env with SOPS...");
+    crate::say!("🔒 Encrypting .enc.env with SOPS...");
 
     // Check if SOPS is installed
-    let check = Command::new("sops").arg("--version").output();
+    let check = process::output(process::command("sops")?.arg("--version"));
 
     match check {
         Ok(output) if output.status.success() => {
@@ -315,13 +447,14 @@ pub fn encrypt_enc_env(enc_env_path: &Path) -> Result<()> {
         Path::new(".")
     };
 
-    let output = Command::new("sops")
+    let mut command = process::command("sops")?;
+    command
         .arg("--encrypt")
         .arg("--output")
         .arg(enc_env_path)  // Output to same file for in-place encryption
         .arg(enc_env_path)  // Input file
-        .current_dir(enc_dir)
-        .output()
+        .current_dir(enc_dir);
+    let output = process::output(&mut command)
         .with_context(|| "Failed to execute SOPS encryption")?;
 
     if !output.status.success() {
@@ -342,7 +475,7 @@ pub fn encrypt_enc_env(enc_env_path: &Path) -> Result<()> {
         ));
     }
 
-    println!("✓ .enc.env encrypted successfully");
+    crate::say!("✓ .enc.env encrypted successfully");
     Ok(())
 }
 
@@ -356,8 +489,8 @@ pub fn add_to_global_config(project_dir: &Path) -> Result<()> {
 
     // Check if global config exists
     if !global_config_path.exists() {
-        println!("⚠️  Global config not found at: {:?}", global_config_path);
-        println!("💡 Run 'shadow-secret init-global' first to create global config");
+        crate::say!("⚠️  Global config not found at: {:?}", global_config_path);
+        crate::say!("💡 Run 'shadow-secret init-global' first to create global config");
         return Ok(());
     }
 
@@ -365,137 +498,70 @@ pub fn add_to_global_config(project_dir: &Path) -> Result<()> {
     let content = fs::read_to_string(&global_config_path)
         .with_context(|| format!("Failed to read global config: {:?}", global_config_path))?;
 
+    let Some(yaml_content) = global_config_with_project(&content, project_dir)? else {
+        crate::say!("ℹ️  Project already in global config");
+        return Ok(());
+    };
+
+    // Write back
+    fs::write(&global_config_path, yaml_content)
+        .with_context(|| format!("Failed to write global config: {:?}", global_config_path))?;
+    crate::say!("✓ Added project to global config");
+
+    Ok(())
+}
+
+/// `global.yaml` content with `project_dir` added as a `$ALL` target, or
+/// `None` if the project is already one of its targets.
+fn global_config_with_project(content: &str, project_dir: &Path) -> Result<Option<String>> {
     // Parse YAML
-    let mut config: serde_yaml::Value = serde_yaml::from_str(&content)
+    let mut config: serde_yaml::Value = serde_yaml::from_str(content)
         .with_context(|| "Failed to parse global config YAML")?;
 
     // Add project to targets
     let project_path = project_dir.to_string_lossy().to_string();
+    let new_target = serde_yaml::Value::Mapping(serde_yaml::Mapping::from_iter([
+        (serde_yaml::Value::String("name".to_string()), serde_yaml::Value::String(
+            project_dir.file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string(),
+        )),
+        (serde_yaml::Value::String("path".to_string()), serde_yaml::Value::String(project_path.clone())),
+        (
+            serde_yaml::Value::String("placeholders".to_string()),
+            serde_yaml::Value::Sequence(vec![
+                serde_yaml::Value::String("$ALL".to_string()),
+            ]),
+        ),
+    ]));
 
     if let Some(targets) = config["targets"].as_sequence_mut() {
         // Check if already exists
-        for target in targets.iter() {
-            if target["path"].as_str() == Some(&project_path) {
-                println!("ℹ️  Project already in global config");
-                return Ok(());
-            }
+        if targets.iter().any(|target| target["path"].as_str() == Some(&project_path)) {
+            return Ok(None);
         }
-
-        // Add new target
-        let new_target = serde_yaml::Mapping::from_iter([
-            (serde_yaml::Value::String("name".to_string()), serde_yaml::Value::String(
-                project_dir.file_name()
-                    .unwrap_or_default()
-                    .to_string_lossy()
-                    .to_string(),
-            )),
-            (serde_yaml::Value::String("path".to_string()), serde_yaml::Value::String(project_path.clone())),
-            (
-                serde_yaml::Value::String("placeholders".to_string()),
-                serde_yaml::Value::Sequence(vec![
-                    serde_yaml::Value::String("$ALL".to_string()),
-                ]),
-            ),
-        ]);
-
-        targets.push(serde_yaml::Value::Mapping(new_target));
-
-        println!("✓ Added project to global config");
+        targets.push(new_target);
     } else {
         // Create targets array if it doesn't exist
-        let targets = serde_yaml::Value::Sequence(vec![
-            serde_yaml::Value::Mapping(serde_yaml::Mapping::from_iter([
-                (serde_yaml::Value::String("name".to_string()), serde_yaml::Value::String(
-                    project_dir
-                        .file_name()
-                        .unwrap_or_default()
-                        .to_string_lossy()
-                        .to_string(),
-                )),
-                (serde_yaml::Value::String("path".to_string()), serde_yaml::Value::String(project_path.clone())),
-                (
-                    serde_yaml::Value::String("placeholders".to_string()),
-                    serde_yaml::Value::Sequence(vec![serde_yaml::Value::String("$ALL".to_string())]),
-                ),
-            ])),
-        ]);
-
-        config["targets"] = targets;
+        config["targets"] = serde_yaml::Value::Sequence(vec![new_target]);
     }
 
-    // Write back
     let yaml_content = serde_yaml::to_string(&config)
         .with_context(|| "Failed to serialize global config")?;
-
-    fs::write(&global_config_path, yaml_content)
-        .with_context(|| format!("Failed to write global config: {:?}", global_config_path))?;
-
-    Ok(())
+    Ok(Some(yaml_content))
 }
 
 /// Global configuration directory path
+///
+/// `~/.config/shadow-secret` unless overridden, see [`crate::paths`].
 pub fn get_global_config_dir() -> Result<PathBuf> {
-    let home = dirs::home_dir()
-        .context("Failed to determine home directory")?;
-    Ok(home.join(".config").join("shadow-secret"))
+    crate::paths::config_dir()
 }
 
-/// Initialize global Shadow Secret configuration.
-///
-/// This creates:
-/// - ~/.config/shadow-secret/ directory
-/// - global.yaml (configuration file)
-/// - global.enc.env (encrypted secrets, created as empty file first)
-///
-/// The user can then move this directory to an encrypted drive for security.
-pub fn init_global() -> Result<()> {
-    println!("🌍 Shadow Secret Global Configuration Initialization");
-    println!();
-
-    // Step 1: Create global config directory
-    println!("📁 Step 1: Creating global configuration directory");
-    let global_dir = get_global_config_dir()?;
-
-    if global_dir.exists() {
-        println!("   ⚠️  Directory already exists: {:?}", global_dir);
-        print!("   Continue? [Y/n]: ");
-        use std::io::Write;
-        std::io::stdout().flush()?;
-
-        let mut input = String::new();
-        std::io::stdin().read_line(&mut input)?;
-
-        if input.trim().to_lowercase() == "n" {
-            return Ok(());
-        }
-    } else {
-        fs::create_dir_all(&global_dir)
-            .with_context(|| format!("Failed to create directory: {:?}", global_dir))?;
-        println!("   ✓ Created: {:?}", global_dir);
-    }
-    println!();
-
-    // Step 2: Check for or generate age keypair
-    println!("📝 Step 2: Age Encryption Key");
-    let default_key_path = get_default_master_key_path();
-
-    let keypair = if default_key_path.exists() {
-        println!("   ✓ Existing key found: {:?}", default_key_path);
-        extract_age_keypair(&default_key_path)?
-    } else {
-        println!("   ✗ No age key found");
-        println!("   💡 Generating new age keypair...");
-
-        generate_age_keypair(&default_key_path)?
-    };
-
-    println!("   Public key: age1{}...", &keypair.public_key[..16]);
-    println!();
-
-    // Step 3: Create .sops.yaml in global directory
-    println!("📝 Step 3: SOPS Configuration");
-    let sops_config_path = global_dir.join(".sops.yaml");
-    let sops_config_content = format!(
+/// Content of the global `.sops.yaml` encrypting for `public_key`.
+fn global_sops_config_content(public_key: &str) -> String {
+    format!(
         r#"# SOPS configuration for Shadow Secret (global)
 # This file was auto-generated by: shadow-secret init-global
 
@@ -505,24 +571,12 @@ creation_rules:
 
 # For more information, see: https://github.com/getsops/sops
 "#,
-        keypair.public_key
-    );
-
-    fs::write(&sops_config_path, sops_config_content)
-        .with_context(|| format!("Failed to write .sops.yaml to: {:?}", sops_config_path))?;
-    println!("   ✓ Created: {:?}", sops_config_path);
-    println!();
-
-    // Step 4: Create global.enc.env with placeholder and encrypt it
-    println!("📝 Step 4: Global Secrets File");
-    let global_enc_env = global_dir.join("global.enc.env");
+        public_key
+    )
+}
 
-    if global_enc_env.exists() {
-        println!("   ℹ️  File already exists: {:?}", global_enc_env);
-    } else {
-        // Create the .enc.env file directly with placeholder secret
-        // SOPS will encrypt it in place
-        let enc_env_content = r#"# Global encrypted secrets file
+/// Plaintext of a new `global.enc.env`, before encryption.
+const GLOBAL_ENC_ENV_CONTENT: &str = r#"# Global encrypted secrets file
 # This file is encrypted with SOPS using your age key
 #
 # To edit secrets:
@@ -537,22 +591,9 @@ creation_rules:
 EXAMPLE_SECRET=placeholder_value
 "#;
 
-        fs::write(&global_enc_env, enc_env_content)
-            .with_context(|| format!("Failed to write global.enc.env: {:?}", global_enc_env))?;
-
-        // Encrypt with SOPS (encrypts in place)
-        println!("   🔒 Encrypting with SOPS...");
-        encrypt_enc_env(&global_enc_env)?;
-
-        println!("   ✓ Created and encrypted: {:?}", global_enc_env);
-    }
-    println!();
-
-    // Step 5: Create global.yaml configuration
-    println!("📝 Step 5: Global Configuration File");
-    let global_yaml = global_dir.join("global.yaml");
-
-    let global_yaml_content = format!(
+/// Content of a new `global.yaml` using the age key at `age_key_path`.
+fn global_yaml_content(age_key_path: &Path) -> String {
+    format!(
         r#"# Shadow Secret Global Configuration
 # This file was auto-generated by: shadow-secret init-global
 
@@ -618,25 +659,119 @@ targets:
 #    - Or create project.yaml manually with vault.source pointing to this global.enc.env
 #    - Define your project-specific targets
 "#,
-        default_key_path.display().to_string()
-    );
+        age_key_path.display()
+    )
+}
+
+/// Initialize global Shadow Secret configuration.
+///
+/// This creates:
+/// - ~/.config/shadow-secret/ directory
+/// - global.yaml (configuration file)
+/// - global.enc.env (encrypted secrets, created as empty file first)
+///
+/// The user can then move this directory to an encrypted drive for security.
+/// Existing `.sops.yaml` and `global.yaml` are only overwritten with `force`.
+pub fn init_global(force: bool) -> Result<()> {
+    crate::say!("🌍 Shadow Secret Global Configuration Initialization");
+    println!();
+
+    let global_dir = get_global_config_dir()?;
+    if !force {
+        refuse_overwrites(&global_overwrites(&global_dir))?;
+    }
+
+    // Step 1: Create global config directory
+    crate::say!("📁 Step 1: Creating global configuration directory");
+
+    if global_dir.exists() {
+        crate::say!("   ⚠️  Directory already exists: {:?}", global_dir);
+        print!("   Continue? [Y/n]: ");
+        use std::io::Write;
+        std::io::stdout().flush()?;
+
+        let mut input = String::new();
+        std::io::stdin().read_line(&mut input)?;
+
+        if input.trim().to_lowercase() == "n" {
+            return Ok(());
+        }
+    } else {
+        fs::create_dir_all(&global_dir)
+            .with_context(|| format!("Failed to create directory: {:?}", global_dir))?;
+        crate::say!("   ✓ Created: {:?}", global_dir);
+    }
+    println!();
+
+    // Step 2: Check for or generate age keypair
+    crate::say!("📝 Step 2: Age Encryption Key");
+    let default_key_path = get_default_master_key_path();
+
+    let keypair = if default_key_path.exists() {
+        crate::say!("   ✓ Existing key found: {:?}", default_key_path);
+        extract_age_keypair(&default_key_path)?
+    } else {
+        crate::say!("   ✗ No age key found");
+        crate::say!("   💡 Generating new age keypair...");
+
+        generate_age_keypair(&default_key_path)?
+    };
+
+    println!("   Public key: age1{}...", &keypair.public_key[..16]);
+    println!();
+
+    // Step 3: Create .sops.yaml in global directory
+    crate::say!("📝 Step 3: SOPS Configuration");
+    let sops_config_path = global_dir.join(".sops.yaml");
+    let sops_config_content = global_sops_config_content(&keypair.public_key);
+
+    fs::write(&sops_config_path, sops_config_content)
+        .with_context(|| format!("Failed to write .sops.yaml to: {:?}", sops_config_path))?;
+    crate::say!("   ✓ Created: {:?}", sops_config_path);
+    println!();
+
+    // Step 4: Create global.enc.env with placeholder and encrypt it
+    crate::say!("📝 Step 4: Global Secrets File");
+    let global_enc_env = global_dir.join("global.enc.env");
+
+    if global_enc_env.exists() {
+        crate::say!("   ℹ️  File already exists: {:?}", global_enc_env);
+    } else {
+        // Create the .enc.env file directly with placeholder secret
+        // SOPS will encrypt it in place
+        fs::write(&global_enc_env, GLOBAL_ENC_ENV_CONTENT)
+            .with_context(|| format!("Failed to write global.enc.env: {:?}", global_enc_env))?;
+
+        // Encrypt with SOPS (encrypts in place)
+        crate::say!("   🔒 Encrypting with SOPS...");
+        encrypt_enc_env(&global_enc_env)?;
+
+        crate::say!("   ✓ Created and encrypted: {:?}", global_enc_env);
+    }
+    println!();
+
+    // Step 5: Create global.yaml configuration
+    crate::say!("📝 Step 5: Global Configuration File");
+    let global_yaml = global_dir.join("global.yaml");
+
+    let global_yaml_content = global_yaml_content(&default_key_path);
 
     fs::write(&global_yaml, global_yaml_content)
         .with_context(|| format!("Failed to write global.yaml to: {:?}", global_yaml))?;
-    println!("   ✓ Created: {:?}", global_yaml);
+    crate::say!("   ✓ Created: {:?}", global_yaml);
     println!();
 
     // Step 6: Final instructions
-    println!("✅ Global configuration initialized successfully!");
+    crate::say!("✅ Global configuration initialized successfully!");
     println!();
-    println!("📁 Configuration directory: {:?}", global_dir);
+    crate::say!("📁 Configuration directory: {:?}", global_dir);
     println!();
-    println!("🔐 Security Note:");
+    crate::say!("🔐 Security Note:");
     println!("   You can now move the entire ~/.config/shadow-secret/ directory");
     println!("   to an encrypted drive (e.g., VeraCrypt volume) for enhanced security.");
     println!("   Just update the path in your project configurations accordingly.");
     println!();
-    println!("📝 Next steps:");
+    crate::say!("📝 Next steps:");
     println!("   1. Add secrets to global.enc.env:");
     println!("      sops --encrypt {:?} < {:?}.tmp", global_enc_env, global_enc_env);
     println!("   2. Use in any project:");
@@ -653,19 +788,28 @@ targets:
 ///
 /// This is the main entry point for the `init-project` command.
 pub fn init_project(config: InitConfig) -> Result<()> {
-    println!("🚀 Shadow Secret Project Initialization");
-    println!("Current directory: {:?}\n", std::env::current_dir());
+    crate::say!("🚀 Shadow Secret Project Initialization");
+    println!("Current directory: {:?}", std::env::current_dir());
+    if let Some(profile) = &config.profile {
+        println!("Organization profile: {} ({})", profile.name, profile.source);
+    }
+    println!();
+
+    let project_dir = std::env::current_dir()?;
+    if !config.force {
+        refuse_overwrites(&project_overwrites(&project_dir))?;
+    }
 
     // Step 1: Check for or generate age master key
-    println!("📝 Step 1: Age Master Key");
+    crate::say!("📝 Step 1: Age Master Key");
     println!("   Checking: {:?}", config.master_key_path);
 
     let keypair = if config.master_key_path.exists() {
-        println!("   ✓ Existing key found");
+        crate::say!("   ✓ Existing key found");
         extract_age_keypair(&config.master_key_path)?
     } else {
-        println!("   ✗ No key found");
-        println!("   💡 To generate manually: age-keygen -o {:?}", config.master_key_path);
+        crate::say!("   ✗ No key found");
+        crate::say!("   💡 To generate manually: age-keygen -o {:?}", config.master_key_path);
 
         // Prompt user
         print!("   Generate new keypair now? [Y/n]: ");
@@ -687,29 +831,37 @@ pub fn init_project(config: InitConfig) -> Result<()> {
     println!("   Public key: age1{}...\n", &keypair.public_key[..16]);
 
     // Step 2: Create .sops.yaml
-    println!("📝 Step 2: SOPS Configuration");
-    let project_dir = std::env::current_dir()?;
-    let sops_config_path = create_sops_config(&project_dir, &keypair.public_key)?;
-    println!("   ✓ Created: {:?}\n", sops_config_path);
+    crate::say!("📝 Step 2: SOPS Configuration");
+    let recipients = project_recipients(&keypair.public_key, config.profile.as_ref());
+    let sops_config_path = create_sops_config_for_recipients(&project_dir, &recipients)?;
+    crate::say!("   ✓ Created: {:?}", sops_config_path);
+    if recipients.len() > 1 {
+        crate::say!("   ℹ️  Encrypting for {} recipient(s)", recipients.len());
+    }
+    println!();
 
     // Step 3: Create .enc.env
-    println!("📝 Step 3: Encrypted Secrets File");
+    crate::say!("📝 Step 3: Encrypted Secrets File");
     let enc_env_path = create_enc_env(&project_dir, config.create_example)?;
-    println!("   ✓ Created: {:?}\n", enc_env_path);
+    crate::say!("   ✓ Created: {:?}\n", enc_env_path);
 
     // Step 4: Encrypt .enc.env
-    println!("📝 Step 4: Encryption");
+    crate::say!("📝 Step 4: Encryption");
     encrypt_enc_env(&enc_env_path)?;
     println!();
 
     // Step 5: Create project.yaml configuration
-    println!("📝 Step 5: Project Configuration");
-    let project_config_path = create_project_config(&project_dir, &config.master_key_path)?;
-    println!("   ✓ Created: {:?}\n", project_config_path);
+    crate::say!("📝 Step 5: Project Configuration");
+    let project_config_path = create_project_config_with_profile(
+        &project_dir,
+        &config.master_key_path,
+        config.profile.as_ref(),
+    )?;
+    crate::say!("   ✓ Created: {:?}\n", project_config_path);
 
     // Step 6: Optional global config
     if config.prompt_global {
-        println!("📝 Step 6: Global Configuration");
+        crate::say!("📝 Step 6: Global Configuration");
         print!("   Add this project to global config? [Y/n]: ");
         use std::io::Write;
         std::io::stdout().flush()?;
@@ -720,24 +872,177 @@ pub fn init_project(config: InitConfig) -> Result<()> {
         if input.trim().to_lowercase() != "n" {
             add_to_global_config(&project_dir)?;
         } else {
-            println!("   ⊘ Skipped");
+            crate::say!("   ⊘ Skipped");
         }
         println!();
     }
 
     // Summary
-    println!("✅ Project initialized successfully!");
+    crate::say!("✅ Project initialized successfully!");
     println!();
     println!("Next steps:");
     println!("  1. Edit project.yaml to configure your targets");
     println!("  2. Edit .enc.env: sops --decrypt .enc.env > .env.tmp");
     println!("  3. Add secrets, then encrypt: sops --encrypt .env.tmp > .enc.env");
     println!("  4. Run: shadow-secret unlock");
+    if let Some(key_server) = config.profile.as_ref().and_then(|p| p.key_server.as_ref()) {
+        println!();
+        crate::say!("🔑 Team public keys: {}", key_server);
+    }
     println!();
 
     Ok(())
 }
 
+/// Age recipients of a project: the local master key, then the
+/// organization profile's.
+fn project_recipients(public_key: &str, profile: Option<&OrgProfile>) -> Vec<String> {
+    let mut recipients = vec![public_key.to_string()];
+    if let Some(profile) = profile {
+        for recipient in &profile.recipients {
+            if !recipients.contains(recipient) {
+                recipients.push(recipient.clone());
+            }
+        }
+    }
+    recipients
+}
+
+/// Public key of the age key at `key_path`, or a placeholder if init would
+/// generate it.
+fn planned_public_key(key_path: &Path) -> Result<String> {
+    if key_path.exists() {
+        Ok(extract_age_keypair(key_path)?.public_key)
+    } else {
+        Ok(NEW_KEY_PLACEHOLDER.to_string())
+    }
+}
+
+/// Files `init-project` would write for `config` in `project_dir`,
+/// including `global.yaml` if the project would be offered to it.
+pub fn project_plan(config: &InitConfig, project_dir: &Path) -> Result<Vec<PlannedFile>> {
+    let public_key = planned_public_key(&config.master_key_path)?;
+    let recipients = project_recipients(&public_key, config.profile.as_ref());
+
+    let mut files = vec![
+        PlannedFile::plain(project_dir.join(".sops.yaml"), sops_config_content(&recipients)),
+        PlannedFile {
+            path: project_dir.join(".enc.env"),
+            content: enc_env_content(config.create_example).to_string(),
+            encrypted: true,
+        },
+        PlannedFile::plain(
+            project_dir.join("project.yaml"),
+            project_config_content(&config.master_key_path, config.profile.as_ref())?,
+        ),
+    ];
+
+    if config.prompt_global {
+        let global_yaml = get_global_config_dir()?.join("global.yaml");
+        if let Ok(content) = fs::read_to_string(&global_yaml) {
+            if let Some(updated) = global_config_with_project(&content, project_dir)? {
+                files.push(PlannedFile::plain(global_yaml, updated));
+            }
+        }
+    }
+
+    Ok(files)
+}
+
+/// Files `init-global` would write in `global_dir`.
+pub fn global_plan(global_dir: &Path) -> Result<Vec<PlannedFile>> {
+    let key_path = get_default_master_key_path();
+    let public_key = planned_public_key(&key_path)?;
+
+    let mut files = vec![PlannedFile::plain(
+        global_dir.join(".sops.yaml"),
+        global_sops_config_content(&public_key),
+    )];
+    let enc_env = global_dir.join("global.enc.env");
+    if !enc_env.exists() {
+        files.push(PlannedFile {
+            path: enc_env,
+            content: GLOBAL_ENC_ENV_CONTENT.to_string(),
+            encrypted: true,
+        });
+    }
+    files.push(PlannedFile::plain(global_dir.join("global.yaml"), global_yaml_content(&key_path)));
+
+    Ok(files)
+}
+
+/// Existing files `init-project` would overwrite in `project_dir`.
+pub fn project_overwrites(project_dir: &Path) -> Vec<PathBuf> {
+    existing(project_dir, &PROJECT_FILES)
+}
+
+/// Existing files `init-global` would overwrite in `global_dir`.
+pub fn global_overwrites(global_dir: &Path) -> Vec<PathBuf> {
+    existing(global_dir, &GLOBAL_FILES)
+}
+
+fn existing(dir: &Path, names: &[&str]) -> Vec<PathBuf> {
+    names
+        .iter()
+        .map(|name| dir.join(name))
+        .filter(|path| path.exists())
+        .collect()
+}
+
+/// Fail if init would overwrite any of `existing` without `--force`.
+fn refuse_overwrites(existing: &[PathBuf]) -> Result<()> {
+    if existing.is_empty() {
+        return Ok(());
+    }
+
+    let list: Vec<String> = existing.iter().map(|path| format!("  - {}", path.display())).collect();
+    anyhow::bail!(
+        "Refusing to overwrite existing files:\n{}\nReview the changes with --plan, then re-run with --force to overwrite them",
+        list.join("\n")
+    )
+}
+
+/// Render `files` for `--plan`: whether each is created, modified or
+/// unchanged, with a line diff against its current content.
+///
+/// The current content of encrypted files is not diffed: it is ciphertext.
+pub fn render_plan(files: &[PlannedFile]) -> String {
+    let mut out = String::new();
+
+    for file in files {
+        let current = file.path.exists().then(|| fs::read_to_string(&file.path).ok());
+        let lines = match current {
+            None => {
+                out.push_str(&format!("📄 Create: {:?}\n", file.path));
+                line_diff("", &file.content)
+            }
+            Some(Some(current)) if current == file.content => {
+                out.push_str(&format!("✓ Unchanged: {:?}\n", file.path));
+                continue;
+            }
+            Some(Some(current)) if !file.encrypted => {
+                out.push_str(&format!("✏️  Modify: {:?}\n", file.path));
+                line_diff(&current, &file.content)
+            }
+            Some(_) => {
+                out.push_str(&format!("✏️  Replace: {:?} (current content not shown)\n", file.path));
+                line_diff("", &file.content)
+            }
+        };
+
+        if file.encrypted {
+            out.push_str("   (encrypted with SOPS once written)\n");
+        }
+        for line in lines {
+            out.push_str(format!("   {}", line).trim_end());
+            out.push('\n');
+        }
+        out.push('\n');
+    }
+
+    out
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -856,4 +1161,97 @@ AGE-SECRET-KEY-1TESTPRIVATEKEYABCDEFGHIJKLMNOPQRSTUVWXYZ
         assert!(content.contains("IMPORTANT: Configuration Required"));
         assert!(content.contains("shadow-secret unlock"));
     }
+
+    #[test]
+    fn test_create_sops_config_for_recipients() {
+        let temp_dir = TempDir::new().unwrap();
+        let recipients = vec!["age1local".to_string(), "age1team".to_string()];
+
+        let config_path = create_sops_config_for_recipients(temp_dir.path(), &recipients).unwrap();
+
+        let content = fs::read_to_string(&config_path).unwrap();
+        assert!(content.contains("age: \"age1local,age1team\""));
+    }
+
+    #[test]
+    fn test_create_project_config_with_profile() {
+        let temp_dir = TempDir::new().unwrap();
+        let age_key_path = PathBuf::from("/path/to/keys.txt");
+
+        let profile = OrgProfile {
+            name: "acme".to_string(),
+            key_server: Some("https://keys.acme.internal".to_string()),
+            policies: Some(serde_yaml::from_str("require_signed_config: true").unwrap()),
+            targets: vec![crate::config::TargetConfig {
+                name: "api".to_string(),
+                path: "config/app.json".to_string(),
+                placeholders: vec!["$API_KEY".to_string()],
+                ..Default::default()
+            }],
+            source: "https://internal/org-profile.yaml".to_string(),
+            ..Default::default()
+        };
+
+        let config_path =
+            create_project_config_with_profile(temp_dir.path(), &age_key_path, Some(&profile))
+                .unwrap();
+        let content = fs::read_to_string(&config_path).unwrap();
+
+        // Profile targets replace the example target
+        assert!(!content.contains("example-target"));
+        assert!(content.contains("config/app.json"));
+
+        // Profile metadata is recorded
+        assert!(content.contains("profile:\n  name: \"acme\""));
+        assert!(content.contains("key_server: \"https://keys.acme.internal\""));
+        assert!(content.contains("policies:\n  require_signed_config: true"));
+
+        // The result is still a valid configuration
+        let config = crate::config::Config::from_file(&config_path).unwrap();
+        assert_eq!(config.targets.len(), 1);
+        assert_eq!(config.targets[0].name, "api");
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_line_diff() {
+        assert_eq!(
+            line_diff("a\nb\nc\n", "a\nB\nc\nd\n"),
+            vec!["  a", "+ B", "- b", "  c", "+ d"]
+        );
+        assert_eq!(line_diff("", "x\n"), vec!["+ x"]);
+    }
+
+    #[test]
+    fn test_project_plan_and_overwrites() {
+        let temp_dir = TempDir::new().unwrap();
+        let key_path = temp_dir.path().join("keys.txt");
+        fs::write(&key_path, "# public key: age1planned\nAGE-SECRET-KEY-1PLANNED\n").unwrap();
+        fs::write(temp_dir.path().join(".sops.yaml"), "creation_rules: []\n").unwrap();
+
+        let config = InitConfig {
+            master_key_path: key_path,
+            create_example: false,
+            prompt_global: false,
+            profile: None,
+            force: false,
+        };
+        let files = project_plan(&config, temp_dir.path()).unwrap();
+        assert_eq!(files.len(), 3);
+        assert!(files[1].encrypted);
+
+        let rendered = render_plan(&files);
+        assert!(rendered.contains("✏️  Modify:"));
+        assert!(rendered.contains("- creation_rules: []"));
+        assert!(rendered.contains("+     age: \"age1planned\""));
+        assert_eq!(rendered.matches("📄 Create:").count(), 2);
+        // Planning writes nothing
+        assert!(!temp_dir.path().join("project.yaml").exists());
+
+        let overwrites = project_overwrites(temp_dir.path());
+        assert_eq!(overwrites, vec![temp_dir.path().join(".sops.yaml")]);
+        let err = refuse_overwrites(&overwrites).unwrap_err().to_string();
+        assert!(err.contains(".sops.yaml") && err.contains("--force"));
+        assert!(refuse_overwrites(&[]).is_ok());
+    }
 }
@@ -2,9 +2,14 @@
 //!
 //! # Security Guarantees
 //!
-//! - **NO new files created**: Only modifies existing files in-place
-//! - **Atomic operations**: Creates backups before modification
-//! - **Preserves permissions**: Maintains original file metadata
+//! - **NO new files created**: Only modifies existing files in-place, except
+//!   targets that opt in with `create_if_missing` ([`create_target_file`]),
+//!   which are deleted on lock
+//! - **Atomic operations**: Creates backups before modification, and
+//!   replaces files through a temporary file and a rename
+//!   ([`write_atomic`]), so a crash never leaves a truncated target
+//! - **Preserves permissions**: Maintains original file metadata (mode on
+//!   Unix; ACL, read-only, hidden and system attributes on Windows)
 //! - **Format preservation**: Keeps structure and formatting intact
 //!
 //! # Supported Formats
@@ -12,10 +17,28 @@
 //! - JSON: Replaces string values while preserving structure
 //! - YAML: Replaces string values while preserving structure
 //! - ENV: Simple placeholder replacement
+//! - INI: Replaces values only; sections, keys and comments are kept as is
+//! - TOML: Replaces string values only, escaping them; keys, comments,
+//!   ordering and formatting are kept as is
+//! - HCL (`.tf`, `.tfvars`, `.hcl`): Replaces placeholders in strings and
+//!   heredocs, escaped for HCL; comments are kept as is
+//! - Java properties: Replaces values only, escaped for `Properties.load`;
+//!   keys, comments and line continuations are kept as is
+//! - Shell scripts (`.sh`, `.ps1`, or a shebang): Replaces placeholders
+//!   quoted for the shell, so a secret is never expanded or split;
+//!   comments are kept as is
 //!
 //! # Placeholder Format
 //!
-//! Placeholders are formatted as: `$KEY_NAME` or `${KEY_NAME}`
+//! Placeholders are formatted as: `$KEY_NAME` or `${KEY_NAME}`. The special
+//! placeholder `$ALL` replaces every `$KEY`/`${KEY}` naming a vault key.
+//! `${KEY_NAME:-default}` falls back to the literal `default` when the key
+//! has no value, and `${KEY_NAME|urlencode}` injects a transformed value
+//! (see [`TRANSFORMS`]; transforms can be chained).
+//!
+//! Targets whose templates already use `$VAR` for other tooling can match
+//! another form instead, such as `{{KEY}}` or `%KEY%` (see
+//! [`PlaceholderStyle`]).
 //!
 //! # Example
 //!
@@ -27,7 +50,7 @@
 //! let mut secrets = HashMap::new();
 //! secrets.insert("API_KEY".to_string(), "sk_live_12345".to_string());
 //!
-//! let placeholders = vec["$API_KEY".to_string()];
+//! let placeholders = vec!["$API_KEY".to_string()];
 //!
 //! let backup = inject_secrets(
 //!     std::path::Path::new("config.json"),
@@ -47,6 +70,118 @@ use std::fs;
 use std::io::Write;
 use std::path::{Path, PathBuf};
 
+/// Key name of the `$ALL` placeholder.
+pub const ALL_PLACEHOLDER_KEY: &str = "ALL";
+
+/// Separator of a default value in a placeholder (`${KEY:-default}`).
+pub const DEFAULT_SEPARATOR: &str = ":-";
+
+/// Separator of value transforms in a placeholder (`${KEY|base64}`).
+pub const TRANSFORM_SEPARATOR: char = '|';
+
+/// Token standing for the key name in a placeholder style (`{{KEY}}`).
+pub const STYLE_KEY_TOKEN: &str = "KEY";
+
+/// Placeholder form matched in a target instead of `$KEY` / `${KEY}`
+/// (`placeholder_style: "{{KEY}}"`): the text around `KEY` is matched
+/// literally around the key name. Transforms apply as in `${KEY|base64}`
+/// (`{{KEY|base64}}`); defaults are not supported.
+#[derive(Debug, Clone)]
+pub struct PlaceholderStyle {
+    prefix: String,
+    suffix: String,
+    pattern: regex::Regex,
+}
+
+impl PlaceholderStyle {
+    /// Parse a style such as `{{KEY}}`, `%KEY%` or `__KEY__`.
+    pub fn parse(style: &str) -> Result<Self> {
+        let Some((prefix, suffix)) = style.split_once(STYLE_KEY_TOKEN) else {
+            anyhow::bail!("Placeholder style '{}' must contain {}", style, STYLE_KEY_TOKEN);
+        };
+        if prefix.is_empty() || suffix.contains(STYLE_KEY_TOKEN) {
+            anyhow::bail!(
+                "Placeholder style '{}' needs text before {} and a single {} (e.g. {{{{KEY}}}}, %KEY%, __KEY__)",
+                style,
+                STYLE_KEY_TOKEN,
+                STYLE_KEY_TOKEN
+            );
+        }
+
+        let pattern = regex::Regex::new(&format!(
+            r"{}([A-Za-z0-9_.\-]+?)((?:\|[A-Za-z0-9_\-]+)*){}",
+            regex::escape(prefix),
+            regex::escape(suffix)
+        ))?;
+        Ok(Self {
+            prefix: prefix.to_string(),
+            suffix: suffix.to_string(),
+            pattern,
+        })
+    }
+
+    /// Placeholder for `key` in this style.
+    pub fn placeholder(&self, key: &str) -> String {
+        format!("{}{}{}", self.prefix, key, self.suffix)
+    }
+
+    /// Keys referenced in `content` in this style.
+    pub fn referenced_keys(&self, content: &str) -> std::collections::BTreeSet<String> {
+        self.pattern
+            .captures_iter(content)
+            .filter_map(|captures| captures.get(1))
+            .map(|key| key.as_str().to_string())
+            .collect()
+    }
+
+    /// Replace the references of `content` in this style whose key is
+    /// listed in `placeholders` (any vault key with `$ALL`) and has a
+    /// secret.
+    pub fn replace(&self, content: &str, secrets: &HashMap<String, String>, placeholders: &[String]) -> String {
+        self.replace_escaped(content, secrets, placeholders, &|value| value.to_string())
+    }
+
+    /// Like [`PlaceholderStyle::replace`], passing injected values through
+    /// `escape`.
+    fn replace_escaped(
+        &self,
+        content: &str,
+        secrets: &HashMap<String, String>,
+        placeholders: &[String],
+        escape: Escape,
+    ) -> String {
+        let all = placeholders.iter().any(|p| is_all_placeholder(p));
+        self.pattern
+            .replace_all(content, |captures: &regex::Captures| {
+                let key = &captures[1];
+                let listed = all
+                    || placeholders
+                        .iter()
+                        .any(|placeholder| extract_key_name(placeholder) == key);
+                match secrets.get(key) {
+                    Some(value) if listed => apply_transforms(value, &captures[2])
+                        .map(|value| escape(&value))
+                        .unwrap_or_else(|| captures[0].to_string()),
+                    _ => captures[0].to_string(),
+                }
+            })
+            .into_owned()
+    }
+}
+
+/// Escapes an injected value for the syntax around the placeholder.
+type Escape<'a> = &'a dyn Fn(&str) -> String;
+
+/// Value transforms: `base64`, `urlencode` (percent-encodes all but
+/// RFC 3986 unreserved characters), `upper`, `lower` and `json-escape`
+/// (for embedding in a JSON string).
+pub const TRANSFORMS: &[&str] = &["base64", "urlencode", "upper", "lower", "json-escape"];
+
+/// Whether `placeholder` is `$ALL` (or `${ALL}`).
+pub fn is_all_placeholder(placeholder: &str) -> bool {
+    placeholder.starts_with('$') && extract_key_name(placeholder) == ALL_PLACEHOLDER_KEY
+}
+
 /// File backup containing original content for restoration.
 #[derive(Debug, Clone)]
 pub struct FileBackup {
@@ -57,6 +192,10 @@ pub struct FileBackup {
     /// Original file permissions (Unix-only)
     #[cfg(unix)]
     original_permissions: std::fs::Permissions,
+    /// Original file attributes: read-only, hidden, system... (Windows-only;
+    /// the ACL is kept by [`write_atomic`])
+    #[cfg(windows)]
+    original_attributes: u32,
 }
 
 impl FileBackup {
@@ -83,11 +222,22 @@ impl FileBackup {
             .with_context(|| format!("Failed to get file metadata: {}", path.display()))?
             .permissions();
 
+        // Get file attributes for restoration (Windows-only)
+        #[cfg(windows)]
+        let original_attributes = {
+            use std::os::windows::fs::MetadataExt;
+            fs::metadata(path)
+                .with_context(|| format!("Failed to get file metadata: {}", path.display()))?
+                .file_attributes()
+        };
+
         Ok(Self {
             original_content,
             file_path: path.to_path_buf(),
             #[cfg(unix)]
             original_permissions,
+            #[cfg(windows)]
+            original_attributes,
         })
     }
 
@@ -97,17 +247,10 @@ impl FileBackup {
     ///
     /// Returns an error if:
     /// - The file cannot be written
-    /// - Permissions cannot be restored (Unix)
+    /// - Permissions cannot be restored (Unix) or attributes (Windows)
     pub fn restore(&self) -> Result<()> {
         // Write original content back to file
-        let mut file = fs::File::create(&self.file_path).with_context(|| {
-            format!(
-                "Failed to create file for restore: {}",
-                self.file_path.display()
-            )
-        })?;
-
-        file.write_all(self.original_content.as_bytes()).with_context(|| {
+        write_atomic(&self.file_path, self.original_content.as_bytes()).with_context(|| {
             format!(
                 "Failed to write restored content to: {}",
                 self.file_path.display()
@@ -125,6 +268,15 @@ impl FileBackup {
             })?;
         }
 
+        // Restore original attributes (Windows-only)
+        #[cfg(windows)]
+        set_file_attributes(&self.file_path, self.original_attributes).with_context(|| {
+            format!(
+                "Failed to restore attributes for: {}",
+                self.file_path.display()
+            )
+        })?;
+
         Ok(())
     }
 
@@ -139,6 +291,185 @@ impl FileBackup {
     }
 }
 
+/// Replace the content of the file at `path` atomically: the content is
+/// written and synced to a temporary file in the same directory, which is
+/// then renamed over `path`. A crash or a failed write leaves the old
+/// content, never a truncated file.
+///
+/// Permissions of an existing file are kept (on Windows, its ACL and
+/// attributes too, see [`replace_file`]), and a symlink is followed so the
+/// file it points to is replaced, not the link.
+pub fn write_atomic(path: &Path, content: &[u8]) -> Result<()> {
+    let path = if fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_symlink()) {
+        fs::canonicalize(path).with_context(|| format!("Failed to resolve symlink: {}", path.display()))?
+    } else {
+        path.to_path_buf()
+    };
+    let file_name = path
+        .file_name()
+        .with_context(|| format!("Not a file path: {}", path.display()))?;
+    let temp = path.with_file_name(format!(
+        ".{}.shadow-secret-{}.tmp",
+        file_name.to_string_lossy(),
+        std::process::id()
+    ));
+
+    let written = (|| -> Result<()> {
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options
+            .open(&temp)
+            .with_context(|| format!("Failed to create temporary file: {}", temp.display()))?;
+        file.write_all(content)
+            .with_context(|| format!("Failed to write temporary file: {}", temp.display()))?;
+        file.sync_all()
+            .with_context(|| format!("Failed to sync temporary file: {}", temp.display()))?;
+        #[cfg(unix)]
+        if let Ok(metadata) = fs::metadata(&path) {
+            fs::set_permissions(&temp, metadata.permissions())
+                .with_context(|| format!("Failed to copy permissions of: {}", path.display()))?;
+        }
+        replace_file(&temp, &path)
+    })();
+
+    if written.is_err() {
+        let _ = wipe_file(&temp);
+        let _ = fs::remove_file(&temp);
+    }
+    written
+}
+
+/// Overwrite the content of the file at `path` with zeros, in place, and
+/// sync it to disk.
+///
+/// Replacing a file (see [`write_atomic`]) or deleting it only frees the
+/// blocks holding the old content; wiping it first means they no longer
+/// hold secrets. This is best effort: copy-on-write filesystems (Btrfs,
+/// ZFS, APFS), SSD wear leveling and filesystem journals in `data` mode may
+/// still keep earlier copies.
+pub fn wipe_file(path: &Path) -> Result<()> {
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .open(path)
+        .with_context(|| format!("Failed to open file to wipe: {}", path.display()))?;
+    let len = file
+        .metadata()
+        .with_context(|| format!("Failed to get file metadata: {}", path.display()))?
+        .len();
+
+    let zeros = [0u8; 8192];
+    let mut remaining = len;
+    while remaining > 0 {
+        let chunk = remaining.min(zeros.len() as u64) as usize;
+        file.write_all(&zeros[..chunk])
+            .with_context(|| format!("Failed to wipe: {}", path.display()))?;
+        remaining -= chunk as u64;
+    }
+    file.sync_all()
+        .with_context(|| format!("Failed to sync wiped file: {}", path.display()))
+}
+
+/// Move `temp` over `path`.
+#[cfg(not(windows))]
+fn replace_file(temp: &Path, path: &Path) -> Result<()> {
+    fs::rename(temp, path).with_context(|| format!("Failed to replace: {}", path.display()))
+}
+
+/// Move `temp` over `path`. An existing file is replaced with
+/// `ReplaceFileW`, which keeps its ACL, attributes and creation time where
+/// a rename would give the file the directory's defaults. A read-only file
+/// is made writable for the replacement, then read-only again.
+#[cfg(windows)]
+fn replace_file(temp: &Path, path: &Path) -> Result<()> {
+    use std::os::windows::fs::MetadataExt;
+    use windows_sys::Win32::Storage::FileSystem::{
+        ReplaceFileW, FILE_ATTRIBUTE_READONLY, REPLACEFILE_IGNORE_MERGE_ERRORS,
+    };
+
+    let Ok(metadata) = fs::metadata(path) else {
+        return fs::rename(temp, path).with_context(|| format!("Failed to replace: {}", path.display()));
+    };
+    let attributes = metadata.file_attributes();
+    let read_only = attributes & FILE_ATTRIBUTE_READONLY != 0;
+    if read_only {
+        set_file_attributes(path, attributes & !FILE_ATTRIBUTE_READONLY)?;
+    }
+
+    let (replaced, replacement) = (wide_path(path), wide_path(temp));
+    // SAFETY: both paths are NUL-terminated UTF-16 strings that outlive the
+    // call; no backup file is requested and the reserved pointers are null.
+    let ok = unsafe {
+        ReplaceFileW(
+            replaced.as_ptr(),
+            replacement.as_ptr(),
+            std::ptr::null(),
+            REPLACEFILE_IGNORE_MERGE_ERRORS,
+            std::ptr::null(),
+            std::ptr::null(),
+        )
+    } != 0;
+    let error = std::io::Error::last_os_error();
+
+    if read_only {
+        set_file_attributes(path, attributes)?;
+    }
+    if !ok {
+        return Err(anyhow::Error::new(error).context(format!("Failed to replace: {}", path.display())));
+    }
+    Ok(())
+}
+
+/// Set the attributes (read-only, hidden, system...) of the file at `path`.
+#[cfg(windows)]
+fn set_file_attributes(path: &Path, attributes: u32) -> Result<()> {
+    use windows_sys::Win32::Storage::FileSystem::SetFileAttributesW;
+
+    let wide = wide_path(path);
+    // SAFETY: `wide` is a NUL-terminated UTF-16 string that outlives the call.
+    if unsafe { SetFileAttributesW(wide.as_ptr(), attributes) } == 0 {
+        return Err(anyhow::Error::new(std::io::Error::last_os_error())
+            .context(format!("Failed to set attributes of: {}", path.display())));
+    }
+    Ok(())
+}
+
+/// `path` as a NUL-terminated UTF-16 string, for Win32 calls.
+#[cfg(windows)]
+fn wide_path(path: &Path) -> Vec<u16> {
+    use std::os::windows::ffi::OsStrExt;
+    path.as_os_str().encode_wide().chain(Some(0)).collect()
+}
+
+/// Create a missing target file from its template (mode 0600 on Unix).
+///
+/// Used for targets with `create_if_missing: true`; the caller registers the
+/// file with [`crate::cleaner::register_created_file`] so it is deleted on
+/// lock.
+///
+/// # Errors
+///
+/// Returns an error if the file already exists or its directory does not.
+pub fn create_target_file(path: &Path, template: &str) -> Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options
+        .open(path)
+        .with_context(|| format!("Failed to create target file: {}", path.display()))?;
+    file.write_all(template.as_bytes())
+        .with_context(|| format!("Failed to write template to: {}", path.display()))
+}
+
 /// Inject secrets into a file by replacing placeholders.
 ///
 /// # Security
@@ -171,151 +502,1336 @@ impl FileBackup {
 /// let mut secrets = HashMap::new();
 /// secrets.insert("API_KEY".to_string(), "sk_live_12345".to_string());
 ///
-/// let placeholders = vec["$API_KEY".to_string()];
+/// let placeholders = vec!["$API_KEY".to_string()];
+///
+/// let backup = inject_secrets(
+///     std::path::Path::new("config.json"),
+///     &secrets,
+///     &placeholders
+/// )?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn inject_secrets(
+    file_path: &Path,
+    secrets: &HashMap<String, String>,
+    placeholders: &[String],
+) -> Result<FileBackup> {
+    // Create backup
+    let backup = FileBackup::create(file_path)?;
+
+    // Read file content
+    let content = fs::read_to_string(file_path)
+        .with_context(|| format!("Failed to read file: {}", file_path.display()))?;
+
+    // Detect file format and replace placeholders
+    let extension = file_path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("");
+
+    let modified_content = match extension {
+        // Simple text replacement preserves formatting and key order
+        "json" | "yaml" | "yml" => replace_placeholders(&content, secrets, placeholders),
+        "env" | "dotenv" => replace_placeholders(&content, secrets, placeholders),
+        "ini" => replace_placeholders_ini(&content, secrets, placeholders)?,
+        "toml" => replace_placeholders_toml(&content, secrets, placeholders),
+        "tf" | "tfvars" | "hcl" => replace_placeholders_hcl(&content, secrets, placeholders),
+        "properties" => replace_placeholders_properties(&content, secrets, placeholders),
+        _ => {
+            // Try to auto-detect format
+            if let Some(dialect) = ShellDialect::detect(file_path, &content) {
+                replace_placeholders_shell(&content, secrets, placeholders, dialect)
+            } else if content.trim_start().starts_with('{') {
+                // JSON-like - use simple replacement to preserve format
+                replace_placeholders(&content, secrets, placeholders)
+            } else {
+                // Default to simple replacement
+                replace_placeholders(&content, secrets, placeholders)
+            }
+        }
+    };
+
+    // Write modified content back to file
+    write_atomic(file_path, modified_content.as_bytes())?;
+
+    Ok(backup)
+}
+
+/// Like [`inject_secrets`], but fail without touching the file if any
+/// placeholder has no value in `secrets` (`strict` targets).
+///
+/// # Errors
+///
+/// The error lists every unresolved placeholder.
+pub fn inject_secrets_strict(
+    file_path: &Path,
+    secrets: &HashMap<String, String>,
+    placeholders: &[String],
+) -> Result<FileBackup> {
+    ensure_resolved(file_path, secrets, placeholders)?;
+    inject_secrets(file_path, secrets, placeholders)
+}
+
+/// Fail, listing them, if placeholders of the file at `file_path` have no
+/// value in `secrets`.
+pub fn ensure_resolved(file_path: &Path, secrets: &HashMap<String, String>, placeholders: &[String]) -> Result<()> {
+    let unresolved = unresolved_placeholders(secrets, placeholders);
+    if !unresolved.is_empty() {
+        anyhow::bail!(
+            "Unresolved placeholder(s) in {}: {}",
+            file_path.display(),
+            unresolved.join(", ")
+        );
+    }
+    Ok(())
+}
+
+/// Like [`inject_secrets`], matching placeholders written in `style`
+/// instead of `$KEY` / `${KEY}`.
+pub fn inject_secrets_styled(
+    file_path: &Path,
+    secrets: &HashMap<String, String>,
+    placeholders: &[String],
+    style: &PlaceholderStyle,
+) -> Result<FileBackup> {
+    let backup = FileBackup::create(file_path)?;
+    let content = fs::read_to_string(file_path)
+        .with_context(|| format!("Failed to read target file: {}", file_path.display()))?;
+
+    let modified = replace_placeholders_for(file_path, &content, secrets, placeholders, Some(style))?;
+    write_atomic(file_path, modified.as_bytes())
+        .with_context(|| format!("Failed to write target file: {}", file_path.display()))?;
+
+    Ok(backup)
+}
+
+/// Like [`inject_secrets`], for `preserve_formatting` targets: see
+/// [`replace_placeholders_verbatim`]. The file is left untouched if the
+/// result does not parse.
+pub fn inject_secrets_verbatim(
+    file_path: &Path,
+    secrets: &HashMap<String, String>,
+    placeholders: &[String],
+    style: Option<&PlaceholderStyle>,
+) -> Result<FileBackup> {
+    let content = fs::read_to_string(file_path)
+        .with_context(|| format!("Failed to read target file: {}", file_path.display()))?;
+    let modified = replace_placeholders_verbatim(file_path, &content, secrets, placeholders, style)?;
+
+    let backup = FileBackup::create(file_path)?;
+    write_atomic(file_path, modified.as_bytes())
+        .with_context(|| format!("Failed to write target file: {}", file_path.display()))?;
+
+    Ok(backup)
+}
+
+/// Replace placeholders in `content` as plain text, whatever the format of
+/// the file at `path`: nothing is parsed and re-emitted or escaped, so the
+/// result differs from the template only where values were injected. The
+/// result must then still parse as JSON, YAML or TOML for those formats
+/// (see [`validate_format`]).
+pub fn replace_placeholders_verbatim(
+    path: &Path,
+    content: &str,
+    secrets: &HashMap<String, String>,
+    placeholders: &[String],
+    style: Option<&PlaceholderStyle>,
+) -> Result<String> {
+    let modified = match style {
+        Some(style) => style.replace(content, secrets, placeholders),
+        None => replace_placeholders(content, secrets, placeholders),
+    };
+    validate_format(path, &modified)
+        .with_context(|| format!("Injecting secrets would break {}", path.display()))?;
+    Ok(modified)
+}
+
+/// Check that `content` parses as the format of `path` (JSON, YAML or TOML;
+/// other formats are not checked). Errors give the position only, never the
+/// content, which holds secrets.
+pub fn validate_format(path: &Path, content: &str) -> Result<()> {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("json") => match serde_json::from_str::<serde_json::Value>(content) {
+            Ok(_) => Ok(()),
+            Err(e) => anyhow::bail!("invalid JSON at line {}, column {}", e.line(), e.column()),
+        },
+        Some("yaml" | "yml") => match serde_yaml::from_str::<serde_yaml::Value>(content) {
+            Ok(_) => Ok(()),
+            Err(e) => match e.location() {
+                Some(location) => anyhow::bail!("invalid YAML at line {}, column {}", location.line(), location.column()),
+                None => anyhow::bail!("invalid YAML"),
+            },
+        },
+        Some("toml") => match content.parse::<toml_edit::DocumentMut>() {
+            Ok(_) => Ok(()),
+            Err(e) => match e.span() {
+                Some(span) => {
+                    let line = content[..span.start].matches('\n').count() + 1;
+                    anyhow::bail!("invalid TOML at line {}", line)
+                }
+                None => anyhow::bail!("invalid TOML"),
+            },
+        },
+        _ => Ok(()),
+    }
+}
+
+/// A `$KEY` / `${KEY...}` placeholder found by [`find_placeholders`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlaceholderHit {
+    /// Key the placeholder refers to
+    pub key: String,
+    /// The placeholder as written (`${API_KEY|base64}`)
+    pub text: String,
+    /// Byte range of `text` in the content
+    pub start: usize,
+    pub end: usize,
+    /// 1-based line and column (in characters) of the placeholder
+    pub line: usize,
+    pub column: usize,
+    /// Written with braces (`${KEY}`)
+    pub braced: bool,
+    /// Transforms applied to the value (`${KEY|base64|upper}`), in order
+    pub transforms: Vec<String>,
+    /// Fallback used when the key has no value (`${KEY:-default}`)
+    pub default: Option<String>,
+    /// The line the placeholder is on, without its line break
+    pub context: String,
+}
+
+/// Every `$KEY` and `${KEY}` placeholder of `content` (with transforms
+/// and defaults), in order. This is the parser behind the injection
+/// reports; editor plugins and tooling should use it rather than their own
+/// patterns. `$` references that are not placeholders (`$HOME`) are
+/// included: whether a key is injected depends on the target's
+/// placeholders and the vault.
+pub fn find_placeholders(content: &str) -> Vec<PlaceholderHit> {
+    static REFERENCE: std::sync::OnceLock<regex::Regex> = std::sync::OnceLock::new();
+    let reference = REFERENCE.get_or_init(|| {
+        regex::Regex::new(
+            r"\$\{([A-Za-z0-9_.\-]+)((?:\|[A-Za-z0-9_\-]+)*)(?::-([^}]*))?\}|\$([A-Za-z_][A-Za-z0-9_]*)",
+        )
+        .expect("valid regex")
+    });
+
+    let (mut line, mut line_start, mut scanned) = (1, 0, 0);
+    reference
+        .captures_iter(content)
+        .map(|captures| {
+            let whole = captures.get(0).expect("match");
+            for (offset, _) in content[scanned..whole.start()].match_indices('\n') {
+                line += 1;
+                line_start = scanned + offset + 1;
+            }
+            scanned = whole.start();
+            let line_end = content[line_start..].find('\n').map_or(content.len(), |end| line_start + end);
+
+            let braced = captures.get(1).is_some();
+            PlaceholderHit {
+                key: captures.get(1).or_else(|| captures.get(4)).map_or("", |m| m.as_str()).to_string(),
+                text: whole.as_str().to_string(),
+                start: whole.start(),
+                end: whole.end(),
+                line,
+                column: content[line_start..whole.start()].chars().count() + 1,
+                braced,
+                transforms: captures
+                    .get(2)
+                    .map(|m| m.as_str().split(TRANSFORM_SEPARATOR).filter(|t| !t.is_empty()).map(str::to_string).collect())
+                    .unwrap_or_default(),
+                default: captures.get(3).map(|m| m.as_str().to_string()),
+                context: content[line_start..line_end].trim_end_matches('\r').to_string(),
+            }
+        })
+        .collect()
+}
+
+/// Keys referenced in `template` by the placeholders of `placeholders`
+/// (written in `style` if given), split into the ones replaced from
+/// `secrets` and the listed ones left without a value. With `$ALL`,
+/// references to keys the vault does not have are not reported as left:
+/// they are not placeholders (`$HOME`). Both lists are sorted.
+pub fn placeholder_coverage(
+    template: &str,
+    secrets: &HashMap<String, String>,
+    placeholders: &[String],
+    style: Option<&PlaceholderStyle>,
+) -> (Vec<String>, Vec<String>) {
+    let referenced: std::collections::BTreeSet<String> = match style {
+        Some(style) => style.referenced_keys(template),
+        None => find_placeholders(template).into_iter().map(|hit| hit.key).collect(),
+    };
+
+    let all = placeholders.iter().any(|p| is_all_placeholder(p));
+    let unresolved = unresolved_placeholders(secrets, placeholders);
+    let (mut replaced, mut left) = (Vec::new(), Vec::new());
+    for key in referenced {
+        let listed = placeholders.iter().any(|placeholder| extract_key_name(placeholder) == key);
+        if secrets.contains_key(&key) && (all || listed) {
+            replaced.push(key);
+        } else if unresolved.iter().any(|placeholder| extract_key_name(placeholder) == key) {
+            left.push(key);
+        }
+    }
+    (replaced, left)
+}
+
+/// Placeholders of `placeholders` whose key has no value in `secrets`.
+///
+/// `$ALL` is never unresolved: it only stands for the keys the vault has.
+/// Neither is a placeholder with a default (`${KEY:-default}`).
+pub fn unresolved_placeholders(secrets: &HashMap<String, String>, placeholders: &[String]) -> Vec<String> {
+    placeholders
+        .iter()
+        .filter(|placeholder| !is_all_placeholder(placeholder))
+        .filter(|placeholder| default_value(placeholder).is_none())
+        .filter(|placeholder| !secrets.contains_key(extract_key_name(placeholder)))
+        .cloned()
+        .collect()
+}
+
+/// Replace placeholders in any text content.
+///
+/// This is a simple string replacement function that preserves formatting.
+/// It handles both `$KEY` and `${KEY}` placeholder formats, and `$ALL`
+/// (see [`replace_all_keys`]). `${KEY:-default}` and `${KEY|transform}`
+/// references of the listed keys are handled by [`replace_expressions`].
+///
+/// # Arguments
+///
+/// * `content` - Original content
+/// * `secrets` - Map of secret keys to values
+/// * `placeholders` - List of placeholders to replace
+///
+/// # Returns
+///
+/// Modified content with placeholders replaced by secret values.
+pub fn replace_placeholders(
+    content: &str,
+    secrets: &HashMap<String, String>,
+    placeholders: &[String],
+) -> String {
+    replace_placeholders_escaped(content, secrets, placeholders, &|value| value.to_string())
+}
+
+/// Like [`replace_placeholders`], passing injected values through `escape`
+/// (after transforms; defaults are template text and are kept as is).
+///
+/// Every placeholder form is resolved in one pass over the template, so
+/// injected values are never scanned again: a secret containing `$OTHER`
+/// or `${OTHER:-x}` is written as is.
+fn replace_placeholders_escaped(
+    content: &str,
+    secrets: &HashMap<String, String>,
+    placeholders: &[String],
+    escape: Escape,
+) -> String {
+    const REFERENCE: &str = r"\$\{(?P<key>[A-Za-z0-9_.\-]+)(?P<transforms>(?:\|[A-Za-z0-9_\-]+)*)(?::-(?P<default>[^}]*))?\}|\$(?P<bare>[A-Za-z_][A-Za-z0-9_]*)";
+    static REFERENCE_ONLY: std::sync::OnceLock<regex::Regex> = std::sync::OnceLock::new();
+    let reference = REFERENCE_ONLY.get_or_init(|| regex::Regex::new(REFERENCE).expect("valid regex"));
+
+    let all = placeholders.iter().any(|p| is_all_placeholder(p));
+
+    // Placeholders not written as `$KEY` / `${KEY...}` (e.g. a bare
+    // `API_KEY`) are matched literally, longest first
+    let mut literals: Vec<&str> = placeholders
+        .iter()
+        .filter(|placeholder| !is_all_placeholder(placeholder))
+        .filter(|placeholder| {
+            !reference
+                .find(placeholder)
+                .is_some_and(|m| m.start() == 0 && m.end() == placeholder.len())
+        })
+        .map(String::as_str)
+        .collect();
+    literals.sort_by_key(|literal| std::cmp::Reverse(literal.len()));
+    literals.dedup();
+    let combined;
+    let pattern = if literals.iter().all(|literal| literal.is_empty()) {
+        reference
+    } else {
+        let alternatives: Vec<String> = literals.iter().filter(|l| !l.is_empty()).map(|l| regex::escape(l)).collect();
+        combined = regex::Regex::new(&format!("(?P<literal>{})|{}", alternatives.join("|"), REFERENCE))
+            .expect("valid regex");
+        &combined
+    };
+
+    pattern
+        .replace_all(content, |captures: &regex::Captures| {
+            let text = &captures[0];
+            if captures.name("literal").is_some() {
+                return match secrets.get(extract_key_name(text)) {
+                    Some(value) => escape(value),
+                    None => text.to_string(),
+                };
+            }
+
+            let key = captures.name("key").or_else(|| captures.name("bare")).map_or("", |m| m.as_str());
+            let transforms = captures.name("transforms").map_or("", |m| m.as_str());
+            let default = captures.name("default").map(|m| m.as_str());
+            if transforms.is_empty() && default.is_none() {
+                // Plain `$KEY` / `${KEY}`: listed as written, or any vault key with `$ALL`
+                return match secrets.get(key) {
+                    Some(value) if all || placeholders.iter().any(|placeholder| placeholder == text) => escape(value),
+                    _ => text.to_string(),
+                };
+            }
+            let expression = Expression { text, key, transforms, default };
+            expression.resolve(secrets, placeholders, all, escape)
+        })
+        .into_owned()
+}
+
+/// Replace the `${KEY|transform...:-default}` references of `content`
+/// (transforms and default both optional) whose key is listed in
+/// `placeholders`: with the transformed secret when there is one, else with
+/// the literal `default`.
+///
+/// With `$ALL` (`all`), references to any vault key get their secret, but
+/// unlisted keys without a secret are left alone: `${HOME:-/root}` may be
+/// a shell expansion, not a placeholder. References with an unknown
+/// transform are left alone too.
+pub fn replace_expressions(
+    content: &str,
+    secrets: &HashMap<String, String>,
+    placeholders: &[String],
+    all: bool,
+) -> String {
+    replace_expressions_escaped(content, secrets, placeholders, all, &|value| value.to_string())
+}
+
+fn replace_expressions_escaped(
+    content: &str,
+    secrets: &HashMap<String, String>,
+    placeholders: &[String],
+    all: bool,
+    escape: Escape,
+) -> String {
+    static REFERENCE: std::sync::OnceLock<regex::Regex> = std::sync::OnceLock::new();
+    let reference = REFERENCE.get_or_init(|| {
+        regex::Regex::new(r"\$\{([A-Za-z0-9_.\-]+)((?:\|[A-Za-z0-9_\-]+)*)(?::-([^}]*))?\}").expect("valid regex")
+    });
+
+    reference
+        .replace_all(content, |captures: &regex::Captures| {
+            let transforms = &captures[2];
+            let default = captures.get(3).map(|m| m.as_str());
+            if transforms.is_empty() && default.is_none() {
+                // Plain ${KEY}: replaced by the placeholder pass
+                return captures[0].to_string();
+            }
+            let expression = Expression {
+                text: &captures[0],
+                key: &captures[1],
+                transforms,
+                default,
+            };
+            expression.resolve(secrets, placeholders, all, escape)
+        })
+        .into_owned()
+}
+
+/// A `${KEY|transform...:-default}` reference of a template.
+struct Expression<'a> {
+    /// The reference as written
+    text: &'a str,
+    key: &'a str,
+    /// `|`-separated, possibly empty
+    transforms: &'a str,
+    default: Option<&'a str>,
+}
+
+impl Expression<'_> {
+    /// The transformed secret when the key has one and is listed (or
+    /// `all`), else the literal default for listed keys, else the
+    /// reference unchanged.
+    fn resolve(&self, secrets: &HashMap<String, String>, placeholders: &[String], all: bool, escape: Escape) -> String {
+        let listed = placeholders
+            .iter()
+            .any(|placeholder| !is_all_placeholder(placeholder) && extract_key_name(placeholder) == self.key);
+        match (secrets.get(self.key), self.default) {
+            (Some(value), _) if listed || all => apply_transforms(value, self.transforms)
+                .map(|value| escape(&value))
+                .unwrap_or_else(|| self.text.to_string()),
+            (None, Some(default)) if listed => default.to_string(),
+            _ => self.text.to_string(),
+        }
+    }
+}
+
+/// Apply the `|`-separated `transforms` to `value`, in order (`None` if one
+/// is unknown).
+pub fn apply_transforms(value: &str, transforms: &str) -> Option<String> {
+    use base64::Engine as _;
+
+    let mut value = value.to_string();
+    for transform in transforms.split(TRANSFORM_SEPARATOR).filter(|t| !t.is_empty()) {
+        value = match transform {
+            "base64" => base64::engine::general_purpose::STANDARD.encode(value.as_bytes()),
+            "urlencode" => value
+                .bytes()
+                .map(|byte| match byte {
+                    b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (byte as char).to_string(),
+                    _ => format!("%{:02X}", byte),
+                })
+                .collect(),
+            "upper" => value.to_uppercase(),
+            "lower" => value.to_lowercase(),
+            "json-escape" => {
+                let quoted = serde_json::to_string(&value).ok()?;
+                quoted[1..quoted.len() - 1].to_string()
+            }
+            _ => return None,
+        };
+    }
+    Some(value)
+}
+
+/// Transforms of a `${KEY|transform...}` placeholder.
+pub fn placeholder_transforms(placeholder: &str) -> Vec<&str> {
+    let Some(inner) = placeholder.strip_prefix("${").and_then(|p| p.strip_suffix('}')) else {
+        return Vec::new();
+    };
+    let expression = inner.split_once(DEFAULT_SEPARATOR).map_or(inner, |(expression, _)| expression);
+    expression.split(TRANSFORM_SEPARATOR).skip(1).collect()
+}
+
+/// Replace every `$KEY` and `${KEY}` of `content` whose key is in
+/// `secrets`, leaving other `$` references alone.
+///
+/// Unbraced references take the longest key-like name (`$API_KEY` is never
+/// read as `$API` followed by `_KEY`); keys with `.` or `-` need braces.
+pub fn replace_all_keys(content: &str, secrets: &HashMap<String, String>) -> String {
+    replace_all_keys_escaped(content, secrets, &|value| value.to_string())
+}
+
+fn replace_all_keys_escaped(content: &str, secrets: &HashMap<String, String>, escape: Escape) -> String {
+    static REFERENCE: std::sync::OnceLock<regex::Regex> = std::sync::OnceLock::new();
+    let reference = REFERENCE.get_or_init(|| {
+        regex::Regex::new(r"\$\{([A-Za-z0-9_.\-]+)\}|\$([A-Za-z_][A-Za-z0-9_]*)").expect("valid regex")
+    });
+
+    reference
+        .replace_all(content, |captures: &regex::Captures| {
+            let key = captures.get(1).or_else(|| captures.get(2)).map_or("", |m| m.as_str());
+            match secrets.get(key) {
+                Some(value) => escape(value),
+                None => captures[0].to_string(),
+            }
+        })
+        .into_owned()
+}
+
+/// Replace placeholders in INI content, in values only.
+///
+/// Section headers, keys and comments are left untouched even if they
+/// contain a placeholder's text. INI has no escape for line breaks, so a
+/// secret spanning several lines is refused rather than breaking the file.
+///
+/// # Errors
+///
+/// Returns an error if an injected value contains a line break.
+pub fn replace_placeholders_ini(
+    content: &str,
+    secrets: &HashMap<String, String>,
+    placeholders: &[String],
+) -> Result<String> {
+    replace_ini_values(content, |value| replace_placeholders(value, secrets, placeholders))
+}
+
+/// Apply `replace` to the values of INI `content` (see
+/// [`replace_placeholders_ini`]).
+fn replace_ini_values(content: &str, replace: impl Fn(&str) -> String) -> Result<String> {
+    let mut result = String::with_capacity(content.len());
+
+    for (number, line) in content.split_inclusive('\n').enumerate() {
+        let trimmed = line.trim_start();
+        let structural = trimmed.starts_with(';') || trimmed.starts_with('#') || trimmed.starts_with('[');
+
+        match line.find(['=', ':']) {
+            Some(separator) if !structural => {
+                let (key, value) = line.split_at(separator + 1);
+                let injected = replace(value);
+                let breaks = |text: &str| text.matches(['\n', '\r']).count();
+                if breaks(&injected) > breaks(value) {
+                    anyhow::bail!(
+                        "Secret injected into INI key '{}' (line {}) contains a line break, which INI cannot represent",
+                        key[..separator].trim(),
+                        number + 1
+                    );
+                }
+                result.push_str(key);
+                result.push_str(&injected);
+            }
+            _ => result.push_str(line),
+        }
+    }
+
+    Ok(result)
+}
+
+/// Replace placeholders in TOML content, in string values only.
+///
+/// The document is edited with `toml_edit`: keys, tables, comments,
+/// ordering and whitespace are kept, and injected values are escaped so a
+/// quote or line break in a secret cannot break the file. Only the string
+/// values that change are re-rendered, as single-line basic strings.
+/// Templates that are not valid TOML yet (`port = $PORT`) fall back to
+/// plain text replacement.
+pub fn replace_placeholders_toml(
+    content: &str,
+    secrets: &HashMap<String, String>,
+    placeholders: &[String],
+) -> String {
+    replace_toml_values(content, |value| replace_placeholders(value, secrets, placeholders))
+}
+
+/// Apply `replace` to the string values of TOML `content` (see
+/// [`replace_placeholders_toml`]).
+fn replace_toml_values(content: &str, replace: impl Fn(&str) -> String) -> String {
+    use toml_edit::visit_mut::VisitMut;
+
+    struct StringValues<F>(F);
+
+    impl<F: Fn(&str) -> String> VisitMut for StringValues<F> {
+        fn visit_string_mut(&mut self, node: &mut toml_edit::Formatted<String>) {
+            let injected = (self.0)(node.value());
+            if injected == *node.value() {
+                return;
+            }
+            // A JSON string is a valid single-line TOML basic string
+            let mut replaced = match serde_json::to_string(&injected).map(|s| s.parse::<toml_edit::Value>()) {
+                Ok(Ok(toml_edit::Value::String(replaced))) => replaced,
+                _ => toml_edit::Formatted::new(injected),
+            };
+            *replaced.decor_mut() = node.decor().clone();
+            *node = replaced;
+        }
+    }
+
+    let Ok(mut document) = content.parse::<toml_edit::DocumentMut>() else {
+        return replace(content);
+    };
+    let mut values = StringValues(replace);
+    values.visit_document_mut(&mut document);
+    document.to_string()
+}
+
+/// Replace placeholders in HCL content (Terraform `.tf`/`.tfvars`, `.hcl`).
+///
+/// Inside quoted strings (`token = "$TOKEN"`), values are escaped so that
+/// quotes, backslashes, line breaks and HCL template sequences (`${`, `%{`)
+/// in a secret are taken literally. Inside heredocs only the template
+/// sequences are escaped. A bare placeholder (`port = $PORT`) becomes a
+/// quoted string. Comments are left untouched.
+pub fn replace_placeholders_hcl(
+    content: &str,
+    secrets: &HashMap<String, String>,
+    placeholders: &[String],
+) -> String {
+    replace_hcl_values(content, &|text, escape| {
+        replace_placeholders_escaped(text, secrets, placeholders, escape)
+    })
+}
+
+/// Apply `replace` to the strings, heredocs and code of HCL `content` with
+/// the matching escape (see [`replace_placeholders_hcl`]).
+fn replace_hcl_values(content: &str, replace: &dyn Fn(&str, Escape) -> String) -> String {
+    hcl_segments(content)
+        .into_iter()
+        .map(|(segment, text)| match segment {
+            HclSegment::Code => replace(text, &|value| format!("\"{}\"", escape_hcl_string(value))),
+            HclSegment::Quoted => replace(text, &escape_hcl_string),
+            HclSegment::Heredoc => replace(text, &escape_hcl_template),
+            HclSegment::Comment => text.to_string(),
+        })
+        .collect()
+}
+
+/// Kind of a piece of HCL source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum HclSegment {
+    Code,
+    /// Content of a quoted string, without its quotes
+    Quoted,
+    /// Body of a heredoc, without its delimiter lines
+    Heredoc,
+    Comment,
+}
+
+/// Split HCL `content` into segments that, concatenated, give it back.
+fn hcl_segments(content: &str) -> Vec<(HclSegment, &str)> {
+    let bytes = content.as_bytes();
+    let mut segments = Vec::new();
+    let mut push = |segment, from: usize, to: usize| {
+        if to > from {
+            segments.push((segment, &content[from..to]));
+        }
+    };
+
+    let (mut start, mut i) = (0, 0);
+    while i < bytes.len() {
+        let rest = &bytes[i..];
+        if rest[0] == b'"' {
+            let end = hcl_quoted_end(bytes, i + 1);
+            push(HclSegment::Code, start, i + 1);
+            push(HclSegment::Quoted, i + 1, end);
+            (start, i) = (end, end + 1);
+        } else if rest[0] == b'#' || rest.starts_with(b"//") || rest.starts_with(b"/*") {
+            let end = if rest.starts_with(b"/*") {
+                find(bytes, i + 2, b"*/").map_or(bytes.len(), |end| end + 2)
+            } else {
+                find(bytes, i, b"\n").unwrap_or(bytes.len())
+            };
+            push(HclSegment::Code, start, i);
+            push(HclSegment::Comment, i, end);
+            (start, i) = (end, end);
+        } else if let Some(marker) = heredoc_marker(rest) {
+            let body = find(bytes, i, b"\n").map_or(bytes.len(), |end| end + 1);
+            let mut end = body;
+            while end < bytes.len() {
+                let line_end = find(bytes, end, b"\n").unwrap_or(bytes.len());
+                if content[end..line_end].trim() == marker {
+                    break;
+                }
+                end = (line_end + 1).min(bytes.len());
+            }
+            push(HclSegment::Code, start, body);
+            push(HclSegment::Heredoc, body, end);
+            (start, i) = (end, end);
+            // The closing marker is code
+            i = find(bytes, i, b"\n").unwrap_or(bytes.len());
+        } else {
+            i += 1;
+        }
+    }
+    push(HclSegment::Code, start, bytes.len());
+    segments
+}
+
+/// Index of the closing quote of the string starting at `from` (or of the
+/// end of the line for an unterminated one). Interpolations may contain
+/// quoted strings themselves.
+fn hcl_quoted_end(bytes: &[u8], from: usize) -> usize {
+    let mut i = from;
+    while i < bytes.len() {
+        let rest = &bytes[i..];
+        match rest[0] {
+            b'"' | b'\n' => return i,
+            b'\\' => i += 2,
+            _ if rest.starts_with(b"$${") || rest.starts_with(b"%%{") => i += 3,
+            _ if rest.starts_with(b"${") || rest.starts_with(b"%{") => {
+                let mut depth = 0;
+                i += 1;
+                while i < bytes.len() {
+                    match bytes[i] {
+                        b'{' => depth += 1,
+                        b'}' => depth -= 1,
+                        b'"' => i = hcl_quoted_end(bytes, i + 1),
+                        _ => {}
+                    }
+                    i += 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+            }
+            _ => i += 1,
+        }
+    }
+    bytes.len()
+}
+
+/// Marker of the heredoc opened at the start of `rest` (`<<EOT`, `<<-EOT`).
+fn heredoc_marker(rest: &[u8]) -> Option<&str> {
+    let name = rest.strip_prefix(b"<<")?;
+    let name = name.strip_prefix(b"-").unwrap_or(name);
+    let len = name
+        .iter()
+        .take_while(|&&b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-')
+        .count();
+    if len == 0 || !name[0].is_ascii_alphabetic() {
+        return None;
+    }
+    std::str::from_utf8(&name[..len]).ok()
+}
+
+fn find(bytes: &[u8], from: usize, needle: &[u8]) -> Option<usize> {
+    bytes[from.min(bytes.len())..]
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map(|position| from + position)
+}
+
+/// Escape `value` for an HCL quoted string.
+fn escape_hcl_string(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04X}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escape_hcl_template(&escaped)
+}
+
+/// Escape the HCL template sequences of `value` (`${` and `%{`).
+fn escape_hcl_template(value: &str) -> String {
+    value.replace("${", "$${").replace("%{", "%%{")
+}
+
+/// Replace placeholders in Java `.properties` content, in values only.
+///
+/// Entries may use `key=value`, `key: value` or `key value`, and span
+/// several lines with a trailing backslash. Injected values are escaped the
+/// way `Properties.store` does (backslashes, line breaks, a leading space,
+/// non-ASCII characters as `\uXXXX`), so `Properties.load` reads the
+/// secret back exactly, whatever the file encoding. Comments (`#`, `!`) and
+/// keys are left untouched.
+pub fn replace_placeholders_properties(
+    content: &str,
+    secrets: &HashMap<String, String>,
+    placeholders: &[String],
+) -> String {
+    replace_properties_values(content, &|value, escape| {
+        replace_placeholders_escaped(value, secrets, placeholders, escape)
+    })
+}
+
+/// Apply `replace` to the values of `.properties` `content`, with the
+/// properties escape (see [`replace_placeholders_properties`]).
+fn replace_properties_values(content: &str, replace: &dyn Fn(&str, Escape) -> String) -> String {
+    let mut result = String::with_capacity(content.len());
+    let mut continued = false;
+
+    for line in content.split_inclusive('\n') {
+        let text = line.trim_end_matches(['\n', '\r']);
+        let ending = &line[text.len()..];
+        let trimmed = text.trim_start();
+
+        let value_start = if continued {
+            Some(0)
+        } else if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with('!') {
+            None
+        } else {
+            Some(properties_value_start(text))
+        };
+
+        match value_start {
+            Some(start) => {
+                result.push_str(&text[..start]);
+                result.push_str(&replace(&text[start..], &escape_properties));
+                // An odd number of trailing backslashes continues the value
+                let trailing = text.len() - text.trim_end_matches('\\').len();
+                continued = trailing % 2 == 1;
+            }
+            None => result.push_str(text),
+        }
+        result.push_str(ending);
+    }
+
+    result
+}
+
+/// Index where the value of the entry on `line` starts: after the key
+/// (which ends at an unescaped `=`, `:` or whitespace) and the separator.
+fn properties_value_start(line: &str) -> usize {
+    let is_blank = |c: char| matches!(c, ' ' | '\t' | '\x0c');
+    let mut chars = line.char_indices().skip_while(|(_, c)| is_blank(*c)).peekable();
+
+    let mut key_end = line.len();
+    while let Some((index, c)) = chars.next() {
+        if c == '\\' {
+            chars.next();
+        } else if c == '=' || c == ':' || is_blank(c) {
+            key_end = index;
+            break;
+        }
+    }
+
+    let rest = line[key_end..].trim_start_matches(is_blank);
+    let rest = rest.strip_prefix(['=', ':']).unwrap_or(rest);
+    line.len() - rest.trim_start_matches(is_blank).len()
+}
+
+/// Escape `value` for a `.properties` value.
+fn escape_properties(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for (index, c) in value.chars().enumerate() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            '\x0c' => escaped.push_str("\\f"),
+            // A leading space would be dropped as part of the separator
+            ' ' if index == 0 => escaped.push_str("\\ "),
+            c if c.is_control() || !c.is_ascii() => {
+                for unit in c.encode_utf16(&mut [0; 2]) {
+                    escaped.push_str(&format!("\\u{:04X}", unit));
+                }
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Shell whose quoting rules apply to a script target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShellDialect {
+    /// sh, bash, zsh, ksh
+    Posix,
+    PowerShell,
+}
+
+impl ShellDialect {
+    /// The shell of the script at `path`: from its extension (`.sh`,
+    /// `.bash`, `.zsh`, `.ksh`, `.ps1`, `.psm1`), or from the shebang of a
+    /// file without one (`#!/usr/bin/env bash`, `#!/usr/bin/pwsh`).
+    pub fn detect(path: &Path, content: &str) -> Option<Self> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("sh" | "bash" | "zsh" | "ksh") => return Some(Self::Posix),
+            Some("ps1" | "psm1") => return Some(Self::PowerShell),
+            Some(_) => return None,
+            None => {}
+        }
+
+        let shebang = content.lines().next()?.strip_prefix("#!")?;
+        let mut words = shebang.split_whitespace();
+        let mut program = words.next()?.rsplit('/').next()?;
+        if program == "env" {
+            program = words.find(|word| !word.starts_with('-'))?;
+        }
+        match program {
+            "sh" | "bash" | "zsh" | "ksh" | "dash" | "ash" => Some(Self::Posix),
+            "pwsh" | "powershell" => Some(Self::PowerShell),
+            _ => None,
+        }
+    }
+}
+
+/// Replace placeholders in a shell script, quoted for `dialect` so that a
+/// secret is always a single literal word.
 ///
-/// let backup = inject_secrets(
-///     std::path::Path::new("config.json"),
-///     &secrets,
-///     &placeholders
-/// )?;
-/// # Ok(())
-/// # }
-/// ```
-pub fn inject_secrets(
-    file_path: &Path,
+/// Inside double quotes (`"$TOKEN"`), the characters the shell would
+/// expand (`$`, backticks, quotes, backslashes) are escaped. Inside single
+/// quotes, a quote in the secret closes the string around an escaped quote
+/// (`'\''` in POSIX shells, `''` in PowerShell). A bare placeholder
+/// (`export TOKEN=$TOKEN`) becomes a single-quoted string. Heredocs and
+/// here-strings are escaped when they expand and injected as is when they
+/// do not. Comments are left untouched.
+pub fn replace_placeholders_shell(
+    content: &str,
     secrets: &HashMap<String, String>,
     placeholders: &[String],
-) -> Result<FileBackup> {
-    eprintln!("🔍 [DEBUG] Starting injection for: {}", file_path.display());
-    eprintln!("🔍 [DEBUG] Placeholders: {:?}", placeholders);
-    eprintln!("🔍 [DEBUG] Secrets keys: {:?}", secrets.keys().collect::<Vec<_>>());
+    dialect: ShellDialect,
+) -> String {
+    replace_shell_values(content, dialect, &|text, escape| {
+        replace_placeholders_escaped(text, secrets, placeholders, escape)
+    })
+}
 
-    // Create backup
-    let backup = match FileBackup::create(file_path) {
-        Ok(b) => {
-            eprintln!("✓ [DEBUG] Backup created successfully");
-            b
-        }
-        Err(e) => {
-            eprintln!("❌ [DEBUG] Failed to create backup: {:#?}", e);
-            return Err(e.into());
-        }
+/// Apply `replace` to the quoted strings, heredocs and code of a `dialect`
+/// script with the matching escape (see [`replace_placeholders_shell`]).
+fn replace_shell_values(content: &str, dialect: ShellDialect, replace: &dyn Fn(&str, Escape) -> String) -> String {
+    let segments = match dialect {
+        ShellDialect::Posix => posix_segments(content),
+        ShellDialect::PowerShell => powershell_segments(content),
     };
+    segments
+        .into_iter()
+        .map(|(segment, text)| match (dialect, segment) {
+            (_, ShellSegment::Comment) => text.to_string(),
+            (_, ShellSegment::Literal) => replace(text, &|value| value.to_string()),
+            (ShellDialect::Posix, ShellSegment::Code) => {
+                replace(text, &|value| format!("'{}'", escape_posix_single(value)))
+            }
+            (ShellDialect::Posix, ShellSegment::Single) => replace(text, &escape_posix_single),
+            (ShellDialect::Posix, ShellSegment::Double) => replace(text, &escape_posix_double),
+            (ShellDialect::Posix, ShellSegment::Heredoc) => replace(text, &escape_posix_heredoc),
+            (ShellDialect::PowerShell, ShellSegment::Code) => {
+                replace(text, &|value| format!("'{}'", escape_powershell_single(value)))
+            }
+            (ShellDialect::PowerShell, ShellSegment::Single) => replace(text, &escape_powershell_single),
+            (ShellDialect::PowerShell, ShellSegment::Double | ShellSegment::Heredoc) => {
+                replace(text, &escape_powershell_double)
+            }
+        })
+        .collect()
+}
 
-    // Read file content
-    let content = match fs::read_to_string(file_path) {
-        Ok(c) => {
-            eprintln!("✓ [DEBUG] File read successfully ({} bytes)", c.len());
-            c
+/// Kind of a piece of shell script.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ShellSegment {
+    Code,
+    /// Content of a single-quoted string, without its quotes
+    Single,
+    /// Content of a double-quoted string or here-string, without its quotes
+    Double,
+    /// Body of a heredoc that expands (unquoted marker)
+    Heredoc,
+    /// Body of a heredoc or here-string taken literally
+    Literal,
+    Comment,
+}
+
+/// Whether a `#` after `byte` starts a comment.
+fn is_word_break(byte: u8) -> bool {
+    byte.is_ascii_whitespace() || matches!(byte, b';' | b'&' | b'|' | b'(' | b')' | b'{' | b'}')
+}
+
+/// Split POSIX shell `content` into segments that, concatenated, give it
+/// back.
+fn posix_segments(content: &str) -> Vec<(ShellSegment, &str)> {
+    let bytes = content.as_bytes();
+    let mut segments = Vec::new();
+    let mut push = |segment, from: usize, to: usize| {
+        if to > from {
+            segments.push((segment, &content[from..to]));
+        }
+    };
+    // Heredocs opened on the current line, their bodies start on the next
+    let mut heredocs = Vec::new();
+
+    let (mut start, mut i) = (0, 0);
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'\'' => {
+                let end = find(bytes, i + 1, b"'").unwrap_or(bytes.len());
+                push(ShellSegment::Code, start, i + 1);
+                push(ShellSegment::Single, i + 1, end);
+                (start, i) = (end, end + 1);
+            }
+            b'"' => {
+                let end = posix_double_end(bytes, i + 1);
+                push(ShellSegment::Code, start, i + 1);
+                push(ShellSegment::Double, i + 1, end);
+                (start, i) = (end, end + 1);
+            }
+            b'#' if i == 0 || is_word_break(bytes[i - 1]) => {
+                let end = find(bytes, i, b"\n").unwrap_or(bytes.len());
+                push(ShellSegment::Code, start, i);
+                push(ShellSegment::Comment, i, end);
+                (start, i) = (end, end);
+            }
+            b'<' if bytes[i..].starts_with(b"<<") && !bytes[i..].starts_with(b"<<<") => {
+                match posix_heredoc(&bytes[i + 2..]) {
+                    Some((heredoc, len)) => {
+                        heredocs.push(heredoc);
+                        i += 2 + len;
+                    }
+                    None => i += 2,
+                }
+            }
+            b'\n' if !heredocs.is_empty() => {
+                let mut end = i + 1;
+                push(ShellSegment::Code, start, end);
+                for heredoc in heredocs.drain(..) {
+                    let body = end;
+                    while end < bytes.len() {
+                        let line_end = find(bytes, end, b"\n").unwrap_or(bytes.len());
+                        let line = &content[end..line_end];
+                        let line = if heredoc.strip_tabs { line.trim_start_matches('\t') } else { line };
+                        if line.trim_end_matches('\r') == heredoc.marker {
+                            break;
+                        }
+                        end = (line_end + 1).min(bytes.len());
+                    }
+                    let kind = if heredoc.expands { ShellSegment::Heredoc } else { ShellSegment::Literal };
+                    push(kind, body, end);
+                    // The closing marker is code
+                    let next = find(bytes, end, b"\n").map_or(bytes.len(), |line_end| line_end + 1);
+                    push(ShellSegment::Code, end, next);
+                    end = next;
+                }
+                (start, i) = (end, end);
+            }
+            _ => i += 1,
         }
-        Err(e) => {
-            eprintln!("❌ [DEBUG] Failed to read file: {:#?}", e);
-            return Err(e.into());
+    }
+    push(ShellSegment::Code, start, bytes.len());
+    segments
+}
+
+/// Index of the closing quote of the POSIX double-quoted string starting
+/// at `from` (or the end of `bytes` for an unterminated one). Command
+/// substitutions may contain quoted strings themselves.
+fn posix_double_end(bytes: &[u8], from: usize) -> usize {
+    let mut i = from;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'"' => return i,
+            b'\\' => i += 2,
+            b'`' => i = find(bytes, i + 1, b"`").map_or(bytes.len(), |end| end + 1),
+            b'$' if bytes.get(i + 1) == Some(&b'(') => {
+                let mut depth = 0;
+                i += 1;
+                while i < bytes.len() {
+                    match bytes[i] {
+                        b'(' => depth += 1,
+                        b')' => depth -= 1,
+                        b'\\' => i += 1,
+                        b'"' => i = posix_double_end(bytes, i + 1),
+                        b'\'' => i = find(bytes, i + 1, b"'").unwrap_or(bytes.len()),
+                        _ => {}
+                    }
+                    i += 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+            }
+            _ => i += 1,
         }
+    }
+    bytes.len()
+}
+
+/// A heredoc waiting for its body.
+struct PosixHeredoc {
+    marker: String,
+    /// The marker is unquoted: the body expands `$` and backticks
+    expands: bool,
+    /// `<<-`: leading tabs are stripped from the body and the marker line
+    strip_tabs: bool,
+}
+
+/// Heredoc opened by `<<` followed by `rest`, and the length of what
+/// follows `<<` up to the end of the marker.
+fn posix_heredoc(rest: &[u8]) -> Option<(PosixHeredoc, usize)> {
+    let strip_tabs = rest.first() == Some(&b'-');
+    let mut len = usize::from(strip_tabs);
+    len += rest[len..].iter().take_while(|&&b| b == b' ' || b == b'\t').count();
+    let word_len = rest[len..]
+        .iter()
+        .take_while(|&&b| !b.is_ascii_whitespace() && !matches!(b, b';' | b'&' | b'|' | b'<' | b'>' | b'(' | b')'))
+        .count();
+    let word = std::str::from_utf8(&rest[len..len + word_len]).ok()?;
+
+    let marker: String = word.chars().filter(|c| !matches!(c, '\'' | '"' | '\\')).collect();
+    if !marker.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        return None;
+    }
+    let heredoc = PosixHeredoc {
+        expands: marker.len() == word.len(),
+        marker,
+        strip_tabs,
     };
+    Some((heredoc, len + word_len))
+}
 
-    // Detect file format and replace placeholders
-    let extension = file_path
-        .extension()
-        .and_then(|ext| ext.to_str())
-        .unwrap_or("");
+/// Split PowerShell `content` into segments that, concatenated, give it
+/// back.
+fn powershell_segments(content: &str) -> Vec<(ShellSegment, &str)> {
+    let bytes = content.as_bytes();
+    let mut segments = Vec::new();
+    let mut push = |segment, from: usize, to: usize| {
+        if to > from {
+            segments.push((segment, &content[from..to]));
+        }
+    };
 
-    eprintln!("🔍 [DEBUG] File extension: '{}'", extension);
+    let (mut start, mut i) = (0, 0);
+    while i < bytes.len() {
+        let rest = &bytes[i..];
+        match rest[0] {
+            b'`' => i += 2,
+            b'@' if matches!(rest.get(1), Some(b'"' | b'\''))
+                && (rest[2..].starts_with(b"\n") || rest[2..].starts_with(b"\r\n")) =>
+            {
+                let quote = rest[1];
+                let body = find(bytes, i, b"\n").map_or(bytes.len(), |end| end + 1);
+                // The here-string ends at a line starting with `"@` (or `'@`)
+                let mut end = body;
+                while end < bytes.len() && !bytes[end..].starts_with(&[quote, b'@']) {
+                    end = find(bytes, end, b"\n").map_or(bytes.len(), |line_end| line_end + 1);
+                }
+                let kind = if quote == b'"' { ShellSegment::Double } else { ShellSegment::Literal };
+                push(ShellSegment::Code, start, body);
+                push(kind, body, end);
+                (start, i) = (end, end + 2);
+            }
+            b'\'' => {
+                let end = powershell_single_end(bytes, i + 1);
+                push(ShellSegment::Code, start, i + 1);
+                push(ShellSegment::Single, i + 1, end);
+                (start, i) = (end, end + 1);
+            }
+            b'"' => {
+                let end = powershell_double_end(bytes, i + 1);
+                push(ShellSegment::Code, start, i + 1);
+                push(ShellSegment::Double, i + 1, end);
+                (start, i) = (end, end + 1);
+            }
+            b'<' if rest.starts_with(b"<#") => {
+                let end = find(bytes, i + 2, b"#>").map_or(bytes.len(), |end| end + 2);
+                push(ShellSegment::Code, start, i);
+                push(ShellSegment::Comment, i, end);
+                (start, i) = (end, end);
+            }
+            b'#' if i == 0 || is_word_break(bytes[i - 1]) => {
+                let end = find(bytes, i, b"\n").unwrap_or(bytes.len());
+                push(ShellSegment::Code, start, i);
+                push(ShellSegment::Comment, i, end);
+                (start, i) = (end, end);
+            }
+            _ => i += 1,
+        }
+    }
+    push(ShellSegment::Code, start, bytes.len());
+    segments
+}
 
-    let modified_content = match extension {
-        "json" => {
-            eprintln!("🔍 [DEBUG] Processing as JSON...");
-            // Use simple text replacement to preserve formatting and key order
-            eprintln!("✓ [DEBUG] JSON replacement successful (preserving format)");
-            replace_placeholders(&content, secrets, placeholders)
-        }
-        "yaml" | "yml" => {
-            eprintln!("🔍 [DEBUG] Processing as YAML...");
-            // Use simple text replacement to preserve formatting and key order
-            eprintln!("✓ [DEBUG] YAML replacement successful (preserving format)");
-            replace_placeholders(&content, secrets, placeholders)
+/// Index of the closing quote of the PowerShell single-quoted string
+/// starting at `from` (`''` is an escaped quote).
+fn powershell_single_end(bytes: &[u8], from: usize) -> usize {
+    let mut i = from;
+    while let Some(quote) = find(bytes, i, b"'") {
+        if bytes.get(quote + 1) != Some(&b'\'') {
+            return quote;
         }
-        "env" | "dotenv" => replace_placeholders(&content, secrets, placeholders),
-        _ => {
-            // Try to auto-detect format
-            if content.trim_start().starts_with('{') {
-                // JSON-like - use simple replacement to preserve format
-                replace_placeholders(&content, secrets, placeholders)
-            } else {
-                // Default to simple replacement
-                replace_placeholders(&content, secrets, placeholders)
+        i = quote + 2;
+    }
+    bytes.len()
+}
+
+/// Index of the closing quote of the PowerShell double-quoted string
+/// starting at `from`. Subexpressions (`$(...)`) may contain quoted strings
+/// themselves.
+fn powershell_double_end(bytes: &[u8], from: usize) -> usize {
+    let mut i = from;
+    while i < bytes.len() {
+        let rest = &bytes[i..];
+        match rest[0] {
+            b'"' if rest.get(1) == Some(&b'"') => i += 2,
+            b'"' => return i,
+            b'`' => i += 2,
+            b'$' if rest.get(1) == Some(&b'(') => {
+                let mut depth = 0;
+                i += 1;
+                while i < bytes.len() {
+                    match bytes[i] {
+                        b'(' => depth += 1,
+                        b')' => depth -= 1,
+                        b'`' => i += 1,
+                        b'"' => i = powershell_double_end(bytes, i + 1),
+                        b'\'' => i = powershell_single_end(bytes, i + 1),
+                        _ => {}
+                    }
+                    i += 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
             }
+            _ => i += 1,
         }
-    };
+    }
+    bytes.len()
+}
 
-    // Write modified content back to file
-    eprintln!("🔍 [DEBUG] Writing modified content back to file...");
-    let mut file = match fs::File::create(file_path) {
-        Ok(f) => {
-            eprintln!("✓ [DEBUG] File opened for writing");
-            f
+/// Escape `value` for a POSIX single-quoted string: a quote closes the
+/// string around an escaped quote (`'\''`).
+fn escape_posix_single(value: &str) -> String {
+    value.replace('\'', "'\\''")
+}
+
+/// Escape `value` for a POSIX double-quoted string.
+fn escape_posix_double(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '\\' | '"' | '$' | '`') {
+            escaped.push('\\');
         }
-        Err(e) => {
-            eprintln!("❌ [DEBUG] Failed to open file for writing: {:#?}", e);
-            return Err(e.into());
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Escape `value` for the body of a heredoc that expands (a backslash
+/// before a quote would be kept there).
+fn escape_posix_heredoc(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '\\' | '$' | '`') {
+            escaped.push('\\');
         }
-    };
+        escaped.push(c);
+    }
+    escaped
+}
 
-    match file.write_all(modified_content.as_bytes()) {
-        Ok(_) => eprintln!("✓ [DEBUG] Content written successfully"),
-        Err(e) => {
-            eprintln!("❌ [DEBUG] Failed to write content: {:#?}", e);
-            return Err(e.into());
+/// Escape `value` for a PowerShell single-quoted string, where quotes
+/// (typographic ones included) are doubled.
+fn escape_powershell_single(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '\'' | '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}') {
+            escaped.push(c);
         }
+        escaped.push(c);
     }
+    escaped
+}
 
-    eprintln!("✓ [DEBUG] Injection completed successfully");
-    Ok(backup)
+/// Escape `value` for a PowerShell double-quoted string or here-string,
+/// with backticks before `$`, backticks and quotes (typographic ones
+/// included).
+fn escape_powershell_double(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '`' | '$' | '"' | '\u{201C}' | '\u{201D}' | '\u{201E}') {
+            escaped.push('`');
+        }
+        escaped.push(c);
+    }
+    escaped
 }
 
-/// Replace placeholders in any text content.
-///
-/// This is a simple string replacement function that preserves formatting.
-/// It handles both `$KEY` and `${KEY}` placeholder formats.
-///
-/// # Arguments
-///
-/// * `content` - Original content
-/// * `secrets` - Map of secret keys to values
-/// * `placeholders` - List of placeholders to replace
-///
-/// # Returns
-///
-/// Modified content with placeholders replaced by secret values.
-pub fn replace_placeholders(
+/// Replace placeholders in `content`, the text of the file at `path`, the
+/// way [`inject_secrets`] (or [`inject_secrets_styled`] with a `style`)
+/// does for its format.
+pub fn replace_placeholders_for(
+    path: &Path,
     content: &str,
     secrets: &HashMap<String, String>,
     placeholders: &[String],
-) -> String {
-    let mut result = content.to_string();
-
-    for placeholder in placeholders {
-        // Extract key name from placeholder
-        // Supports both $KEY and ${KEY} formats
-        let key = if placeholder.starts_with("${") && placeholder.ends_with('}') {
-            // ${KEY} format
-            &placeholder[2..placeholder.len() - 1]
-        } else if placeholder.starts_with('$') {
-            // $KEY format
-            &placeholder[1..]
-        } else {
-            // No prefix, treat entire string as key
-            placeholder.as_str()
-        };
+    style: Option<&PlaceholderStyle>,
+) -> Result<String> {
+    if let Some(dialect) = ShellDialect::detect(path, content) {
+        return Ok(replace_shell_values(content, dialect, &|text, escape| match style {
+            Some(style) => style.replace_escaped(text, secrets, placeholders, escape),
+            None => replace_placeholders_escaped(text, secrets, placeholders, escape),
+        }));
+    }
 
-        // Look up secret value
-        if let Some(secret_value) = secrets.get(key) {
-            // Replace all occurrences
-            result = result.replace(placeholder, secret_value);
+    let extension = path.extension().and_then(|ext| ext.to_str());
+    match (style, extension) {
+        (Some(style), Some("ini")) => replace_ini_values(content, |value| style.replace(value, secrets, placeholders)),
+        (Some(style), Some("toml")) => {
+            Ok(replace_toml_values(content, |value| style.replace(value, secrets, placeholders)))
         }
+        (Some(style), Some("tf" | "tfvars" | "hcl")) => Ok(replace_hcl_values(content, &|text, escape| {
+            style.replace_escaped(text, secrets, placeholders, escape)
+        })),
+        (Some(style), Some("properties")) => Ok(replace_properties_values(content, &|value, escape| {
+            style.replace_escaped(value, secrets, placeholders, escape)
+        })),
+        (Some(style), _) => Ok(style.replace(content, secrets, placeholders)),
+        (None, Some("ini")) => replace_placeholders_ini(content, secrets, placeholders),
+        (None, Some("toml")) => Ok(replace_placeholders_toml(content, secrets, placeholders)),
+        (None, Some("tf" | "tfvars" | "hcl")) => Ok(replace_placeholders_hcl(content, secrets, placeholders)),
+        (None, Some("properties")) => Ok(replace_placeholders_properties(content, secrets, placeholders)),
+        (None, _) => Ok(replace_placeholders(content, secrets, placeholders)),
     }
-
-    result
 }
 
 /// Replace placeholders in YAML content while preserving structure.
@@ -327,17 +1843,30 @@ pub fn replace_placeholders(
 /// Supports:
 /// - `$KEY` -> "KEY"
 /// - `${KEY}` -> "KEY"
+/// - `${KEY:-default}` -> "KEY"
+/// - `${KEY|transform}` -> "KEY"
 /// - `KEY` -> "KEY"
 pub fn extract_key_name(placeholder: &str) -> &str {
     if placeholder.starts_with("${") && placeholder.ends_with('}') {
-        &placeholder[2..placeholder.len() - 1]
-    } else if placeholder.starts_with('$') {
-        &placeholder[1..]
+        let inner = &placeholder[2..placeholder.len() - 1];
+        let expression = inner.split_once(DEFAULT_SEPARATOR).map_or(inner, |(expression, _)| expression);
+        expression.split(TRANSFORM_SEPARATOR).next().unwrap_or(expression)
+    } else if let Some(stripped) = placeholder.strip_prefix('$') {
+        stripped
     } else {
         placeholder
     }
 }
 
+/// Default value of a `${KEY:-default}` placeholder.
+pub fn default_value(placeholder: &str) -> Option<&str> {
+    placeholder
+        .strip_prefix("${")?
+        .strip_suffix('}')?
+        .split_once(DEFAULT_SEPARATOR)
+        .map(|(_, default)| default)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -808,4 +2337,416 @@ mod tests {
         // Restore backup to clean up
         backup.restore().unwrap();
     }
+    #[test]
+    fn test_all_placeholder_replaces_every_vault_key() {
+        let mut secrets = HashMap::new();
+        secrets.insert("API".to_string(), "short".to_string());
+        secrets.insert("API_KEY".to_string(), "sk_live_123".to_string());
+        secrets.insert("database.password".to_string(), "s3cret".to_string());
+
+        let content = "key=$API_KEY\nraw=${API}\ndb=${database.password}\nhome=$HOME\nprice=$5\n";
+        let injected = replace_placeholders(content, &secrets, &["$ALL".to_string()]);
+
+        assert_eq!(
+            injected,
+            "key=sk_live_123\nraw=short\ndb=s3cret\nhome=$HOME\nprice=$5\n"
+        );
+        assert!(is_all_placeholder("${ALL}"));
+        assert!(!is_all_placeholder("ALL"));
+    }
+
+    #[test]
+    fn test_replace_placeholders_ini_values_only() {
+        let mut secrets = HashMap::new();
+        secrets.insert("API_KEY".to_string(), "sk_live_123".to_string());
+        let placeholders = vec!["$API_KEY".to_string()];
+
+        let content = "; uses $API_KEY\n[$API_KEY]\napi_key = $API_KEY\nurl: https://x?k=$API_KEY\n$API_KEY=kept\n";
+        let injected = replace_placeholders_ini(content, &secrets, &placeholders).unwrap();
+        assert_eq!(
+            injected,
+            "; uses $API_KEY\n[$API_KEY]\napi_key = sk_live_123\nurl: https://x?k=sk_live_123\n$API_KEY=kept\n"
+        );
+
+        secrets.insert("API_KEY".to_string(), "line1\nline2".to_string());
+        assert!(replace_placeholders_ini("key=$API_KEY\n", &secrets, &placeholders).is_err());
+    }
+
+    #[test]
+    fn test_create_target_file_from_template() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("app.json");
+
+        create_target_file(&path, "{\"key\": \"$API_KEY\"}").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "{\"key\": \"$API_KEY\"}");
+
+        // Never overwrites an existing file
+        assert!(create_target_file(&path, "{}").is_err());
+        assert!(create_target_file(&temp_dir.path().join("missing/app.json"), "{}").is_err());
+    }
+
+    #[test]
+    fn test_inject_secrets_strict_leaves_file_untouched() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("app.env");
+        std::fs::write(&path, "API_KEY=$API_KEY\nDB=$DB_URL\n").unwrap();
+
+        let secrets = HashMap::from([("API_KEY".to_string(), "sk_live_123".to_string())]);
+        let placeholders = vec!["$API_KEY".to_string(), "$DB_URL".to_string(), "$ALL".to_string()];
+        assert_eq!(unresolved_placeholders(&secrets, &placeholders), vec!["$DB_URL".to_string()]);
+
+        let err = inject_secrets_strict(&path, &secrets, &placeholders).unwrap_err();
+        assert!(err.to_string().contains("$DB_URL"));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "API_KEY=$API_KEY\nDB=$DB_URL\n");
+    }
+
+    #[test]
+    fn test_default_values() {
+        assert_eq!(extract_key_name("${LOG_LEVEL:-info}"), "LOG_LEVEL");
+        assert_eq!(default_value("${LOG_LEVEL:-info}"), Some("info"));
+        assert_eq!(default_value("${LOG_LEVEL:-}"), Some(""));
+        assert_eq!(default_value("${LOG_LEVEL}"), None);
+
+        let secrets = HashMap::from([("API_KEY".to_string(), "sk_live_123".to_string())]);
+        let content = "api=${API_KEY:-none}\nlog=${LOG_LEVEL:-info}\nhome=${HOME:-/root}\n";
+
+        let placeholders = vec!["$API_KEY".to_string(), "${LOG_LEVEL:-debug}".to_string()];
+        assert_eq!(
+            replace_placeholders(content, &secrets, &placeholders),
+            "api=sk_live_123\nlog=info\nhome=${HOME:-/root}\n"
+        );
+        assert!(unresolved_placeholders(&secrets, &placeholders).is_empty());
+
+        // $ALL injects vault keys but leaves other defaults to the shell
+        assert_eq!(
+            replace_placeholders(content, &secrets, &["$ALL".to_string()]),
+            "api=sk_live_123\nlog=${LOG_LEVEL:-info}\nhome=${HOME:-/root}\n"
+        );
+    }
+
+    #[test]
+    fn test_injected_values_are_not_rescanned() {
+        let secrets = HashMap::from([
+            ("A".to_string(), "x${B:-pwned}y $B ${B|upper}".to_string()),
+            ("B".to_string(), "bee".to_string()),
+        ]);
+        let content = "a=$A\nb=${B:-none}\n";
+
+        let listed = vec!["$A".to_string(), "$B".to_string()];
+        assert_eq!(
+            replace_placeholders(content, &secrets, &listed),
+            "a=x${B:-pwned}y $B ${B|upper}\nb=bee\n"
+        );
+        assert_eq!(
+            replace_placeholders(content, &secrets, &["$ALL".to_string()]),
+            "a=x${B:-pwned}y $B ${B|upper}\nb=bee\n"
+        );
+        // Literal placeholders too
+        let secrets = HashMap::from([("TOKEN".to_string(), "$B".to_string()), ("B".to_string(), "bee".to_string())]);
+        assert_eq!(
+            replace_placeholders("t=TOKEN\nb=$B\n", &secrets, &["TOKEN".to_string(), "$B".to_string()]),
+            "t=$B\nb=bee\n"
+        );
+    }
+
+    #[test]
+    fn test_transforms() {
+        let secrets = HashMap::from([("DB_PASSWORD".to_string(), "p@ss w/\"rd\"".to_string())]);
+        let placeholders = vec!["$DB_PASSWORD".to_string()];
+
+        let content = "url=postgres://app:${DB_PASSWORD|urlencode}@db\nb64=${DB_PASSWORD|base64}\n\
+                       json=\"${DB_PASSWORD|json-escape}\"\nup=${DB_PASSWORD|upper|urlencode}\nraw=$DB_PASSWORD\n";
+        assert_eq!(
+            replace_placeholders(content, &secrets, &placeholders),
+            "url=postgres://app:p%40ss%20w%2F%22rd%22@db\nb64=cEBzcyB3LyJyZCI=\n\
+             json=\"p@ss w/\\\"rd\\\"\"\nup=P%40SS%20W%2F%22RD%22\nraw=p@ss w/\"rd\"\n"
+        );
+
+        // Unknown transforms are left alone; defaults still apply
+        assert_eq!(
+            replace_placeholders("${DB_PASSWORD|rot13} ${TOKEN|upper:-none}", &secrets, &[
+                "$DB_PASSWORD".to_string(),
+                "${TOKEN|upper:-none}".to_string()
+            ]),
+            "${DB_PASSWORD|rot13} none"
+        );
+        assert_eq!(extract_key_name("${TOKEN|upper:-none}"), "TOKEN");
+        assert_eq!(placeholder_transforms("${TOKEN|upper|base64:-none}"), vec!["upper", "base64"]);
+    }
+
+    #[test]
+    fn test_placeholder_style() {
+        let secrets = HashMap::from([
+            ("API_KEY".to_string(), "sk_live_123".to_string()),
+            ("DB_URL".to_string(), "postgres://db".to_string()),
+        ]);
+        let placeholders = vec!["$API_KEY".to_string()];
+
+        let mustache = PlaceholderStyle::parse("{{KEY}}").unwrap();
+        assert_eq!(mustache.placeholder("API_KEY"), "{{API_KEY}}");
+        assert_eq!(
+            mustache.replace("key={{API_KEY}} b64={{API_KEY|base64}} db={{DB_URL}} shell=$API_KEY", &secrets, &placeholders),
+            "key=sk_live_123 b64=c2tfbGl2ZV8xMjM= db={{DB_URL}} shell=$API_KEY"
+        );
+
+        let percent = PlaceholderStyle::parse("%KEY%").unwrap();
+        assert_eq!(
+            percent.replace("%API_KEY%;%DB_URL%;100%", &secrets, &["$ALL".to_string()]),
+            "sk_live_123;postgres://db;100%"
+        );
+
+        let dunder = PlaceholderStyle::parse("__KEY__").unwrap();
+        assert_eq!(dunder.replace("x=__API_KEY__", &secrets, &placeholders), "x=sk_live_123");
+
+        for invalid in ["{{NAME}}", "KEY}}", "{{KEY}}KEY"] {
+            assert!(PlaceholderStyle::parse(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_inject_secrets_styled() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("settings.ini");
+        fs::write(&path, "[db]\npassword = {{DB_PASSWORD}}\nscript = echo $HOME\n").unwrap();
+        let secrets = HashMap::from([("DB_PASSWORD".to_string(), "s3cret".to_string())]);
+        let style = PlaceholderStyle::parse("{{KEY}}").unwrap();
+
+        let backup = inject_secrets_styled(&path, &secrets, &["DB_PASSWORD".to_string()], &style).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "[db]\npassword = s3cret\nscript = echo $HOME\n"
+        );
+
+        backup.restore().unwrap();
+        assert!(fs::read_to_string(&path).unwrap().contains("{{DB_PASSWORD}}"));
+    }
+
+    #[test]
+    fn test_replace_placeholders_toml_preserves_structure() {
+        let secrets = HashMap::from([
+            ("API_KEY".to_string(), "sk_\"live\"\n123".to_string()),
+            ("DB_HOST".to_string(), "db.internal".to_string()),
+        ]);
+        let placeholders = vec!["$ALL".to_string()];
+
+        let content = "# API settings ($API_KEY)\n[api]\nkey = \"$API_KEY\"   # injected\nurl = 'https://${DB_HOST}:5432'\nretries = 3\n\n[[hosts]]\nnames = [\"$DB_HOST\", \"backup\"]\n";
+        assert_eq!(
+            replace_placeholders_toml(content, &secrets, &placeholders),
+            "# API settings ($API_KEY)\n[api]\nkey = \"sk_\\\"live\\\"\\n123\"   # injected\nurl = \"https://db.internal:5432\"\nretries = 3\n\n[[hosts]]\nnames = [\"db.internal\", \"backup\"]\n"
+        );
+
+        // Not valid TOML until injected: plain text replacement
+        assert_eq!(
+            replace_placeholders_toml("host = $DB_HOST\n", &secrets, &placeholders),
+            "host = db.internal\n"
+        );
+    }
+
+    #[test]
+    fn test_replace_placeholders_hcl() {
+        let secrets = HashMap::from([
+            ("DB_PASSWORD".to_string(), "p\"a${ss}\n".to_string()),
+            ("PORT".to_string(), "5432".to_string()),
+        ]);
+        let placeholders = vec!["$ALL".to_string()];
+
+        let content = "# password: $DB_PASSWORD\nresource \"db\" \"main\" {\n  password = \"$DB_PASSWORD\" // injected\n  name     = \"${var.env}-$PORT\"\n  port     = $PORT\n  script   = <<-EOT\n    echo $DB_PASSWORD\n  EOT\n}\n";
+        assert_eq!(
+            replace_placeholders_hcl(content, &secrets, &placeholders),
+            "# password: $DB_PASSWORD\nresource \"db\" \"main\" {\n  password = \"p\\\"a$${ss}\\n\" // injected\n  name     = \"${var.env}-5432\"\n  port     = \"5432\"\n  script   = <<-EOT\n    echo p\"a$${ss}\n\n  EOT\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_replace_placeholders_properties() {
+        let secrets = HashMap::from([
+            ("DB_PASSWORD".to_string(), " p\\ss\nwörd".to_string()),
+            ("DB_USER".to_string(), "app".to_string()),
+        ]);
+        let placeholders = vec!["$ALL".to_string()];
+
+        let content = "# Database ($DB_PASSWORD)\n! $DB_USER\ndb.password=$DB_PASSWORD\ndb.user : $DB_USER\ndb.url jdbc:postgresql://${DB_USER}@db\ndb.options = ssl=true,\\\n    user=$DB_USER\r\n$DB_USER.key=1\n";
+        assert_eq!(
+            replace_placeholders_properties(content, &secrets, &placeholders),
+            "# Database ($DB_PASSWORD)\n! $DB_USER\ndb.password=\\ p\\\\ss\\nw\\u00F6rd\ndb.user : app\ndb.url jdbc:postgresql://app@db\ndb.options = ssl=true,\\\n    user=app\r\n$DB_USER.key=1\n"
+        );
+        assert_eq!(properties_value_start("key\\=a = b"), 9);
+    }
+
+    #[test]
+    fn test_replace_placeholders_shell_posix() {
+        let secrets = HashMap::from([
+            ("TOKEN".to_string(), "a'b\"c$d`e\\f g".to_string()),
+            ("HOST".to_string(), "db.local".to_string()),
+        ]);
+        let placeholders = vec!["$ALL".to_string()];
+
+        let content = "#!/bin/sh\n# token: $TOKEN\nexport TOKEN=$TOKEN\necho \"$TOKEN $(printf \"%s\" $HOST)\" '$TOKEN'\ncat <<EOF\n$TOKEN\nEOF\ncat <<'EOF'\n$HOST\nEOF\n";
+        assert_eq!(
+            replace_placeholders_shell(content, &secrets, &placeholders, ShellDialect::Posix),
+            "#!/bin/sh\n# token: $TOKEN\nexport TOKEN='a'\\''b\"c$d`e\\f g'\necho \"a'b\\\"c\\$d\\`e\\\\f g $(printf \"%s\" db.local)\" 'a'\\''b\"c$d`e\\f g'\ncat <<EOF\na'b\"c\\$d\\`e\\\\f g\nEOF\ncat <<'EOF'\ndb.local\nEOF\n"
+        );
+    }
+
+    #[test]
+    fn test_replace_placeholders_shell_powershell() {
+        let secrets = HashMap::from([("TOKEN".to_string(), "a'b\"c$d`e".to_string())]);
+        let placeholders = vec!["$TOKEN".to_string()];
+
+        let content = "<# $TOKEN #>\n$env:TOKEN = $TOKEN\nWrite-Host \"token: $TOKEN\" 'it''s $TOKEN'\n$body = @\"\n$TOKEN\n\"@\n";
+        assert_eq!(
+            replace_placeholders_shell(content, &secrets, &placeholders, ShellDialect::PowerShell),
+            "<# $TOKEN #>\n$env:TOKEN = 'a''b\"c$d`e'\nWrite-Host \"token: a'b`\"c`$d``e\" 'it''s a''b\"c$d`e'\n$body = @\"\na'b`\"c`$d``e\n\"@\n"
+        );
+    }
+
+    #[test]
+    fn test_shell_dialect_detect() {
+        assert_eq!(ShellDialect::detect(Path::new("deploy.sh"), ""), Some(ShellDialect::Posix));
+        assert_eq!(ShellDialect::detect(Path::new("deploy.ps1"), ""), Some(ShellDialect::PowerShell));
+        assert_eq!(ShellDialect::detect(Path::new("deploy"), "#!/usr/bin/env -S bash -e\n"), Some(ShellDialect::Posix));
+        assert_eq!(ShellDialect::detect(Path::new("deploy"), "#!/usr/bin/pwsh\n"), Some(ShellDialect::PowerShell));
+        assert_eq!(ShellDialect::detect(Path::new("deploy"), "#!/usr/bin/env python3\n"), None);
+        assert_eq!(ShellDialect::detect(Path::new("app.env"), "#!/bin/sh\n"), None);
+    }
+
+    #[test]
+    fn test_inject_secrets_verbatim() {
+        let secrets = HashMap::from([
+            ("API_KEY".to_string(), "sk_live_123".to_string()),
+            ("QUOTED".to_string(), "say \"hi\"".to_string()),
+        ]);
+        let placeholders = vec!["$API_KEY".to_string(), "$QUOTED".to_string()];
+
+        let template = "{\n\t\"api\":   {\"key\" : \"$API_KEY\"},\n\n  \"retries\":3 }\n";
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("config.json");
+        std::fs::write(&path, template).unwrap();
+        let backup = inject_secrets_verbatim(&path, &secrets, &placeholders, None).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "{\n\t\"api\":   {\"key\" : \"sk_live_123\"},\n\n  \"retries\":3 }\n"
+        );
+        assert_eq!(backup.content(), template);
+
+        // A value that breaks the file: nothing is written, nothing leaks
+        std::fs::write(&path, "{\"greeting\": \"$QUOTED\"}").unwrap();
+        let error = inject_secrets_verbatim(&path, &secrets, &placeholders, None).unwrap_err();
+        assert!(format!("{:#}", error).contains("invalid JSON at line 1"), "{:#}", error);
+        assert!(!format!("{:#}", error).contains("hi"));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "{\"greeting\": \"$QUOTED\"}");
+
+        // TOML literal strings stay literal
+        let toml = Path::new("app.toml");
+        assert_eq!(
+            replace_placeholders_verbatim(toml, "key = '$API_KEY'\n", &secrets, &placeholders, None).unwrap(),
+            "key = 'sk_live_123'\n"
+        );
+    }
+
+    #[test]
+    fn test_placeholder_coverage() {
+        let secrets = HashMap::from([("API_KEY".to_string(), "sk".to_string()), ("DEBUG".to_string(), "1".to_string())]);
+        let template = "KEY=${API_KEY}\nDB=$DB_URL\nHOME=$HOME\nX=$API_KEY\n";
+
+        let listed = vec!["$API_KEY".to_string(), "$DB_URL".to_string()];
+        let (replaced, left) = placeholder_coverage(template, &secrets, &listed, None);
+        assert_eq!(replaced, vec!["API_KEY".to_string()]);
+        assert_eq!(left, vec!["DB_URL".to_string()]);
+
+        // $ALL: unknown references are not placeholders
+        let (replaced, left) = placeholder_coverage(template, &secrets, &["$ALL".to_string()], None);
+        assert_eq!(replaced, vec!["API_KEY".to_string()]);
+        assert!(left.is_empty());
+    }
+
+    #[test]
+    fn test_write_atomic_replaces_file() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("app.env");
+        fs::write(&path, "API_KEY=$API_KEY\n").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+        }
+
+        write_atomic(&path, b"API_KEY=sk_test_123\n").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "API_KEY=sk_test_123\n");
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 1, "temporary file left behind");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o640);
+
+            // A symlinked target keeps its link
+            let link = temp_dir.path().join("link.env");
+            std::os::unix::fs::symlink(&path, &link).unwrap();
+            write_atomic(&link, b"API_KEY=$API_KEY\n").unwrap();
+            assert!(fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+            assert_eq!(fs::read_to_string(&path).unwrap(), "API_KEY=$API_KEY\n");
+        }
+
+        assert!(write_atomic(&temp_dir.path().join("missing/app.env"), b"x").is_err());
+    }
+
+    #[test]
+    fn test_find_placeholders() {
+        let content = "# app\r\nurl = \"https://${HOST}/v1\"\ntoken = ${TOKEN|base64|upper}  é $DB_URL\nport = ${PORT:-8080}\n";
+        let hits = find_placeholders(content);
+
+        let keys: Vec<&str> = hits.iter().map(|hit| hit.key.as_str()).collect();
+        assert_eq!(keys, vec!["HOST", "TOKEN", "DB_URL", "PORT"]);
+        assert_eq!((hits[0].line, hits[0].column, hits[0].braced), (2, 16, true));
+        assert_eq!(&content[hits[0].start..hits[0].end], "${HOST}");
+        assert_eq!(hits[0].context, "url = \"https://${HOST}/v1\"");
+        assert_eq!(hits[1].transforms, vec!["base64".to_string(), "upper".to_string()]);
+        assert_eq!((hits[2].line, hits[2].column, hits[2].braced), (3, 34, false));
+        assert_eq!(hits[3].default.as_deref(), Some("8080"));
+        assert!(find_placeholders("price: 5$").is_empty());
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_write_atomic_keeps_windows_attributes() {
+        use std::os::windows::fs::MetadataExt;
+        use windows_sys::Win32::Storage::FileSystem::{FILE_ATTRIBUTE_HIDDEN, FILE_ATTRIBUTE_READONLY};
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("app.env");
+        fs::write(&path, "API_KEY=$API_KEY\n").unwrap();
+        let attributes = fs::metadata(&path).unwrap().file_attributes() | FILE_ATTRIBUTE_HIDDEN | FILE_ATTRIBUTE_READONLY;
+        set_file_attributes(&path, attributes).unwrap();
+
+        let backup = FileBackup::create(&path).unwrap();
+        write_atomic(&path, b"API_KEY=sk_test_123\n").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "API_KEY=sk_test_123\n");
+        assert_eq!(fs::metadata(&path).unwrap().file_attributes() & FILE_ATTRIBUTE_HIDDEN, FILE_ATTRIBUTE_HIDDEN);
+
+        backup.restore().unwrap();
+        let restored = fs::metadata(&path).unwrap().file_attributes();
+        assert_eq!(restored & (FILE_ATTRIBUTE_HIDDEN | FILE_ATTRIBUTE_READONLY), FILE_ATTRIBUTE_HIDDEN | FILE_ATTRIBUTE_READONLY);
+        set_file_attributes(&path, restored & !FILE_ATTRIBUTE_READONLY).unwrap();
+    }
+
+    #[test]
+    fn test_inject_yaml_keeps_anchors_and_merge_keys() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("docker-compose.yml");
+        let template = "x-env: &env\n  API_KEY: $API_KEY\n  LOG: info\nservices:\n  api:\n    environment:\n      <<: *env\n      PORT: \"8080\"\n  worker:\n    environment: *env\n";
+        fs::write(&path, template).unwrap();
+
+        let secrets = HashMap::from([("API_KEY".to_string(), "sk_test_123".to_string())]);
+        let backup = inject_secrets(&path, &secrets, &["$API_KEY".to_string()]).unwrap();
+
+        let injected = fs::read_to_string(&path).unwrap();
+        assert_eq!(injected, template.replace("$API_KEY", "sk_test_123"));
+        let mut parsed: serde_yaml::Value = serde_yaml::from_str(&injected).unwrap();
+        parsed.apply_merge().unwrap();
+        assert_eq!(parsed["services"]["api"]["environment"]["API_KEY"], "sk_test_123");
+        assert_eq!(parsed["services"]["worker"]["environment"]["API_KEY"], "sk_test_123");
+
+        backup.restore().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), template);
+    }
 }
@@ -0,0 +1,139 @@
+//! Per-user isolation on shared machines (build servers, jump hosts).
+//!
+//! Shadow Secret's state (audit log, push transcripts and fingerprints)
+//! lives in the user's own `~/.config/shadow-secret`. [`state_dir`] makes
+//! sure that directory is private (`0700`) and belongs to the user running
+//! the command, so a `HOME` inherited through `sudo` or pointing at a
+//! colleague's account is refused instead of silently written to.
+//!
+//! Targets get the same treatment: [`check_owner`] refuses to inject into a
+//! file owned by another user (a group-writable checkout shared by several
+//! developers), and [`is_shared_readable`] flags targets other users could
+//! read while secrets are injected. Root may work on any user's files.
+//!
+//! On non-Unix platforms ownership is not checked.
+
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+/// Shadow Secret's per-user state directory, created private if missing.
+///
+/// # Errors
+///
+/// Fails if the directory belongs to another user.
+pub fn state_dir() -> Result<PathBuf> {
+    let dir = crate::init::get_global_config_dir()?;
+    ensure_private_dir(&dir)?;
+    Ok(dir)
+}
+
+/// Create `dir` (mode `0700`) if missing; otherwise check that the current
+/// user owns it and remove group/other access.
+pub fn ensure_private_dir(dir: &Path) -> Result<()> {
+    if !dir.exists() {
+        std::fs::create_dir_all(dir).with_context(|| format!("Failed to create directory: {:?}", dir))?;
+    }
+    check_owner(dir)?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mode = std::fs::metadata(dir)?.permissions().mode();
+        if mode & 0o077 != 0 {
+            std::fs::set_permissions(dir, std::fs::Permissions::from_mode(mode & 0o700))
+                .with_context(|| format!("Failed to restrict permissions of {:?}", dir))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Refuse `path` if it belongs to another user (root may use any path).
+pub fn check_owner(path: &Path) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+
+        let owner = std::fs::metadata(path)
+            .with_context(|| format!("Failed to read metadata of {:?}", path))?
+            .uid();
+        let current = current_uid();
+        if current != 0 && owner != current {
+            anyhow::bail!(
+                "{:?} belongs to uid {}, not to the current user (uid {}); refusing cross-user access",
+                path,
+                owner,
+                current
+            );
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+
+    Ok(())
+}
+
+/// Whether users other than the owner can read `path`.
+pub fn is_shared_readable(path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        std::fs::metadata(path)
+            .map(|metadata| metadata.permissions().mode() & 0o044 != 0)
+            .unwrap_or(false)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        false
+    }
+}
+
+#[cfg(unix)]
+pub(crate) fn current_uid() -> u32 {
+    // SAFETY: geteuid has no preconditions and cannot fail
+    unsafe { libc::geteuid() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_ensure_private_dir_restricts_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let created = temp_dir.path().join("state");
+        ensure_private_dir(&created).unwrap();
+
+        let existing = temp_dir.path().join("shared");
+        std::fs::create_dir(&existing).unwrap();
+        std::fs::set_permissions(&existing, std::fs::Permissions::from_mode(0o775)).unwrap();
+        ensure_private_dir(&existing).unwrap();
+
+        for dir in [&created, &existing] {
+            let mode = std::fs::metadata(dir).unwrap().permissions().mode();
+            assert_eq!(mode & 0o077, 0, "{:?} is not private", dir);
+        }
+        check_owner(&existing).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_is_shared_readable() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("app.env");
+        std::fs::write(&path, "KEY=$KEY\n").unwrap();
+
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+        assert!(is_shared_readable(&path));
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600)).unwrap();
+        assert!(!is_shared_readable(&path));
+    }
+}
@@ -0,0 +1,390 @@
+//! Crash-recovery journal of unlock sessions.
+//!
+//! Backups of injected targets live in memory (see [`crate::cleaner`]), so a
+//! session killed with SIGKILL, or a machine that loses power, would leave
+//! secrets in the targets with nothing to restore them from. While a
+//! session is unlocked, its backups are also written to
+//! `~/.config/shadow-secret/journal/<id>.age`, encrypted to the age
+//! recipients of the project's vault: the journal is useless without the
+//! project's key. It is deleted once the session restored everything.
+//!
+//! The journal is written ahead: [`begin`] creates it before the first
+//! target is touched, and [`record`] adds each target (and syncs the file
+//! to disk) before the target is modified or created. A crash at any point
+//! of the unlock leaves a journal covering every modified target.
+//!
+//! `journal list` shows leftover journals, and `journal restore` puts the
+//! templates back from one of them.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Directory of the journals, inside the per-user state directory.
+pub(crate) const JOURNAL_DIR: &str = "journal";
+
+/// One target of a journaled session.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    /// Absolute path, so the journal can be restored from any directory
+    pub path: String,
+    /// Content before unlock; `None` for files created by unlock (deleted
+    /// on restore)
+    pub backup: Option<String>,
+}
+
+/// Everything needed to restore the targets of one session.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Journal {
+    /// RFC 3339 time the session was unlocked
+    pub created_at: String,
+    /// Process holding the session
+    pub pid: u32,
+    pub entries: Vec<JournalEntry>,
+}
+
+/// A journal file, as shown by `journal list` (nothing is decrypted).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JournalFile {
+    pub id: String,
+    pub path: PathBuf,
+    pub pid: Option<u32>,
+}
+
+impl JournalFile {
+    /// Whether the session that wrote the journal is still running.
+    pub fn is_active(&self) -> bool {
+        self.pid.is_some_and(is_running)
+    }
+}
+
+impl Journal {
+    /// Put every target back: rewrite backups, delete created files.
+    ///
+    /// # Returns
+    ///
+    /// The paths restored or deleted.
+    pub fn restore(&self) -> Result<Vec<String>> {
+        let mut restored = Vec::new();
+        for entry in &self.entries {
+            match &entry.backup {
+                Some(content) => crate::injector::write_atomic(Path::new(&entry.path), content.as_bytes())
+                    .with_context(|| format!("Failed to restore {}", entry.path))?,
+                None => match std::fs::remove_file(&entry.path) {
+                    Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                        return Err(e).with_context(|| format!("Failed to delete {}", entry.path))
+                    }
+                    _ => {}
+                },
+            }
+            restored.push(entry.path.clone());
+        }
+        Ok(restored)
+    }
+}
+
+/// Default journal directory (`~/.config/shadow-secret/journal`).
+pub fn journal_dir() -> Result<PathBuf> {
+    let dir = crate::isolation::state_dir()?.join(JOURNAL_DIR);
+    crate::isolation::ensure_private_dir(&dir)?;
+    Ok(dir)
+}
+
+/// Parse `age1...` recipients.
+pub fn parse_recipients(recipients: &BTreeSet<String>) -> Result<Vec<age::x25519::Recipient>> {
+    recipients
+        .iter()
+        .map(|recipient| {
+            recipient
+                .parse()
+                .map_err(|e| anyhow::anyhow!("Invalid age recipient '{}': {}", recipient, e))
+        })
+        .collect()
+}
+
+/// Age recipients of the vault file at `vault_path`, else those of the
+/// `.sops.yaml` rule governing it.
+pub fn vault_file_recipients(vault_path: &Path) -> Result<Vec<age::x25519::Recipient>> {
+    let content = std::fs::read_to_string(vault_path)
+        .with_context(|| format!("Failed to read vault file: {}", vault_path.display()))?;
+    let mut recipients = crate::fsck::vault_recipients(&content);
+    if recipients.is_empty() {
+        if let Some((_, configured)) = crate::fsck::sops_config_recipients(vault_path)? {
+            recipients = configured;
+        }
+    }
+    parse_recipients(&recipients)
+}
+
+/// Name of journals in messages.
+const WHAT: &str = "recovery journal";
+
+fn encrypt(journal: &Journal, recipients: &[age::x25519::Recipient]) -> Result<Vec<u8>> {
+    crate::age_file::encrypt(serde_json::to_string(journal)?.as_bytes(), recipients, WHAT)
+}
+
+/// Encrypt `journal` to `recipients` into a new file of `dir`.
+pub fn write(dir: &Path, journal: &Journal, recipients: &[age::x25519::Recipient]) -> Result<PathBuf> {
+    let encrypted = encrypt(journal, recipients)?;
+    crate::age_file::create(dir, &crate::age_file::id(&journal.created_at, journal.pid), &encrypted, WHAT)
+}
+
+/// Replace the journal at `path` atomically: a crash leaves either the old
+/// or the new journal.
+fn rewrite(path: &Path, journal: &Journal, recipients: &[age::x25519::Recipient]) -> Result<()> {
+    let encrypted = encrypt(journal, recipients)?;
+    crate::injector::write_atomic(path, &encrypted)
+        .with_context(|| format!("Failed to write recovery journal: {}", path.display()))
+}
+
+/// A journal kept up to date while targets are injected.
+pub struct JournalWriter {
+    path: PathBuf,
+    recipients: Vec<age::x25519::Recipient>,
+    journal: Journal,
+}
+
+impl JournalWriter {
+    /// Write an empty journal for this process into `dir`.
+    pub fn create(dir: &Path, recipients: Vec<age::x25519::Recipient>) -> Result<Self> {
+        let journal = Journal {
+            created_at: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            pid: std::process::id(),
+            entries: Vec::new(),
+        };
+        let path = write(dir, &journal, &recipients)?;
+        Ok(Self {
+            path,
+            recipients,
+            journal,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Add `path` (relative to the current directory, as target paths are)
+    /// with its content before unlock (`None` for a file unlock creates),
+    /// and persist the journal. A path already recorded keeps its first
+    /// backup: that is the original content.
+    pub fn record(&mut self, path: &str, backup: Option<&str>) -> Result<()> {
+        let path = absolute_path(Path::new(path))?;
+        if self.journal.entries.iter().any(|entry| entry.path == path) {
+            return Ok(());
+        }
+        self.journal.entries.push(JournalEntry {
+            path,
+            backup: backup.map(str::to_string),
+        });
+        self.journal.entries.sort_by(|a, b| a.path.cmp(&b.path));
+        rewrite(&self.path, &self.journal, &self.recipients)
+    }
+}
+
+/// `path` made absolute: its directory resolved (symlinks and `..`
+/// included) when it exists, else joined to the current directory.
+pub(crate) fn absolute_path(path: &Path) -> Result<String> {
+    let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let absolute = match (dir.canonicalize(), path.file_name()) {
+        (Ok(dir), Some(name)) => dir.join(name),
+        _ => std::path::absolute(path).with_context(|| format!("Failed to resolve {}", path.display()))?,
+    };
+    Ok(absolute.to_string_lossy().to_string())
+}
+
+/// Journal of the session in progress, if any.
+static ACTIVE: Mutex<Option<JournalWriter>> = Mutex::new(None);
+
+/// Start journaling this session into [`journal_dir`] (see the module
+/// documentation). The journal is deleted by [`crate::cleaner`] once every
+/// target is restored.
+pub fn begin(recipients: Vec<age::x25519::Recipient>) -> Result<PathBuf> {
+    let writer = JournalWriter::create(&journal_dir()?, recipients)?;
+    let path = writer.path().to_path_buf();
+    crate::cleaner::register_journal(path.clone());
+    *ACTIVE.lock().unwrap_or_else(|e| e.into_inner()) = Some(writer);
+    Ok(path)
+}
+
+/// Whether this session is journaled.
+pub fn is_active() -> bool {
+    ACTIVE.lock().unwrap_or_else(|e| e.into_inner()).is_some()
+}
+
+/// Record a target in the session's journal before modifying it (see
+/// [`JournalWriter::record`]); does nothing if the session is not
+/// journaled.
+pub fn record(path: &str, backup: Option<&str>) -> Result<()> {
+    match ACTIVE.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+        Some(writer) => writer.record(path, backup),
+        None => Ok(()),
+    }
+}
+
+/// Stop journaling (the session was locked).
+pub fn end() {
+    ACTIVE.lock().unwrap_or_else(|e| e.into_inner()).take();
+}
+
+/// Decrypt the journal at `path` (see [`crate::age_file::decrypt`]).
+pub fn read(path: &Path, age_key_path: Option<&str>) -> Result<Journal> {
+    let plaintext = crate::age_file::decrypt(path, age_key_path, WHAT)?;
+    serde_json::from_slice(&plaintext).with_context(|| format!("Corrupted recovery journal: {}", path.display()))
+}
+
+/// Journals in `dir`, oldest first.
+pub fn list(dir: &Path) -> Result<Vec<JournalFile>> {
+    Ok(crate::age_file::list(dir)?
+        .into_iter()
+        .map(|(id, path)| {
+            let pid = id.rsplit_once('-').and_then(|(_, pid)| pid.parse().ok());
+            JournalFile { id, path, pid }
+        })
+        .collect())
+}
+
+/// Find the journal `id` in `dir`.
+pub fn find(dir: &Path, id: &str) -> Result<JournalFile> {
+    list(dir)?
+        .into_iter()
+        .find(|journal| journal.id == id)
+        .with_context(|| format!("No recovery journal '{}' (see 'shadow-secret journal list')", id))
+}
+
+#[cfg(unix)]
+fn is_running(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    // SAFETY: signal 0 only checks that the process exists
+    let result = unsafe { libc::kill(pid, 0) };
+    result == 0 || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(not(unix))]
+fn is_running(_pid: u32) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use age::secrecy::ExposeSecret;
+
+    #[test]
+    fn test_write_read_and_restore() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let identity = age::x25519::Identity::generate();
+        let key_path = temp_dir.path().join("keys.txt");
+        std::fs::write(&key_path, identity.to_string().expose_secret()).unwrap();
+
+        let target = temp_dir.path().join("app.env");
+        let created = temp_dir.path().join("demo.env");
+        std::fs::write(&target, "API_KEY=sk_live_leaked\n").unwrap();
+        std::fs::write(&created, "KEY=fake\n").unwrap();
+        let journal = Journal {
+            created_at: "2026-01-01T00:00:00Z".to_string(),
+            pid: 4242,
+            entries: vec![
+                JournalEntry {
+                    path: target.to_string_lossy().to_string(),
+                    backup: Some("API_KEY=$API_KEY\n".to_string()),
+                },
+                JournalEntry {
+                    path: created.to_string_lossy().to_string(),
+                    backup: None,
+                },
+            ],
+        };
+
+        let path = write(temp_dir.path(), &journal, &[identity.to_public()]).unwrap();
+        let encrypted = std::fs::read(&path).unwrap();
+        assert!(!String::from_utf8_lossy(&encrypted).contains("API_KEY"));
+
+        let listed = list(temp_dir.path()).unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].id, "20260101T000000-4242");
+        assert_eq!(listed[0].pid, Some(4242));
+
+        let read_back = read(&path, Some(&key_path.to_string_lossy())).unwrap();
+        assert_eq!(read_back, journal);
+
+        read_back.restore().unwrap();
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "API_KEY=$API_KEY\n");
+        assert!(!created.exists());
+    }
+
+    #[test]
+    fn test_read_needs_a_recipient_key() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let journal = Journal {
+            created_at: "2026-01-01T00:00:00Z".to_string(),
+            pid: 1,
+            entries: Vec::new(),
+        };
+        let path = write(temp_dir.path(), &journal, &[age::x25519::Identity::generate().to_public()]).unwrap();
+
+        let other = temp_dir.path().join("other.txt");
+        std::fs::write(&other, age::x25519::Identity::generate().to_string().expose_secret()).unwrap();
+        assert!(read(&path, Some(&other.to_string_lossy())).is_err());
+    }
+
+    #[test]
+    fn test_writer_records_targets_ahead() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let identity = age::x25519::Identity::generate();
+        let key_path = temp_dir.path().join("keys.txt");
+        std::fs::write(&key_path, identity.to_string().expose_secret()).unwrap();
+        let key_path = key_path.to_string_lossy().to_string();
+
+        let mut writer = JournalWriter::create(temp_dir.path(), vec![identity.to_public()]).unwrap();
+        assert!(read(writer.path(), Some(&key_path)).unwrap().entries.is_empty());
+
+        writer.record("/project/b.env", Some("B=$B\n")).unwrap();
+        writer.record("/project/a.env", None).unwrap();
+        // Injected twice: the first backup is the original
+        writer.record("/project/b.env", Some("B=leaked\n")).unwrap();
+
+        let journal = read(writer.path(), Some(&key_path)).unwrap();
+        assert_eq!(
+            journal.entries,
+            vec![
+                JournalEntry { path: "/project/a.env".to_string(), backup: None },
+                JournalEntry { path: "/project/b.env".to_string(), backup: Some("B=$B\n".to_string()) },
+            ]
+        );
+        // No temporary file left behind
+        assert_eq!(std::fs::read_dir(temp_dir.path()).unwrap().count(), 2);
+        assert_eq!(list(temp_dir.path()).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_relative_targets_restore_from_any_directory() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let identity = age::x25519::Identity::generate();
+        let key_path = temp_dir.path().join("keys.txt");
+        std::fs::write(&key_path, identity.to_string().expose_secret()).unwrap();
+        let project = temp_dir.path().canonicalize().unwrap().join("project");
+        std::fs::create_dir(&project).unwrap();
+        let target = project.join(".env");
+        std::fs::write(&target, "API_KEY=$API_KEY\n").unwrap();
+
+        // Target paths are relative to the directory unlock runs in
+        let current_dir = std::env::current_dir().unwrap().canonicalize().unwrap();
+        let up = "../".repeat(current_dir.components().count() - 1);
+        let relative = format!("{}{}", up, target.strip_prefix("/").unwrap().display());
+        assert!(Path::new(&relative).is_relative());
+
+        let mut writer = JournalWriter::create(temp_dir.path(), vec![identity.to_public()]).unwrap();
+        writer.record(&relative, Some("API_KEY=$API_KEY\n")).unwrap();
+        std::fs::write(&target, "API_KEY=sk_live_leaked\n").unwrap();
+
+        let journal = read(writer.path(), Some(&key_path.to_string_lossy())).unwrap();
+        assert_eq!(journal.entries[0].path, target.to_string_lossy());
+        journal.restore().unwrap();
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "API_KEY=$API_KEY\n");
+    }
+}
@@ -0,0 +1,218 @@
+//! Time-limited secret leases.
+//!
+//! Keys listed under `leases:` are ephemeral credentials: unlocking starts
+//! a timer for each of them, and when it runs out, or when the session
+//! locks (Enter, Ctrl+C, panic), the lease's `revoke` command runs once,
+//! e.g. a script calling the provider's API to rotate the token:
+//!
+//! ```yaml
+//! leases:
+//!   - key: DEPLOY_TOKEN
+//!     ttl: 1h
+//!     revoke: ["./scripts/revoke-token.sh"]
+//! ```
+//!
+//! The command is run without a shell. It gets the key in
+//! `$SHADOW_SECRET_LEASE_KEY`, the value in `$SHADOW_SECRET_LEASE_VALUE`
+//! (never on the command line) and `expired` or `locked` in
+//! `$SHADOW_SECRET_LEASE_REASON`. An expired lease does not lock the
+//! session: the injected value simply stops working.
+
+use crate::config::LeaseConfig;
+use crate::process;
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+/// Environment variable giving the hook the leased key.
+pub const LEASE_KEY_ENV: &str = "SHADOW_SECRET_LEASE_KEY";
+
+/// Environment variable giving the hook the leased value.
+pub const LEASE_VALUE_ENV: &str = "SHADOW_SECRET_LEASE_VALUE";
+
+/// Environment variable giving the hook why the lease ends.
+pub const LEASE_REASON_ENV: &str = "SHADOW_SECRET_LEASE_REASON";
+
+/// Why a lease is revoked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RevokeReason {
+    /// The lease's `ttl` ran out
+    Expired,
+    /// The session locked first
+    Locked,
+}
+
+impl std::fmt::Display for RevokeReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RevokeReason::Expired => write!(f, "expired"),
+            RevokeReason::Locked => write!(f, "locked"),
+        }
+    }
+}
+
+/// A lease of the current session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lease {
+    pub key: String,
+    pub value: String,
+    pub ttl: Duration,
+    /// Revocation command (program, then arguments)
+    pub revoke: Vec<String>,
+}
+
+/// Leases not revoked yet.
+static ACTIVE: Mutex<Vec<Lease>> = Mutex::new(Vec::new());
+
+fn active() -> MutexGuard<'static, Vec<Lease>> {
+    ACTIVE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Leases of `configs` whose key has a value in `secrets`.
+pub fn leases(configs: &[LeaseConfig], secrets: &HashMap<String, String>) -> Result<Vec<Lease>> {
+    let mut leases = Vec::new();
+    for config in configs {
+        let Some(value) = secrets.get(&config.key) else {
+            continue;
+        };
+        leases.push(Lease {
+            key: config.key.clone(),
+            value: value.clone(),
+            ttl: config.ttl()?,
+            revoke: config.revoke.clone(),
+        });
+    }
+    Ok(leases)
+}
+
+/// Start the leases of `configs` for the keys of `secrets`: each is revoked
+/// when its `ttl` runs out, unless [`revoke_all`] got to it first.
+///
+/// # Returns
+///
+/// The leases started.
+pub fn start(configs: &[LeaseConfig], secrets: &HashMap<String, String>) -> Result<Vec<Lease>> {
+    let leases = leases(configs, secrets)?;
+    active().extend(leases.iter().cloned());
+
+    for lease in &leases {
+        let (key, ttl) = (lease.key.clone(), lease.ttl);
+        std::thread::spawn(move || {
+            std::thread::sleep(ttl);
+            match revoke(&key, RevokeReason::Expired) {
+                Ok(true) => crate::say_err!("\n⌛ Lease on {} expired: revoked", key),
+                Ok(false) => {}
+                Err(e) => crate::say_err!("\n⚠️  Lease on {} expired, but revocation failed: {:#}", key, e),
+            }
+        });
+    }
+    Ok(leases)
+}
+
+/// Revoke the lease on `key`, if it is still active.
+///
+/// # Returns
+///
+/// Whether a lease was revoked.
+pub fn revoke(key: &str, reason: RevokeReason) -> Result<bool> {
+    let lease = {
+        let mut active = active();
+        match active.iter().position(|lease| lease.key == key) {
+            Some(index) => active.remove(index),
+            None => return Ok(false),
+        }
+    };
+    run_hook(&lease, reason)?;
+    Ok(true)
+}
+
+/// Revoke every active lease (on lock).
+///
+/// # Returns
+///
+/// The outcome for each leased key.
+pub fn revoke_all(reason: RevokeReason) -> Vec<(String, Result<()>)> {
+    let leases = std::mem::take(&mut *active());
+    leases
+        .into_iter()
+        .map(|lease| {
+            let result = run_hook(&lease, reason);
+            (lease.key, result)
+        })
+        .collect()
+}
+
+/// Run the revocation command of `lease`.
+pub fn run_hook(lease: &Lease, reason: RevokeReason) -> Result<()> {
+    let (program, args) = lease
+        .revoke
+        .split_first()
+        .with_context(|| format!("Lease on {} has no revoke command", lease.key))?;
+
+    let mut command = process::command(program)?;
+    command
+        .args(args)
+        .env(LEASE_KEY_ENV, &lease.key)
+        .env(LEASE_VALUE_ENV, &lease.value)
+        .env(LEASE_REASON_ENV, reason.to_string());
+
+    let output = process::output(&mut command)
+        .with_context(|| format!("Failed to run revoke command of lease on {}", lease.key))?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "Revoke command of lease on {} failed: {}",
+            lease.key,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockRunner;
+    use std::sync::Arc;
+
+    fn config(key: &str, ttl: &str) -> LeaseConfig {
+        LeaseConfig {
+            key: key.to_string(),
+            ttl: ttl.to_string(),
+            revoke: vec!["revoke-token".to_string(), "--now".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_leases_of_present_keys() {
+        let secrets = HashMap::from([("DEPLOY_TOKEN".to_string(), "tok_123".to_string())]);
+        let leases = leases(&[config("DEPLOY_TOKEN", "1h"), config("MISSING", "1h")], &secrets).unwrap();
+
+        assert_eq!(leases.len(), 1);
+        assert_eq!(leases[0].value, "tok_123");
+        assert_eq!(leases[0].ttl, Duration::from_secs(3600));
+        assert!(super::leases(&[config("DEPLOY_TOKEN", "soon")], &secrets).is_err());
+    }
+
+    #[test]
+    fn test_run_hook_passes_value_in_env() {
+        let lease = Lease {
+            key: "DEPLOY_TOKEN".to_string(),
+            value: "tok_123".to_string(),
+            ttl: Duration::from_secs(60),
+            revoke: vec!["revoke-token".to_string(), "--now".to_string()],
+        };
+
+        let runner = Arc::new(MockRunner::new().respond("revoke-token", &["--now"], 0, ""));
+        process::with_runner(runner.clone(), || run_hook(&lease, RevokeReason::Expired)).unwrap();
+        let calls = runner.calls();
+        assert_eq!(calls.len(), 1);
+        assert!(!calls[0].args.iter().any(|arg| arg.contains("tok_123")));
+        for (name, value) in [(LEASE_KEY_ENV, "DEPLOY_TOKEN"), (LEASE_VALUE_ENV, "tok_123"), (LEASE_REASON_ENV, "expired")] {
+            assert!(calls[0].env.contains(&(name.to_string(), value.to_string())), "{}", name);
+        }
+
+        let failing = Arc::new(MockRunner::new().respond("revoke-token", &["--now"], 1, ""));
+        assert!(process::with_runner(failing, || run_hook(&lease, RevokeReason::Locked)).is_err());
+    }
+}
@@ -9,3 +9,56 @@ pub mod cleaner;
 pub mod config;
 pub mod init;
 pub mod cloud;
+pub mod profile;
+pub mod signature;
+pub mod process;
+pub mod explain;
+pub mod sops;
+pub mod fsck;
+pub mod canary;
+pub mod fake;
+pub mod audit;
+pub mod scrub;
+pub mod target;
+pub mod rename;
+pub mod exec;
+pub mod age_native;
+pub mod preview;
+pub mod hashicorp;
+pub mod aws;
+pub mod gcp;
+pub mod testing;
+pub mod unlock;
+pub mod confirm;
+pub mod onepassword;
+pub mod bitwarden;
+pub mod isolation;
+pub mod remote;
+pub mod pass;
+pub mod journal;
+pub mod gc;
+pub mod rekey;
+pub mod status;
+pub mod lease;
+pub mod dynamic;
+pub mod deploy;
+pub mod binaries;
+pub mod report;
+pub mod usage;
+pub mod shape;
+pub mod diff;
+pub mod agent;
+pub mod tray;
+pub mod naming;
+pub mod provision;
+pub mod backup;
+pub mod accessible;
+pub mod alias;
+pub mod attempts;
+pub mod compliance;
+pub mod paths;
+pub mod health;
+pub mod table;
+pub mod sink;
+pub mod snapshot;
+pub mod age_file;
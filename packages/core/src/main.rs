@@ -2,16 +2,17 @@
 //
 // This is the main entry point for the application.
 
-mod cleaner;
-
 use anyhow::{Context, Result};
-use clap::{Parser, Subcommand};
-use shadow_secret::cloud::vercel::{detect_project_id, push_secrets_to_vercel};
-use shadow_secret::config::Config;
-use shadow_secret::vault::Vault;
-use std::collections::HashMap;
+use clap::{CommandFactory, Parser, Subcommand};
+use shadow_secret::cloud::vercel::{detect_project_id, push_secrets_to_vercel, PushOutcome, PushReport};
+use shadow_secret::config::{verify_config_signature, Config, SignatureCheck, TargetConfig};
+use shadow_secret::process;
+use shadow_secret::{say, say_err};
+use shadow_secret::sops::SopsError;
+use shadow_secret::unlock::{UnlockReport, UnlockSession};
+use shadow_secret::vault::{LazyVault, LocalOverlay, Vault};
+use std::collections::{BTreeMap, HashMap};
 use std::path::{Path, PathBuf};
-use std::process::Command;
 
 /// Shadow Secret - A secure, distributed secret management system
 #[derive(Parser, Debug)]
@@ -20,6 +21,26 @@ use std::process::Command;
 #[command(version = "0.5.6")]
 #[command(about = "A secure, distributed secret management system", long_about = None)]
 struct Cli {
+    /// Hardened mode: drop capabilities, only spawn sops/age, never kill processes
+    #[arg(long, global = true, default_value = "false")]
+    restricted: bool,
+
+    /// Print every external command and file to be touched, then ask for confirmation
+    #[arg(long, global = true, default_value = "false")]
+    explain: bool,
+
+    /// Refuse to run sops/age/vercel binaries that changed since first use (implied by --restricted)
+    #[arg(long, global = true, default_value = "false")]
+    strict_binaries: bool,
+
+    /// Screen-reader friendly output: words (PASS, FAIL, WARNING) instead of emoji, plain prompts
+    #[arg(long, global = true, default_value = "false")]
+    accessible: bool,
+
+    /// Global config directory (default: $SHADOW_SECRET_CONFIG_DIR, ~/.config/shadow-secret, or /etc/shadow-secret without a home)
+    #[arg(long, global = true, value_name = "DIR")]
+    config_dir: Option<PathBuf>,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -34,10 +55,141 @@ enum Commands {
         /// Path to the configuration file (default: project.yaml)
         #[arg(short, long, default_value = "project.yaml")]
         config: String,
+
+        /// Refuse to run unless the config has a valid signature
+        #[arg(long, default_value = "false")]
+        require_signature: bool,
+
+        /// Write injected copies of the targets into this directory instead
+        /// of modifying them (e.g. ./.shadow-preview)
+        #[arg(long)]
+        preview_dir: Option<String>,
+
+        /// Inject deterministic fake values instead of real secrets (for
+        /// demos and screenshots; nothing is decrypted)
+        #[arg(long, default_value = "false")]
+        fake: bool,
+
+        /// Fail before modifying any file if a placeholder has no value
+        /// (as if every target set `strict: true`)
+        #[arg(long, default_value = "false")]
+        strict: bool,
+
+        /// Decrypt and print what each target would become (values masked),
+        /// without writing anything
+        #[arg(long, default_value = "false", conflicts_with = "preview_dir")]
+        dry_run: bool,
+
+        /// Lock on its own after this long (e.g. 30m, 2h; overrides
+        /// timeouts.session, `none` for no limit)
+        #[arg(long)]
+        timeout: Option<String>,
+
+        /// Wait until no other process has a target open before injecting
+        #[arg(long, default_value = "false")]
+        wait: bool,
+
+        /// Copy every target into an encrypted snapshot before modifying
+        /// anything (see 'restore-snapshot')
+        #[arg(long, default_value = "false", conflicts_with_all = ["preview_dir", "dry_run"])]
+        snapshot: bool,
     },
 
     /// Unlock global secrets (global config only)
-    UnlockGlobal,
+    UnlockGlobal {
+        /// Lock on its own after this long (e.g. 30m, 2h; overrides
+        /// timeouts.session, `none` for no limit)
+        #[arg(long)]
+        timeout: Option<String>,
+
+        /// Wait until no other process has a target open before injecting
+        #[arg(long, default_value = "false")]
+        wait: bool,
+    },
+
+    /// Check the integrity of the encrypted vault file(s)
+    Fsck {
+        /// Path to the configuration file (default: project.yaml)
+        #[arg(short, long, default_value = "project.yaml")]
+        config: String,
+    },
+
+    /// List secret names in the vault (values are never printed)
+    List {
+        /// Path to the configuration file (default: project.yaml)
+        #[arg(short, long, default_value = "project.yaml")]
+        config: String,
+
+        /// Show which source each value comes from and what it overrides
+        #[arg(long, default_value = "false")]
+        provenance: bool,
+
+        /// Table columns, comma-separated: key, source, overrides, encrypted, last-used, length
+        #[arg(long, value_name = "COLUMNS")]
+        columns: Option<String>,
+
+        /// Sort the table by key, last use (most recent first) or value length (longest first)
+        #[arg(long, value_parser = ["key", "last-used", "length"])]
+        sort: Option<String>,
+
+        /// Print JSON: an array of key names, or of rows with the table options
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Clear vault values from the clipboard and shell history
+    Scrub {
+        /// Path to the configuration file (default: project.yaml)
+        #[arg(short, long, default_value = "project.yaml")]
+        config: String,
+
+        /// Redact history files without asking
+        #[arg(short, long, default_value = "false")]
+        yes: bool,
+    },
+
+    /// Print a single secret to stdout (decrypts only that key)
+    Get {
+        /// Name of the secret (e.g., API_KEY)
+        key: String,
+
+        /// Path to the configuration file (default: project.yaml)
+        #[arg(short, long, default_value = "project.yaml")]
+        config: String,
+
+        /// Describe the value (length, characters, format) instead of printing it
+        #[arg(long)]
+        shape: bool,
+
+        /// Copy the value to the clipboard instead of printing it
+        #[arg(long, conflicts_with = "shape")]
+        copy: bool,
+    },
+
+    /// Add or update a secret in the encrypted vault (value from stdin or a hidden prompt)
+    Set {
+        /// Name of the secret (e.g., API_KEY)
+        key: String,
+
+        /// Path to the configuration file (default: project.yaml)
+        #[arg(short, long, default_value = "project.yaml")]
+        config: String,
+    },
+
+    /// Run a command with secrets as environment variables (nothing written to disk)
+    Exec {
+        /// Path to the configuration file (default: project.yaml)
+        #[arg(short, long, default_value = "project.yaml")]
+        config: String,
+
+        /// Only pass these secrets (comma-separated; default: all)
+        #[arg(long, value_delimiter = ',')]
+        only: Vec<String>,
+
+        /// Command to run, after `--` (e.g. `-- npm run dev`)
+        #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        command: Vec<String>,
+    },
 
     /// Initialize a new project with secret management infrastructure
     InitProject {
@@ -52,10 +204,188 @@ enum Commands {
         /// Don't prompt to add to global config
         #[arg(long, default_value = "false")]
         no_global: bool,
+
+        /// Signed organization profile to apply (URL or path)
+        #[arg(long)]
+        profile: Option<String>,
+
+        /// Trusted organization public key (minisign key or path to .pub file)
+        #[arg(long, requires = "profile")]
+        profile_key: Option<String>,
+
+        /// Show the files that would be created or modified, with diffs, and exit
+        #[arg(long)]
+        plan: bool,
+
+        /// Overwrite an existing .sops.yaml, .enc.env or project.yaml
+        #[arg(long)]
+        force: bool,
     },
 
     /// Initialize global Shadow Secret configuration
-    InitGlobal,
+    InitGlobal {
+        /// Show the files that would be created or modified, with diffs, and exit
+        #[arg(long)]
+        plan: bool,
+
+        /// Overwrite an existing .sops.yaml or global.yaml
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Guided first-run setup (doctor, age key, global and project init)
+    Setup {
+        /// Accept every default without prompting
+        #[arg(short, long, default_value = "false")]
+        yes: bool,
+    },
+
+    /// Manage canary secrets used to detect leaks
+    Canary {
+        #[command(subcommand)]
+        action: CanaryAction,
+    },
+
+    /// Maintain secrets in the vault
+    Vault {
+        #[command(subcommand)]
+        action: VaultAction,
+    },
+
+    /// Manage injection targets in project.yaml
+    Target {
+        #[command(subcommand)]
+        action: TargetAction,
+    },
+
+    /// Inspect and restore crash-recovery journals of unlock sessions
+    Journal {
+        #[command(subcommand)]
+        action: JournalAction,
+    },
+
+    /// Put the targets back from a snapshot taken by 'unlock --snapshot'
+    RestoreSnapshot {
+        /// Snapshot ID, as shown by --list (default: the latest)
+        id: Option<String>,
+
+        /// List the snapshots instead of restoring one
+        #[arg(long, conflicts_with = "id")]
+        list: bool,
+
+        /// age key file able to decrypt the snapshot (default: as SOPS finds it)
+        #[arg(long)]
+        age_key: Option<String>,
+    },
+
+    /// Remove stale state: interrupted-session journals and temporary files
+    Gc {
+        /// Only remove files older than this (e.g. 30d, 12h)
+        #[arg(long, default_value = "30d")]
+        older_than: String,
+
+        /// List what would be removed without removing it
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Re-key the global vault and every registered project for a new age key
+    Rekey {
+        /// New age public key to encrypt for (e.g. age1...)
+        #[arg(long)]
+        recipient: String,
+
+        /// Old age public key to stop encrypting for
+        #[arg(long)]
+        remove: Option<String>,
+    },
+
+    /// Summarize the local audit log: sessions, pushes, most used keys
+    Report {
+        /// Only count entries this recent (e.g. 30d, 12h; default: all)
+        #[arg(long)]
+        since: Option<String>,
+
+        /// Output format
+        #[arg(long, default_value = "markdown", value_parser = ["markdown", "table", "json"])]
+        format: String,
+    },
+
+    /// Print the guarantees of a configuration as JSON, for archiving
+    ComplianceReport {
+        /// Path to project config file
+        #[arg(short, long, default_value = "project.yaml")]
+        config: String,
+
+        /// Write the report to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Show what each target holds, vault and key health, and the session status
+    Status {
+        /// Print the injection report of the last unlock
+        #[arg(long)]
+        last: bool,
+
+        /// Path to project config file
+        #[arg(short, long, default_value = "project.yaml")]
+        config: String,
+    },
+
+    /// Record the current sops/age/vercel binaries as trusted (after an upgrade)
+    TrustBinaries {
+        /// Programs to trust (default: every tracked program found in PATH)
+        programs: Vec<String>,
+    },
+
+    /// Unlock a project on another machine over SSH (decryption stays local)
+    Remote {
+        #[command(subcommand)]
+        action: RemoteAction,
+    },
+
+    /// Keep the decrypted vault in memory and serve it on a local socket
+    Agent {
+        #[command(subcommand)]
+        action: AgentAction,
+    },
+
+    /// Show the agent's lock state in the system tray, with Unlock/Lock/Extend actions
+    Tray {
+        /// How long Unlock injects the targets, e.g. 1h (default: the session timeout of the agent's config)
+        #[arg(long, value_name = "DURATION")]
+        unlock_for: Option<String>,
+
+        /// How much Extend adds to the session
+        #[arg(long, value_name = "DURATION", default_value = "15m")]
+        extend_by: String,
+    },
+
+    /// Back up the encrypted vaults of every registered project (ciphertext only)
+    Backup {
+        #[command(subcommand)]
+        action: BackupAction,
+    },
+
+    /// Move the age key and global config to a new machine over a direct, code-authenticated channel
+    Provision {
+        /// Old machine to receive from (host or host:port)
+        #[arg(long, required_unless_present = "send", conflicts_with = "send")]
+        from: Option<String>,
+
+        /// Send this machine's key and global config to a new machine
+        #[arg(long)]
+        send: bool,
+
+        /// Port to listen on with --send
+        #[arg(long, default_value_t = shadow_secret::provision::DEFAULT_PORT)]
+        port: u16,
+
+        /// Overwrite an existing key or global config on this machine
+        #[arg(long)]
+        force: bool,
+    },
 
     /// Push secrets from local .enc.env to Vercel cloud
     PushCloud {
@@ -67,9 +397,80 @@ enum Commands {
         #[arg(short, long)]
         project: Option<String>,
 
+        /// Vercel environment to push to (production, preview, development; default: all)
+        #[arg(short, long)]
+        env: Option<String>,
+
+        /// Ticket or justification (required when pushing to production)
+        #[arg(long)]
+        reason: Option<String>,
+
+        /// Webhook notified with the audit entry (default: $SHADOW_SECRET_AUDIT_WEBHOOK)
+        #[arg(long)]
+        webhook: Option<String>,
+
+        /// Store variables as sensitive (values cannot be read back from Vercel)
+        #[arg(long, default_value = "false")]
+        sensitive: bool,
+
+        /// Scope variables to a git branch (requires --env preview)
+        #[arg(long)]
+        git_branch: Option<String>,
+
         /// Dry run - show what would be pushed without actually pushing
         #[arg(long, default_value = "false")]
         dry_run: bool,
+
+        /// Refuse to run unless the config has a valid signature
+        #[arg(long, default_value = "false")]
+        require_signature: bool,
+    },
+
+    /// Unlock, run a build command, push to Vercel, then lock (even on failure)
+    Deploy {
+        /// Path to the configuration file (default: project.yaml)
+        #[arg(short, long, default_value = "project.yaml")]
+        config: String,
+
+        /// Build command, run through the shell while secrets are injected
+        /// (e.g. "pnpm build && vercel deploy")
+        #[arg(long)]
+        run: String,
+
+        /// Override Vercel project ID (auto-detected if not specified)
+        #[arg(short, long)]
+        project: Option<String>,
+
+        /// Vercel environment to push to (production, preview, development; default: all)
+        #[arg(short, long)]
+        env: Option<String>,
+
+        /// Ticket or justification (required when pushing to production)
+        #[arg(long)]
+        reason: Option<String>,
+
+        /// Webhook notified with the audit entry (default: $SHADOW_SECRET_AUDIT_WEBHOOK)
+        #[arg(long)]
+        webhook: Option<String>,
+
+        /// Skip the push-cloud step
+        #[arg(long, default_value = "false")]
+        no_push: bool,
+
+        /// Fail before modifying any file if a placeholder has no value
+        #[arg(long, default_value = "false")]
+        strict: bool,
+
+        /// Refuse to run unless the config has a valid signature
+        #[arg(long, default_value = "false")]
+        require_signature: bool,
+    },
+
+    /// Print the seccomp profile describing the restricted-mode syscall surface
+    SeccompProfile {
+        /// Write the profile to a file instead of stdout
+        #[arg(short, long)]
+        output: Option<String>,
     },
 
     /// Update Shadow Secret to latest version from NPM
@@ -80,6 +481,186 @@ enum Commands {
     },
 }
 
+#[derive(Subcommand, Debug)]
+enum VaultAction {
+    /// Rename a secret in the vault, the config and every target file
+    RenameKey {
+        /// Current name of the secret
+        old: String,
+
+        /// New name of the secret
+        new: String,
+
+        /// Path to the configuration file (default: project.yaml)
+        #[arg(short, long, default_value = "project.yaml")]
+        config: String,
+
+        /// Show what would change without changing anything
+        #[arg(long, default_value = "false")]
+        dry_run: bool,
+
+        /// Also rename the Vercel environment variable
+        #[arg(long, default_value = "false")]
+        cloud: bool,
+
+        /// Override Vercel project ID (auto-detected if not specified)
+        #[arg(short, long, requires = "cloud")]
+        project: Option<String>,
+
+        /// Vercel environment of the variable (default: all)
+        #[arg(short, long, requires = "cloud")]
+        env: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum JournalAction {
+    /// List journals left by unlock sessions (active or interrupted)
+    List,
+
+    /// Restore the targets recorded in a journal, then delete it
+    Restore {
+        /// Journal ID, as shown by 'journal list'
+        id: String,
+
+        /// age key file able to decrypt the journal (default: as SOPS finds it)
+        #[arg(long)]
+        age_key: Option<String>,
+
+        /// Restore even if the session that wrote the journal is still running
+        #[arg(long, default_value = "false")]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum RemoteAction {
+    /// Decrypt locally and inject the targets of a project on HOST
+    Unlock {
+        /// SSH destination (e.g. deploy@build-1)
+        host: String,
+
+        /// Project directory on the remote host
+        #[arg(long)]
+        project: String,
+
+        /// Local configuration file describing vault and targets (default: project.yaml)
+        #[arg(short, long, default_value = "project.yaml")]
+        config: String,
+
+        /// Refuse to run unless the config has a valid signature
+        #[arg(long, default_value = "false")]
+        require_signature: bool,
+
+        /// shadow-secret binary on the remote host
+        #[arg(long, default_value = "shadow-secret")]
+        remote_bin: String,
+    },
+
+    /// Receive secrets from 'remote unlock' on stdin (run over SSH)
+    Receive {
+        /// Project directory whose targets are injected
+        #[arg(long)]
+        project: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum AgentAction {
+    /// Decrypt the vault once and serve get/list/inject requests until stopped
+    Start {
+        /// Path to the configuration file (default: project.yaml)
+        #[arg(short, long, default_value = "project.yaml")]
+        config: String,
+    },
+
+    /// Stop the running agent
+    Stop,
+
+    /// Show which config the running agent serves
+    Status,
+
+    /// Print FILE with its placeholders replaced by the running agent
+    Inject {
+        /// File to inject (its extension picks the format)
+        file: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum BackupAction {
+    /// Upload a snapshot of the vault files to backup.destination, pruning old ones
+    Push,
+
+    /// List the snapshots at backup.destination
+    List,
+
+    /// Write the vault files of a snapshot back to their original paths
+    Restore {
+        /// Snapshot to restore (default: the latest)
+        snapshot: Option<String>,
+
+        /// Only restore the vaults of this project ("global" for the global vault)
+        #[arg(long)]
+        project: Option<String>,
+
+        /// Overwrite vault files that changed since the snapshot
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum TargetAction {
+    /// Append a target to project.yaml (comments are preserved)
+    Add {
+        /// Name of the target
+        #[arg(short, long)]
+        name: String,
+
+        /// File where secrets are injected (relative to the config directory)
+        #[arg(short, long)]
+        path: String,
+
+        /// Secret names to inject, comma-separated (e.g. API_KEY,DB_URL)
+        #[arg(short, long, value_delimiter = ',', required = true)]
+        keys: Vec<String>,
+
+        /// Path to the configuration file (default: project.yaml)
+        #[arg(short, long, default_value = "project.yaml")]
+        config: String,
+
+        /// Insert missing placeholders into the target file without asking
+        #[arg(short, long, default_value = "false")]
+        yes: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum CanaryAction {
+    /// Add a unique canary token for this machine to the vault
+    Add {
+        /// Path to the configuration file (default: project.yaml)
+        #[arg(short, long, default_value = "project.yaml")]
+        config: String,
+    },
+
+    /// Search GitHub for canary tokens and alert if any has leaked
+    Check {
+        /// Path to the configuration file (default: project.yaml)
+        #[arg(short, long, default_value = "project.yaml")]
+        config: String,
+
+        /// Webhook notified when a canary is found (Slack-compatible JSON)
+        #[arg(long)]
+        webhook: Option<String>,
+
+        /// GitHub API base URL (for GitHub Enterprise)
+        #[arg(long, default_value = shadow_secret::canary::GITHUB_API)]
+        github_api: String,
+    },
+}
+
 fn check_binary(name: &str) -> Result<bool> {
     match which::which(name) {
         Ok(_) => Ok(true),
@@ -103,16 +684,16 @@ fn run_basic_checks() -> Result<()> {
     // Check 1: sops installation
     print!("1. Checking if 'sops' is installed... ");
     match check_binary("sops") {
-        Ok(true) => println!("✓"),
+        Ok(true) => say!("✓"),
         Ok(false) => {
-            println!("✗");
-            println!("   ❌ 'sops' is not installed or not in PATH");
-            println!("   📦 Install from: https://github.com/getsops/sops/releases");
+            say!("✗");
+            say!("   ❌ 'sops' is not installed or not in PATH");
+            say!("   📦 Install from: https://github.com/getsops/sops/releases");
             all_checks_passed = false;
         }
         Err(e) => {
-            println!("✗");
-            println!("   ❌ Error checking for 'sops': {}", e);
+            say!("✗");
+            say!("   ❌ Error checking for 'sops': {}", e);
             all_checks_passed = false;
         }
     }
@@ -120,16 +701,16 @@ fn run_basic_checks() -> Result<()> {
     // Check 2: age installation
     print!("2. Checking if 'age' is installed... ");
     match check_binary("age") {
-        Ok(true) => println!("✓"),
+        Ok(true) => say!("✓"),
         Ok(false) => {
-            println!("✗");
-            println!("   ❌ 'age' is not installed or not in PATH");
-            println!("   📦 Install from: https://github.com/FiloSottile/age/releases");
+            say!("✗");
+            say!("   ❌ 'age' is not installed or not in PATH");
+            say!("   📦 Install from: https://github.com/FiloSottile/age/releases");
             all_checks_passed = false;
         }
         Err(e) => {
-            println!("✗");
-            println!("   ❌ Error checking for 'age': {}", e);
+            say!("✗");
+            say!("   ❌ Error checking for 'age': {}", e);
             all_checks_passed = false;
         }
     }
@@ -137,17 +718,17 @@ fn run_basic_checks() -> Result<()> {
     // Check 3: SOPS_AGE_KEY_FILE environment variable
     print!("3. Checking $SOPS_AGE_KEY_FILE environment variable... ");
     match check_env_var("SOPS_AGE_KEY_FILE") {
-        Ok(true) => println!("✓"),
+        Ok(true) => say!("✓"),
         Ok(false) => {
-            println!("✗");
-            println!("   ❌ $SOPS_AGE_KEY_FILE is not set");
-            println!("   💡 Set it with: export SOPS_AGE_KEY_FILE=/path/to/key.txt");
-            println!("   💡 Or specify 'age_key_path' in global.yaml");
+            say!("✗");
+            say!("   ❌ $SOPS_AGE_KEY_FILE is not set");
+            say!("   💡 Set it with: export SOPS_AGE_KEY_FILE=/path/to/key.txt");
+            say!("   💡 Or specify 'age_key_path' in global.yaml");
             all_checks_passed = false;
         }
         Err(e) => {
-            println!("✗");
-            println!("   ❌ Error checking environment variable: {}", e);
+            say!("✗");
+            say!("   ❌ Error checking environment variable: {}", e);
             all_checks_passed = false;
         }
     }
@@ -156,53 +737,53 @@ fn run_basic_checks() -> Result<()> {
     print!("4. Checking if $SOPS_AGE_KEY_FILE file exists... ");
     if let Ok(key_file) = std::env::var("SOPS_AGE_KEY_FILE") {
         match check_file_exists(&key_file) {
-            Ok(true) => println!("✓"),
+            Ok(true) => say!("✓"),
             Ok(false) => {
-                println!("✗");
-                println!("   ❌ File not found: {}", key_file);
-                println!("   💡 Verify the path is correct");
+                say!("✗");
+                say!("   ❌ File not found: {}", key_file);
+                say!("   💡 Verify the path is correct");
                 all_checks_passed = false;
             }
             Err(e) => {
-                println!("✗");
-                println!("   ❌ Error checking file: {}", e);
+                say!("✗");
+                say!("   ❌ Error checking file: {}", e);
                 all_checks_passed = false;
             }
         }
     } else {
-        println!("⊘");
-        println!("   ⚠️  Skipped (environment variable not set)");
+        say!("⊘");
+        say!("   ⚠️  Skipped (environment variable not set)");
     }
 
-    println!();
+    say!();
     if all_checks_passed {
-        println!("✅ All basic checks passed! Your system is ready.");
+        say!("✅ All basic checks passed! Your system is ready.");
         Ok(())
     } else {
-        println!("❌ Some checks failed. Please fix the issues above.");
+        say!("❌ Some checks failed. Please fix the issues above.");
         Err(anyhow::anyhow!("Basic checks failed"))
     }
 }
 
 fn run_doctor() -> Result<()> {
-    println!("🔍 Shadow Secret Doctor");
-    println!("Checking prerequisites...\n");
+    say!("🔍 Shadow Secret Doctor");
+    say!("Checking prerequisites...\n");
 
     let mut all_checks_passed = true;
 
     // Check 1: sops installation
     print!("1. Checking if 'sops' is installed... ");
     match check_binary("sops") {
-        Ok(true) => println!("✓"),
+        Ok(true) => say!("✓"),
         Ok(false) => {
-            println!("✗");
-            println!("   ❌ 'sops' is not installed or not in PATH");
-            println!("   📦 Install from: https://github.com/getsops/sops/releases");
+            say!("✗");
+            say!("   ❌ 'sops' is not installed or not in PATH");
+            say!("   📦 Install from: https://github.com/getsops/sops/releases");
             all_checks_passed = false;
         }
         Err(e) => {
-            println!("✗");
-            println!("   ❌ Error checking for 'sops': {}", e);
+            say!("✗");
+            say!("   ❌ Error checking for 'sops': {}", e);
             all_checks_passed = false;
         }
     }
@@ -210,16 +791,16 @@ fn run_doctor() -> Result<()> {
     // Check 2: age installation
     print!("2. Checking if 'age' is installed... ");
     match check_binary("age") {
-        Ok(true) => println!("✓"),
+        Ok(true) => say!("✓"),
         Ok(false) => {
-            println!("✗");
-            println!("   ❌ 'age' is not installed or not in PATH");
-            println!("   📦 Install from: https://github.com/FiloSottile/age/releases");
+            say!("✗");
+            say!("   ❌ 'age' is not installed or not in PATH");
+            say!("   📦 Install from: https://github.com/FiloSottile/age/releases");
             all_checks_passed = false;
         }
         Err(e) => {
-            println!("✗");
-            println!("   ❌ Error checking for 'age': {}", e);
+            say!("✗");
+            say!("   ❌ Error checking for 'age': {}", e);
             all_checks_passed = false;
         }
     }
@@ -228,20 +809,20 @@ fn run_doctor() -> Result<()> {
     print!("3. Checking $SOPS_AGE_KEY_FILE environment variable... ");
     let env_var_set = match check_env_var("SOPS_AGE_KEY_FILE") {
         Ok(true) => {
-            println!("✓");
+            say!("✓");
             true
         }
         Ok(false) => {
-            println!("⊘");
-            println!("   ⚠️  $SOPS_AGE_KEY_FILE is not set");
-            println!("   💡 You can either:");
-            println!("      1. Set it: export SOPS_AGE_KEY_FILE=/path/to/key.txt");
-            println!("      2. Add 'age_key_path' field to your vault config");
+            say!("⊘");
+            say!("   ⚠️  $SOPS_AGE_KEY_FILE is not set");
+            say!("   💡 You can either:");
+            say!("      1. Set it: export SOPS_AGE_KEY_FILE=/path/to/key.txt");
+            say!("      2. Add 'age_key_path' field to your vault config");
             false
         }
         Err(e) => {
-            println!("✗");
-            println!("   ❌ Error checking environment variable: {}", e);
+            say!("✗");
+            say!("   ❌ Error checking environment variable: {}", e);
             all_checks_passed = false;
             false
         }
@@ -251,10 +832,11 @@ fn run_doctor() -> Result<()> {
     if !env_var_set {
         // Check if project.yaml or global config has age_key_path
         let config_path = if Path::new("project.yaml").exists() {
-            "project.yaml"
+            PathBuf::from("project.yaml")
         } else {
-            "~/.config/shadow-secret/global.yaml"
+            shadow_secret::paths::global_config_path().unwrap_or_default()
         };
+        let config_path = config_path.to_str().unwrap_or("");
 
         print!("   Checking if 'age_key_path' is in config... ");
         match check_file_exists(config_path) {
@@ -262,27 +844,27 @@ fn run_doctor() -> Result<()> {
                 // Try to read and parse config to check for age_key_path field
                 if let Ok(content) = std::fs::read_to_string(config_path) {
                     if content.contains("age_key_path:") {
-                        println!("✓");
-                        println!("   ℹ️  Config has 'age_key_path' field");
+                        say!("✓");
+                        say!("   ℹ️  Config has 'age_key_path' field");
                     } else {
-                        println!("⊘");
-                        println!("   ⚠️  Config does not have 'age_key_path' field");
-                        println!("   💡 Add it to your vault config:");
-                        println!("      vault:");
-                        println!("        age_key_path: \"/path/to/your/keys.txt\"");
+                        say!("⊘");
+                        say!("   ⚠️  Config does not have 'age_key_path' field");
+                        say!("   💡 Add it to your vault config:");
+                        say!("      vault:");
+                        say!("        age_key_path: \"/path/to/your/keys.txt\"");
                     }
                 } else {
-                    println!("⊘");
-                    println!("   ⚠️  Could not read config file");
+                    say!("⊘");
+                    say!("   ⚠️  Could not read config file");
                 }
             }
             Ok(false) => {
-                println!("⊘");
-                println!("   ℹ️  No config file found to check");
+                say!("⊘");
+                say!("   ℹ️  No config file found to check");
             }
             Err(e) => {
-                println!("⊘");
-                println!("   ⚠️  Could not check config file: {}", e);
+                say!("⊘");
+                say!("   ⚠️  Could not check config file: {}", e);
             }
         }
     }
@@ -291,22 +873,22 @@ fn run_doctor() -> Result<()> {
     print!("4. Checking if $SOPS_AGE_KEY_FILE file exists... ");
     if let Ok(key_file) = std::env::var("SOPS_AGE_KEY_FILE") {
         match check_file_exists(&key_file) {
-            Ok(true) => println!("✓"),
+            Ok(true) => say!("✓"),
             Ok(false) => {
-                println!("✗");
-                println!("   ❌ File not found: {}", key_file);
-                println!("   💡 Verify the path is correct");
+                say!("✗");
+                say!("   ❌ File not found: {}", key_file);
+                say!("   💡 Verify the path is correct");
                 all_checks_passed = false;
             }
             Err(e) => {
-                println!("✗");
-                println!("   ❌ Error checking file: {}", e);
+                say!("✗");
+                say!("   ❌ Error checking file: {}", e);
                 all_checks_passed = false;
             }
         }
     } else {
-        println!("⊘");
-        println!("   ⚠️  Skipped (environment variable not set)");
+        say!("⊘");
+        say!("   ⚠️  Skipped (environment variable not set)");
     }
 
     // Check 5: Vault source path accessibility
@@ -315,8 +897,7 @@ fn run_doctor() -> Result<()> {
     // Check if we're in global mode or project mode
     let project_config_exists = check_file_exists("project.yaml")?;
 
-    let global_config_path = dirs::home_dir()
-        .map(|home| home.join(".config/shadow-secret/global.yaml"));
+    let global_config_path = shadow_secret::paths::global_config_path().ok();
 
     let global_config_exists = if let Some(ref path) = global_config_path {
         check_file_exists(path.to_str().unwrap_or(""))?
@@ -325,46 +906,396 @@ fn run_doctor() -> Result<()> {
     };
 
     if project_config_exists {
-        println!("✓");
-        println!("   ℹ️  Project config found: project.yaml");
+        say!("✓");
+        say!("   ℹ️  Project config found: project.yaml");
     } else if global_config_exists {
-        println!("✓");
-        println!("   ℹ️  Global config found: ~/.config/shadow-secret/global.yaml");
-        println!("   💡 Use 'shadow-secret unlock-global' for global secrets");
+        say!("✓");
+        say!("   ℹ️  Global config found: ~/.config/shadow-secret/global.yaml");
+        say!("   💡 Use 'shadow-secret unlock-global' for global secrets");
     } else {
-        println!("✗");
-        println!("   ❌ No configuration found");
-        println!("   💡 Create one of:");
-        println!("      1. Project: project.yaml in current directory (run 'shadow-secret init-project')");
-        println!("      2. Global: ~/.config/shadow-secret/global.yaml (run 'shadow-secret init-global')");
-        println!("   💡 Run 'shadow-secret init-global' to create global config");
+        say!("✗");
+        say!("   ❌ No configuration found");
+        say!("   💡 Create one of:");
+        say!("      1. Project: project.yaml in current directory (run 'shadow-secret init-project')");
+        say!("      2. Global: ~/.config/shadow-secret/global.yaml (run 'shadow-secret init-global')");
+        say!("   💡 Run 'shadow-secret init-global' to create global config");
         all_checks_passed = false;
     }
 
-    println!();
+    say!();
     if all_checks_passed {
-        println!("✅ All checks passed! Your system is ready.");
+        say!("✅ All checks passed! Your system is ready.");
         Ok(())
     } else {
-        println!("❌ Some checks failed. Please fix the issues above.");
+        say!("❌ Some checks failed. Please fix the issues above.");
         Err(anyhow::anyhow!("Doctor checks failed"))
     }
 }
 
-fn run_unlock(config_path: &str) -> Result<()> {
-    println!("🔓 Shadow Secret Unlock (Project)");
-    println!("Loading configuration from: {}\n", config_path);
-
-    // Step 1: Load and validate configuration (project-specific only, no global fallback)
-    let config = Config::from_file(config_path)
-        .with_context(|| format!("Failed to load config from: {}", config_path))?;
+/// Print a command error, including SOPS diagnostics hidden behind context.
+fn report_error(e: &anyhow::Error) {
+    say_err!("\nError: {}", e);
+    if let Some(sops_error) = e.downcast_ref::<SopsError>() {
+        say_err!("{}", sops_error);
+    }
+}
 
-    config.validate()
-        .with_context(|| "Configuration validation failed")?;
+/// Show an `--explain` plan and ask for confirmation.
+///
+/// Returns `true` when execution should continue.
+fn confirm_plan(plan: &shadow_secret::explain::Plan) -> Result<bool> {
+    if plan.confirm()? {
+        say!();
+        Ok(true)
+    } else {
+        say!("⊘ Aborted, nothing was executed");
+        Ok(false)
+    }
+}
 
-    println!("✓ Configuration loaded and validated");
+/// The name of `command` if it cannot build an `--explain` plan: the flag
+/// is refused for it rather than silently ignored.
+fn explain_unsupported(command: &Commands) -> Option<&'static str> {
+    match command {
+        Commands::Unlock { .. }
+        | Commands::UnlockGlobal { .. }
+        | Commands::InitProject { .. }
+        | Commands::InitGlobal { .. }
+        | Commands::Setup { .. }
+        | Commands::Set { .. }
+        | Commands::Exec { .. }
+        | Commands::PushCloud { .. }
+        | Commands::Deploy { .. }
+        | Commands::Update { .. } => None,
+        Commands::Doctor => Some("doctor"),
+        Commands::Fsck { .. } => Some("fsck"),
+        Commands::List { .. } => Some("list"),
+        Commands::Scrub { .. } => Some("scrub"),
+        Commands::Get { .. } => Some("get"),
+        Commands::Canary { action: CanaryAction::Add { .. } } => Some("canary add"),
+        Commands::Canary { action: CanaryAction::Check { .. } } => Some("canary check"),
+        Commands::Vault { action: VaultAction::RenameKey { .. } } => Some("vault rename-key"),
+        Commands::Target { action: TargetAction::Add { .. } } => Some("target add"),
+        Commands::Journal { action: JournalAction::List } => Some("journal list"),
+        Commands::Journal { action: JournalAction::Restore { .. } } => Some("journal restore"),
+        Commands::RestoreSnapshot { .. } => Some("restore-snapshot"),
+        Commands::Gc { .. } => Some("gc"),
+        Commands::Rekey { .. } => Some("rekey"),
+        Commands::Report { .. } => Some("report"),
+        Commands::ComplianceReport { .. } => Some("compliance-report"),
+        Commands::Status { .. } => Some("status"),
+        Commands::TrustBinaries { .. } => Some("trust-binaries"),
+        Commands::Remote { action: RemoteAction::Unlock { .. } } => Some("remote unlock"),
+        Commands::Remote { action: RemoteAction::Receive { .. } } => Some("remote receive"),
+        Commands::Agent { action: AgentAction::Start { .. } } => Some("agent start"),
+        Commands::Agent { action: AgentAction::Stop } => Some("agent stop"),
+        Commands::Agent { action: AgentAction::Status } => Some("agent status"),
+        Commands::Agent { action: AgentAction::Inject { .. } } => Some("agent inject"),
+        Commands::Tray { .. } => Some("tray"),
+        Commands::Backup { action: BackupAction::Push } => Some("backup push"),
+        Commands::Backup { action: BackupAction::List } => Some("backup list"),
+        Commands::Backup { action: BackupAction::Restore { .. } } => Some("backup restore"),
+        Commands::Provision { .. } => Some("provision"),
+        Commands::SeccompProfile { .. } => Some("seccomp-profile"),
+    }
+}
 
-    // Step 2: Get config directory for path resolution
+/// Verify the config's detached signature when a trusted key is configured.
+fn check_config_signature(config_path: &str, require: bool) -> Result<()> {
+    for line in config_signature_report(config_path, require)? {
+        say!("{}", line);
+    }
+    Ok(())
+}
+
+/// Verify the signatures of the config and of its included and local
+/// files, returning the status lines to print.
+fn config_signature_report(config_path: &str, require: bool) -> Result<Vec<String>> {
+    let mut report = Vec::new();
+    match verify_config_signature(Path::new(config_path), require)? {
+        SignatureCheck::Verified { key_id, trusted_comment } => {
+            report.push(format!("✓ Config signature verified (key ID {})", key_id));
+            report.push(format!("  {}", trusted_comment));
+
+            // Included and local files carry targets too: each needs its own signature
+            let local = shadow_secret::config::local_config_path(Path::new(config_path));
+            let extra = Config::included_files(config_path)?
+                .into_iter()
+                .chain(local.exists().then_some(local));
+            for included in extra {
+                if let SignatureCheck::Verified { key_id, .. } = verify_config_signature(&included, true)? {
+                    report.push(format!("✓ {} signature verified (key ID {})", included.display(), key_id));
+                }
+            }
+        }
+        SignatureCheck::NotConfigured => {
+            if shadow_secret::signature::signature_path(Path::new(config_path)).exists() {
+                report.push("⚠️  Config is signed but no trusted key is configured (signature not checked)".to_string());
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+/// Print the loud warning shown whenever a plaintext overlay is used.
+fn warn_plaintext_overlay(overlay: &LocalOverlay) {
+    say_err!(
+        "⚠️  WARNING: using PLAINTEXT local overlay {}\n\
+        ⚠️  Its values are stored unencrypted on disk. Keep it gitignored, or encrypt it:\n\
+        ⚠️    sops -e {} > {}",
+        overlay.path().display(),
+        shadow_secret::vault::LOCAL_OVERLAY_PLAINTEXT,
+        shadow_secret::vault::LOCAL_OVERLAY_ENCRYPTED
+    );
+}
+
+/// Merge the developer's local overlay (local.enc.env / local.env), if any,
+/// on top of the shared vault.
+fn apply_local_overlay(vault: &mut Vault, config_dir: &Path, age_key_path: Option<&str>) -> Result<()> {
+    if let Some(overlay) = LocalOverlay::find(config_dir) {
+        if overlay.is_plaintext() {
+            warn_plaintext_overlay(&overlay);
+        }
+        say!("📖 Loading local overlay from: {}", overlay.path().display());
+        let keys = vault.apply_overlay(&overlay.path().to_string_lossy(), overlay.load(age_key_path)?);
+        say!("✓ Local overlay overrides {} key(s): {}", keys.len(), keys.join(", "));
+    }
+
+    Ok(())
+}
+
+/// Mint the short-lived credentials of `config` (`dynamic:`) and merge them
+/// over the vault.
+fn apply_dynamic_secrets(vault: &mut Vault, config: &Config) -> Result<()> {
+    for dynamic in &config.dynamic {
+        let source = shadow_secret::dynamic::source_name(dynamic);
+        say!("🔑 Minting short-lived credentials: {} ({}s)", source, dynamic.duration()?.as_secs());
+        let secrets = shadow_secret::dynamic::mint(dynamic)
+            .with_context(|| format!("Failed to mint dynamic secret {}", source))?;
+        let keys = vault.apply_overlay(&source, secrets);
+        say!("✓ Dynamic credentials: {}", keys.join(", "));
+    }
+
+    Ok(())
+}
+
+/// Load the vault of `config`, printing where secrets come from.
+fn load_vault(config: &Config, config_dir: &Path) -> Result<Vault> {
+    let vault = &config.vault;
+    match vault.engine.as_str() {
+        shadow_secret::vault::ENGINE_HASHICORP => {
+            if let Some(hashicorp) = &vault.hashicorp {
+                say!(
+                    "📖 Loading secrets from HashiCorp Vault: {}",
+                    shadow_secret::hashicorp::source_name(hashicorp)
+                );
+            }
+        }
+        shadow_secret::vault::ENGINE_AWS_SECRETS_MANAGER => {
+            if let Some(aws) = &vault.aws_secretsmanager {
+                say!("📖 Loading secrets from AWS Secrets Manager: {}", aws.secret_id);
+            }
+        }
+        shadow_secret::vault::ENGINE_GCP_SECRET_MANAGER => {
+            if let Some(gcp) = &vault.gcp {
+                say!(
+                    "📖 Loading secrets from GCP Secret Manager: {}",
+                    shadow_secret::gcp::source_name(gcp)
+                );
+            }
+        }
+        shadow_secret::vault::ENGINE_ONEPASSWORD => {
+            if let Some(onepassword) = &vault.onepassword {
+                for (vault_name, item) in shadow_secret::onepassword::items(onepassword)? {
+                    say!(
+                        "📖 Loading secrets from 1Password: {}",
+                        shadow_secret::onepassword::source_name(vault_name.as_deref(), &item)
+                    );
+                }
+            }
+        }
+        shadow_secret::vault::ENGINE_BITWARDEN => {
+            if let Some(bitwarden) = &vault.bitwarden {
+                say!("📖 Loading secrets from Bitwarden project: {}", bitwarden.project_id);
+            }
+        }
+        shadow_secret::vault::ENGINE_PASS => {
+            if let Some(pass) = &vault.pass {
+                say!("📖 Loading {} secret(s) from password-store", pass.keys.len());
+            }
+        }
+        _ => {
+            for vault_path in config.vault_source_paths(config_dir)? {
+                say!("📖 Loading secrets from: {}", vault_path.display());
+            }
+        }
+    }
+
+    Vault::load_config(config, config_dir)
+}
+
+/// Warn about keys defined in several sources, naming the winning source.
+/// Targets whose `when` condition holds here; the others are listed as
+/// skipped.
+fn active_targets(targets: Vec<TargetConfig>) -> Result<Vec<TargetConfig>> {
+    let (active, skipped) = shadow_secret::config::partition_active_targets(targets)?;
+    if !skipped.is_empty() {
+        say!(
+            "⏭️  Skipping {} target(s) whose 'when' condition does not hold: {}",
+            skipped.len(),
+            skipped.join(", ")
+        );
+    }
+    Ok(active)
+}
+
+fn warn_shadowed_keys(vault: &Vault) {
+    let shadowed = vault.shadowed();
+    if shadowed.is_empty() {
+        return;
+    }
+
+    say!(
+        "⚠️  {} key(s) defined in several sources (last one wins):",
+        shadowed.len()
+    );
+    for entry in shadowed {
+        say!(
+            "    {} ← {} (overrides: {})",
+            entry.key,
+            entry.source,
+            entry.shadowed.join(", ")
+        );
+    }
+}
+
+/// What `unlock` does with the decrypted vault.
+enum UnlockMode {
+    /// Inject the targets until locked, or until the timeout (`--timeout`),
+    /// first waiting for other processes to close them if `wait`, and
+    /// snapshotting them first if `snapshot`
+    Inject { timeout: Option<String>, wait: bool, snapshot: bool },
+    /// Write injected copies into this directory (`--preview-dir`)
+    Preview(String),
+    /// Print redacted diffs only (`--dry-run`)
+    DryRun,
+}
+
+fn run_unlock(
+    config_path: &str,
+    require_signature: bool,
+    mode: UnlockMode,
+    fake: bool,
+    strict: bool,
+    explain: bool,
+) -> Result<()> {
+    say!("🔓 Shadow Secret Unlock (Project)");
+    say!("Loading configuration from: {}\n", config_path);
+
+    let (config, config_dir) = load_unlock_config(config_path, require_signature, strict)?;
+    let config_dir = config_dir.as_path();
+    let timeout = match &mode {
+        UnlockMode::Inject { timeout, .. } => session_timeout(timeout.clone(), &config)?,
+        UnlockMode::Preview(_) | UnlockMode::DryRun => None,
+    };
+
+    let plan = match &mode {
+        UnlockMode::Preview(dir) => shadow_secret::explain::unlock_preview_plan(&config, config_dir, Path::new(dir), fake)?,
+        UnlockMode::Inject { .. } | UnlockMode::DryRun => shadow_secret::explain::unlock_plan("unlock", &config, config_dir, fake)?,
+    };
+    if explain && !confirm_plan(&plan)? {
+        return Ok(());
+    }
+
+    // Step 3: Load secrets from vault (extra sources are decrypted concurrently)
+    let vault = if fake {
+        let vault_paths = config.vault_source_paths(config_dir)?;
+        for vault_path in &vault_paths {
+            say!("📖 Reading key names from: {}", vault_path.display());
+        }
+        say!("🎭 Fake mode: injecting deterministic fake values, nothing is decrypted");
+        let mut vault = Vault::load_fake(&vault_paths)?
+            .with_normalized_keys(config.vault.normalize_keys);
+        if let Some(overlay) = LocalOverlay::find(config_dir) {
+            vault.apply_overlay(&overlay.path().to_string_lossy(), overlay.load_fake()?);
+        }
+        vault
+    } else {
+        let mut vault = load_vault(&config, config_dir)?
+            .with_normalized_keys(config.vault.normalize_keys);
+        apply_local_overlay(&mut vault, config_dir, config.vault.age_key_path.as_deref())?;
+        apply_dynamic_secrets(&mut vault, &config)?;
+        vault
+    };
+    warn_shadowed_keys(&vault);
+
+    let secrets = vault.all();
+    say!("✓ Loaded {} secret(s)", secrets.len());
+
+    match &mode {
+        UnlockMode::Preview(dir) => return write_unlock_preview(&config, config_dir, &vault, Path::new(dir)),
+        UnlockMode::DryRun => return print_unlock_dry_run(&config, &vault),
+        UnlockMode::Inject { wait, snapshot, .. } => {
+            check_busy_targets(&config.targets, *wait)?;
+            if *snapshot {
+                write_snapshot(&config, config_dir)?;
+            }
+        }
+    }
+
+    // Step 4: Inject secrets into each target
+    say!("\n🎯 Injecting secrets into targets...");
+
+    begin_recovery_journal(&config, config_dir);
+    let session = unlock_targets(&config, &vault)?;
+    print_unlock_report(session.report());
+    write_injection_report(&session);
+
+    say!("\n✓ All secrets injected successfully!");
+    say!("\n🎉 Secrets are now unlocked and injected!");
+    wait_for_lock(timeout)?;
+
+    say!("\n🔄 Restoring templates...");
+
+    // Restore all backups
+    lock_session(session);
+
+    say!("✓ Templates restored!");
+    say!("👋 See you next time!");
+
+    Ok(())
+}
+
+/// Load the project config for unlocking: verify its signature, validate
+/// it, keep the targets whose `when` holds (all strict with `strict`) and
+/// check they stay inside the project.
+///
+/// # Returns
+///
+/// The config and its directory.
+fn load_unlock_config(config_path: &str, require_signature: bool, strict: bool) -> Result<(Config, PathBuf)> {
+    // Step 0: Verify config signature before trusting its content
+    check_config_signature(config_path, require_signature)?;
+
+    // Step 1: Load and validate configuration (project-specific only, no global fallback)
+    let mut config = Config::from_file(config_path)
+        .with_context(|| format!("Failed to load config from: {}", config_path))?;
+
+    config.validate()
+        .with_context(|| "Configuration validation failed")?;
+    shadow_secret::vault::set_engine(&config.vault.engine)?;
+    shadow_secret::process::set_timeouts(config.timeouts.resolve()?);
+    config.targets = active_targets(config.targets)?;
+    if strict {
+        for target in &mut config.targets {
+            target.strict = true;
+        }
+    }
+
+    say!("✓ Configuration loaded and validated");
+
+    // Step 2: Get config directory for path resolution
     let config_abs_path = PathBuf::from(config_path)
         .canonicalize()
         .with_context(|| format!("Failed to resolve config file path: {}", config_path))?;
@@ -373,179 +1304,1811 @@ fn run_unlock(config_path: &str) -> Result<()> {
         .parent()
         .context("Config file has no parent directory")?;
 
-    // Step 3: Load secrets from vault
-    let vault_path = config.vault_source_path(config_dir)?;
-    let vault_path_str = vault_path.to_str()
-        .ok_or_else(|| anyhow::anyhow!("Vault path contains invalid UTF-8"))?;
+    // Refuse targets that escape the project directory (tampered config)
+    config.check_target_containment(config_dir)?;
 
-    println!("📖 Loading secrets from: {}", vault_path_str);
+    Ok((config, config_dir.to_path_buf()))
+}
 
-    // Extract age_key_path from config if available
-    let age_key_path = config.vault.age_key_path.as_deref();
+/// Inject `vault` into the targets of `config`, keeping the CI status file
+/// (see [`shadow_secret::status`]) up to date, and start its leases.
+fn unlock_targets(config: &Config, vault: &Vault) -> Result<UnlockSession> {
+    use shadow_secret::status::{self, SessionState};
+
+    let targets = &config.targets;
+    let paths: Vec<String> = targets.iter().map(|target| target.path.clone()).collect();
+    match status::start(&paths) {
+        Ok(Some(path)) => say!("📡 Status file: {}", path.display()),
+        Ok(None) => {}
+        Err(e) => say_err!("⚠️  No status file written: {:#}", e),
+    }
 
-    let vault = Vault::load(vault_path_str, age_key_path)
-        .with_context(|| format!("Failed to load vault from: {}", vault_path_str))?;
+    let session = UnlockSession::unlock(targets, vault).inspect_err(|_| {
+        let _ = status::set_state(SessionState::Failed);
+    })?;
+    if let Err(e) = status::set_state(SessionState::Unlocked) {
+        say_err!("⚠️  Failed to update status file: {:#}", e);
+    }
 
-    let secrets = vault.all();
-    println!("✓ Loaded {} secret(s)", secrets.len());
+    for lease in shadow_secret::lease::start(&config.leases, vault.all())? {
+        say!("⏳ Lease on {} expires in {}s", lease.key, lease.ttl.as_secs());
+    }
+    Ok(session)
+}
 
-    // Step 4: Inject secrets into each target
-    println!("\n🎯 Injecting secrets into targets...");
+/// Warn about the targets other processes have open, before any is
+/// modified. On Windows such a target usually cannot be replaced, so the
+/// unlock stops there instead of failing halfway; with `wait`, poll until
+/// every target is closed.
+fn check_busy_targets(targets: &[TargetConfig], wait: bool) -> Result<()> {
+    let mut busy = shadow_secret::unlock::busy_targets(targets)?;
+    if busy.is_empty() {
+        return Ok(());
+    }
+    for target in &busy {
+        say_err!("⚠️  {}", target.message());
+    }
+
+    if wait {
+        say_err!("⏳ Waiting for the targets to be closed (Ctrl+C to abort)...");
+        while !busy.is_empty() {
+            std::thread::sleep(std::time::Duration::from_millis(500));
+            busy = shadow_secret::unlock::busy_targets(targets)?;
+        }
+        say_err!("✓ Targets closed");
+        return Ok(());
+    }
+    if cfg!(windows) {
+        anyhow::bail!(
+            "Targets are open in other processes and may not be replaceable (no file was modified). \
+             Close them, or pass --wait to wait until they are closed"
+        );
+    }
+    say_err!("💡 They may not see the secrets until they reopen the file (--wait waits for them to close it)");
+    Ok(())
+}
+
+/// Start the session's encrypted crash-recovery journal, written ahead of
+/// each target (best effort: the in-memory backups still restore the
+/// targets on a normal exit).
+fn begin_recovery_journal(config: &Config, config_dir: &Path) {
+    let written = config
+        .vault_source_path(config_dir)
+        .and_then(|vault_path| shadow_secret::journal::vault_file_recipients(&vault_path))
+        .and_then(shadow_secret::journal::begin);
+
+    match written {
+        Ok(path) => say!("📓 Recovery journal: {}", path.display()),
+        Err(e) => say_err!("⚠️  No recovery journal written: {:#}", e),
+    }
+}
+
+/// Snapshot every target before anything is modified (`--snapshot`):
+/// unlike the recovery journal, a snapshot that cannot be written stops
+/// the unlock.
+fn write_snapshot(config: &Config, config_dir: &Path) -> Result<()> {
+    use shadow_secret::snapshot;
+
+    let recipients = shadow_secret::journal::vault_file_recipients(&config.vault_source_path(config_dir)?)?;
+    let paths: Vec<&str> = config.targets.iter().map(|target| target.path.as_str()).collect();
+    let path = snapshot::write(&snapshot::snapshots_dir()?, &snapshot::Snapshot::capture(&paths)?, &recipients)
+        .context("Failed to snapshot the targets, nothing was modified")?;
+    say!("📸 Snapshot: {}", path.display());
+    Ok(())
+}
+
+/// Lock `session` and record it in the audit log (best effort): the keys
+/// it injected and how long they stayed injected.
+fn lock_session(session: UnlockSession) {
+    let mut event = shadow_secret::audit::AuditEvent::new("session");
+    event.project = std::env::current_dir()
+        .ok()
+        .and_then(|dir| dir.file_name().map(|name| name.to_string_lossy().to_string()));
+    event.keys = session.injected_keys();
+    event.duration_ms = Some(session.elapsed().as_millis() as u64);
+    event.outcome = "success".to_string();
+
+    session.lock();
+    let recorded = shadow_secret::audit::audit_log_path().and_then(|path| shadow_secret::audit::append(&path, &event));
+    if let Err(e) = recorded {
+        say_err!("⚠️  Failed to record session in audit log: {:#}", e);
+    }
+}
+
+/// How long the session may stay unlocked: `--timeout`, else
+/// `timeouts.session` of the config.
+///
+/// # Returns
+///
+/// The limit as written and its duration, or `None` for no limit.
+fn session_timeout(flag: Option<String>, config: &Config) -> Result<Option<(String, std::time::Duration)>> {
+    let (timeouts, source) = match flag {
+        Some(value) => (
+            shadow_secret::config::TimeoutsConfig { session: Some(value), ..Default::default() },
+            "--timeout",
+        ),
+        None => (config.timeouts.clone(), "config"),
+    };
+    let limit = timeouts.session().with_context(|| format!("Invalid session timeout ({})", source))?;
+    Ok(limit.map(|limit| (timeouts.session.unwrap_or_default().trim().to_string(), limit)))
+}
+
+/// Wait for Enter, or for the session timeout to run out, whichever comes
+/// first. Meanwhile Ctrl+C, SIGTERM, SIGHUP or closing the console restore
+/// the templates before exiting.
+fn wait_for_lock(timeout: Option<(String, std::time::Duration)>) -> Result<()> {
+    shadow_secret::cleaner::setup_signal_handlers();
+
+    let Some((label, limit)) = timeout else {
+        say!("👉 Press Enter to lock secrets and restore templates...");
+        let mut input = String::new();
+        std::io::stdin().read_line(&mut input)?;
+        return Ok(());
+    };
+
+    say!("👉 Press Enter to lock secrets and restore templates (locks on its own in {})...", label);
+    let (sender, receiver) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let mut input = String::new();
+        let _ = sender.send(std::io::stdin().read_line(&mut input));
+    });
+    match receiver.recv_timeout(limit) {
+        Ok(read) => {
+            read?;
+        }
+        Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {
+            say!("\n⏰ Session timed out after {}: locking", label);
+        }
+        Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => {}
+    }
+    Ok(())
+}
+
+/// Write the session's injection report (best effort: it only feeds
+/// `status --last` and tooling).
+fn write_injection_report(session: &UnlockSession) {
+    match session.write_report() {
+        Ok(path) => say!("📋 Injection report: {}", path.display()),
+        Err(e) => say_err!("⚠️  No injection report written: {:#}", e),
+    }
+}
+
+/// Print what an unlock did to each target.
+fn print_unlock_report(report: &UnlockReport) {
+    for target in &report.targets {
+        say!("  → Target: {}", target.name);
+        say!("    File: {}", target.path);
+        if target.outside_project {
+            say!("    ⚠️  Outside project directory (allow_outside_project)");
+        }
+        if target.created {
+            say!("    📄 Created from template (deleted on lock)");
+        }
+        if target.untrusted {
+            say!("    🍯 Untrusted target: injecting fake values");
+        }
+        if target.shared_readable && !target.untrusted {
+            say!("    ⚠️  Readable by other users on this machine (chmod 600 to keep secrets private)");
+        }
+        say!("    ✓ Injected {} placeholder(s)", target.placeholders);
+        if !target.left.is_empty() {
+            say!("    ⚠️  Left without a value: {}", target.left.join(", "));
+        }
+    }
+}
+
+/// Write injected copies of every target into `preview_dir`; the targets
+/// themselves are left untouched, so no cleanup is registered.
+fn write_unlock_preview(config: &Config, config_dir: &Path, vault: &Vault, preview_dir: &Path) -> Result<()> {
+    use shadow_secret::preview;
+
+    say!("\n🔍 Writing previews to: {}", preview_dir.display());
+    shadow_secret::unlock::check_strict(&config.targets, vault)?;
+
+    let cwd = std::env::current_dir()?;
+    let preview_dir = if preview_dir.is_absolute() {
+        preview_dir.to_path_buf()
+    } else {
+        cwd.join(preview_dir)
+    };
 
     for target in &config.targets {
-        println!("  → Target: {}", target.name);
-        println!("    File: {}", target.path);
+        let resolved = vault.secrets_for(target);
+        let target_secrets = if target.untrusted {
+            shadow_secret::fake::fake_secrets(&resolved)
+        } else {
+            resolved
+        };
 
-        // Create a copy of placeholders for the injector
-        let placeholders: Vec<String> = target.placeholders.iter().cloned().collect();
+        let content = preview::injected_content(target, &target_secrets)
+            .with_context(|| format!("Failed to preview target: {}", target.name))?;
+        let path = preview::preview_path(&preview_dir, config_dir, &cwd, Path::new(&target.path));
+        preview::write_preview(&path, &content)?;
 
-        // Inject secrets
-        let backup = shadow_secret::injector::inject_secrets(
-            Path::new(&target.path),
-            secrets,
-            &placeholders,
-        ).with_context(|| format!("Failed to inject secrets into: {}", target.path))?;
+        say!("  ✓ {} → {}", target.path, path.display());
+    }
+
+    say!("\n✓ Targets were not modified; nothing to restore");
+    say!("⚠️  Preview files contain secrets: keep {} gitignored and delete it when done", preview_dir.display());
+
+    Ok(())
+}
+
+/// Print, for every target, a diff of what unlock would write, values
+/// masked; nothing is written.
+fn print_unlock_dry_run(config: &Config, vault: &Vault) -> Result<()> {
+    say!("\n🔍 Dry run: nothing is written, values are masked");
+    shadow_secret::unlock::check_strict(&config.targets, vault)?;
 
-        // Register backup for cleanup
-        cleaner::register_backup(&target.path, backup.content());
+    for target in &config.targets {
+        let diff = shadow_secret::preview::redacted_diff(target, &vault.secrets_for(target))
+            .with_context(|| format!("Failed to preview target: {}", target.name))?;
 
-        println!("    ✓ Injected {} placeholder(s)", placeholders.len());
+        say!("\n--- {}\n+++ {} (unlocked)", target.path, target.path);
+        if diff.is_empty() {
+            say!("   (no placeholder to replace)");
+        }
+        for line in diff {
+            say!("{}", line);
+        }
     }
 
-    println!("\n✓ All secrets injected successfully!");
-    println!("\n🎉 Secrets are now unlocked and injected!");
-    println!("👉 Press Enter to lock secrets and restore templates...");
+    say!("\n✓ Dry run complete: targets were not modified");
+    Ok(())
+}
 
-    // Wait for user input
-    let mut input = String::new();
-    std::io::stdin().read_line(&mut input)?;
+fn run_get(config_path: &str, key: &str, shape: bool, copy: bool) -> Result<()> {
+    let value = get_secret(config_path, key)?;
+    if shape {
+        print!("{}", shadow_secret::shape::ValueShape::of(&value).render());
+    } else if copy {
+        shadow_secret::scrub::copy_to_clipboard(&value)?;
+        say_err!("📋 Copied {} to the clipboard (clear it with `shadow-secret scrub`)", key);
+    } else {
+        println!("{}", value);
+    }
+    Ok(())
+}
 
-    println!("\n🔄 Restoring templates...");
+fn run_set(config_path: &str, key: &str, explain: bool) -> Result<()> {
+    use std::io::{IsTerminal, Read};
 
-    // Restore all backups
-    cleaner::cleanup_and_restore();
+    let (config, config_dir) = load_project_config(config_path)?;
+    if shadow_secret::vault::is_remote_engine(&config.vault.engine) {
+        anyhow::bail!(
+            "'set' writes to SOPS vault files; engine '{}' stores secrets remotely",
+            config.vault.engine
+        );
+    }
+    if !shadow_secret::rename::is_valid_key(key) {
+        anyhow::bail!("Invalid key name '{}': use letters, digits and '_'", key);
+    }
+    if let Some(violation) = shadow_secret::naming::Rules::new(&config.naming)?.check(key) {
+        anyhow::bail!("Key name breaks the naming rules: {}", violation.message());
+    }
+
+    let sources = config.vault_source_paths(&config_dir)?;
+    let (vault_path, exists) = shadow_secret::vault::set_destination(&sources, key)?;
+    if explain && !confirm_plan(&shadow_secret::explain::set_plan(&config, &vault_path, key))? {
+        return Ok(());
+    }
 
-    println!("✓ Templates restored!");
-    println!("👋 See you next time!");
+    // Piped values are taken as is, minus the newline `echo` adds
+    let value = if std::io::stdin().is_terminal() {
+        dialoguer::Password::new()
+            .with_prompt(format!("Value for {}", key))
+            .with_confirmation("Confirm value", "Values do not match")
+            .interact()
+            .context("Failed to read the value")?
+    } else {
+        let mut value = String::new();
+        std::io::stdin().read_to_string(&mut value).context("Failed to read the value from stdin")?;
+        let trimmed = value.strip_suffix('\n').map(|value| value.strip_suffix('\r').unwrap_or(value));
+        trimmed.map(str::to_string).unwrap_or(value)
+    };
+    if value.is_empty() {
+        anyhow::bail!("Empty value: nothing was written");
+    }
 
+    shadow_secret::vault::set_secret_from_stdin(&vault_path, key, &value, config.vault.age_key_path.as_deref())?;
+    let verb = if exists { "Updated" } else { "Added" };
+    say_err!("✓ {} {} in {}", verb, key, vault_path.display());
+    if shadow_secret::agent::serving(Path::new(config_path)).is_some() {
+        say_err!("💡 A running agent still serves the old value: restart it to pick up the change");
+    }
     Ok(())
 }
 
-fn run_unlock_global() -> Result<()> {
-    println!("🔓 Shadow Secret Unlock (Global)");
-    println!("Loading global configuration from ~/.config/shadow-secret/global.yaml\n");
+/// The value of `key`, decrypting as little of the vault as possible.
+fn get_secret(config_path: &str, key: &str) -> Result<String> {
+    // stdout carries only the secret, so the signature report goes to stderr
+    for line in config_signature_report(config_path, false)? {
+        say_err!("{}", line);
+    }
 
-    // Step 1: Load global config explicitly
-    let global_config_path = dirs::home_dir()
-        .map(|home| home.join(".config/shadow-secret/global.yaml"))
-        .context("Failed to determine global config path")?;
+    // A running agent for this config answers without decrypting anything
+    if let Some(socket) = shadow_secret::agent::serving(Path::new(config_path)) {
+        let request = shadow_secret::agent::Request::Get { key: key.to_string() };
+        let response = shadow_secret::agent::request(&socket, &request)?.into_result()?;
+        return Ok(response.value.unwrap_or_default());
+    }
 
-    let config = Config::from_file(&global_config_path)
-        .with_context(|| "Failed to load global config")?;
+    let config = Config::from_file(config_path)
+        .with_context(|| format!("Failed to load config from: {}", config_path))?;
 
     config.validate()
-        .with_context(|| "Global configuration validation failed")?;
+        .with_context(|| "Configuration validation failed")?;
+    shadow_secret::vault::set_engine(&config.vault.engine)?;
+    shadow_secret::process::set_timeouts(config.timeouts.resolve()?);
 
-    println!("✓ Global configuration loaded and validated");
+    let config_abs_path = PathBuf::from(config_path)
+        .canonicalize()
+        .with_context(|| format!("Failed to resolve config file path: {}", config_path))?;
 
-    // Step 2: Get config directory for path resolution
-    let config_dir = global_config_path
+    let config_dir = config_abs_path
         .parent()
-        .context("Global config has no parent directory")?;
+        .context("Config file has no parent directory")?;
+
+    let age_key_path = config.vault.age_key_path.as_deref();
+
+    // Remote engines have no vault file to decrypt lazily, and normalized
+    // lookups need every key name: the whole vault is loaded
+    if shadow_secret::vault::is_remote_engine(&config.vault.engine) || config.vault.normalize_keys {
+        let mut vault = Vault::load_config(&config, config_dir)?.with_normalized_keys(config.vault.normalize_keys);
+        if let Some(overlay) = LocalOverlay::find(config_dir) {
+            if overlay.is_plaintext() {
+                warn_plaintext_overlay(&overlay);
+            }
+            vault.apply_overlay(&overlay.path().to_string_lossy(), overlay.load(age_key_path)?);
+        }
+
+        return match vault.get(key) {
+            Some(value) => Ok(value.clone()),
+            None => anyhow::bail!("Secret '{}' not found in vault", key),
+        };
+    }
+
+    let mut sources = config.vault_source_paths(config_dir)?;
+
+    // The local overlay wins over the shared vault, as in `unlock`
+    match LocalOverlay::find(config_dir) {
+        Some(LocalOverlay::Encrypted(path)) => sources.push(path),
+        Some(overlay) => {
+            warn_plaintext_overlay(&overlay);
+            if let Some(value) = overlay.load(age_key_path)?.remove(key) {
+                return Ok(value);
+            }
+        }
+        None => {}
+    }
+
+    // Lazy mode: only the requested key is decrypted
+    let vault = LazyVault::new(sources, age_key_path);
+
+    match vault.get(key)? {
+        Some(value) => Ok(value),
+        None => anyhow::bail!("Secret '{}' not found in vault", key),
+    }
+}
+
+/// Run `command` with secrets in its environment and return its exit code.
+fn run_exec(config_path: &str, only: &[String], command: &[String], explain: bool) -> Result<i32> {
+    // stdout belongs to the child, so status messages go to stderr
+    for line in config_signature_report(config_path, false)? {
+        say_err!("{}", line);
+    }
+
+    let (config, config_dir) = load_project_config(config_path)?;
+    let age_key_path = config.vault.age_key_path.as_deref();
+    if explain && !confirm_plan(&shadow_secret::explain::exec_plan(&config, &config_dir, command)?)? {
+        return Ok(0);
+    }
+
+    let mut vault = Vault::load_config(&config, &config_dir)?
+        .with_normalized_keys(config.vault.normalize_keys);
+    if let Some(overlay) = LocalOverlay::find(&config_dir) {
+        if overlay.is_plaintext() {
+            warn_plaintext_overlay(&overlay);
+        }
+        vault.apply_overlay(&overlay.path().to_string_lossy(), overlay.load(age_key_path)?);
+    }
+
+    let env = shadow_secret::exec::child_environment(&vault, only)?;
+    drop(vault);
+    say_err!("🔐 Running '{}' with {} secret(s) in its environment", command[0], env.len());
+
+    let status = tokio::runtime::Runtime::new()
+        .context("Failed to start async runtime")?
+        .block_on(shadow_secret::exec::run(&command[0], &command[1..], &env))?;
+
+    Ok(shadow_secret::exec::exit_code(status))
+}
+
+fn run_list(config_path: &str, provenance: bool, columns: Option<&str>, sort: Option<&str>, json: bool) -> Result<()> {
+    use shadow_secret::table::{self, Column, Table};
+
+    // Tables need the sources, which the agent does not keep
+    let as_table = provenance || columns.is_some() || sort.is_some();
+    if !as_table {
+        if let Some(socket) = shadow_secret::agent::serving(Path::new(config_path)) {
+            let request = shadow_secret::agent::Request::List;
+            let response = shadow_secret::agent::request(&socket, &request)?.into_result()?;
+            return print_key_names(&response.keys.unwrap_or_default(), json);
+        }
+    }
+
+    let (config, config_dir) = load_project_config(config_path)?;
+    let age_key_path = config.vault.age_key_path.as_deref();
+
+    let mut vault = Vault::load_config(&config, &config_dir)?;
+    if let Some(overlay) = LocalOverlay::find(&config_dir) {
+        if overlay.is_plaintext() {
+            warn_plaintext_overlay(&overlay);
+        }
+        vault.apply_overlay(&overlay.path().to_string_lossy(), overlay.load(age_key_path)?);
+    }
+
+    if !as_table {
+        let keys: Vec<String> = vault.provenance().into_iter().map(|entry| entry.key).collect();
+        return print_key_names(&keys, json);
+    }
+
+    let (events, _) = shadow_secret::audit::read(&shadow_secret::audit::audit_log_path()?)?;
+    let last_used = shadow_secret::usage::last_used(&events);
+
+    let mut rows = Table::new(vec![
+        Column::new("key"),
+        Column::truncated("source"),
+        Column::truncated("overrides"),
+        Column::new("encrypted"),
+        Column::new("last-used"),
+        Column::numeric("length"),
+    ]);
+    // Keys SOPS left in plaintext, by source file (partial encryption)
+    let mut encryption: HashMap<String, BTreeMap<String, bool>> = HashMap::new();
+    for entry in vault.provenance() {
+        let status = encryption.entry(entry.source.clone()).or_insert_with(|| {
+            std::fs::read(&entry.source)
+                .ok()
+                .and_then(|content| shadow_secret::vault::encryption_status(&entry.source, &content).ok())
+                .unwrap_or_default()
+        });
+        let encrypted = match status.get(&entry.key) {
+            Some(true) => "yes",
+            Some(false) => "no",
+            None => "",
+        };
+        rows.push(vec![
+            entry.key.clone(),
+            entry.source.clone(),
+            entry.shadowed.join(", "),
+            encrypted.to_string(),
+            last_used.get(&entry.key).cloned().unwrap_or_default(),
+            vault.get(&entry.key).map(|value| value.chars().count().to_string()).unwrap_or_default(),
+        ]);
+    }
+
+    match sort {
+        Some("last-used") => rows.sort_by("last-used", true)?,
+        Some("length") => rows.sort_by("length", true)?,
+        _ => rows.sort_by("key", false)?,
+    }
+    let selected = match columns {
+        Some(columns) => table::parse_columns(columns),
+        None if provenance => ["key", "source", "overrides", "encrypted"].map(str::to_string).to_vec(),
+        None => ["key", "source", "last-used", "length"].map(str::to_string).to_vec(),
+    };
+    rows.select(&selected)?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&rows.to_json())?);
+    } else {
+        print!("{}", rows.render(table::terminal_width()));
+    }
+    Ok(())
+}
+
+/// Print key names, one per line or as a JSON array.
+fn print_key_names(keys: &[String], json: bool) -> Result<()> {
+    if json {
+        println!("{}", serde_json::to_string_pretty(keys)?);
+    } else {
+        for key in keys {
+            println!("{}", key);
+        }
+    }
+    Ok(())
+}
 
-    // Step 3: Load secrets from vault
-    let vault_path = config.vault_source_path(config_dir)?;
-    let vault_path_str = vault_path.to_str()
-        .ok_or_else(|| anyhow::anyhow!("Vault path contains invalid UTF-8"))?;
+fn run_scrub(config_path: &str, yes: bool) -> Result<()> {
+    use shadow_secret::scrub;
 
-    println!("📖 Loading secrets from: {}", vault_path_str);
+    say!("🧽 Shadow Secret Scrub\n");
+
+    let (config, config_dir) = load_project_config(config_path)?;
+    let age_key_path = config.vault.age_key_path.as_deref();
+
+    let mut vault = Vault::load_config(&config, &config_dir)?;
+    if let Some(overlay) = LocalOverlay::find(&config_dir) {
+        vault.apply_overlay(&overlay.path().to_string_lossy(), overlay.load(age_key_path)?);
+    }
+    let secrets = vault.all();
+
+    // Step 1: Clipboard
+    match scrub::clipboard_contents()? {
+        None => say!("⚠️  No clipboard tool found, skipping clipboard"),
+        Some(text) => {
+            let keys = scrub::matching_keys(&text, secrets);
+            if keys.is_empty() {
+                say!("✓ Clipboard is clean");
+            } else {
+                scrub::clear_clipboard()?;
+                say!("🧹 Cleared clipboard (contained {})", keys.join(", "));
+            }
+        }
+    }
+
+    // Step 2: Shell history files
+    let mut redacted = 0;
+    for path in scrub::history_files() {
+        let content = std::fs::read(&path).with_context(|| format!("Failed to read {:?}", path))?;
+        let leaks = scrub::find_leaks(&content, secrets);
+        if leaks.is_empty() {
+            say!("✓ {} is clean", path.display());
+            continue;
+        }
+
+        say!("\n🚨 {} line(s) in {} contain vault values:", leaks.len(), path.display());
+        for leak in &leaks {
+            println!("    line {}: {}", leak.line, leak.keys.join(", "));
+        }
+
+        let approved = yes
+            || shadow_secret::confirm::confirm(&format!("Redact these values in {}?", path.display()), true)?;
+        if approved {
+            scrub::redact_file(&path, secrets)?;
+            redacted += 1;
+            say!("🧹 Redacted {}", path.display());
+        }
+    }
+
+    if redacted > 0 {
+        say!("\n💡 Running shells keep history in memory: restart them (or run 'history -c') so the values are not written back.");
+    }
+
+    Ok(())
+}
+
+fn run_fsck(config_path: &str) -> Result<()> {
+    use shadow_secret::fsck::{check_vault, Severity};
+
+    say!("🩺 Shadow Secret Vault Check");
+    println!("Loading configuration from: {}\n", config_path);
+
+    let config = Config::from_file(config_path)
+        .with_context(|| format!("Failed to load config from: {}", config_path))?;
+    shadow_secret::vault::set_engine(&config.vault.engine)?;
+    shadow_secret::process::set_timeouts(config.timeouts.resolve()?);
+
+    let config_abs_path = PathBuf::from(config_path)
+        .canonicalize()
+        .with_context(|| format!("Failed to resolve config file path: {}", config_path))?;
+
+    let config_dir = config_abs_path
+        .parent()
+        .context("Config file has no parent directory")?;
+
+    let age_key_path = config.vault.age_key_path.as_deref();
+    let naming = shadow_secret::naming::Rules::new(&config.naming)?;
+    let secret_keys = shadow_secret::fsck::secret_keys(&config);
+    let mut errors = 0;
+    let mut warnings = 0;
+
+    for vault_path in config.vault_source_paths(config_dir)? {
+        say!("📦 {}", vault_path.display());
+
+        let report = check_vault(&vault_path, age_key_path, &naming, &secret_keys)?;
+        if report.findings.is_empty() {
+            say!("   ✓ MAC, recipients, keys and encoding OK");
+        }
+        if !report.plaintext_keys.is_empty() {
+            say!("   🔓 Stored unencrypted: {}", report.plaintext_keys.join(", "));
+        }
+
+        for finding in &report.findings {
+            match finding.severity {
+                Severity::Error => {
+                    errors += 1;
+                    say!("   ❌ [{}] {}", finding.check, finding.message);
+                }
+                Severity::Warning => {
+                    warnings += 1;
+                    say!("   ⚠️  [{}] {}", finding.check, finding.message);
+                }
+            }
+        }
+        println!();
+    }
+
+    if errors > 0 {
+        anyhow::bail!("Vault check failed: {} error(s), {} warning(s)", errors, warnings);
+    }
+
+    if warnings > 0 {
+        say!("✅ Vault is usable ({} warning(s))", warnings);
+    } else {
+        say!("✅ Vault is healthy");
+    }
+
+    Ok(())
+}
+
+/// Load a project config and return it with its directory.
+fn load_project_config(config_path: &str) -> Result<(Config, PathBuf)> {
+    let config = Config::from_file(config_path)
+        .with_context(|| format!("Failed to load config from: {}", config_path))?;
+
+    config.validate()
+        .with_context(|| "Configuration validation failed")?;
+    shadow_secret::vault::set_engine(&config.vault.engine)?;
+    shadow_secret::process::set_timeouts(config.timeouts.resolve()?);
+
+    let config_dir = PathBuf::from(config_path)
+        .canonicalize()
+        .with_context(|| format!("Failed to resolve config file path: {}", config_path))?
+        .parent()
+        .context("Config file has no parent directory")?
+        .to_path_buf();
+
+    Ok((config, config_dir))
+}
+
+fn run_canary_add(config_path: &str) -> Result<()> {
+    say!("🐤 Shadow Secret Canary");
+
+    let (config, config_dir) = load_project_config(config_path)?;
+    let vault_path = config.vault_source_path(&config_dir)?;
+    let project = config_dir
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let key = shadow_secret::canary::add_canary(
+        &vault_path,
+        &project,
+        config.vault.age_key_path.as_deref(),
+    )?;
+
+    say!("✓ Added canary {} to {}", key, vault_path.display());
+    say!("💡 It is injected and pushed like any other secret ('$ALL', push-cloud).");
+    say!("💡 Run 'shadow-secret canary check' periodically to detect leaks.");
+
+    Ok(())
+}
+
+fn run_vault_rename_key(
+    config_path: &str,
+    old_key: &str,
+    new_key: &str,
+    dry_run: bool,
+    cloud: bool,
+    project: Option<String>,
+    env: Option<String>,
+) -> Result<()> {
+    use shadow_secret::rename;
+
+    say!("✏️  Shadow Secret Rename: {} → {}\n", old_key, new_key);
+
+    let (config, config_dir) = load_project_config(config_path)?;
+    let age_key_path = config.vault.age_key_path.as_deref();
+
+    let plan = rename::plan_rename(&config, Path::new(config_path), &config_dir, old_key, new_key)?;
+
+    say!("🔐 Vault file(s):");
+    for path in &plan.vault_files {
+        say!("   → {}", path.display());
+    }
+    say!("📝 File(s) with placeholders:");
+    if plan.file_changes.is_empty() {
+        println!("   (none)");
+    }
+    for change in &plan.file_changes {
+        say!("   → {} ({} placeholder(s))", change.path.display(), change.occurrences);
+    }
+    for path in &plan.missing_targets {
+        say!("   ⚠️  Target file not found, skipped: {}", path.display());
+    }
+    if cloud {
+        say!(
+            "☁️  Vercel variable (project: {}, env: {})",
+            project.as_deref().unwrap_or("auto-detected"),
+            env.as_deref().unwrap_or("all")
+        );
+    }
+
+    if dry_run {
+        say!("\n🔍 Dry run - nothing was changed.");
+        return Ok(());
+    }
+
+    rename::apply_rename(&plan, age_key_path)?;
+    say!("\n✓ Renamed {} to {} locally", old_key, new_key);
+
+    if cloud {
+        let project = match project {
+            Some(project) => Some(project),
+            None => detect_project_id()?,
+        };
+        let value = LazyVault::new(plan.vault_files.clone(), age_key_path)
+            .get(new_key)?
+            .with_context(|| format!("'{}' not found after rename", new_key))?;
+
+        let naming = &config.cloud.vercel;
+        tokio::runtime::Runtime::new()
+            .context("Failed to start async runtime")?
+            .block_on(shadow_secret::cloud::rename_vercel_env_var(
+                &naming.remote_name(old_key),
+                &naming.remote_name(new_key),
+                &value,
+                project.as_deref(),
+                env.as_deref(),
+                naming,
+            ))
+            .context("Local rename succeeded, but renaming the Vercel variable failed")?;
+        say!(
+            "✓ Renamed Vercel variable {} to {}",
+            naming.remote_name(old_key),
+            naming.remote_name(new_key)
+        );
+    }
+
+    if Path::new(&format!("{}.minisig", config_path)).exists() {
+        say!("💡 The config changed: re-sign it with 'minisign -Sm {}'", config_path);
+    }
+
+    Ok(())
+}
+
+fn run_target_add(config_path: &str, name: String, path: String, keys: &[String], yes: bool) -> Result<()> {
+    use shadow_secret::target;
+
+    let (_, config_dir) = load_project_config(config_path)?;
+
+    let target_config = TargetConfig {
+        name,
+        placeholders: keys.iter().map(|key| target::placeholder(key)).collect(),
+        path,
+        ..Default::default()
+    };
+
+    // Step 1: The target file must exist and contain the placeholders
+    let target_path = config_dir.join(&target_config.path);
+    let content = std::fs::read_to_string(&target_path)
+        .with_context(|| format!("Target file does not exist or is not readable: {}", target_path.display()))?;
+
+    let missing = target::missing_placeholders(&content, &target_config.placeholders);
+    if !missing.is_empty() {
+        say!(
+            "⚠️  {} does not contain: {}",
+            target_config.path,
+            missing.join(", ")
+        );
+        if ask("Insert the missing placeholders into the file?", true, yes)? {
+            let missing_keys: Vec<String> = missing
+                .iter()
+                .map(|placeholder| shadow_secret::injector::extract_key_name(placeholder).to_string())
+                .collect();
+            let updated = target::insert_placeholders(&target_path, &content, &missing_keys)?;
+            std::fs::write(&target_path, updated)
+                .with_context(|| format!("Failed to write {}", target_path.display()))?;
+            say!("✓ Inserted {} placeholder(s) into {}", missing.len(), target_config.path);
+        }
+    }
+
+    // Step 2: Append the target to the config, keeping its comments
+    let config_content = std::fs::read_to_string(config_path)
+        .with_context(|| format!("Failed to read config: {}", config_path))?;
+    let updated = target::add_target(&config_content, &target_config)?;
+
+    let updated_config: Config = serde_yaml::from_str(&updated)?;
+    updated_config.check_target_containment(&config_dir)?;
+
+    std::fs::write(config_path, updated)
+        .with_context(|| format!("Failed to write config: {}", config_path))?;
+    say!("✓ Added target '{}' to {}", target_config.name, config_path);
+
+    if Path::new(&format!("{}.minisig", config_path)).exists() {
+        say!("💡 The config changed: re-sign it with 'minisign -Sm {}'", config_path);
+    }
+
+    Ok(())
+}
+
+fn run_canary_check(config_path: &str, webhook: Option<String>, github_api: &str) -> Result<()> {
+    use shadow_secret::canary;
+
+    say!("🐤 Shadow Secret Canary Check\n");
+
+    let github_token = std::env::var(canary::GITHUB_TOKEN_ENV).with_context(|| {
+        format!("${} must be set to use GitHub code search", canary::GITHUB_TOKEN_ENV)
+    })?;
+
+    let (config, config_dir) = load_project_config(config_path)?;
+    let vault = Vault::load_config(&config, &config_dir)?;
+
+    let canaries = canary::canaries(vault.all());
+    if canaries.is_empty() {
+        say!("⚠️  No canaries in the vault. Add one with 'shadow-secret canary add'.");
+        return Ok(());
+    }
+
+    let mut leaked = 0;
+    for (key, token) in &canaries {
+        print!("{}", shadow_secret::accessible::render(&format!("   → {}... ", key)));
+        let matches = canary::search_github(github_api, &github_token, token)?;
+
+        if matches.is_empty() {
+            say!("✓ not found");
+            continue;
+        }
+
+        leaked += 1;
+        say!("🚨 FOUND");
+        for url in &matches {
+            println!("      {}", url);
+        }
+
+        if let Some(webhook) = &webhook {
+            canary::send_alert(webhook, key, &matches)?;
+            say!("      ✓ Alert sent to webhook");
+        }
+    }
+
+    if leaked > 0 {
+        anyhow::bail!(
+            "{} canary(ies) leaked. Rotate every secret stored in this vault.",
+            leaked
+        );
+    }
+
+    say!("\n✅ No canary leaks detected");
+    Ok(())
+}
+
+fn run_unlock_global(timeout: Option<String>, wait: bool, explain: bool) -> Result<()> {
+    say!("🔓 Shadow Secret Unlock (Global)");
+    // Step 1: Load global config explicitly
+    let global_config_path = shadow_secret::paths::global_config_path()?;
+    say!("Loading global configuration from {}\n", global_config_path.display());
+
+    let mut config = Config::from_file(&global_config_path)
+        .with_context(|| "Failed to load global config")?;
+
+    config.validate()
+        .with_context(|| "Global configuration validation failed")?;
+    shadow_secret::vault::set_engine(&config.vault.engine)?;
+    shadow_secret::process::set_timeouts(config.timeouts.resolve()?);
+    config.targets = active_targets(config.targets)?;
+
+    let timeout = session_timeout(timeout, &config)?;
+
+    say!("✓ Global configuration loaded and validated");
+
+    // Step 2: Get config directory for path resolution
+    let config_dir = global_config_path
+        .parent()
+        .context("Global config has no parent directory")?;
+
+    if explain
+        && !confirm_plan(&shadow_secret::explain::unlock_plan("unlock-global", &config, config_dir, false)?)?
+    {
+        return Ok(());
+    }
+
+    // Step 3: Load secrets from vault (extra sources are decrypted concurrently)
+    let mut vault = load_vault(&config, config_dir)?
+        .with_normalized_keys(config.vault.normalize_keys);
+    apply_local_overlay(&mut vault, config_dir, config.vault.age_key_path.as_deref())?;
+    apply_dynamic_secrets(&mut vault, &config)?;
+    warn_shadowed_keys(&vault);
+
+    let secrets = vault.all();
+    say!("✓ Loaded {} secret(s)", secrets.len());
+
+    check_busy_targets(&config.targets, wait)?;
+
+    // Step 4: Inject secrets into each target
+    say!("\n🎯 Injecting secrets into targets...");
+
+    begin_recovery_journal(&config, config_dir);
+    let session = unlock_targets(&config, &vault)?;
+    print_unlock_report(session.report());
+    write_injection_report(&session);
+
+    say!("\n✓ All secrets injected successfully!");
+    say!("\n🎉 Global secrets are now unlocked and injected!");
+    wait_for_lock(timeout)?;
+
+    say!("\n🔄 Restoring templates...");
+
+    // Restore all backups
+    lock_session(session);
+
+    say!("✓ Templates restored!");
+    say!("👋 See you next time!");
+
+    Ok(())
+}
+
+fn run_journal_list() -> Result<()> {
+    let journals = shadow_secret::journal::list(&shadow_secret::journal::journal_dir()?)?;
+    if journals.is_empty() {
+        say!("✓ No recovery journals: every session was restored");
+        return Ok(());
+    }
+
+    say!("📓 {} recovery journal(s):", journals.len());
+    for journal in &journals {
+        let status = if journal.is_active() { "active session" } else { "interrupted" };
+        println!("  {} ({})", journal.id, status);
+    }
+    say!("\n💡 Restore an interrupted session with: shadow-secret journal restore <id>");
+    Ok(())
+}
+
+fn run_journal_restore(id: &str, age_key: Option<&str>, force: bool) -> Result<()> {
+    let dir = shadow_secret::journal::journal_dir()?;
+    let file = shadow_secret::journal::find(&dir, id)?;
+    if file.is_active() && !force {
+        anyhow::bail!(
+            "Session {} is still running; lock it there, or pass --force to restore anyway",
+            id
+        );
+    }
+
+    let journal = shadow_secret::journal::read(&file.path, age_key)?;
+    say!("🔄 Restoring {} target(s) unlocked at {}...", journal.entries.len(), journal.created_at);
+    for path in journal.restore()? {
+        say!("  ✓ {}", path);
+    }
+
+    std::fs::remove_file(&file.path)
+        .with_context(|| format!("Failed to delete journal: {}", file.path.display()))?;
+    say!("✓ Templates restored, journal deleted");
+    Ok(())
+}
+
+fn run_restore_snapshot(id: Option<&str>, list: bool, age_key: Option<&str>) -> Result<()> {
+    use shadow_secret::snapshot;
+
+    let dir = snapshot::snapshots_dir()?;
+    if list {
+        let snapshots = snapshot::list(&dir)?;
+        if snapshots.is_empty() {
+            say!("ℹ️  No snapshots: unlock with --snapshot to take one");
+            return Ok(());
+        }
+        say!("📸 {} snapshot(s):", snapshots.len());
+        for entry in &snapshots {
+            println!("  {}", entry.id);
+        }
+        say!("\n💡 Restore one with: shadow-secret restore-snapshot <id>");
+        return Ok(());
+    }
+
+    let entry = snapshot::find(&dir, id)?;
+    let snapshot = snapshot::read(&entry.path, age_key)?;
+    say!("🔄 Restoring {} target(s) from snapshot {} ({})...", snapshot.files.len(), entry.id, snapshot.created_at);
+    for path in snapshot.restore()? {
+        say!("  ✓ {}", path);
+    }
+    say!("✓ Targets restored (the snapshot is kept)");
+    Ok(())
+}
+
+fn run_gc(older_than: &str, dry_run: bool) -> Result<()> {
+    let max_age = shadow_secret::gc::parse_max_age(older_than)?;
+    let state_dir = shadow_secret::isolation::state_dir()?;
+    let stale = shadow_secret::gc::collect(&state_dir, max_age, dry_run)?;
+    if stale.is_empty() {
+        say!("✓ No stale state older than {}", older_than);
+        return Ok(());
+    }
+
+    let verb = if dry_run { "Would remove" } else { "Removed" };
+    say!("🧹 {} {} stale file(s):", verb, stale.len());
+    for entry in &stale {
+        println!(
+            "  {} ({}, {} day(s) old)",
+            entry.path.display(),
+            entry.kind,
+            entry.age.as_secs() / 86400
+        );
+    }
+    Ok(())
+}
+
+fn run_rekey(recipient: &str, remove: Option<&str>) -> Result<()> {
+    use shadow_secret::rekey;
+
+    let global_dir = shadow_secret::init::get_global_config_dir()?;
+    let projects = rekey::registered_projects(&global_dir)?;
+    if projects.is_empty() {
+        say!("ℹ️  No registered project (run 'shadow-secret init-global', then 'init-project')");
+        return Ok(());
+    }
+
+    say!("🔑 Re-keying {} project(s) for {}", projects.len(), recipient);
+    let outcomes = rekey::rekey_all(&projects, recipient, remove)?;
+
+    let mut failed = 0;
+    for outcome in &outcomes {
+        match &outcome.result {
+            Ok(vaults) => say!("  ✓ {} ({} vault file(s))", outcome.project.display(), vaults.len()),
+            Err(e) => {
+                failed += 1;
+                say!("  ✗ {}: {:#}", outcome.project.display(), e);
+            }
+        }
+    }
+
+    if failed > 0 {
+        anyhow::bail!(
+            "{} of {} project(s) could not be re-keyed (their .sops.yaml was left unchanged)",
+            failed,
+            outcomes.len()
+        );
+    }
+    say!("\n✓ All projects re-keyed");
+    Ok(())
+}
+
+fn run_usage_report(since: Option<&str>, format: &str) -> Result<()> {
+    use shadow_secret::{audit, sink, usage};
+
+    let since = since
+        .map(|age| -> Result<_> {
+            let age = chrono::Duration::from_std(shadow_secret::gc::parse_max_age(age)?)?;
+            Ok(chrono::Utc::now() - age)
+        })
+        .transpose()?;
+    let (events, skipped) = audit::read(&audit::audit_log_path()?)?;
+    if skipped > 0 {
+        say_err!("⚠️  Skipped {} unreadable audit log line(s)", skipped);
+    }
+
+    let summary = usage::summarize(&events, since);
+    let (rendered, extension) = match format {
+        "json" => (format!("{}\n", serde_json::to_string_pretty(&summary)?), "json"),
+        "table" => {
+            print!("{}", summary.render_table(shadow_secret::table::terminal_width()));
+            // Archived uncut
+            (summary.render_table(None), "txt")
+        }
+        _ => (summary.render_markdown(), "md"),
+    };
+    if format != "table" {
+        print!("{}", rendered);
+    }
+
+    let name = sink::file_name(sink::REPORT_AUDIT, chrono::Utc::now(), extension);
+    sink::archive(sink::REPORT_AUDIT, &name, rendered.as_bytes());
+    Ok(())
+}
+
+fn run_compliance_report(config_path: &str, output: Option<&Path>) -> Result<()> {
+    let config_path = PathBuf::from(config_path)
+        .canonicalize()
+        .with_context(|| format!("Failed to resolve config file path: {}", config_path))?;
+    let report = shadow_secret::compliance::generate(&config_path)?;
+    let json = serde_json::to_string_pretty(&report)?;
+
+    match output {
+        Some(path) => {
+            std::fs::write(path, format!("{}\n", json)).with_context(|| format!("Failed to write {:?}", path))?;
+            say_err!("✅ Compliance report written to {}", path.display());
+        }
+        None => println!("{}", json),
+    }
+
+    let name = shadow_secret::sink::file_name(shadow_secret::sink::REPORT_COMPLIANCE, chrono::Utc::now(), "json");
+    shadow_secret::sink::archive(shadow_secret::sink::REPORT_COMPLIANCE, &name, format!("{}\n", json).as_bytes());
+    Ok(())
+}
+
+fn run_status(last: bool, config_path: &str) -> Result<()> {
+    use shadow_secret::{report, status};
+
+    if last {
+        match report::latest(&report::reports_dir()?)? {
+            Some(session) => print!("{}", session.render()),
+            None => say!("ℹ️  No injection report yet: unlock a project first"),
+        }
+        return Ok(());
+    }
+
+    if Path::new(config_path).exists() {
+        print_project_status(config_path)?;
+    }
+
+    let Some(path) = status::status_path()?.filter(|path| path.exists()) else {
+        say!("ℹ️  No status file (set ${} or run under CI)", status::STATUS_FILE_ENV);
+        println!("   Run 'shadow-secret status --last' for the last injection report");
+        return Ok(());
+    };
+    let current = status::read(&path)?;
+    println!("State: {:?} (pid {})", current.state, current.pid);
+    println!("Started: {}", current.started_at);
+    println!("Updated: {}", current.updated_at);
+    for target in &current.targets {
+        say!("  → {}", target);
+    }
+    Ok(())
+}
+
+/// Targets, vault and key of the project at `config_path` (nothing is
+/// decrypted).
+fn print_project_status(config_path: &str) -> Result<()> {
+    use shadow_secret::health::{self, TargetState};
+    use shadow_secret::table::{self, Column, Table};
+
+    let (config, config_dir) = load_project_config(config_path)?;
+    let (vault_checks, vault_keys, recipients) = health::vault_health(&config, &config_dir)?;
+    let journals = shadow_secret::journal::list(&shadow_secret::journal::journal_dir()?)?;
+    let session_running = journals.iter().any(|journal| journal.is_active());
+
+    let mut targets = Table::new(vec![Column::new("target"), Column::truncated("path"), Column::truncated("state")]);
+    let mut injected = false;
+    for target in &config.targets {
+        let state = health::target_state(target, &vault_keys)?;
+        injected |= matches!(state, TargetState::Injected | TargetState::Partial { .. });
+        targets.push(vec![target.name.clone(), target.path.clone(), state.to_string()]);
+    }
+    print!("{}", targets.render(table::terminal_width()));
+
+    println!("\nVault and key:");
+    for check in vault_checks.iter().chain(&health::key_health(&config, &recipients)) {
+        say!("  {} {}", if check.ok { "✓" } else { "✗" }, check.message);
+    }
+
+    println!("\nSessions:");
+    if session_running {
+        say!("  🔓 An unlock session is running: its targets are restored when it locks");
+    } else if journals.is_empty() {
+        say!("  ✓ No session running");
+    } else {
+        say!("  ⚠️  {} interrupted session(s): run 'shadow-secret journal list'", journals.len());
+    }
+    if injected && !session_running {
+        if journals.is_empty() {
+            say!("  ⚠️  Secrets are injected but no session is running, and there is no recovery journal");
+        } else {
+            say!("  ⚠️  Secrets are injected but no session is running: restore the templates with 'shadow-secret journal restore <id>'");
+        }
+    }
+    println!();
+    Ok(())
+}
+
+fn run_trust_binaries(programs: &[String]) -> Result<()> {
+    use shadow_secret::binaries;
+
+    let trusted = binaries::trust(&binaries::BinaryStore::default_path()?, programs)?;
+    if trusted.is_empty() {
+        say!("ℹ️  None of {} found in PATH", binaries::TRACKED_PROGRAMS.join(", "));
+        return Ok(());
+    }
+    for (name, binary) in &trusted {
+        say!("  🔏 {}: {} (sha256 {})", name, binary.path.display(), binary.sha256);
+    }
+    say!("\n✓ Trusted {} binary(ies)", trusted.len());
+    Ok(())
+}
+
+fn run_remote_unlock(
+    config_path: &str,
+    host: &str,
+    project: &str,
+    require_signature: bool,
+    remote_bin: &str,
+) -> Result<()> {
+    use std::io::Write;
+
+    say!("🔓 Shadow Secret Remote Unlock ({}:{})", host, project);
+    println!("Loading configuration from: {}\n", config_path);
+
+    check_config_signature(config_path, require_signature)?;
+
+    let config = Config::from_file(config_path)
+        .with_context(|| format!("Failed to load config from: {}", config_path))?;
+    config.validate()
+        .with_context(|| "Configuration validation failed")?;
+    shadow_secret::vault::set_engine(&config.vault.engine)?;
+    shadow_secret::process::set_timeouts(config.timeouts.resolve()?);
+
+    let config_abs_path = PathBuf::from(config_path)
+        .canonicalize()
+        .with_context(|| format!("Failed to resolve config file path: {}", config_path))?;
+    let config_dir = config_abs_path
+        .parent()
+        .context("Config file has no parent directory")?;
+
+    // Decrypt locally: the key never leaves this machine
+    let mut vault = load_vault(&config, config_dir)?
+        .with_normalized_keys(config.vault.normalize_keys);
+    apply_local_overlay(&mut vault, config_dir, config.vault.age_key_path.as_deref())?;
+    apply_dynamic_secrets(&mut vault, &config)?;
+    warn_shadowed_keys(&vault);
+
+    let payload = shadow_secret::remote::payload(&config, &vault);
+    say!("✓ Sending {} secret(s) for {} target(s)", payload.secrets.len(), payload.targets.len());
+
+    say!("\n🔗 Connecting to {}...", host);
+    let mut child = process::command("ssh")?
+        .args(shadow_secret::remote::ssh_args(host, project, remote_bin))
+        .stdin(std::process::Stdio::piped())
+        .spawn()
+        .context("Failed to run ssh (is OpenSSH installed?)")?;
+
+    // The receiver restores the templates once stdin closes
+    let mut stdin = child.stdin.take().context("Failed to open ssh stdin")?;
+    writeln!(stdin, "{}", serde_json::to_string(&payload)?)?;
+    stdin.flush()?;
+
+    say!("👉 Press Enter to lock secrets and restore the remote templates...");
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input)?;
+
+    drop(stdin);
+    let status = child.wait()?;
+    if !status.success() {
+        anyhow::bail!("Remote unlock failed on {} ({})", host, status);
+    }
+
+    say!("👋 Remote templates restored!");
+    Ok(())
+}
+
+fn run_remote_receive(project: &str) -> Result<()> {
+    use std::io::BufRead;
+
+    let mut stdin = std::io::stdin().lock();
+    let payload = shadow_secret::remote::read_payload(&mut stdin)?;
+    // Conditions are evaluated here, on the machine being unlocked
+    let targets = active_targets(payload.targets)?;
+    let targets = shadow_secret::remote::resolve_targets(&targets, Path::new(project))?;
+    let vault = Vault::new(payload.secrets);
+
+    say!("🎯 Injecting secrets into {}...", project);
+    let session = UnlockSession::unlock(&targets, &vault)?;
+    print_unlock_report(session.report());
+    write_injection_report(&session);
+    say!("\n🎉 Remote secrets are now unlocked and injected!");
+
+    // Hold until the sender locks or the connection drops
+    for line in stdin.lines() {
+        if line.is_err() {
+            break;
+        }
+    }
+
+    say!("🔄 Restoring templates on the remote host...");
+    lock_session(session);
+    say!("✓ Templates restored!");
+    Ok(())
+}
+
+fn run_agent_start(config_path: &str) -> Result<()> {
+    use shadow_secret::agent::{self, Agent};
+
+    say!("🤖 Shadow Secret Agent");
+    println!("Loading configuration from: {}\n", config_path);
+
+    // Targets are checked as for unlock: the agent injects them on request
+    let (config, config_dir) = load_unlock_config(config_path, false, false)?;
+    let config_file = PathBuf::from(config_path)
+        .canonicalize()
+        .with_context(|| format!("Failed to resolve config file path: {}", config_path))?;
+    let session_limit = session_timeout(None, &config)?.map(|(_, limit)| limit);
+
+    let mut vault = load_vault(&config, &config_dir)?.with_normalized_keys(config.vault.normalize_keys);
+    apply_local_overlay(&mut vault, &config_dir, config.vault.age_key_path.as_deref())?;
+    let agent = Agent::new(config_file, vault.all().clone()).with_targets(config.targets.clone(), session_limit);
+    drop(vault);
+
+    say!("✓ Loaded {} secret(s)", agent.secrets.all().len());
+    if !agent.secrets.is_locked() {
+        say_err!("⚠️  Could not lock the secrets in memory (raise 'ulimit -l'): they may be swapped to disk");
+    }
+
+    let socket = agent::socket_path()?;
+    let cleanup = socket.clone();
+    ctrlc::set_handler(move || {
+        if !cfg!(windows) {
+            let _ = std::fs::remove_file(&cleanup);
+        }
+        // Targets unlocked through the agent get their templates back
+        if shadow_secret::cleaner::has_registered_files() {
+            shadow_secret::cleaner::cleanup_and_restore();
+        }
+        std::process::exit(130);
+    })
+    .context("Failed to set Ctrl+C handler")?;
+
+    say!("\n🔌 Listening on {}", socket.display());
+    say!("👉 Stop with 'shadow-secret agent stop' or Ctrl+C");
+    agent::serve(&agent, &socket)?;
+
+    say!("👋 Agent stopped");
+    Ok(())
+}
+
+/// The global config, its directory and its backup destination.
+fn load_backup_config() -> Result<(Config, PathBuf, shadow_secret::backup::Destination)> {
+    let global_dir = shadow_secret::init::get_global_config_dir()?;
+    let config = Config::from_file(global_dir.join("global.yaml")).with_context(|| "Failed to load global config")?;
+    config.validate().with_context(|| "Configuration validation failed")?;
+    shadow_secret::process::set_timeouts(config.timeouts.resolve()?);
+    let destination = shadow_secret::backup::Destination::from_config(&config.backup, &global_dir)?;
+    Ok((config, global_dir, destination))
+}
+
+fn run_backup_push() -> Result<()> {
+    use shadow_secret::backup;
+
+    say!("💾 Shadow Secret Backup\n");
+
+    let (config, global_dir, destination) = load_backup_config()?;
+    let (sources, skipped) = backup::registered_sources(&global_dir.join("global.yaml"))?;
+    for note in &skipped {
+        say_err!("⚠️  Skipped {}", note);
+    }
+    if sources.is_empty() {
+        anyhow::bail!("No vault file to back up");
+    }
+
+    let snapshot = backup::snapshot_id(chrono::Utc::now());
+    let keep = config.backup.keep.unwrap_or(backup::DEFAULT_KEEP);
+    let summary = backup::push(&destination, &sources, &snapshot, keep)?;
+    for file in &summary.manifest.files {
+        say!("   ✓ {}  ({})", file.path, file.source);
+    }
+    for pruned in &summary.pruned {
+        say!("   🗑️  Pruned snapshot {}", pruned);
+    }
+
+    say!("\n✅ Snapshot {}: {} encrypted file(s)", summary.snapshot, summary.manifest.files.len());
+    Ok(())
+}
+
+fn run_backup_list() -> Result<()> {
+    let (_, _, destination) = load_backup_config()?;
+    let snapshots = destination.snapshots()?;
+    if snapshots.is_empty() {
+        say!("📭 No backup snapshot");
+        return Ok(());
+    }
+    for snapshot in snapshots {
+        match destination.manifest(&snapshot) {
+            Ok(manifest) => println!("{}\t{}\t{} file(s)", snapshot, manifest.host, manifest.files.len()),
+            Err(_) => println!("{}\t(incomplete)", snapshot),
+        }
+    }
+    Ok(())
+}
+
+fn run_backup_restore(snapshot: Option<&str>, project: Option<&str>, force: bool) -> Result<()> {
+    say!("💾 Shadow Secret Backup Restore\n");
+
+    let (_, global_dir, destination) = load_backup_config()?;
+    // Files only go back to vaults registered here, whatever the manifest says
+    let (sources, skipped) = shadow_secret::backup::registered_sources(&global_dir.join("global.yaml"))?;
+    for note in &skipped {
+        say_err!("⚠️  Skipped {}", note);
+    }
+    let written = shadow_secret::backup::restore(&destination, &sources, snapshot, project, force)?;
+    for path in &written {
+        say!("   ✓ {}", path.display());
+    }
+
+    say!("\n✅ Restored {} vault file(s)", written.len());
+    say!("💡 Decrypting them needs your age key (see 'shadow-secret provision')");
+    Ok(())
+}
+
+fn run_provision_send(port: u16) -> Result<()> {
+    use shadow_secret::{init, provision};
+    use std::time::Duration;
+
+    say!("📦 Shadow Secret Provisioning (sending)\n");
+
+    let home = shadow_secret::paths::require_home()?;
+    let bundle = provision::collect(
+        &init::get_default_master_key_path(),
+        &init::get_global_config_dir()?,
+        &home,
+    )?;
+    for name in bundle.files.keys() {
+        say!("   • {}", name);
+    }
+
+    let listener = std::net::TcpListener::bind(("0.0.0.0", port))
+        .with_context(|| format!("Failed to listen on port {}", port))?;
+    let code = provision::generate_code()?;
+    let mut host = sysinfo::System::host_name().unwrap_or_else(|| "<this-host>".to_string());
+    if port != provision::DEFAULT_PORT {
+        host.push_str(&format!(":{}", port));
+    }
+    say!("\n🔑 Code: {}", code);
+    say!("👉 On the new machine run: shadow-secret provision --from {}", host);
+    say!("⏳ Waiting on port {}...", port);
+
+    // One connection only: a wrong code ends the session
+    let (mut stream, peer) = listener.accept().context("Failed to accept the connection")?;
+    stream.set_read_timeout(Some(Duration::from_secs(120)))?;
+    say!("🔌 Connection from {}", peer);
+    provision::send(&mut stream, &code, &bundle).with_context(|| {
+        format!("Provisioning aborted (wrong code from {}?); run 'provision --send' again for a new code", peer)
+    })?;
+
+    say!("✓ Sent {} file(s) to {}", bundle.files.len(), peer);
+    Ok(())
+}
+
+fn run_provision_receive(from: &str, force: bool) -> Result<()> {
+    use dialoguer::{theme::ColorfulTheme, Input};
+    use shadow_secret::{init, provision};
+    use std::time::Duration;
+
+    say!("📦 Shadow Secret Provisioning (receiving)\n");
+
+    let (host, port) = provision::parse_address(from)?;
+    let code: String = Input::with_theme(&ColorfulTheme::default())
+        .with_prompt(format!("Code shown on {}", host))
+        .interact_text()?;
+
+    let address = std::net::ToSocketAddrs::to_socket_addrs(&(host.as_str(), port))
+        .with_context(|| format!("Cannot resolve {}", host))?
+        .next()
+        .with_context(|| format!("Cannot resolve {}", host))?;
+    let mut stream = std::net::TcpStream::connect_timeout(&address, Duration::from_secs(10))
+        .with_context(|| format!("Cannot connect to {}:{} (is 'provision --send' running there?)", host, port))?;
+    stream.set_read_timeout(Some(Duration::from_secs(120)))?;
+    let bundle = provision::receive(&mut stream, &code)?;
+    say!("✓ Authenticated {} and received {} file(s)\n", host, bundle.files.len());
+
+    let home = shadow_secret::paths::require_home()?;
+    let global_dir = shadow_secret::isolation::state_dir()?;
+    let written = provision::install(&bundle, &init::get_default_master_key_path(), &global_dir, &home, force)?;
+    for path in &written {
+        say!("   ✓ {}", path.display());
+    }
+
+    say!("\n✅ This machine is provisioned");
+    say!("💡 Project paths in global.yaml were moved to this home directory; check that projects are checked out there.");
+    Ok(())
+}
+
+fn run_agent_stop() -> Result<()> {
+    use shadow_secret::agent::{self, Request};
+
+    let response = agent::request(&agent::socket_path()?, &Request::Stop)?.into_result()?;
+    say!("✓ Agent stopped (pid {})", response.pid.unwrap_or_default());
+    Ok(())
+}
+
+fn run_agent_status() -> Result<()> {
+    use shadow_secret::agent::{self, Request};
+
+    let socket = agent::socket_path()?;
+    let Ok(response) = agent::request(&socket, &Request::Ping) else {
+        println!("No agent running (start one with 'shadow-secret agent start')");
+        return Ok(());
+    };
+    say!("🤖 Agent running on {}", socket.display());
+    println!("   config:  {}", response.config.unwrap_or_default());
+    println!("   pid:     {}", response.pid.unwrap_or_default());
+    let targets = match (response.unlocked, response.expires_in) {
+        (Some(true), Some(seconds)) => format!("unlocked (locked again in {}s)", seconds),
+        (Some(true), None) => "unlocked".to_string(),
+        _ => "locked".to_string(),
+    };
+    println!("   targets: {}", targets);
+    Ok(())
+}
+
+fn run_tray(unlock_for: Option<&str>, extend_by: &str) -> Result<()> {
+    let options = shadow_secret::tray::TrayOptions {
+        unlock_for: unlock_for.map(shadow_secret::gc::parse_max_age).transpose()?,
+        extend_by: shadow_secret::gc::parse_max_age(extend_by)?,
+    };
+
+    #[cfg(feature = "tray")]
+    {
+        shadow_secret::tray::run(shadow_secret::agent::socket_path()?, options)
+    }
+    #[cfg(not(feature = "tray"))]
+    {
+        let _ = options;
+        anyhow::bail!("This build has no tray support: rebuild with `--features tray`")
+    }
+}
 
-    // Extract age_key_path from config if available
-    let age_key_path = config.vault.age_key_path.as_deref();
+fn run_agent_inject(file: &str) -> Result<()> {
+    use shadow_secret::agent::{self, Request};
 
-    let vault = Vault::load(vault_path_str, age_key_path)
-        .with_context(|| format!("Failed to load vault from: {}", vault_path_str))?;
+    let content = std::fs::read_to_string(file).with_context(|| format!("Failed to read {}", file))?;
+    let request = Request::Inject {
+        path: file.to_string(),
+        content,
+        placeholders: vec!["$ALL".to_string()],
+    };
+    let response = agent::request(&agent::socket_path()?, &request)?.into_result()?;
+    print!("{}", response.value.unwrap_or_default());
+    Ok(())
+}
 
-    let secrets = vault.all();
-    println!("✓ Loaded {} secret(s)", secrets.len());
+fn run_init_project(
+    mut config: shadow_secret::init::InitConfig,
+    profile: Option<String>,
+    profile_key: Option<String>,
+    plan: bool,
+    explain: bool,
+) -> Result<()> {
+    use shadow_secret::init::init_project;
 
-    // Step 4: Inject secrets into each target
-    println!("\n🎯 Injecting secrets into targets...");
+    if explain && !plan {
+        let mut plan = shadow_secret::explain::init_project_plan(&config, &std::env::current_dir()?);
+        if let Some(source) = &profile {
+            plan.note(&format!(
+                "Organization profile fetched from {} (and its .minisig signature)",
+                source
+            ));
+        }
+        if !confirm_plan(&plan)? {
+            return Ok(());
+        }
+    }
 
-    for target in &config.targets {
-        println!("  → Target: {}", target.name);
-        println!("    File: {}", target.path);
+    // Fetch and verify organization defaults before touching the filesystem
+    config.profile = match profile {
+        Some(source) => {
+            say!("🏢 Fetching organization profile: {}", source);
+            let public_key = shadow_secret::profile::resolve_profile_key(profile_key.as_deref())?;
+            let profile = shadow_secret::profile::fetch_profile(&source, &public_key)?;
+            say!(
+                "✓ Signature verified (key ID {}) for profile '{}'\n",
+                public_key.key_id(),
+                profile.name
+            );
+            Some(profile)
+        }
+        None => None,
+    };
 
-        let placeholders: Vec<String> = target.placeholders.iter().cloned().collect();
+    if plan {
+        let project_dir = std::env::current_dir()?;
+        let files = shadow_secret::init::project_plan(&config, &project_dir)?;
+        print_init_plan(&files, &shadow_secret::init::project_overwrites(&project_dir), config.force);
+        return Ok(());
+    }
 
-        let backup = shadow_secret::injector::inject_secrets(
-            Path::new(&target.path),
-            secrets,
-            &placeholders,
-        ).with_context(|| format!("Failed to inject secrets into: {}", target.path))?;
+    init_project(config)
+}
 
-        cleaner::register_backup(&target.path, backup.content());
+fn run_init_global(plan: bool, force: bool, explain: bool) -> Result<()> {
+    use shadow_secret::init::{get_global_config_dir, init_global};
 
-        println!("    ✓ Injected {} placeholder(s)", placeholders.len());
+    let global_dir = get_global_config_dir()?;
+    if plan {
+        let files = shadow_secret::init::global_plan(&global_dir)?;
+        print_init_plan(&files, &shadow_secret::init::global_overwrites(&global_dir), force);
+        return Ok(());
     }
 
-    println!("\n✓ All secrets injected successfully!");
-    println!("\n🎉 Global secrets are now unlocked and injected!");
-    println!("👉 Press Enter to lock secrets and restore templates...");
-
-    // Wait for user input
-    let mut input = String::new();
-    std::io::stdin().read_line(&mut input)?;
+    if explain && !confirm_plan(&shadow_secret::explain::init_global_plan(&global_dir))? {
+        return Ok(());
+    }
 
-    println!("\n🔄 Restoring templates...");
+    init_global(force)
+}
 
-    // Restore all backups
-    cleaner::cleanup_and_restore();
+/// Print an init `--plan`; nothing is written.
+fn print_init_plan(files: &[shadow_secret::init::PlannedFile], overwrites: &[PathBuf], force: bool) {
+    say!("📋 Plan (nothing is written)\n");
+    print!("{}", shadow_secret::init::render_plan(files));
+    if !overwrites.is_empty() && !force {
+        say!("⚠️  {} existing file(s) would be overwritten: re-run with --force to allow it", overwrites.len());
+    }
+}
 
-    println!("✓ Templates restored!");
-    println!("👋 See you next time!");
+/// Ask a yes/no question (`yes` accepts the default without prompting).
+fn ask(prompt: &str, default: bool, yes: bool) -> Result<bool> {
+    if yes {
+        return Ok(default);
+    }
 
-    Ok(())
+    shadow_secret::confirm::confirm(prompt, default)
 }
 
-fn run_init_project(
-    master_key: Option<String>,
-    no_example: bool,
-    no_global: bool,
-) -> Result<()> {
-    use shadow_secret::init::init_project;
+fn run_setup(yes: bool, explain: bool) -> Result<()> {
+    use dialoguer::{theme::ColorfulTheme, Input, Select};
+    use shadow_secret::init;
+
+    say!("🧙 Shadow Secret Setup");
+    println!("This wizard checks your tools, sets up your age key, then initializes");
+    println!("the global configuration and the current project.\n");
+
+    // Step 1: Prerequisites
+    say!("📝 Step 1/4: Prerequisites");
+    println!("   Secrets are encrypted with SOPS using age keys; both tools must be installed.");
+    let mut missing = Vec::new();
+    for (binary, url) in [
+        ("sops", "https://github.com/getsops/sops/releases"),
+        ("age", "https://github.com/FiloSottile/age/releases"),
+    ] {
+        if check_binary(binary)? {
+            say!("   ✓ {} found", binary);
+        } else {
+            say!("   ✗ {} not found — install from: {}", binary, url);
+            missing.push(binary);
+        }
+    }
+    if !missing.is_empty() {
+        anyhow::bail!(
+            "Missing prerequisites: {}. Install them, then re-run 'shadow-secret setup'.",
+            missing.join(", ")
+        );
+    }
+    println!();
+
+    // Step 2: Age key
+    say!("📝 Step 2/4: Age key");
+    println!("   Your age key decrypts the vault. The private key never leaves this machine;");
+    println!("   teammates encrypt for your public key.");
+    let default_key_path = init::get_default_master_key_path();
+    let master_key_path = if default_key_path.exists() {
+        say!("   ✓ Using existing key: {}", default_key_path.display());
+        default_key_path
+    } else {
+        let choice = if yes {
+            0
+        } else {
+            Select::with_theme(&ColorfulTheme::default())
+                .with_prompt("No age key found. What do you want to do?")
+                .items(&[
+                    format!("Generate a new key at {}", default_key_path.display()),
+                    "Use an existing key file".to_string(),
+                ])
+                .default(0)
+                .interact()?
+        };
 
-    let config = shadow_secret::init::InitConfig {
-        master_key_path: if let Some(path) = master_key {
-            PathBuf::from(path)
+        if choice == 0 {
+            init::generate_age_keypair(&default_key_path)?;
+            say!("   ✓ Generated: {}", default_key_path.display());
+            say!("   💡 Back this file up (e.g. in your password manager).");
+            default_key_path
         } else {
-            shadow_secret::init::get_default_master_key_path()
-        },
-        create_example: !no_example,
-        prompt_global: !no_global,
+            let path: String = Input::with_theme(&ColorfulTheme::default())
+                .with_prompt("Path to your age key file")
+                .interact_text()?;
+            let path = PathBuf::from(path);
+            init::extract_age_keypair(&path)
+                .with_context(|| format!("Not a valid age key file: {}", path.display()))?;
+            say!("   ✓ Using key: {}", path.display());
+            path
+        }
     };
+    let keypair = init::extract_age_keypair(&master_key_path)?;
+    println!("   Public key (share with teammates): {}\n", keypair.public_key);
+
+    // Step 3: Global configuration
+    say!("📝 Step 3/4: Global configuration");
+    println!("   Global secrets (shared by all your projects) live in ~/.config/shadow-secret.");
+    let global_dir = init::get_global_config_dir()?;
+    if global_dir.join("global.yaml").exists() {
+        say!("   ✓ Already initialized: {}\n", global_dir.display());
+    } else if ask("Create the global configuration now?", true, yes)? {
+        if !explain || confirm_plan(&shadow_secret::explain::init_global_plan(&global_dir))? {
+            init::init_global(false)?;
+        }
+    } else {
+        say!("   ⊘ Skipped (run 'shadow-secret init-global' later)\n");
+    }
 
-    init_project(config)
+    // Step 4: Project
+    say!("📝 Step 4/4: Project");
+    let project_dir = std::env::current_dir()?;
+    println!("   A project gets its own vault (.enc.env), .sops.yaml and project.yaml.");
+    if project_dir.join("project.yaml").exists() {
+        say!("   ✓ Already initialized: {}", project_dir.display());
+    } else if ask(
+        &format!("Initialize a project in {}?", project_dir.display()),
+        true,
+        yes,
+    )? {
+        let config = init::InitConfig {
+            master_key_path,
+            create_example: ask("Add example secrets to the vault?", true, yes)?,
+            prompt_global: !yes,
+            profile: None,
+            force: false,
+        };
+        if !explain || confirm_plan(&shadow_secret::explain::init_project_plan(&config, &project_dir))? {
+            init::init_project(config)?;
+        }
+    } else {
+        say!("   ⊘ Skipped (run 'shadow-secret init-project' in your project later)");
+    }
+
+    say!("\n🎉 Setup complete! Run 'shadow-secret doctor' at any time to re-check your setup.");
+
+    Ok(())
 }
 
-fn run_init_global() -> Result<()> {
-    use shadow_secret::init::init_global;
+/// Options of the `push-cloud` command.
+struct PushCloudOptions {
+    project_id: Option<String>,
+    environment: Option<String>,
+    reason: Option<String>,
+    webhook: Option<String>,
+    sensitive: bool,
+    git_branch: Option<String>,
+    dry_run: bool,
+    require_signature: bool,
+}
 
-    init_global()
+/// Production pushes must be traceable to a ticket or justification.
+fn check_push_reason(environment: Option<&str>, reason: Option<&str>, dry_run: bool) -> Result<()> {
+    if !dry_run && reason.is_none() && shadow_secret::audit::requires_reason(environment) {
+        anyhow::bail!(
+            "Pushing to {} requires --reason (e.g. --reason \"INC-123\").\n\
+            Use --env preview/development for non-production pushes.",
+            environment.unwrap_or("all environments (including production)")
+        );
+    }
+    Ok(())
 }
 
-fn run_push_cloud(config_path: &str, project_id: Option<String>, dry_run: bool) -> Result<()> {
-    println!("🚀 Shadow Secret Push-Cloud");
-    println!("Loading configuration from: {}\n", config_path);
+fn run_push_cloud(config_path: &str, options: PushCloudOptions, explain: bool) -> Result<()> {
+    let PushCloudOptions {
+        project_id,
+        environment,
+        reason,
+        webhook,
+        sensitive,
+        git_branch,
+        dry_run,
+        require_signature,
+    } = options;
+
+    check_push_reason(environment.as_deref(), reason.as_deref(), dry_run)?;
+
+    say!("🚀 Shadow Secret Push-Cloud");
+    say!("Loading configuration from: {}\n", config_path);
+
+    // Step 0: Verify config signature before trusting its content
+    check_config_signature(config_path, require_signature)?;
 
     // Step 1: Load and validate configuration
-    let config = Config::from_file(config_path)
+    let mut config = Config::from_file(config_path)
         .with_context(|| format!("Failed to load config from: {}", config_path))?;
 
     config.validate()
         .with_context(|| "Configuration validation failed")?;
+    shadow_secret::vault::set_engine(&config.vault.engine)?;
+    shadow_secret::process::set_timeouts(config.timeouts.resolve()?);
 
-    println!("✓ Configuration loaded and validated");
+    // Command-line flags take precedence over cloud.vercel in the config
+    if sensitive {
+        config.cloud.vercel.sensitive = true;
+    }
+    if git_branch.is_some() {
+        config.cloud.vercel.git_branch = git_branch;
+    }
+    config.cloud.vercel.check_scope(environment.as_deref())?;
+
+    say!("✓ Configuration loaded and validated");
 
     // Step 2: Get config directory for path resolution
     let config_abs_path = PathBuf::from(config_path)
@@ -556,67 +3119,247 @@ fn run_push_cloud(config_path: &str, project_id: Option<String>, dry_run: bool)
         .parent()
         .context("Config file has no parent directory")?;
 
-    // Step 3: Load secrets from vault
-    let vault_path = config.vault_source_path(config_dir)?;
-    let vault_path_str = vault_path.to_str()
-        .ok_or_else(|| anyhow::anyhow!("Vault path contains invalid UTF-8"))?;
-
-    println!("📖 Loading secrets from: {}", vault_path_str);
-
-    // Extract age_key_path from config if available
-    let age_key_path = config.vault.age_key_path.as_deref();
+    if explain {
+        let plan = shadow_secret::explain::push_cloud_plan(
+            &config,
+            config_dir,
+            project_id.as_deref(),
+            environment.as_deref(),
+            dry_run,
+        )?;
+        if !confirm_plan(&plan)? {
+            return Ok(());
+        }
+    }
 
-    let vault = Vault::load(vault_path_str, age_key_path)
-        .with_context(|| format!("Failed to load vault from: {}", vault_path_str))?;
+    // Step 3: Load secrets from vault (extra sources are decrypted concurrently)
+    let vault = load_vault(&config, config_dir)?;
+    warn_shadowed_keys(&vault);
 
     let secrets: HashMap<String, String> = vault.all().clone();
-    println!("✓ Loaded {} secret(s)", secrets.len());
+    say!("✓ Loaded {} secret(s)", secrets.len());
 
     // Step 4: Detect or use provided project ID
     let project_id = if let Some(pid) = project_id {
-        println!("🔗 Using provided project ID: {}", pid);
+        say!("🔗 Using provided project ID: {}", pid);
         Some(pid)
     } else {
-        println!("🔍 Detecting Vercel project ID...");
+        say!("🔍 Detecting Vercel project ID...");
         match detect_project_id()? {
             Some(id) => {
-                println!("✓ Detected project ID: {}", id);
+                say!("✓ Detected project ID: {}", id);
                 Some(id)
             }
             None => {
-                println!("⚠️  No project ID found. Using current Vercel CLI context.");
+                say!("⚠️  No project ID found. Using current Vercel CLI context.");
                 None
             }
         }
     };
 
     // Step 5: Push secrets to Vercel
-    println!("\n🎯 Pushing secrets to Vercel...\n");
+    say!("\n🎯 Pushing secrets to Vercel...\n");
+
+    let mut audit_event = shadow_secret::audit::AuditEvent::new("push_cloud");
+    audit_event.project = project_id.clone();
+    audit_event.environment = environment.clone();
+    audit_event.reason = reason;
+    audit_event.provider = Some("vercel".to_string());
 
     // Push secrets using Vercel CLI
-    tokio::runtime::Runtime::new()
+    let result = tokio::runtime::Runtime::new()
         .unwrap()
         .block_on(async {
-            push_secrets_to_vercel(&secrets, project_id, dry_run).await
-        })?;
+            push_secrets_to_vercel(
+                &secrets,
+                project_id,
+                environment.as_deref(),
+                &config.cloud.vercel,
+                dry_run,
+            )
+            .await
+        });
+
+    // Step 6: Record the attempt in the audit log (dry runs are not recorded)
+    audit_event.outcome = match &result {
+        Ok(PushOutcome::DryRun) | Ok(PushOutcome::NothingToPush) => return result.map(|_| ()),
+        Ok(PushOutcome::Cancelled) => "cancelled".to_string(),
+        Ok(PushOutcome::Pushed(report)) if report.is_success() => {
+            audit_event.keys = report.succeeded();
+            "success".to_string()
+        }
+        Ok(PushOutcome::Pushed(report)) if report.failed().is_empty() => {
+            audit_event.keys = report.succeeded();
+            "cancelled".to_string()
+        }
+        Ok(PushOutcome::Pushed(_)) | Err(_) => {
+            let mut keys: Vec<String> = secrets
+                .keys()
+                .filter(|key| !key.starts_with("LOCAL_ONLY_"))
+                .map(|key| config.cloud.vercel.remote_name(key))
+                .collect();
+            keys.sort();
+            audit_event.keys = keys;
+            "failed".to_string()
+        }
+    };
+
+    if let Ok(PushOutcome::Pushed(report)) = &result {
+        print_push_report(report);
+    }
+
+    match shadow_secret::audit::record(&audit_event, webhook.as_deref()) {
+        Ok(()) => say!("📝 Recorded in audit log"),
+        Err(e) => say_err!("⚠️  Failed to record audit entry: {:#}", e),
+    }
+
+    match result? {
+        PushOutcome::Pushed(report) if !report.failed().is_empty() => {
+            anyhow::bail!("Failed to push {} variable(s)", report.failed().len())
+        }
+        PushOutcome::Pushed(report) if !report.cancelled.is_empty() => {
+            anyhow::bail!("Push cancelled: {} variable(s) not pushed", report.cancelled.len())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Unlock the project, run `script`, push to Vercel (unless `push` is
+/// `None`), then lock, and print a summary of the steps.
+fn run_deploy(
+    config_path: &str,
+    script: &str,
+    push: Option<PushCloudOptions>,
+    strict: bool,
+    require_signature: bool,
+    explain: bool,
+) -> Result<()> {
+    use shadow_secret::deploy::DeployReport;
+
+    say!("🚀 Shadow Secret Deploy");
+    say!("Loading configuration from: {}\n", config_path);
+
+    // Refuse a production push before building anything
+    if let Some(push) = &push {
+        check_push_reason(push.environment.as_deref(), push.reason.as_deref(), push.dry_run)?;
+    }
+
+    let (config, config_dir) = load_unlock_config(config_path, require_signature, strict)?;
+    let plan = shadow_secret::explain::unlock_plan("deploy", &config, &config_dir, false)?;
+    if explain && !confirm_plan(&plan)? {
+        return Ok(());
+    }
+
+    let mut report = DeployReport::default();
+    let session = report.run("unlock", || {
+        let mut vault = load_vault(&config, &config_dir)?
+            .with_normalized_keys(config.vault.normalize_keys);
+        apply_local_overlay(&mut vault, &config_dir, config.vault.age_key_path.as_deref())?;
+        apply_dynamic_secrets(&mut vault, &config)?;
+        warn_shadowed_keys(&vault);
+        check_busy_targets(&config.targets, false)?;
+
+        say!("\n🎯 Injecting secrets into targets...");
+        begin_recovery_journal(&config, &config_dir);
+        let session = unlock_targets(&config, &vault)?;
+        print_unlock_report(session.report());
+        write_injection_report(&session);
+        Ok(session)
+    });
+
+    let built = if session.is_some() {
+        report.run("build", || run_build(script)).is_some()
+    } else {
+        report.skip("build");
+        false
+    };
+
+    match push {
+        Some(push) if built => {
+            report.run("push-cloud", || run_push_cloud(config_path, push, false));
+        }
+        _ => report.skip("push-cloud"),
+    }
+
+    // Always lock: a failed unlock may have injected some targets already
+    say!("\n🔄 Restoring templates...");
+    report.run("lock", || {
+        match session {
+            Some(session) => lock_session(session),
+            None => shadow_secret::cleaner::cleanup_and_restore(),
+        }
+        Ok(())
+    });
+
+    say!("\n📊 Deploy summary:");
+    print!("{}", shadow_secret::accessible::render(&report.render()));
+    if !report.is_success() {
+        anyhow::bail!("Deploy failed (templates restored)");
+    }
+
+    say!("\n✅ Deploy complete!");
+    Ok(())
+}
 
+/// Run the deploy's build command through the shell, forwarding signals to
+/// it so that Ctrl+C still lets the deploy lock.
+fn run_build(script: &str) -> Result<()> {
+    let (program, args) = shadow_secret::deploy::shell_command(script);
+    say!("\n🔨 Running: {}", script);
+
+    let status = tokio::runtime::Runtime::new()?
+        .block_on(shadow_secret::exec::run(&program, &args, &HashMap::new()))?;
+    if !status.success() {
+        anyhow::bail!("Build command exited with code {}", shadow_secret::exec::exit_code(status));
+    }
     Ok(())
 }
 
+/// Print the results of a push that ran.
+fn print_push_report(report: &PushReport) {
+    if let Some(path) = &report.transcript {
+        say!("\n📝 Transcript: {}", path.display());
+    }
+
+    let failed = report.failed();
+    say!("\n📊 Results:");
+    say!("   ✓ Succeeded: {}", report.keys.len() - failed.len());
+    say!("   ✗ Failed: {}", failed.len());
+    if !report.cancelled.is_empty() {
+        say!("   ⊘ Not pushed (cancelled): {}", report.cancelled.len());
+    }
+
+    if report.is_success() {
+        say!("\n✅ All secrets pushed successfully!");
+    }
+    if !failed.is_empty() {
+        say!("\n❌ Failed variables:");
+        for (key, error) in &failed {
+            say!("   - {}: {}", key, error);
+        }
+    }
+    if !report.cancelled.is_empty() {
+        say!("\n⊘ Not pushed:");
+        for key in &report.cancelled {
+            say!("   - {}", key);
+        }
+    }
+}
+
 fn get_current_version() -> Result<String> {
     // Version from Cargo.toml
     Ok(env!("CARGO_PKG_VERSION").to_string())
 }
 
 fn get_latest_version() -> Result<String> {
-    println!("🔍 Checking for updates on NPM...\n");
+    say!("🔍 Checking for updates on NPM...\n");
 
     // On Windows, npm is npm.cmd; on Unix, it's npm
     // Use which to find the actual npm executable
     let npm_exe = which::which("npm")
         .context("Failed to find 'npm'. Is NPM installed and in PATH?")?;
 
-    let output = Command::new(&npm_exe)
+    let output = process::command(&npm_exe)?
         .args(["view", "@oalacea/shadow-secret", "version"])
         .output()
         .context("Failed to execute 'npm view'. Is NPM installed?")?;
@@ -633,38 +3376,42 @@ fn get_latest_version() -> Result<String> {
     Ok(version)
 }
 
-fn run_update(check_only: bool) -> Result<()> {
-    println!("🔄 Shadow Secret Update");
+fn run_update(check_only: bool, explain: bool) -> Result<()> {
+    say!("🔄 Shadow Secret Update");
     println!();
 
+    if explain && !confirm_plan(&shadow_secret::explain::update_plan(check_only))? {
+        return Ok(());
+    }
+
     let current = get_current_version()?;
     let latest = get_latest_version()?;
 
-    println!("📦 Current version: {}", current);
-    println!("📦 Latest version:  {}", latest);
+    say!("📦 Current version: {}", current);
+    say!("📦 Latest version:  {}", latest);
     println!();
 
     if current == latest {
-        println!("✅ You're already on the latest version!");
+        say!("✅ You're already on the latest version!");
         return Ok(());
     }
 
-    println!("🆕 A new version is available!");
+    say!("🆕 A new version is available!");
     println!();
 
     if check_only {
-        println!("ℹ️  Run 'shadow-secret update' to install the latest version.");
+        say!("ℹ️  Run 'shadow-secret update' to install the latest version.");
         return Ok(());
     }
 
-    println!("📥 Installing @oalacea/shadow-secret@{}...\n", latest);
+    say!("📥 Installing @oalacea/shadow-secret@{}...\n", latest);
 
     // On Windows, npm is npm.cmd; on Unix, it's npm
     // Use which to find the actual npm executable
     let npm_exe = which::which("npm")
         .context("Failed to find 'npm'. Is NPM installed and in PATH?")?;
 
-    let output = Command::new(&npm_exe)
+    let output = process::command(&npm_exe)?
         .args(["install", "-g", "@oalacea/shadow-secret@latest"])
         .status()
         .context("Failed to execute 'npm install'. Is NPM installed?")?;
@@ -674,24 +3421,121 @@ fn run_update(check_only: bool) -> Result<()> {
     }
 
     println!();
-    println!("✅ Successfully updated to version {}!", latest);
+    say!("✅ Successfully updated to version {}!", latest);
     println!();
-    println!("🎉 Shadow Secret has been updated!");
-    println!("💡 Run 'shadow-secret --version' to verify the update.");
+    say!("🎉 Shadow Secret has been updated!");
+    say!("💡 Run 'shadow-secret --version' to verify the update.");
+
+    Ok(())
+}
+
+fn run_seccomp_profile(output: Option<String>) -> Result<()> {
+    let profile = serde_json::to_string_pretty(&process::seccomp_profile())?;
+
+    match output {
+        Some(path) => {
+            std::fs::write(&path, format!("{}\n", profile))
+                .with_context(|| format!("Failed to write seccomp profile: {}", path))?;
+            say!("✓ Seccomp profile written to: {}", path);
+        }
+        None => println!("{}", profile),
+    }
 
     Ok(())
 }
 
+/// The command line with a user-defined alias expanded (see
+/// [`shadow_secret::alias`]). The global config is only read when the
+/// command is not a built-in one.
+fn expand_aliases(args: Vec<String>) -> Result<Vec<String>> {
+    let command = Cli::command();
+    let builtins: Vec<&str> = command
+        .get_subcommands()
+        .flat_map(|subcommand| std::iter::once(subcommand.get_name()).chain(subcommand.get_all_aliases()))
+        .chain(["help"])
+        .collect();
+    let is_builtin = |name: &str| builtins.contains(&name);
+
+    let Some(index) = shadow_secret::alias::command_index(&args) else {
+        return Ok(args);
+    };
+    if is_builtin(&args[index]) {
+        return Ok(args);
+    }
+    if let Some(dir) = shadow_secret::alias::global_flag_value(&args, "--config-dir") {
+        shadow_secret::paths::set_config_dir(PathBuf::from(dir));
+    }
+    let global_config = shadow_secret::paths::global_config_path()?;
+    if !global_config.exists() {
+        return Ok(args);
+    }
+    let config = Config::from_file(&global_config)
+        .with_context(|| format!("Failed to load aliases from: {}", global_config.display()))?;
+    shadow_secret::alias::validate(&config.aliases)?;
+    shadow_secret::alias::expand(args, &config.aliases, is_builtin)
+}
+
 fn main() -> Result<()> {
-    let cli = Cli::parse();
+    let cli = match std::env::args_os().map(|arg| arg.into_string().ok()).collect() {
+        Some(args) => match expand_aliases(args) {
+            Ok(args) => Cli::parse_from(args),
+            Err(e) => {
+                report_error(&e);
+                std::process::exit(1);
+            }
+        },
+        // Non-UTF-8 arguments are never an alias
+        None => Cli::parse(),
+    };
+    shadow_secret::accessible::set_enabled(cli.accessible);
+    if let Some(dir) = cli.config_dir.clone() {
+        shadow_secret::paths::set_config_dir(dir);
+    }
+
+    // Test/CI mode: record or replay sops/age/vercel invocations
+    if let Err(e) = shadow_secret::testing::init_from_env() {
+        report_error(&e);
+        std::process::exit(1);
+    }
+
+    // Headless mode: answer confirmations from $SHADOW_SECRET_CONFIRM
+    if let Err(e) = shadow_secret::confirm::init_from_env() {
+        report_error(&e);
+        std::process::exit(1);
+    }
+
+    if cli.restricted {
+        if let Err(e) = process::enable_restricted_mode() {
+            eprintln!("\nError: {:#}", e);
+            say_err!("\n⚠️  Could not enter restricted mode.");
+            std::process::exit(1);
+        }
+        say_err!("🔒 Restricted mode: only {} may be executed", process::RESTRICTED_PROGRAMS.join(", "));
+    }
+    shadow_secret::binaries::set_strict(cli.strict_binaries || cli.restricted);
+
+    if let Some(name) = explain_unsupported(&cli.command).filter(|_| cli.explain) {
+        report_error(&anyhow::anyhow!("--explain is not supported by {}", name));
+        std::process::exit(1);
+    }
+
+    // Daily lightweight cleanup of stale state (`gc` reports its own)
+    if !matches!(cli.command, Commands::Gc { .. }) {
+        let removed = shadow_secret::gc::auto();
+        if !removed.is_empty() {
+            say_err!(
+                "🧹 Removed {} stale file(s) from the state directory (see 'shadow-secret gc')",
+                removed.len()
+            );
+        }
+    }
 
     match cli.command {
         Commands::Doctor => {
             // Smart doctor: auto-detect if we should check global config
             let project_config_exists = Path::new("project.yaml").exists();
 
-            let global_config_path = dirs::home_dir()
-                .map(|home| home.join(".config/shadow-secret/global.yaml"));
+            let global_config_path = shadow_secret::paths::global_config_path().ok();
 
             let global_config_exists = if let Some(ref path) = global_config_path {
                 path.exists()
@@ -701,37 +3545,93 @@ fn main() -> Result<()> {
 
             // If only global config exists, provide helpful hint
             if !project_config_exists && global_config_exists {
-                println!("🔍 Shadow Secret Doctor");
-                println!("Checking prerequisites...\n");
-                println!("ℹ️  No project config found (project.yaml)");
-                println!("ℹ️  Global config detected: ~/.config/shadow-secret/global.yaml");
-                println!("\n💡 Use 'shadow-secret unlock-global' for global secrets");
-                println!("💡 Or create a project config with 'shadow-secret init-project'");
+                say!("🔍 Shadow Secret Doctor");
+                say!("Checking prerequisites...\n");
+                say!("ℹ️  No project config found (project.yaml)");
+                say!("ℹ️  Global config detected: ~/.config/shadow-secret/global.yaml");
+                say!("\n💡 Use 'shadow-secret unlock-global' for global secrets");
+                say!("💡 Or create a project config with 'shadow-secret init-project'");
 
                 // Run basic checks (sops, age, SOPS_AGE_KEY_FILE)
                 run_basic_checks()?;
             } else {
                 // Normal doctor for project mode
                 if let Err(e) = run_doctor() {
-                    eprintln!("\nError: {}", e);
+                    say_err!("\nError: {}", e);
                     std::process::exit(1);
                 }
             }
         }
-        Commands::Unlock { config } => {
-            if let Err(e) = run_unlock(&config) {
-                eprintln!("\nError: {}", e);
-                eprintln!("\n⚠️  Project secrets may not be properly injected.");
-                eprintln!("💡 Run 'shadow-secret doctor' to check your configuration.");
-                eprintln!("💡 Use 'shadow-secret unlock-global' for global secrets.");
+        Commands::Unlock {
+            config,
+            require_signature,
+            preview_dir,
+            fake,
+            strict,
+            dry_run,
+            timeout,
+            wait,
+            snapshot,
+        } => {
+            let mode = match (preview_dir, dry_run) {
+                (Some(dir), _) => UnlockMode::Preview(dir),
+                (None, true) => UnlockMode::DryRun,
+                (None, false) => UnlockMode::Inject { timeout, wait, snapshot },
+            };
+            if let Err(e) = run_unlock(&config, require_signature, mode, fake, strict, cli.explain) {
+                report_error(&e);
+                say_err!("\n⚠️  Project secrets may not be properly injected.");
+                say_err!("💡 Run 'shadow-secret doctor' to check your configuration.");
+                say_err!("💡 Use 'shadow-secret unlock-global' for global secrets.");
+                std::process::exit(1);
+            }
+        }
+        Commands::Get { key, config, shape, copy } => {
+            if let Err(e) = run_get(&config, &key, shape, copy) {
+                eprintln!("Error: {:#}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Set { key, config } => {
+            if let Err(e) = run_set(&config, &key, cli.explain) {
+                report_error(&e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Exec {
+            config,
+            only,
+            command,
+        } => match run_exec(&config, &only, &command, cli.explain) {
+            Ok(code) => std::process::exit(code),
+            Err(e) => {
+                report_error(&e);
+                std::process::exit(1);
+            }
+        },
+        Commands::List { config, provenance, columns, sort, json } => {
+            if let Err(e) = run_list(&config, provenance, columns.as_deref(), sort.as_deref(), json) {
+                report_error(&e);
                 std::process::exit(1);
             }
         }
-        Commands::UnlockGlobal => {
-            if let Err(e) = run_unlock_global() {
+        Commands::Scrub { config, yes } => {
+            if let Err(e) = run_scrub(&config, yes) {
+                report_error(&e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Fsck { config } => {
+            if let Err(e) = run_fsck(&config) {
                 eprintln!("\nError: {}", e);
-                eprintln!("\n⚠️  Global secrets may not be properly injected.");
-                eprintln!("💡 Run 'shadow-secret doctor' to check your configuration.");
+                std::process::exit(1);
+            }
+        }
+        Commands::UnlockGlobal { timeout, wait } => {
+            if let Err(e) = run_unlock_global(timeout, wait, cli.explain) {
+                report_error(&e);
+                say_err!("\n⚠️  Global secrets may not be properly injected.");
+                say_err!("💡 Run 'shadow-secret doctor' to check your configuration.");
                 std::process::exit(1);
             }
         }
@@ -739,40 +3639,268 @@ fn main() -> Result<()> {
             master_key,
             no_example,
             no_global,
+            profile,
+            profile_key,
+            plan,
+            force,
         } => {
-            if let Err(e) = run_init_project(master_key, no_example, no_global) {
+            let config = shadow_secret::init::InitConfig {
+                master_key_path: if let Some(path) = master_key {
+                    PathBuf::from(path)
+                } else {
+                    shadow_secret::init::get_default_master_key_path()
+                },
+                create_example: !no_example,
+                prompt_global: !no_global,
+                profile: None,
+                force,
+            };
+            if let Err(e) = run_init_project(config, profile, profile_key, plan, cli.explain) {
+                eprintln!("\nError: {}", e);
+                say_err!("\n⚠️  Project initialization failed.");
+                say_err!("💡 Run 'shadow-secret doctor' to check your configuration.");
+                std::process::exit(1);
+            }
+        }
+        Commands::Canary { action } => {
+            let result = match action {
+                CanaryAction::Add { config } => run_canary_add(&config),
+                CanaryAction::Check {
+                    config,
+                    webhook,
+                    github_api,
+                } => run_canary_check(&config, webhook, &github_api),
+            };
+            if let Err(e) = result {
+                report_error(&e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Journal { action } => {
+            let result = match action {
+                JournalAction::List => run_journal_list(),
+                JournalAction::Restore { id, age_key, force } => {
+                    run_journal_restore(&id, age_key.as_deref(), force)
+                }
+            };
+            if let Err(e) = result {
+                report_error(&e);
+                std::process::exit(1);
+            }
+        }
+        Commands::RestoreSnapshot { id, list, age_key } => {
+            if let Err(e) = run_restore_snapshot(id.as_deref(), list, age_key.as_deref()) {
+                report_error(&e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Gc { older_than, dry_run } => {
+            if let Err(e) = run_gc(&older_than, dry_run) {
+                report_error(&e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Rekey { recipient, remove } => {
+            if let Err(e) = run_rekey(&recipient, remove.as_deref()) {
+                report_error(&e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Report { since, format } => {
+            if let Err(e) = run_usage_report(since.as_deref(), &format) {
+                report_error(&e);
+                std::process::exit(1);
+            }
+        }
+        Commands::ComplianceReport { config, output } => {
+            if let Err(e) = run_compliance_report(&config, output.as_deref()) {
+                report_error(&e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Status { last, config } => {
+            if let Err(e) = run_status(last, &config) {
+                report_error(&e);
+                std::process::exit(1);
+            }
+        }
+        Commands::TrustBinaries { programs } => {
+            if let Err(e) = run_trust_binaries(&programs) {
+                report_error(&e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Remote { action } => {
+            let result = match action {
+                RemoteAction::Unlock {
+                    host,
+                    project,
+                    config,
+                    require_signature,
+                    remote_bin,
+                } => run_remote_unlock(&config, &host, &project, require_signature, &remote_bin),
+                RemoteAction::Receive { project } => run_remote_receive(&project),
+            };
+            if let Err(e) = result {
+                report_error(&e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Agent { action } => {
+            let result = match action {
+                AgentAction::Start { config } => run_agent_start(&config),
+                AgentAction::Stop => run_agent_stop(),
+                AgentAction::Status => run_agent_status(),
+                AgentAction::Inject { file } => run_agent_inject(&file),
+            };
+            if let Err(e) = result {
+                report_error(&e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Tray { unlock_for, extend_by } => {
+            if let Err(e) = run_tray(unlock_for.as_deref(), &extend_by) {
+                report_error(&e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Backup { action } => {
+            let result = match action {
+                BackupAction::Push => run_backup_push(),
+                BackupAction::List => run_backup_list(),
+                BackupAction::Restore { snapshot, project, force } => {
+                    run_backup_restore(snapshot.as_deref(), project.as_deref(), force)
+                }
+            };
+            if let Err(e) = result {
+                report_error(&e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Provision { from, send, port, force } => {
+            let result = match from {
+                Some(from) => run_provision_receive(&from, force),
+                None if send => run_provision_send(port),
+                None => unreachable!("clap requires --from or --send"),
+            };
+            if let Err(e) = result {
+                report_error(&e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Vault { action } => {
+            let result = match action {
+                VaultAction::RenameKey {
+                    old,
+                    new,
+                    config,
+                    dry_run,
+                    cloud,
+                    project,
+                    env,
+                } => run_vault_rename_key(&config, &old, &new, dry_run, cloud, project, env),
+            };
+            if let Err(e) = result {
+                report_error(&e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Target { action } => {
+            let result = match action {
+                TargetAction::Add {
+                    name,
+                    path,
+                    keys,
+                    config,
+                    yes,
+                } => run_target_add(&config, name, path, &keys, yes),
+            };
+            if let Err(e) = result {
+                report_error(&e);
+                std::process::exit(1);
+            }
+        }
+        Commands::InitGlobal { plan, force } => {
+            if let Err(e) = run_init_global(plan, force, cli.explain) {
                 eprintln!("\nError: {}", e);
-                eprintln!("\n⚠️  Project initialization failed.");
-                eprintln!("💡 Run 'shadow-secret doctor' to check your configuration.");
+                say_err!("\n⚠️  Global initialization failed.");
+                say_err!("💡 Run 'shadow-secret doctor' to check your configuration.");
                 std::process::exit(1);
             }
         }
-        Commands::InitGlobal => {
-            if let Err(e) = run_init_global() {
+        Commands::Setup { yes } => {
+            if let Err(e) = run_setup(yes, cli.explain) {
                 eprintln!("\nError: {}", e);
-                eprintln!("\n⚠️  Global initialization failed.");
-                eprintln!("💡 Run 'shadow-secret doctor' to check your configuration.");
+                say_err!("\n⚠️  Setup did not complete. You can re-run 'shadow-secret setup' at any time.");
                 std::process::exit(1);
             }
         }
         Commands::PushCloud {
             config,
             project,
+            env,
+            reason,
+            webhook,
+            sensitive,
+            git_branch,
             dry_run,
+            require_signature,
+        } => {
+            let options = PushCloudOptions {
+                project_id: project,
+                environment: env,
+                reason,
+                webhook,
+                sensitive,
+                git_branch,
+                dry_run,
+                require_signature,
+            };
+            if let Err(e) = run_push_cloud(&config, options, cli.explain) {
+                report_error(&e);
+                say_err!("\n⚠️  Failed to push secrets to Vercel.");
+                say_err!("💡 Run 'shadow-secret doctor' to check your configuration.");
+                say_err!("💡 Make sure Vercel CLI is installed: npm install -g vercel");
+                std::process::exit(1);
+            }
+        }
+        Commands::Deploy {
+            config,
+            run,
+            project,
+            env,
+            reason,
+            webhook,
+            no_push,
+            strict,
+            require_signature,
         } => {
-            if let Err(e) = run_push_cloud(&config, project, dry_run) {
+            let push = (!no_push).then_some(PushCloudOptions {
+                project_id: project,
+                environment: env,
+                reason,
+                webhook,
+                sensitive: false,
+                git_branch: None,
+                dry_run: false,
+                require_signature,
+            });
+            if let Err(e) = run_deploy(&config, &run, push, strict, require_signature, cli.explain) {
+                report_error(&e);
+                std::process::exit(1);
+            }
+        }
+        Commands::SeccompProfile { output } => {
+            if let Err(e) = run_seccomp_profile(output) {
                 eprintln!("\nError: {}", e);
-                eprintln!("\n⚠️  Failed to push secrets to Vercel.");
-                eprintln!("💡 Run 'shadow-secret doctor' to check your configuration.");
-                eprintln!("💡 Make sure Vercel CLI is installed: npm install -g vercel");
                 std::process::exit(1);
             }
         }
         Commands::Update { check_only } => {
-            if let Err(e) = run_update(check_only) {
+            if let Err(e) = run_update(check_only, cli.explain) {
                 eprintln!("\nError: {}", e);
-                eprintln!("\n⚠️  Update failed.");
-                eprintln!("💡 You can manually update with: npm install -g @oalacea/shadow-secret@latest");
+                say_err!("\n⚠️  Update failed.");
+                say_err!("💡 You can manually update with: npm install -g @oalacea/shadow-secret@latest");
                 std::process::exit(1);
             }
         }
@@ -0,0 +1,249 @@
+//! Key naming rules (`naming:` in the config).
+//!
+//! A shared vault stays consistent when every team names keys the same
+//! way:
+//!
+//! ```yaml
+//! naming:
+//!   case: screaming_snake       # API_KEY, not apiKey or api-key
+//!   prefixes: [APP_, DB_]       # every key starts with one of these
+//!   pattern: '^[A-Z]+_[A-Z0-9_]+$'
+//! ```
+//!
+//! `fsck` reports the keys of the vault breaking the rules, and
+//! `vault rename-key` refuses to give a key such a name. When a compliant
+//! name can be derived (case conversion, the single approved prefix), it is
+//! suggested with the violation.
+
+use crate::config::NamingConfig;
+use anyhow::{Context, Result};
+use regex::Regex;
+
+/// Values of `naming.case`.
+pub const CASES: &[&str] = &["screaming_snake", "snake"];
+
+/// Letter case of key names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Case {
+    /// `API_KEY`
+    ScreamingSnake,
+    /// `api_key`
+    Snake,
+}
+
+impl Case {
+    /// Whether `key` is written in this case.
+    pub fn matches(self, key: &str) -> bool {
+        let is_letter = |c: char| match self {
+            Case::ScreamingSnake => c.is_ascii_uppercase(),
+            Case::Snake => c.is_ascii_lowercase(),
+        };
+        let mut chars = key.chars();
+        chars.next().is_some_and(|c| is_letter(c) || c == '_')
+            && chars.all(|c| is_letter(c) || c.is_ascii_digit() || c == '_')
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Case::ScreamingSnake => "SCREAMING_SNAKE_CASE",
+            Case::Snake => "snake_case",
+        }
+    }
+}
+
+/// A key breaking the rules.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    pub key: String,
+    /// Each rule broken, e.g. "not SCREAMING_SNAKE_CASE"
+    pub problems: Vec<String>,
+    /// A name following every rule, if one can be derived
+    pub suggestion: Option<String>,
+}
+
+impl Violation {
+    /// One-line description, with the suggested name.
+    pub fn message(&self) -> String {
+        let mut message = format!("{} is {}", self.key, self.problems.join(", "));
+        if let Some(suggestion) = &self.suggestion {
+            message.push_str(&format!(" (suggested: {})", suggestion));
+        }
+        message
+    }
+}
+
+/// The naming rules of a config, ready to check keys.
+#[derive(Debug, Clone, Default)]
+pub struct Rules {
+    pub case: Option<Case>,
+    pub prefixes: Vec<String>,
+    pub pattern: Option<Regex>,
+}
+
+impl Rules {
+    /// Compile `config`.
+    ///
+    /// # Errors
+    ///
+    /// Fails on an unknown case, an empty prefix or an invalid pattern.
+    pub fn new(config: &NamingConfig) -> Result<Self> {
+        let case = match config.case.as_deref() {
+            None => None,
+            Some("screaming_snake") => Some(Case::ScreamingSnake),
+            Some("snake") => Some(Case::Snake),
+            Some(other) => anyhow::bail!("Unknown naming.case '{}' (expected one of: {})", other, CASES.join(", ")),
+        };
+        if config.prefixes.iter().any(|prefix| prefix.is_empty()) {
+            anyhow::bail!("naming.prefixes cannot contain an empty prefix");
+        }
+        let pattern = config
+            .pattern
+            .as_deref()
+            .map(|pattern| Regex::new(pattern).with_context(|| format!("Invalid naming.pattern '{}'", pattern)))
+            .transpose()?;
+
+        Ok(Self {
+            case,
+            prefixes: config.prefixes.clone(),
+            pattern,
+        })
+    }
+
+    /// Whether no rule is set.
+    pub fn is_empty(&self) -> bool {
+        self.case.is_none() && self.prefixes.is_empty() && self.pattern.is_none()
+    }
+
+    /// The rules `key` breaks, if any.
+    pub fn check(&self, key: &str) -> Option<Violation> {
+        let problems = self.problems(key);
+        if problems.is_empty() {
+            return None;
+        }
+        Some(Violation {
+            key: key.to_string(),
+            problems,
+            suggestion: self.suggest(key),
+        })
+    }
+
+    fn problems(&self, key: &str) -> Vec<String> {
+        let mut problems = Vec::new();
+        if let Some(case) = self.case.filter(|case| !case.matches(key)) {
+            problems.push(format!("not {}", case.name()));
+        }
+        if !self.prefixes.is_empty() && !self.prefixes.iter().any(|prefix| key.starts_with(prefix.as_str())) {
+            problems.push(format!("missing an approved prefix ({})", self.prefixes.join(", ")));
+        }
+        if let Some(pattern) = self.pattern.as_ref().filter(|pattern| !pattern.is_match(key)) {
+            problems.push(format!("not matching '{}'", pattern.as_str()));
+        }
+        problems
+    }
+
+    /// A name close to `key` that follows every rule: converted to the
+    /// case, with the prefix added when only one is approved.
+    pub fn suggest(&self, key: &str) -> Option<String> {
+        let words = words(key);
+        if words.is_empty() {
+            return None;
+        }
+        let mut name = match self.case {
+            Some(Case::ScreamingSnake) => words.join("_").to_ascii_uppercase(),
+            Some(Case::Snake) => words.join("_").to_ascii_lowercase(),
+            None => key.to_string(),
+        };
+        if !self.prefixes.iter().any(|prefix| name.starts_with(prefix.as_str())) {
+            match self.prefixes.as_slice() {
+                [] => {}
+                [prefix] => name.insert_str(0, prefix),
+                _ => return None,
+            }
+        }
+        (name != key && self.problems(&name).is_empty()).then_some(name)
+    }
+}
+
+/// Words of `key`: split at separators and at case changes (`apiKey`,
+/// `HTTPServer`).
+fn words(key: &str) -> Vec<String> {
+    let chars: Vec<char> = key.chars().collect();
+    let mut words = Vec::new();
+    let mut word = String::new();
+
+    for (index, &c) in chars.iter().enumerate() {
+        if !c.is_ascii_alphanumeric() {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            continue;
+        }
+        let previous = index.checked_sub(1).map(|previous| chars[previous]);
+        let next = chars.get(index + 1);
+        let boundary = c.is_ascii_uppercase()
+            && previous.is_some_and(|previous| {
+                previous.is_ascii_lowercase()
+                    || (previous.is_ascii_uppercase() && next.is_some_and(|next| next.is_ascii_lowercase()))
+            });
+        if boundary && !word.is_empty() {
+            words.push(std::mem::take(&mut word));
+        }
+        word.push(c);
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(case: Option<&str>, prefixes: &[&str], pattern: Option<&str>) -> Rules {
+        Rules::new(&NamingConfig {
+            case: case.map(str::to_string),
+            prefixes: prefixes.iter().map(|prefix| prefix.to_string()).collect(),
+            pattern: pattern.map(str::to_string),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_check_and_suggest() {
+        let screaming = rules(Some("screaming_snake"), &[], None);
+        assert_eq!(screaming.check("API_KEY"), None);
+        assert_eq!(screaming.check("_INTERNAL_2"), None);
+        for (key, suggestion) in [
+            ("apiKey", "API_KEY"),
+            ("database-url", "DATABASE_URL"),
+            ("HTTPServer.port", "HTTP_SERVER_PORT"),
+            ("stripe_secret_key", "STRIPE_SECRET_KEY"),
+        ] {
+            let violation = screaming.check(key).unwrap();
+            assert_eq!(violation.suggestion.as_deref(), Some(suggestion), "{}", key);
+        }
+
+        let prefixed = rules(Some("screaming_snake"), &["APP_"], None);
+        let violation = prefixed.check("dbUrl").unwrap();
+        assert_eq!(violation.problems.len(), 2);
+        assert_eq!(violation.message(), "dbUrl is not SCREAMING_SNAKE_CASE, missing an approved prefix (APP_) (suggested: APP_DB_URL)");
+
+        // Several prefixes: no way to pick one
+        let prefixes = rules(None, &["APP_", "DB_"], None);
+        assert_eq!(prefixes.check("DB_URL"), None);
+        assert_eq!(prefixes.check("URL").unwrap().suggestion, None);
+
+        let pattern = rules(Some("snake"), &[], Some("^[a-z]+_[a-z]+$"));
+        assert_eq!(pattern.check("ApiKey").unwrap().suggestion.as_deref(), Some("api_key"));
+        assert_eq!(pattern.check("ApiKeyId").unwrap().suggestion, None);
+        assert!(Rules::default().is_empty());
+    }
+
+    #[test]
+    fn test_invalid_rules() {
+        assert!(Rules::new(&NamingConfig { case: Some("camel".to_string()), ..Default::default() }).is_err());
+        assert!(Rules::new(&NamingConfig { prefixes: vec![String::new()], ..Default::default() }).is_err());
+        assert!(Rules::new(&NamingConfig { pattern: Some("(".to_string()), ..Default::default() }).is_err());
+    }
+}
@@ -0,0 +1,308 @@
+//! 1Password backend (`vault.engine: 1password`).
+//!
+//! Items are read with the 1Password CLI (`op item get --format json`), so
+//! authentication is whatever `op` already uses: the desktop app
+//! integration, `op signin`, or a service account token in
+//! `$OP_SERVICE_ACCOUNT_TOKEN`. Two ways to map fields to vault keys, which
+//! can be combined:
+//!
+//! - `item`: every labelled field of the item becomes a key
+//! - `keys`: one secret reference per key (`op://<vault>/<item>/<field>`,
+//!   or `op://<vault>/<item>/<section>/<field>`); later entries override
+//!   `item` fields
+//!
+//! Each item is fetched once, however many keys reference it. Output is
+//! captured in memory only.
+
+use crate::config::OnePasswordConfig;
+use crate::process;
+use anyhow::{Context, Result};
+use std::collections::{BTreeMap, HashMap};
+
+/// A field of an item, as printed by `op item get --format json`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ItemField {
+    pub id: String,
+    pub label: String,
+    /// Section label (or ID when unlabelled)
+    pub section: Option<String>,
+    pub value: Option<String>,
+}
+
+/// A parsed `op://` secret reference.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecretReference {
+    pub vault: String,
+    pub item: String,
+    pub section: Option<String>,
+    pub field: String,
+}
+
+impl SecretReference {
+    /// Parse `op://<vault>/<item>[/<section>]/<field>`.
+    pub fn parse(reference: &str) -> Result<Self> {
+        let path = reference
+            .strip_prefix("op://")
+            .with_context(|| format!("1Password reference must start with op://, got '{}'", reference))?;
+        if path.contains('?') {
+            anyhow::bail!("1Password reference query parameters are not supported: '{}'", reference);
+        }
+
+        let parts: Vec<&str> = path.split('/').collect();
+        if parts.iter().any(|part| part.is_empty()) {
+            anyhow::bail!("1Password reference has an empty segment: '{}'", reference);
+        }
+        match parts.as_slice() {
+            [vault, item, field] => Ok(Self {
+                vault: vault.to_string(),
+                item: item.to_string(),
+                section: None,
+                field: field.to_string(),
+            }),
+            [vault, item, section, field] => Ok(Self {
+                vault: vault.to_string(),
+                item: item.to_string(),
+                section: Some(section.to_string()),
+                field: field.to_string(),
+            }),
+            _ => anyhow::bail!(
+                "1Password reference must be op://<vault>/<item>[/<section>]/<field>, got '{}'",
+                reference
+            ),
+        }
+    }
+}
+
+/// Name of an item in provenance output (`1password:<vault>/<item>`).
+pub fn source_name(vault: Option<&str>, item: &str) -> String {
+    match vault {
+        Some(vault) => format!("{}:{}/{}", crate::vault::ENGINE_ONEPASSWORD, vault, item),
+        None => format!("{}:{}", crate::vault::ENGINE_ONEPASSWORD, item),
+    }
+}
+
+/// Arguments of the `op` invocation reading one item.
+pub fn item_get_args(config: &OnePasswordConfig, vault: Option<&str>, item: &str) -> Vec<String> {
+    let mut args = vec![
+        "item".to_string(),
+        "get".to_string(),
+        item.to_string(),
+        "--format".to_string(),
+        "json".to_string(),
+    ];
+    if let Some(vault) = vault {
+        args.extend(["--vault".to_string(), vault.to_string()]);
+    }
+    if let Some(account) = &config.account {
+        args.extend(["--account".to_string(), account.clone()]);
+    }
+    args
+}
+
+/// Items to fetch, in order: `item` first, then every item referenced by
+/// `keys` (once each).
+pub fn items(config: &OnePasswordConfig) -> Result<Vec<(Option<String>, String)>> {
+    let mut items: Vec<(Option<String>, String)> = Vec::new();
+    if let Some(item) = &config.item {
+        items.push((config.vault.clone(), item.clone()));
+    }
+    for reference in config.keys.values() {
+        let reference = SecretReference::parse(reference)?;
+        let item = (Some(reference.vault), reference.item);
+        if !items.contains(&item) {
+            items.push(item);
+        }
+    }
+    Ok(items)
+}
+
+/// Fetch the configured keys, grouped by source item.
+pub fn fetch(config: &OnePasswordConfig) -> Result<Vec<(String, HashMap<String, String>)>> {
+    let mut fetched: BTreeMap<(Option<String>, String), Vec<ItemField>> = BTreeMap::new();
+    for (vault, item) in items(config)? {
+        let fields = get_item(config, vault.as_deref(), &item)?;
+        fetched.insert((vault, item), fields);
+    }
+
+    let mut sources = Vec::new();
+    if let Some(item) = &config.item {
+        let fields = &fetched[&(config.vault.clone(), item.clone())];
+        sources.push((source_name(config.vault.as_deref(), item), item_secrets(item, fields)?));
+    }
+
+    // Referenced keys, grouped per item for provenance
+    let mut referenced: BTreeMap<String, HashMap<String, String>> = BTreeMap::new();
+    for (key, reference) in &config.keys {
+        let parsed = SecretReference::parse(reference)?;
+        let fields = &fetched[&(Some(parsed.vault.clone()), parsed.item.clone())];
+        referenced
+            .entry(source_name(Some(&parsed.vault), &parsed.item))
+            .or_default()
+            .insert(key.clone(), resolve(&parsed, fields).with_context(|| format!("Cannot resolve {}", key))?);
+    }
+    sources.extend(referenced);
+
+    Ok(sources)
+}
+
+fn get_item(config: &OnePasswordConfig, vault: Option<&str>, item: &str) -> Result<Vec<ItemField>> {
+    let mut command = process::command("op")?;
+    command.args(item_get_args(config, vault, item));
+    let output = process::output_within(&mut command, process::timeouts().decrypt)
+        .map_err(|e| {
+            if process::is_interrupted(&e) {
+                return e.into();
+            }
+            anyhow::anyhow!(
+                "1Password CLI is not installed or not in PATH: {}. Install it: https://developer.1password.com/docs/cli/get-started/",
+                e
+            )
+        })?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(classify_error(item, &stderr));
+    }
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .with_context(|| format!("Failed to parse 1Password item '{}'", item))?;
+    parse_item(&json)
+}
+
+/// Extract the fields of an `op item get --format json` document.
+pub fn parse_item(json: &serde_json::Value) -> Result<Vec<ItemField>> {
+    let fields = json
+        .get("fields")
+        .and_then(|fields| fields.as_array())
+        .context("1Password item has no fields")?;
+
+    Ok(fields
+        .iter()
+        .map(|field| {
+            let text = |value: Option<&serde_json::Value>| value.and_then(|v| v.as_str()).map(str::to_string);
+            let section = field.get("section").and_then(|section| {
+                text(section.get("label"))
+                    .filter(|label| !label.is_empty())
+                    .or_else(|| text(section.get("id")))
+            });
+            ItemField {
+                id: text(field.get("id")).unwrap_or_default(),
+                label: text(field.get("label")).unwrap_or_default(),
+                section,
+                value: text(field.get("value")),
+            }
+        })
+        .collect())
+}
+
+/// Every labelled field with a value, keyed by label.
+fn item_secrets(item: &str, fields: &[ItemField]) -> Result<HashMap<String, String>> {
+    let mut secrets = HashMap::new();
+    for field in fields {
+        let Some(value) = &field.value else { continue };
+        if field.label.is_empty() {
+            continue;
+        }
+        if secrets.insert(field.label.clone(), value.clone()).is_some() {
+            anyhow::bail!(
+                "1Password item '{}' has several fields labelled '{}'; map them with vault.onepassword.keys",
+                item,
+                field.label
+            );
+        }
+    }
+    Ok(secrets)
+}
+
+/// Value of the field named by `reference` (by label or ID).
+fn resolve(reference: &SecretReference, fields: &[ItemField]) -> Result<String> {
+    let matches = |name: &str, field: &ItemField| field.label == name || field.id == name;
+    fields
+        .iter()
+        .filter(|field| matches(&reference.field, field))
+        .find(|field| match &reference.section {
+            Some(section) => field.section.as_deref() == Some(section.as_str()),
+            None => true,
+        })
+        .with_context(|| {
+            format!(
+                "1Password item '{}' has no field '{}'",
+                reference.item, reference.field
+            )
+        })?
+        .value
+        .clone()
+        .with_context(|| format!("1Password field '{}' is empty", reference.field))
+}
+
+/// Turn `op` stderr into an actionable error.
+fn classify_error(item: &str, stderr: &str) -> anyhow::Error {
+    let stderr = stderr.trim();
+    if stderr.contains("isn't an item") || stderr.contains("isn't a vault") {
+        anyhow::anyhow!("1Password has no item '{}' (check item and vault names): {}", item, stderr)
+    } else if stderr.contains("not currently signed in") || stderr.contains("no accounts configured") {
+        anyhow::anyhow!(
+            "Not signed in to 1Password: run 'op signin', enable the desktop app integration or set OP_SERVICE_ACCOUNT_TOKEN ({})",
+            stderr
+        )
+    } else {
+        anyhow::anyhow!("Failed to read 1Password item '{}': {}", item, stderr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockRunner;
+    use std::sync::Arc;
+
+    const ITEM: &str = r#"{
+        "id": "abc", "title": "postgres",
+        "fields": [
+            {"id": "username", "label": "username", "value": "app"},
+            {"id": "password", "label": "password", "value": "s3cret"},
+            {"id": "notesPlain", "label": "notesPlain"},
+            {"id": "x1", "label": "url", "value": "postgres://staging", "section": {"id": "s1", "label": "staging"}}
+        ]
+    }"#;
+
+    #[test]
+    fn test_parse_reference() {
+        let reference = SecretReference::parse("op://Dev/postgres/staging/url").unwrap();
+        assert_eq!(reference.section.as_deref(), Some("staging"));
+        assert_eq!(reference.field, "url");
+
+        assert!(SecretReference::parse("Dev/postgres/password").is_err());
+        assert!(SecretReference::parse("op://Dev/postgres").is_err());
+        assert!(SecretReference::parse("op://Dev/totp/one-time?attribute=otp").is_err());
+    }
+
+    #[test]
+    fn test_fetch_reads_each_item_once() {
+        let config = OnePasswordConfig {
+            account: None,
+            vault: Some("Dev".to_string()),
+            item: Some("postgres".to_string()),
+            keys: BTreeMap::from([
+                ("DB_PASSWORD".to_string(), "op://Dev/postgres/password".to_string()),
+                ("DB_URL".to_string(), "op://Dev/postgres/staging/url".to_string()),
+            ]),
+        };
+        let runner = Arc::new(MockRunner::new().respond(
+            "op",
+            &["item", "get", "postgres", "--format", "json", "--vault", "Dev"],
+            0,
+            ITEM,
+        ));
+
+        let sources = process::with_runner(runner.clone(), || fetch(&config)).unwrap();
+
+        assert_eq!(runner.calls().len(), 1);
+        assert_eq!(sources.len(), 2);
+        assert_eq!(sources[0].0, "1password:Dev/postgres");
+        assert_eq!(sources[0].1["username"], "app");
+        assert!(!sources[0].1.contains_key("notesPlain"));
+        assert_eq!(sources[1].1["DB_PASSWORD"], "s3cret");
+        assert_eq!(sources[1].1["DB_URL"], "postgres://staging");
+    }
+}
@@ -0,0 +1,117 @@
+//! password-store backend (`vault.engine: pass`).
+//!
+//! Each configured key is read from one entry of a GPG-backed `pass` store
+//! (`pass show <path>`). Following the pass convention, the first line of
+//! an entry is its password and is used as the value; further lines
+//! (usernames, URLs, notes) are ignored. Decryption goes through the user's
+//! gpg-agent, and output is captured in memory only.
+
+use crate::config::PassConfig;
+use crate::process;
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+
+/// Store location read by `pass` when `store_dir` is not set.
+pub const STORE_DIR_ENV: &str = "PASSWORD_STORE_DIR";
+
+/// Name of the source in provenance output.
+pub fn source_name(config: &PassConfig) -> String {
+    match &config.store_dir {
+        Some(dir) => format!("{}:{}", crate::vault::ENGINE_PASS, dir),
+        None => crate::vault::ENGINE_PASS.to_string(),
+    }
+}
+
+/// Fetch every configured key.
+pub fn fetch(config: &PassConfig) -> Result<HashMap<String, String>> {
+    let mut secrets = HashMap::new();
+    for (key, entry) in &config.keys {
+        let value = show(config, entry).with_context(|| format!("Cannot read {} from pass", key))?;
+        secrets.insert(key.clone(), value);
+    }
+    Ok(secrets)
+}
+
+/// First line of the entry at `path`.
+fn show(config: &PassConfig, path: &str) -> Result<String> {
+    let mut command = process::command("pass")?;
+    command.args(["show", path]);
+    if let Some(dir) = &config.store_dir {
+        command.env(STORE_DIR_ENV, shellexpand_home(dir));
+    }
+
+    let output = process::output_within(&mut command, process::timeouts().decrypt).map_err(|e| {
+        if process::is_interrupted(&e) {
+            return e.into();
+        }
+        anyhow::anyhow!(
+            "pass is not installed or not in PATH: {}. Install it: https://www.passwordstore.org/",
+            e
+        )
+    })?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stderr = stderr.trim();
+        if stderr.contains("is not in the password store") {
+            anyhow::bail!("pass has no entry '{}'", path);
+        }
+        anyhow::bail!("pass show {} failed: {}", path, stderr);
+    }
+
+    password_line(&output.stdout).with_context(|| format!("pass entry '{}' is empty", path))
+}
+
+/// The password of an entry: its first line, without the line ending.
+pub fn password_line(stdout: &[u8]) -> Option<String> {
+    let text = String::from_utf8_lossy(stdout);
+    let line = text.lines().next()?.trim_end_matches('\r');
+    (!line.is_empty()).then(|| line.to_string())
+}
+
+/// Expand a leading `~/` to the home directory.
+fn shellexpand_home(path: &str) -> String {
+    match (path.strip_prefix("~/"), crate::paths::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest).to_string_lossy().to_string(),
+        _ => path.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockRunner;
+    use std::collections::BTreeMap;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_password_line() {
+        assert_eq!(password_line(b"s3cret\nuser: app\nurl: x\n").as_deref(), Some("s3cret"));
+        assert_eq!(password_line(b"s3cret\r\n").as_deref(), Some("s3cret"));
+        assert_eq!(password_line(b"\nuser: app\n"), None);
+    }
+
+    #[test]
+    fn test_fetch_maps_entries_to_keys() {
+        let config = PassConfig {
+            store_dir: Some("/srv/team-store".to_string()),
+            keys: BTreeMap::from([
+                ("API_KEY".to_string(), "myapp/stripe".to_string()),
+                ("DB_PASSWORD".to_string(), "myapp/postgres".to_string()),
+            ]),
+        };
+        let runner = Arc::new(
+            MockRunner::new()
+                .respond("pass", &["show", "myapp/stripe"], 0, "sk_test_123\n")
+                .respond("pass", &["show", "myapp/postgres"], 0, "s3cret\nuser: app\n"),
+        );
+
+        let secrets = process::with_runner(runner.clone(), || fetch(&config)).unwrap();
+
+        assert_eq!(secrets["API_KEY"], "sk_test_123");
+        assert_eq!(secrets["DB_PASSWORD"], "s3cret");
+        assert!(runner.calls()[0]
+            .env
+            .contains(&(STORE_DIR_ENV.to_string(), "/srv/team-store".to_string())));
+    }
+}
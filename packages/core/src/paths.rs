@@ -0,0 +1,139 @@
+//! Resolution of the home and global config directories.
+//!
+//! Minimal container images used by CI often run without `HOME`, or as a
+//! UID with no passwd entry, so the home directory cannot be determined.
+//! Instead of failing, the global config directory (keys, `global.yaml`,
+//! audit log and other state) is resolved from, in order:
+//!
+//! 1. `--config-dir` (see [`set_config_dir`])
+//! 2. `$SHADOW_SECRET_CONFIG_DIR`
+//! 3. `~/.config/shadow-secret`
+//! 4. `/etc/shadow-secret` (`%ProgramData%\shadow-secret` on Windows), when
+//!    there is no home directory
+//!
+//! Paths that only make sense under a home directory (`~` in config paths,
+//! shell histories, credential files of other CLIs) use [`home_dir`] and
+//! are skipped, or fail with a hint, when there is none.
+
+use anyhow::Result;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Environment variable overriding the global config directory.
+pub const CONFIG_DIR_ENV: &str = "SHADOW_SECRET_CONFIG_DIR";
+
+/// System-wide config directory, used when there is no home directory.
+#[cfg(unix)]
+pub const SYSTEM_CONFIG_DIR: &str = "/etc/shadow-secret";
+
+/// Global config file inside the config directory.
+pub const GLOBAL_CONFIG_FILE: &str = "global.yaml";
+
+static CONFIG_DIR_FLAG: OnceLock<PathBuf> = OnceLock::new();
+
+/// Use `dir` as the global config directory for the rest of the process
+/// (`--config-dir`). Only the first call has an effect.
+pub fn set_config_dir(dir: PathBuf) {
+    let _ = CONFIG_DIR_FLAG.set(dir);
+}
+
+/// The user's home directory, if it can be determined.
+pub fn home_dir() -> Option<PathBuf> {
+    dirs::home_dir().filter(|home| !home.as_os_str().is_empty())
+}
+
+/// The user's home directory.
+///
+/// # Errors
+///
+/// Fails with a hint when there is none (containers without `HOME`).
+pub fn require_home() -> Result<PathBuf> {
+    home_dir().ok_or_else(|| {
+        anyhow::anyhow!(
+            "Failed to determine home directory: set $HOME (or ${} for Shadow Secret's own files)",
+            CONFIG_DIR_ENV
+        )
+    })
+}
+
+/// The platform's per-user config directory (`$XDG_CONFIG_HOME`,
+/// `~/.config`, `%APPDATA%`), if it can be determined.
+pub fn user_config_dir() -> Option<PathBuf> {
+    dirs::config_dir()
+}
+
+/// System-wide config directory, used when there is no home directory.
+pub fn system_config_dir() -> Option<PathBuf> {
+    #[cfg(unix)]
+    return Some(PathBuf::from(SYSTEM_CONFIG_DIR));
+    #[cfg(not(unix))]
+    return std::env::var_os("ProgramData").map(|data| PathBuf::from(data).join("shadow-secret"));
+}
+
+/// Shadow Secret's global config directory (see the module documentation
+/// for the resolution order).
+///
+/// # Errors
+///
+/// Fails only when no source applies (no home directory, and no
+/// system-wide directory on this platform).
+pub fn config_dir() -> Result<PathBuf> {
+    resolve_config_dir(
+        CONFIG_DIR_FLAG.get().map(PathBuf::as_path),
+        std::env::var_os(CONFIG_DIR_ENV),
+        home_dir(),
+        system_config_dir(),
+    )
+    .ok_or_else(|| {
+        anyhow::anyhow!(
+            "Failed to determine the config directory: set $HOME, ${} or --config-dir",
+            CONFIG_DIR_ENV
+        )
+    })
+}
+
+/// The global config file (`global.yaml` in [`config_dir`]).
+pub fn global_config_path() -> Result<PathBuf> {
+    Ok(config_dir()?.join(GLOBAL_CONFIG_FILE))
+}
+
+/// First applicable source of the config directory.
+pub fn resolve_config_dir(
+    flag: Option<&Path>,
+    env: Option<OsString>,
+    home: Option<PathBuf>,
+    system: Option<PathBuf>,
+) -> Option<PathBuf> {
+    flag.map(Path::to_path_buf)
+        .or_else(|| env.filter(|dir| !dir.is_empty()).map(PathBuf::from))
+        .or_else(|| home.map(|home| home.join(".config").join("shadow-secret")))
+        .or(system)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_config_dir_order() {
+        let home = || Some(PathBuf::from("/home/ci"));
+        let system = || Some(PathBuf::from("/etc/shadow-secret"));
+
+        assert_eq!(
+            resolve_config_dir(Some(Path::new("/flag")), Some("/env".into()), home(), system()),
+            Some(PathBuf::from("/flag"))
+        );
+        assert_eq!(
+            resolve_config_dir(None, Some("/env".into()), home(), system()),
+            Some(PathBuf::from("/env"))
+        );
+        assert_eq!(
+            resolve_config_dir(None, Some("".into()), home(), system()),
+            Some(PathBuf::from("/home/ci/.config/shadow-secret"))
+        );
+        // Containers without HOME
+        assert_eq!(resolve_config_dir(None, None, None, system()), system());
+        assert_eq!(resolve_config_dir(None, None, None, None), None);
+    }
+}
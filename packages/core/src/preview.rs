@@ -0,0 +1,189 @@
+//! Previewing `unlock` without touching target files.
+//!
+//! `unlock --preview-dir DIR` writes the injected version of every target
+//! into `DIR`, mirroring the target paths relative to the project directory
+//! (targets outside the project go under `DIR/outside/`). Originals are
+//! never modified, so nothing is registered for cleanup.
+//!
+//! Preview files contain the real (or, for untrusted targets, fake) values
+//! and stay on disk until deleted: keep the directory gitignored.
+//!
+//! `unlock --dry-run` writes nothing: [`redacted_diff`] shows what each
+//! target would become, with every value masked as `<redacted:KEY>`.
+
+use crate::config::TargetConfig;
+use crate::injector;
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+
+/// Subdirectory of the preview directory for targets outside the project.
+pub const OUTSIDE_DIR: &str = "outside";
+
+/// Resolve `path` against `base` and remove `.`/`..` segments, without
+/// touching the filesystem.
+fn normalize(base: &Path, path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in base.join(path).components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+/// Where the preview of `target_path` is written.
+///
+/// `target_path` is resolved like the injector does (relative to the current
+/// directory `cwd`); `config_dir` must be absolute.
+pub fn preview_path(preview_dir: &Path, config_dir: &Path, cwd: &Path, target_path: &Path) -> PathBuf {
+    let resolved = normalize(cwd, target_path);
+
+    match resolved.strip_prefix(config_dir) {
+        Ok(relative) => preview_dir.join(relative),
+        Err(_) => {
+            let relative: PathBuf = resolved
+                .components()
+                .filter(|component| matches!(component, Component::Normal(_)))
+                .collect();
+            preview_dir.join(OUTSIDE_DIR).join(relative)
+        }
+    }
+}
+
+/// Content of `target` after injection, without modifying it.
+///
+/// A missing file with `create_if_missing` is rendered from its template.
+pub fn injected_content(target: &TargetConfig, secrets: &HashMap<String, String>) -> Result<String> {
+    let path = Path::new(&target.path);
+    let content = if !path.exists() && target.create_if_missing {
+        target.template.clone().unwrap_or_default()
+    } else {
+        std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read target file: {}", target.path))?
+    };
+
+    if target.preserve_formatting {
+        return injector::replace_placeholders_verbatim(path, &content, secrets, &target.placeholders, target.style()?.as_ref());
+    }
+    injector::replace_placeholders_for(path, &content, secrets, &target.placeholders, target.style()?.as_ref())
+}
+
+/// `secrets` with every value replaced by `<redacted:KEY>`.
+pub fn masked_secrets(secrets: &HashMap<String, String>) -> HashMap<String, String> {
+    secrets
+        .keys()
+        .map(|key| (key.clone(), format!("<redacted:{}>", key)))
+        .collect()
+}
+
+/// Unified diff between `target` as it is and as it would be injected with
+/// `secrets`, values masked (see [`masked_secrets`]). A target that would
+/// be created is diffed against an empty file.
+pub fn redacted_diff(target: &TargetConfig, secrets: &HashMap<String, String>) -> Result<Vec<String>> {
+    let path = Path::new(&target.path);
+    let current = if !path.exists() && target.create_if_missing {
+        String::new()
+    } else {
+        std::fs::read_to_string(path).with_context(|| format!("Failed to read target file: {}", target.path))?
+    };
+    let injected = injected_content(target, &masked_secrets(secrets))?;
+    Ok(crate::diff::unified_diff(&current, &injected, 3))
+}
+
+/// Write a preview file (mode 0600 on Unix), creating its directories.
+pub fn write_preview(path: &Path, content: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory: {:?}", parent))?;
+    }
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    use std::io::Write;
+    options
+        .open(path)
+        .and_then(|mut file| file.write_all(content.as_bytes()))
+        .with_context(|| format!("Failed to write preview: {:?}", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preview_path_mirrors_project_layout() {
+        let preview = Path::new("/project/.shadow-preview");
+        let project = Path::new("/project");
+
+        assert_eq!(
+            preview_path(preview, project, project, Path::new("config/app.json")),
+            PathBuf::from("/project/.shadow-preview/config/app.json")
+        );
+        assert_eq!(
+            preview_path(preview, project, Path::new("/project/sub"), Path::new("../.env")),
+            PathBuf::from("/project/.shadow-preview/.env")
+        );
+        assert_eq!(
+            preview_path(preview, project, project, Path::new("/home/me/.tool/config.yaml")),
+            PathBuf::from("/project/.shadow-preview/outside/home/me/.tool/config.yaml")
+        );
+    }
+
+    #[test]
+    fn test_injected_content_leaves_original_untouched() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("app.env");
+        std::fs::write(&path, "API_KEY=$API_KEY\n").unwrap();
+
+        let target = TargetConfig {
+            name: "app".to_string(),
+            path: path.to_string_lossy().to_string(),
+            placeholders: vec!["$API_KEY".to_string()],
+            ..Default::default()
+        };
+        let secrets = HashMap::from([("API_KEY".to_string(), "sk_test_123".to_string())]);
+
+        assert_eq!(injected_content(&target, &secrets).unwrap(), "API_KEY=sk_test_123\n");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "API_KEY=$API_KEY\n");
+
+        let missing = TargetConfig {
+            path: temp_dir.path().join("missing.env").to_string_lossy().to_string(),
+            create_if_missing: true,
+            template: Some("KEY=$API_KEY".to_string()),
+            ..target
+        };
+        assert_eq!(injected_content(&missing, &secrets).unwrap(), "KEY=sk_test_123");
+    }
+
+    #[test]
+    fn test_redacted_diff_masks_values() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("app.json");
+        std::fs::write(&path, "{\n  \"name\": \"app\",\n  \"key\": \"$API_KEY\"\n}\n").unwrap();
+
+        let target = TargetConfig {
+            name: "app".to_string(),
+            path: path.to_string_lossy().to_string(),
+            placeholders: vec!["$API_KEY".to_string()],
+            ..Default::default()
+        };
+        let secrets = HashMap::from([("API_KEY".to_string(), "sk_test_123".to_string())]);
+
+        let diff = redacted_diff(&target, &secrets).unwrap();
+        assert!(diff.contains(&"-  \"key\": \"$API_KEY\"".to_string()), "{:?}", diff);
+        assert!(diff.contains(&"+  \"key\": \"<redacted:API_KEY>\"".to_string()), "{:?}", diff);
+        assert!(!diff.concat().contains("sk_test_123"));
+        assert!(std::fs::read_to_string(&path).unwrap().contains("$API_KEY"));
+    }
+}
@@ -0,0 +1,456 @@
+//! External process spawning and restricted mode.
+//!
+//! Every subprocess Shadow Secret runs (sops, age, vercel, npm) is created
+//! through [`command`], so the set of binaries the tool may execute is
+//! enforced in a single place. Their output is collected with [`output`]
+//! or [`output_with_stdin`], which go through a [`CommandRunner`] that
+//! tests can replace (see [`crate::testing`]).
+//!
+//! # Timeouts and cancellation
+//!
+//! [`output_within`] kills a subprocess still running after its timeout
+//! (`timeouts:` in the config, see [`Timeouts`]) instead of hanging on it.
+//! [`cancel`] kills the subprocess being waited on and lets long loops
+//! (pushing variables one by one) stop at the next step.
+//!
+//! # Restricted mode
+//!
+//! `--restricted` is meant for hardened CI runners (SELinux/AppArmor
+//! confined, seccomp filtered). Once enabled:
+//!
+//! - only the encryption binaries in [`RESTRICTED_PROGRAMS`] may be spawned,
+//!   as found in `PATH` (and pinned, see [`crate::binaries`])
+//! - blocking process termination during cleanup is disabled
+//! - all Linux capabilities are dropped from the current process
+//!
+//! [`seccomp_profile`] documents the resulting syscall surface as an OCI
+//! seccomp profile that can be loaded by Docker, Podman or Kubernetes.
+
+use anyhow::{Context, Result};
+use std::ffi::OsStr;
+use std::path::Path;
+use std::cell::RefCell;
+use std::io::{Read, Write};
+use std::process::{Command, Output, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+/// Binaries allowed to run in restricted mode.
+pub const RESTRICTED_PROGRAMS: &[&str] = &["sops", "age", "age-keygen"];
+
+static RESTRICTED: AtomicBool = AtomicBool::new(false);
+
+/// Enable restricted mode for the rest of the process lifetime.
+///
+/// # Errors
+///
+/// Returns an error if capabilities could not be dropped.
+pub fn enable_restricted_mode() -> Result<()> {
+    RESTRICTED.store(true, Ordering::SeqCst);
+    drop_capabilities()
+}
+
+/// Whether restricted mode is active.
+pub fn is_restricted() -> bool {
+    RESTRICTED.load(Ordering::SeqCst)
+}
+
+/// Create a [`Command`] for `program`, enforcing restricted mode.
+///
+/// # Errors
+///
+/// Returns an error in restricted mode if `program` does not resolve to
+/// one of [`RESTRICTED_PROGRAMS`] as found in `PATH`.
+pub fn command<S: AsRef<OsStr>>(program: S) -> Result<Command> {
+    let program = program.as_ref();
+
+    if is_restricted() && !is_allowed(program) {
+        anyhow::bail!(
+            "Refusing to run '{}' in restricted mode (allowed: {})",
+            program.to_string_lossy(),
+            RESTRICTED_PROGRAMS.join(", ")
+        );
+    }
+
+    Ok(Command::new(program))
+}
+
+/// Runs the commands built by [`command`] and collects their output.
+///
+/// [`SystemRunner`] spawns real processes. Other implementations record,
+/// replay or mock them (see [`crate::testing`]); install one for the whole
+/// process with [`set_runner`], or for the current thread with
+/// [`with_runner`].
+pub trait CommandRunner: Send + Sync {
+    /// Run `command`, writing `stdin` to its standard input when given.
+    fn run(&self, command: &mut Command, stdin: Option<&[u8]>) -> std::io::Result<Output>;
+
+    /// Like [`run`](Self::run), killing the process if it still runs after
+    /// `timeout`. Runners that do not spawn processes ignore the limit.
+    fn run_within(&self, command: &mut Command, stdin: Option<&[u8]>, timeout: Option<Duration>) -> std::io::Result<Output> {
+        let _ = timeout;
+        self.run(command, stdin)
+    }
+}
+
+/// Spawns real processes.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemRunner;
+
+impl CommandRunner for SystemRunner {
+    fn run(&self, command: &mut Command, stdin: Option<&[u8]>) -> std::io::Result<Output> {
+        self.run_within(command, stdin, None)
+    }
+
+    fn run_within(&self, command: &mut Command, stdin: Option<&[u8]>, timeout: Option<Duration>) -> std::io::Result<Output> {
+        // Never hand plaintext to a binary swapped since first use
+        crate::binaries::verify(command.get_program())
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::PermissionDenied, format!("{:#}", e)))?;
+
+        let mut child = command
+            .stdin(if stdin.is_some() { Stdio::piped() } else { Stdio::null() })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        if let (Some(input), Some(mut pipe)) = (stdin, child.stdin.take()) {
+            pipe.write_all(input)?;
+        }
+
+        // Drain the pipes while waiting, so a chatty child never blocks
+        let stdout = child.stdout.take().map(drain);
+        let stderr = child.stderr.take().map(drain);
+
+        let started = Instant::now();
+        let status = loop {
+            if let Some(status) = child.try_wait()? {
+                break status;
+            }
+            let timed_out = timeout.filter(|timeout| started.elapsed() >= *timeout);
+            if timed_out.is_some() || is_cancelled() {
+                let _ = child.kill();
+                let _ = child.wait();
+                let program = command.get_program().to_string_lossy();
+                return Err(match timed_out {
+                    Some(timeout) => std::io::Error::new(
+                        std::io::ErrorKind::TimedOut,
+                        format!("'{}' timed out after {:?}", program, timeout),
+                    ),
+                    None => std::io::Error::new(std::io::ErrorKind::Interrupted, format!("'{}' was cancelled", program)),
+                });
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        };
+
+        let collect = |reader: Option<std::thread::JoinHandle<Vec<u8>>>| {
+            reader.map(|reader| reader.join().unwrap_or_default()).unwrap_or_default()
+        };
+        Ok(Output {
+            status,
+            stdout: collect(stdout),
+            stderr: collect(stderr),
+        })
+    }
+}
+
+/// Time between two checks of a running subprocess.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Read `pipe` to the end on a thread of its own.
+fn drain(mut pipe: impl Read + Send + 'static) -> std::thread::JoinHandle<Vec<u8>> {
+    std::thread::spawn(move || {
+        let mut content = Vec::new();
+        let _ = pipe.read_to_end(&mut content);
+        content
+    })
+}
+
+/// How long each kind of work may take before it is abandoned (`None`:
+/// no limit).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timeouts {
+    /// Decrypting a vault (`sops -d`, secret manager CLIs)
+    pub decrypt: Option<Duration>,
+    /// Injecting secrets into one target
+    pub inject: Option<Duration>,
+    /// One cloud CLI call (`vercel env add`, `aws sts assume-role`)
+    pub cloud: Option<Duration>,
+}
+
+impl Timeouts {
+    /// Limits used when the config sets none.
+    pub const DEFAULT: Timeouts = Timeouts {
+        decrypt: Some(Duration::from_secs(5 * 60)),
+        inject: Some(Duration::from_secs(60)),
+        cloud: Some(Duration::from_secs(5 * 60)),
+    };
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+static TIMEOUTS: RwLock<Timeouts> = RwLock::new(Timeouts::DEFAULT);
+
+/// Use `timeouts` for the rest of the process (`timeouts:` in the config).
+pub fn set_timeouts(timeouts: Timeouts) {
+    *TIMEOUTS.write().unwrap_or_else(|e| e.into_inner()) = timeouts;
+}
+
+/// The timeouts in effect.
+pub fn timeouts() -> Timeouts {
+    *TIMEOUTS.read().unwrap_or_else(|e| e.into_inner())
+}
+
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+
+/// Ask the work in progress to stop: the subprocess being waited on is
+/// killed, and loops checking [`is_cancelled`] stop at their next step.
+pub fn cancel() {
+    CANCELLED.store(true, Ordering::SeqCst);
+}
+
+/// Whether [`cancel`] was called since the last [`reset_cancel`].
+pub fn is_cancelled() -> bool {
+    CANCELLED.load(Ordering::SeqCst)
+}
+
+/// Whether `error` is a subprocess timing out or being cancelled (rather
+/// than failing to start).
+pub fn is_interrupted(error: &std::io::Error) -> bool {
+    matches!(error.kind(), std::io::ErrorKind::TimedOut | std::io::ErrorKind::Interrupted)
+}
+
+/// Let subprocesses run again after a cancellation was handled (lock must
+/// still be able to run its hooks).
+pub fn reset_cancel() {
+    CANCELLED.store(false, Ordering::SeqCst);
+}
+
+static GLOBAL_RUNNER: RwLock<Option<Arc<dyn CommandRunner>>> = RwLock::new(None);
+
+thread_local! {
+    static THREAD_RUNNER: RefCell<Option<Arc<dyn CommandRunner>>> = const { RefCell::new(None) };
+}
+
+/// Use `runner` for every command of the process (until [`reset_runner`]).
+pub fn set_runner(runner: Arc<dyn CommandRunner>) {
+    *GLOBAL_RUNNER.write().unwrap_or_else(|e| e.into_inner()) = Some(runner);
+}
+
+/// Go back to spawning real processes.
+pub fn reset_runner() {
+    *GLOBAL_RUNNER.write().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+/// Run `f` with `runner` handling the commands of the current thread (and
+/// of the threads Shadow Secret starts for it, such as concurrent vault
+/// decryption), leaving other threads alone. Meant for unit tests.
+pub fn with_runner<T>(runner: Arc<dyn CommandRunner>, f: impl FnOnce() -> T) -> T {
+    // Restores the previous runner, even if `f` panics
+    struct Restore(Option<Arc<dyn CommandRunner>>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let previous = self.0.take();
+            THREAD_RUNNER.with(|local| *local.borrow_mut() = previous);
+        }
+    }
+
+    let _restore = Restore(THREAD_RUNNER.with(|local| local.replace(Some(runner))));
+    f()
+}
+
+/// The runner in effect on this thread.
+pub fn runner() -> Arc<dyn CommandRunner> {
+    if let Some(runner) = THREAD_RUNNER.with(|local| local.borrow().clone()) {
+        return runner;
+    }
+    GLOBAL_RUNNER
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+        .unwrap_or_else(|| Arc::new(SystemRunner))
+}
+
+/// Run `command` and collect its output, like [`Command::output`], through
+/// the current [`CommandRunner`].
+pub fn output(command: &mut Command) -> std::io::Result<Output> {
+    runner().run(command, None)
+}
+
+/// Run `command` with `input` written to its stdin and collect its output,
+/// through the current [`CommandRunner`].
+pub fn output_with_stdin(command: &mut Command, input: &[u8]) -> std::io::Result<Output> {
+    runner().run(command, Some(input))
+}
+
+/// Like [`output`], failing with [`std::io::ErrorKind::TimedOut`] (and
+/// killing the process) after `timeout`, e.g. `timeouts().decrypt`.
+pub fn output_within(command: &mut Command, timeout: Option<Duration>) -> std::io::Result<Output> {
+    runner().run_within(command, None, timeout)
+}
+
+/// Like [`output_with_stdin`], with a timeout (see [`output_within`]).
+pub fn output_with_stdin_within(command: &mut Command, input: &[u8], timeout: Option<Duration>) -> std::io::Result<Output> {
+    runner().run_within(command, Some(input), timeout)
+}
+
+/// Check a program name or path against the restricted allowlist.
+///
+/// The name alone is not enough: `program` must resolve to the same file
+/// as the allowed program of that name found in `PATH`, so a `sops`
+/// planted elsewhere and passed by path is refused.
+fn is_allowed(program: &OsStr) -> bool {
+    let name = Path::new(program)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    if !RESTRICTED_PROGRAMS.contains(&name.as_str()) {
+        return false;
+    }
+
+    match (crate::binaries::resolve(program), crate::binaries::resolve(OsStr::new(&name))) {
+        (Some(resolved), Some(expected)) => resolved == expected,
+        _ => false,
+    }
+}
+
+/// Drop every capability from the effective, permitted, inheritable and
+/// ambient sets.
+#[cfg(target_os = "linux")]
+fn drop_capabilities() -> Result<()> {
+    use caps::CapSet;
+
+    for set in [
+        CapSet::Ambient,
+        CapSet::Inheritable,
+        CapSet::Effective,
+        CapSet::Permitted,
+    ] {
+        caps::clear(None, set)
+            .map_err(|e| anyhow::anyhow!("{}", e))
+            .with_context(|| format!("Failed to drop {:?} capabilities", set))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn drop_capabilities() -> Result<()> {
+    Ok(())
+}
+
+/// Syscalls needed by Shadow Secret and the sops/age children it spawns.
+///
+/// Networking is included: sops reaches KMS backends, and the HashiCorp,
+/// AWS, GCP, 1Password and Bitwarden engines as well as the agent socket
+/// need it too.
+const SECCOMP_SYSCALLS: &[&str] = &[
+    // Process lifecycle
+    "execve", "execveat", "clone", "clone3", "fork", "vfork", "wait4", "waitid", "exit",
+    "exit_group", "kill", "tgkill", "getpid", "getppid", "gettid", "set_tid_address",
+    "set_robust_list", "prctl", "capget", "capset", "prlimit64", "getrlimit", "sched_yield",
+    "sched_getaffinity", "rseq",
+    // Memory
+    "brk", "mmap", "munmap", "mprotect", "mremap", "madvise",
+    // Files
+    "openat", "open", "close", "close_range", "read", "write", "pread64", "pwrite64", "readv",
+    "writev", "lseek", "fstat", "newfstatat", "stat", "lstat", "statx", "access", "faccessat",
+    "faccessat2", "readlink", "readlinkat", "getdents64", "fcntl", "ioctl", "fsync",
+    "fdatasync", "ftruncate", "rename", "renameat", "renameat2", "unlink", "unlinkat", "mkdir",
+    "mkdirat", "fchmod", "fchmodat", "getcwd", "chdir", "umask",
+    // Pipes and polling
+    "pipe", "pipe2", "dup", "dup2", "dup3", "poll", "ppoll", "epoll_create1", "epoll_ctl",
+    "epoll_wait", "epoll_pwait", "eventfd2",
+    // Signals and threads
+    "rt_sigaction", "rt_sigprocmask", "rt_sigreturn", "sigaltstack", "futex", "nanosleep",
+    "clock_nanosleep", "clock_gettime", "gettimeofday",
+    // Networking
+    "socket", "socketpair", "connect", "bind", "listen", "accept", "accept4", "shutdown",
+    "sendto", "recvfrom", "sendmsg", "recvmsg", "sendmmsg", "recvmmsg", "getsockopt",
+    "setsockopt", "getsockname", "getpeername", "select", "pselect6",
+    // Identity and randomness
+    "getuid", "geteuid", "getgid", "getegid", "getrandom", "uname", "sysinfo",
+];
+
+/// Generate an OCI seccomp profile describing the syscall surface used in
+/// restricted mode. Anything not listed fails with `EPERM`.
+pub fn seccomp_profile() -> serde_json::Value {
+    serde_json::json!({
+        "defaultAction": "SCMP_ACT_ERRNO",
+        "architectures": [
+            "SCMP_ARCH_X86_64",
+            "SCMP_ARCH_AARCH64"
+        ],
+        "syscalls": [
+            {
+                "names": SECCOMP_SYSCALLS,
+                "action": "SCMP_ACT_ALLOW"
+            }
+        ]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allowlist_matches_program_names_and_paths() {
+        assert!(!is_allowed(OsStr::new("vercel")));
+        assert!(!is_allowed(OsStr::new("/usr/bin/npm")));
+
+        if let Some(sops) = crate::binaries::resolve(OsStr::new("sops")) {
+            assert!(is_allowed(OsStr::new("sops")));
+            assert!(is_allowed(sops.as_os_str()));
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_allowlist_refuses_programs_outside_path() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let fake = dir.path().join("sops");
+        std::fs::write(&fake, "#!/bin/sh\n").unwrap();
+        std::fs::set_permissions(&fake, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        assert!(!is_allowed(fake.as_os_str()));
+    }
+
+    #[test]
+    fn test_command_unrestricted_by_default() {
+        assert!(command("vercel").is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_system_runner_kills_commands_after_timeout() {
+        let output = SystemRunner
+            .run_within(Command::new("sh").args(["-c", "echo ok; cat"]), Some(b"input"), Some(Duration::from_secs(5)))
+            .unwrap();
+        assert_eq!(output.stdout, b"ok\ninput");
+
+        let started = Instant::now();
+        let error = SystemRunner
+            .run_within(Command::new("sleep").arg("10"), None, Some(Duration::from_millis(100)))
+            .unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::TimedOut);
+        assert!(error.to_string().contains("'sleep' timed out after 100ms"), "{}", error);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_seccomp_profile_shape() {
+        let profile = seccomp_profile();
+
+        assert_eq!(profile["defaultAction"], "SCMP_ACT_ERRNO");
+        let names = profile["syscalls"][0]["names"].as_array().unwrap();
+        assert!(names.iter().any(|name| name == "execve"));
+        assert!(names.iter().any(|name| name == "openat"));
+        assert!(names.iter().any(|name| name == "connect"));
+    }
+}
@@ -0,0 +1,258 @@
+//! Organization profiles for `init-project --profile`.
+//!
+//! A profile is a small YAML document published by an organization that
+//! carries the defaults every team should start from: extra age recipients,
+//! key server location, policies, naming conventions and default targets.
+//!
+//! Profiles are only trusted when they carry a valid detached minisign
+//! signature (`<profile>.minisig`) made with the organization key.
+//!
+//! # Example profile
+//!
+//! ```yaml
+//! name: "acme"
+//! key_server: "https://keys.acme.internal"
+//! recipients:
+//!   - "age1qyqszqgpqyqszqgpqyqszqgpqyqszqgpqyqszqgpqyqszqgpqyqs3290gq"
+//! policies:
+//!   require_signed_config: true
+//! naming:
+//!   prefix: "ACME_"
+//! targets:
+//!   - name: "dotenv"
+//!     path: ".env"
+//!     placeholders: ["$ALL"]
+//! ```
+
+use crate::config::TargetConfig;
+use crate::signature::{self, PublicKey};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::path::Path;
+
+/// Environment variable holding the trusted organization public key.
+pub const PROFILE_KEY_ENV: &str = "SHADOW_SECRET_PROFILE_KEY";
+
+/// Organization defaults applied during project initialization.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OrgProfile {
+    /// Organization or team name
+    pub name: String,
+
+    /// Where team members publish their age public keys
+    #[serde(default)]
+    pub key_server: Option<String>,
+
+    /// Additional age recipients added to `.sops.yaml`
+    #[serde(default)]
+    pub recipients: Vec<String>,
+
+    /// Organization policies, copied verbatim into `project.yaml`
+    #[serde(default)]
+    pub policies: Option<serde_yaml::Value>,
+
+    /// Naming conventions, copied verbatim into `project.yaml`
+    #[serde(default)]
+    pub naming: Option<serde_yaml::Value>,
+
+    /// Default targets used instead of the generated example target
+    #[serde(default)]
+    pub targets: Vec<TargetConfig>,
+
+    /// Where the profile was fetched from (set on load)
+    #[serde(skip)]
+    pub source: String,
+}
+
+impl OrgProfile {
+    /// Parse and validate a profile document.
+    pub fn parse(content: &str) -> Result<Self> {
+        let profile: OrgProfile =
+            serde_yaml::from_str(content).context("Failed to parse organization profile")?;
+
+        if profile.name.trim().is_empty() {
+            anyhow::bail!("Organization profile must have a 'name'");
+        }
+
+        for recipient in &profile.recipients {
+            if !recipient.starts_with("age1") {
+                anyhow::bail!(
+                    "Invalid recipient '{}' in organization profile (expected an age public key)",
+                    recipient
+                );
+            }
+        }
+
+        for target in &profile.targets {
+            if target.name.is_empty() || target.path.is_empty() {
+                anyhow::bail!("Organization profile targets need a 'name' and a 'path'");
+            }
+        }
+
+        Ok(profile)
+    }
+}
+
+/// Resolve the trusted organization key.
+///
+/// # Resolution Order
+/// 1. Explicit `--profile-key` value (inline key or path to a `.pub` file)
+/// 2. `$SHADOW_SECRET_PROFILE_KEY`
+/// 3. `~/.config/shadow-secret/profile.pub`
+pub fn resolve_profile_key(explicit: Option<&str>) -> Result<PublicKey> {
+    if let Some(value) = explicit {
+        return PublicKey::from_arg(value);
+    }
+
+    if let Ok(value) = std::env::var(PROFILE_KEY_ENV) {
+        return PublicKey::from_arg(&value);
+    }
+
+    let default_path = crate::init::get_global_config_dir()?.join("profile.pub");
+    if default_path.exists() {
+        return PublicKey::from_arg(&default_path.to_string_lossy());
+    }
+
+    anyhow::bail!(
+        "No trusted organization key found.\n\
+        Provide one of:\n\
+        1. --profile-key <key or path to .pub file>\n\
+        2. ${} environment variable\n\
+        3. ~/.config/shadow-secret/profile.pub",
+        PROFILE_KEY_ENV
+    )
+}
+
+/// Fetch a profile from a URL or local path and verify its signature.
+///
+/// The signature is expected next to the profile (`<source>.minisig`).
+pub fn fetch_profile(source: &str, public_key: &PublicKey) -> Result<OrgProfile> {
+    let content = read_source(source)
+        .with_context(|| format!("Failed to fetch organization profile: {}", source))?;
+
+    let signature_source = format!("{}.{}", source, signature::SIGNATURE_EXTENSION);
+    let signature = read_source(&signature_source)
+        .with_context(|| format!("Failed to fetch profile signature: {}", signature_source))?;
+    let signature = String::from_utf8(signature).context("Profile signature is not valid UTF-8")?;
+
+    signature::verify(&content, &signature, public_key)
+        .with_context(|| format!("Organization profile signature is invalid: {}", source))?;
+
+    let content = String::from_utf8(content).context("Organization profile is not valid UTF-8")?;
+
+    let mut profile = OrgProfile::parse(&content)?;
+    profile.source = source.to_string();
+
+    Ok(profile)
+}
+
+/// Read raw bytes from an `http(s)://` URL or a local file.
+fn read_source(source: &str) -> Result<Vec<u8>> {
+    if source.starts_with("https://") || source.starts_with("http://") {
+        let response = ureq::get(source)
+            .call()
+            .map_err(|e| anyhow::anyhow!("HTTP request failed: {}", e))?;
+
+        let mut bytes = Vec::new();
+        response
+            .into_reader()
+            .take(1024 * 1024)
+            .read_to_end(&mut bytes)
+            .context("Failed to read HTTP response body")?;
+        return Ok(bytes);
+    }
+
+    std::fs::read(Path::new(source)).with_context(|| format!("Failed to read file: {}", source))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signature::test_support::{public_key_line, sign, signing_key};
+    use tempfile::TempDir;
+
+    const PROFILE: &str = r#"name: "acme"
+key_server: "https://keys.acme.internal"
+recipients:
+  - "age1teamkey"
+policies:
+  require_signed_config: true
+targets:
+  - name: "dotenv"
+    path: ".env"
+    placeholders: ["$ALL"]
+"#;
+
+    #[test]
+    fn test_parse_profile() {
+        let profile = OrgProfile::parse(PROFILE).unwrap();
+
+        assert_eq!(profile.name, "acme");
+        assert_eq!(profile.recipients, vec!["age1teamkey".to_string()]);
+        assert_eq!(profile.targets.len(), 1);
+        assert!(profile.policies.is_some());
+        assert!(profile.naming.is_none());
+    }
+
+    #[test]
+    fn test_parse_profile_rejects_invalid_recipient() {
+        let result = OrgProfile::parse("name: acme\nrecipients: [\"ssh-rsa AAAA\"]\n");
+
+        assert!(result.is_err());
+        assert!(result.unwrap_err().to_string().contains("Invalid recipient"));
+    }
+
+    #[test]
+    fn test_fetch_profile_verifies_signature() {
+        let temp_dir = TempDir::new().unwrap();
+        let key = signing_key(11);
+        let public_key = PublicKey::parse(&public_key_line(&key)).unwrap();
+
+        let path = temp_dir.path().join("org-profile.yaml");
+        std::fs::write(&path, PROFILE).unwrap();
+        std::fs::write(
+            temp_dir.path().join("org-profile.yaml.minisig"),
+            sign(&key, PROFILE.as_bytes()),
+        )
+        .unwrap();
+
+        let source = path.to_string_lossy().to_string();
+        let profile = fetch_profile(&source, &public_key).unwrap();
+
+        assert_eq!(profile.name, "acme");
+        assert_eq!(profile.source, source);
+    }
+
+    #[test]
+    fn test_fetch_profile_rejects_tampered_profile() {
+        let temp_dir = TempDir::new().unwrap();
+        let key = signing_key(11);
+        let public_key = PublicKey::parse(&public_key_line(&key)).unwrap();
+
+        let path = temp_dir.path().join("org-profile.yaml");
+        std::fs::write(&path, PROFILE.replace("age1teamkey", "age1attacker")).unwrap();
+        std::fs::write(
+            temp_dir.path().join("org-profile.yaml.minisig"),
+            sign(&key, PROFILE.as_bytes()),
+        )
+        .unwrap();
+
+        let result = fetch_profile(&path.to_string_lossy(), &public_key);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_fetch_profile_requires_signature() {
+        let temp_dir = TempDir::new().unwrap();
+        let key = signing_key(11);
+        let public_key = PublicKey::parse(&public_key_line(&key)).unwrap();
+
+        let path = temp_dir.path().join("org-profile.yaml");
+        std::fs::write(&path, PROFILE).unwrap();
+
+        let result = fetch_profile(&path.to_string_lossy(), &public_key);
+        assert!(result.is_err());
+        assert!(format!("{:#}", result.unwrap_err()).contains("signature"));
+    }
+}
@@ -0,0 +1,459 @@
+//! Machine-to-machine provisioning (`shadow-secret provision`).
+//!
+//! Setting up a new laptop means moving the age key, which should never
+//! transit through a cloud drive or a chat. Instead, the old machine runs
+//! `provision --send`, which listens on a TCP port and shows a short code
+//! (`7-lantern-orbit`); the new machine runs `provision --from old-host`
+//! and types the code.
+//!
+//! The code is never sent. Both sides run SPAKE2 over ristretto255 with it
+//! (as magic-wormhole does), which gives them a shared key only if they
+//! typed the same code: an eavesdropper learns nothing and an active
+//! attacker gets a single guess, after which the sender stops. Each side
+//! proves it holds the key before the sender encrypts the [`Bundle`]
+//! (ChaCha20-Poly1305):
+//!
+//! - the master age key
+//! - the global config directory: `global.yaml` (with the registry of
+//!   projects added by `init-project`), `.sops.yaml` and `global.enc.env`
+//!
+//! The receiver writes the key and the files where it would have created
+//! them itself, replacing the old home directory in their paths.
+
+use anyhow::{Context, Result};
+use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
+use curve25519_dalek::scalar::Scalar;
+use ring::aead::{self, Aad, LessSafeKey, Nonce, UnboundKey};
+use ring::hmac;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256, Sha512};
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Port `provision --send` listens on by default.
+pub const DEFAULT_PORT: u16 = 7425;
+
+/// Files of the global config directory sent with the key.
+pub const GLOBAL_FILES: &[&str] = &["global.yaml", ".sops.yaml", "global.enc.env"];
+
+/// Name of the age key in a [`Bundle`].
+pub const AGE_KEY: &str = "age-key";
+
+/// Protocol identifier, sent first and bound into the session key.
+const PROTOCOL: &[u8] = b"shadow-secret-provision-v1";
+
+/// Largest frame accepted (the bundle holds a few small text files).
+const MAX_FRAME: usize = 16 * 1024 * 1024;
+
+/// Words of the codes: a digit and two of these 64 words make 40,960 codes
+/// (about 15 bits), plenty against an attacker allowed a single guess.
+const WORDS: &[&str] = &[
+    "acorn", "amber", "anchor", "apple", "arrow", "aspen", "badger", "banjo", "basil", "beacon", "birch", "bison",
+    "bolt", "breeze", "brook", "cactus", "canyon", "cedar", "cobalt", "comet", "coral", "cosmos", "crane", "dune",
+    "ember", "falcon", "fern", "fjord", "flint", "galaxy", "garnet", "glacier", "granite", "harbor", "hazel",
+    "heron", "island", "jasper", "juniper", "kestrel", "lagoon", "lantern", "lemon", "lichen", "lotus", "maple",
+    "meadow", "mesa", "nectar", "nutmeg", "oasis", "orbit", "otter", "pebble", "pepper", "pine", "quartz", "raven",
+    "river", "saffron", "summit", "thistle", "tundra", "walnut",
+];
+
+/// What the old machine sends: file contents by name ([`AGE_KEY`] or one of
+/// [`GLOBAL_FILES`]).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Bundle {
+    /// Home directory of the sender, replaced in the received files
+    pub home: String,
+    pub files: BTreeMap<String, String>,
+}
+
+/// Read the bundle to send: the age key at `age_key_path` and the
+/// [`GLOBAL_FILES`] present in `global_dir`.
+///
+/// # Errors
+///
+/// Fails if there is no age key to send.
+pub fn collect(age_key_path: &Path, global_dir: &Path, home: &Path) -> Result<Bundle> {
+    let mut files = BTreeMap::new();
+    let key = std::fs::read_to_string(age_key_path)
+        .with_context(|| format!("No age key to send at {:?} (run 'shadow-secret init-global' first)", age_key_path))?;
+    files.insert(AGE_KEY.to_string(), key);
+
+    for name in GLOBAL_FILES {
+        let path = global_dir.join(name);
+        if path.exists() {
+            let content = std::fs::read_to_string(&path).with_context(|| format!("Failed to read {:?}", path))?;
+            files.insert(name.to_string(), content);
+        }
+    }
+
+    Ok(Bundle {
+        home: home.to_string_lossy().to_string(),
+        files,
+    })
+}
+
+/// Where `install` writes each file of `bundle`.
+pub fn destinations(bundle: &Bundle, age_key_path: &Path, global_dir: &Path) -> Vec<(String, PathBuf)> {
+    bundle
+        .files
+        .keys()
+        .filter_map(|name| {
+            let path = if name == AGE_KEY {
+                age_key_path.to_path_buf()
+            } else if GLOBAL_FILES.contains(&name.as_str()) {
+                global_dir.join(name)
+            } else {
+                return None;
+            };
+            Some((name.clone(), path))
+        })
+        .collect()
+}
+
+/// Write `bundle` on this machine: the age key to `age_key_path`, the
+/// global files to `global_dir`, with the sender's home replaced by `home`.
+/// Files are written atomically and private (`0600`), including files
+/// replaced with `force`.
+///
+/// # Returns
+///
+/// The paths written.
+///
+/// # Errors
+///
+/// Fails without writing anything if a destination already exists with a
+/// different content, unless `force` is set.
+pub fn install(bundle: &Bundle, age_key_path: &Path, global_dir: &Path, home: &Path, force: bool) -> Result<Vec<PathBuf>> {
+    let home = home.to_string_lossy();
+    let planned: Vec<(PathBuf, String)> = destinations(bundle, age_key_path, global_dir)
+        .into_iter()
+        .map(|(name, path)| {
+            let content = &bundle.files[&name];
+            let content = if name == AGE_KEY || bundle.home.is_empty() {
+                content.clone()
+            } else {
+                content.replace(&bundle.home, &home)
+            };
+            (path, content)
+        })
+        .collect();
+
+    if !force {
+        let conflicts: Vec<String> = planned
+            .iter()
+            .filter(|(path, content)| std::fs::read_to_string(path).is_ok_and(|existing| existing != *content))
+            .map(|(path, _)| path.display().to_string())
+            .collect();
+        if !conflicts.is_empty() {
+            anyhow::bail!(
+                "Refusing to overwrite existing files (use --force): {}",
+                conflicts.join(", ")
+            );
+        }
+    }
+
+    let mut written = Vec::new();
+    for (path, content) in planned {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).with_context(|| format!("Failed to create directory: {:?}", parent))?;
+        }
+
+        // write_atomic keeps the mode of a replaced file: make it private
+        // first, so the key is never readable by others
+        #[cfg(unix)]
+        if path.exists() {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))
+                .with_context(|| format!("Failed to set permissions of {:?}", path))?;
+        }
+        crate::injector::write_atomic(&path, content.as_bytes())
+            .with_context(|| format!("Failed to write {:?}", path))?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+/// A fresh code, e.g. `7-lantern-orbit`.
+pub fn generate_code() -> Result<String> {
+    Ok(format!(
+        "{}-{}-{}",
+        random_below(10)?,
+        WORDS[random_below(WORDS.len())?],
+        WORDS[random_below(WORDS.len())?]
+    ))
+}
+
+/// A uniformly random number below `bound` (at most 256): bytes past the
+/// last multiple of `bound` are drawn again, so the modulo favors nothing.
+fn random_below(bound: usize) -> Result<usize> {
+    let limit = 256 - 256 % bound;
+    loop {
+        let mut byte = [0u8; 1];
+        getrandom::getrandom(&mut byte).context("Failed to generate a provisioning code")?;
+        if usize::from(byte[0]) < limit {
+            return Ok(usize::from(byte[0]) % bound);
+        }
+    }
+}
+
+/// `host` or `host:port` of `--from`.
+pub fn parse_address(from: &str) -> Result<(String, u16)> {
+    match from.rsplit_once(':') {
+        // An IPv6 address without port has several colons
+        Some((host, port)) if !host.contains(':') || host.ends_with(']') => {
+            let port = port.parse().with_context(|| format!("Invalid port in '{}'", from))?;
+            Ok((host.trim_matches(|c| c == '[' || c == ']').to_string(), port))
+        }
+        _ => Ok((from.to_string(), DEFAULT_PORT)),
+    }
+}
+
+/// Side of the SPAKE2 exchange.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Side {
+    /// The old machine
+    Sender,
+    /// The new machine
+    Receiver,
+}
+
+/// One side of a SPAKE2 exchange, before the other side's message.
+struct Spake2 {
+    side: Side,
+    scalar: Scalar,
+    password: Scalar,
+    message: [u8; 32],
+}
+
+/// Fixed points blinding each side's message, derived from public labels
+/// so nobody knows their discrete logarithm.
+fn blinding_point(side: Side) -> RistrettoPoint {
+    let label: &[u8] = match side {
+        Side::Sender => b"shadow-secret-provision M",
+        Side::Receiver => b"shadow-secret-provision N",
+    };
+    RistrettoPoint::hash_from_bytes::<Sha512>(label)
+}
+
+/// Codes are compared case-insensitively, surrounding spaces ignored.
+fn normalize_code(code: &str) -> String {
+    code.trim().to_lowercase()
+}
+
+impl Spake2 {
+    fn start(side: Side, code: &str) -> Result<Self> {
+        let mut random = [0u8; 64];
+        getrandom::getrandom(&mut random).context("Failed to generate a key exchange secret")?;
+        let scalar = Scalar::from_bytes_mod_order_wide(&random);
+        let password = Scalar::hash_from_bytes::<Sha512>(normalize_code(code).as_bytes());
+
+        let point = RistrettoPoint::mul_base(&scalar) + password * blinding_point(side);
+        Ok(Self {
+            side,
+            scalar,
+            password,
+            message: point.compress().to_bytes(),
+        })
+    }
+
+    /// The session key, from the other side's message.
+    fn finish(self, theirs: &[u8]) -> Result<[u8; 32]> {
+        let theirs: [u8; 32] = theirs.try_into().ok().context("Invalid key exchange message")?;
+        let point = CompressedRistretto(theirs)
+            .decompress()
+            .context("Invalid key exchange message")?;
+        let other = match self.side {
+            Side::Sender => Side::Receiver,
+            Side::Receiver => Side::Sender,
+        };
+        let shared = self.scalar * (point - self.password * blinding_point(other));
+
+        let (sender, receiver) = match self.side {
+            Side::Sender => (self.message, theirs),
+            Side::Receiver => (theirs, self.message),
+        };
+        let mut hasher = Sha256::new();
+        for part in [PROTOCOL, &sender, &receiver, shared.compress().as_bytes(), self.password.as_bytes()] {
+            hasher.update((part.len() as u32).to_be_bytes());
+            hasher.update(part);
+        }
+        Ok(hasher.finalize().into())
+    }
+}
+
+/// Key for `purpose`, derived from the session key.
+fn subkey(session: &[u8; 32], purpose: &str) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(session);
+    hasher.update(purpose.as_bytes());
+    hasher.finalize().into()
+}
+
+/// Proof that `side` holds the session key.
+fn confirmation(session: &[u8; 32], side: Side) -> hmac::Tag {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, &subkey(session, "confirmation"));
+    hmac::sign(&key, format!("{:?}", side).as_bytes())
+}
+
+fn verify_confirmation(session: &[u8; 32], side: Side, tag: &[u8]) -> Result<()> {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, &subkey(session, "confirmation"));
+    hmac::verify(&key, format!("{:?}", side).as_bytes(), tag)
+        .map_err(|_| anyhow::anyhow!("Key exchange failed: the codes do not match"))
+}
+
+/// Encryption key of the bundle (used once, so the nonce is fixed).
+fn bundle_key(session: &[u8; 32]) -> Result<LessSafeKey> {
+    let key = UnboundKey::new(&aead::CHACHA20_POLY1305, &subkey(session, "bundle"))
+        .map_err(|_| anyhow::anyhow!("Failed to create the bundle key"))?;
+    Ok(LessSafeKey::new(key))
+}
+
+fn write_frame(stream: &mut impl Write, frame: &[u8]) -> Result<()> {
+    stream.write_all(&(frame.len() as u32).to_be_bytes())?;
+    stream.write_all(frame)?;
+    stream.flush()?;
+    Ok(())
+}
+
+fn read_frame(stream: &mut impl Read) -> Result<Vec<u8>> {
+    let mut length = [0u8; 4];
+    stream.read_exact(&mut length).context("Connection closed during provisioning")?;
+    let length = u32::from_be_bytes(length) as usize;
+    if length > MAX_FRAME {
+        anyhow::bail!("Provisioning message too large ({} bytes)", length);
+    }
+    let mut frame = vec![0u8; length];
+    stream.read_exact(&mut frame).context("Connection closed during provisioning")?;
+    Ok(frame)
+}
+
+/// Old machine: authenticate the peer with `code`, then send `bundle`.
+///
+/// # Errors
+///
+/// Fails if the peer does not know the code; nothing is sent then.
+pub fn send<S: Read + Write>(stream: &mut S, code: &str, bundle: &Bundle) -> Result<()> {
+    if read_frame(stream)? != PROTOCOL {
+        anyhow::bail!("Peer does not speak the provisioning protocol");
+    }
+    let theirs = read_frame(stream)?;
+    let spake = Spake2::start(Side::Sender, code)?;
+    write_frame(stream, &spake.message)?;
+    let session = spake.finish(&theirs)?;
+
+    write_frame(stream, confirmation(&session, Side::Sender).as_ref())?;
+    verify_confirmation(&session, Side::Receiver, &read_frame(stream)?)?;
+
+    let mut sealed = serde_json::to_vec(bundle)?;
+    bundle_key(&session)?
+        .seal_in_place_append_tag(Nonce::assume_unique_for_key([0; 12]), Aad::from(PROTOCOL), &mut sealed)
+        .map_err(|_| anyhow::anyhow!("Failed to encrypt the bundle"))?;
+    write_frame(stream, &sealed)
+}
+
+/// New machine: authenticate the peer with `code`, then receive its
+/// bundle.
+///
+/// # Errors
+///
+/// Fails if the peer does not know the code or the bundle was tampered
+/// with.
+pub fn receive<S: Read + Write>(stream: &mut S, code: &str) -> Result<Bundle> {
+    let spake = Spake2::start(Side::Receiver, code)?;
+    write_frame(stream, PROTOCOL)?;
+    write_frame(stream, &spake.message)?;
+    let theirs = read_frame(stream)?;
+    let session = spake.finish(&theirs)?;
+
+    verify_confirmation(&session, Side::Sender, &read_frame(stream)?)?;
+    write_frame(stream, confirmation(&session, Side::Receiver).as_ref())?;
+
+    let mut sealed = read_frame(stream)?;
+    let plaintext = bundle_key(&session)?
+        .open_in_place(Nonce::assume_unique_for_key([0; 12]), Aad::from(PROTOCOL), &mut sealed)
+        .map_err(|_| anyhow::anyhow!("The bundle failed authentication"))?;
+    serde_json::from_slice(plaintext).context("Failed to parse the received bundle")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{TcpListener, TcpStream};
+
+    fn bundle() -> Bundle {
+        Bundle {
+            home: "/home/old".to_string(),
+            files: BTreeMap::from([
+                (AGE_KEY.to_string(), "AGE-SECRET-KEY-1TEST\n".to_string()),
+                ("global.yaml".to_string(), "targets:\n  - path: /home/old/app/.env\n".to_string()),
+            ]),
+        }
+    }
+
+    /// Run `send` with `send_code` and `receive` with `receive_code` over
+    /// a local connection.
+    fn exchange(send_code: &str, receive_code: &str) -> (Result<()>, Result<Bundle>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let send_code = send_code.to_string();
+        let sender = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            send(&mut stream, &send_code, &bundle())
+        });
+
+        let mut stream = TcpStream::connect(address).unwrap();
+        let received = receive(&mut stream, receive_code);
+        drop(stream);
+        (sender.join().unwrap(), received)
+    }
+
+    #[test]
+    fn test_exchange_requires_the_same_code() {
+        let (sent, received) = exchange("7-lantern-orbit", " 7-Lantern-Orbit ");
+        sent.unwrap();
+        assert_eq!(received.unwrap(), bundle());
+
+        let (sent, received) = exchange("7-lantern-orbit", "7-lantern-otter");
+        assert!(sent.is_err());
+        assert!(received.unwrap_err().to_string().contains("codes do not match"));
+
+        let code = generate_code().unwrap();
+        assert_eq!(code.split('-').count(), 3);
+        assert_eq!(parse_address("old-laptop").unwrap(), ("old-laptop".to_string(), DEFAULT_PORT));
+        assert_eq!(parse_address("10.0.0.2:9000").unwrap(), ("10.0.0.2".to_string(), 9000));
+        assert_eq!(parse_address("[::1]:9000").unwrap(), ("::1".to_string(), 9000));
+    }
+
+    #[test]
+    fn test_install_rewrites_home_and_refuses_overwrites() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let key_path = temp_dir.path().join("keys.txt");
+        let global_dir = temp_dir.path().join("global");
+        let home = Path::new("/home/new");
+
+        let written = install(&bundle(), &key_path, &global_dir, home, false).unwrap();
+        assert_eq!(written.len(), 2);
+        assert_eq!(std::fs::read_to_string(&key_path).unwrap(), "AGE-SECRET-KEY-1TEST\n");
+        assert!(std::fs::read_to_string(global_dir.join("global.yaml")).unwrap().contains("/home/new/app/.env"));
+
+        // Same content again is fine; a different key is not
+        install(&bundle(), &key_path, &global_dir, home, false).unwrap();
+        std::fs::write(&key_path, "AGE-SECRET-KEY-1OTHER\n").unwrap();
+        assert!(install(&bundle(), &key_path, &global_dir, home, false).is_err());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&key_path, std::fs::Permissions::from_mode(0o644)).unwrap();
+        }
+        install(&bundle(), &key_path, &global_dir, home, true).unwrap();
+        assert_eq!(std::fs::read_to_string(&key_path).unwrap(), "AGE-SECRET-KEY-1TEST\n");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(std::fs::metadata(&key_path).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+
+        let collected = collect(&key_path, &global_dir, Path::new("/home/new")).unwrap();
+        assert_eq!(collected.files.len(), 2);
+    }
+}
@@ -0,0 +1,268 @@
+//! Fleet-wide age key rotation (`shadow-secret rekey`).
+//!
+//! The projects registered in `global.yaml` (`init-project` adds each one as
+//! a `$ALL` target pointing at the project directory), and the global
+//! directory itself, are re-keyed one after the other: the new recipient is
+//! added to every creation rule of their `.sops.yaml` (and an old one
+//! removed with `--remove`), then `sops updatekeys` re-encrypts the data key
+//! of each vault file for the updated recipients. Secret values are never
+//! decrypted to disk.
+//!
+//! A project that fails keeps its original `.sops.yaml` and does not stop
+//! the others; [`rekey_all`] reports the outcome of each.
+
+use crate::config::Config;
+use crate::process;
+use crate::sops::SopsError;
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+/// Outcome of re-keying one project.
+#[derive(Debug)]
+pub struct RekeyOutcome {
+    pub project: PathBuf,
+    /// Vault files re-encrypted, or why the project failed
+    pub result: Result<Vec<PathBuf>>,
+}
+
+/// Directories to re-key: `global_dir` (if it has a `.sops.yaml`), then the
+/// project directories listed as targets of its `global.yaml`.
+pub fn registered_projects(global_dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut projects = Vec::new();
+    if global_dir.join(".sops.yaml").is_file() {
+        projects.push(global_dir.to_path_buf());
+    }
+
+    let global_yaml = global_dir.join("global.yaml");
+    if !global_yaml.exists() {
+        return Ok(projects);
+    }
+    let content = std::fs::read_to_string(&global_yaml)
+        .with_context(|| format!("Failed to read global config: {:?}", global_yaml))?;
+    let config: serde_yaml::Value = serde_yaml::from_str(&content)
+        .with_context(|| format!("Failed to parse global config: {:?}", global_yaml))?;
+
+    let targets = config["targets"].as_sequence().cloned().unwrap_or_default();
+    for target in targets {
+        // Relative target paths are relative to the global directory
+        let Some(path) = target["path"].as_str().map(|path| global_dir.join(path)) else {
+            continue;
+        };
+        if path.is_dir() && !projects.contains(&path) {
+            projects.push(path);
+        }
+    }
+
+    Ok(projects)
+}
+
+/// Add `recipient` to the `age:` recipients of every creation rule of a
+/// `.sops.yaml`, and remove `remove`. Comments and layout are kept.
+///
+/// # Errors
+///
+/// Fails if no rule lists its age recipients inline (`age: "age1...,age1..."`).
+pub fn update_recipients(content: &str, recipient: &str, remove: Option<&str>) -> Result<String> {
+    static AGE_LINE: std::sync::OnceLock<regex::Regex> = std::sync::OnceLock::new();
+    let age_line = AGE_LINE.get_or_init(|| {
+        regex::Regex::new(r#"^(\s*(?:-\s+)?age:\s*)(["']?)([^"'#]*?)(["']?)(\s*(?:#.*)?)$"#).expect("valid regex")
+    });
+
+    let mut updated = String::with_capacity(content.len() + recipient.len());
+    let mut rules = 0;
+    for line in content.split_inclusive('\n') {
+        let (text, ending) = match line.strip_suffix('\n') {
+            Some(text) => (text, "\n"),
+            None => (line, ""),
+        };
+
+        match age_line.captures(text) {
+            Some(captures) if !captures[3].trim().is_empty() => {
+                let mut recipients: Vec<&str> = captures[3]
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|r| !r.is_empty() && Some(*r) != remove)
+                    .collect();
+                if !recipients.contains(&recipient) {
+                    recipients.push(recipient);
+                }
+
+                updated.push_str(&format!(
+                    "{}{}{}{}{}{}",
+                    &captures[1],
+                    &captures[2],
+                    recipients.join(","),
+                    &captures[4],
+                    &captures[5],
+                    ending
+                ));
+                rules += 1;
+            }
+            _ => updated.push_str(line),
+        }
+    }
+
+    if rules == 0 {
+        anyhow::bail!("No creation rule lists age recipients inline (age: \"age1...\")");
+    }
+    Ok(updated)
+}
+
+/// Vault files of the project in `project_dir` and the age key used to
+/// decrypt them, from its `project.yaml` or `global.yaml` (else `.enc.env`).
+fn project_vaults(project_dir: &Path) -> Result<(Vec<PathBuf>, Option<String>)> {
+    let config_path = ["project.yaml", "global.yaml"]
+        .iter()
+        .map(|name| project_dir.join(name))
+        .find(|path| path.is_file());
+
+    let Some(config_path) = config_path else {
+        let enc_env = project_dir.join(".enc.env");
+        if !enc_env.is_file() {
+            anyhow::bail!("No project.yaml, global.yaml or .enc.env found");
+        }
+        return Ok((vec![enc_env], None));
+    };
+
+    let config = Config::from_file(&config_path)
+        .with_context(|| format!("Failed to load config from: {:?}", config_path))?;
+    let vaults = config
+        .vault_source_paths(project_dir)?
+        .into_iter()
+        .filter(|path| path.is_file())
+        .collect();
+    Ok((vaults, config.vault.age_key_path))
+}
+
+/// Re-key the project in `project_dir`: update its `.sops.yaml`, then run
+/// `sops updatekeys` on each of its vault files.
+///
+/// # Returns
+///
+/// The vault files re-encrypted.
+///
+/// # Errors
+///
+/// The original `.sops.yaml` is put back if any vault file fails.
+pub fn rekey_project(project_dir: &Path, recipient: &str, remove: Option<&str>) -> Result<Vec<PathBuf>> {
+    let sops_config = project_dir.join(".sops.yaml");
+    let original = std::fs::read_to_string(&sops_config)
+        .with_context(|| format!("Failed to read {:?}", sops_config))?;
+    let updated = update_recipients(&original, recipient, remove)
+        .with_context(|| format!("Cannot update {:?}", sops_config))?;
+    let (vaults, age_key_path) = project_vaults(project_dir)?;
+
+    std::fs::write(&sops_config, &updated).with_context(|| format!("Failed to write {:?}", sops_config))?;
+
+    for vault in &vaults {
+        if let Err(e) = update_keys(project_dir, vault, age_key_path.as_deref()) {
+            std::fs::write(&sops_config, &original)
+                .with_context(|| format!("Failed to restore {:?}", sops_config))?;
+            return Err(e);
+        }
+    }
+
+    Ok(vaults)
+}
+
+/// Re-key every project of `projects`, continuing past failures.
+pub fn rekey_all(projects: &[PathBuf], recipient: &str, remove: Option<&str>) -> Result<Vec<RekeyOutcome>> {
+    recipient
+        .parse::<age::x25519::Recipient>()
+        .map_err(|e| anyhow::anyhow!("Invalid age recipient '{}': {}", recipient, e))?;
+
+    Ok(projects
+        .iter()
+        .map(|project| RekeyOutcome {
+            project: project.clone(),
+            result: rekey_project(project, recipient, remove),
+        })
+        .collect())
+}
+
+/// `sops updatekeys` on `vault`, run from `project_dir` so that SOPS picks
+/// up the project's `.sops.yaml`.
+fn update_keys(project_dir: &Path, vault: &Path, age_key_path: Option<&str>) -> Result<()> {
+    let mut command = process::command("sops")?;
+    command
+        .arg("updatekeys")
+        .arg("--yes")
+        .arg(vault)
+        .current_dir(project_dir);
+    if let Some(key_path) = age_key_path {
+        command.env("SOPS_AGE_KEY_FILE", key_path);
+    }
+
+    let output = process::output(&mut command)
+        .with_context(|| format!("Failed to execute 'sops updatekeys' on {:?}", vault))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(SopsError::classify(&vault.to_string_lossy(), &stderr).into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockRunner;
+    use std::sync::Arc;
+
+    const SOPS_CONFIG: &str = "creation_rules:\n  - path_regex: .*\\.enc\\.env$\n    age: \"age1old,age1team\" # Age public key(s) for encryption\n";
+
+    #[test]
+    fn test_update_recipients() {
+        let updated = update_recipients(SOPS_CONFIG, "age1new", Some("age1old")).unwrap();
+        assert_eq!(
+            updated,
+            "creation_rules:\n  - path_regex: .*\\.enc\\.env$\n    age: \"age1team,age1new\" # Age public key(s) for encryption\n"
+        );
+
+        // Idempotent
+        assert_eq!(update_recipients(&updated, "age1new", None).unwrap(), updated);
+        assert!(update_recipients("creation_rules: []\n", "age1new", None).is_err());
+    }
+
+    #[test]
+    fn test_rekey_project_restores_sops_config_on_failure() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let project = temp_dir.path();
+        std::fs::write(project.join(".sops.yaml"), SOPS_CONFIG).unwrap();
+        std::fs::write(project.join(".enc.env"), "API_KEY=ENC[...]\n").unwrap();
+
+        let vault = project.join(".enc.env");
+        let vault_arg = vault.to_string_lossy().to_string();
+        let runner = Arc::new(MockRunner::new().respond("sops", &["updatekeys", "--yes", &vault_arg], 0, ""));
+        let rekeyed = process::with_runner(runner.clone(), || rekey_project(project, "age1new", None)).unwrap();
+        assert_eq!(rekeyed, vec![vault.clone()]);
+        assert!(std::fs::read_to_string(project.join(".sops.yaml"))
+            .unwrap()
+            .contains("age1old,age1team,age1new"));
+        assert_eq!(runner.calls().len(), 1);
+
+        std::fs::write(project.join(".sops.yaml"), SOPS_CONFIG).unwrap();
+        let failing = Arc::new(MockRunner::new().respond("sops", &["updatekeys", "--yes", &vault_arg], 1, ""));
+        assert!(process::with_runner(failing, || rekey_project(project, "age1new", None)).is_err());
+        assert_eq!(std::fs::read_to_string(project.join(".sops.yaml")).unwrap(), SOPS_CONFIG);
+    }
+
+    #[test]
+    fn test_registered_projects() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let global = temp_dir.path().join("global");
+        let project = temp_dir.path().join("app");
+        std::fs::create_dir_all(&global).unwrap();
+        std::fs::create_dir_all(&project).unwrap();
+        std::fs::write(global.join(".sops.yaml"), SOPS_CONFIG).unwrap();
+        std::fs::write(
+            global.join("global.yaml"),
+            format!(
+                "vault: {{source: global.enc.env, engine: sops}}\ntargets:\n  - {{name: example, path: example-config.json, placeholders: [$ALL]}}\n  - {{name: app, path: {:?}, placeholders: [$ALL]}}\n",
+                project
+            ),
+        )
+        .unwrap();
+
+        assert_eq!(registered_projects(&global).unwrap(), vec![global.clone(), project]);
+    }
+}
@@ -0,0 +1,190 @@
+//! Remote unlock over SSH (`remote unlock` / `remote receive`).
+//!
+//! The vault is decrypted on the local machine; the private key never
+//! leaves it. Only the values the targets need are sent, as one JSON line,
+//! to `shadow-secret remote receive` started on the remote host over SSH.
+//! The receiver injects them into the remote project's targets and restores
+//! the templates when its stdin closes: when the local user locks, or when
+//! the SSH connection drops.
+
+use crate::config::{Config, TargetConfig};
+use crate::vault::Vault;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+
+/// What the local side sends to `remote receive`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RemotePayload {
+    /// Targets, with paths relative to the remote project
+    pub targets: Vec<TargetConfig>,
+    /// Values of the keys trusted targets reference (untrusted targets get
+    /// fake values generated remotely)
+    pub secrets: HashMap<String, String>,
+}
+
+/// Build the payload for `config`'s targets: only referenced keys (every
+/// allowed key for `$ALL` targets) are included.
+pub fn payload(config: &Config, vault: &Vault) -> RemotePayload {
+    let trusted = config.targets.iter().filter(|target| !target.untrusted);
+
+    let mut secrets = HashMap::new();
+    for target in trusted {
+        let resolved = vault.secrets_for(target);
+        let keys: Vec<&str> = target
+            .placeholders
+            .iter()
+            .map(|placeholder| crate::injector::extract_key_name(placeholder))
+            .collect();
+        let all = target.placeholders.iter().any(|p| crate::injector::is_all_placeholder(p));
+        for (key, value) in resolved {
+            if all || keys.contains(&key.as_str()) {
+                secrets.insert(key, value);
+            }
+        }
+    }
+
+    RemotePayload {
+        targets: config.targets.clone(),
+        secrets,
+    }
+}
+
+/// Arguments of the `ssh` invocation starting the receiver on `host`.
+///
+/// The remote command goes through the remote user's shell, so the project
+/// path is quoted.
+pub fn ssh_args(host: &str, project: &str, remote_bin: &str) -> Vec<String> {
+    vec![
+        "-T".to_string(),
+        "--".to_string(),
+        host.to_string(),
+        format!(
+            "{} remote receive --project {}",
+            crate::explain::shell_quote(remote_bin),
+            crate::explain::shell_quote(project)
+        ),
+    ]
+}
+
+/// Read the payload (first line of `input`).
+pub fn read_payload(input: &mut impl BufRead) -> Result<RemotePayload> {
+    let mut line = String::new();
+    input
+        .read_line(&mut line)
+        .context("Failed to read remote unlock payload")?;
+    if line.trim().is_empty() {
+        anyhow::bail!("No remote unlock payload received (is the sender a shadow-secret 'remote unlock'?)");
+    }
+    serde_json::from_str(&line).context("Invalid remote unlock payload")
+}
+
+/// Resolve `targets` inside `project_dir`, refusing any that escape it.
+///
+/// `allow_outside_project` is ignored: the receiving side never lets a
+/// sender write outside the project it named.
+pub fn resolve_targets(targets: &[TargetConfig], project_dir: &Path) -> Result<Vec<TargetConfig>> {
+    let root = project_dir
+        .canonicalize()
+        .with_context(|| format!("Remote project directory not found: {:?}", project_dir))?;
+
+    targets
+        .iter()
+        .map(|target| {
+            let path = root.join(&target.path);
+            let resolved = resolve_existing(&path)
+                .with_context(|| format!("Failed to resolve path of target '{}'", target.name))?;
+            if !resolved.starts_with(&root) {
+                anyhow::bail!(
+                    "Target '{}' resolves outside the remote project: {:?}",
+                    target.name,
+                    resolved
+                );
+            }
+            Ok(TargetConfig {
+                path: resolved.to_string_lossy().to_string(),
+                allow_outside_project: false,
+                ..target.clone()
+            })
+        })
+        .collect()
+}
+
+/// Canonicalize `path`, or its parent for files that do not exist yet.
+fn resolve_existing(path: &Path) -> Result<PathBuf> {
+    if let Ok(resolved) = path.canonicalize() {
+        return Ok(resolved);
+    }
+    let parent = path.parent().context("Target path has no parent directory")?;
+    let name = path.file_name().context("Target path has no file name")?;
+    Ok(parent.canonicalize()?.join(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(name: &str, path: &str, placeholders: &[&str], untrusted: bool) -> TargetConfig {
+        TargetConfig {
+            name: name.to_string(),
+            path: path.to_string(),
+            placeholders: placeholders.iter().map(|p| p.to_string()).collect(),
+            untrusted,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_payload_sends_only_referenced_keys() {
+        let config: Config = serde_yaml::from_str(
+            "vault: {source: v.enc.env, engine: sops}\ntargets: []\n",
+        )
+        .unwrap();
+        let config = Config {
+            targets: vec![
+                target("app", ".env", &["$API_KEY"], false),
+                target("demo", "demo.env", &["$DB_URL"], true),
+            ],
+            ..config
+        };
+        let vault = Vault::new(HashMap::from([
+            ("API_KEY".to_string(), "sk_test_123".to_string()),
+            ("DB_URL".to_string(), "postgres://prod".to_string()),
+            ("UNUSED".to_string(), "x".to_string()),
+        ]));
+
+        let payload = payload(&config, &vault);
+
+        assert_eq!(payload.secrets.len(), 1);
+        assert_eq!(payload.secrets["API_KEY"], "sk_test_123");
+        assert_eq!(payload.targets.len(), 2);
+
+        let line = format!("{}\n", serde_json::to_string(&payload).unwrap());
+        assert_eq!(read_payload(&mut line.as_bytes()).unwrap(), payload);
+    }
+
+    #[test]
+    fn test_resolve_targets_stays_in_project() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join(".env"), "KEY=$KEY\n").unwrap();
+
+        let resolved = resolve_targets(&[target("app", ".env", &["$KEY"], false)], temp_dir.path()).unwrap();
+        assert!(Path::new(&resolved[0].path).is_absolute());
+
+        let escape = TargetConfig {
+            allow_outside_project: true,
+            ..target("escape", "../outside.env", &["$KEY"], false)
+        };
+        assert!(resolve_targets(&[escape], temp_dir.path()).is_err());
+    }
+
+    #[test]
+    fn test_ssh_args_quote_remote_command() {
+        assert_eq!(
+            ssh_args("deploy@build-1", "/srv/my app", "shadow-secret").last().unwrap(),
+            "shadow-secret remote receive --project '/srv/my app'"
+        );
+    }
+}
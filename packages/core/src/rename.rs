@@ -0,0 +1,238 @@
+//! Renaming a secret everywhere it is referenced.
+//!
+//! `shadow-secret vault rename-key OLD NEW` renames the key in every vault
+//! file that defines it, rewrites `$OLD` / `${OLD}` placeholders in the
+//! config and in target files, and can rename the cloud variable.
+//!
+//! The new name must follow the config's naming rules
+//! ([`crate::naming`]).
+//!
+//! All local changes are computed up front ([`plan_rename`]) so they can be
+//! shown as a dry run, then applied together ([`apply_rename`]): if any
+//! step fails, the steps already done are rolled back.
+
+use crate::config::Config;
+use crate::vault;
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::path::{Path, PathBuf};
+
+/// A text file rewritten by the rename.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileChange {
+    pub path: PathBuf,
+    /// Number of placeholders rewritten
+    pub occurrences: usize,
+    original: String,
+    updated: String,
+}
+
+/// Every local change needed to rename a key.
+#[derive(Debug, Clone)]
+pub struct RenamePlan {
+    pub old_key: String,
+    pub new_key: String,
+    /// Vault files defining `old_key`
+    pub vault_files: Vec<PathBuf>,
+    /// Config and target files referencing `old_key`
+    pub file_changes: Vec<FileChange>,
+    /// Target files that do not exist (nothing to rewrite)
+    pub missing_targets: Vec<PathBuf>,
+}
+
+/// Whether `key` is a valid secret name (`[A-Za-z_][A-Za-z0-9_]*`).
+pub fn is_valid_key(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Rewrite `$OLD` and `${OLD}` placeholders (but not `$OLD_SUFFIX`).
+///
+/// # Returns
+///
+/// The rewritten content and the number of placeholders replaced.
+pub fn rename_placeholders(content: &str, old_key: &str, new_key: &str) -> (String, usize) {
+    let escaped = regex::escape(old_key);
+    let pattern = Regex::new(&format!(r"\$\{{{0}\}}|\${0}\b", escaped))
+        .expect("placeholder pattern is valid");
+
+    let mut count = 0;
+    let updated = pattern.replace_all(content, |captures: &regex::Captures| {
+        count += 1;
+        if captures[0].starts_with("${") {
+            format!("${{{}}}", new_key)
+        } else {
+            format!("${}", new_key)
+        }
+    });
+
+    (updated.into_owned(), count)
+}
+
+/// Compute the changes needed to rename `old_key` to `new_key`.
+///
+/// Decrypts `old_key` / `new_key` in each vault file (one key at a time) to
+/// find where the secret lives.
+pub fn plan_rename(
+    config: &Config,
+    config_path: &Path,
+    config_dir: &Path,
+    old_key: &str,
+    new_key: &str,
+) -> Result<RenamePlan> {
+    if !is_valid_key(new_key) {
+        anyhow::bail!("Invalid key name '{}': use letters, digits and '_'", new_key);
+    }
+    if old_key == new_key {
+        anyhow::bail!("Old and new key names are identical");
+    }
+    if let Some(violation) = crate::naming::Rules::new(&config.naming)?.check(new_key) {
+        anyhow::bail!("Key name breaks the naming rules: {}", violation.message());
+    }
+
+    let age_key_path = config.vault.age_key_path.as_deref();
+    let mut vault_files = Vec::new();
+    for path in config.vault_source_paths(config_dir)? {
+        if vault::extract_key(&path, new_key, age_key_path)?.is_some() {
+            anyhow::bail!("'{}' already exists in {}", new_key, path.display());
+        }
+        if vault::extract_key(&path, old_key, age_key_path)?.is_some() {
+            vault_files.push(path);
+        }
+    }
+    if vault_files.is_empty() {
+        anyhow::bail!("Secret '{}' not found in vault", old_key);
+    }
+
+    let mut paths = vec![config_path.to_path_buf()];
+    let mut missing_targets = Vec::new();
+    for target in &config.targets {
+        let path = config_dir.join(&target.path);
+        if !path.exists() {
+            missing_targets.push(path);
+        } else if !paths.contains(&path) {
+            paths.push(path);
+        }
+    }
+
+    let mut file_changes = Vec::new();
+    for path in paths {
+        let original =
+            std::fs::read_to_string(&path).with_context(|| format!("Failed to read {:?}", path))?;
+        let (updated, occurrences) = rename_placeholders(&original, old_key, new_key);
+        if occurrences > 0 {
+            file_changes.push(FileChange {
+                path,
+                occurrences,
+                original,
+                updated,
+            });
+        }
+    }
+
+    Ok(RenamePlan {
+        old_key: old_key.to_string(),
+        new_key: new_key.to_string(),
+        vault_files,
+        file_changes,
+        missing_targets,
+    })
+}
+
+/// Apply a rename plan, rolling back completed steps on failure.
+///
+/// Order: add `new_key` to each vault file, rewrite files, then remove
+/// `old_key` from the vault files.
+pub fn apply_rename(plan: &RenamePlan, age_key_path: Option<&str>) -> Result<()> {
+    let mut added: Vec<&PathBuf> = Vec::new();
+    let mut written: Vec<&FileChange> = Vec::new();
+
+    let result = (|| -> Result<()> {
+        for path in &plan.vault_files {
+            let value = vault::extract_key(path, &plan.old_key, age_key_path)?
+                .with_context(|| format!("'{}' disappeared from {}", plan.old_key, path.display()))?;
+            vault::set_secret_from_stdin(path, &plan.new_key, &value, age_key_path)?;
+            added.push(path);
+        }
+
+        for change in &plan.file_changes {
+            std::fs::write(&change.path, &change.updated)
+                .with_context(|| format!("Failed to write {:?}", change.path))?;
+            written.push(change);
+        }
+
+        Ok(())
+    })();
+
+    if let Err(e) = result {
+        for change in written {
+            let _ = std::fs::write(&change.path, &change.original);
+        }
+        for path in added {
+            let _ = vault::unset_secret(path, &plan.new_key, age_key_path);
+        }
+        return Err(e.context("Rename failed; changes were rolled back"));
+    }
+
+    for path in &plan.vault_files {
+        vault::unset_secret(path, &plan.old_key, age_key_path).with_context(|| {
+            format!(
+                "'{}' was renamed but the old key could not be removed from {}",
+                plan.new_key,
+                path.display()
+            )
+        })?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rename_placeholders_respects_boundaries() {
+        let content = r#"{"a": "$DB_URL", "b": "${DB_URL}", "c": "$DB_URL_RO", "d": "$XDB_URL"}"#;
+
+        let (updated, count) = rename_placeholders(content, "DB_URL", "DATABASE_URL");
+
+        assert_eq!(count, 2);
+        assert_eq!(
+            updated,
+            r#"{"a": "$DATABASE_URL", "b": "${DATABASE_URL}", "c": "$DB_URL_RO", "d": "$XDB_URL"}"#
+        );
+    }
+
+    #[test]
+    fn test_rename_placeholders_in_config() {
+        let config = "targets:\n  - name: \"api\"\n    placeholders:\n      - \"$API_KEY\" # main key\n";
+
+        let (updated, count) = rename_placeholders(config, "API_KEY", "STRIPE_KEY");
+
+        assert_eq!(count, 1);
+        assert!(updated.contains("- \"$STRIPE_KEY\" # main key"));
+    }
+
+    #[test]
+    fn test_is_valid_key() {
+        assert!(is_valid_key("DATABASE_URL"));
+        assert!(is_valid_key("_private"));
+        assert!(!is_valid_key("1KEY"));
+        assert!(!is_valid_key("API-KEY"));
+        assert!(!is_valid_key(""));
+    }
+
+    #[test]
+    fn test_plan_rename_enforces_naming() {
+        let config: Config = serde_yaml::from_str(
+            "vault: {source: .enc.env, engine: sops}\ntargets: []\nnaming: {case: screaming_snake, prefixes: [APP_]}\n",
+        )
+        .unwrap();
+        let error = plan_rename(&config, Path::new("project.yaml"), Path::new("."), "APP_KEY", "appToken").unwrap_err();
+        assert!(error.to_string().ends_with("(suggested: APP_TOKEN)"), "{}", error);
+    }
+}
@@ -0,0 +1,212 @@
+//! Per-session injection reports.
+//!
+//! After every unlock, a report is written to
+//! `~/.config/shadow-secret/reports/`: for each target, the keys whose
+//! placeholders were replaced, the listed keys left without a value, and how
+//! long injection took. Reports hold key names and paths, never values.
+//! `shadow-secret status --last` prints the latest one; the JSON files are
+//! meant for tooling checking that locked files are clean, or that targets
+//! drifted from the previous session.
+
+use crate::unlock::UnlockReport;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Report directory in the state directory.
+pub const REPORTS_DIR: &str = "reports";
+
+/// Extension of report files.
+const REPORT_EXTENSION: &str = "json";
+
+/// What one unlock injected.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionReport {
+    /// RFC 3339 time the session unlocked
+    pub unlocked_at: String,
+    pub pid: u32,
+    /// Working directory of the session
+    pub cwd: String,
+    pub duration_ms: u64,
+    pub targets: Vec<TargetReport>,
+}
+
+/// What one target received.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TargetReport {
+    pub name: String,
+    pub path: String,
+    /// The file was created from its template
+    pub created: bool,
+    /// The target received fake values only
+    pub untrusted: bool,
+    /// Placeholders configured for the target
+    pub placeholders: usize,
+    /// Keys whose placeholders were replaced, sorted
+    pub replaced: Vec<String>,
+    /// Listed keys whose placeholders were left (no value), sorted
+    pub left: Vec<String>,
+    pub duration_ms: u64,
+}
+
+impl SessionReport {
+    /// Report of an unlock that just happened.
+    pub fn from_unlock(report: &UnlockReport) -> Self {
+        Self {
+            unlocked_at: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            pid: std::process::id(),
+            cwd: std::env::current_dir()
+                .map(|dir| dir.display().to_string())
+                .unwrap_or_default(),
+            duration_ms: report.duration.as_millis() as u64,
+            targets: report
+                .targets
+                .iter()
+                .map(|target| TargetReport {
+                    name: target.name.clone(),
+                    path: target.path.clone(),
+                    created: target.created,
+                    untrusted: target.untrusted,
+                    placeholders: target.placeholders,
+                    replaced: target.replaced.clone(),
+                    left: target.left.clone(),
+                    duration_ms: target.duration.as_millis() as u64,
+                })
+                .collect(),
+        }
+    }
+
+    /// The report for a terminal, one block per target.
+    pub fn render(&self) -> String {
+        let mut out = format!(
+            "Session of {} (pid {}, {} ms)\n   {}\n",
+            self.unlocked_at, self.pid, self.duration_ms, self.cwd
+        );
+        for target in &self.targets {
+            let mut flags = Vec::new();
+            if target.created {
+                flags.push("created");
+            }
+            if target.untrusted {
+                flags.push("untrusted");
+            }
+            let flags = if flags.is_empty() { String::new() } else { format!(" [{}]", flags.join(", ")) };
+            out.push_str(&format!(
+                "\n   {} → {}{} ({} replaced, {} left, {} ms)\n",
+                target.name,
+                target.path,
+                flags,
+                target.replaced.len(),
+                target.left.len(),
+                target.duration_ms
+            ));
+            if !target.replaced.is_empty() {
+                out.push_str(&format!("      ✓ {}\n", target.replaced.join(", ")));
+            }
+            if !target.left.is_empty() {
+                out.push_str(&format!("      ✗ {}\n", target.left.join(", ")));
+            }
+        }
+        out
+    }
+}
+
+/// Default report directory (`~/.config/shadow-secret/reports`).
+pub fn reports_dir() -> Result<PathBuf> {
+    let dir = crate::isolation::state_dir()?.join(REPORTS_DIR);
+    crate::isolation::ensure_private_dir(&dir)?;
+    Ok(dir)
+}
+
+/// Write `report` to `dir` as `session-<time>-<pid>.json`.
+///
+/// # Returns
+///
+/// The path of the report.
+pub fn write(dir: &Path, report: &SessionReport) -> Result<PathBuf> {
+    let path = dir.join(format!(
+        "session-{}-{}.{}",
+        report.unlocked_at.replace([':', '-'], "").trim_end_matches('Z'),
+        report.pid,
+        REPORT_EXTENSION
+    ));
+    std::fs::write(&path, serde_json::to_string_pretty(report)?)
+        .with_context(|| format!("Failed to write report {:?}", path))?;
+    Ok(path)
+}
+
+/// Report files in `dir`, oldest first.
+pub fn list(dir: &Path) -> Result<Vec<PathBuf>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut reports = Vec::new();
+    for entry in std::fs::read_dir(dir).with_context(|| format!("Failed to read {:?}", dir))? {
+        let path = entry?.path();
+        let is_report = path.extension().and_then(|e| e.to_str()) == Some(REPORT_EXTENSION)
+            && path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("session-"));
+        if is_report {
+            reports.push(path);
+        }
+    }
+    reports.sort();
+    Ok(reports)
+}
+
+/// Read the report at `path`.
+pub fn read(path: &Path) -> Result<SessionReport> {
+    let content = std::fs::read_to_string(path).with_context(|| format!("Failed to read report {:?}", path))?;
+    serde_json::from_str(&content).with_context(|| format!("Invalid report {:?}", path))
+}
+
+/// The latest report in `dir`, if any.
+pub fn latest(dir: &Path) -> Result<Option<SessionReport>> {
+    list(dir)?.last().map(|path| read(path)).transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(unlocked_at: &str) -> SessionReport {
+        SessionReport {
+            unlocked_at: unlocked_at.to_string(),
+            pid: 4242,
+            cwd: "/work/app".to_string(),
+            duration_ms: 12,
+            targets: vec![TargetReport {
+                name: "app".to_string(),
+                path: "config/app.env".to_string(),
+                created: false,
+                untrusted: false,
+                placeholders: 2,
+                replaced: vec!["API_KEY".to_string()],
+                left: vec!["DB_URL".to_string()],
+                duration_ms: 3,
+            }],
+        }
+    }
+
+    #[test]
+    fn test_latest_report() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        assert_eq!(latest(temp_dir.path()).unwrap(), None);
+
+        write(temp_dir.path(), &report("2026-01-02T00:00:00Z")).unwrap();
+        let path = write(temp_dir.path(), &report("2026-01-01T00:00:00Z")).unwrap();
+        assert!(path.ends_with("session-20260101T000000-4242.json"));
+        std::fs::write(temp_dir.path().join("notes.json"), "{}").unwrap();
+
+        assert_eq!(list(temp_dir.path()).unwrap().len(), 2);
+        let last = latest(temp_dir.path()).unwrap().unwrap();
+        assert_eq!(last.unlocked_at, "2026-01-02T00:00:00Z");
+
+        let rendered = last.render();
+        assert!(rendered.contains("app → config/app.env (1 replaced, 1 left, 3 ms)"), "{}", rendered);
+        assert!(rendered.contains("✗ DB_URL"), "{}", rendered);
+    }
+}
@@ -0,0 +1,282 @@
+//! Clipboard and shell history hygiene.
+//!
+//! `shadow-secret scrub` cleans up after accidental copy/pastes: it clears
+//! the clipboard when it holds a vault value, and finds shell history lines
+//! containing vault values so they can be redacted in place.
+//! `shadow-secret get --copy` puts a value on the clipboard instead of
+//! printing it.
+//!
+//! Clipboard access goes through the platform tools (`pbpaste`/`pbcopy`,
+//! `wl-paste`/`wl-copy`, `xclip`, `xsel`, PowerShell), like every other
+//! external program in Shadow Secret.
+
+use crate::process;
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+
+/// Values shorter than this are ignored (too many false positives).
+pub const MIN_SECRET_LEN: usize = 8;
+
+/// Replacement written over secret values in history files.
+pub const REDACTED: &str = "[REDACTED]";
+
+/// A history line containing one or more vault values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryLeak {
+    /// 1-based line number
+    pub line: usize,
+    /// Keys whose values appear on the line, sorted
+    pub keys: Vec<String>,
+}
+
+/// Secrets long enough to be searched for, longest first (so a value that
+/// contains another is redacted as a whole).
+fn searchable(secrets: &HashMap<String, String>) -> Vec<(&str, &str)> {
+    let mut values: Vec<(&str, &str)> = secrets
+        .iter()
+        .filter(|(_, value)| value.len() >= MIN_SECRET_LEN)
+        .map(|(key, value)| (key.as_str(), value.as_str()))
+        .collect();
+    values.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then(a.0.cmp(b.0)));
+    values
+}
+
+/// Keys whose values appear in `text`, sorted.
+pub fn matching_keys(text: &str, secrets: &HashMap<String, String>) -> Vec<String> {
+    let mut keys: Vec<String> = searchable(secrets)
+        .into_iter()
+        .filter(|(_, value)| text.contains(value))
+        .map(|(key, _)| key.to_string())
+        .collect();
+    keys.sort();
+    keys
+}
+
+/// Find history lines containing vault values.
+pub fn find_leaks(content: &[u8], secrets: &HashMap<String, String>) -> Vec<HistoryLeak> {
+    content
+        .split(|&b| b == b'\n')
+        .enumerate()
+        .filter_map(|(index, line)| {
+            let keys = matching_keys(&String::from_utf8_lossy(line), secrets);
+            (!keys.is_empty()).then_some(HistoryLeak {
+                line: index + 1,
+                keys,
+            })
+        })
+        .collect()
+}
+
+/// Replace every vault value in `content` with [`REDACTED`].
+///
+/// Works on raw bytes: zsh history is not always valid UTF-8.
+pub fn redact(content: &[u8], secrets: &HashMap<String, String>) -> Vec<u8> {
+    let mut result = content.to_vec();
+    for (_, value) in searchable(secrets) {
+        result = replace_bytes(&result, value.as_bytes(), REDACTED.as_bytes());
+    }
+    result
+}
+
+fn replace_bytes(haystack: &[u8], needle: &[u8], replacement: &[u8]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(haystack.len());
+    let mut rest = haystack;
+    while let Some(position) = rest.windows(needle.len()).position(|window| window == needle) {
+        result.extend_from_slice(&rest[..position]);
+        result.extend_from_slice(replacement);
+        rest = &rest[position + needle.len()..];
+    }
+    result.extend_from_slice(rest);
+    result
+}
+
+/// Redact a history file in place, keeping its permissions.
+pub fn redact_file(path: &Path, secrets: &HashMap<String, String>) -> Result<()> {
+    let content = std::fs::read(path).with_context(|| format!("Failed to read {:?}", path))?;
+    std::fs::write(path, redact(&content, secrets))
+        .with_context(|| format!("Failed to write {:?}", path))
+}
+
+/// Shell history files that exist for the current user.
+pub fn history_files() -> Vec<PathBuf> {
+    let mut candidates = Vec::new();
+
+    if let Ok(histfile) = std::env::var("HISTFILE") {
+        candidates.push(PathBuf::from(histfile));
+    }
+    if let Some(home) = crate::paths::home_dir() {
+        candidates.push(home.join(".bash_history"));
+        candidates.push(home.join(".zsh_history"));
+        candidates.push(home.join(".zhistory"));
+        candidates.push(home.join(".local/share/fish/fish_history"));
+    }
+    if let Some(data) = dirs::data_dir() {
+        candidates.push(data.join("Microsoft/Windows/PowerShell/PSReadLine/ConsoleHost_history.txt"));
+    }
+
+    let mut files = Vec::new();
+    for path in candidates {
+        if path.is_file() && !files.contains(&path) {
+            files.push(path);
+        }
+    }
+    files
+}
+
+/// Commands that print the clipboard, in order of preference.
+fn paste_commands() -> Vec<(&'static str, Vec<&'static str>)> {
+    if cfg!(target_os = "macos") {
+        vec![("pbpaste", vec![])]
+    } else if cfg!(windows) {
+        vec![("powershell", vec!["-NoProfile", "-Command", "Get-Clipboard"])]
+    } else {
+        vec![
+            ("wl-paste", vec!["--no-newline"]),
+            ("xclip", vec!["-selection", "clipboard", "-o"]),
+            ("xsel", vec!["--clipboard", "--output"]),
+        ]
+    }
+}
+
+/// Commands that empty the clipboard, in order of preference.
+fn clear_commands() -> Vec<(&'static str, Vec<&'static str>)> {
+    if cfg!(target_os = "macos") {
+        vec![("pbcopy", vec![])]
+    } else if cfg!(windows) {
+        vec![("powershell", vec!["-NoProfile", "-Command", "Set-Clipboard -Value $null"])]
+    } else {
+        vec![
+            ("wl-copy", vec!["--clear"]),
+            ("xclip", vec!["-selection", "clipboard", "-i"]),
+            ("xsel", vec!["--clipboard", "--clear"]),
+        ]
+    }
+}
+
+/// Commands that set the clipboard from their input, in order of preference.
+fn copy_commands() -> Vec<(&'static str, Vec<&'static str>)> {
+    if cfg!(target_os = "macos") {
+        vec![("pbcopy", vec![])]
+    } else if cfg!(windows) {
+        vec![("powershell", vec!["-NoProfile", "-Command", "$input | Set-Clipboard"])]
+    } else {
+        vec![
+            ("wl-copy", vec![]),
+            ("xclip", vec!["-selection", "clipboard", "-i"]),
+            ("xsel", vec!["--clipboard", "--input"]),
+        ]
+    }
+}
+
+/// Current clipboard text.
+///
+/// # Returns
+///
+/// `None` when no clipboard tool is available (e.g. headless sessions).
+pub fn clipboard_contents() -> Result<Option<String>> {
+    for (program, args) in paste_commands() {
+        if which::which(program).is_err() {
+            continue;
+        }
+        let output = process::command(program)?
+            .args(&args)
+            .stderr(Stdio::null())
+            .output()
+            .with_context(|| format!("Failed to run {}", program))?;
+        if output.status.success() {
+            return Ok(Some(String::from_utf8_lossy(&output.stdout).into_owned()));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Empty the clipboard.
+pub fn clear_clipboard() -> Result<()> {
+    // Tools without a "clear" flag get empty input instead
+    if set_clipboard(clear_commands(), "")? {
+        return Ok(());
+    }
+
+    anyhow::bail!("No clipboard tool found to clear the clipboard")
+}
+
+/// Put `text` on the clipboard.
+pub fn copy_to_clipboard(text: &str) -> Result<()> {
+    if set_clipboard(copy_commands(), text)? {
+        return Ok(());
+    }
+
+    anyhow::bail!("No clipboard tool found (install wl-clipboard, xclip or xsel)")
+}
+
+/// Feed `text` to the first of `commands` that is installed and succeeds.
+///
+/// # Returns
+///
+/// `false` when none did.
+fn set_clipboard(commands: Vec<(&'static str, Vec<&'static str>)>, text: &str) -> Result<bool> {
+    for (program, args) in commands {
+        if which::which(program).is_err() {
+            continue;
+        }
+        let mut child = process::command(program)?
+            .args(&args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .with_context(|| format!("Failed to run {}", program))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes())?;
+        }
+        if child.wait()?.success() {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secrets() -> HashMap<String, String> {
+        HashMap::from([
+            ("API_KEY".to_string(), "sk_live_1234567890".to_string()),
+            ("DEBUG".to_string(), "true".to_string()),
+        ])
+    }
+
+    #[test]
+    fn test_find_leaks_reports_lines_and_keys() {
+        let history = b"ls\ncurl -H 'Authorization: sk_live_1234567890' api\necho true\n";
+
+        assert_eq!(
+            find_leaks(history, &secrets()),
+            vec![HistoryLeak {
+                line: 2,
+                keys: vec!["API_KEY".to_string()],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_short_values_are_ignored() {
+        assert!(matching_keys("echo true", &secrets()).is_empty());
+    }
+
+    #[test]
+    fn test_redact_replaces_values_only() {
+        let history = b"ls\nexport API_KEY=sk_live_1234567890\n\xff\n";
+
+        assert_eq!(
+            redact(history, &secrets()),
+            b"ls\nexport API_KEY=[REDACTED]\n\xff\n".to_vec()
+        );
+    }
+}
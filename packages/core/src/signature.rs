@@ -0,0 +1,319 @@
+//! Detached signature verification (minisign format).
+//!
+//! Shadow Secret never signs anything itself: files are signed by a human
+//! with the standard `minisign` tool and verified here before they are
+//! trusted.
+//!
+//! # Formats
+//!
+//! Public key (`minisign -G`):
+//!
+//! ```text
+//! untrusted comment: minisign public key 1A2B3C4D5E6F7A8B
+//! RWQ...   (base64: "Ed" || key_id[8] || public_key[32])
+//! ```
+//!
+//! Signature (`minisign -S -m file`):
+//!
+//! ```text
+//! untrusted comment: signature from minisign secret key
+//! RUQ...   (base64: "Ed"/"ED" || key_id[8] || signature[64])
+//! trusted comment: timestamp:1700000000 file:project.yaml
+//! ...      (base64: global_signature[64] over signature || trusted comment)
+//! ```
+//!
+//! Both the legacy (`Ed`, raw message) and pre-hashed (`ED`, BLAKE2b-512)
+//! algorithms are supported.
+
+use anyhow::{Context, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use blake2::{Blake2b512, Digest};
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use std::path::{Path, PathBuf};
+
+/// File extension used for detached signatures (`project.yaml.minisig`).
+pub const SIGNATURE_EXTENSION: &str = "minisig";
+
+/// A minisign public key.
+#[derive(Debug, Clone)]
+pub struct PublicKey {
+    key_id: [u8; 8],
+    key: VerifyingKey,
+}
+
+impl PublicKey {
+    /// Parse a public key from its base64 line or from a full `.pub` file.
+    ///
+    /// Comment lines are ignored, so both `RWQ...` and the two-line file
+    /// produced by `minisign -G` are accepted.
+    pub fn parse(input: &str) -> Result<Self> {
+        let encoded = input
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty() && !line.starts_with("untrusted comment:"))
+            .context("Public key is empty")?;
+
+        let bytes = BASE64
+            .decode(encoded)
+            .context("Public key is not valid base64")?;
+
+        if bytes.len() != 42 || &bytes[..2] != b"Ed" {
+            anyhow::bail!("Unsupported public key format (expected a minisign Ed25519 key)");
+        }
+
+        let mut key_id = [0u8; 8];
+        key_id.copy_from_slice(&bytes[2..10]);
+
+        let mut raw = [0u8; 32];
+        raw.copy_from_slice(&bytes[10..42]);
+        let key = VerifyingKey::from_bytes(&raw).context("Invalid Ed25519 public key")?;
+
+        Ok(Self { key_id, key })
+    }
+
+    /// Load a public key from an inline value or a path to a `.pub` file.
+    pub fn from_arg(value: &str) -> Result<Self> {
+        let path = Path::new(value);
+        if path.is_file() {
+            let content = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read public key file: {:?}", path))?;
+            return Self::parse(&content);
+        }
+
+        Self::parse(value)
+    }
+
+    /// Key identifier as printed by minisign (uppercase hex, little-endian).
+    pub fn key_id(&self) -> String {
+        self.key_id
+            .iter()
+            .rev()
+            .map(|b| format!("{:02X}", b))
+            .collect()
+    }
+}
+
+/// A parsed detached signature.
+#[derive(Debug, Clone)]
+struct DetachedSignature {
+    prehashed: bool,
+    key_id: [u8; 8],
+    signature: Signature,
+    trusted_comment: String,
+    global_signature: Signature,
+}
+
+impl DetachedSignature {
+    fn parse(input: &str) -> Result<Self> {
+        let mut lines = input.lines().map(str::trim_end);
+
+        let first = lines.next().context("Signature file is empty")?;
+        if !first.starts_with("untrusted comment:") {
+            anyhow::bail!("Signature file must start with an 'untrusted comment:' line");
+        }
+
+        let bytes = BASE64
+            .decode(lines.next().context("Signature line missing")?.trim())
+            .context("Signature is not valid base64")?;
+
+        if bytes.len() != 74 {
+            anyhow::bail!("Unsupported signature format (expected a minisign signature)");
+        }
+
+        let prehashed = match &bytes[..2] {
+            b"Ed" => false,
+            b"ED" => true,
+            _ => anyhow::bail!("Unsupported signature algorithm"),
+        };
+
+        let mut key_id = [0u8; 8];
+        key_id.copy_from_slice(&bytes[2..10]);
+        let signature = Signature::from_slice(&bytes[10..74]).context("Invalid signature bytes")?;
+
+        let trusted_comment = lines
+            .next()
+            .and_then(|line| line.strip_prefix("trusted comment: "))
+            .context("Trusted comment line missing")?
+            .to_string();
+
+        let global_bytes = BASE64
+            .decode(lines.next().context("Global signature line missing")?.trim())
+            .context("Global signature is not valid base64")?;
+        let global_signature =
+            Signature::from_slice(&global_bytes).context("Invalid global signature bytes")?;
+
+        Ok(Self {
+            prehashed,
+            key_id,
+            signature,
+            trusted_comment,
+            global_signature,
+        })
+    }
+}
+
+/// Verify `data` against a detached minisign signature.
+///
+/// # Returns
+///
+/// The trusted comment embedded in the signature (e.g. `timestamp:... file:...`).
+///
+/// # Errors
+///
+/// Returns an error if the signature was made by another key, or if either
+/// the data signature or the trusted comment signature does not verify.
+pub fn verify(data: &[u8], signature: &str, public_key: &PublicKey) -> Result<String> {
+    let sig = DetachedSignature::parse(signature)?;
+
+    if sig.key_id != public_key.key_id {
+        anyhow::bail!(
+            "Signature was made with a different key (expected key ID {})",
+            public_key.key_id()
+        );
+    }
+
+    let verified = if sig.prehashed {
+        let digest = Blake2b512::digest(data);
+        public_key.key.verify(&digest, &sig.signature)
+    } else {
+        public_key.key.verify(data, &sig.signature)
+    };
+    verified.map_err(|_| anyhow::anyhow!("Signature verification failed: content was modified"))?;
+
+    let mut global_message = sig.signature.to_bytes().to_vec();
+    global_message.extend_from_slice(sig.trusted_comment.as_bytes());
+    public_key
+        .key
+        .verify(&global_message, &sig.global_signature)
+        .map_err(|_| anyhow::anyhow!("Signature verification failed: trusted comment was modified"))?;
+
+    Ok(sig.trusted_comment)
+}
+
+/// Path of the detached signature for `path` (`<path>.minisig`).
+pub fn signature_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(".");
+    name.push(SIGNATURE_EXTENSION);
+    PathBuf::from(name)
+}
+
+/// Verify a file against its detached signature stored next to it.
+pub fn verify_file(path: &Path, public_key: &PublicKey) -> Result<String> {
+    let data =
+        std::fs::read(path).with_context(|| format!("Failed to read signed file: {:?}", path))?;
+
+    let sig_path = signature_path(path);
+    let signature = std::fs::read_to_string(&sig_path)
+        .with_context(|| format!("Signature not found: {:?}", sig_path))?;
+
+    verify(&data, &signature, public_key)
+        .with_context(|| format!("Invalid signature for {:?}", path))
+}
+
+#[cfg(test)]
+pub(crate) mod test_support {
+    //! Minimal minisign-compatible signer used by unit tests.
+
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    pub const KEY_ID: [u8; 8] = [1, 2, 3, 4, 5, 6, 7, 8];
+
+    pub fn signing_key(seed: u8) -> SigningKey {
+        SigningKey::from_bytes(&[seed; 32])
+    }
+
+    pub fn public_key_line(key: &SigningKey) -> String {
+        let mut bytes = b"Ed".to_vec();
+        bytes.extend_from_slice(&KEY_ID);
+        bytes.extend_from_slice(key.verifying_key().as_bytes());
+        format!(
+            "untrusted comment: minisign public key\n{}\n",
+            BASE64.encode(bytes)
+        )
+    }
+
+    pub fn sign(key: &SigningKey, data: &[u8]) -> String {
+        let digest = Blake2b512::digest(data);
+        let signature = key.sign(&digest);
+
+        let mut bytes = b"ED".to_vec();
+        bytes.extend_from_slice(&KEY_ID);
+        bytes.extend_from_slice(&signature.to_bytes());
+
+        let trusted_comment = "timestamp:1700000000";
+        let mut global_message = signature.to_bytes().to_vec();
+        global_message.extend_from_slice(trusted_comment.as_bytes());
+        let global = key.sign(&global_message);
+
+        format!(
+            "untrusted comment: signature from minisign secret key\n{}\ntrusted comment: {}\n{}\n",
+            BASE64.encode(bytes),
+            trusted_comment,
+            BASE64.encode(global.to_bytes())
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_support::*;
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_verify_valid_signature() {
+        let key = signing_key(7);
+        let public_key = PublicKey::parse(&public_key_line(&key)).unwrap();
+
+        let data = b"vault:\n  source: .enc.env\n";
+        let signature = sign(&key, data);
+
+        let comment = verify(data, &signature, &public_key).unwrap();
+        assert_eq!(comment, "timestamp:1700000000");
+    }
+
+    #[test]
+    fn test_verify_rejects_modified_content() {
+        let key = signing_key(7);
+        let public_key = PublicKey::parse(&public_key_line(&key)).unwrap();
+
+        let signature = sign(&key, b"path: config.json");
+        let result = verify(b"path: /etc/passwd", &signature, &public_key);
+
+        assert!(result.is_err());
+        assert!(result.unwrap_err().to_string().contains("content was modified"));
+    }
+
+    #[test]
+    fn test_verify_rejects_other_key() {
+        let signer = signing_key(7);
+        let other = signing_key(9);
+        let public_key = PublicKey::parse(&public_key_line(&other)).unwrap();
+
+        let signature = sign(&signer, b"data");
+        assert!(verify(b"data", &signature, &public_key).is_err());
+    }
+
+    #[test]
+    fn test_public_key_parse_rejects_garbage() {
+        assert!(PublicKey::parse("not-a-key").is_err());
+        assert!(PublicKey::parse("").is_err());
+    }
+
+    #[test]
+    fn test_verify_file_uses_sibling_signature() {
+        let temp_dir = TempDir::new().unwrap();
+        let key = signing_key(3);
+        let public_key = PublicKey::parse(&public_key_line(&key)).unwrap();
+
+        let file = temp_dir.path().join("project.yaml");
+        std::fs::write(&file, "targets: []\n").unwrap();
+        std::fs::write(signature_path(&file), sign(&key, b"targets: []\n")).unwrap();
+
+        assert!(verify_file(&file, &public_key).is_ok());
+        assert!(signature_path(&file).ends_with("project.yaml.minisig"));
+    }
+}
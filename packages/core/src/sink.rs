@@ -0,0 +1,240 @@
+//! Archiving of reports to configured sinks.
+//!
+//! CI jobs keep the reports Shadow Secret produces without shell glue by
+//! declaring sinks in `global.yaml`:
+//!
+//! ```yaml
+//! sinks:
+//!   - destination: /mnt/audit/shadow-secret        # directory
+//!   - destination: s3://acme-audit/shadow-secret   # through the AWS CLI
+//!     profile: audit
+//!     reports: [compliance, push]
+//!   - destination: https://hooks.example.com/reports   # POSTed as is
+//! ```
+//!
+//! Each report is written to every sink listing its kind in `reports` (all
+//! kinds when empty): [`REPORT_AUDIT`] (`report`), [`REPORT_PUSH`] (push
+//! transcripts) and [`REPORT_COMPLIANCE`] (`compliance-report`). Archiving
+//! is best effort: a sink that fails is reported, the command still
+//! succeeds. Reports never contain secret values.
+
+use crate::config::{Config, SinkConfig};
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+/// Summary of the audit log (`shadow-secret report`).
+pub const REPORT_AUDIT: &str = "audit";
+
+/// Transcript of a cloud push.
+pub const REPORT_PUSH: &str = "push";
+
+/// Guarantees of a configuration (`shadow-secret compliance-report`).
+pub const REPORT_COMPLIANCE: &str = "compliance";
+
+/// Every report kind.
+pub const REPORT_KINDS: &[&str] = &[REPORT_AUDIT, REPORT_PUSH, REPORT_COMPLIANCE];
+
+/// Where a report is archived.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Sink {
+    Directory(PathBuf),
+    /// `s3://bucket/prefix`, through the AWS CLI
+    S3 { url: String, profile: Option<String> },
+    /// `http(s)://` URL receiving the report as the body of a POST
+    Webhook(String),
+}
+
+impl Sink {
+    /// The sink of `config`, relative paths resolved against `config_dir`.
+    pub fn from_config(config: &SinkConfig, config_dir: &Path) -> Result<Self> {
+        let destination = config.destination.as_str();
+        if destination.starts_with("s3://") {
+            return Ok(Self::S3 {
+                url: destination.trim_end_matches('/').to_string(),
+                profile: config.profile.clone(),
+            });
+        }
+        if destination.starts_with("https://") || destination.starts_with("http://") {
+            return Ok(Self::Webhook(destination.to_string()));
+        }
+        Ok(Self::Directory(Config::resolve_path(destination, config_dir)?))
+    }
+
+    /// Write the report `name` (a file name) of kind `kind`.
+    pub fn write(&self, kind: &str, name: &str, content: &[u8]) -> Result<()> {
+        match self {
+            Self::Directory(dir) => {
+                std::fs::create_dir_all(dir).with_context(|| format!("Failed to create directory: {:?}", dir))?;
+                let path = dir.join(name);
+                std::fs::write(&path, content).with_context(|| format!("Failed to write {:?}", path))
+            }
+            Self::S3 { url, profile } => {
+                let args = ["s3".to_string(), "cp".to_string(), "-".to_string(), format!("{}/{}", url, name)];
+                crate::backup::aws(&args, profile, Some(content)).map(drop)
+            }
+            Self::Webhook(url) => {
+                ureq::post(url)
+                    .set("Content-Type", content_type(name))
+                    .set("X-Shadow-Secret-Report", kind)
+                    .set("X-Shadow-Secret-Report-Name", name)
+                    .send_bytes(content)
+                    .map_err(|e| anyhow::anyhow!("Failed to post report to {}: {}", url, e))?;
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Sink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Sink::Directory(dir) => write!(f, "{}", dir.display()),
+            Sink::S3 { url, .. } => write!(f, "{}", url),
+            Sink::Webhook(url) => write!(f, "{}", url),
+        }
+    }
+}
+
+fn content_type(name: &str) -> &'static str {
+    match Path::new(name).extension().and_then(|extension| extension.to_str()) {
+        Some("json") => "application/json",
+        Some("md") => "text/markdown; charset=utf-8",
+        _ => "text/plain; charset=utf-8",
+    }
+}
+
+/// Check the sinks of a config.
+pub fn validate(sinks: &[SinkConfig]) -> Result<()> {
+    for sink in sinks {
+        if sink.destination.trim().is_empty() {
+            anyhow::bail!("Sink destination cannot be empty");
+        }
+        for kind in &sink.reports {
+            if !REPORT_KINDS.contains(&kind.as_str()) {
+                anyhow::bail!(
+                    "Unknown report '{}' in sink {} (expected one of: {})",
+                    kind,
+                    sink.destination,
+                    REPORT_KINDS.join(", ")
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Outcome of writing a report to one sink.
+#[derive(Debug)]
+pub struct Delivery {
+    pub sink: String,
+    pub result: Result<()>,
+}
+
+/// Write the report `name` of kind `kind` to every sink of `sinks` taking
+/// it.
+pub fn deliver(sinks: &[SinkConfig], config_dir: &Path, kind: &str, name: &str, content: &[u8]) -> Vec<Delivery> {
+    sinks
+        .iter()
+        .filter(|sink| sink.reports.is_empty() || sink.reports.iter().any(|report| report == kind))
+        .map(|config| match Sink::from_config(config, config_dir) {
+            Ok(sink) => Delivery {
+                sink: sink.to_string(),
+                result: sink.write(kind, name, content),
+            },
+            Err(e) => Delivery {
+                sink: config.destination.clone(),
+                result: Err(e),
+            },
+        })
+        .collect()
+}
+
+/// Archive a report to the sinks of the global config, printing where it
+/// went. Nothing happens without a global config or sinks.
+pub fn archive(kind: &str, name: &str, content: &[u8]) {
+    let deliveries = match global_sinks() {
+        Ok(Some((sinks, config_dir))) => deliver(&sinks, &config_dir, kind, name, content),
+        Ok(None) => return,
+        Err(e) => {
+            crate::say_err!("⚠️  Could not load report sinks: {:#}", e);
+            return;
+        }
+    };
+    for delivery in deliveries {
+        match delivery.result {
+            Ok(()) => crate::say_err!("📦 Archived {} to {}", name, delivery.sink),
+            Err(e) => crate::say_err!("⚠️  Could not archive {} to {}: {:#}", name, delivery.sink, e),
+        }
+    }
+}
+
+/// Sinks of the global config and its directory, if it declares any.
+fn global_sinks() -> Result<Option<(Vec<SinkConfig>, PathBuf)>> {
+    let path = crate::paths::global_config_path()?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    let config = Config::from_file(&path)?;
+    if config.sinks.is_empty() {
+        return Ok(None);
+    }
+    validate(&config.sinks)?;
+    let config_dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+    Ok(Some((config.sinks, config_dir)))
+}
+
+/// File name of a report of kind `kind` generated at `time`
+/// (`compliance-20260101T120000Z.json`).
+pub fn file_name(kind: &str, time: chrono::DateTime<chrono::Utc>, extension: &str) -> String {
+    format!("{}-{}.{}", kind, time.format("%Y%m%dT%H%M%SZ"), extension)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sink(destination: &str, reports: &[&str]) -> SinkConfig {
+        SinkConfig {
+            destination: destination.to_string(),
+            reports: reports.iter().map(|report| report.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_sink_kinds() {
+        let dir = Path::new("/home/ci/.config/shadow-secret");
+        assert_eq!(
+            Sink::from_config(&sink("reports", &[]), dir).unwrap(),
+            Sink::Directory(dir.join("reports"))
+        );
+        assert_eq!(
+            Sink::from_config(&sink("s3://acme/reports/", &[]), dir).unwrap(),
+            Sink::S3 { url: "s3://acme/reports".to_string(), profile: None }
+        );
+        assert_eq!(
+            Sink::from_config(&sink("https://hooks.example.com/r", &[]), dir).unwrap(),
+            Sink::Webhook("https://hooks.example.com/r".to_string())
+        );
+
+        assert!(validate(&[sink("reports", &["compliance", "push"])]).is_ok());
+        assert!(validate(&[sink("reports", &["drift"])]).is_err());
+        assert!(validate(&[sink(" ", &[])]).is_err());
+    }
+
+    #[test]
+    fn test_deliver_to_matching_sinks() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let sinks = [sink("all", &[]), sink("push-only", &[REPORT_PUSH])];
+
+        let deliveries = deliver(&sinks, temp_dir.path(), REPORT_COMPLIANCE, "compliance-1.json", b"{}");
+        assert_eq!(deliveries.len(), 1);
+        assert!(deliveries[0].result.is_ok());
+        assert_eq!(std::fs::read(temp_dir.path().join("all/compliance-1.json")).unwrap(), b"{}");
+        assert!(!temp_dir.path().join("push-only").exists());
+
+        let deliveries = deliver(&sinks, temp_dir.path(), REPORT_PUSH, "push-1.md", b"# Push");
+        assert_eq!(deliveries.len(), 2);
+        assert!(temp_dir.path().join("push-only/push-1.md").exists());
+    }
+}
@@ -0,0 +1,227 @@
+//! Safe-mode snapshots of unlock targets (`unlock --snapshot`).
+//!
+//! For extra-cautious users, a last recovery layer independent of the
+//! in-memory backups (see [`crate::cleaner`]) and of the crash-recovery
+//! journal (see [`crate::journal`]): before any target is modified, every
+//! target file is copied into a file encrypted to the age recipients of
+//! the project's vault, `~/.config/shadow-secret/snapshots/<id>.age` (see
+//! [`crate::age_file`]). Unlike journals, snapshots are kept after a clean
+//! lock and are never collected by `gc`; `restore-snapshot` puts the files
+//! back from one.
+//!
+//! Decrypted, a snapshot is JSON: the original absolute paths in target
+//! order, each with its content in base64. Targets that did not exist yet
+//! have a `null` content, and are deleted on restore.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Directory of the snapshots, inside the per-user state directory.
+pub const SNAPSHOTS_DIR: &str = "snapshots";
+
+/// Name of snapshots in messages.
+const WHAT: &str = "snapshot";
+
+/// A target file as it was before unlock.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotFile {
+    /// Absolute path
+    pub path: String,
+    /// Base64 in the file; `None` for a file that did not exist (deleted
+    /// on restore)
+    #[serde(with = "base64_content")]
+    pub content: Option<Vec<u8>>,
+}
+
+/// Target files of one unlock, before any modification.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// RFC 3339 time of the snapshot
+    pub created_at: String,
+    pub files: Vec<SnapshotFile>,
+}
+
+impl Snapshot {
+    /// Read the files at `paths` (relative to the current directory).
+    pub fn capture<P: AsRef<Path>>(paths: &[P]) -> Result<Self> {
+        let mut files: Vec<SnapshotFile> = Vec::new();
+        for path in paths {
+            let absolute = crate::journal::absolute_path(path.as_ref())?;
+            if files.iter().any(|file| file.path == absolute) {
+                continue;
+            }
+            let content = match std::fs::read(path.as_ref()) {
+                Ok(content) => Some(content),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+                Err(e) => return Err(e).with_context(|| format!("Failed to snapshot {}", absolute)),
+            };
+            files.push(SnapshotFile { path: absolute, content });
+        }
+        Ok(Self {
+            created_at: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            files,
+        })
+    }
+
+    /// Put every file back: rewrite contents, delete files that did not
+    /// exist.
+    ///
+    /// # Returns
+    ///
+    /// The paths restored or deleted.
+    pub fn restore(&self) -> Result<Vec<String>> {
+        let mut restored = Vec::new();
+        for file in &self.files {
+            match &file.content {
+                Some(content) => {
+                    crate::injector::write_atomic(Path::new(&file.path), content)
+                        .with_context(|| format!("Failed to restore {}", file.path))?;
+                }
+                None => match std::fs::remove_file(&file.path) {
+                    Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                        return Err(e).with_context(|| format!("Failed to delete {}", file.path))
+                    }
+                    _ => {}
+                },
+            }
+            restored.push(file.path.clone());
+        }
+        Ok(restored)
+    }
+}
+
+/// File contents as base64 strings, so the snapshot stays valid JSON.
+mod base64_content {
+    use base64::engine::general_purpose::STANDARD as BASE64;
+    use base64::Engine as _;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(content: &Option<Vec<u8>>, serializer: S) -> Result<S::Ok, S::Error> {
+        match content {
+            Some(content) => serializer.serialize_some(&BASE64.encode(content)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Vec<u8>>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|content| BASE64.decode(content).map_err(serde::de::Error::custom))
+            .transpose()
+    }
+}
+
+/// A snapshot file, as shown by `restore-snapshot --list` (nothing is
+/// decrypted).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotEntry {
+    pub id: String,
+    pub path: PathBuf,
+}
+
+/// Default snapshot directory (`~/.config/shadow-secret/snapshots`).
+pub fn snapshots_dir() -> Result<PathBuf> {
+    let dir = crate::isolation::state_dir()?.join(SNAPSHOTS_DIR);
+    crate::isolation::ensure_private_dir(&dir)?;
+    Ok(dir)
+}
+
+/// Encrypt `snapshot` to `recipients` into a new file of `dir`.
+pub fn write(dir: &Path, snapshot: &Snapshot, recipients: &[age::x25519::Recipient]) -> Result<PathBuf> {
+    let encrypted = crate::age_file::encrypt(&serde_json::to_vec(snapshot)?, recipients, WHAT)?;
+    crate::age_file::create(dir, &crate::age_file::id(&snapshot.created_at, std::process::id()), &encrypted, WHAT)
+}
+
+/// Decrypt the snapshot at `path` (see [`crate::age_file::decrypt`]).
+pub fn read(path: &Path, age_key_path: Option<&str>) -> Result<Snapshot> {
+    let plaintext = crate::age_file::decrypt(path, age_key_path, WHAT)?;
+    serde_json::from_slice(&plaintext).with_context(|| format!("Corrupted snapshot: {}", path.display()))
+}
+
+/// Snapshots in `dir`, oldest first.
+pub fn list(dir: &Path) -> Result<Vec<SnapshotEntry>> {
+    Ok(crate::age_file::list(dir)?
+        .into_iter()
+        .map(|(id, path)| SnapshotEntry { id, path })
+        .collect())
+}
+
+/// Find the snapshot `id` in `dir`, or the latest one.
+pub fn find(dir: &Path, id: Option<&str>) -> Result<SnapshotEntry> {
+    let snapshots = list(dir)?;
+    match id {
+        Some(id) => snapshots
+            .into_iter()
+            .find(|snapshot| snapshot.id == id)
+            .with_context(|| format!("No snapshot '{}' (see 'shadow-secret restore-snapshot --list')", id)),
+        None => snapshots
+            .into_iter()
+            .next_back()
+            .context("No snapshot yet: unlock with --snapshot first"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use age::secrecy::ExposeSecret;
+
+    #[test]
+    fn test_json_round_trip() {
+        let snapshot = Snapshot {
+            created_at: "2026-01-01T00:00:00Z".to_string(),
+            files: vec![
+                SnapshotFile {
+                    path: "/srv/app/.env".to_string(),
+                    content: Some(b"API_KEY=$API_KEY\n".to_vec()),
+                },
+                SnapshotFile {
+                    path: "/srv/app/empty".to_string(),
+                    content: Some(Vec::new()),
+                },
+                SnapshotFile {
+                    path: "/srv/app/demo.env".to_string(),
+                    content: None,
+                },
+            ],
+        };
+
+        let json = serde_json::to_string(&snapshot).unwrap();
+        assert!(json.contains("\"content\":\"QVBJX0tFWT0kQVBJX0tFWQo=\""));
+        assert!(json.contains("\"content\":null"));
+        assert_eq!(serde_json::from_str::<Snapshot>(&json).unwrap(), snapshot);
+    }
+
+    #[test]
+    fn test_write_read_and_restore() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let identity = age::x25519::Identity::generate();
+        let key_path = temp_dir.path().join("keys.txt");
+        std::fs::write(&key_path, identity.to_string().expose_secret()).unwrap();
+
+        let target = temp_dir.path().join("app.env");
+        let created = temp_dir.path().join("demo.env");
+        std::fs::write(&target, "API_KEY=$API_KEY\n").unwrap();
+        // The same target reached through `..` is captured once
+        std::fs::create_dir(temp_dir.path().join("sub")).unwrap();
+        let dotted = temp_dir.path().join("sub").join("..").join("app.env");
+        let snapshot = Snapshot::capture(&[&target, &created, &dotted]).unwrap();
+        assert_eq!(snapshot.files.len(), 2);
+
+        let dir = temp_dir.path().join(SNAPSHOTS_DIR);
+        std::fs::create_dir(&dir).unwrap();
+        let path = write(&dir, &snapshot, &[identity.to_public()]).unwrap();
+        assert!(!String::from_utf8_lossy(&std::fs::read(&path).unwrap()).contains("API_KEY"));
+        assert_eq!(find(&dir, None).unwrap().path, path);
+
+        // Unlock, then lose the in-memory backups and the journal
+        std::fs::write(&target, "API_KEY=sk_live_leaked\n").unwrap();
+        std::fs::write(&created, "KEY=fake\n").unwrap();
+
+        let read_back = read(&path, Some(&key_path.to_string_lossy())).unwrap();
+        assert_eq!(read_back, snapshot);
+        read_back.restore().unwrap();
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "API_KEY=$API_KEY\n");
+        assert!(!created.exists());
+    }
+}
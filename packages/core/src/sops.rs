@@ -0,0 +1,242 @@
+//! SOPS failure classification and retries.
+//!
+//! SOPS reports every problem as free-form text on stderr. This module
+//! turns that text into a [`SopsError`] with a targeted remediation hint,
+//! and retries failures that are likely transient (KMS or network hiccups).
+
+use std::fmt;
+use std::thread;
+use std::time::Duration;
+
+/// Maximum number of attempts for a SOPS call that fails transiently.
+pub const MAX_ATTEMPTS: u32 = 3;
+
+/// Delay before the first retry; doubled after each further failure.
+pub const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+
+/// Classified SOPS failure.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SopsError {
+    /// None of the available keys can decrypt the file
+    WrongKey { path: String, stderr: String },
+    /// The file content does not match its MAC (tampered or corrupted)
+    MacMismatch { path: String, stderr: String },
+    /// The encrypted file does not exist
+    FileNotFound { path: String, stderr: String },
+    /// A KMS or network call timed out or was throttled (retried)
+    KmsTimeout { path: String, stderr: String },
+    /// Any other SOPS failure
+    Other { path: String, stderr: String },
+}
+
+impl SopsError {
+    /// Classify SOPS stderr output for `path`.
+    pub fn classify(path: &str, stderr: &str) -> Self {
+        let path = path.to_string();
+        let lower = stderr.to_lowercase();
+        let stderr = stderr.trim().to_string();
+        let has = |needles: &[&str]| needles.iter().any(|needle| lower.contains(needle));
+
+        // Order matters: data key failures often embed lower-level causes
+        // (e.g. a missing age key file), so the most specific cases go first.
+        if has(&["mac mismatch"]) {
+            SopsError::MacMismatch { path, stderr }
+        } else if has(&[
+            "timeout",
+            "timed out",
+            "deadline exceeded",
+            "connection reset",
+            "connection refused",
+            "temporary failure",
+            "throttl",
+            "too many requests",
+            "service unavailable",
+            "503",
+        ]) {
+            SopsError::KmsTimeout { path, stderr }
+        } else if has(&[
+            "failed to get the data key",
+            "no identity matched",
+            "could not decrypt data key",
+            "no matching keys",
+            "0 successful groups",
+        ]) {
+            SopsError::WrongKey { path, stderr }
+        } else if has(&[
+            "non-existent file",
+            "no such file or directory",
+            "cannot find the file",
+            "the system cannot find",
+        ]) {
+            SopsError::FileNotFound { path, stderr }
+        } else {
+            SopsError::Other { path, stderr }
+        }
+    }
+
+    /// Whether retrying the same call may succeed.
+    pub fn is_transient(&self) -> bool {
+        matches!(self, SopsError::KmsTimeout { .. })
+    }
+
+    /// Short remediation hint for the user.
+    pub fn hint(&self) -> &'static str {
+        match self {
+            SopsError::WrongKey { .. } => {
+                "Your age key is not a recipient of this file. Check $SOPS_AGE_KEY_FILE or vault.age_key_path, \
+                and ask a teammate to re-encrypt with your public key (sops updatekeys)."
+            }
+            SopsError::MacMismatch { .. } => {
+                "The file was modified outside SOPS or is corrupted. Restore it from version control \
+                or re-create it with 'sops edit'."
+            }
+            SopsError::FileNotFound { .. } => {
+                "Check vault.source / vault.vault_path in your config, and that encrypted drives are mounted."
+            }
+            SopsError::KmsTimeout { .. } => {
+                "The key service did not respond in time. Check your network/VPN and cloud credentials, then retry."
+            }
+            SopsError::Other { .. } => "Run 'shadow-secret doctor' to check your SOPS and age setup.",
+        }
+    }
+
+    fn summary(&self) -> &'static str {
+        match self {
+            SopsError::WrongKey { .. } => "no available key can decrypt the file",
+            SopsError::MacMismatch { .. } => "MAC mismatch (file tampered or corrupted)",
+            SopsError::FileNotFound { .. } => "encrypted file not found",
+            SopsError::KmsTimeout { .. } => "key service timed out",
+            SopsError::Other { .. } => "decryption failed",
+        }
+    }
+
+    fn parts(&self) -> (&str, &str) {
+        match self {
+            SopsError::WrongKey { path, stderr }
+            | SopsError::MacMismatch { path, stderr }
+            | SopsError::FileNotFound { path, stderr }
+            | SopsError::KmsTimeout { path, stderr }
+            | SopsError::Other { path, stderr } => (path, stderr),
+        }
+    }
+}
+
+impl fmt::Display for SopsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (path, stderr) = self.parts();
+        write!(f, "SOPS decryption failed for {}: {}", path, self.summary())?;
+        if !stderr.is_empty() {
+            write!(f, "\n  sops: {}", stderr)?;
+        }
+        write!(f, "\n💡 {}", self.hint())
+    }
+}
+
+impl std::error::Error for SopsError {}
+
+/// Run `call` up to `max_attempts` times, retrying transient [`SopsError`]s
+/// with exponential backoff starting at `base_delay`.
+pub fn with_retry<T, F>(max_attempts: u32, base_delay: Duration, mut call: F) -> anyhow::Result<T>
+where
+    F: FnMut() -> anyhow::Result<T>,
+{
+    let mut attempt = 1;
+    loop {
+        match call() {
+            Err(e) if attempt < max_attempts && is_transient(&e) => {
+                let delay = base_delay * 2u32.pow(attempt - 1);
+                crate::say_err!(
+                    "⏳ SOPS call failed transiently (attempt {}/{}), retrying in {:?}...",
+                    attempt, max_attempts, delay
+                );
+                thread::sleep(delay);
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+fn is_transient(error: &anyhow::Error) -> bool {
+    error
+        .downcast_ref::<SopsError>()
+        .is_some_and(SopsError::is_transient)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_common_failures() {
+        let cases = [
+            (
+                "Failed to get the data key required to decrypt the SOPS file.",
+                "WrongKey",
+            ),
+            ("Error: MAC mismatch. File has 1234, computed 5678", "MacMismatch"),
+            ("Error: cannot operate on non-existent file", "FileNotFound"),
+            (
+                "Error decrypting key: RequestError: send request failed caused by: i/o timeout",
+                "KmsTimeout",
+            ),
+            ("Error unmarshalling input json", "Other"),
+        ];
+
+        for (stderr, expected) in cases {
+            let error = SopsError::classify("vault.enc.env", stderr);
+            assert!(
+                format!("{:?}", error).starts_with(expected),
+                "{} should classify as {}, got {:?}",
+                stderr,
+                expected,
+                error
+            );
+        }
+    }
+
+    #[test]
+    fn test_display_includes_hint() {
+        let error = SopsError::classify("vault.enc.env", "MAC mismatch");
+        let message = error.to_string();
+
+        assert!(message.contains("vault.enc.env"));
+        assert!(message.contains("tampered"));
+        assert!(message.contains("💡"));
+    }
+
+    #[test]
+    fn test_retry_only_transient_failures() {
+        let mut calls = 0;
+        let result: anyhow::Result<()> = with_retry(3, Duration::ZERO, || {
+            calls += 1;
+            Err(SopsError::classify("v", "deadline exceeded").into())
+        });
+        assert!(result.is_err());
+        assert_eq!(calls, 3);
+
+        let mut calls = 0;
+        let result: anyhow::Result<()> = with_retry(3, Duration::ZERO, || {
+            calls += 1;
+            Err(SopsError::classify("v", "MAC mismatch").into())
+        });
+        assert!(result.is_err());
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn test_retry_recovers() {
+        let mut calls = 0;
+        let result = with_retry(3, Duration::ZERO, || {
+            calls += 1;
+            if calls < 2 {
+                Err(SopsError::classify("v", "503 Service Unavailable").into())
+            } else {
+                Ok("secret")
+            }
+        });
+
+        assert_eq!(result.unwrap(), "secret");
+        assert_eq!(calls, 2);
+    }
+}
@@ -0,0 +1,188 @@
+//! Machine-readable session status for CI wrappers.
+//!
+//! When `$SHADOW_SECRET_STATUS_FILE` names a file, or when running under CI
+//! (`$CI` set; the file is then `~/.config/shadow-secret/status.json`),
+//! `unlock` and `unlock-global` keep a JSON status file up to date:
+//!
+//! ```json
+//! {"state":"unlocked","started_at":"2026-01-01T00:00:00Z","updated_at":"2026-01-01T00:05:00Z","pid":4242,"targets":["config/app.json"]}
+//! ```
+//!
+//! While the session is alive, `updated_at` is refreshed every
+//! [`HEARTBEAT_INTERVAL`]. Orchestration can then spot a hung or killed
+//! unlock (a stale `updated_at`, or `pid` gone while `unlocked`) and force
+//! cleanup with `journal restore`. The file only holds target paths, never
+//! secrets, and is replaced atomically.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+/// Environment variable naming the status file.
+pub const STATUS_FILE_ENV: &str = "SHADOW_SECRET_STATUS_FILE";
+
+/// Status file in the state directory, used under CI.
+pub const STATUS_FILE: &str = "status.json";
+
+/// Time between two refreshes of `updated_at`.
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Where the session is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionState {
+    /// Injecting secrets into the targets
+    Unlocking,
+    /// Secrets are injected, waiting for lock
+    Unlocked,
+    /// Restoring the templates
+    Locking,
+    /// Every target was restored
+    Locked,
+    /// Unlock failed, or some target could not be restored
+    Failed,
+}
+
+impl SessionState {
+    /// Whether the session may still change the targets.
+    fn is_live(self) -> bool {
+        matches!(self, SessionState::Unlocking | SessionState::Unlocked | SessionState::Locking)
+    }
+}
+
+/// Content of the status file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Status {
+    pub state: SessionState,
+    /// RFC 3339 time the session started
+    pub started_at: String,
+    /// RFC 3339 time of the last update or heartbeat
+    pub updated_at: String,
+    pub pid: u32,
+    /// Paths of the targets of the session
+    pub targets: Vec<String>,
+}
+
+/// Status file of the current session and its content.
+static CURRENT: Mutex<Option<(PathBuf, Status)>> = Mutex::new(None);
+
+fn current() -> MutexGuard<'static, Option<(PathBuf, Status)>> {
+    CURRENT.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn timestamp() -> String {
+    chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+}
+
+/// Status file to write: `$SHADOW_SECRET_STATUS_FILE`, else the default
+/// one under CI, else none.
+pub fn status_path() -> Result<Option<PathBuf>> {
+    if let Some(path) = std::env::var_os(STATUS_FILE_ENV).filter(|path| !path.is_empty()) {
+        return Ok(Some(PathBuf::from(path)));
+    }
+    if std::env::var_os("CI").is_some_and(|ci| !ci.is_empty()) {
+        return Ok(Some(crate::isolation::state_dir()?.join(STATUS_FILE)));
+    }
+    Ok(None)
+}
+
+/// Start reporting a session unlocking `targets`, if a status file is
+/// configured (see [`status_path`]).
+///
+/// # Returns
+///
+/// The status file written, if any.
+pub fn start(targets: &[String]) -> Result<Option<PathBuf>> {
+    let Some(path) = status_path()? else {
+        return Ok(None);
+    };
+    start_at(&path, targets)?;
+    Ok(Some(path))
+}
+
+/// Start reporting a session unlocking `targets` into `path`, and refresh
+/// it every [`HEARTBEAT_INTERVAL`] until the session is over.
+pub fn start_at(path: &Path, targets: &[String]) -> Result<()> {
+    let now = timestamp();
+    let status = Status {
+        state: SessionState::Unlocking,
+        started_at: now.clone(),
+        updated_at: now,
+        pid: std::process::id(),
+        targets: targets.to_vec(),
+    };
+    write(path, &status)?;
+
+    *current() = Some((path.to_path_buf(), status));
+    std::thread::spawn(heartbeat);
+    Ok(())
+}
+
+/// Record that the session reached `state` (nothing happens if no status
+/// file was started).
+pub fn set_state(state: SessionState) -> Result<()> {
+    let mut current = current();
+    let Some((path, status)) = current.as_mut() else {
+        return Ok(());
+    };
+    status.state = state;
+    status.updated_at = timestamp();
+    write(path, status)
+}
+
+/// Refresh `updated_at` while the session is live.
+fn heartbeat() {
+    loop {
+        std::thread::sleep(HEARTBEAT_INTERVAL);
+
+        let mut current = current();
+        let Some((path, status)) = current.as_mut() else {
+            return;
+        };
+        if !status.state.is_live() {
+            return;
+        }
+        status.updated_at = timestamp();
+        // A missed heartbeat only makes the session look older
+        let _ = write(path, status);
+    }
+}
+
+/// Read the status file at `path`.
+pub fn read(path: &Path) -> Result<Status> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read status file: {}", path.display()))?;
+    serde_json::from_str(&content).with_context(|| format!("Invalid status file: {}", path.display()))
+}
+
+/// Replace the status file atomically, so readers never see half of it.
+fn write(path: &Path, status: &Status) -> Result<()> {
+    let temp = PathBuf::from(format!("{}.tmp", path.display()));
+    std::fs::write(&temp, serde_json::to_string(status)?)
+        .with_context(|| format!("Failed to write status file: {}", temp.display()))?;
+    std::fs::rename(&temp, path).with_context(|| format!("Failed to write status file: {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_file_follows_the_session() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("status.json");
+
+        start_at(&path, &["config/app.json".to_string()]).unwrap();
+        let status = read(&path).unwrap();
+        assert_eq!(status.state, SessionState::Unlocking);
+        assert_eq!(status.pid, std::process::id());
+        assert_eq!(status.targets, vec!["config/app.json".to_string()]);
+
+        set_state(SessionState::Locked).unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.contains("\"state\":\"locked\""), "{}", content);
+        assert!(!temp_dir.path().join("status.json.tmp").exists());
+    }
+}
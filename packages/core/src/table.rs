@@ -0,0 +1,287 @@
+//! Column-aligned tables for terminal output (`list`, `status`, `report`).
+//!
+//! Widths are measured in terminal cells, so keys and paths in any script
+//! (CJK characters and emoji take two cells) stay aligned. Users pick the
+//! columns (`--columns key,source`) and the row order (`--sort`). When the
+//! table is wider than the terminal, truncatable columns are cut with `…`,
+//! widest first and never below [`MIN_WIDTH`]; key names are never cut.
+
+use anyhow::Result;
+use std::cmp::Ordering;
+
+/// Narrowest a truncated column gets.
+pub const MIN_WIDTH: usize = 8;
+
+/// Space between two columns.
+const GAP: &str = "  ";
+
+/// Shown for an empty cell.
+const EMPTY: &str = "-";
+
+/// A table column.
+#[derive(Debug, Clone)]
+pub struct Column {
+    /// Name used by `--columns` and `--sort` (e.g. "last-used")
+    pub name: &'static str,
+    /// May be cut to fit the terminal
+    pub truncate: bool,
+    /// Numbers, aligned right and sorted by value
+    pub numeric: bool,
+}
+
+impl Column {
+    /// A column never cut.
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            truncate: false,
+            numeric: false,
+        }
+    }
+
+    /// A column cut to fit the terminal (paths, lists).
+    pub fn truncated(name: &'static str) -> Self {
+        Self {
+            truncate: true,
+            ..Self::new(name)
+        }
+    }
+
+    /// A column of numbers.
+    pub fn numeric(name: &'static str) -> Self {
+        Self {
+            numeric: true,
+            ..Self::new(name)
+        }
+    }
+}
+
+/// Rows of cells under named columns.
+#[derive(Debug, Clone)]
+pub struct Table {
+    columns: Vec<Column>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub fn new(columns: Vec<Column>) -> Self {
+        Self {
+            columns,
+            rows: Vec::new(),
+        }
+    }
+
+    /// Add a row, one cell per column.
+    pub fn push(&mut self, row: Vec<String>) {
+        debug_assert_eq!(row.len(), self.columns.len());
+        self.rows.push(row);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    fn index(&self, name: &str) -> Result<usize> {
+        self.columns
+            .iter()
+            .position(|column| column.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Unknown column '{}' (available: {})",
+                    name,
+                    self.columns.iter().map(|column| column.name).collect::<Vec<_>>().join(", ")
+                )
+            })
+    }
+
+    /// Keep only the columns named in `names`, in that order.
+    pub fn select(&mut self, names: &[String]) -> Result<()> {
+        let indices = names.iter().map(|name| self.index(name)).collect::<Result<Vec<_>>>()?;
+        self.columns = indices.iter().map(|&index| self.columns[index].clone()).collect();
+        for row in &mut self.rows {
+            *row = indices.iter().map(|&index| row[index].clone()).collect();
+        }
+        Ok(())
+    }
+
+    /// Order the rows by the column `name` (numbers by value, text
+    /// alphabetically); empty cells always come last.
+    pub fn sort_by(&mut self, name: &str, descending: bool) -> Result<()> {
+        let index = self.index(name)?;
+        let numeric = self.columns[index].numeric;
+        self.rows.sort_by(|a, b| {
+            let (a, b) = (&a[index], &b[index]);
+            match (a.is_empty(), b.is_empty()) {
+                (true, true) => return Ordering::Equal,
+                (true, false) => return Ordering::Greater,
+                (false, true) => return Ordering::Less,
+                _ => {}
+            }
+            let order = match (numeric, a.parse::<f64>(), b.parse::<f64>()) {
+                (true, Ok(a), Ok(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+                _ => a.cmp(b),
+            };
+            if descending {
+                order.reverse()
+            } else {
+                order
+            }
+        });
+        Ok(())
+    }
+
+    /// The rows as JSON objects keyed by column name; empty cells are
+    /// `null`, numeric cells numbers.
+    pub fn to_json(&self) -> serde_json::Value {
+        let records = self.rows.iter().map(|row| {
+            let record: serde_json::Map<String, serde_json::Value> = self
+                .columns
+                .iter()
+                .zip(row)
+                .map(|(column, text)| {
+                    let value = match text.parse::<u64>() {
+                        _ if text.is_empty() => serde_json::Value::Null,
+                        Ok(number) if column.numeric => number.into(),
+                        _ => text.clone().into(),
+                    };
+                    (column.name.to_string(), value)
+                })
+                .collect();
+            serde_json::Value::Object(record)
+        });
+        serde_json::Value::Array(records.collect())
+    }
+
+    /// The table as text: a header of column names, then one line per row,
+    /// fitting in `width` cells if given.
+    pub fn render(&self, width: Option<usize>) -> String {
+        let cell = |text: &str| if text.is_empty() { EMPTY.to_string() } else { text.to_string() };
+        let header: Vec<String> = self.columns.iter().map(|column| column.name.to_uppercase()).collect();
+        let mut widths: Vec<usize> = header.iter().map(|name| console::measure_text_width(name)).collect();
+        for row in &self.rows {
+            for (width, text) in widths.iter_mut().zip(row) {
+                *width = (*width).max(console::measure_text_width(&cell(text)));
+            }
+        }
+        if let Some(limit) = width {
+            fit(&mut widths, &self.columns, limit);
+        }
+
+        let line = |cells: Vec<String>| -> String {
+            let last = cells.len().saturating_sub(1);
+            let mut out = String::new();
+            for (index, text) in cells.iter().enumerate() {
+                let column = &self.columns[index];
+                let text = truncate(text, widths[index]);
+                let padding = widths[index].saturating_sub(console::measure_text_width(&text));
+                if column.numeric {
+                    out.push_str(&" ".repeat(padding));
+                    out.push_str(&text);
+                } else {
+                    out.push_str(&text);
+                    if index != last {
+                        out.push_str(&" ".repeat(padding));
+                    }
+                }
+                if index != last {
+                    out.push_str(GAP);
+                }
+            }
+            out.trim_end().to_string() + "\n"
+        };
+
+        let mut out = line(header);
+        for row in &self.rows {
+            out.push_str(&line(row.iter().map(|text| cell(text)).collect()));
+        }
+        out
+    }
+}
+
+/// `text` cut to `width` cells, ending with `…` if it was cut.
+fn truncate(text: &str, width: usize) -> std::borrow::Cow<'_, str> {
+    if console::measure_text_width(text) <= width {
+        return text.into();
+    }
+    console::truncate_str(text, width, "…")
+}
+
+/// Narrow truncatable columns, widest first, until `widths` fit in `limit`.
+fn fit(widths: &mut [usize], columns: &[Column], limit: usize) {
+    let total = |widths: &[usize]| widths.iter().sum::<usize>() + GAP.len() * widths.len().saturating_sub(1);
+    while total(widths) > limit {
+        let widest = (0..widths.len())
+            .filter(|&index| columns[index].truncate && widths[index] > MIN_WIDTH)
+            .max_by_key(|&index| widths[index]);
+        match widest {
+            Some(index) => widths[index] -= 1,
+            None => return,
+        }
+    }
+}
+
+/// Column names of a `--columns` value (`key,source`).
+pub fn parse_columns(spec: &str) -> Vec<String> {
+    spec.split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Width to fit tables in: `$COLUMNS`, else the terminal's when stdout is
+/// one, else none (output piped to a file or another program is not cut).
+pub fn terminal_width() -> Option<usize> {
+    if let Some(columns) = std::env::var("COLUMNS").ok().and_then(|columns| columns.parse().ok()) {
+        return Some(columns);
+    }
+    console::Term::stdout()
+        .size_checked()
+        .map(|(_, columns)| columns as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table() -> Table {
+        let mut table = Table::new(vec![Column::new("key"), Column::truncated("source"), Column::numeric("length")]);
+        table.push(vec!["API_KEY".into(), "/home/dev/project/.enc.env".into(), "32".into()]);
+        table.push(vec!["DB_URL".into(), "/home/dev/project/.enc.env".into(), "".into()]);
+        table.push(vec!["密钥".into(), "global.enc.env".into(), "128".into()]);
+        table
+    }
+
+    #[test]
+    fn test_render_aligns_by_display_width() {
+        assert_eq!(
+            table().render(None),
+            "KEY      SOURCE                      LENGTH\n\
+             API_KEY  /home/dev/project/.enc.env      32\n\
+             DB_URL   /home/dev/project/.enc.env       -\n\
+             密钥     global.enc.env                 128\n"
+        );
+    }
+
+    #[test]
+    fn test_truncate_select_and_sort() {
+        let mut table = table();
+        let narrow = table.render(Some(30));
+        assert!(narrow.lines().all(|line| console::measure_text_width(line) <= 30), "{}", narrow);
+        assert!(narrow.contains("API_KEY  /home/dev/pr…"), "{}", narrow);
+
+        table.sort_by("length", true).unwrap();
+        table.select(&parse_columns("length, key")).unwrap();
+        assert_eq!(table.render(None), "LENGTH  KEY\n   128  密钥\n    32  API_KEY\n     -  DB_URL\n");
+        assert!(table.select(&["nope".to_string()]).is_err());
+
+        assert_eq!(
+            table.to_json(),
+            serde_json::json!([
+                {"length": 128, "key": "密钥"},
+                {"length": 32, "key": "API_KEY"},
+                {"length": null, "key": "DB_URL"},
+            ])
+        );
+    }
+}
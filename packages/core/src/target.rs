@@ -0,0 +1,291 @@
+//! Editing targets in `project.yaml`.
+//!
+//! `shadow-secret target add` appends a target to an existing config as
+//! text, so comments and formatting written by humans (or by
+//! `init-project`) are preserved. It can also insert missing placeholders
+//! into the target file itself.
+
+use crate::config::{Config, TargetConfig};
+use anyhow::{Context, Result};
+use std::path::Path;
+
+/// Placeholder used for a secret key in target files.
+pub fn placeholder(key: &str) -> String {
+    format!("${}", key)
+}
+
+/// Render a target as a YAML sequence item indented by `indent` spaces.
+pub fn render_target(target: &TargetConfig, indent: usize) -> String {
+    let pad = " ".repeat(indent);
+    let mut item = format!("{}- name: {:?}\n", pad, target.name);
+    item.push_str(&format!("{}  path: {:?}\n", pad, target.path));
+    item.push_str(&format!("{}  placeholders:\n", pad));
+    for placeholder in &target.placeholders {
+        item.push_str(&format!("{}    - {:?}\n", pad, placeholder));
+    }
+    if target.allow_outside_project {
+        item.push_str(&format!("{}  allow_outside_project: true\n", pad));
+    }
+    if target.untrusted {
+        item.push_str(&format!("{}  untrusted: true\n", pad));
+    }
+    if target.create_if_missing {
+        item.push_str(&format!("{}  create_if_missing: true\n", pad));
+    }
+    if let Some(template) = &target.template {
+        item.push_str(&format!("{}  template: {:?}\n", pad, template));
+    }
+    item
+}
+
+/// Append `target` to the `targets:` section of a config file's content.
+///
+/// The rest of the file (comments, other sections, ordering) is kept
+/// byte-for-byte. The result is parsed and validated before being returned.
+pub fn add_target(content: &str, target: &TargetConfig) -> Result<String> {
+    let existing: Config = serde_yaml::from_str(content).context("Failed to parse config")?;
+    if existing.targets.iter().any(|t| t.name == target.name) {
+        anyhow::bail!("A target named '{}' already exists", target.name);
+    }
+
+    let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+
+    let section = lines.iter().position(|line| {
+        let line = strip_comment(line).trim_end();
+        line == "targets:" || line == "targets: []"
+    });
+
+    match section {
+        None => {
+            if lines.last().is_some_and(|line| !line.trim().is_empty()) {
+                lines.push(String::new());
+            }
+            lines.push("targets:".to_string());
+            lines.extend(render_target(target, 2).lines().map(str::to_string));
+        }
+        Some(start) => {
+            if strip_comment(&lines[start]).trim_end() == "targets: []" {
+                lines[start] = "targets:".to_string();
+            }
+
+            // The section ends at the next top-level key; trailing comments
+            // and blank lines stay where they are.
+            let mut last_item_line = start;
+            let mut indent = None;
+            for (index, line) in lines.iter().enumerate().skip(start + 1) {
+                let content = strip_comment(line);
+                if content.trim().is_empty() {
+                    continue;
+                }
+                let leading = line.len() - line.trim_start().len();
+                if leading == 0 && !line.starts_with('-') {
+                    break;
+                }
+                if indent.is_none() && line.trim_start().starts_with('-') {
+                    indent = Some(leading);
+                }
+                last_item_line = index;
+            }
+
+            let item = render_target(target, indent.unwrap_or(2));
+            for (offset, line) in item.lines().enumerate() {
+                lines.insert(last_item_line + 1 + offset, line.to_string());
+            }
+        }
+    }
+
+    let mut updated = lines.join("\n");
+    updated.push('\n');
+
+    let config: Config = serde_yaml::from_str(&updated)
+        .context("Internal error: the updated config is not valid YAML")?;
+    config.validate()?;
+
+    Ok(updated)
+}
+
+/// Remove a trailing `# comment` (outside quotes) from a YAML line.
+fn strip_comment(line: &str) -> &str {
+    let mut in_single = false;
+    let mut in_double = false;
+    for (index, c) in line.char_indices() {
+        match c {
+            '\'' if !in_double => in_single = !in_single,
+            '"' if !in_single => in_double = !in_double,
+            '#' if !in_single
+                && !in_double
+                && (index == 0 || line[..index].ends_with(char::is_whitespace)) =>
+            {
+                return &line[..index];
+            }
+            _ => {}
+        }
+    }
+    line
+}
+
+/// Placeholders of `placeholders` that do not appear in `content`.
+pub fn missing_placeholders(content: &str, placeholders: &[String]) -> Vec<String> {
+    placeholders
+        .iter()
+        .filter(|placeholder| !crate::injector::is_all_placeholder(placeholder))
+        .filter(|placeholder| {
+            let key = crate::injector::extract_key_name(placeholder);
+            !content.contains(placeholder.as_str())
+                && !content.contains(&format!("${{{}}}", key))
+                && !content.contains(&format!("${{{}{}", key, crate::injector::DEFAULT_SEPARATOR))
+                && !content.contains(&format!("${{{}{}", key, crate::injector::TRANSFORM_SEPARATOR))
+        })
+        .cloned()
+        .collect()
+}
+
+/// Insert placeholders for `keys` into the content of `path`.
+///
+/// Supports `.env` files (`KEY=$KEY`), YAML (`KEY: "$KEY"`) and JSON
+/// objects (`"KEY": "$KEY"` at the top level).
+pub fn insert_placeholders(path: &Path, content: &str, keys: &[String]) -> Result<String> {
+    let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+
+    let mut updated = content.to_string();
+    match extension {
+        "json" => {
+            let close = updated
+                .rfind('}')
+                .with_context(|| format!("{:?} does not contain a JSON object", path))?;
+            let before = updated[..close].trim_end();
+            let mut insert = String::new();
+            for (index, key) in keys.iter().enumerate() {
+                if index > 0 || !before.ends_with('{') {
+                    insert.push(',');
+                }
+                insert.push_str(&format!("\n  {:?}: {:?}", key, placeholder(key)));
+            }
+            insert.push('\n');
+            updated = format!("{}{}{}", before, insert, &updated[close..]);
+        }
+        "yaml" | "yml" => {
+            push_lines(&mut updated, keys, |key| format!("{}: {:?}", key, placeholder(key)));
+        }
+        _ if extension == "env" || extension == "dotenv" || file_name.starts_with(".env") => {
+            push_lines(&mut updated, keys, |key| format!("{}={}", key, placeholder(key)));
+        }
+        _ => anyhow::bail!(
+            "Cannot insert placeholders into {:?} automatically; add them manually",
+            path
+        ),
+    }
+
+    Ok(updated)
+}
+
+fn push_lines<F: Fn(&str) -> String>(content: &mut String, keys: &[String], line: F) {
+    if !content.is_empty() && !content.ends_with('\n') {
+        content.push('\n');
+    }
+    for key in keys {
+        content.push_str(&line(key));
+        content.push('\n');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    const CONFIG: &str = r#"# Project config
+vault:
+  source: ".enc.env"
+  engine: "sops"
+
+# Targets
+targets:
+  - name: "example-target"
+    path: ".env"
+    placeholders:
+      - "$ALL"
+
+# ================================
+# Example targets:
+#   - name: "env-file"
+
+profile:
+  name: "acme"
+  source: "https://example.invalid/profile.yaml"
+"#;
+
+    fn api_target() -> TargetConfig {
+        TargetConfig {
+            name: "api".to_string(),
+            path: "config/app.json".to_string(),
+            placeholders: vec!["$API_KEY".to_string(), "$DB_URL".to_string()],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_add_target_preserves_comments_and_sections() {
+        let updated = add_target(CONFIG, &api_target()).unwrap();
+
+        assert!(updated.contains(
+            "      - \"$ALL\"\n  - name: \"api\"\n    path: \"config/app.json\"\n    placeholders:\n      - \"$API_KEY\"\n      - \"$DB_URL\"\n\n# ================================"
+        ));
+        assert!(updated.contains("# Project config\n"));
+        assert!(updated.ends_with("source: \"https://example.invalid/profile.yaml\"\n"));
+
+        let config: Config = serde_yaml::from_str(&updated).unwrap();
+        assert_eq!(config.targets.len(), 2);
+        assert_eq!(config.targets[1], api_target());
+    }
+
+    #[test]
+    fn test_add_target_rejects_duplicates() {
+        let mut target = api_target();
+        target.name = "example-target".to_string();
+
+        assert!(add_target(CONFIG, &target).is_err());
+    }
+
+    #[test]
+    fn test_add_target_to_empty_section() {
+        let config = "vault:\n  source: \".enc.env\"\n  engine: \"sops\"\ntargets: []\n";
+
+        let updated = add_target(config, &api_target()).unwrap();
+        let parsed: Config = serde_yaml::from_str(&updated).unwrap();
+        assert_eq!(parsed.targets, vec![api_target()]);
+    }
+
+    #[test]
+    fn test_missing_placeholders() {
+        let placeholders = vec!["$API_KEY".to_string(), "$DB_URL".to_string()];
+
+        assert_eq!(
+            missing_placeholders(r#"{"api": "${API_KEY}"}"#, &placeholders),
+            vec!["$DB_URL".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_insert_placeholders() {
+        let keys = vec!["DB_URL".to_string()];
+
+        let json = insert_placeholders(
+            &PathBuf::from("app.json"),
+            "{\n  \"api\": \"$API_KEY\"\n}\n",
+            &keys,
+        )
+        .unwrap();
+        assert_eq!(json, "{\n  \"api\": \"$API_KEY\",\n  \"DB_URL\": \"$DB_URL\"\n}\n");
+        serde_json::from_str::<serde_json::Value>(&json).unwrap();
+
+        let empty = insert_placeholders(&PathBuf::from("app.json"), "{}", &keys).unwrap();
+        assert_eq!(empty, "{\n  \"DB_URL\": \"$DB_URL\"\n}");
+
+        let env = insert_placeholders(&PathBuf::from(".env.local"), "A=1", &keys).unwrap();
+        assert_eq!(env, "A=1\nDB_URL=$DB_URL\n");
+
+        assert!(insert_placeholders(&PathBuf::from("app.toml"), "", &keys).is_err());
+    }
+}
@@ -0,0 +1,480 @@
+//! Recording and replaying external CLI interactions.
+//!
+//! Every sops, age and vercel invocation goes through a
+//! [`CommandRunner`] (see [`crate::process`]). This module provides runners
+//! that replace real processes:
+//!
+//! - [`Recorder`]: the program runs normally and each invocation (program,
+//!   arguments, exit code, stdout, stderr) is appended to a fixture file
+//! - [`Replayer`]: nothing is spawned; the recorded output is returned
+//!   instead, so tests and CI run without the binaries installed
+//! - [`MockRunner`]: stubbed responses and a log of calls, for unit tests
+//!
+//! Recording and replay are enabled for the whole process with [`record`] /
+//! [`replay`], or with `SHADOW_SECRET_RECORD=<fixture>` /
+//! `SHADOW_SECRET_REPLAY=<fixture>` for the CLI (see [`init_from_env`]).
+//! Any runner can be scoped to one test with [`process::with_runner`].
+//!
+//! Occurrences of the current directory in arguments are stored as
+//! [`CWD_PLACEHOLDER`], so fixtures recorded in one temporary directory
+//! replay in another. Stdin is never recorded, but recorded stdout does
+//! contain whatever the program printed (decrypted test secrets included):
+//! record against test vaults only.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use crate::process::{self, CommandRunner, SystemRunner};
+use std::process::{Command, ExitStatus, Output};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Environment variable naming a fixture to record into.
+pub const RECORD_ENV: &str = "SHADOW_SECRET_RECORD";
+
+/// Environment variable naming a fixture to replay.
+pub const REPLAY_ENV: &str = "SHADOW_SECRET_REPLAY";
+
+/// Stands for the current directory in recorded arguments.
+pub const CWD_PLACEHOLDER: &str = "{cwd}";
+
+/// One recorded invocation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Interaction {
+    /// Program name, without directory or extension (`sops`)
+    pub program: String,
+    pub args: Vec<String>,
+    /// Exit code (`-1` when the program was killed by a signal)
+    pub status: i32,
+    #[serde(default)]
+    pub stdout: String,
+    #[serde(default)]
+    pub stderr: String,
+}
+
+impl Interaction {
+    /// The recorded result as a process [`Output`].
+    pub fn output(&self) -> Output {
+        Output {
+            status: exit_status(self.status),
+            stdout: self.stdout.clone().into_bytes(),
+            stderr: self.stderr.clone().into_bytes(),
+        }
+    }
+}
+
+/// A fixture: recorded interactions, in the order they happened.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Cassette {
+    pub interactions: Vec<Interaction>,
+    /// Interactions already replayed
+    #[serde(skip)]
+    used: Vec<bool>,
+}
+
+impl Cassette {
+    /// Create a cassette from interactions.
+    pub fn new(interactions: Vec<Interaction>) -> Self {
+        Self {
+            interactions,
+            used: Vec::new(),
+        }
+    }
+
+    /// Load a fixture file (JSON).
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read fixture: {:?}", path))?;
+        let cassette: Cassette = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse fixture: {:?}", path))?;
+        Ok(cassette)
+    }
+
+    /// Write the fixture file (JSON).
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory: {:?}", parent))?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)? + "\n")
+            .with_context(|| format!("Failed to write fixture: {:?}", path))
+    }
+
+    /// Take the first not yet replayed interaction matching `program` and
+    /// `args`.
+    ///
+    /// Identical invocations are answered in recording order, which lets a
+    /// fixture replay a retry (first failure, then success).
+    pub fn take(&mut self, program: &str, args: &[String]) -> Option<&Interaction> {
+        self.used.resize(self.interactions.len(), false);
+
+        let index = self
+            .interactions
+            .iter()
+            .enumerate()
+            .position(|(i, interaction)| {
+                !self.used[i] && interaction.program == program && interaction.args == args
+            })?;
+        self.used[index] = true;
+        Some(&self.interactions[index])
+    }
+}
+
+/// Runs programs for real and appends each invocation to a fixture file
+/// (rewritten after each one, so nothing is lost if the process exits).
+pub struct Recorder {
+    path: PathBuf,
+    cassette: Mutex<Cassette>,
+}
+
+impl Recorder {
+    /// Start an empty fixture at `path`.
+    pub fn new(path: &Path) -> Result<Self> {
+        let cassette = Cassette::default();
+        cassette.save(path)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            cassette: Mutex::new(cassette),
+        })
+    }
+}
+
+impl CommandRunner for Recorder {
+    fn run(&self, command: &mut Command, stdin: Option<&[u8]>) -> std::io::Result<Output> {
+        self.run_within(command, stdin, None)
+    }
+
+    fn run_within(&self, command: &mut Command, stdin: Option<&[u8]>, timeout: Option<Duration>) -> std::io::Result<Output> {
+        let (program, args) = invocation(command);
+        let output = SystemRunner.run_within(command, stdin, timeout)?;
+
+        let mut cassette = self.cassette.lock().unwrap_or_else(|e| e.into_inner());
+        cassette.interactions.push(Interaction {
+            program,
+            args,
+            status: output.status.code().unwrap_or(-1),
+            stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        });
+        cassette
+            .save(&self.path)
+            .map_err(|e| std::io::Error::other(format!("{:#}", e)))?;
+
+        Ok(output)
+    }
+}
+
+/// Answers invocations from a [`Cassette`] without spawning anything.
+pub struct Replayer {
+    cassette: Mutex<Cassette>,
+}
+
+impl Replayer {
+    pub fn new(cassette: Cassette) -> Self {
+        Self {
+            cassette: Mutex::new(cassette),
+        }
+    }
+}
+
+impl CommandRunner for Replayer {
+    fn run(&self, command: &mut Command, _stdin: Option<&[u8]>) -> std::io::Result<Output> {
+        let (program, args) = invocation(command);
+        self.cassette
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take(&program, &args)
+            .map(Interaction::output)
+            .ok_or_else(|| not_found(&program, &args))
+    }
+}
+
+/// An invocation received by a [`MockRunner`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Call {
+    pub program: String,
+    pub args: Vec<String>,
+    /// Environment variables set on the command (e.g. `SOPS_AGE_KEY_FILE`)
+    pub env: Vec<(String, String)>,
+    pub stdin: Option<Vec<u8>>,
+}
+
+/// Stubbed responses plus a log of every call, for unit tests.
+///
+/// Unlike a [`Replayer`], a stubbed response answers any number of
+/// identical calls. Calls without a stub fail as if the program were not
+/// installed.
+///
+/// ```
+/// use shadow_secret::process;
+/// use shadow_secret::testing::MockRunner;
+/// use shadow_secret::vault::Vault;
+/// use std::sync::Arc;
+///
+/// let mock = Arc::new(
+///     MockRunner::new()
+///         .respond("sops", &["--version"], 0, "sops 3.8.1")
+///         .respond("sops", &["-d", "secrets.enc.env"], 0, "API_KEY=sk_test\n"),
+/// );
+/// let vault = process::with_runner(mock.clone(), || Vault::load("secrets.enc.env", None)).unwrap();
+///
+/// assert_eq!(vault.get("API_KEY").unwrap(), "sk_test");
+/// assert_eq!(mock.calls().len(), 2);
+/// ```
+#[derive(Default)]
+pub struct MockRunner {
+    responses: Vec<Interaction>,
+    calls: Mutex<Vec<Call>>,
+}
+
+impl MockRunner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answer `program args` with `status` and `stdout`.
+    pub fn respond(mut self, program: &str, args: &[&str], status: i32, stdout: &str) -> Self {
+        self.responses.push(Interaction {
+            program: program.to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            status,
+            stdout: stdout.to_string(),
+            stderr: String::new(),
+        });
+        self
+    }
+
+    /// Answer `program args` with a full recorded interaction (e.g. to set
+    /// stderr).
+    pub fn respond_with(mut self, interaction: Interaction) -> Self {
+        self.responses.push(interaction);
+        self
+    }
+
+    /// Every call received so far, in order.
+    pub fn calls(&self) -> Vec<Call> {
+        self.calls.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+impl CommandRunner for MockRunner {
+    fn run(&self, command: &mut Command, stdin: Option<&[u8]>) -> std::io::Result<Output> {
+        let (program, args) = invocation(command);
+        let env = command
+            .get_envs()
+            .filter_map(|(key, value)| {
+                Some((key.to_string_lossy().to_string(), value?.to_string_lossy().to_string()))
+            })
+            .collect();
+
+        self.calls.lock().unwrap_or_else(|e| e.into_inner()).push(Call {
+            program: program.clone(),
+            args: args.clone(),
+            env,
+            stdin: stdin.map(<[u8]>::to_vec),
+        });
+
+        self.responses
+            .iter()
+            .find(|response| response.program == program && response.args == args)
+            .map(Interaction::output)
+            .ok_or_else(|| not_found(&program, &args))
+    }
+}
+
+/// Record every external invocation of the process into `path`.
+pub fn record(path: &Path) -> Result<()> {
+    process::set_runner(Arc::new(Recorder::new(path)?));
+    Ok(())
+}
+
+/// Answer every external invocation of the process from the fixture at
+/// `path`.
+pub fn replay(path: &Path) -> Result<()> {
+    replay_cassette(Cassette::load(path)?);
+    Ok(())
+}
+
+/// Answer every external invocation of the process from `cassette`.
+pub fn replay_cassette(cassette: Cassette) {
+    process::set_runner(Arc::new(Replayer::new(cassette)));
+}
+
+/// Go back to running programs normally.
+pub fn stop() {
+    process::reset_runner();
+}
+
+/// Enable recording or replay from [`RECORD_ENV`] / [`REPLAY_ENV`].
+pub fn init_from_env() -> Result<()> {
+    match (std::env::var_os(RECORD_ENV), std::env::var_os(REPLAY_ENV)) {
+        (Some(_), Some(_)) => anyhow::bail!("Set only one of ${} and ${}", RECORD_ENV, REPLAY_ENV),
+        (Some(path), None) => record(Path::new(&path)),
+        (None, Some(path)) => replay(Path::new(&path)),
+        (None, None) => Ok(()),
+    }
+}
+
+/// Error returned for an invocation nobody recorded or stubbed.
+fn not_found(program: &str, args: &[String]) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::NotFound,
+        format!("no recorded interaction for '{} {}'", program, args.join(" ")),
+    )
+}
+
+/// Program name and arguments of `command`, as stored in fixtures.
+fn invocation(command: &Command) -> (String, Vec<String>) {
+    let program = Path::new(command.get_program())
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let cwd = std::env::current_dir()
+        .map(|cwd| cwd.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let args = command
+        .get_args()
+        .map(|arg| {
+            let arg = arg.to_string_lossy();
+            if cwd.len() > 1 {
+                arg.replace(&cwd, CWD_PLACEHOLDER)
+            } else {
+                arg.to_string()
+            }
+        })
+        .collect();
+
+    (program, args)
+}
+
+#[cfg(unix)]
+fn exit_status(code: i32) -> ExitStatus {
+    use std::os::unix::process::ExitStatusExt;
+    if code < 0 {
+        ExitStatus::from_raw(libc::SIGKILL)
+    } else {
+        ExitStatus::from_raw((code & 0xff) << 8)
+    }
+}
+
+#[cfg(windows)]
+fn exit_status(code: i32) -> ExitStatus {
+    use std::os::windows::process::ExitStatusExt;
+    ExitStatus::from_raw(code as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn interaction(args: &[&str], status: i32, stdout: &str) -> Interaction {
+        Interaction {
+            program: "sops".to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            status,
+            stdout: stdout.to_string(),
+            stderr: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_cassette_replays_in_order() {
+        let mut cassette = Cassette::new(vec![
+            interaction(&["-d", "a.env"], 1, ""),
+            interaction(&["--version"], 0, "sops 3.8.1"),
+            interaction(&["-d", "a.env"], 0, "API_KEY=sk_test\n"),
+        ]);
+        let args = vec!["-d".to_string(), "a.env".to_string()];
+
+        assert!(!cassette.take("sops", &args).unwrap().output().status.success());
+        let output = cassette.take("sops", &args).unwrap().output();
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"API_KEY=sk_test\n");
+        assert!(cassette.take("sops", &args).is_none());
+        assert!(cassette.take("age", &["--version".to_string()]).is_none());
+    }
+
+    #[test]
+    fn test_invocation_normalizes_program_and_cwd() {
+        let cwd = std::env::current_dir().unwrap();
+        let mut command = Command::new("/usr/local/bin/sops");
+        command.arg("-d").arg(cwd.join(".enc.env"));
+
+        let (program, args) = invocation(&command);
+        assert_eq!(program, "sops");
+        assert_eq!(
+            args,
+            vec![
+                "-d".to_string(),
+                format!("{}{}.enc.env", CWD_PLACEHOLDER, std::path::MAIN_SEPARATOR)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_mock_runner_reaches_concurrent_decryption() {
+        let mock = Arc::new(
+            MockRunner::new()
+                .respond("sops", &["--version"], 0, "sops 3.8.1")
+                .respond("sops", &["-d", "a.env"], 0, "API_KEY=sk_a\n")
+                .respond("sops", &["-d", "b.env"], 0, "API_KEY=sk_b\n"),
+        );
+
+        let vault = process::with_runner(mock.clone(), || {
+            crate::vault::Vault::load_many(&["a.env", "b.env"], Some("/keys/age.txt"))
+        })
+        .unwrap();
+
+        assert_eq!(vault.get("API_KEY"), Some(&"sk_b".to_string()));
+        let decrypts: Vec<Call> = mock
+            .calls()
+            .into_iter()
+            .filter(|call| call.args[0] == "-d")
+            .collect();
+        assert_eq!(decrypts.len(), 2);
+        assert!(decrypts.iter().all(|call| call.env
+            == vec![("SOPS_AGE_KEY_FILE".to_string(), "/keys/age.txt".to_string())]));
+    }
+
+    #[test]
+    fn test_mock_runner_sees_vercel_stdin() {
+        let mock = Arc::new(
+            MockRunner::new()
+                .respond("vercel", &["--version"], 0, "Vercel CLI 37.0.0")
+                .respond("vercel", &["env", "add", "NEW_KEY", "--yes"], 0, "")
+                .respond("vercel", &["env", "rm", "OLD_KEY", "--yes"], 0, ""),
+        );
+
+        process::with_runner(mock.clone(), || {
+            tokio::runtime::Builder::new_current_thread()
+                .build()
+                .unwrap()
+                .block_on(crate::cloud::vercel::rename_vercel_env_var(
+                    "OLD_KEY",
+                    "NEW_KEY",
+                    "sk_test_123",
+                    None,
+                    None,
+                    &Default::default(),
+                ))
+        })
+        .unwrap();
+
+        let calls = mock.calls();
+        assert_eq!(calls[1].args, vec!["env", "add", "NEW_KEY", "--yes"]);
+        assert_eq!(calls[1].stdin.as_deref(), Some(&b"sk_test_123\n"[..]));
+        assert_eq!(calls[2].args, vec!["env", "rm", "OLD_KEY", "--yes"]);
+        assert!(process::with_runner(mock, || process::output(&mut Command::new("age"))).is_err());
+    }
+
+    #[test]
+    fn test_cassette_file_round_trip() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("fixtures/sops.json");
+        let cassette = Cassette::new(vec![interaction(&["--version"], 0, "sops 3.8.1")]);
+
+        cassette.save(&path).unwrap();
+        assert_eq!(Cassette::load(&path).unwrap(), cassette);
+    }
+}
@@ -0,0 +1,296 @@
+//! System tray companion (`shadow-secret tray`).
+//!
+//! Shows whether the running agent's targets are unlocked, with Unlock,
+//! Lock and Extend actions, for long sessions where the terminal running the
+//! agent gets buried. The tray holds no secret and decrypts nothing: it only
+//! sends requests on the agent's socket (see [`crate::agent`]), so it can be
+//! closed and restarted at will.
+//!
+//! The tray icon itself needs the `tray` feature (`cargo install
+//! shadow-secret --features tray`); on Linux it links GTK and
+//! libappindicator.
+
+use crate::agent::{self, Request, Response};
+use anyhow::Result;
+use std::path::Path;
+use std::time::Duration;
+
+/// How often the tray refreshes the agent's state.
+pub const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// What the tray shows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TrayState {
+    /// No agent answers on the socket
+    NoAgent,
+    Locked,
+    Unlocked {
+        /// Seconds before the agent locks the targets again, if it ever does
+        expires_in: Option<u64>,
+    },
+}
+
+/// Menu actions that apply to a [`TrayState`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Actions {
+    pub unlock: bool,
+    pub lock: bool,
+    pub extend: bool,
+}
+
+impl TrayState {
+    /// The state an agent reports (`None`: no agent answered).
+    pub fn from_response(response: Option<&Response>) -> Self {
+        match response {
+            None => Self::NoAgent,
+            Some(response) if response.unlocked == Some(true) => Self::Unlocked {
+                expires_in: response.expires_in,
+            },
+            Some(_) => Self::Locked,
+        }
+    }
+
+    /// Ask the agent listening on `socket`.
+    pub fn query(socket: &Path) -> Self {
+        Self::from_response(agent::request(socket, &Request::Ping).ok().as_ref())
+    }
+
+    /// One line for the tooltip and the top of the menu, e.g. "Unlocked,
+    /// locks in 14m".
+    pub fn label(&self) -> String {
+        match self {
+            Self::NoAgent => "No agent running (shadow-secret agent start)".to_string(),
+            Self::Locked => "Locked".to_string(),
+            Self::Unlocked { expires_in: None } => "Unlocked".to_string(),
+            Self::Unlocked { expires_in: Some(seconds) } => format!("Unlocked, locks in {}", remaining(*seconds)),
+        }
+    }
+
+    pub fn actions(&self) -> Actions {
+        match self {
+            Self::NoAgent => Actions { unlock: false, lock: false, extend: false },
+            Self::Locked => Actions { unlock: true, lock: false, extend: false },
+            Self::Unlocked { expires_in } => Actions {
+                unlock: false,
+                lock: true,
+                extend: expires_in.is_some(),
+            },
+        }
+    }
+}
+
+/// Options of `shadow-secret tray`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TrayOptions {
+    /// How long Unlock injects the targets (`None`: the agent's session
+    /// timeout)
+    pub unlock_for: Option<Duration>,
+    /// How much Extend adds
+    pub extend_by: Duration,
+}
+
+impl TrayOptions {
+    /// The agent request behind a menu action.
+    pub fn unlock_request(&self) -> Request {
+        Request::Unlock {
+            seconds: self.unlock_for.map(|limit| limit.as_secs()),
+        }
+    }
+
+    pub fn extend_request(&self) -> Request {
+        Request::Extend {
+            seconds: self.extend_by.as_secs(),
+        }
+    }
+}
+
+/// Send `request` to the agent on `socket`.
+///
+/// # Returns
+///
+/// The state the agent reports afterwards.
+pub fn send(socket: &Path, request: &Request) -> Result<TrayState> {
+    let response = agent::request(socket, request)?.into_result()?;
+    Ok(TrayState::from_response(Some(&response)))
+}
+
+/// `seconds` for a menu: "1h 05m", "14m", "42s".
+pub fn remaining(seconds: u64) -> String {
+    match seconds {
+        0..=59 => format!("{}s", seconds),
+        60..=3599 => format!("{}m", seconds / 60),
+        _ => format!("{}h {:02}m", seconds / 3600, seconds % 3600 / 60),
+    }
+}
+
+/// Show the tray icon until Quit, following the agent on `socket`.
+///
+/// # Errors
+///
+/// Fails if the menu cannot be built; once the event loop runs, the process
+/// exits with it.
+#[cfg(feature = "tray")]
+pub fn run(socket: std::path::PathBuf, options: TrayOptions) -> Result<()> {
+    use std::time::Instant;
+    use tao::event::{Event, StartCause};
+    use tao::event_loop::{ControlFlow, EventLoopBuilder};
+    use tray_icon::menu::{Menu, MenuEvent, MenuItem, PredefinedMenuItem};
+    use tray_icon::TrayIconBuilder;
+
+    let event_loop = EventLoopBuilder::<MenuEvent>::with_user_event().build();
+    // Wake the event loop on each click instead of waiting for the next poll
+    let proxy = event_loop.create_proxy();
+    MenuEvent::set_event_handler(Some(move |event| {
+        let _ = proxy.send_event(event);
+    }));
+
+    let status = MenuItem::new(TrayState::NoAgent.label(), false, None);
+    let unlock = MenuItem::new("Unlock", false, None);
+    let lock = MenuItem::new("Lock", false, None);
+    let extend = MenuItem::new(format!("Extend by {}", remaining(options.extend_by.as_secs())), false, None);
+    let quit = MenuItem::new("Quit", true, None);
+    let menu = Menu::new();
+    menu.append_items(&[
+        &status,
+        &PredefinedMenuItem::separator(),
+        &unlock,
+        &lock,
+        &extend,
+        &PredefinedMenuItem::separator(),
+        &quit,
+    ])?;
+
+    let mut menu = Some(menu);
+    let mut tray = None;
+    let mut shown: Option<TrayState> = None;
+    let mut failure: Option<String> = None;
+    event_loop.run(move |event, _, control_flow| {
+        *control_flow = ControlFlow::WaitUntil(Instant::now() + POLL_INTERVAL);
+        match event {
+            // The icon can only be created once the loop runs (macOS)
+            Event::NewEvents(StartCause::Init) => {
+                let built = TrayIconBuilder::new()
+                    .with_menu(Box::new(menu.take().expect("the tray is built once")))
+                    .with_tooltip("Shadow Secret")
+                    .with_icon(icon(&TrayState::NoAgent))
+                    .build();
+                match built {
+                    Ok(built) => tray = Some(built),
+                    Err(e) => {
+                        crate::say_err!("❌ Failed to create the tray icon: {}", e);
+                        *control_flow = ControlFlow::ExitWithCode(1);
+                        return;
+                    }
+                }
+            }
+            Event::NewEvents(StartCause::ResumeTimeReached { .. }) => {}
+            Event::UserEvent(event) => {
+                let request = if event.id == *quit.id() {
+                    *control_flow = ControlFlow::Exit;
+                    return;
+                } else if event.id == *unlock.id() {
+                    options.unlock_request()
+                } else if event.id == *lock.id() {
+                    Request::Lock
+                } else if event.id == *extend.id() {
+                    options.extend_request()
+                } else {
+                    return;
+                };
+                failure = send(&socket, &request).err().map(|e| format!("Failed: {:#}", e));
+            }
+            _ => return,
+        }
+
+        let Some(tray) = &tray else {
+            return;
+        };
+        let state = TrayState::query(&socket);
+        let actions = state.actions();
+        unlock.set_enabled(actions.unlock);
+        lock.set_enabled(actions.lock);
+        extend.set_enabled(actions.extend);
+        status.set_text(failure.clone().unwrap_or_else(|| state.label()));
+        let _ = tray.set_tooltip(Some(format!("Shadow Secret: {}", state.label())));
+        if shown.as_ref().map(std::mem::discriminant) != Some(std::mem::discriminant(&state)) {
+            let _ = tray.set_icon(Some(icon(&state)));
+        }
+        shown = Some(state);
+    })
+}
+
+/// A dot colored after `state`: gray without agent, green when locked,
+/// orange while secrets are injected.
+#[cfg(feature = "tray")]
+fn icon(state: &TrayState) -> tray_icon::Icon {
+    const SIZE: u32 = 32;
+
+    let [red, green, blue] = match state {
+        TrayState::NoAgent => [0x9e, 0x9e, 0x9e],
+        TrayState::Locked => [0x2e, 0x7d, 0x32],
+        TrayState::Unlocked { .. } => [0xef, 0x6c, 0x00],
+    };
+    let center = (SIZE - 1) as f32 / 2.0;
+    let mut rgba = Vec::with_capacity((SIZE * SIZE * 4) as usize);
+    for y in 0..SIZE {
+        for x in 0..SIZE {
+            let distance = ((x as f32 - center).powi(2) + (y as f32 - center).powi(2)).sqrt();
+            let alpha = if distance <= center - 1.0 { 0xff } else { 0 };
+            rgba.extend_from_slice(&[red, green, blue, alpha]);
+        }
+    }
+    tray_icon::Icon::from_rgba(rgba, SIZE, SIZE).expect("the icon has SIZE x SIZE pixels")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_state_from_agent_responses() {
+        assert_eq!(TrayState::from_response(None), TrayState::NoAgent);
+
+        let locked = Response {
+            ok: true,
+            unlocked: Some(false),
+            ..Default::default()
+        };
+        assert_eq!(TrayState::from_response(Some(&locked)), TrayState::Locked);
+        assert_eq!(TrayState::Locked.actions(), Actions { unlock: true, lock: false, extend: false });
+
+        let unlocked = Response {
+            ok: true,
+            unlocked: Some(true),
+            expires_in: Some(3900),
+            ..Default::default()
+        };
+        let state = TrayState::from_response(Some(&unlocked));
+        assert_eq!(state, TrayState::Unlocked { expires_in: Some(3900) });
+        assert_eq!(state.label(), "Unlocked, locks in 1h 05m");
+        assert_eq!(state.actions(), Actions { unlock: false, lock: true, extend: true });
+
+        // Nothing to extend when the targets stay unlocked until locked
+        assert!(!TrayState::Unlocked { expires_in: None }.actions().extend);
+        assert_eq!(remaining(42), "42s");
+        assert_eq!(remaining(14 * 60 + 59), "14m");
+    }
+
+    #[test]
+    fn test_requests_sent_by_the_menu() {
+        let options = TrayOptions {
+            unlock_for: Some(Duration::from_secs(3600)),
+            extend_by: Duration::from_secs(900),
+        };
+        assert_eq!(
+            serde_json::to_string(&options.unlock_request()).unwrap(),
+            r#"{"op":"unlock","seconds":3600}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&options.extend_request()).unwrap(),
+            r#"{"op":"extend","seconds":900}"#
+        );
+
+        let options = TrayOptions { unlock_for: None, ..options };
+        assert_eq!(serde_json::to_string(&options.unlock_request()).unwrap(), r#"{"op":"unlock"}"#);
+    }
+}
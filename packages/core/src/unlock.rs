@@ -0,0 +1,468 @@
+//! Injecting secrets into targets (`unlock` / `unlock-global`).
+//!
+//! [`UnlockSession::unlock`] injects a loaded vault into every target and
+//! registers what must be undone (backups, created files) with
+//! [`crate::cleaner`], so signal handlers restore the templates even if the
+//! session is never locked explicitly. It prints nothing: the returned
+//! [`UnlockReport`] says what happened to each target, for the CLI or an
+//! embedding application to render.
+
+use crate::cleaner;
+use crate::config::TargetConfig;
+use crate::vault::Vault;
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// What happened to one target.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TargetOutcome {
+    pub name: String,
+    pub path: String,
+    /// The file did not exist and was created from its template (it is
+    /// deleted on lock instead of restored)
+    pub created: bool,
+    /// The target received fake values only
+    pub untrusted: bool,
+    /// The target lives outside the project (`allow_outside_project`)
+    pub outside_project: bool,
+    /// Other users can read the file while secrets are injected
+    pub shared_readable: bool,
+    /// Placeholders configured for the target
+    pub placeholders: usize,
+    /// Placeholders that had a value in the vault
+    pub resolved: usize,
+    /// Keys whose placeholders were replaced in the file, sorted
+    pub replaced: Vec<String>,
+    /// Listed keys whose placeholders were left in the file (no value),
+    /// sorted
+    pub left: Vec<String>,
+    pub duration: Duration,
+}
+
+/// Result of [`UnlockSession::unlock`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct UnlockReport {
+    /// One entry per target, in config order
+    pub targets: Vec<TargetOutcome>,
+    pub duration: Duration,
+}
+
+/// Targets holding injected secrets until [`UnlockSession::lock`].
+#[derive(Debug)]
+pub struct UnlockSession {
+    report: UnlockReport,
+    unlocked_at: Instant,
+}
+
+impl UnlockSession {
+    /// Inject `vault` into every target, in order. When the session is
+    /// journaled (see [`crate::journal::begin`]), each target is recorded
+    /// in the journal before it is modified.
+    ///
+    /// # Errors
+    ///
+    /// Fails before touching any file if a `strict` target has unresolved
+    /// placeholders (see [`check_strict`]). Otherwise stops at the first
+    /// target that cannot be created or injected, or that belongs to another
+    /// user (see [`crate::isolation::check_owner`]). Targets injected before
+    /// it are rolled back (see [`cleaner::rollback`]): no file keeps secrets
+    /// from a failed unlock.
+    pub fn unlock(targets: &[TargetConfig], vault: &Vault) -> Result<Self> {
+        let started = Instant::now();
+        let mut report = UnlockReport::default();
+        check_strict(targets, vault)?;
+
+        let mut touched = Vec::new();
+        for target in targets {
+            touched.push(target.path.clone());
+            match inject_target(target, vault) {
+                Ok(outcome) => report.targets.push(outcome),
+                Err(e) => {
+                    return Err(match cleaner::rollback(&touched) {
+                        Ok(()) => e.context("Unlock aborted, targets already injected were restored"),
+                        Err(rollback) => e.context(format!("Unlock aborted, rolling back failed: {:#}", rollback)),
+                    })
+                }
+            }
+        }
+
+        report.duration = started.elapsed();
+        Ok(Self {
+            report,
+            unlocked_at: Instant::now(),
+        })
+    }
+
+    /// What happened to each target.
+    pub fn report(&self) -> &UnlockReport {
+        &self.report
+    }
+
+    /// Time since every target was injected.
+    pub fn elapsed(&self) -> Duration {
+        self.unlocked_at.elapsed()
+    }
+
+    /// Keys injected into at least one target, sorted.
+    pub fn injected_keys(&self) -> Vec<String> {
+        let keys: std::collections::BTreeSet<&String> =
+            self.report.targets.iter().flat_map(|target| &target.replaced).collect();
+        keys.into_iter().cloned().collect()
+    }
+
+    /// Write the injection report of this session (see [`crate::report`]).
+    pub fn write_report(&self) -> Result<PathBuf> {
+        let report = crate::report::SessionReport::from_unlock(&self.report);
+        crate::report::write(&crate::report::reports_dir()?, &report)
+    }
+
+    /// Restore every target (and stop blocking processes first, see
+    /// [`cleaner::cleanup_and_restore`]).
+    pub fn lock(self) {
+        cleaner::cleanup_and_restore();
+    }
+}
+
+/// Fail if any `strict` target has placeholders without a value in
+/// `vault`, listing all of them.
+pub fn check_strict(targets: &[TargetConfig], vault: &Vault) -> Result<()> {
+    let mut unresolved = Vec::new();
+    for target in targets.iter().filter(|target| target.strict) {
+        let missing = crate::injector::unresolved_placeholders(&vault.secrets_for(target), &target.placeholders);
+        if !missing.is_empty() {
+            unresolved.push(format!("  - {}: {}", target.name, missing.join(", ")));
+        }
+    }
+
+    if unresolved.is_empty() {
+        return Ok(());
+    }
+    anyhow::bail!(
+        "Unresolved placeholders in strict targets (no file was modified):\n{}",
+        unresolved.join("\n")
+    )
+}
+
+fn inject_target(target: &TargetConfig, vault: &Vault) -> Result<TargetOutcome> {
+    let started = Instant::now();
+    let path = Path::new(&target.path);
+
+    // Opt-in: create a missing file from its template, deleted on lock
+    let created = target.create_if_missing && !path.exists();
+    if created {
+        crate::journal::record(&target.path, None)?;
+        crate::injector::create_target_file(path, target.template.as_deref().unwrap_or_default())?;
+        cleaner::register_created_file(&target.path);
+    }
+
+    // Never write secrets into another user's file (shared machines)
+    crate::isolation::check_owner(path)?;
+
+    if target.secure_wipe {
+        cleaner::register_secure_wipe(&target.path);
+    }
+
+    // Persist the original content before touching the file (crash recovery)
+    if !created && crate::journal::is_active() {
+        if let Ok(original) = std::fs::read_to_string(path) {
+            crate::journal::record(&target.path, Some(&original))?;
+        }
+    }
+
+    // Untrusted targets only ever see fake values
+    let resolved = vault.secrets_for(target);
+    let resolved_count = resolved.len();
+    let target_secrets = if target.untrusted {
+        crate::fake::fake_secrets(&resolved)
+    } else {
+        resolved
+    };
+
+    if target.strict {
+        crate::injector::ensure_resolved(path, &target_secrets, &target.placeholders)?;
+    }
+    let (target_path, placeholders, style) = (target.path.clone(), target.placeholders.clone(), target.style()?);
+    let preserve_formatting = target.preserve_formatting;
+    let (replaced, left) = within(crate::process::timeouts().inject, &target.path, move || {
+        let path = Path::new(&target_path);
+        let backup = match &style {
+            _ if preserve_formatting => {
+                crate::injector::inject_secrets_verbatim(path, &target_secrets, &placeholders, style.as_ref())
+            }
+            Some(style) => crate::injector::inject_secrets_styled(path, &target_secrets, &placeholders, style),
+            None => crate::injector::inject_secrets(path, &target_secrets, &placeholders),
+        }
+        .with_context(|| format!("Failed to inject secrets into: {}", target_path))?;
+
+        // Register backup for cleanup (created files are deleted instead)
+        if !created {
+            cleaner::register_backup(&target_path, backup.content());
+        }
+        Ok(crate::injector::placeholder_coverage(backup.content(), &target_secrets, &placeholders, style.as_ref()))
+    })?;
+
+    Ok(TargetOutcome {
+        name: target.name.clone(),
+        path: target.path.clone(),
+        created,
+        untrusted: target.untrusted,
+        outside_project: target.allow_outside_project,
+        shared_readable: crate::isolation::is_shared_readable(path),
+        placeholders: target.placeholders.len(),
+        resolved: resolved_count,
+        replaced,
+        left,
+        duration: started.elapsed(),
+    })
+}
+
+/// A target other processes have open.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BusyTarget {
+    pub path: String,
+    /// PID and name of each process holding the file
+    pub processes: Vec<(u32, String)>,
+}
+
+impl BusyTarget {
+    /// One-line description, e.g. "config.json is open in node (PID 4242)".
+    pub fn message(&self) -> String {
+        let processes: Vec<String> = self
+            .processes
+            .iter()
+            .map(|(pid, name)| format!("{} (PID {})", name, pid))
+            .collect();
+        format!("{} is open in {}", self.path, processes.join(", "))
+    }
+}
+
+/// Targets other processes have open (see
+/// [`cleaner::open_file_holders`]), to check before unlocking: on Windows a
+/// file held without write sharing cannot be replaced, and the unlock would
+/// stop halfway through the targets.
+pub fn busy_targets(targets: &[TargetConfig]) -> Result<Vec<BusyTarget>> {
+    let mut busy = Vec::new();
+    for target in targets {
+        let path = PathBuf::from(&target.path);
+        if !path.exists() {
+            continue;
+        }
+        let mut holders = cleaner::open_file_holders(std::slice::from_ref(&path))?;
+        holders.remove(&std::process::id());
+        if holders.is_empty() {
+            continue;
+        }
+
+        let mut sys = sysinfo::System::new();
+        let pids: Vec<sysinfo::Pid> = holders.iter().map(|pid| sysinfo::Pid::from_u32(*pid)).collect();
+        sys.refresh_processes(sysinfo::ProcessesToUpdate::Some(&pids));
+        let processes = holders
+            .into_iter()
+            .map(|pid| {
+                let name = sys
+                    .process(sysinfo::Pid::from_u32(pid))
+                    .map(|process| process.name().to_string_lossy().to_string())
+                    .unwrap_or_else(|| "unknown process".to_string());
+                (pid, name)
+            })
+            .collect();
+        busy.push(BusyTarget {
+            path: target.path.clone(),
+            processes,
+        });
+    }
+    Ok(busy)
+}
+
+/// Run `inject` on a thread of its own, giving up on it after `timeout`
+/// (a target on a hung network mount, a FIFO nobody reads). An injection
+/// that finishes late still registers its backup, so lock restores it.
+fn within<T: Send + 'static>(
+    timeout: Option<Duration>,
+    path: &str,
+    inject: impl FnOnce() -> Result<T> + Send + 'static,
+) -> Result<T> {
+    let Some(timeout) = timeout else {
+        return inject();
+    };
+
+    let (sender, receiver) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let _ = sender.send(inject());
+    });
+    match receiver.recv_timeout(timeout) {
+        Ok(result) => result,
+        Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {
+            anyhow::bail!("Injecting secrets into {} timed out after {:?}", path, timeout)
+        }
+        Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => {
+            anyhow::bail!("Injecting secrets into {} panicked", path)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_unlock_reports_each_target() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let existing = temp_dir.path().join("app.env");
+        std::fs::write(&existing, "API_KEY=$API_KEY\nDB=$DB_URL\n").unwrap();
+
+        let targets = vec![
+            TargetConfig {
+                name: "app".to_string(),
+                path: existing.to_string_lossy().to_string(),
+                placeholders: vec!["$API_KEY".to_string(), "$DB_URL".to_string()],
+                ..Default::default()
+            },
+            TargetConfig {
+                name: "demo".to_string(),
+                path: temp_dir.path().join("demo.env").to_string_lossy().to_string(),
+                placeholders: vec!["$API_KEY".to_string()],
+                untrusted: true,
+                create_if_missing: true,
+                template: Some("KEY=$API_KEY\n".to_string()),
+                ..Default::default()
+            },
+        ];
+        let vault = Vault::new(HashMap::from([("API_KEY".to_string(), "sk_test_123".to_string())]));
+
+        let session = UnlockSession::unlock(&targets, &vault).unwrap();
+        let report = session.report();
+
+        assert_eq!(report.targets.len(), 2);
+        assert_eq!((report.targets[0].placeholders, report.targets[0].resolved), (2, 1));
+        assert_eq!(report.targets[0].replaced, vec!["API_KEY".to_string()]);
+        assert_eq!(report.targets[0].left, vec!["DB_URL".to_string()]);
+        assert!(!report.targets[0].created);
+        assert!(report.targets[1].created && report.targets[1].untrusted);
+        assert_eq!(
+            std::fs::read_to_string(&existing).unwrap(),
+            "API_KEY=sk_test_123\nDB=$DB_URL\n"
+        );
+        let demo = std::fs::read_to_string(temp_dir.path().join("demo.env")).unwrap();
+        assert!(!demo.contains("sk_test_123"));
+    }
+
+    #[test]
+    fn test_strict_target_fails_before_any_file_is_modified() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let first = temp_dir.path().join("first.env");
+        let strict = temp_dir.path().join("strict.env");
+        std::fs::write(&first, "API_KEY=$API_KEY\n").unwrap();
+        std::fs::write(&strict, "DB=$DB_URL\nCACHE=${CACHE_URL}\n").unwrap();
+
+        let targets = vec![
+            TargetConfig {
+                name: "first".to_string(),
+                path: first.to_string_lossy().to_string(),
+                placeholders: vec!["$API_KEY".to_string()],
+                ..Default::default()
+            },
+            TargetConfig {
+                name: "strict".to_string(),
+                path: strict.to_string_lossy().to_string(),
+                placeholders: vec!["$DB_URL".to_string(), "${CACHE_URL}".to_string(), "$API_KEY".to_string()],
+                strict: true,
+                ..Default::default()
+            },
+        ];
+        let vault = Vault::new(HashMap::from([("API_KEY".to_string(), "sk_test_123".to_string())]));
+
+        let err = UnlockSession::unlock(&targets, &vault).unwrap_err().to_string();
+        assert!(err.contains("strict: $DB_URL, ${CACHE_URL}"), "{}", err);
+        assert_eq!(std::fs::read_to_string(&first).unwrap(), "API_KEY=$API_KEY\n");
+    }
+
+    #[test]
+    fn test_failed_target_rolls_back_earlier_ones() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let first = temp_dir.path().join("first.env");
+        let created = temp_dir.path().join("created.env");
+        std::fs::write(&first, "API_KEY=$API_KEY\n").unwrap();
+
+        let targets = vec![
+            TargetConfig {
+                name: "first".to_string(),
+                path: first.to_string_lossy().to_string(),
+                placeholders: vec!["$API_KEY".to_string()],
+                ..Default::default()
+            },
+            TargetConfig {
+                name: "created".to_string(),
+                path: created.to_string_lossy().to_string(),
+                placeholders: vec!["$API_KEY".to_string()],
+                create_if_missing: true,
+                template: Some("KEY=$API_KEY\n".to_string()),
+                ..Default::default()
+            },
+            TargetConfig {
+                name: "missing".to_string(),
+                path: temp_dir.path().join("missing.env").to_string_lossy().to_string(),
+                placeholders: vec!["$API_KEY".to_string()],
+                ..Default::default()
+            },
+        ];
+        let vault = Vault::new(HashMap::from([("API_KEY".to_string(), "sk_test_123".to_string())]));
+
+        let err = UnlockSession::unlock(&targets, &vault).unwrap_err();
+        assert!(format!("{:#}", err).contains("missing.env"), "{:#}", err);
+        assert_eq!(std::fs::read_to_string(&first).unwrap(), "API_KEY=$API_KEY\n");
+        assert!(!created.exists());
+    }
+
+    #[test]
+    fn test_injection_gives_up_after_timeout() {
+        let slow = || {
+            std::thread::sleep(Duration::from_secs(2));
+            Ok(())
+        };
+        let error = within(Some(Duration::from_millis(50)), "config/app.json", slow).unwrap_err();
+        assert_eq!(error.to_string(), "Injecting secrets into config/app.json timed out after 50ms");
+
+        within(Some(Duration::from_secs(5)), "config/app.json", || Ok(())).unwrap();
+        assert!(within::<()>(None, "config/app.json", || anyhow::bail!("denied")).is_err());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_busy_targets() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("app.env");
+        std::fs::write(&path, "API_KEY=$API_KEY\n").unwrap();
+        let targets = vec![TargetConfig {
+            name: "app".to_string(),
+            path: path.to_string_lossy().to_string(),
+            ..Default::default()
+        }];
+
+        // Shadow Secret's own handles do not count
+        let _own = std::fs::File::open(&path).unwrap();
+        assert!(busy_targets(&targets).unwrap().is_empty());
+
+        let mut holder = std::process::Command::new("sh")
+            .args(["-c", "exec 3<\"$0\"; exec sleep 30"])
+            .arg(&path)
+            .spawn()
+            .unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let busy = loop {
+            let busy = busy_targets(&targets).unwrap();
+            if !busy.is_empty() || Instant::now() > deadline {
+                break busy;
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        };
+        holder.kill().unwrap();
+        holder.wait().unwrap();
+
+        assert_eq!(busy.len(), 1);
+        assert_eq!(busy[0].processes, vec![(holder.id(), "sleep".to_string())]);
+        assert_eq!(busy[0].message(), format!("{} is open in sleep (PID {})", targets[0].path, holder.id()));
+    }
+}
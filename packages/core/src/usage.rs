@@ -0,0 +1,274 @@
+//! Usage statistics from the local audit log (`shadow-secret report`).
+//!
+//! [`summarize`] aggregates the audit entries of a period: unlock sessions
+//! and their average length, pushes per provider and outcome, and the keys
+//! used most often, by count. Everything is computed from
+//! `~/.config/shadow-secret/audit.log` without any network call, and the
+//! summary renders as markdown or JSON, e.g. for a team retro.
+
+use crate::audit::AuditEvent;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// Number of keys listed in [`UsageSummary::top_keys`].
+pub const TOP_KEYS: usize = 10;
+
+/// Provider of push entries recorded before providers were (all Vercel).
+const DEFAULT_PROVIDER: &str = "vercel";
+
+/// Pushes to one provider, by outcome.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct PushStats {
+    pub total: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub cancelled: usize,
+}
+
+/// How many sessions and pushes involved a key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct KeyUsage {
+    pub key: String,
+    pub count: usize,
+}
+
+/// Summary of the audit log over a period.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UsageSummary {
+    /// RFC 3339 start of the period (`None`: the whole log)
+    pub since: Option<String>,
+    /// Unlock sessions that locked in the period
+    pub sessions: usize,
+    /// Average time from unlock to lock, in seconds
+    pub average_session_secs: Option<u64>,
+    /// Pushes by provider
+    pub pushes: BTreeMap<String, PushStats>,
+    /// Most used keys, most used first (at most [`TOP_KEYS`])
+    pub top_keys: Vec<KeyUsage>,
+}
+
+/// Summarize the entries of `events` recorded at or after `since` (all of
+/// them if `None`). Entries whose timestamp cannot be read only count
+/// without `since`.
+pub fn summarize(events: &[AuditEvent], since: Option<DateTime<Utc>>) -> UsageSummary {
+    let in_period = |event: &&AuditEvent| match since {
+        None => true,
+        Some(since) => DateTime::parse_from_rfc3339(&event.timestamp).is_ok_and(|timestamp| timestamp >= since),
+    };
+
+    let (mut sessions, mut session_ms) = (0, Vec::new());
+    let mut pushes: BTreeMap<String, PushStats> = BTreeMap::new();
+    let mut key_counts: HashMap<&str, usize> = HashMap::new();
+    for event in events.iter().filter(in_period) {
+        match event.event.as_str() {
+            "session" => {
+                sessions += 1;
+                session_ms.extend(event.duration_ms);
+            }
+            "push_cloud" => {
+                let stats = pushes
+                    .entry(event.provider.clone().unwrap_or_else(|| DEFAULT_PROVIDER.to_string()))
+                    .or_default();
+                stats.total += 1;
+                match event.outcome.as_str() {
+                    "success" => stats.succeeded += 1,
+                    "cancelled" => stats.cancelled += 1,
+                    _ => stats.failed += 1,
+                }
+            }
+            _ => continue,
+        }
+        for key in &event.keys {
+            *key_counts.entry(key).or_default() += 1;
+        }
+    }
+
+    let mut top_keys: Vec<KeyUsage> = key_counts
+        .into_iter()
+        .map(|(key, count)| KeyUsage {
+            key: key.to_string(),
+            count,
+        })
+        .collect();
+    top_keys.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.key.cmp(&b.key)));
+    top_keys.truncate(TOP_KEYS);
+
+    UsageSummary {
+        since: since.map(|since| since.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)),
+        sessions,
+        average_session_secs: (!session_ms.is_empty())
+            .then(|| session_ms.iter().sum::<u64>() / session_ms.len() as u64 / 1000),
+        pushes,
+        top_keys,
+    }
+}
+
+/// When each key was last injected or pushed (RFC 3339), from `events`.
+pub fn last_used(events: &[AuditEvent]) -> HashMap<String, String> {
+    let mut last: HashMap<String, String> = HashMap::new();
+    for event in events.iter().filter(|event| matches!(event.event.as_str(), "session" | "push_cloud")) {
+        for key in &event.keys {
+            let entry = last.entry(key.clone()).or_default();
+            if event.timestamp > *entry {
+                *entry = event.timestamp.clone();
+            }
+        }
+    }
+    last
+}
+
+/// `3725` seconds as `1h 02m`.
+fn format_duration(secs: u64) -> String {
+    match (secs / 3600, secs % 3600 / 60) {
+        (0, 0) => format!("{}s", secs),
+        (0, minutes) => format!("{}m", minutes),
+        (hours, minutes) => format!("{}h {:02}m", hours, minutes),
+    }
+}
+
+impl UsageSummary {
+    /// Session and push counts, as a markdown list.
+    fn overview(&self) -> String {
+        let mut out = format!("- Sessions: {}", self.sessions);
+        if let Some(average) = self.average_session_secs {
+            out.push_str(&format!(" (average {})", format_duration(average)));
+        }
+        out.push_str(&format!(
+            "\n- Pushes: {}\n",
+            self.pushes.values().map(|stats| stats.total).sum::<usize>()
+        ));
+        out
+    }
+
+    /// The summary as a markdown document.
+    pub fn render_markdown(&self) -> String {
+        let mut out = match &self.since {
+            Some(since) => format!("# Shadow Secret usage since {}\n\n", since),
+            None => "# Shadow Secret usage\n\n".to_string(),
+        };
+        out.push_str(&self.overview());
+
+        if !self.pushes.is_empty() {
+            out.push_str("\n| Provider | Pushes | Succeeded | Failed | Cancelled |\n|---|---|---|---|---|\n");
+            for (provider, stats) in &self.pushes {
+                out.push_str(&format!(
+                    "| {} | {} | {} | {} | {} |\n",
+                    provider, stats.total, stats.succeeded, stats.failed, stats.cancelled
+                ));
+            }
+        }
+
+        if !self.top_keys.is_empty() {
+            out.push_str("\n## Most used keys\n\n| Key | Uses |\n|---|---|\n");
+            for usage in &self.top_keys {
+                out.push_str(&format!("| {} | {} |\n", usage.key, usage.count));
+            }
+        }
+        out
+    }
+
+    /// The summary as aligned tables for the terminal, fitting in `width`
+    /// cells if given.
+    pub fn render_table(&self, width: Option<usize>) -> String {
+        use crate::table::{Column, Table};
+
+        let mut out = match &self.since {
+            Some(since) => format!("Shadow Secret usage since {}\n\n", since),
+            None => "Shadow Secret usage\n\n".to_string(),
+        };
+        out.push_str(&self.overview());
+
+        if !self.pushes.is_empty() {
+            let mut pushes = Table::new(vec![
+                Column::new("provider"),
+                Column::numeric("pushes"),
+                Column::numeric("succeeded"),
+                Column::numeric("failed"),
+                Column::numeric("cancelled"),
+            ]);
+            for (provider, stats) in &self.pushes {
+                pushes.push(vec![
+                    provider.clone(),
+                    stats.total.to_string(),
+                    stats.succeeded.to_string(),
+                    stats.failed.to_string(),
+                    stats.cancelled.to_string(),
+                ]);
+            }
+            out.push('\n');
+            out.push_str(&pushes.render(width));
+        }
+
+        if !self.top_keys.is_empty() {
+            let mut keys = Table::new(vec![Column::new("key"), Column::numeric("uses")]);
+            for usage in &self.top_keys {
+                keys.push(vec![usage.key.clone(), usage.count.to_string()]);
+            }
+            out.push('\n');
+            out.push_str(&keys.render(width));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(event: &str, timestamp: &str, keys: &[&str], outcome: &str) -> AuditEvent {
+        AuditEvent {
+            timestamp: timestamp.to_string(),
+            keys: keys.iter().map(|key| key.to_string()).collect(),
+            outcome: outcome.to_string(),
+            ..AuditEvent::new(event)
+        }
+    }
+
+    #[test]
+    fn test_summarize_period() {
+        let events = vec![
+            event("push_cloud", "2026-01-01T00:00:00Z", &["OLD_KEY"], "success"),
+            AuditEvent {
+                duration_ms: Some(30 * 60 * 1000),
+                ..event("session", "2026-02-01T00:00:00Z", &["API_KEY", "DB_URL"], "success")
+            },
+            AuditEvent {
+                duration_ms: Some(90 * 60 * 1000),
+                ..event("session", "2026-02-02T00:00:00Z", &["API_KEY"], "success")
+            },
+            event("push_cloud", "2026-02-03T00:00:00Z", &["API_KEY"], "success"),
+            AuditEvent {
+                provider: Some("vercel".to_string()),
+                ..event("push_cloud", "2026-02-04T00:00:00Z", &["DB_URL"], "failed")
+            },
+        ];
+
+        let since = DateTime::parse_from_rfc3339("2026-01-15T00:00:00Z").unwrap().with_timezone(&Utc);
+        let summary = summarize(&events, Some(since));
+        assert_eq!((summary.sessions, summary.average_session_secs), (2, Some(3600)));
+        assert_eq!(
+            summary.pushes["vercel"],
+            PushStats {
+                total: 2,
+                succeeded: 1,
+                failed: 1,
+                cancelled: 0
+            }
+        );
+        let keys: Vec<(&str, usize)> = summary.top_keys.iter().map(|k| (k.key.as_str(), k.count)).collect();
+        assert_eq!(keys, vec![("API_KEY", 3), ("DB_URL", 2)]);
+
+        let markdown = summary.render_markdown();
+        assert!(markdown.contains("- Sessions: 2 (average 1h 00m)\n- Pushes: 2\n"), "{}", markdown);
+        assert!(markdown.contains("| vercel | 2 | 1 | 1 | 0 |"), "{}", markdown);
+        assert_eq!(summarize(&events, None).pushes["vercel"].total, 3);
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(42), "42s");
+        assert_eq!(format_duration(25 * 60), "25m");
+        assert_eq!(format_duration(3725), "1h 02m");
+    }
+}
@@ -13,13 +13,92 @@
 //! - YAML (flat key-value structure)
 
 use anyhow::{Context, Result};
-use std::collections::HashMap;
-use std::process::Command;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use crate::process;
+use crate::sops::{self, SopsError};
+
+/// Maximum number of SOPS processes run at the same time by [`Vault::load_many`].
+pub const MAX_PARALLEL_DECRYPTIONS: usize = 4;
+
+/// Engine decrypting with the `sops` binary (default).
+pub const ENGINE_SOPS: &str = "sops";
+
+/// Engine decrypting in process with age (see [`crate::age_native`]).
+pub const ENGINE_AGE_NATIVE: &str = "age-native";
+
+/// Engine reading KV v2 secrets from HashiCorp Vault (see [`crate::hashicorp`]).
+pub const ENGINE_HASHICORP: &str = "hashicorp";
+
+/// Engine reading a JSON secret from AWS Secrets Manager (see [`crate::aws`]).
+pub const ENGINE_AWS_SECRETS_MANAGER: &str = "aws-secretsmanager";
+
+/// Engine reading a secret from GCP Secret Manager (see [`crate::gcp`]).
+pub const ENGINE_GCP_SECRET_MANAGER: &str = "gcp-secretmanager";
+
+/// Engine reading 1Password items with the `op` CLI (see [`crate::onepassword`]).
+pub const ENGINE_ONEPASSWORD: &str = "1password";
+
+/// Engine reading a Bitwarden Secrets Manager project (see [`crate::bitwarden`]).
+pub const ENGINE_BITWARDEN: &str = "bitwarden";
+
+/// Engine reading password-store entries with `pass` (see [`crate::pass`]).
+pub const ENGINE_PASS: &str = "pass";
+
+static NATIVE_AGE: AtomicBool = AtomicBool::new(false);
+
+/// Select how vault files are decrypted for the rest of the process
+/// (`vault.engine`).
+pub fn set_engine(engine: &str) -> Result<()> {
+    match engine {
+        ENGINE_SOPS
+        | ENGINE_HASHICORP
+        | ENGINE_AWS_SECRETS_MANAGER
+        | ENGINE_GCP_SECRET_MANAGER
+        | ENGINE_ONEPASSWORD
+        | ENGINE_BITWARDEN
+        | ENGINE_PASS => NATIVE_AGE.store(false, Ordering::SeqCst),
+        ENGINE_AGE_NATIVE => NATIVE_AGE.store(true, Ordering::SeqCst),
+        other => anyhow::bail!("Unsupported vault engine: '{}'", other),
+    }
+    Ok(())
+}
+
+/// Whether `engine` reads secrets from an external secret store (a remote
+/// service, 1Password, pass) instead of vault files.
+pub fn is_remote_engine(engine: &str) -> bool {
+    engine == ENGINE_HASHICORP
+        || engine == ENGINE_AWS_SECRETS_MANAGER
+        || engine == ENGINE_GCP_SECRET_MANAGER
+        || engine == ENGINE_ONEPASSWORD
+        || engine == ENGINE_BITWARDEN
+        || engine == ENGINE_PASS
+}
+
+/// Whether vault files are decrypted in process instead of by `sops`.
+pub fn is_native_age() -> bool {
+    NATIVE_AGE.load(Ordering::SeqCst)
+}
 
 /// Secure vault that holds decrypted secrets in memory only.
 #[derive(Debug, Clone)]
 pub struct Vault {
     pub(crate) secrets: HashMap<String, String>,
+    normalize_keys: bool,
+    /// Sources that defined each key, in merge order (the last one wins)
+    origins: HashMap<String, Vec<String>>,
+}
+
+/// Where the final value of a key came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Provenance {
+    pub key: String,
+    /// Source of the value in use
+    pub source: String,
+    /// Earlier sources whose value was overridden, in merge order
+    pub shadowed: Vec<String>,
 }
 
 impl Vault {
@@ -30,7 +109,20 @@ impl Vault {
     /// This is primarily intended for testing. For production use,
     /// prefer [`Vault::load()`] which loads from encrypted files.
     pub fn new(secrets: HashMap<String, String>) -> Self {
-        Self { secrets }
+        Self {
+            secrets,
+            normalize_keys: false,
+            origins: HashMap::new(),
+        }
+    }
+
+    /// Enable normalized lookups (see [`normalize_key`]) in [`Vault::get`]
+    /// and [`Vault::resolve_placeholders`].
+    pub fn with_normalized_keys(self, normalize_keys: bool) -> Self {
+        Self {
+            normalize_keys,
+            ..self
+        }
     }
 
     /// Load secrets from a SOPS-encrypted file.
@@ -70,7 +162,137 @@ impl Vault {
         // Parse based on file extension
         let secrets = parse_output(encrypted_path, &output)?;
 
-        Ok(Self { secrets })
+        let mut vault = Self::new(HashMap::new());
+        vault.merge(encrypted_path, secrets);
+        Ok(vault)
+    }
+
+    /// Load and merge secrets from several SOPS-encrypted files.
+    ///
+    /// Files are decrypted concurrently (at most [`MAX_PARALLEL_DECRYPTIONS`]
+    /// SOPS processes at a time), which matters when each decryption calls
+    /// out to a KMS and takes seconds. Results are merged in the order of
+    /// `encrypted_paths`: on duplicate keys, later files win (see
+    /// [`Vault::shadowed`]).
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the first file (in order) that failed to load.
+    pub fn load_many<P: AsRef<Path> + Sync>(
+        encrypted_paths: &[P],
+        age_key_path: Option<&str>,
+    ) -> Result<Self> {
+        let loaded = decrypt_concurrently(encrypted_paths, MAX_PARALLEL_DECRYPTIONS, |path| {
+            let path_str = path
+                .to_str()
+                .ok_or_else(|| anyhow::anyhow!("Vault path contains invalid UTF-8: {:?}", path))?;
+            Self::load(path_str, age_key_path)
+                .map(|vault| vault.secrets)
+                .with_context(|| format!("Failed to load vault from: {}", path_str))
+        })?;
+
+        let mut vault = Self::new(HashMap::new());
+        for (path, secrets) in encrypted_paths.iter().zip(loaded) {
+            vault.merge(&path.as_ref().to_string_lossy(), secrets);
+        }
+
+        Ok(vault)
+    }
+
+    /// Load the secret configured in `vault.hashicorp` from HashiCorp Vault.
+    pub fn load_hashicorp(config: &crate::config::HashicorpConfig) -> Result<Self> {
+        let secrets = crate::hashicorp::fetch(config)?;
+
+        let mut vault = Self::new(HashMap::new());
+        vault.merge(&crate::hashicorp::source_name(config), secrets);
+        Ok(vault)
+    }
+
+    /// Load the secret configured in `vault.aws_secretsmanager` from AWS
+    /// Secrets Manager.
+    pub fn load_aws_secrets_manager(config: &crate::config::AwsSecretsManagerConfig) -> Result<Self> {
+        let secrets = crate::aws::fetch(config)?;
+
+        let mut vault = Self::new(HashMap::new());
+        vault.merge(&crate::aws::source_name(config), secrets);
+        Ok(vault)
+    }
+
+    /// Load the secret configured in `vault.gcp` from GCP Secret Manager.
+    pub fn load_gcp_secret_manager(config: &crate::config::GcpConfig) -> Result<Self> {
+        let secrets = crate::gcp::fetch(config)?;
+
+        let mut vault = Self::new(HashMap::new());
+        vault.merge(&crate::gcp::source_name(config), secrets);
+        Ok(vault)
+    }
+
+    /// Load the items configured in `vault.onepassword` with the 1Password
+    /// CLI (one source per item).
+    pub fn load_onepassword(config: &crate::config::OnePasswordConfig) -> Result<Self> {
+        let mut vault = Self::new(HashMap::new());
+        for (source, secrets) in crate::onepassword::fetch(config)? {
+            vault.merge(&source, secrets);
+        }
+        Ok(vault)
+    }
+
+    /// Load the project configured in `vault.bitwarden` from Bitwarden
+    /// Secrets Manager.
+    pub fn load_bitwarden(config: &crate::config::BitwardenConfig) -> Result<Self> {
+        let secrets = crate::bitwarden::fetch(config)?;
+
+        let mut vault = Self::new(HashMap::new());
+        vault.merge(&crate::bitwarden::source_name(config), secrets);
+        Ok(vault)
+    }
+
+    /// Load the entries configured in `vault.pass` from a password-store.
+    pub fn load_pass(config: &crate::config::PassConfig) -> Result<Self> {
+        let secrets = crate::pass::fetch(config)?;
+
+        let mut vault = Self::new(HashMap::new());
+        vault.merge(&crate::pass::source_name(config), secrets);
+        Ok(vault)
+    }
+
+    /// Load secrets the way `config` says: from HashiCorp Vault, AWS Secrets
+    /// Manager, GCP Secret Manager, 1Password, Bitwarden or pass with a
+    /// remote engine, otherwise from every vault file (see [`Vault::load_many`]).
+    pub fn load_config(config: &crate::config::Config, config_dir: &Path) -> Result<Self> {
+        let vault = &config.vault;
+        match vault.engine.as_str() {
+            ENGINE_HASHICORP => Self::load_hashicorp(engine_block(&vault.hashicorp, "hashicorp")?),
+            ENGINE_AWS_SECRETS_MANAGER => {
+                Self::load_aws_secrets_manager(engine_block(&vault.aws_secretsmanager, "aws_secretsmanager")?)
+            }
+            ENGINE_GCP_SECRET_MANAGER => Self::load_gcp_secret_manager(engine_block(&vault.gcp, "gcp")?),
+            ENGINE_ONEPASSWORD => Self::load_onepassword(engine_block(&vault.onepassword, "onepassword")?),
+            ENGINE_BITWARDEN => Self::load_bitwarden(engine_block(&vault.bitwarden, "bitwarden")?),
+            ENGINE_PASS => Self::load_pass(engine_block(&vault.pass, "pass")?),
+            _ => Self::load_many(
+                &config.vault_source_paths(config_dir)?,
+                vault.age_key_path.as_deref(),
+            ),
+        }
+    }
+
+    /// Load deterministic fake values for every key of the given files.
+    ///
+    /// Key names are read from the encrypted files without decrypting them
+    /// (SOPS leaves them in clear), and values come from
+    /// [`crate::fake::fake_value`]. Neither `sops` nor an age key is needed,
+    /// so demos and screenshots of real configs expose nothing.
+    pub fn load_fake<P: AsRef<Path>>(encrypted_paths: &[P]) -> Result<Self> {
+        let mut vault = Self::new(HashMap::new());
+        for path in encrypted_paths {
+            let path = path.as_ref();
+            let keys = crate::age_native::key_names(path)
+                .with_context(|| format!("Failed to read key names from: {}", path.display()))?;
+            vault.merge(&path.to_string_lossy(), fake_values(keys));
+        }
+
+        Ok(vault)
     }
 
     /// Get a secret value by key.
@@ -83,14 +305,475 @@ impl Vault {
     ///
     /// - `Some(&String)` - Reference to the secret value if it exists
     /// - `None` - If the key doesn't exist
+    ///
+    /// With normalized keys enabled, an exact match wins; otherwise the first
+    /// key (in sorted order) whose normalized form matches is used.
     pub fn get(&self, key: &str) -> Option<&String> {
-        self.secrets.get(key)
+        if let Some(value) = self.secrets.get(key) {
+            return Some(value);
+        }
+        if !self.normalize_keys {
+            return None;
+        }
+
+        let wanted = normalize_key(key);
+        self.secrets
+            .iter()
+            .filter(|(name, _)| normalize_key(name) == wanted)
+            .min_by(|(a, _), (b, _)| a.cmp(b))
+            .map(|(_, value)| value)
+    }
+
+    /// Secrets to hand to the injector for `placeholders`.
+    ///
+    /// With normalized keys enabled, each placeholder whose key has no exact
+    /// match but a normalized one (e.g. `${api-key}` for `API_KEY`) gets an
+    /// alias entry under the placeholder's spelling.
+    pub fn resolve_placeholders(&self, placeholders: &[String]) -> HashMap<String, String> {
+        let mut secrets = self.secrets.clone();
+        if !self.normalize_keys {
+            return secrets;
+        }
+
+        for placeholder in placeholders {
+            let key = crate::injector::extract_key_name(placeholder);
+            if !self.secrets.contains_key(key) {
+                if let Some(value) = self.get(key) {
+                    secrets.insert(key.to_string(), value.clone());
+                }
+            }
+        }
+
+        secrets
+    }
+
+    /// Secrets to hand to the injector for `target`: its placeholders
+    /// resolved as by [`Vault::resolve_placeholders`], restricted to the
+    /// target's `keys` allowlist when it has one.
+    pub fn secrets_for(&self, target: &crate::config::TargetConfig) -> HashMap<String, String> {
+        let mut secrets = self.resolve_placeholders(&target.placeholders);
+        if target.keys.is_empty() {
+            return secrets;
+        }
+
+        secrets.retain(|key, _| {
+            target.keys.iter().any(|allowed| {
+                allowed == key || (self.normalize_keys && normalize_key(allowed) == normalize_key(key))
+            })
+        });
+        secrets
     }
 
     /// Get all secrets as a read-only map.
     pub fn all(&self) -> &HashMap<String, String> {
         &self.secrets
     }
+
+    /// Merge `overrides` from `source` on top of the loaded secrets.
+    ///
+    /// # Returns
+    ///
+    /// The overridden or added keys, sorted.
+    pub fn apply_overlay(&mut self, source: &str, overrides: HashMap<String, String>) -> Vec<String> {
+        let mut keys: Vec<String> = overrides.keys().cloned().collect();
+        keys.sort();
+        self.merge(source, overrides);
+        keys
+    }
+
+    /// Merge `secrets` from `source`, recording where each key came from.
+    fn merge(&mut self, source: &str, secrets: HashMap<String, String>) {
+        for (key, value) in secrets {
+            self.origins
+                .entry(key.clone())
+                .or_default()
+                .push(source.to_string());
+            self.secrets.insert(key, value);
+        }
+    }
+
+    /// Origin of every key, sorted by key.
+    ///
+    /// Keys added through [`Vault::new`] are attributed to `"memory"`.
+    pub fn provenance(&self) -> Vec<Provenance> {
+        let mut provenance: Vec<Provenance> = self
+            .secrets
+            .keys()
+            .map(|key| match self.origins.get(key).and_then(|sources| sources.split_last()) {
+                Some((source, shadowed)) => Provenance {
+                    key: key.clone(),
+                    source: source.clone(),
+                    shadowed: shadowed.to_vec(),
+                },
+                None => Provenance {
+                    key: key.clone(),
+                    source: "memory".to_string(),
+                    shadowed: Vec::new(),
+                },
+            })
+            .collect();
+        provenance.sort_by(|a, b| a.key.cmp(&b.key));
+        provenance
+    }
+
+    /// Keys defined by more than one source, sorted by key.
+    pub fn shadowed(&self) -> Vec<Provenance> {
+        self.provenance()
+            .into_iter()
+            .filter(|entry| !entry.shadowed.is_empty())
+            .collect()
+    }
+}
+
+/// Normalized form of a key: uppercase, with `-` mapped to `_` and the `.`
+/// of flattened keys to `__` (`database.password` ~ `DATABASE__PASSWORD`).
+pub fn normalize_key(key: &str) -> String {
+    key.replace('.', "__")
+        .chars()
+        .map(|c| if c == '-' { '_' } else { c.to_ascii_uppercase() })
+        .collect()
+}
+
+/// File name of the encrypted local overlay, looked up next to the config.
+pub const LOCAL_OVERLAY_ENCRYPTED: &str = "local.enc.env";
+
+/// File name of the plaintext local overlay (discouraged).
+pub const LOCAL_OVERLAY_PLAINTEXT: &str = "local.env";
+
+/// Developer-specific overrides merged on top of the shared vault.
+///
+/// Overlays are meant to stay out of version control (gitignored), so a
+/// developer can point e.g. `DATABASE_URL` at their own instance without
+/// touching the team vault.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LocalOverlay {
+    /// `local.enc.env`, decrypted with SOPS like the vault
+    Encrypted(PathBuf),
+    /// `local.env`, read as-is from disk
+    Plaintext(PathBuf),
+}
+
+impl LocalOverlay {
+    /// Find the overlay in `config_dir`.
+    ///
+    /// `local.enc.env` takes precedence over `local.env` when both exist.
+    pub fn find(config_dir: &Path) -> Option<Self> {
+        let encrypted = config_dir.join(LOCAL_OVERLAY_ENCRYPTED);
+        if encrypted.is_file() {
+            return Some(LocalOverlay::Encrypted(encrypted));
+        }
+
+        let plaintext = config_dir.join(LOCAL_OVERLAY_PLAINTEXT);
+        if plaintext.is_file() {
+            return Some(LocalOverlay::Plaintext(plaintext));
+        }
+
+        None
+    }
+
+    /// Path of the overlay file.
+    pub fn path(&self) -> &Path {
+        match self {
+            LocalOverlay::Encrypted(path) | LocalOverlay::Plaintext(path) => path,
+        }
+    }
+
+    /// Whether the overlay is stored unencrypted.
+    pub fn is_plaintext(&self) -> bool {
+        matches!(self, LocalOverlay::Plaintext(_))
+    }
+
+    /// Fake values for the overlay's keys (see [`Vault::load_fake`]).
+    pub fn load_fake(&self) -> Result<HashMap<String, String>> {
+        let keys = match self {
+            LocalOverlay::Encrypted(path) => crate::age_native::key_names(path)?,
+            LocalOverlay::Plaintext(path) => {
+                let content = std::fs::read(path)
+                    .with_context(|| format!("Failed to read local overlay: {:?}", path))?;
+                parse_env(&content)?.into_keys().collect()
+            }
+        };
+        Ok(fake_values(keys))
+    }
+
+    /// Load the overlay's key-value pairs.
+    pub fn load(&self, age_key_path: Option<&str>) -> Result<HashMap<String, String>> {
+        match self {
+            LocalOverlay::Encrypted(path) => {
+                let path_str = path
+                    .to_str()
+                    .ok_or_else(|| anyhow::anyhow!("Overlay path contains invalid UTF-8: {:?}", path))?;
+                Vault::load(path_str, age_key_path)
+                    .map(|vault| vault.secrets)
+                    .with_context(|| format!("Failed to load local overlay: {}", path_str))
+            }
+            LocalOverlay::Plaintext(path) => {
+                let content = std::fs::read(path)
+                    .with_context(|| format!("Failed to read local overlay: {:?}", path))?;
+                parse_env(&content).with_context(|| format!("Failed to parse local overlay: {:?}", path))
+            }
+        }
+    }
+}
+
+/// Number of values kept in memory by [`LazyVault`].
+pub const LAZY_CACHE_CAPACITY: usize = 16;
+
+/// Vault that decrypts one key at a time on demand.
+///
+/// Instead of decrypting the whole file, each lookup runs
+/// `sops -d --extract '["KEY"]'`, so unrelated secrets never enter memory.
+/// Recently used values are kept in a small LRU cache to avoid repeated
+/// SOPS invocations.
+///
+/// With several sources, later files take precedence, matching
+/// [`Vault::load_many`].
+///
+/// # Example
+///
+/// ```no_run
+/// use shadow_secret::vault::LazyVault;
+/// use std::path::PathBuf;
+///
+/// # fn main() -> anyhow::Result<()> {
+/// let vault = LazyVault::new(vec![PathBuf::from(".enc.env")], None);
+/// let api_key = vault.get("API_KEY")?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct LazyVault {
+    sources: Vec<PathBuf>,
+    age_key_path: Option<String>,
+    cache: Mutex<LruCache>,
+}
+
+impl LazyVault {
+    /// Create a lazy vault over `sources` (in merge order).
+    pub fn new(sources: Vec<PathBuf>, age_key_path: Option<&str>) -> Self {
+        Self {
+            sources,
+            age_key_path: age_key_path.map(str::to_string),
+            cache: Mutex::new(LruCache::new(LAZY_CACHE_CAPACITY)),
+        }
+    }
+
+    /// Change the number of cached values (0 disables caching).
+    pub fn with_cache_capacity(self, capacity: usize) -> Self {
+        Self {
+            cache: Mutex::new(LruCache::new(capacity)),
+            ..self
+        }
+    }
+
+    /// Decrypt a single secret.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(Some(value))` - The secret exists in one of the sources
+    /// - `Ok(None)` - No source contains the key
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if SOPS fails for any reason other than a missing key.
+    pub fn get(&self, key: &str) -> Result<Option<String>> {
+        self.lookup(key, |path, key| {
+            extract_key(path, key, self.age_key_path.as_deref())
+        })
+    }
+
+    fn lookup<F>(&self, key: &str, extract: F) -> Result<Option<String>>
+    where
+        F: Fn(&Path, &str) -> Result<Option<String>>,
+    {
+        if key.is_empty() {
+            anyhow::bail!("Secret key cannot be empty");
+        }
+
+        if let Some(value) = self.cache.lock().unwrap_or_else(|e| e.into_inner()).get(key) {
+            return Ok(Some(value));
+        }
+
+        for source in self.sources.iter().rev() {
+            if let Some(value) = extract(source, key)? {
+                self.cache
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .put(key.to_string(), value.clone());
+                return Ok(Some(value));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+/// Minimal least-recently-used cache for [`LazyVault`].
+#[derive(Debug)]
+struct LruCache {
+    capacity: usize,
+    entries: VecDeque<(String, String)>,
+}
+
+impl LruCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    fn get(&mut self, key: &str) -> Option<String> {
+        let index = self.entries.iter().position(|(k, _)| k == key)?;
+        let entry = self.entries.remove(index)?;
+        let value = entry.1.clone();
+        self.entries.push_back(entry);
+        Some(value)
+    }
+
+    fn put(&mut self, key: String, value: String) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.entries.retain(|(k, _)| *k != key);
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back((key, value));
+    }
+}
+
+/// Decrypt one top-level key with `sops -d --extract` (or in process with
+/// engine `age-native`).
+///
+/// Returns `Ok(None)` when the key does not exist in the file.
+pub(crate) fn extract_key(path: &Path, key: &str, age_key_path: Option<&str>) -> Result<Option<String>> {
+    if is_native_age() {
+        return crate::age_native::decrypt_key(path, key, age_key_path);
+    }
+
+    let path_str = path
+        .to_str()
+        .ok_or_else(|| anyhow::anyhow!("Vault path contains invalid UTF-8: {:?}", path))?;
+
+    let expression = extract_expression(key);
+    let output = sops::with_retry(sops::MAX_ATTEMPTS, sops::RETRY_BASE_DELAY, || {
+        let output = run_sops_decrypt(path_str, Some(&expression), age_key_path)?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            // sops: "component [\"KEY\"] not found"
+            if stderr.contains("component") && stderr.contains("not found") {
+                return Ok(None);
+            }
+            return Err(SopsError::classify(path_str, &stderr).into());
+        }
+
+        Ok(Some(output))
+    })?;
+
+    let Some(output) = output else {
+        return Ok(None);
+    };
+
+    let mut value = String::from_utf8(output.stdout).context("SOPS output is not valid UTF-8")?;
+    if value.ends_with('\n') {
+        value.pop();
+    }
+
+    Ok(Some(value))
+}
+
+/// Build the `--extract` expression for a top-level key (`["KEY"]`).
+pub(crate) fn extract_expression(key: &str) -> String {
+    format!("[\"{}\"]", key.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Settings block of a remote engine (`vault.<name>`).
+fn engine_block<'a, T>(block: &'a Option<T>, name: &str) -> Result<&'a T> {
+    block
+        .as_ref()
+        .with_context(|| format!("This engine requires a 'vault.{}' section", name))
+}
+
+/// Secrets from a flat JSON object fetched from a remote engine.
+///
+/// Numbers and booleans are converted to strings; nested values are
+/// rejected.
+pub(crate) fn flat_json_secrets(
+    object: &serde_json::Map<String, serde_json::Value>,
+    source: &str,
+) -> Result<HashMap<String, String>> {
+    object
+        .iter()
+        .map(|(key, value)| {
+            let value = match value {
+                serde_json::Value::String(s) => s.clone(),
+                serde_json::Value::Number(_) | serde_json::Value::Bool(_) => value.to_string(),
+                other => anyhow::bail!(
+                    "{} value for key '{}' must be a string, found: {}",
+                    source,
+                    key,
+                    other
+                ),
+            };
+            Ok((key.clone(), value))
+        })
+        .collect()
+}
+
+/// Fake values for `keys`.
+fn fake_values(keys: Vec<String>) -> HashMap<String, String> {
+    keys.into_iter()
+        .map(|key| {
+            let value = crate::fake::fake_value(&key);
+            (key, value)
+        })
+        .collect()
+}
+
+/// Secrets decrypted from a single vault file.
+type SourceSecrets = HashMap<String, String>;
+
+/// Run `decrypt` on every path with at most `max_parallel` concurrent calls.
+///
+/// Results are returned in input order regardless of completion order.
+fn decrypt_concurrently<P, F>(
+    paths: &[P],
+    max_parallel: usize,
+    decrypt: F,
+) -> Result<Vec<SourceSecrets>>
+where
+    P: AsRef<Path> + Sync,
+    F: Fn(&Path) -> Result<SourceSecrets> + Sync,
+{
+    let results: Mutex<Vec<Option<Result<SourceSecrets>>>> =
+        Mutex::new((0..paths.len()).map(|_| None).collect());
+    let next = AtomicUsize::new(0);
+    let workers = max_parallel.clamp(1, paths.len().max(1));
+    // Workers use the caller's command runner (see `process::with_runner`)
+    let runner = process::runner();
+
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| process::with_runner(runner.clone(), || loop {
+                let index = next.fetch_add(1, Ordering::SeqCst);
+                let Some(path) = paths.get(index) else {
+                    break;
+                };
+
+                let result = decrypt(path.as_ref());
+                results.lock().unwrap_or_else(|e| e.into_inner())[index] = Some(result);
+            }));
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap_or_else(|e| e.into_inner())
+        .into_iter()
+        .map(|result| result.unwrap_or_else(|| Err(anyhow::anyhow!("Vault decryption did not run"))))
+        .collect()
 }
 
 /// Execute SOPS command and capture stdout to memory.
@@ -100,15 +783,140 @@ impl Vault {
 /// - Captures stdout as bytes directly
 /// - Never writes to disk
 /// - Validates SOPS installation
-/// - Uses age_key_path if provided to set SOPS_AGE_KEY_FILE environment variable
-fn execute_sops(encrypted_path: &str, age_key_path: Option<&str>) -> Result<Vec<u8>> {
-    // Set SOPS_AGE_KEY_FILE environment variable if age_key_path is provided
+/// - Uses age_key_path if provided to set SOPS_AGE_KEY_FILE for the SOPS process
+/// - Failures are classified into [`SopsError`]; transient ones are retried
+/// - With engine `age-native`, decrypts in process instead (no `sops` needed)
+pub(crate) fn execute_sops(encrypted_path: &str, age_key_path: Option<&str>) -> Result<Vec<u8>> {
+    if is_native_age() {
+        return crate::age_native::decrypt_file(Path::new(encrypted_path), age_key_path);
+    }
+
+    sops::with_retry(sops::MAX_ATTEMPTS, sops::RETRY_BASE_DELAY, || {
+        let output = run_sops_decrypt(encrypted_path, None, age_key_path)?;
+
+        // Check if SOPS command succeeded
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(SopsError::classify(encrypted_path, &stderr).into());
+        }
+
+        Ok(output.stdout)
+    })
+}
+
+/// Set a single top-level key in an encrypted file with `sops set`.
+///
+/// The file is updated in place by SOPS; the plaintext never touches disk.
+/// The value is passed as a command-line argument, so use this only for
+/// non-sensitive values such as canary tokens.
+pub fn set_secret(
+    encrypted_path: &Path,
+    key: &str,
+    value: &str,
+    age_key_path: Option<&str>,
+) -> Result<()> {
+    let value_json = serde_json::to_string(value)?;
+
+    let mut command = process::command("sops")?;
+    command
+        .arg("set")
+        .arg(encrypted_path)
+        .arg(extract_expression(key))
+        .arg(value_json);
+    if let Some(key_path) = age_key_path {
+        command.env("SOPS_AGE_KEY_FILE", key_path);
+    }
+
+    let output = process::output(&mut command)
+        .with_context(|| format!("Failed to execute 'sops set' on {:?}", encrypted_path))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(SopsError::classify(&encrypted_path.to_string_lossy(), &stderr).into());
+    }
+
+    Ok(())
+}
+
+/// Set a single top-level key in an encrypted file, passing the value on
+/// stdin (`sops set --value-stdin`, SOPS 3.10+) so it never appears in the
+/// process list.
+pub fn set_secret_from_stdin(
+    encrypted_path: &Path,
+    key: &str,
+    value: &str,
+    age_key_path: Option<&str>,
+) -> Result<()> {
+    let value_json = serde_json::to_string(value)?;
+
+    let mut command = process::command("sops")?;
+    command
+        .arg("set")
+        .arg("--value-stdin")
+        .arg(encrypted_path)
+        .arg(extract_expression(key));
+    if let Some(key_path) = age_key_path {
+        command.env("SOPS_AGE_KEY_FILE", key_path);
+    }
+
+    let output = process::output_with_stdin(&mut command, value_json.as_bytes())
+        .with_context(|| format!("Failed to execute 'sops set' on {:?}", encrypted_path))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(SopsError::classify(&encrypted_path.to_string_lossy(), &stderr).into());
+    }
+
+    Ok(())
+}
+
+/// The vault file `shadow-secret set` writes `key` to, among `sources`:
+/// the last one defining it (its value is the one in effect), else the
+/// first. Key names are read without decrypting.
+///
+/// # Returns
+///
+/// The file, and whether it already defines `key`.
+pub fn set_destination(sources: &[PathBuf], key: &str) -> Result<(PathBuf, bool)> {
+    for path in sources.iter().rev() {
+        if crate::age_native::key_names(path)?.iter().any(|name| name == key) {
+            return Ok((path.clone(), true));
+        }
+    }
+    let first = sources.first().context("No vault file configured")?;
+    Ok((first.clone(), false))
+}
+
+/// Remove a single top-level key from an encrypted file with `sops unset`.
+pub fn unset_secret(encrypted_path: &Path, key: &str, age_key_path: Option<&str>) -> Result<()> {
+    let mut command = process::command("sops")?;
+    command
+        .arg("unset")
+        .arg(encrypted_path)
+        .arg(extract_expression(key));
     if let Some(key_path) = age_key_path {
-        std::env::set_var("SOPS_AGE_KEY_FILE", key_path);
+        command.env("SOPS_AGE_KEY_FILE", key_path);
+    }
+
+    let output = process::output(&mut command)
+        .with_context(|| format!("Failed to execute 'sops unset' on {:?}", encrypted_path))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(SopsError::classify(&encrypted_path.to_string_lossy(), &stderr).into());
     }
 
+    Ok(())
+}
+
+/// Run `sops -d [--extract <path>] <file>` and return the raw process output.
+fn run_sops_decrypt(
+    encrypted_path: &str,
+    extract: Option<&str>,
+    age_key_path: Option<&str>,
+) -> Result<std::process::Output> {
     // Check if SOPS is installed
-    let check = Command::new("sops").arg("--version").output();
+    let check = process::output(process::command("sops")?.arg("--version"));
 
     match check {
         Ok(output) if output.status.success() => {
@@ -127,38 +935,30 @@ fn execute_sops(encrypted_path: &str, age_key_path: Option<&str>) -> Result<Vec<
         }
     }
 
-    // Execute sops -d <path>
-    let output = Command::new("sops")
-        .arg("-d")
-        .arg(encrypted_path)
-        .output()
-        .with_context(|| {
-            format!(
-                "Failed to execute SOPS on file '{}'. Ensure the file exists and is readable.",
-                encrypted_path
-            )
-        })?;
-
-    // Check if SOPS command succeeded
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(anyhow::anyhow!(
-            "SOPS decryption failed: {}",
-            if stderr.is_empty() {
-                "Unknown error"
-            } else {
-                &*stderr
-            }
-        ));
+    // Execute sops -d <path>, passing SOPS_AGE_KEY_FILE to this process only
+    // (safe when several vaults are decrypted concurrently)
+    let mut command = process::command("sops")?;
+    command.arg("-d");
+    if let Some(extract) = extract {
+        command.arg("--extract").arg(extract);
+    }
+    command.arg(encrypted_path);
+    if let Some(key_path) = age_key_path {
+        command.env("SOPS_AGE_KEY_FILE", key_path);
     }
 
-    Ok(output.stdout)
+    process::output_within(&mut command, process::timeouts().decrypt).with_context(|| {
+        format!(
+            "Failed to execute SOPS on file '{}'. Ensure the file exists and is readable.",
+            encrypted_path
+        )
+    })
 }
 
 /// Parse SOPS output based on file extension.
 ///
-/// Supports: ENV, JSON, YAML
-fn parse_output(path: &str, output: &[u8]) -> Result<HashMap<String, String>> {
+/// Supports: ENV, JSON, YAML, INI
+pub(crate) fn parse_output(path: &str, output: &[u8]) -> Result<HashMap<String, String>> {
     let extension = std::path::Path::new(path)
         .extension()
         .and_then(|ext| ext.to_str())
@@ -168,6 +968,7 @@ fn parse_output(path: &str, output: &[u8]) -> Result<HashMap<String, String>> {
         "env" | "dotenv" => parse_env(output),
         "json" => parse_json(output),
         "yaml" | "yml" => parse_yaml(output),
+        "ini" => parse_ini(output),
         _ => {
             // Try to auto-detect format
             try_autodetect(output)
@@ -175,36 +976,77 @@ fn parse_output(path: &str, output: &[u8]) -> Result<HashMap<String, String>> {
     }
 }
 
-/// Parse ENV format (key=value pairs).
-fn parse_env(output: &[u8]) -> Result<HashMap<String, String>> {
+/// Parse ENV format (key=value pairs), following dotenv conventions:
+///
+/// - An `export ` prefix is ignored
+/// - Unquoted values are trimmed; ` #` starts a comment (`a#b` is kept)
+/// - Single-quoted values are literal
+/// - Double-quoted values understand `\n`, `\r`, `\t`, `\"`, `\\` and `\$`
+/// - Quoted values may span several lines and be followed by a comment
+///
+/// Variables are not expanded: `$KEY` is kept as is. Lines without `=`
+/// are skipped. Errors give line numbers, never values.
+pub(crate) fn parse_env(output: &[u8]) -> Result<HashMap<String, String>> {
     let content = std::str::from_utf8(output).context("SOPS output is not valid UTF-8")?;
 
     let mut secrets = HashMap::new();
+    let mut lines = content.lines().enumerate();
 
-    for line in content.lines() {
-        let line = line.trim();
+    while let Some((index, line)) = lines.next() {
+        let line = line.trim_start();
 
         // Skip empty lines and comments
-        if line.is_empty() || line.starts_with('#') {
+        if line.trim_end().is_empty() || line.starts_with('#') {
             continue;
         }
 
-        // Parse key=value
-        if let Some((key, value)) = line.split_once('=') {
-            let key = key.trim().to_string();
-            let value = value.trim().to_string();
+        let line = line
+            .strip_prefix("export")
+            .filter(|rest| rest.starts_with([' ', '\t']))
+            .map_or(line, str::trim_start);
+        let Some((key, raw)) = line.split_once('=') else {
+            continue;
+        };
+        let key = key.trim();
+        if key.is_empty() {
+            continue;
+        }
 
-            // Remove quotes if present
-            let value = if (value.starts_with('"') && value.ends_with('"'))
-                || (value.starts_with('\'') && value.ends_with('\''))
-            {
-                value[1..value.len() - 1].to_string()
-            } else {
-                value
-            };
+        let trimmed = raw.trim_start();
+        let value = match trimmed.chars().next() {
+            Some(quote @ ('"' | '\'')) => {
+                let mut quoted = trimmed[1..].to_string();
+                let end = loop {
+                    if let Some(end) = closing_quote(&quoted, quote) {
+                        break end;
+                    }
+                    let Some((_, next)) = lines.next() else {
+                        anyhow::bail!("Unterminated quoted value for '{}' (line {})", key, index + 1);
+                    };
+                    quoted.push('\n');
+                    quoted.push_str(next);
+                };
+                let rest = quoted[end + 1..].trim();
+                if !rest.is_empty() && !rest.starts_with('#') {
+                    anyhow::bail!("Unexpected text after the quoted value of '{}' (line {})", key, index + 1);
+                }
+                quoted.truncate(end);
+                if quote == '"' {
+                    unescape_env(&quoted)
+                } else {
+                    quoted
+                }
+            }
+            _ => {
+                let comment = raw
+                    .char_indices()
+                    .find(|&(i, c)| c == '#' && raw[..i].ends_with(char::is_whitespace))
+                    .map_or(raw.len(), |(i, _)| i);
+                raw[..comment].trim().to_string()
+            }
+        };
 
-            secrets.insert(key, value);
-        }
+        secrets.insert(key.to_string(), value);
     }
 
     if secrets.is_empty() {
@@ -216,7 +1058,164 @@ fn parse_env(output: &[u8]) -> Result<HashMap<String, String>> {
     Ok(secrets)
 }
 
-/// Parse JSON format (flat key-value structure).
+/// Byte offset of the quote closing a value opened with `quote` (escaped
+/// quotes are skipped in double-quoted values).
+fn closing_quote(value: &str, quote: char) -> Option<usize> {
+    let mut escaped = false;
+    for (i, c) in value.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quote == '"' => escaped = true,
+            c if c == quote => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Resolve the escapes of a double-quoted ENV value. Unknown escapes are
+/// kept as written.
+fn unescape_env(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some('t') => unescaped.push('\t'),
+            Some(c @ ('"' | '\\' | '$')) => unescaped.push(c),
+            Some(other) => {
+                unescaped.push('\\');
+                unescaped.push(other);
+            }
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+/// Separator between the levels of a flattened key.
+pub const NESTED_KEY_SEPARATOR: char = '.';
+
+/// Add `value` to `secrets` under `key`, flattening nested objects into
+/// dotted keys (`{"database": {"password": ..}}` gives `database.password`).
+///
+/// Numbers and booleans are converted to strings; lists and nulls are
+/// rejected.
+fn flatten_value(
+    secrets: &mut HashMap<String, String>,
+    key: String,
+    value: &serde_json::Value,
+    format: &str,
+) -> Result<()> {
+    let value = match value {
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Number(_) | serde_json::Value::Bool(_) => value.to_string(),
+        serde_json::Value::Object(object) => {
+            for (name, nested) in object {
+                flatten_value(secrets, format!("{}{}{}", key, NESTED_KEY_SEPARATOR, name), nested, format)?;
+            }
+            return Ok(());
+        }
+        other => anyhow::bail!(
+            "{} value for key '{}' must be a string or a mapping, found: {}",
+            format,
+            key,
+            other
+        ),
+    };
+
+    if secrets.insert(key.clone(), value).is_some() {
+        anyhow::bail!("{} defines key '{}' twice once flattened", format, key);
+    }
+    Ok(())
+}
+
+/// Whether `value` is a value SOPS encrypted (`ENC[AES256_GCM,data:...]`).
+pub fn is_sops_encrypted(value: &str) -> bool {
+    value.starts_with("ENC[") && value.ends_with(']')
+}
+
+/// Which keys of a SOPS file are stored encrypted, read from the encrypted
+/// file itself.
+///
+/// With `encrypted_regex` (or `unencrypted_suffix` and friends) in
+/// `.sops.yaml`, SOPS leaves the values of non-matching keys in plaintext:
+/// the file decrypts like any other, but those values sit readable in git.
+/// Keys are named as in the decrypted vault (nested keys flattened); the
+/// SOPS metadata is skipped.
+///
+/// # Returns
+///
+/// Each key, mapped to `true` if its value is encrypted. A nested list
+/// counts as encrypted only if every item is.
+pub fn encryption_status(path: &str, encrypted: &[u8]) -> Result<BTreeMap<String, bool>> {
+    let extension = Path::new(path).extension().and_then(|ext| ext.to_str()).unwrap_or("");
+
+    // YAML is a superset of JSON; dotenv and INI files are read as vaults
+    let document = match extension {
+        "env" | "dotenv" | "ini" => None,
+        _ => std::str::from_utf8(encrypted)
+            .ok()
+            .and_then(|content| serde_yaml::from_str::<serde_yaml::Value>(content).ok())
+            .filter(|document| document.is_mapping()),
+    };
+
+    let Some(mut document) = document else {
+        // dotenv metadata is `sops_*`, INI metadata the `[sops]` section
+        return Ok(parse_output(path, encrypted)?
+            .into_iter()
+            .filter(|(key, _)| !key.starts_with("sops_") && !key.starts_with("sops."))
+            .map(|(key, value)| {
+                let encrypted = is_sops_encrypted(&value);
+                (key, encrypted)
+            })
+            .collect());
+    };
+
+    if let Some(mapping) = document.as_mapping_mut() {
+        mapping.remove("sops");
+    }
+    let data = match document.get("data").filter(|data| data.is_mapping()) {
+        Some(data) => data.clone(),
+        None => document,
+    };
+    let data = serde_json::to_value(data).context("Vault content cannot be read")?;
+
+    let mut status = BTreeMap::new();
+    if let serde_json::Value::Object(object) = data {
+        for (key, value) in &object {
+            collect_encryption_status(&mut status, key.clone(), value);
+        }
+    }
+    Ok(status)
+}
+
+fn collect_encryption_status(status: &mut BTreeMap<String, bool>, key: String, value: &serde_json::Value) {
+    match value {
+        serde_json::Value::Object(object) => {
+            for (name, nested) in object {
+                collect_encryption_status(status, format!("{}{}{}", key, NESTED_KEY_SEPARATOR, name), nested);
+            }
+        }
+        serde_json::Value::Array(items) => {
+            let encrypted = items.iter().all(|item| item.as_str().is_some_and(is_sops_encrypted));
+            status.insert(key, encrypted);
+        }
+        serde_json::Value::String(value) => {
+            status.insert(key, is_sops_encrypted(value));
+        }
+        _ => {
+            status.insert(key, false);
+        }
+    }
+}
+
+/// Parse JSON format (key-value structure, nested objects flattened).
 fn parse_json(output: &[u8]) -> Result<HashMap<String, String>> {
     let content = std::str::from_utf8(output).context("SOPS output is not valid UTF-8")?;
 
@@ -238,17 +1237,8 @@ fn parse_json(output: &[u8]) -> Result<HashMap<String, String>> {
         ));
     };
 
-    // Extract all string values
     for (key, value) in data {
-        if let Some(str_value) = value.as_str() {
-            secrets.insert(key.clone(), str_value.to_string());
-        } else {
-            return Err(anyhow::anyhow!(
-                "JSON value for key '{}' must be a string, found: {}",
-                key,
-                value
-            ));
-        }
+        flatten_value(&mut secrets, key.clone(), value, "JSON")?;
     }
 
     if secrets.is_empty() {
@@ -260,12 +1250,14 @@ fn parse_json(output: &[u8]) -> Result<HashMap<String, String>> {
     Ok(secrets)
 }
 
-/// Parse YAML format (flat key-value structure).
+/// Parse YAML format (key-value structure, nested mappings flattened).
 fn parse_yaml(output: &[u8]) -> Result<HashMap<String, String>> {
     let content = std::str::from_utf8(output).context("SOPS output is not valid UTF-8")?;
 
-    let yaml: serde_yaml::Value =
+    let mut yaml: serde_yaml::Value =
         serde_yaml::from_str(content).with_context(|| "Failed to parse YAML output from SOPS")?;
+    // Aliases are resolved by the parser; merge keys (`<<: *defaults`) are not
+    yaml.apply_merge().with_context(|| "Failed to apply YAML merge keys")?;
 
     let mut secrets = HashMap::new();
 
@@ -282,24 +1274,66 @@ fn parse_yaml(output: &[u8]) -> Result<HashMap<String, String>> {
         ));
     };
 
-    // Extract all string values
     for (key, value) in data {
         let key = key.as_str().with_context(|| "YAML key must be a string")?;
+        let value = serde_json::to_value(value)
+            .with_context(|| format!("YAML value for key '{}' cannot be read", key))?;
+        flatten_value(&mut secrets, key.to_string(), &value, "YAML")?;
+    }
 
-        if let Some(str_value) = value.as_str() {
-            secrets.insert(key.to_string(), str_value.to_string());
-        } else {
-            return Err(anyhow::anyhow!(
-                "YAML value for key '{}' must be a string, found: {:?}",
-                key,
-                value
-            ));
+    if secrets.is_empty() {
+        return Err(anyhow::anyhow!(
+            "No secrets found in YAML format. Expected key-value pairs with string values."
+        ));
+    }
+
+    Ok(secrets)
+}
+
+/// Parse INI format. Keys of a `[section]` become `section.key`, as nested
+/// JSON/YAML keys do; keys before the first section keep their name.
+fn parse_ini(output: &[u8]) -> Result<HashMap<String, String>> {
+    let content = std::str::from_utf8(output).context("SOPS output is not valid UTF-8")?;
+
+    let mut secrets = HashMap::new();
+    let mut section: Option<String> = None;
+
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+            continue;
+        }
+
+        if let Some(name) = line.strip_prefix('[') {
+            let name = name
+                .strip_suffix(']')
+                .with_context(|| format!("INI line {}: unterminated section header", number + 1))?;
+            section = Some(name.trim().to_string());
+            continue;
+        }
+
+        let (key, value) = line
+            .split_once(['=', ':'])
+            .with_context(|| format!("INI line {}: expected 'key = value'", number + 1))?;
+        let value = value.trim();
+        let value = value
+            .strip_prefix('"')
+            .and_then(|v| v.strip_suffix('"'))
+            .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+            .unwrap_or(value);
+
+        let key = match &section {
+            Some(section) => format!("{}{}{}", section, NESTED_KEY_SEPARATOR, key.trim()),
+            None => key.trim().to_string(),
+        };
+        if secrets.insert(key.clone(), value.to_string()).is_some() {
+            anyhow::bail!("INI defines key '{}' twice", key);
         }
     }
 
     if secrets.is_empty() {
         return Err(anyhow::anyhow!(
-            "No secrets found in YAML format. Expected key-value pairs with string values."
+            "No secrets found in INI format. Expected 'key = value' lines."
         ));
     }
 
@@ -317,6 +1351,13 @@ fn try_autodetect(output: &[u8]) -> Result<HashMap<String, String>> {
         }
     }
 
+    // INI files usually open with a section header
+    if content.trim_start().starts_with('[') {
+        if let Ok(secrets) = parse_ini(output) {
+            return Ok(secrets);
+        }
+    }
+
     // Try YAML next
     if content.trim_start().starts_with("data:") || content.contains(':') {
         if let Ok(secrets) = parse_yaml(output) {
@@ -330,7 +1371,7 @@ fn try_autodetect(output: &[u8]) -> Result<HashMap<String, String>> {
     }
 
     Err(anyhow::anyhow!(
-        "Unable to auto-detect format. Please use a file extension: .env, .json, .yaml, or .yml"
+        "Unable to auto-detect format. Please use a file extension: .env, .json, .yaml, .yml, or .ini"
     ))
 }
 
@@ -351,6 +1392,129 @@ pub fn parse_yaml_for_testing(output: &[u8]) -> Result<HashMap<String, String>>
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_provenance_tracks_shadowed_keys() {
+        let mut vault = Vault::new(HashMap::new());
+        vault.merge(
+            "team.enc.env",
+            HashMap::from([
+                ("API_KEY".to_string(), "sk_team".to_string()),
+                ("DATABASE_URL".to_string(), "postgres://team".to_string()),
+            ]),
+        );
+        vault.merge(
+            "extra.enc.env",
+            HashMap::from([("DATABASE_URL".to_string(), "postgres://extra".to_string())]),
+        );
+        vault.apply_overlay(
+            "local.enc.env",
+            HashMap::from([("DATABASE_URL".to_string(), "postgres://me".to_string())]),
+        );
+
+        assert_eq!(
+            vault.shadowed(),
+            vec![Provenance {
+                key: "DATABASE_URL".to_string(),
+                source: "local.enc.env".to_string(),
+                shadowed: vec!["team.enc.env".to_string(), "extra.enc.env".to_string()],
+            }]
+        );
+        assert_eq!(vault.provenance()[0].source, "team.enc.env");
+        assert_eq!(vault.get("DATABASE_URL"), Some(&"postgres://me".to_string()));
+    }
+
+    #[test]
+    fn test_normalized_lookup() {
+        let mut secrets = HashMap::new();
+        secrets.insert("API_KEY".to_string(), "sk_123".to_string());
+        secrets.insert("api_key".to_string(), "sk_lower".to_string());
+
+        let strict = Vault::new(secrets.clone());
+        assert_eq!(strict.get("api-key"), None);
+
+        let vault = Vault::new(secrets).with_normalized_keys(true);
+        assert_eq!(vault.get("api_key"), Some(&"sk_lower".to_string()));
+        assert_eq!(vault.get("Api-Key"), Some(&"sk_123".to_string()));
+        assert_eq!(vault.get("API-SECRET"), None);
+    }
+
+    #[test]
+    fn test_resolve_placeholders_adds_aliases() {
+        let mut secrets = HashMap::new();
+        secrets.insert("API_KEY".to_string(), "sk_123".to_string());
+        let placeholders = vec!["${api-key}".to_string(), "$MISSING".to_string()];
+
+        let strict = Vault::new(secrets.clone()).resolve_placeholders(&placeholders);
+        assert!(!strict.contains_key("api-key"));
+
+        let resolved = Vault::new(secrets)
+            .with_normalized_keys(true)
+            .resolve_placeholders(&placeholders);
+        assert_eq!(resolved.get("api-key"), Some(&"sk_123".to_string()));
+        assert!(!resolved.contains_key("MISSING"));
+
+        let content = crate::injector::replace_placeholders("key=${api-key}", &resolved, &placeholders);
+        assert_eq!(content, "key=sk_123");
+    }
+
+    #[test]
+    fn test_local_overlay_prefers_encrypted() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        assert_eq!(LocalOverlay::find(temp_dir.path()), None);
+
+        let plaintext = temp_dir.path().join(LOCAL_OVERLAY_PLAINTEXT);
+        std::fs::write(&plaintext, "DATABASE_URL=postgres://me@localhost/dev\n").unwrap();
+        assert_eq!(
+            LocalOverlay::find(temp_dir.path()),
+            Some(LocalOverlay::Plaintext(plaintext))
+        );
+
+        let encrypted = temp_dir.path().join(LOCAL_OVERLAY_ENCRYPTED);
+        std::fs::write(&encrypted, "").unwrap();
+        assert_eq!(
+            LocalOverlay::find(temp_dir.path()),
+            Some(LocalOverlay::Encrypted(encrypted))
+        );
+    }
+
+    #[test]
+    fn test_plaintext_overlay_overrides_vault() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join(LOCAL_OVERLAY_PLAINTEXT);
+        std::fs::write(&path, "DATABASE_URL=postgres://me@localhost/dev\nDEBUG=1\n").unwrap();
+
+        let mut secrets = HashMap::new();
+        secrets.insert("DATABASE_URL".to_string(), "postgres://team/prod".to_string());
+        secrets.insert("API_KEY".to_string(), "sk_team".to_string());
+        let mut vault = Vault::new(secrets);
+
+        let overrides = LocalOverlay::Plaintext(path).load(None).unwrap();
+        let keys = vault.apply_overlay("local.env", overrides);
+
+        assert_eq!(keys, vec!["DATABASE_URL".to_string(), "DEBUG".to_string()]);
+        assert_eq!(
+            vault.get("DATABASE_URL"),
+            Some(&"postgres://me@localhost/dev".to_string())
+        );
+        assert_eq!(vault.get("API_KEY"), Some(&"sk_team".to_string()));
+    }
+
+    #[test]
+    fn test_load_fake_needs_no_key() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("secrets.enc.env");
+        std::fs::write(
+            &path,
+            "API_KEY=ENC[AES256_GCM,data:abc,iv:def,tag:ghi,type:str]\nsops_version=3.8.1\n",
+        )
+        .unwrap();
+
+        let vault = Vault::load_fake(&[&path]).unwrap();
+        assert_eq!(vault.all().len(), 1);
+        assert_eq!(vault.get("API_KEY"), Some(&crate::fake::fake_value("API_KEY")));
+        assert_eq!(vault.provenance()[0].source, path.to_string_lossy());
+    }
+
     #[test]
     fn test_parse_env_format() {
         let env_output = b"API_KEY=sk_test_123\nDATABASE_URL=postgres://localhost\n# Comment\n";
@@ -373,6 +1537,50 @@ mod tests {
         assert_eq!(secrets.get("SECRET"), Some(&"value".to_string()));
     }
 
+    #[test]
+    fn test_parse_env_dotenv_conventions() {
+        let cases: &[(&str, &str, &str)] = &[
+            ("KEY=value", "KEY", "value"),
+            ("export KEY=value", "KEY", "value"),
+            ("export\tKEY = value  ", "KEY", "value"),
+            ("exported=1", "exported", "1"),
+            ("KEY=", "KEY", ""),
+            ("KEY=a#b", "KEY", "a#b"),
+            ("KEY=value # comment", "KEY", "value"),
+            ("KEY= # comment", "KEY", ""),
+            ("KEY=#value", "KEY", "#value"),
+            ("KEY=a=b=c", "KEY", "a=b=c"),
+            ("KEY=\"multi word\"", "KEY", "multi word"),
+            ("KEY=\"  padded  \"", "KEY", "  padded  "),
+            ("KEY=\"a # not a comment\" # comment", "KEY", "a # not a comment"),
+            ("KEY=\"say \\\"hi\\\"\"", "KEY", "say \"hi\""),
+            ("KEY=\"line1\\nline2\\ttab\"", "KEY", "line1\nline2\ttab"),
+            ("KEY=\"back\\\\slash \\$HOME\"", "KEY", "back\\slash $HOME"),
+            ("KEY=\"C:\\dir\"", "KEY", "C:\\dir"),
+            ("KEY='single \\n \"quoted\" $HOME'", "KEY", "single \\n \"quoted\" $HOME"),
+            ("KEY='it''s'x", "", ""),
+            ("KEY=\"first\nsecond\"", "KEY", "first\nsecond"),
+            ("KEY='-----BEGIN KEY-----\nabc\n-----END KEY-----'", "KEY", "-----BEGIN KEY-----\nabc\n-----END KEY-----"),
+            ("KEY=$OTHER", "KEY", "$OTHER"),
+            ("  # indented comment\nKEY=value", "KEY", "value"),
+            ("KEY=value\r\nOTHER=x", "KEY", "value"),
+        ];
+        for (input, key, expected) in cases {
+            let parsed = parse_env(input.as_bytes());
+            if key.is_empty() {
+                assert!(parsed.is_err(), "{:?} should be rejected", input);
+                continue;
+            }
+            let secrets = parsed.unwrap_or_else(|e| panic!("{:?}: {}", input, e));
+            assert_eq!(secrets.get(*key).map(String::as_str), Some(*expected), "{:?}", input);
+        }
+
+        for input in ["KEY=\"unterminated", "KEY='open\nOTHER=x", "KEY=\"done\" trailing"] {
+            let error = parse_env(input.as_bytes()).unwrap_err().to_string();
+            assert!(error.contains("'KEY' (line 1)"), "{}", error);
+        }
+    }
+
     #[test]
     fn test_parse_json_format() {
         let json_output = br#"{"API_KEY":"sk_test_123","DATABASE_URL":"postgres://localhost"}"#;
@@ -415,6 +1623,76 @@ mod tests {
         assert_eq!(secrets.len(), 1);
     }
 
+    #[test]
+    fn test_secrets_for_respects_key_allowlist() {
+        let vault = Vault::new(HashMap::from([
+            ("API_KEY".to_string(), "sk_test_123".to_string()),
+            ("DB_URL".to_string(), "postgres://x".to_string()),
+            ("OTHER".to_string(), "x".to_string()),
+        ]));
+        let mut target = crate::config::TargetConfig {
+            name: "app".to_string(),
+            path: ".env".to_string(),
+            placeholders: vec!["$API_KEY".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(vault.secrets_for(&target).len(), 3);
+
+        target.keys = vec!["API_KEY".to_string(), "DB_URL".to_string()];
+        let secrets = vault.secrets_for(&target);
+        assert_eq!(secrets.len(), 2);
+        assert!(!secrets.contains_key("OTHER"));
+    }
+
+    #[test]
+    fn test_parse_nested_values_into_dotted_keys() {
+        let json = br#"{"API_KEY":"sk_test_123","database":{"password":"s3cret","port":5432}}"#;
+        let secrets = parse_json(json).unwrap();
+        assert_eq!(secrets["database.password"], "s3cret");
+        assert_eq!(secrets["database.port"], "5432");
+        assert_eq!(secrets.len(), 3);
+
+        let yaml = b"database:\n  primary:\n    password: s3cret\nfeature: true\n";
+        let secrets = parse_yaml(yaml).unwrap();
+        assert_eq!(secrets["database.primary.password"], "s3cret");
+        assert_eq!(secrets["feature"], "true");
+
+        assert!(parse_yaml(b"hosts:\n  - a\n  - b\n").is_err());
+        assert!(parse_json(br#"{"a.b":"x","a":{"b":"y"}}"#).is_err());
+
+        let vault = Vault::new(parse_yaml(yaml).unwrap()).with_normalized_keys(true);
+        assert_eq!(
+            vault.get("DATABASE__PRIMARY__PASSWORD").map(String::as_str),
+            Some("s3cret")
+        );
+    }
+
+    #[test]
+    fn test_parse_yaml_anchors_and_merge_keys() {
+        let yaml = b"defaults: &defaults\n  host: db.internal\n  port: 5432\nprimary:\n  <<: *defaults\n  password: &pw s3cret\nreplica:\n  <<: *defaults\n  host: replica.internal\n  password: *pw\n";
+        let secrets = parse_yaml(yaml).unwrap();
+
+        assert_eq!(secrets["primary.host"], "db.internal");
+        assert_eq!(secrets["replica.host"], "replica.internal");
+        assert_eq!(secrets["replica.port"], "5432");
+        assert_eq!(secrets["replica.password"], "s3cret");
+        assert!(!secrets.keys().any(|key| key.contains("<<")));
+    }
+
+    #[test]
+    fn test_parse_ini_sections_into_dotted_keys() {
+        let ini = b"; legacy settings\nAPI_KEY = sk_test_123\n\n[database]\npassword = \"s3cret\"\nhost: localhost\n";
+        let secrets = parse_output("legacy.enc.ini", ini).unwrap();
+
+        assert_eq!(secrets["API_KEY"], "sk_test_123");
+        assert_eq!(secrets["database.password"], "s3cret");
+        assert_eq!(secrets["database.host"], "localhost");
+        assert_eq!(secrets.len(), 3);
+
+        assert!(parse_ini(b"[a]\nk=1\n[a]\nk=2\n").is_err());
+        assert!(parse_ini(b"[broken\nk=1\n").is_err());
+    }
+
     #[test]
     fn test_vault_get() {
         let mut secrets = HashMap::new();
@@ -439,7 +1717,7 @@ mod tests {
     fn test_sops_not_installed_error() {
         // Mock SOPS not being installed by using an invalid command
         // In real scenario, this would catch SOPS not in PATH
-        let output = Command::new("nonexistent_sops_command_xyz")
+        let output = std::process::Command::new("nonexistent_sops_command_xyz")
             .arg("-d")
             .arg("test.env")
             .output();
@@ -485,4 +1763,138 @@ mod tests {
         assert_eq!(secrets.get("KEY"), Some(&"value".to_string()));
         assert_eq!(secrets.get("SECRET2"), Some(&"value2".to_string()));
     }
+
+    #[test]
+    fn test_decrypt_concurrently_preserves_order() {
+        let paths = ["a.env", "b.env", "c.env", "d.env", "e.env"];
+        let running = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+
+        let results = decrypt_concurrently(&paths, 2, |path| {
+            let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+            peak.fetch_max(now, Ordering::SeqCst);
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            running.fetch_sub(1, Ordering::SeqCst);
+
+            let mut secrets = HashMap::new();
+            secrets.insert("SOURCE".to_string(), path.to_string_lossy().to_string());
+            Ok(secrets)
+        })
+        .unwrap();
+
+        let order: Vec<&str> = results.iter().map(|s| s["SOURCE"].as_str()).collect();
+        assert_eq!(order, paths);
+        assert!(peak.load(Ordering::SeqCst) <= 2);
+    }
+
+    #[test]
+    fn test_decrypt_concurrently_reports_failure() {
+        let paths = ["ok.env", "broken.env"];
+
+        let result = decrypt_concurrently(&paths, 4, |path| {
+            if path.ends_with("broken.env") {
+                anyhow::bail!("cannot decrypt {:?}", path);
+            }
+            Ok(HashMap::new())
+        });
+
+        assert!(result.is_err());
+        assert!(result.unwrap_err().to_string().contains("broken.env"));
+    }
+
+    #[test]
+    fn test_set_destination_prefers_the_file_in_effect() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let team = temp_dir.path().join("team.enc.env");
+        let mine = temp_dir.path().join("mine.enc.env");
+        std::fs::write(&team, "API_KEY=ENC[AES256_GCM,data:a]\nDB_URL=ENC[AES256_GCM,data:b]\nsops_version=3.9.0\n").unwrap();
+        std::fs::write(&mine, "API_KEY=ENC[AES256_GCM,data:c]\nsops_version=3.9.0\n").unwrap();
+        let sources = vec![team.clone(), mine.clone()];
+
+        assert_eq!(set_destination(&sources, "API_KEY").unwrap(), (mine, true));
+        assert_eq!(set_destination(&sources, "DB_URL").unwrap(), (team.clone(), true));
+        assert_eq!(set_destination(&sources, "NEW_KEY").unwrap(), (team, false));
+        assert!(set_destination(&[], "NEW_KEY").is_err());
+    }
+
+    #[test]
+    fn test_extract_expression_escapes_quotes() {
+        assert_eq!(extract_expression("API_KEY"), r#"["API_KEY"]"#);
+        assert_eq!(extract_expression(r#"a"b\c"#), r#"["a\"b\\c"]"#);
+    }
+
+    #[test]
+    fn test_lazy_vault_caches_and_prefers_later_sources() {
+        let vault = LazyVault::new(
+            vec![PathBuf::from("base.env"), PathBuf::from("override.env")],
+            None,
+        );
+        let calls = AtomicUsize::new(0);
+        let extract = |path: &Path, key: &str| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Ok(match (path.to_str().unwrap(), key) {
+                ("override.env", "API_KEY") => Some("override".to_string()),
+                ("base.env", "API_KEY") => Some("base".to_string()),
+                ("base.env", "DB_URL") => Some("postgres://".to_string()),
+                _ => None,
+            })
+        };
+
+        assert_eq!(vault.lookup("API_KEY", extract).unwrap().as_deref(), Some("override"));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // Cached: no further SOPS calls
+        assert_eq!(vault.lookup("API_KEY", extract).unwrap().as_deref(), Some("override"));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // Falls back to earlier sources
+        assert_eq!(vault.lookup("DB_URL", extract).unwrap().as_deref(), Some("postgres://"));
+        assert_eq!(vault.lookup("MISSING", extract).unwrap(), None);
+    }
+
+    #[test]
+    fn test_lru_cache_evicts_least_recently_used() {
+        let mut cache = LruCache::new(2);
+        cache.put("A".to_string(), "1".to_string());
+        cache.put("B".to_string(), "2".to_string());
+        assert_eq!(cache.get("A").as_deref(), Some("1"));
+
+        cache.put("C".to_string(), "3".to_string());
+        assert_eq!(cache.get("B"), None);
+        assert_eq!(cache.get("A").as_deref(), Some("1"));
+        assert_eq!(cache.get("C").as_deref(), Some("3"));
+    }
+
+    #[test]
+    fn test_encryption_status_with_encrypted_regex() {
+        let yaml = "\
+app:
+    name: demo
+    port: 8080
+    password: ENC[AES256_GCM,data:abc=,iv:x,tag:y,type:str]
+API_KEY: ENC[AES256_GCM,data:def=,iv:x,tag:y,type:str]
+DB_URL: postgres://user:hunter2@db
+sops:
+    age:
+        - recipient: age1abc
+    encrypted_regex: ^(password|API_KEY)$
+";
+        let status = encryption_status("secrets.enc.yaml", yaml.as_bytes()).unwrap();
+        assert_eq!(
+            status.into_iter().collect::<Vec<_>>(),
+            vec![
+                ("API_KEY".to_string(), true),
+                ("DB_URL".to_string(), false),
+                ("app.name".to_string(), false),
+                ("app.password".to_string(), true),
+                ("app.port".to_string(), false),
+            ]
+        );
+
+        let env = "API_KEY=ENC[AES256_GCM,data:abc=,iv:x,tag:y,type:str]\nLOG_LEVEL=debug\nsops_version=3.8.1\n";
+        let status = encryption_status("secrets.enc.env", env.as_bytes()).unwrap();
+        assert_eq!(status.len(), 2);
+        assert!(status["API_KEY"]);
+        assert!(!status["LOG_LEVEL"]);
+    }
 }
@@ -86,6 +86,8 @@ AGE-SECRET-KEY-1TESTPRIVATEKEYFORTESTING
         master_key_path: key_path.clone(),
         create_example: false,
         prompt_global: false,
+        profile: None,
+        force: false,
     };
 
     shadow_secret::init::init_project(config).unwrap();
@@ -116,6 +118,8 @@ AGE-SECRET-KEY-1TESTPRIVATEKEYABC
         master_key_path: key_path,
         create_example: true,
         prompt_global: false,
+        profile: None,
+        force: false,
     };
 
     shadow_secret::init::init_project(config).unwrap();
@@ -145,6 +149,8 @@ AGE-SECRET-KEY-1TESTPRIVATEKEYXYZ
         master_key_path: key_path,
         create_example: false,
         prompt_global: false,
+        profile: None,
+        force: false,
     };
 
     shadow_secret::init::init_project(config).unwrap();
@@ -284,6 +290,8 @@ fn test_init_project_error_when_key_missing_and_no_generate() {
         master_key_path: env.project_dir().join("nonexistent_key.txt"),
         create_example: false,
         prompt_global: false,
+        profile: None,
+        force: false,
     };
 
     let result = shadow_secret::init::init_project(config);
@@ -291,6 +299,29 @@ fn test_init_project_error_when_key_missing_and_no_generate() {
     assert!(result.unwrap_err().to_string().contains("Failed to read age key file"));
 }
 
+#[test]
+fn test_init_project_refuses_to_overwrite_without_force() {
+    let env = TestEnv::new();
+    env.enter();
+
+    let key_path = env.create_age_key("# public key: age1test_public_key_789\nAGE-SECRET-KEY-1TESTPRIVATEKEYFORCE\n");
+    let existing = "# team rules\ncreation_rules: []\n";
+    fs::write(env.project_dir().join(".sops.yaml"), existing).unwrap();
+
+    let config = shadow_secret::init::InitConfig {
+        master_key_path: key_path,
+        create_example: false,
+        prompt_global: false,
+        profile: None,
+        force: false,
+    };
+
+    let result = shadow_secret::init::init_project(config);
+    assert!(result.unwrap_err().to_string().contains("Refusing to overwrite"));
+    assert_eq!(fs::read_to_string(env.project_dir().join(".sops.yaml")).unwrap(), existing);
+    assert!(!env.project_dir().join("project.yaml").exists());
+}
+
 // Note: We skip testing age-keygen generation in automated tests
 // as it requires the age binary to be installed and creates actual keys
 // These scenarios are covered by manual integration testing
@@ -0,0 +1,129 @@
+//! Replaying recorded sops invocations, without sops installed.
+
+use assert_cmd::cargo_bin_cmd;
+use shadow_secret::testing::{Cassette, Interaction, CWD_PLACEHOLDER, REPLAY_ENV};
+
+fn sops(args: &[&str], stdout: &str) -> Interaction {
+    Interaction {
+        program: "sops".to_string(),
+        args: args.iter().map(|arg| arg.to_string()).collect(),
+        status: 0,
+        stdout: stdout.to_string(),
+        stderr: String::new(),
+    }
+}
+
+#[test]
+fn test_list_replays_sops_fixture() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let dir = temp_dir.path().canonicalize().unwrap();
+    std::fs::write(
+        dir.join("project.yaml"),
+        "vault:\n  source: .enc.env\n  engine: sops\ntargets:\n  - name: app\n    path: app.env\n    placeholders: [\"$API_KEY\"]\n",
+    )
+    .unwrap();
+
+    let vault_path = format!("{}/.enc.env", CWD_PLACEHOLDER);
+    let fixture = dir.join("sops.json");
+    Cassette::new(vec![
+        sops(&["--version"], "sops 3.8.1\n"),
+        sops(&["-d", &vault_path], "API_KEY=sk_test_123\nDB_URL=postgres://localhost\n"),
+    ])
+    .save(&fixture)
+    .unwrap();
+
+    let mut cmd = cargo_bin_cmd!("shadow-secret");
+    cmd.current_dir(&dir)
+        .env(REPLAY_ENV, &fixture)
+        .env("PATH", "")
+        .arg("list")
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("API_KEY"))
+        .stdout(predicates::str::contains("DB_URL"));
+}
+
+#[test]
+fn test_list_replays_aws_secrets_manager_fixture() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let dir = temp_dir.path().canonicalize().unwrap();
+    std::fs::write(
+        dir.join("project.yaml"),
+        "vault:\n  engine: aws-secretsmanager\n  aws_secretsmanager:\n    secret_id: myapp/dev\ntargets:\n  - name: app\n    path: app.env\n    placeholders: [\"$API_KEY\"]\n",
+    )
+    .unwrap();
+
+    let response = serde_json::json!({
+        "Name": "myapp/dev",
+        "SecretString": r#"{"API_KEY":"sk_test_123","DB_URL":"postgres://localhost"}"#,
+    });
+    let fixture = dir.join("aws.json");
+    Cassette::new(vec![Interaction {
+        program: "aws".to_string(),
+        args: "secretsmanager get-secret-value --secret-id myapp/dev --output json"
+            .split(' ')
+            .map(str::to_string)
+            .collect(),
+        status: 0,
+        stdout: response.to_string(),
+        stderr: String::new(),
+    }])
+    .save(&fixture)
+    .unwrap();
+
+    let mut cmd = cargo_bin_cmd!("shadow-secret");
+    cmd.current_dir(&dir)
+        .env(REPLAY_ENV, &fixture)
+        .env("PATH", "")
+        .args(["list", "--provenance"])
+        .assert()
+        .success()
+        .stdout(predicates::str::is_match("API_KEY +aws-secretsmanager:myapp/dev").unwrap())
+        .stdout(predicates::str::contains("DB_URL"));
+}
+
+#[test]
+fn test_deploy_builds_with_secrets_and_always_locks() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let dir = temp_dir.path().canonicalize().unwrap();
+    std::fs::write(
+        dir.join("project.yaml"),
+        "vault:\n  engine: aws-secretsmanager\n  aws_secretsmanager:\n    secret_id: myapp/dev\ntargets:\n  - name: app\n    path: app.env\n    placeholders: [\"$API_KEY\"]\n",
+    )
+    .unwrap();
+    std::fs::write(dir.join("app.env"), "API_KEY=$API_KEY\n").unwrap();
+
+    let response = serde_json::json!({"Name": "myapp/dev", "SecretString": r#"{"API_KEY":"sk_test_123"}"#});
+    let fixture = dir.join("aws.json");
+    let aws = Interaction {
+        program: "aws".to_string(),
+        args: "secretsmanager get-secret-value --secret-id myapp/dev --output json"
+            .split(' ')
+            .map(str::to_string)
+            .collect(),
+        status: 0,
+        stdout: response.to_string(),
+        stderr: String::new(),
+    };
+
+    for (script, succeeds) in [("cp app.env built.env", true), ("exit 3", false)] {
+        Cassette::new(vec![aws.clone()]).save(&fixture).unwrap();
+        let mut cmd = cargo_bin_cmd!("shadow-secret");
+        let assert = cmd
+            .current_dir(&dir)
+            .env(REPLAY_ENV, &fixture)
+            .args(["deploy", "--no-push", "--run", script])
+            .assert();
+        if succeeds {
+            assert.success().stdout(predicates::str::contains("✓ build"));
+        } else {
+            assert
+                .failure()
+                .stdout(predicates::str::contains("✗ build"))
+                .stdout(predicates::str::contains("⊘ push-cloud"));
+        }
+
+        assert_eq!(std::fs::read_to_string(dir.join("app.env")).unwrap(), "API_KEY=$API_KEY\n");
+    }
+    assert_eq!(std::fs::read_to_string(dir.join("built.env")).unwrap(), "API_KEY=sk_test_123\n");
+}